@@ -0,0 +1,153 @@
+//! Counting what a bench allocates, on the thread running it.
+#![allow(dead_code)]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// What was allocated while running a closure given to [`measure`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub allocations: usize,
+    pub bytes: usize,
+    /// The most held at once, above what was held before.
+    pub peak: usize,
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} allocations, {} allocated, {} peak",
+            self.allocations,
+            kib(self.bytes),
+            kib(self.peak)
+        )
+    }
+}
+
+fn kib(bytes: usize) -> String {
+    format!("{:.1} KiB", bytes as f64 / 1024.0)
+}
+
+#[derive(Clone, Copy)]
+struct Counters {
+    allocations: usize,
+    bytes: usize,
+    held: isize,
+    peak: isize,
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = const {
+        Cell::new(Counters { allocations: 0, bytes: 0, held: 0, peak: 0 })
+    };
+}
+
+fn record(allocated: usize, freed: usize) {
+    // Not counted while the thread is torn down.
+    let _ = COUNTERS.try_with(|counters| {
+        let mut c = counters.get();
+        if allocated > 0 {
+            c.allocations += 1;
+            c.bytes += allocated;
+        }
+        c.held += allocated as isize - freed as isize;
+        c.peak = c.peak.max(c.held);
+        counters.set(c);
+    });
+}
+
+/// Counts allocations per thread, so that a server running on another thread is left out.
+pub struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size(), 0);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size(), 0);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(0, layout.size());
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size, layout.size());
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Run `f`, counting what it allocates on this thread.
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, Usage) {
+    let start = COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.peak = c.held;
+        counters.set(c);
+        c
+    });
+    let result = f();
+    let end = COUNTERS.with(Cell::get);
+    let usage = Usage {
+        allocations: end.allocations - start.allocations,
+        bytes: end.bytes - start.bytes,
+        peak: (end.peak - start.held).max(0) as usize,
+    };
+    (result, usage)
+}
+
+/// Start a onebot server on its own thread, answering the actions in `data` with it and others with `null`.
+/// Returns the url to connect to.
+///
+/// Each response is built before it is sent, so that only the bot is measured on the bench thread.
+pub fn serve(data: Vec<(&'static str, String)>) -> String {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let (url_tx, url_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            url_tx
+                .send(format!("ws://{}", listener.local_addr().unwrap()))
+                .unwrap();
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+                    continue;
+                };
+                let (mut write, mut read) = ws.split();
+                while let Some(Ok(frame)) = read.next().await {
+                    let Message::Text(text) = frame else {
+                        continue;
+                    };
+                    let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    let found = data
+                        .iter()
+                        .find(|(action, _)| request["action"] == *action)
+                        .map_or("null", |(_, data)| data.as_str());
+                    let response = format!(
+                        r#"{{"status":"ok","retcode":0,"data":{},"echo":{}}}"#,
+                        found, request["echo"]
+                    );
+                    if write.send(Message::Text(response.into())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    });
+    url_rx.recv().unwrap()
+}
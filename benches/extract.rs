@@ -0,0 +1,94 @@
+//! Extracting a 50-segment group message for 15 handlers, from the shared event and as owned copies.
+//!
+//! Run with `cargo bench --bench extract`, the allocations per event are printed before the timings.
+
+mod common;
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use flow_bot::{
+    FlowBotBuilder,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        extract::{FromEvent, MessageBody, Sender},
+    },
+    event::{BotEvent, Event, message::Message},
+};
+use serde_json::json;
+use tokio::runtime::Runtime;
+
+const HANDLERS: usize = 15;
+
+fn group_message() -> BotEvent {
+    let segments = (0..50)
+        .map(|i| match i % 4 {
+            0 => json!({"type": "text", "data": {"text": "a line of text in a long message "}}),
+            1 => json!({"type": "at", "data": {"qq": "123456"}}),
+            2 => json!({"type": "face", "data": {"id": "14"}}),
+            _ => json!({"type": "image", "data": {"file": "abc.image", "url": "https://example.com/abc.png"}}),
+        })
+        .collect::<Vec<_>>();
+    let event = json!({
+        "time": 1700000000, "self_id": 10000, "post_type": "message", "message_type": "group",
+        "sub_type": "normal", "message_id": 5, "group_id": 1, "user_id": 2,
+        "message": segments, "raw_message": "", "font": 0,
+        "sender": {"user_id": 2, "nickname": "Nick", "card": "", "role": "member"}
+    });
+    BotEvent::new(serde_json::from_value::<Event>(event).unwrap())
+}
+
+/// A context of a bot that is never run, which is enough for extractors not calling the API.
+fn context() -> BotContext {
+    FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://127.0.0.1:1".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    })
+    .build()
+    .context()
+}
+
+/// Extract `T` once per handler, as dispatching the event would.
+async fn extract<T: FromEvent>(context: &BotContext, event: &BotEvent) -> Vec<T> {
+    let mut extracted = Vec::with_capacity(HANDLERS);
+    for _ in 0..HANDLERS {
+        extracted.push(T::from_event(context.clone(), event.clone()).await.unwrap());
+    }
+    extracted
+}
+
+fn extractors(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let context = runtime.block_on(async { context() });
+    let event = group_message();
+
+    let shared = || runtime.block_on(extract::<MessageBody>(&context, &event));
+    let sender = || runtime.block_on(extract::<Sender>(&context, &event));
+    // What every handler paid before the views, a copy of the segments.
+    let owned = || {
+        runtime
+            .block_on(extract::<MessageBody>(&context, &event))
+            .into_iter()
+            .map(|body| body.0.to_owned())
+            .collect::<Vec<_>>()
+    };
+    let message = || runtime.block_on(extract::<Message>(&context, &event));
+
+    println!("Allocations per event, for {} handlers:", HANDLERS);
+    println!("  MessageBody: {}", common::measure(shared).1);
+    println!("  Sender: {}", common::measure(sender).1);
+    println!("  MessageBody copied: {}", common::measure(owned).1);
+    println!("  Message: {}", common::measure(message).1);
+
+    c.bench_function("extract/message_body", |b| b.iter(|| black_box(shared())));
+    c.bench_function("extract/sender", |b| b.iter(|| black_box(sender())));
+    c.bench_function("extract/message_body_copied", |b| {
+        b.iter(|| black_box(owned()))
+    });
+    c.bench_function("extract/message", |b| b.iter(|| black_box(message())));
+}
+
+criterion_group!(benches, extractors);
+criterion_main!(benches);
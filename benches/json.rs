@@ -0,0 +1,77 @@
+//! Parsing received frames with serde_json and, with the `fast-json` feature, simd-json.
+//!
+//! Run with `cargo bench --bench json --features fast-json` to compare both backends.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use flow_bot::event::Event;
+use serde_json::json;
+
+fn group_message() -> String {
+    let segments = (0..20)
+        .map(|i| match i % 3 {
+            0 => json!({"type": "text", "data": {"text": "a line of text in a long message "}}),
+            1 => json!({"type": "at", "data": {"qq": "123456"}}),
+            _ => json!({"type": "image", "data": {"file": "abc.image", "url": "https://example.com/abc.png"}}),
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "time": 1700000000, "self_id": 10000, "post_type": "message", "message_type": "group",
+        "sub_type": "normal", "message_id": 5, "group_id": 1, "user_id": 2,
+        "message": segments, "raw_message": "", "font": 0,
+        "sender": {"user_id": 2, "nickname": "Nick", "card": "", "role": "member"}
+    })
+    .to_string()
+}
+
+fn response() -> String {
+    let members = (0..500)
+        .map(|i| json!({"user_id": i, "nickname": format!("member {}", i), "remark": ""}))
+        .collect::<Vec<_>>();
+    json!({"status": "ok", "retcode": 0, "data": members, "echo": "abc:42"}).to_string()
+}
+
+/// What the message loop reads to tell responses from events.
+#[derive(serde::Deserialize)]
+#[allow(dead_code)]
+struct Echo {
+    echo: Option<String>,
+    post_type: Option<serde::de::IgnoredAny>,
+}
+
+fn serde_json_backend(c: &mut Criterion) {
+    let event = group_message();
+    let response = response();
+    c.bench_function("serde_json/event", |b| {
+        b.iter(|| serde_json::from_slice::<Event>(black_box(event.as_bytes())).unwrap())
+    });
+    c.bench_function("serde_json/echo_probe", |b| {
+        b.iter(|| serde_json::from_slice::<Echo>(black_box(response.as_bytes())).unwrap())
+    });
+}
+
+#[cfg(feature = "fast-json")]
+fn simd_json_backend(c: &mut Criterion) {
+    let event = group_message();
+    let response = response();
+    // Including the copy of the frame, which simd-json parses in place.
+    c.bench_function("simd_json/event", |b| {
+        b.iter(|| {
+            let mut frame = black_box(event.as_bytes()).to_vec();
+            simd_json::serde::from_slice::<Event>(&mut frame).unwrap()
+        })
+    });
+    c.bench_function("simd_json/echo_probe", |b| {
+        b.iter(|| {
+            let mut frame = black_box(response.as_bytes()).to_vec();
+            simd_json::serde::from_slice::<Echo>(&mut frame).unwrap()
+        })
+    });
+}
+
+#[cfg(not(feature = "fast-json"))]
+fn simd_json_backend(_: &mut Criterion) {}
+
+criterion_group!(benches, serde_json_backend, simd_json_backend);
+criterion_main!(benches);
@@ -0,0 +1,158 @@
+//! Receiving large API responses, a 10MB group list and a 1MB member list, end to end through a bot
+//! and on the frame alone.
+//!
+//! Run with `cargo bench --bench responses`. What one response costs on the thread running the bot is printed
+//! before the timings, next to the previous read path, which copied the frame and parsed it twice.
+
+mod common;
+
+use std::{hint::black_box, sync::Arc};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use flow_bot::{
+    FlowBotBuilder,
+    api::{ApiResponse, FriendInfo, GroupInfoResponse, api_ext::ApiExt},
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+    },
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tokio::runtime::Runtime;
+
+/// About 10MB of groups.
+fn group_list() -> String {
+    let groups = (0..100_000)
+        .map(|i| {
+            json!({
+                "group_id": 100_000_000 + i, "group_name": format!("a group with a name {}", i),
+                "member_count": 200, "max_member_count": 500
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string(&groups).unwrap()
+}
+
+/// About 1MB of members.
+fn member_list() -> String {
+    let members = (0..17_000)
+        .map(|i| json!({"user_id": 1_000_000 + i, "nickname": format!("member {}", i), "remark": "a remark"}))
+        .collect::<Vec<_>>();
+    serde_json::to_string(&members).unwrap()
+}
+
+fn response(data: &str) -> String {
+    format!(
+        r#"{{"status":"ok","retcode":0,"data":{},"echo":"abc:42"}}"#,
+        data
+    )
+}
+
+/// How a response frame was read before: copied for the pending request, parsed as a `Value` to find the echo,
+/// then parsed again from the copy.
+fn copied<T: DeserializeOwned>(frame: &str) -> ApiResponse<T> {
+    let copy = frame.to_string();
+    let value = serde_json::from_str::<serde_json::Value>(frame).unwrap();
+    black_box(
+        value
+            .get("echo")
+            .and_then(|echo| echo.as_str().map(str::to_string)),
+    );
+    serde_json::from_str(&copy).unwrap()
+}
+
+/// How it is read now: only the echo is probed, and the response is parsed from the frame.
+fn probed<T: DeserializeOwned>(frame: &str) -> ApiResponse<T> {
+    #[derive(serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Echo {
+        echo: Option<String>,
+        post_type: Option<serde::de::IgnoredAny>,
+    }
+
+    black_box(serde_json::from_str::<Echo>(frame).unwrap());
+    serde_json::from_slice(frame.as_bytes()).unwrap()
+}
+
+/// A bot connected to a server answering `action` with `data`, on a single threaded runtime,
+/// so that the bot reads and parses on the bench thread.
+fn connect(action: &'static str, data: String) -> (Runtime, BotContext) {
+    let url = common::serve(vec![(action, data)]);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let bot = Arc::new(
+        FlowBotBuilder::new(ReverseConnectionConfig {
+            target: url,
+            auth: None,
+            reconnection: ReconnectionStrategy::None,
+        })
+        .build(),
+    );
+    let context = bot.context();
+    runtime.spawn(async move { bot.run().await });
+    runtime.block_on(context.wait_for_connected());
+    (runtime, context)
+}
+
+/// Print what receiving `frame` costs through the bot with `call`, and on the frame alone.
+fn report<T: DeserializeOwned, R>(action: &str, frame: &str, call: impl FnOnce() -> R) {
+    println!(
+        "A {} response of {:.1} MiB:",
+        action,
+        frame.len() as f64 / (1024.0 * 1024.0)
+    );
+    println!("  Through the bot: {}", common::measure(call).1);
+    println!("  Probed: {}", common::measure(|| probed::<T>(frame)).1);
+    println!("  Copied: {}", common::measure(|| copied::<T>(frame)).1);
+}
+
+fn group_lists(c: &mut Criterion) {
+    let data = group_list();
+    let frame = response(&data);
+    let (runtime, context) = connect("get_group_list", data);
+    let call = || {
+        let groups = runtime.block_on(context.get_group_list()).unwrap();
+        assert_eq!(groups.len(), 100_000);
+        groups
+    };
+    report::<Vec<GroupInfoResponse>, _>("get_group_list", &frame, call);
+
+    let mut group = c.benchmark_group("group_list_10mb");
+    group.sample_size(10);
+    group.bench_function("bot", |b| b.iter(|| black_box(call())));
+    group.bench_function("probed", |b| {
+        b.iter(|| probed::<Vec<GroupInfoResponse>>(black_box(&frame)))
+    });
+    group.bench_function("copied", |b| {
+        b.iter(|| copied::<Vec<GroupInfoResponse>>(black_box(&frame)))
+    });
+    group.finish();
+}
+
+fn member_lists(c: &mut Criterion) {
+    let data = member_list();
+    let frame = response(&data);
+    let (runtime, context) = connect("get_group_member_list", data);
+    let call = || {
+        let members = runtime.block_on(context.get_group_member_list(1)).unwrap();
+        assert_eq!(members.len(), 17_000);
+        members
+    };
+    report::<Vec<FriendInfo>, _>("get_group_member_list", &frame, call);
+
+    let mut group = c.benchmark_group("member_list_1mb");
+    group.bench_function("bot", |b| b.iter(|| black_box(call())));
+    group.bench_function("probed", |b| {
+        b.iter(|| probed::<Vec<FriendInfo>>(black_box(&frame)))
+    });
+    group.bench_function("copied", |b| {
+        b.iter(|| copied::<Vec<FriendInfo>>(black_box(&frame)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, group_lists, member_lists);
+criterion_main!(benches);
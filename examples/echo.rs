@@ -0,0 +1,56 @@
+use clap::Parser;
+use flow_bot::{
+    FlowBotBuilder,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        extract::Command,
+        handler::HandlerControl,
+    },
+    event::message::Message,
+};
+
+/// Repeat the text after the command.
+#[derive(Parser)]
+struct Echo {
+    text: Vec<String>,
+    /// Repeat it this many times.
+    #[arg(short, long, default_value_t = 1)]
+    times: usize,
+}
+
+#[derive(Parser)]
+struct Ping;
+
+async fn echo(ctx: BotContext, message: Message, echo: Command<"/echo", Echo>) -> HandlerControl {
+    let Echo { text, times } = echo.command;
+    let text = vec![text.join(" "); times.clamp(1, 5)].join("\n");
+    if let Err(e) = ctx.reply(&message, text).await {
+        eprintln!("Failed to echo: {}", e);
+    }
+    HandlerControl::Block
+}
+
+async fn ping(ctx: BotContext, message: Message, _: Command<"/ping", Ping>) -> HandlerControl {
+    if let Err(e) = ctx.reply(&message, "pong").await {
+        eprintln!("Failed to answer ping: {}", e);
+    }
+    HandlerControl::Block
+}
+
+/// Register the handlers of the example, also used by `tests/examples.rs`.
+pub fn configure(builder: FlowBotBuilder) -> FlowBotBuilder {
+    builder.with_handler(echo).with_handler(ping)
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = configure(FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    }))
+    .build();
+
+    bot.run().await.unwrap();
+}
@@ -0,0 +1,90 @@
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        extract::{GroupId, MessageBody, State},
+        group_config::{GroupConfig, GroupConfigStore},
+        handler::HandlerControl,
+    },
+    message::message_ext::MessageExt,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+struct Settings {
+    greeting: String,
+    enabled: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            greeting: "Hello!".to_string(),
+            enabled: true,
+        }
+    }
+}
+
+/// `/greeting <text>` changes the greeting of the group, `/greeting off` disables it.
+async fn set_greeting(
+    ctx: BotContext,
+    GroupId(group_id): GroupId,
+    MessageBody(msg): MessageBody,
+    store: State<GroupConfigStore<Settings>>,
+) -> HandlerControl {
+    let text = msg.extract_plain_text();
+    let Some(arg) = text.strip_prefix("/greeting ") else {
+        return HandlerControl::Skip;
+    };
+
+    let arg = arg.trim().to_string();
+    let settings = store
+        .update(group_id, |settings| match arg.as_str() {
+            "off" => settings.enabled = false,
+            _ => {
+                settings.enabled = true;
+                settings.greeting = arg.clone();
+            }
+        })
+        .await;
+    let reply = if settings.enabled {
+        format!("Greeting set to {}", settings.greeting)
+    } else {
+        "Greeting disabled".to_string()
+    };
+    let _ = ctx.send_group_message(group_id, reply, None).await;
+    HandlerControl::Block
+}
+
+async fn greet(
+    ctx: BotContext,
+    GroupId(group_id): GroupId,
+    MessageBody(msg): MessageBody,
+    settings: GroupConfig<Settings>,
+) -> HandlerControl {
+    if !settings.enabled || msg.extract_plain_text() != "hi" {
+        return HandlerControl::Skip;
+    }
+    let _ = ctx
+        .send_group_message(group_id, settings.greeting.as_str(), None)
+        .await;
+    HandlerControl::Continue
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    })
+    .with_group_config::<Settings>("group_settings")
+    .with_handler(set_greeting)
+    .with_handler(greet)
+    .build();
+
+    bot.run().await.unwrap();
+}
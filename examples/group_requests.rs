@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        handler::HandlerControl,
+        service::Service,
+    },
+    event::{BotEvent, request::GroupRequestSubType},
+};
+
+/// Approves pending group invitations from whitelisted users on startup.
+struct ApproveWhitelisted {
+    whitelist: HashSet<i64>,
+}
+
+#[async_trait]
+impl Service for ApproveWhitelisted {
+    async fn serve(&self, _: BotContext, _: BotEvent) -> HandlerControl {
+        HandlerControl::Continue
+    }
+
+    async fn init(&self, bot: BotContext) {
+        let messages = match bot.get_group_system_msg().await {
+            Ok(messages) => messages,
+            Err(e) => {
+                eprintln!("Failed to get group system messages: {}", e);
+                return;
+            }
+        };
+
+        for request in messages.invited_requests {
+            println!(
+                "Invited to group {} by {:?} (checked: {})",
+                request.group_id, request.invitor_uin, request.checked
+            );
+            let whitelisted = request
+                .invitor_uin
+                .is_some_and(|uin| self.whitelist.contains(&uin));
+            if request.checked || !whitelisted {
+                continue;
+            }
+            let _ = bot
+                .set_group_add_request(
+                    request.request_id,
+                    GroupRequestSubType::Invite,
+                    Some(true),
+                    None,
+                )
+                .await;
+        }
+
+        for request in messages.join_requests {
+            println!(
+                "{} wants to join group {}: {:?}",
+                request.requester_uin, request.group_id, request.message
+            );
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    })
+    .with_service(ApproveWhitelisted {
+        whitelist: HashSet::from([10001]),
+    })
+    .build();
+
+    bot.run().await.unwrap();
+}
@@ -0,0 +1,100 @@
+use std::{sync::Arc, time::Duration};
+
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        extract::State,
+        handler::HandlerControl,
+    },
+    event::message::{GroupMessageEvent, GroupSenderRole},
+    extensions::{
+        flood::{FloodAction, FloodDetector, FloodLimit, NotFlooding},
+        moderation::{ModerationConfig, ModerationService},
+    },
+    message::message_ext::MessageExt,
+};
+
+/// Recall links posted by members and warn them, three warnings get them kicked.
+async fn no_links(
+    ctx: BotContext,
+    message: GroupMessageEvent,
+    _: NotFlooding,
+    State(moderation): State<Arc<ModerationService>>,
+) -> HandlerControl {
+    if message.sender.role != Some(GroupSenderRole::Member)
+        || !message.message.extract_plain_text().contains("http")
+    {
+        return HandlerControl::Continue;
+    }
+
+    if let Err(e) = ctx.delete_message(message.message_id as i64).await {
+        eprintln!("Failed to recall a link: {}", e);
+    }
+    let reason = Some("posted a link".to_string());
+    match moderation
+        .warn(&ctx, message.group_id, message.user_id, None, reason)
+        .await
+    {
+        Ok(outcome) if !outcome.kicked => {
+            let notice = format!("Links are not allowed here, warning {}/3", outcome.warnings);
+            if let Err(e) = ctx.send_group_message(message.group_id, notice, None).await {
+                eprintln!("Failed to send a notice: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to warn {}: {}", message.user_id, e),
+    }
+    HandlerControl::Block
+}
+
+/// Register the services and handlers of the example, also used by `tests/examples.rs`.
+pub fn configure(builder: FlowBotBuilder) -> FlowBotBuilder {
+    let moderation = Arc::new(ModerationService::new(ModerationConfig::default()));
+
+    // Flooding users are muted for ten minutes and get a warning on record.
+    let flood_moderation = moderation.clone();
+    let flood = FloodDetector::new(
+        FloodLimit {
+            max_messages: 5,
+            window: Duration::from_secs(10),
+        },
+        FloodAction::callback(move |ctx, evidence| {
+            let moderation = flood_moderation.clone();
+            async move {
+                let (group_id, user_id) = (evidence.group_id, evidence.user_id);
+                let muted = moderation
+                    .temp_ban(&ctx, group_id, user_id, Duration::from_secs(600), None)
+                    .await;
+                if let Err(e) = muted {
+                    eprintln!("Failed to mute {}: {}", user_id, e);
+                }
+                let reason = Some("flooding".to_string());
+                if let Err(e) = moderation.warn(&ctx, group_id, user_id, None, reason).await {
+                    eprintln!("Failed to warn {}: {}", user_id, e);
+                }
+            }
+        }),
+    );
+
+    builder
+        .with_service(flood)
+        // Admins use /warn, /ban, /kick and friends.
+        .with_service(moderation.clone())
+        .with_state(moderation)
+        .with_handler(no_links)
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = configure(FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    }))
+    .build();
+
+    bot.run().await.unwrap();
+}
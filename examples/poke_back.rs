@@ -0,0 +1,34 @@
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        handler::HandlerControl,
+    },
+    event::notice::PokedMe,
+};
+
+async fn poke_back(ctx: BotContext, poked: PokedMe) -> HandlerControl {
+    let result = match poked.group_id {
+        Some(group_id) => ctx.group_poke(group_id, poked.user_id).await,
+        None => ctx.friend_poke(poked.user_id).await,
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to poke back {}: {}", poked.user_id, e);
+    }
+    HandlerControl::Block
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    })
+    .with_handler(poke_back)
+    .build();
+
+    bot.run().await.unwrap();
+}
@@ -0,0 +1,292 @@
+use std::{collections::HashMap, sync::Arc};
+
+use dashmap::DashMap;
+use flow_bot::{
+    FlowBotBuilder,
+    api::{api_ext::ApiExt, params::ApiParams},
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        extract::State,
+        group_config::{GroupConfig, GroupConfigStore},
+        handler::HandlerControl,
+    },
+    event::message::{GroupMessageEvent, Message, TypedMessageInfo},
+    extensions::dialog::{Dialog, Transition},
+    message::message_ext::MessageExt,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+enum Draft {
+    Start,
+    Question,
+    Options {
+        question: String,
+        options: Vec<String>,
+    },
+}
+
+struct Poll {
+    question: String,
+    options: Vec<String>,
+    /// The option each user voted for.
+    votes: HashMap<i64, usize>,
+}
+
+/// The running poll of each group.
+#[derive(Default)]
+struct OpenPolls(DashMap<i64, Poll>);
+
+/// Results of the finished polls of a group, kept across restarts.
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct PollHistory {
+    polls: Vec<PollResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct PollResult {
+    question: String,
+    tally: Vec<(String, usize)>,
+}
+
+/// `send_group_forward_msg` with custom nodes, which the built-in API does not cover.
+#[derive(Serialize)]
+struct SendGroupForwardMsg {
+    group_id: i64,
+    messages: Vec<ForwardNode>,
+}
+
+#[derive(Serialize)]
+struct ForwardNode {
+    #[serde(rename = "type")]
+    ty: &'static str,
+    data: ForwardNodeData,
+}
+
+#[derive(Serialize)]
+struct ForwardNodeData {
+    name: String,
+    uin: String,
+    content: String,
+}
+
+impl ApiParams for SendGroupForwardMsg {
+    const ACTION: &'static str = "send_group_forward_msg";
+    type Response = serde_json::Value;
+}
+
+async fn say(ctx: &BotContext, message: &Message, text: &str) {
+    if let Err(e) = ctx.reply(message, text).await {
+        eprintln!("Failed to reply to {}: {}", message.user_id, e);
+    }
+}
+
+async fn draft_step(draft: Draft, ctx: BotContext, message: Message) -> Transition<Draft, Poll> {
+    let answer = message.message.extract_plain_text().trim().to_string();
+    match draft {
+        Draft::Start => {
+            say(&ctx, &message, "What should the poll ask?").await;
+            Transition::Move(Draft::Question)
+        }
+        Draft::Question => {
+            say(
+                &ctx,
+                &message,
+                "Send the options one by one, then \"done\".",
+            )
+            .await;
+            Transition::Move(Draft::Options {
+                question: answer,
+                options: Vec::new(),
+            })
+        }
+        Draft::Options { question, options } if answer == "done" => {
+            if options.len() < 2 {
+                say(&ctx, &message, "A poll needs at least two options.").await;
+                return Transition::Stay;
+            }
+            Transition::Finish(Poll {
+                question,
+                options,
+                votes: HashMap::new(),
+            })
+        }
+        Draft::Options {
+            question,
+            mut options,
+        } => {
+            options.push(answer);
+            Transition::Move(Draft::Options { question, options })
+        }
+    }
+}
+
+async fn vote(
+    ctx: BotContext,
+    message: GroupMessageEvent,
+    State(polls): State<Arc<OpenPolls>>,
+) -> HandlerControl {
+    let text = message.message.extract_plain_text();
+    let Some(choice) = text.trim().strip_prefix("/vote") else {
+        return HandlerControl::Continue;
+    };
+    let reply = match polls.0.get_mut(&message.group_id) {
+        None => "There is no poll running, start one with /newpoll".to_string(),
+        Some(mut poll) => match choice.trim().parse::<usize>() {
+            Ok(n) if (1..=poll.options.len()).contains(&n) => {
+                poll.votes.insert(message.user_id, n - 1);
+                format!("Voted for {}", poll.options[n - 1])
+            }
+            _ => format!("Vote with a number from 1 to {}", poll.options.len()),
+        },
+    };
+    if let Err(e) = ctx.send_group_message(message.group_id, reply, None).await {
+        eprintln!("Failed to answer a vote: {}", e);
+    }
+    HandlerControl::Block
+}
+
+async fn end_poll(
+    ctx: BotContext,
+    message: GroupMessageEvent,
+    State(polls): State<Arc<OpenPolls>>,
+    State(history): State<GroupConfigStore<PollHistory>>,
+) -> HandlerControl {
+    if message.message.extract_plain_text().trim() != "/endpoll" {
+        return HandlerControl::Continue;
+    }
+    let Some((_, poll)) = polls.0.remove(&message.group_id) else {
+        return HandlerControl::Block;
+    };
+
+    let tally = poll
+        .options
+        .iter()
+        .enumerate()
+        .map(|(i, option)| {
+            let votes = poll.votes.values().filter(|vote| **vote == i).count();
+            (option.clone(), votes)
+        })
+        .collect::<Vec<_>>();
+    let node = |content: String| ForwardNode {
+        ty: "node",
+        data: ForwardNodeData {
+            name: "Poll results".to_string(),
+            uin: message.user_id.to_string(),
+            content,
+        },
+    };
+    let messages = std::iter::once(node(poll.question.clone()))
+        .chain(
+            tally
+                .iter()
+                .map(|(option, votes)| node(format!("{}: {} votes", option, votes))),
+        )
+        .collect();
+    let posted = ctx
+        .call(SendGroupForwardMsg {
+            group_id: message.group_id,
+            messages,
+        })
+        .await;
+    if let Err(e) = posted {
+        eprintln!("Failed to post the poll results: {}", e);
+    }
+
+    history
+        .update(message.group_id, |history| {
+            history.polls.push(PollResult {
+                question: poll.question,
+                tally,
+            });
+        })
+        .await;
+    HandlerControl::Block
+}
+
+async fn past_polls(
+    ctx: BotContext,
+    message: GroupMessageEvent,
+    GroupConfig(history): GroupConfig<PollHistory>,
+) -> HandlerControl {
+    if message.message.extract_plain_text().trim() != "/polls" {
+        return HandlerControl::Continue;
+    }
+    let reply = match history.polls.is_empty() {
+        true => "No polls yet".to_string(),
+        false => history
+            .polls
+            .iter()
+            .map(|poll| {
+                let (winner, votes) = poll
+                    .tally
+                    .iter()
+                    .max_by_key(|(_, votes)| *votes)
+                    .cloned()
+                    .unwrap_or_default();
+                format!("{}: {} ({} votes)", poll.question, winner, votes)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    if let Err(e) = ctx.send_group_message(message.group_id, reply, None).await {
+        eprintln!("Failed to list the polls: {}", e);
+    }
+    HandlerControl::Block
+}
+
+/// Register the services and handlers of the example, also used by `tests/examples.rs`.
+pub fn configure(builder: FlowBotBuilder) -> FlowBotBuilder {
+    let polls = Arc::new(OpenPolls::default());
+
+    let open_polls = polls.clone();
+    let new_poll = Dialog::new(draft_step)
+        .trigger(|message| {
+            let is_group = matches!(message.info, TypedMessageInfo::Group(_));
+            let is_command = message.message.extract_plain_text().trim() == "/newpoll";
+            (is_group && is_command).then_some(Draft::Start)
+        })
+        .on_finish(move |poll: Poll, ctx, message| {
+            let polls = open_polls.clone();
+            async move {
+                let TypedMessageInfo::Group(info) = &message.info else {
+                    return;
+                };
+                let options = poll
+                    .options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option)| format!("{}. {}", i + 1, option))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let announcement = format!(
+                    "{}\n{}\nVote with /vote <number>, /endpoll ends the poll.",
+                    poll.question, options
+                );
+                polls.0.insert(info.group_id, poll);
+                say(&ctx, &message, &announcement).await;
+            }
+        })
+        .expired_notice("Creating the poll timed out.");
+
+    builder
+        .with_service(new_poll)
+        .with_state(polls)
+        .with_group_config::<PollHistory>("poll_history")
+        .with_handler(vote)
+        .with_handler(end_poll)
+        .with_handler(past_polls)
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = configure(FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    }))
+    .build();
+
+    bot.run().await.unwrap();
+}
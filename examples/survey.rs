@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use flow_bot::{
+    FlowBotBuilder,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+    },
+    event::message::Message,
+    extensions::dialog::{Dialog, Transition},
+    message::message_ext::MessageExt,
+};
+
+#[derive(Clone)]
+enum Survey {
+    Start,
+    Name,
+    Age { name: String },
+    Color { name: String, age: u32 },
+}
+
+struct Answers {
+    name: String,
+    age: u32,
+    color: String,
+}
+
+async fn ask(ctx: &BotContext, message: &Message, question: &str) {
+    if let Err(e) = ctx.reply(message, question).await {
+        eprintln!("Failed to ask {}: {}", message.user_id, e);
+    }
+}
+
+async fn step(state: Survey, ctx: BotContext, message: Message) -> Transition<Survey, Answers> {
+    let answer = message.message.extract_plain_text().trim().to_string();
+    match state {
+        Survey::Start => {
+            ask(&ctx, &message, "What is your name?").await;
+            Transition::Move(Survey::Name)
+        }
+        Survey::Name => {
+            ask(&ctx, &message, "How old are you?").await;
+            Transition::Move(Survey::Age { name: answer })
+        }
+        Survey::Age { name } => match answer.parse() {
+            Ok(age) => {
+                ask(&ctx, &message, "What is your favourite color?").await;
+                Transition::Move(Survey::Color { name, age })
+            }
+            Err(_) => {
+                ask(&ctx, &message, "Please answer with a number.").await;
+                Transition::Stay
+            }
+        },
+        Survey::Color { name, age } => Transition::Finish(Answers {
+            name,
+            age,
+            color: answer,
+        }),
+    }
+}
+
+async fn finish(answers: Answers, ctx: BotContext, message: Message) {
+    let summary = format!(
+        "Thanks {}! {} years old, likes {}.",
+        answers.name, answers.age, answers.color
+    );
+    ask(&ctx, &message, &summary).await;
+}
+
+pub fn configure(builder: FlowBotBuilder) -> FlowBotBuilder {
+    let survey = Dialog::new(step)
+        .trigger(|message| {
+            (message.message.extract_plain_text().trim() == "/survey").then_some(Survey::Start)
+        })
+        .on_finish(finish)
+        .ttl(Duration::from_secs(120))
+        .expired_notice("The survey timed out, send /survey to start again.");
+
+    builder.with_service(survey)
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = configure(FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    }))
+    .build();
+
+    bot.run().await.unwrap();
+}
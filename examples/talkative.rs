@@ -0,0 +1,54 @@
+use flow_bot::{
+    FlowBotBuilder,
+    api::{GroupHonorType, api_ext::ApiExt},
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        handler::HandlerControl,
+    },
+    event::notice::HonorChanged,
+};
+
+async fn announce_talkative(ctx: BotContext, changed: HonorChanged) -> HandlerControl {
+    if changed.honor != GroupHonorType::Talkative {
+        return HandlerControl::Skip;
+    }
+
+    let honors = match ctx
+        .get_group_honor_info(changed.group_id, GroupHonorType::Talkative)
+        .await
+    {
+        Ok(honors) => honors,
+        Err(e) => {
+            eprintln!("Failed to get the honors of {}: {}", changed.group_id, e);
+            return HandlerControl::Continue;
+        }
+    };
+    let nickname = honors
+        .honors_for(changed.user_id)
+        .into_iter()
+        .next()
+        .map_or_else(|| changed.user_id.to_string(), |(_, info)| info.nickname);
+
+    let message = format!("{} became today's talkative!", nickname);
+    if let Err(e) = ctx
+        .send_group_message(changed.group_id, message, None)
+        .await
+    {
+        eprintln!("Failed to announce the talkative: {}", e);
+    }
+    HandlerControl::Block
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let bot = FlowBotBuilder::new(ReverseConnectionConfig {
+        target: "ws://localhost:19999".to_string(),
+        auth: None,
+        reconnection: ReconnectionStrategy::None,
+    })
+    .with_handler(announce_talkative)
+    .build();
+
+    bot.run().await.unwrap();
+}
@@ -1,6 +1,17 @@
 use proc_macro::TokenStream;
 use syn::{FnArg, ImplItem, ItemImpl, parse_macro_input};
 
+/// Methods of the `Service` trait other than `serve`.
+const SERVICE_FNS: &[&str] = &[
+    "init",
+    "on_reconnect",
+    "on_disconnect",
+    "on_parse_error",
+    "on_shutdown",
+    "name",
+    "required_states",
+];
+
 #[proc_macro_attribute]
 pub fn flow_service(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as ItemImpl);
@@ -29,46 +40,71 @@ pub fn flow_service(_attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
-    // Collect the init function if it exists
-    let init_fn = item.items.iter().find_map(|it| {
-        let ImplItem::Fn(fn_item) = it else {
-            return None;
-        };
-        if fn_item.sig.ident == "init" {
-            Some(fn_item)
-        } else {
-            None
+    // Methods of the Service trait are passed through, every other one becomes a handler of `serve`.
+    let is_trait_fn =
+        |fn_item: &syn::ImplItemFn| SERVICE_FNS.iter().any(|name| fn_item.sig.ident == name);
+    let trait_fns = item.items.iter().filter_map(|it| match it {
+        ImplItem::Fn(fn_item) if is_trait_fn(fn_item) => Some(fn_item),
+        _ => None,
+    });
+
+    // States extracted with `State<T>` are required, unless the impl lists the required states itself.
+    let mut required = Vec::<&syn::Type>::new();
+    let declares_required = trait_fns
+        .clone()
+        .any(|fn_item| fn_item.sig.ident == "required_states");
+    for it in &item.items {
+        let ImplItem::Fn(fn_item) = it else { continue };
+        if is_trait_fn(fn_item) || declares_required {
+            continue;
+        }
+        for arg in &fn_item.sig.inputs {
+            if let Some(state) = state_type(arg)
+                && !required.iter().any(|seen| {
+                    quote::quote!(#seen).to_string() == quote::quote!(#state).to_string()
+                })
+            {
+                required.push(state);
+            }
+        }
+    }
+    let required_states = (!required.is_empty()).then(|| {
+        quote::quote! {
+            fn required_states(&self) -> ::std::vec::Vec<::flow_bot::base::service::RequiredState> {
+                ::std::vec![#(::flow_bot::base::service::RequiredState::of::<#required>()),*]
+            }
         }
     });
 
     let methods = item.items.iter().filter_map(|it| {
         let ImplItem::Fn(fn_item) = it else { return None };
-
-        // Skip the "init" function as it's part of the Service trait
-        if fn_item.sig.ident == "init" {
+        if is_trait_fn(fn_item) {
             return None;
         }
 
         let param_decls = fn_item.sig.inputs.iter().filter_map(|arg| {
             let FnArg::Typed(pat_type) = arg else { return None };
             let syn::Pat::Ident(ident) = &*pat_type.pat else { return None };
-            let syn::Type::Path(type_path) = &*pat_type.ty else { return None };
 
             let param_ident = &ident.ident;
-            let ty_ident = &type_path.path.segments.last()?.ident;
+            let ty = &pat_type.ty;
 
             Some(quote::quote! {
-                let #param_ident: #ty_ident = ::flow_bot::base::extract::FromEvent::from_event(context, event).await?;
+                let Some(#param_ident) = <#ty as ::flow_bot::base::extract::FromEvent>::from_event(context.clone(), event.clone()).await else {
+                    return ::flow_bot::base::handler::HandlerControl::Skip;
+                };
             })
         });
 
         let func_body = &fn_item.block;
 
         Some(quote::quote! {
-            let controller_result = {
+            // A method whose extractors do not match is skipped.
+            let controller_result = async {
                 #(#param_decls)*
                 #func_body
-            };
+            }
+            .await;
 
             if matches!(controller_result, ::flow_bot::base::handler::HandlerControl::Block) {
                 return ::flow_bot::base::handler::HandlerControl::Block;
@@ -79,7 +115,8 @@ pub fn flow_service(_attr: TokenStream, item: TokenStream) -> TokenStream {
     quote::quote! {
         #[::async_trait::async_trait]
         impl #trt for #struct_name {
-            #init_fn
+            #(#trait_fns)*
+            #required_states
 
             async fn serve(&self, context: ::flow_bot::base::context::BotContext, event: ::flow_bot::event::BotEvent) -> ::flow_bot::base::handler::HandlerControl {
                 #(#methods)*
@@ -89,3 +126,24 @@ pub fn flow_service(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+/// The `T` of a `State<T>` parameter.
+fn state_type(arg: &FnArg) -> Option<&syn::Type> {
+    let FnArg::Typed(pat_type) = arg else {
+        return None;
+    };
+    let syn::Type::Path(type_path) = &*pat_type.ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "State" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
@@ -2,18 +2,22 @@ use async_trait::async_trait;
 
 use crate::{
     event::{message::GroupAnonymousInfo, request::GroupRequestSubType},
-    message::IntoMessage,
+    message::{IntoMessage, media::MediaSource, segments::ReplyRef},
 };
 
 use super::{
-    BotStatus, CanSendResponse, FriendInfo, GetCookiesResponse, GetCredentialsResponse,
-    GetCsrfTokenResponse, GetFileResponse, GetForwardResponse, GetMessageResponse, GroupHonorInfo,
-    GroupHonorType, GroupInfoResponse, LoginInfo, RecordFormat, SendMessageResponse, StrangerInfo,
-    VersionInfo,
+    BanDuration, BotStatus, CanSendResponse, DownloadHeaders, FriendInfo, GetCookiesResponse,
+    GetCredentialsResponse, GetCsrfTokenResponse, GetFileResponse, GetForwardResponse,
+    GetMessageResponse, GroupAtAllRemain, GroupFileUrl, GroupHonorInfo, GroupHonorType,
+    GroupInfoResponse, GroupMemberInfo, GroupSystemMessages, LoginInfo, ModelShowResponse,
+    OnlineClientsResponse, RecordFormat, SendMessageResponse, StrangerInfo, VersionInfo,
 };
 
+/// The actions of the OneBot 11 standard.
+///
+/// Implemented by [`Context`](crate::base::context::Context), and the trait to implement for other backends, e.g. mocks.
 #[async_trait]
-pub trait ApiExt {
+pub trait CoreApi {
     type Error;
 
     async fn send_private_message<M>(
@@ -34,12 +38,64 @@ pub trait ApiExt {
     where
         M: IntoMessage + Send;
 
+    /// Send a CQ code string message, e.g. `[CQ:face,id=178]`, which the implementation parses unless `auto_escape` is set.
+    ///
+    /// Never pass user-provided text without escaping it, use [`send_private_message`](ApiExt::send_private_message) for it.
+    async fn send_private_message_raw_string(
+        &self,
+        user_id: i64,
+        message: String,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error>;
+
+    /// Group counterpart of [`send_private_message_raw_string`](ApiExt::send_private_message_raw_string).
+    async fn send_group_message_raw_string(
+        &self,
+        group_id: i64,
+        message: String,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error>;
+
+    /// Send a long message as several messages whose plain text is at most `max_len` characters long,
+    /// see [`MessageExt::split_chunks`] for how it is split. Stops at the first failed send.
+    ///
+    /// When chunks were sent before the failure, [`Context`](crate::base::context::Context) fails with
+    /// [`FlowError::PartiallySent`](crate::error::FlowError::PartiallySent) holding their responses.
+    ///
+    /// [`MessageExt::split_chunks`]: crate::message::message_ext::MessageExt::split_chunks
+    async fn send_private_message_chunked<M>(
+        &self,
+        user_id: i64,
+        message: M,
+        max_len: usize,
+    ) -> Result<Vec<SendMessageResponse>, Self::Error>
+    where
+        M: IntoMessage + Send;
+
+    /// Group counterpart of [`send_private_message_chunked`](ApiExt::send_private_message_chunked).
+    async fn send_group_message_chunked<M>(
+        &self,
+        group_id: i64,
+        message: M,
+        max_len: usize,
+    ) -> Result<Vec<SendMessageResponse>, Self::Error>
+    where
+        M: IntoMessage + Send;
+
     async fn delete_message(&self, message_id: i64) -> Result<(), Self::Error>;
 
-    async fn get_message(&self, message_id: i64) -> Result<GetMessageResponse, Self::Error>;
+    /// Get a message by id, accepts both numeric and string ids, see [`ReplyRef`].
+    async fn get_message<R>(&self, message_id: R) -> Result<GetMessageResponse, Self::Error>
+    where
+        R: Into<ReplyRef> + Send;
 
-    async fn get_forward_message(&self, message_id: i64)
-    -> Result<GetForwardResponse, Self::Error>;
+    /// Get forwarded messages by id, accepts both numeric and string ids, see [`ReplyRef`].
+    async fn get_forward_message<R>(
+        &self,
+        message_id: R,
+    ) -> Result<GetForwardResponse, Self::Error>
+    where
+        R: Into<ReplyRef> + Send;
 
     async fn send_like(&self, user_id: i64, times: Option<i32>) -> Result<(), Self::Error>;
 
@@ -50,20 +106,24 @@ pub trait ApiExt {
         reject_add_request: Option<bool>,
     ) -> Result<(), Self::Error>;
 
-    async fn set_group_ban(
+    async fn set_group_ban<D>(
         &self,
         group_id: i64,
         user_id: i64,
-        duration: Option<i64>,
-    ) -> Result<(), Self::Error>;
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send;
 
-    async fn set_group_anonymous_ban(
+    async fn set_group_anonymous_ban<D>(
         &self,
         group_id: i64,
         anonymous: Option<GroupAnonymousInfo>,
         flag: Option<String>,
-        duration: Option<i64>,
-    ) -> Result<(), Self::Error>;
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send;
 
     async fn set_whole_group_ban(
         &self,
@@ -99,13 +159,15 @@ pub trait ApiExt {
         is_dismiss: Option<bool>,
     ) -> Result<(), Self::Error>;
 
-    async fn set_group_special_title(
+    async fn set_group_special_title<D>(
         &self,
         group_id: i64,
         user_id: i64,
         special_title: Option<String>,
-        duration: Option<i64>,
-    ) -> Result<(), Self::Error>;
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send;
 
     async fn set_friend_add_request(
         &self,
@@ -145,7 +207,7 @@ pub trait ApiExt {
         group_id: i64,
         user_id: i64,
         no_cache: Option<bool>,
-    ) -> Result<GroupInfoResponse, Self::Error>;
+    ) -> Result<GroupMemberInfo, Self::Error>;
 
     async fn get_group_member_list(&self, group_id: i64) -> Result<Vec<FriendInfo>, Self::Error>;
 
@@ -184,3 +246,494 @@ pub trait ApiExt {
 
     async fn clean_cache(&self) -> Result<(), Self::Error>;
 }
+
+/// Actions added by go-cqhttp, NapCat, Lagrange and similar implementations, which not every implementation supports.
+///
+/// Check [`Capabilities::supports`](super::capabilities::Capabilities::supports) before relying on one.
+#[async_trait]
+pub trait ExtendedApi: CoreApi {
+    /// Send a private message to `user_id` through a temp session of `group_id`, for users that are not friends.
+    async fn send_temp_message<M>(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        message: M,
+    ) -> Result<SendMessageResponse, Self::Error>
+    where
+        M: IntoMessage + Send;
+
+    async fn get_online_clients(
+        &self,
+        no_cache: Option<bool>,
+    ) -> Result<OnlineClientsResponse, Self::Error>;
+
+    async fn get_model_show(&self, model: String) -> Result<ModelShowResponse, Self::Error>;
+
+    async fn set_model_show(&self, model: String, model_show: String) -> Result<(), Self::Error>;
+
+    async fn download_file(
+        &self,
+        url: String,
+        thread_count: Option<i32>,
+        headers: Option<DownloadHeaders>,
+    ) -> Result<GetFileResponse, Self::Error>;
+
+    async fn upload_private_file(
+        &self,
+        user_id: i64,
+        file: MediaSource,
+        name: String,
+    ) -> Result<(), Self::Error>;
+
+    async fn get_group_file_url(
+        &self,
+        group_id: i64,
+        file_id: String,
+        busid: i64,
+    ) -> Result<GroupFileUrl, Self::Error>;
+
+    /// Upload a file to the files of a group, into `folder` if set, the root folder otherwise.
+    async fn upload_group_file(
+        &self,
+        group_id: i64,
+        file: MediaSource,
+        name: String,
+        folder: Option<String>,
+    ) -> Result<(), Self::Error>;
+
+    async fn get_group_at_all_remain(&self, group_id: i64)
+    -> Result<GroupAtAllRemain, Self::Error>;
+
+    /// Sign in to a group.
+    ///
+    /// Sent as `send_group_sign`, which NapCat, Lagrange and go-cqhttp all accept. Some of them also expose it as `set_group_sign`.
+    async fn send_group_sign(&self, group_id: i64) -> Result<(), Self::Error>;
+
+    async fn mark_msg_as_read(&self, message_id: i64) -> Result<(), Self::Error>;
+
+    /// React to a message with an emoji, see [`emoji_id`] for common ids.
+    /// `set` defaults to `true`, pass `false` to remove the reaction.
+    ///
+    /// [`emoji_id`]: crate::api::emoji_id
+    async fn set_msg_emoji_like(
+        &self,
+        message_id: i64,
+        emoji_id: String,
+        set: Option<bool>,
+    ) -> Result<(), Self::Error>;
+
+    async fn get_group_system_msg(&self) -> Result<GroupSystemMessages, Self::Error>;
+
+    async fn group_poke(&self, group_id: i64, user_id: i64) -> Result<(), Self::Error>;
+
+    async fn friend_poke(&self, user_id: i64) -> Result<(), Self::Error>;
+}
+
+/// Every action of [`CoreApi`] and [`ExtendedApi`] in a single trait, so that one import is enough to call any of them.
+///
+/// Implemented for everything implementing both. Do not import it together with [`CoreApi`] or [`ExtendedApi`],
+/// which makes calls ambiguous.
+#[async_trait]
+pub trait ApiExt: ExtendedApi + Sync {
+    async fn send_private_message<M>(
+        &self,
+        user_id: i64,
+        message: M,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        CoreApi::send_private_message(self, user_id, message, auto_escape).await
+    }
+
+    async fn send_group_message<M>(
+        &self,
+        group_id: i64,
+        message: M,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        CoreApi::send_group_message(self, group_id, message, auto_escape).await
+    }
+
+    async fn send_private_message_raw_string(
+        &self,
+        user_id: i64,
+        message: String,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error> {
+        CoreApi::send_private_message_raw_string(self, user_id, message, auto_escape).await
+    }
+
+    async fn send_group_message_raw_string(
+        &self,
+        group_id: i64,
+        message: String,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error> {
+        CoreApi::send_group_message_raw_string(self, group_id, message, auto_escape).await
+    }
+
+    async fn send_private_message_chunked<M>(
+        &self,
+        user_id: i64,
+        message: M,
+        max_len: usize,
+    ) -> Result<Vec<SendMessageResponse>, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        CoreApi::send_private_message_chunked(self, user_id, message, max_len).await
+    }
+
+    async fn send_group_message_chunked<M>(
+        &self,
+        group_id: i64,
+        message: M,
+        max_len: usize,
+    ) -> Result<Vec<SendMessageResponse>, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        CoreApi::send_group_message_chunked(self, group_id, message, max_len).await
+    }
+
+    async fn delete_message(&self, message_id: i64) -> Result<(), Self::Error> {
+        CoreApi::delete_message(self, message_id).await
+    }
+
+    async fn get_message<R>(&self, message_id: R) -> Result<GetMessageResponse, Self::Error>
+    where
+        R: Into<ReplyRef> + Send,
+    {
+        CoreApi::get_message(self, message_id).await
+    }
+
+    async fn get_forward_message<R>(&self, message_id: R) -> Result<GetForwardResponse, Self::Error>
+    where
+        R: Into<ReplyRef> + Send,
+    {
+        CoreApi::get_forward_message(self, message_id).await
+    }
+
+    async fn send_like(&self, user_id: i64, times: Option<i32>) -> Result<(), Self::Error> {
+        CoreApi::send_like(self, user_id, times).await
+    }
+
+    async fn set_group_kick(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        reject_add_request: Option<bool>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_group_kick(self, group_id, user_id, reject_add_request).await
+    }
+
+    async fn set_group_ban<D>(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send,
+    {
+        CoreApi::set_group_ban(self, group_id, user_id, duration).await
+    }
+
+    async fn set_group_anonymous_ban<D>(
+        &self,
+        group_id: i64,
+        anonymous: Option<GroupAnonymousInfo>,
+        flag: Option<String>,
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send,
+    {
+        CoreApi::set_group_anonymous_ban(self, group_id, anonymous, flag, duration).await
+    }
+
+    async fn set_whole_group_ban(
+        &self,
+        group_id: i64,
+        enable: Option<bool>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_whole_group_ban(self, group_id, enable).await
+    }
+
+    async fn set_group_admin(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        enable: Option<bool>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_group_admin(self, group_id, user_id, enable).await
+    }
+
+    async fn set_group_anonymous(
+        &self,
+        group_id: i64,
+        enable: Option<bool>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_group_anonymous(self, group_id, enable).await
+    }
+
+    async fn set_group_card(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        card: Option<String>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_group_card(self, group_id, user_id, card).await
+    }
+
+    async fn set_group_name(&self, group_id: i64, group_name: String) -> Result<(), Self::Error> {
+        CoreApi::set_group_name(self, group_id, group_name).await
+    }
+
+    async fn set_group_leave(
+        &self,
+        group_id: i64,
+        is_dismiss: Option<bool>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_group_leave(self, group_id, is_dismiss).await
+    }
+
+    async fn set_group_special_title<D>(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        special_title: Option<String>,
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send,
+    {
+        CoreApi::set_group_special_title(self, group_id, user_id, special_title, duration).await
+    }
+
+    async fn set_friend_add_request(
+        &self,
+        flag: String,
+        approve: Option<bool>,
+        remark: Option<String>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_friend_add_request(self, flag, approve, remark).await
+    }
+
+    async fn set_group_add_request(
+        &self,
+        flag: String,
+        sub_type: GroupRequestSubType,
+        approve: Option<bool>,
+        reason: Option<String>,
+    ) -> Result<(), Self::Error> {
+        CoreApi::set_group_add_request(self, flag, sub_type, approve, reason).await
+    }
+
+    async fn get_login_info(&self) -> Result<LoginInfo, Self::Error> {
+        CoreApi::get_login_info(self).await
+    }
+
+    async fn get_stranger_info(
+        &self,
+        user_id: i64,
+        no_cache: Option<bool>,
+    ) -> Result<StrangerInfo, Self::Error> {
+        CoreApi::get_stranger_info(self, user_id, no_cache).await
+    }
+
+    async fn get_friend_list(&self) -> Result<Vec<FriendInfo>, Self::Error> {
+        CoreApi::get_friend_list(self).await
+    }
+
+    async fn get_group_info(
+        &self,
+        group_id: i64,
+        no_cache: Option<bool>,
+    ) -> Result<GroupInfoResponse, Self::Error> {
+        CoreApi::get_group_info(self, group_id, no_cache).await
+    }
+
+    async fn get_group_list(&self) -> Result<Vec<GroupInfoResponse>, Self::Error> {
+        CoreApi::get_group_list(self).await
+    }
+
+    async fn get_group_member_info(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        no_cache: Option<bool>,
+    ) -> Result<GroupMemberInfo, Self::Error> {
+        CoreApi::get_group_member_info(self, group_id, user_id, no_cache).await
+    }
+
+    async fn get_group_member_list(&self, group_id: i64) -> Result<Vec<FriendInfo>, Self::Error> {
+        CoreApi::get_group_member_list(self, group_id).await
+    }
+
+    async fn get_group_honor_info(
+        &self,
+        group_id: i64,
+        ty: GroupHonorType,
+    ) -> Result<GroupHonorInfo, Self::Error> {
+        CoreApi::get_group_honor_info(self, group_id, ty).await
+    }
+
+    async fn get_cookies(&self, domain: Option<String>) -> Result<GetCookiesResponse, Self::Error> {
+        CoreApi::get_cookies(self, domain).await
+    }
+
+    async fn get_csrf_token(&self) -> Result<GetCsrfTokenResponse, Self::Error> {
+        CoreApi::get_csrf_token(self).await
+    }
+
+    async fn get_credentials(
+        &self,
+        domain: Option<String>,
+    ) -> Result<GetCredentialsResponse, Self::Error> {
+        CoreApi::get_credentials(self, domain).await
+    }
+
+    async fn get_record(
+        &self,
+        file: String,
+        out_format: RecordFormat,
+    ) -> Result<GetFileResponse, Self::Error> {
+        CoreApi::get_record(self, file, out_format).await
+    }
+
+    async fn get_image(&self, file: String) -> Result<GetFileResponse, Self::Error> {
+        CoreApi::get_image(self, file).await
+    }
+
+    async fn can_send_image(&self) -> Result<CanSendResponse, Self::Error> {
+        CoreApi::can_send_image(self).await
+    }
+
+    async fn can_send_record(&self) -> Result<CanSendResponse, Self::Error> {
+        CoreApi::can_send_record(self).await
+    }
+
+    async fn get_status(&self) -> Result<BotStatus, Self::Error> {
+        CoreApi::get_status(self).await
+    }
+
+    async fn get_version_info(&self) -> Result<VersionInfo, Self::Error> {
+        CoreApi::get_version_info(self).await
+    }
+
+    async fn set_restart(&self, delay: Option<i32>) -> Result<(), Self::Error> {
+        CoreApi::set_restart(self, delay).await
+    }
+
+    async fn clean_cache(&self) -> Result<(), Self::Error> {
+        CoreApi::clean_cache(self).await
+    }
+
+    async fn send_temp_message<M>(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        message: M,
+    ) -> Result<SendMessageResponse, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        ExtendedApi::send_temp_message(self, group_id, user_id, message).await
+    }
+
+    async fn get_online_clients(
+        &self,
+        no_cache: Option<bool>,
+    ) -> Result<OnlineClientsResponse, Self::Error> {
+        ExtendedApi::get_online_clients(self, no_cache).await
+    }
+
+    async fn get_model_show(&self, model: String) -> Result<ModelShowResponse, Self::Error> {
+        ExtendedApi::get_model_show(self, model).await
+    }
+
+    async fn set_model_show(&self, model: String, model_show: String) -> Result<(), Self::Error> {
+        ExtendedApi::set_model_show(self, model, model_show).await
+    }
+
+    async fn download_file(
+        &self,
+        url: String,
+        thread_count: Option<i32>,
+        headers: Option<DownloadHeaders>,
+    ) -> Result<GetFileResponse, Self::Error> {
+        ExtendedApi::download_file(self, url, thread_count, headers).await
+    }
+
+    async fn upload_private_file(
+        &self,
+        user_id: i64,
+        file: MediaSource,
+        name: String,
+    ) -> Result<(), Self::Error> {
+        ExtendedApi::upload_private_file(self, user_id, file, name).await
+    }
+
+    async fn get_group_file_url(
+        &self,
+        group_id: i64,
+        file_id: String,
+        busid: i64,
+    ) -> Result<GroupFileUrl, Self::Error> {
+        ExtendedApi::get_group_file_url(self, group_id, file_id, busid).await
+    }
+
+    async fn upload_group_file(
+        &self,
+        group_id: i64,
+        file: MediaSource,
+        name: String,
+        folder: Option<String>,
+    ) -> Result<(), Self::Error> {
+        ExtendedApi::upload_group_file(self, group_id, file, name, folder).await
+    }
+
+    async fn get_group_at_all_remain(
+        &self,
+        group_id: i64,
+    ) -> Result<GroupAtAllRemain, Self::Error> {
+        ExtendedApi::get_group_at_all_remain(self, group_id).await
+    }
+
+    async fn send_group_sign(&self, group_id: i64) -> Result<(), Self::Error> {
+        ExtendedApi::send_group_sign(self, group_id).await
+    }
+
+    async fn mark_msg_as_read(&self, message_id: i64) -> Result<(), Self::Error> {
+        ExtendedApi::mark_msg_as_read(self, message_id).await
+    }
+
+    async fn set_msg_emoji_like(
+        &self,
+        message_id: i64,
+        emoji_id: String,
+        set: Option<bool>,
+    ) -> Result<(), Self::Error> {
+        ExtendedApi::set_msg_emoji_like(self, message_id, emoji_id, set).await
+    }
+
+    async fn get_group_system_msg(&self) -> Result<GroupSystemMessages, Self::Error> {
+        ExtendedApi::get_group_system_msg(self).await
+    }
+
+    async fn group_poke(&self, group_id: i64, user_id: i64) -> Result<(), Self::Error> {
+        ExtendedApi::group_poke(self, group_id, user_id).await
+    }
+
+    async fn friend_poke(&self, user_id: i64) -> Result<(), Self::Error> {
+        ExtendedApi::friend_poke(self, user_id).await
+    }
+}
+
+impl<T: ExtendedApi + Sync> ApiExt for T {}
@@ -1,40 +1,80 @@
 use async_trait::async_trait;
-use serde_json::json;
 
 use crate::{
     base::context::Context,
     error::FlowError,
     event::{message::GroupAnonymousInfo, request::GroupRequestSubType},
-    message::IntoMessage,
+    message::{IntoMessage, media::MediaSource, message_ext::MessageExt, segments::ReplyRef},
 };
 
 use super::{
-    BotStatus, CanSendResponse, FriendInfo, GetCookiesResponse, GetCredentialsResponse,
-    GetCsrfTokenResponse, GetFileResponse, GetForwardResponse, GetMessageResponse, GroupHonorInfo,
-    GroupHonorType, GroupInfoResponse, LoginInfo, RecordFormat, SendMessageResponse, VersionInfo,
-    api_ext::ApiExt,
+    AutoEscape, BanDuration, BotStatus, CanSendResponse, DownloadHeaders, FriendInfo,
+    GetCookiesResponse, GetCredentialsResponse, GetCsrfTokenResponse, GetFileResponse,
+    GetForwardResponse, GetMessageResponse, GroupAtAllRemain, GroupFileUrl, GroupHonorInfo,
+    GroupHonorType, GroupInfoResponse, GroupSystemMessages, LoginInfo, ModelShowResponse,
+    OnlineClientsResponse, RecordFormat, SendMessageResponse, VersionInfo,
+    api_ext::{CoreApi, ExtendedApi},
+    params::{self, ApiParams},
 };
 
+/// Call the action of the params struct `$params`, built from the variables named like its fields.
 macro_rules! impl_api {
+    ($s:ident, $params:ident $(, $field:ident $(: $value:expr)?)* $(,)?) => {
+        $s.call(params::$params { $($field $(: $value)?),* }).await
+    };
+}
+
+/// The error of a chunked send failing after sending `sent`, which is returned with it.
+fn partially_sent(sent: Vec<SendMessageResponse>, total: usize, error: FlowError) -> FlowError {
+    match sent.is_empty() {
+        true => error,
+        false => FlowError::PartiallySent {
+            sent,
+            total,
+            source: Box::new(error),
+        },
+    }
+}
 
-    ($s:ident,$name:ident) => {{
-        let resp = $s.send_obj(stringify!($name).to_string(), json!({})).await;
-        resp.map(|r| r.data)
-    }};
-
-    ($s:ident,$name:ident, $($params:ident),*) => {{
-            let params_json = json!({
-                $(
-                    stringify!($params): $params,
-                )*
-            });
-            let resp = $s.send_obj(stringify!($name).to_string(), params_json).await;
-            resp.map(|r| r.data)
-    }};
+impl Context {
+    /// Call the action of `params`, returning the `data` of the response.
+    /// Responses with a failed retcode fail with [`FlowError::Api`].
+    ///
+    /// Used by every [`ApiExt`](super::api_ext::ApiExt) method, for calls built from [`params`] structs directly.
+    ///
+    /// # Migrating from earlier versions
+    ///
+    /// The retcode used to be ignored: calls returning `()` succeeded whatever it was, and other calls failed
+    /// with a JSON error when the failed response had a `null` data. Both now fail with [`FlowError::Api`],
+    /// whose [`RetCode`](super::retcode::RetCode) tells why. To get the response whatever its retcode as before,
+    /// use [`call_action`](Context::call_action) with the params serialized:
+    ///
+    /// ```no_run
+    /// # async fn example(ctx: flow_bot::base::context::BotContext) -> Result<(), flow_bot::error::FlowError> {
+    /// use flow_bot::api::params::{ApiParams, DeleteMessage};
+    ///
+    /// let params = DeleteMessage { message_id: 1 };
+    /// let response = ctx.call_action(DeleteMessage::ACTION, serde_json::to_value(params)?).await?;
+    /// if response.retcode != 0 {
+    ///     tracing::info!("Not deleted, retcode {}", response.retcode);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call<P: ApiParams>(&self, params: P) -> Result<P::Response, FlowError> {
+        self.send_obj(P::ACTION.to_string(), params, true)
+            .await
+            .map(|r| r.data)
+    }
+
+    /// `auto_escape` as passed, or the [`AutoEscape`] registered as a state.
+    fn auto_escape(&self, auto_escape: Option<bool>) -> Option<bool> {
+        auto_escape.or_else(|| self.state.get::<AutoEscape>().map(|default| default.0))
+    }
 }
 
 #[async_trait]
-impl ApiExt for Context {
+impl CoreApi for Context {
     type Error = FlowError;
 
     async fn send_private_message<M>(
@@ -47,15 +87,15 @@ impl ApiExt for Context {
         M: IntoMessage + Send,
     {
         let message = message.into_message();
-        let params_json = json!({
-            "user_id": user_id,
-            "message": message,
-            "auto_escape": auto_escape,
-        });
-        let resp = self
-            .send_obj("send_private_msg".to_string(), params_json)
-            .await;
-        resp.map(|r| r.data)
+        let auto_escape = self.auto_escape(auto_escape);
+        impl_api!(
+            self,
+            SendPrivateMsg,
+            user_id,
+            message: message.into(),
+            group_id: None,
+            auto_escape
+        )
     }
 
     async fn send_group_message<M>(
@@ -68,34 +108,113 @@ impl ApiExt for Context {
         M: IntoMessage + Send,
     {
         let message = message.into_message();
-        let params_json = json!({
-            "group_id": group_id,
-            "message": message,
-            "auto_escape": auto_escape,
-        });
-        let resp = self
-            .send_obj("send_group_msg".to_string(), params_json)
-            .await;
-        resp.map(|r| r.data)
+        let auto_escape = self.auto_escape(auto_escape);
+        impl_api!(
+            self,
+            SendGroupMsg,
+            group_id,
+            message: message.into(),
+            auto_escape
+        )
     }
 
-    async fn delete_message(&self, message_id: i64) -> Result<(), Self::Error> {
-        impl_api!(self, delete_message, message_id)
+    async fn send_private_message_raw_string(
+        &self,
+        user_id: i64,
+        message: String,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error> {
+        let auto_escape = self.auto_escape(auto_escape);
+        impl_api!(
+            self,
+            SendPrivateMsg,
+            user_id,
+            message: message.into(),
+            group_id: None,
+            auto_escape
+        )
     }
 
-    async fn get_message(&self, message_id: i64) -> Result<GetMessageResponse, Self::Error> {
-        impl_api!(self, get_msg, message_id)
+    async fn send_group_message_raw_string(
+        &self,
+        group_id: i64,
+        message: String,
+        auto_escape: Option<bool>,
+    ) -> Result<SendMessageResponse, Self::Error> {
+        let auto_escape = self.auto_escape(auto_escape);
+        impl_api!(
+            self,
+            SendGroupMsg,
+            group_id,
+            message: message.into(),
+            auto_escape
+        )
     }
 
-    async fn get_forward_message(
+    async fn send_private_message_chunked<M>(
         &self,
-        message_id: i64,
-    ) -> Result<GetForwardResponse, Self::Error> {
-        impl_api!(self, get_forward_msg, message_id)
+        user_id: i64,
+        message: M,
+        max_len: usize,
+    ) -> Result<Vec<SendMessageResponse>, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        let chunks = message.into_message().split_chunks(max_len);
+        let total = chunks.len();
+        let mut responses = Vec::new();
+        for chunk in chunks {
+            match self.send_private_message(user_id, chunk, None).await {
+                Ok(response) => responses.push(response),
+                Err(e) => return Err(partially_sent(responses, total, e)),
+            }
+        }
+        Ok(responses)
+    }
+
+    async fn send_group_message_chunked<M>(
+        &self,
+        group_id: i64,
+        message: M,
+        max_len: usize,
+    ) -> Result<Vec<SendMessageResponse>, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        let chunks = message.into_message().split_chunks(max_len);
+        let total = chunks.len();
+        let mut responses = Vec::new();
+        for chunk in chunks {
+            match self.send_group_message(group_id, chunk, None).await {
+                Ok(response) => responses.push(response),
+                Err(e) => return Err(partially_sent(responses, total, e)),
+            }
+        }
+        Ok(responses)
+    }
+
+    async fn delete_message(&self, message_id: i64) -> Result<(), Self::Error> {
+        impl_api!(self, DeleteMessage, message_id)
+    }
+
+    async fn get_message<R>(&self, message_id: R) -> Result<GetMessageResponse, Self::Error>
+    where
+        R: Into<ReplyRef> + Send,
+    {
+        let message_id = message_id.into();
+        impl_api!(self, GetMsg, message_id)
+    }
+
+    async fn get_forward_message<R>(&self, message_id: R) -> Result<GetForwardResponse, Self::Error>
+    where
+        R: Into<ReplyRef> + Send,
+    {
+        let message_id = message_id.into();
+        impl_api!(self, GetForwardMsg, message_id)
     }
 
     async fn send_like(&self, user_id: i64, times: Option<i32>) -> Result<(), Self::Error> {
-        impl_api!(self, send_like, user_id, times)
+        impl_api!(self, SendLike, user_id, times)
     }
 
     async fn set_group_kick(
@@ -104,28 +223,36 @@ impl ApiExt for Context {
         user_id: i64,
         reject_add_request: Option<bool>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_kick, group_id, user_id, reject_add_request)
+        impl_api!(self, SetGroupKick, group_id, user_id, reject_add_request)
     }
 
-    async fn set_group_ban(
+    async fn set_group_ban<D>(
         &self,
         group_id: i64,
         user_id: i64,
-        duration: Option<i64>,
-    ) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_ban, group_id, user_id, duration)
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send,
+    {
+        let duration = duration.into();
+        impl_api!(self, SetGroupBan, group_id, user_id, duration)
     }
 
-    async fn set_group_anonymous_ban(
+    async fn set_group_anonymous_ban<D>(
         &self,
         group_id: i64,
         anonymous: Option<GroupAnonymousInfo>,
         flag: Option<String>,
-        duration: Option<i64>,
-    ) -> Result<(), Self::Error> {
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send,
+    {
+        let duration = duration.into();
         impl_api!(
             self,
-            set_group_anonymous_ban,
+            SetGroupAnonymousBan,
             group_id,
             anonymous,
             flag,
@@ -138,7 +265,7 @@ impl ApiExt for Context {
         group_id: i64,
         enable: Option<bool>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_whole_group_ban, group_id, enable)
+        impl_api!(self, SetWholeGroupBan, group_id, enable)
     }
 
     async fn set_group_admin(
@@ -147,7 +274,7 @@ impl ApiExt for Context {
         user_id: i64,
         enable: Option<bool>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_admin, group_id, user_id, enable)
+        impl_api!(self, SetGroupAdmin, group_id, user_id, enable)
     }
 
     async fn set_group_anonymous(
@@ -155,7 +282,7 @@ impl ApiExt for Context {
         group_id: i64,
         enable: Option<bool>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_anonymous, group_id, enable)
+        impl_api!(self, SetGroupAnonymous, group_id, enable)
     }
 
     async fn set_group_card(
@@ -164,11 +291,11 @@ impl ApiExt for Context {
         user_id: i64,
         card: Option<String>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_card, group_id, user_id, card)
+        impl_api!(self, SetGroupCard, group_id, user_id, card)
     }
 
     async fn set_group_name(&self, group_id: i64, group_name: String) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_name, group_id, group_name)
+        impl_api!(self, SetGroupName, group_id, group_name)
     }
 
     async fn set_group_leave(
@@ -176,19 +303,23 @@ impl ApiExt for Context {
         group_id: i64,
         is_dismiss: Option<bool>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_leave, group_id, is_dismiss)
+        impl_api!(self, SetGroupLeave, group_id, is_dismiss)
     }
 
-    async fn set_group_special_title(
+    async fn set_group_special_title<D>(
         &self,
         group_id: i64,
         user_id: i64,
         special_title: Option<String>,
-        duration: Option<i64>,
-    ) -> Result<(), Self::Error> {
+        duration: D,
+    ) -> Result<(), Self::Error>
+    where
+        D: Into<BanDuration> + Send,
+    {
+        let duration = duration.into();
         impl_api!(
             self,
-            set_group_special_title,
+            SetGroupSpecialTitle,
             group_id,
             user_id,
             special_title,
@@ -202,7 +333,7 @@ impl ApiExt for Context {
         approve: Option<bool>,
         remark: Option<String>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_friend_add_request, flag, approve, remark)
+        impl_api!(self, SetFriendAddRequest, flag, approve, remark)
     }
 
     async fn set_group_add_request(
@@ -212,11 +343,11 @@ impl ApiExt for Context {
         approve: Option<bool>,
         reason: Option<String>,
     ) -> Result<(), Self::Error> {
-        impl_api!(self, set_group_add_request, flag, sub_type, approve, reason)
+        impl_api!(self, SetGroupAddRequest, flag, sub_type, approve, reason)
     }
 
     async fn get_login_info(&self) -> Result<LoginInfo, Self::Error> {
-        impl_api!(self, get_login_info)
+        impl_api!(self, GetLoginInfo)
     }
 
     async fn get_stranger_info(
@@ -224,11 +355,11 @@ impl ApiExt for Context {
         user_id: i64,
         no_cache: Option<bool>,
     ) -> Result<crate::api::StrangerInfo, Self::Error> {
-        impl_api!(self, get_stranger_info, user_id, no_cache)
+        impl_api!(self, GetStrangerInfo, user_id, no_cache)
     }
 
     async fn get_friend_list(&self) -> Result<Vec<FriendInfo>, Self::Error> {
-        impl_api!(self, get_friend_list)
+        impl_api!(self, GetFriendList)
     }
 
     async fn get_group_info(
@@ -236,11 +367,11 @@ impl ApiExt for Context {
         group_id: i64,
         no_cache: Option<bool>,
     ) -> Result<crate::api::GroupInfoResponse, Self::Error> {
-        impl_api!(self, get_group_info, group_id, no_cache)
+        impl_api!(self, GetGroupInfo, group_id, no_cache)
     }
 
     async fn get_group_list(&self) -> Result<Vec<GroupInfoResponse>, Self::Error> {
-        impl_api!(self, get_group_list)
+        impl_api!(self, GetGroupList)
     }
 
     async fn get_group_member_info(
@@ -248,15 +379,15 @@ impl ApiExt for Context {
         group_id: i64,
         user_id: i64,
         no_cache: Option<bool>,
-    ) -> Result<crate::api::GroupInfoResponse, Self::Error> {
-        impl_api!(self, get_group_member_info, group_id, user_id, no_cache)
+    ) -> Result<crate::api::GroupMemberInfo, Self::Error> {
+        impl_api!(self, GetGroupMemberInfo, group_id, user_id, no_cache)
     }
 
     async fn get_group_member_list(
         &self,
         group_id: i64,
     ) -> Result<Vec<crate::api::FriendInfo>, Self::Error> {
-        impl_api!(self, get_group_member_list, group_id)
+        impl_api!(self, GetGroupMemberList, group_id)
     }
 
     async fn get_group_honor_info(
@@ -264,29 +395,22 @@ impl ApiExt for Context {
         group_id: i64,
         ty: GroupHonorType,
     ) -> Result<GroupHonorInfo, Self::Error> {
-        let params_json = json!({
-            "group_id": group_id,
-            "type": ty,
-        });
-        let resp = self
-            .send_obj("get_group_honor_info".to_string(), params_json)
-            .await;
-        resp.map(|r| r.data)
+        impl_api!(self, GetGroupHonorInfo, group_id, ty)
     }
 
     async fn get_cookies(&self, domain: Option<String>) -> Result<GetCookiesResponse, Self::Error> {
-        impl_api!(self, get_cookies, domain)
+        impl_api!(self, GetCookies, domain)
     }
 
     async fn get_csrf_token(&self) -> Result<GetCsrfTokenResponse, Self::Error> {
-        impl_api!(self, get_csrf_token)
+        impl_api!(self, GetCsrfToken)
     }
 
     async fn get_credentials(
         &self,
         domain: Option<String>,
     ) -> Result<GetCredentialsResponse, Self::Error> {
-        impl_api!(self, get_credentials, domain)
+        impl_api!(self, GetCredentials, domain)
     }
 
     async fn get_record(
@@ -294,34 +418,146 @@ impl ApiExt for Context {
         file: String,
         out_format: RecordFormat,
     ) -> Result<GetFileResponse, Self::Error> {
-        impl_api!(self, get_record, file, out_format)
+        impl_api!(self, GetRecord, file, out_format)
     }
 
     async fn get_image(&self, file: String) -> Result<GetFileResponse, Self::Error> {
-        impl_api!(self, get_image, file)
+        impl_api!(self, GetImage, file)
     }
 
     async fn can_send_image(&self) -> Result<CanSendResponse, Self::Error> {
-        impl_api!(self, can_send_image)
+        impl_api!(self, CanSendImage)
     }
 
     async fn can_send_record(&self) -> Result<CanSendResponse, Self::Error> {
-        impl_api!(self, can_send_record)
+        impl_api!(self, CanSendRecord)
     }
 
     async fn get_status(&self) -> Result<BotStatus, Self::Error> {
-        impl_api!(self, get_status)
+        impl_api!(self, GetStatus)
     }
 
     async fn get_version_info(&self) -> Result<VersionInfo, Self::Error> {
-        impl_api!(self, get_version_info)
+        impl_api!(self, GetVersionInfo)
     }
 
     async fn set_restart(&self, delay: Option<i32>) -> Result<(), Self::Error> {
-        impl_api!(self, set_restart, delay)
+        impl_api!(self, SetRestart, delay)
     }
 
     async fn clean_cache(&self) -> Result<(), Self::Error> {
-        impl_api!(self, clean_cache)
+        impl_api!(self, CleanCache)
+    }
+}
+
+#[async_trait]
+impl ExtendedApi for Context {
+    async fn send_temp_message<M>(
+        &self,
+        group_id: i64,
+        user_id: i64,
+        message: M,
+    ) -> Result<SendMessageResponse, Self::Error>
+    where
+        M: IntoMessage + Send,
+    {
+        let message = message.into_message();
+        let auto_escape = self.auto_escape(None);
+        impl_api!(
+            self,
+            SendPrivateMsg,
+            user_id,
+            message: message.into(),
+            group_id: Some(group_id),
+            auto_escape
+        )
+    }
+
+    async fn get_online_clients(
+        &self,
+        no_cache: Option<bool>,
+    ) -> Result<OnlineClientsResponse, Self::Error> {
+        impl_api!(self, GetOnlineClients, no_cache)
+    }
+
+    async fn get_model_show(&self, model: String) -> Result<ModelShowResponse, Self::Error> {
+        impl_api!(self, GetModelShow, model)
+    }
+
+    async fn set_model_show(&self, model: String, model_show: String) -> Result<(), Self::Error> {
+        impl_api!(self, SetModelShow, model, model_show)
+    }
+
+    async fn download_file(
+        &self,
+        url: String,
+        thread_count: Option<i32>,
+        headers: Option<DownloadHeaders>,
+    ) -> Result<GetFileResponse, Self::Error> {
+        impl_api!(self, DownloadFile, url, thread_count, headers)
+    }
+
+    async fn upload_private_file(
+        &self,
+        user_id: i64,
+        file: MediaSource,
+        name: String,
+    ) -> Result<(), Self::Error> {
+        impl_api!(self, UploadPrivateFile, user_id, file, name)
+    }
+
+    async fn get_group_file_url(
+        &self,
+        group_id: i64,
+        file_id: String,
+        busid: i64,
+    ) -> Result<GroupFileUrl, Self::Error> {
+        impl_api!(self, GetGroupFileUrl, group_id, file_id, busid)
+    }
+
+    async fn upload_group_file(
+        &self,
+        group_id: i64,
+        file: MediaSource,
+        name: String,
+        folder: Option<String>,
+    ) -> Result<(), Self::Error> {
+        impl_api!(self, UploadGroupFile, group_id, file, name, folder)
+    }
+
+    async fn get_group_at_all_remain(
+        &self,
+        group_id: i64,
+    ) -> Result<GroupAtAllRemain, Self::Error> {
+        impl_api!(self, GetGroupAtAllRemain, group_id)
+    }
+
+    async fn send_group_sign(&self, group_id: i64) -> Result<(), Self::Error> {
+        impl_api!(self, SendGroupSign, group_id)
+    }
+
+    async fn mark_msg_as_read(&self, message_id: i64) -> Result<(), Self::Error> {
+        impl_api!(self, MarkMsgAsRead, message_id)
+    }
+
+    async fn set_msg_emoji_like(
+        &self,
+        message_id: i64,
+        emoji_id: String,
+        set: Option<bool>,
+    ) -> Result<(), Self::Error> {
+        impl_api!(self, SetMsgEmojiLike, message_id, emoji_id, set)
+    }
+
+    async fn get_group_system_msg(&self) -> Result<GroupSystemMessages, Self::Error> {
+        impl_api!(self, GetGroupSystemMsg)
+    }
+
+    async fn group_poke(&self, group_id: i64, user_id: i64) -> Result<(), Self::Error> {
+        impl_api!(self, GroupPoke, group_id, user_id)
+    }
+
+    async fn friend_poke(&self, user_id: i64) -> Result<(), Self::Error> {
+        impl_api!(self, FriendPoke, user_id)
     }
 }
@@ -0,0 +1,74 @@
+use dashmap::DashMap;
+use tokio::sync::OnceCell;
+
+use super::{
+    VersionInfo,
+    params::{CORE_ACTIONS, EXTENDED_ACTIONS},
+    retcode::RetCode,
+};
+
+/// Implementations known to support the [`EXTENDED_ACTIONS`], matched against the lowercased `app_name`.
+const EXTENDED_IMPLEMENTATIONS: &[&str] = &["go-cqhttp", "napcat", "lagrange", "llonebot", "llbot"];
+
+/// What the connected implementation supports, from [`Context::capabilities`].
+///
+/// Actions are assumed supported by the implementation they are known for until a call answers with retcode 1404,
+/// and any action that succeeded once is known to be supported.
+///
+/// [`Context::capabilities`]: crate::base::context::Context::capabilities
+#[derive(Default)]
+pub struct Capabilities {
+    version: OnceCell<VersionInfo>,
+    learned: DashMap<String, bool>,
+}
+
+impl Capabilities {
+    pub(crate) fn version_cell(&self) -> &OnceCell<VersionInfo> {
+        &self.version
+    }
+
+    /// The version info of the implementation, `None` if it could not be queried yet.
+    pub fn version(&self) -> Option<&VersionInfo> {
+        self.version.get()
+    }
+
+    pub fn supports(&self, action: &str) -> bool {
+        if let Some(supported) = self.learned.get(action) {
+            return *supported;
+        }
+        if CORE_ACTIONS.iter().any(|spec| spec.action == action) {
+            return true;
+        }
+        EXTENDED_ACTIONS.iter().any(|spec| spec.action == action) && self.has_extensions()
+    }
+
+    /// Whether the implementation is one known to support the [`EXTENDED_ACTIONS`].
+    pub fn has_extensions(&self) -> bool {
+        self.version().is_some_and(|version| {
+            let app_name = version.app_name.to_lowercase();
+            EXTENDED_IMPLEMENTATIONS
+                .iter()
+                .any(|name| app_name.contains(name))
+        })
+    }
+
+    /// Map `code` as sent by the connected implementation, see [`RetCode::for_implementation`].
+    pub fn retcode(&self, code: i32) -> RetCode {
+        match self.version() {
+            Some(version) => RetCode::for_implementation(code, &version.app_name),
+            None => RetCode::from(code),
+        }
+    }
+
+    /// Record the result of a call to `action`.
+    pub(crate) fn learn(&self, action: &str, retcode: RetCode) {
+        let supported = !retcode.is_unsupported();
+        // Other failures say nothing about support.
+        if !retcode.is_success() && supported {
+            return;
+        }
+        if self.learned.get(action).as_deref() != Some(&supported) {
+            self.learned.insert(action.to_string(), supported);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use serde_json::Value;
+
+/// Fields redacted by default, they usually carry credentials.
+const DEFAULT_REDACTED: [&str; 5] = ["access_token", "token", "password", "cookies", "csrf_token"];
+
+/// Values longer than this are cut in digests.
+const MAX_VALUE_LEN: usize = 48;
+
+/// Digests longer than this are cut.
+const MAX_DIGEST_LEN: usize = 160;
+
+/// The param fields whose values are hidden in the digests of [`FlowError::ApiCall`],
+/// extended with [`with_redacted_params`].
+///
+/// Fields are matched by name at any depth, `access_token`, `token`, `password`, `cookies`
+/// and `csrf_token` are always redacted.
+///
+/// ```
+/// use flow_bot::api::digest::ParamRedaction;
+/// use serde_json::json;
+///
+/// let redaction = ParamRedaction::default().with_field("secret");
+/// let params = json!({ "group_id": 123, "user_id": 456, "secret": "hunter2", "name": "bot" });
+/// assert_eq!(
+///     redaction.digest(&params),
+///     r#"group_id=123, name="bot", secret=***, user_id=456"#
+/// );
+/// assert_eq!(redaction.digest(&json!({})), "");
+/// ```
+///
+/// [`FlowError::ApiCall`]: crate::error::FlowError::ApiCall
+/// [`with_redacted_params`]: crate::FlowBotBuilder::with_redacted_params
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamRedaction {
+    fields: Vec<String>,
+}
+
+impl Default for ParamRedaction {
+    fn default() -> Self {
+        Self {
+            fields: DEFAULT_REDACTED
+                .iter()
+                .map(|field| field.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl ParamRedaction {
+    /// Also redact `field`.
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.fields.push(field.into());
+        self
+    }
+
+    pub fn is_redacted(&self, field: &str) -> bool {
+        self.fields.iter().any(|redacted| redacted == field)
+    }
+
+    /// A short, redacted rendering of `params`, as `field=value` pairs for an object.
+    pub fn digest(&self, params: &Value) -> String {
+        let digest = match params {
+            Value::Null => String::new(),
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(field, value)| format!("{}={}", field, self.value(field, value)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            value => self.value("", value),
+        };
+        truncate(digest, MAX_DIGEST_LEN)
+    }
+
+    fn value(&self, field: &str, value: &Value) -> String {
+        if self.is_redacted(field) {
+            return "***".to_string();
+        }
+        let mut value = value.clone();
+        self.redact(&mut value);
+        truncate(value.to_string(), MAX_VALUE_LEN)
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (field, value) in fields.iter_mut() {
+                    match self.is_redacted(field) {
+                        true => *value = Value::String("***".to_string()),
+                        false => self.redact(value),
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.redact(value)),
+            _ => {}
+        }
+    }
+}
+
+fn truncate(mut text: String, max_len: usize) -> String {
+    if let Some((at, _)) = text.char_indices().nth(max_len) {
+        text.truncate(at);
+        text.push('…');
+    }
+    text
+}
@@ -0,0 +1,16 @@
+//! Common emoji ids accepted by `set_msg_emoji_like`.
+//!
+//! Ids below 1000 are QQ face ids, larger ones are unicode code points.
+
+pub const GRIN: &str = "13";
+pub const SMILE: &str = "14";
+pub const CRY: &str = "5";
+pub const ROSE: &str = "63";
+pub const HEART: &str = "66";
+pub const THUMBS_UP: &str = "76";
+pub const THUMBS_DOWN: &str = "77";
+pub const APPLAUSE: &str = "99";
+pub const OK: &str = "124";
+pub const DOGE: &str = "179";
+pub const FACE_WITH_TEARS_OF_JOY: &str = "128514";
+pub const FIRE: &str = "128293";
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{
     event::message::{GroupSenderInfo, GroupSenderRole, PrivateSenderInfo, SenderSex},
@@ -9,6 +9,12 @@ use crate::{
 
 pub mod api_ext;
 pub mod api_impl;
+pub mod capabilities;
+pub mod digest;
+pub mod emoji_id;
+pub mod params;
+pub mod quirks;
+pub mod retcode;
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
@@ -21,12 +27,19 @@ pub enum ApiRetStatus {
 #[derive(Deserialize, Debug, Clone)]
 pub struct ApiResponse<T> {
     pub status: ApiRetStatus,
+    /// See [`RetCode`](retcode::RetCode) for its meaning.
     pub retcode: i32,
     pub data: T,
+    /// Why the call failed, `msg` in go-cqhttp.
+    #[serde(default, alias = "msg")]
+    pub message: Option<String>,
+    /// Why the call failed, worded for users.
+    #[serde(default)]
+    pub wording: Option<String>,
     pub echo: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BotStatus {
     pub online: Option<bool>,
     pub good: bool,
@@ -42,20 +55,59 @@ pub struct SendMessageResponse {
 #[derive(Deserialize, Debug, Clone)]
 #[serde(tag = "message_type", rename_all = "snake_case")]
 pub enum GetMessageType {
-    Private { sender: PrivateSenderInfo },
-    Group { sender: GroupSenderInfo },
+    Private {
+        sender: PrivateSenderInfo,
+    },
+    Group {
+        #[serde(default)]
+        group_id: Option<i64>,
+        sender: GroupSenderInfo,
+    },
 }
 
+/// Response of `get_msg`.
+///
+/// Implementations disagree on the shape of this response, `real_id` falls back to `message_seq` (NapCat)
+/// and is `None` if neither is present.
 #[derive(Deserialize, Debug, Clone)]
+#[serde(from = "RawGetMessageResponse")]
 pub struct GetMessageResponse {
-    pub time: i32,
-    pub message_id: i32,
-    pub real_id: i32,
+    pub time: i64,
+    pub message_id: i64,
+    pub real_id: Option<i64>,
+    pub font: Option<i32>,
     pub message: message::Message,
-    #[serde(flatten)]
     pub ty: GetMessageType,
 }
 
+#[derive(Deserialize)]
+struct RawGetMessageResponse {
+    time: i64,
+    message_id: i64,
+    #[serde(default)]
+    real_id: Option<i64>,
+    #[serde(default)]
+    message_seq: Option<i64>,
+    #[serde(default)]
+    font: Option<i32>,
+    message: message::Message,
+    #[serde(flatten)]
+    ty: GetMessageType,
+}
+
+impl From<RawGetMessageResponse> for GetMessageResponse {
+    fn from(raw: RawGetMessageResponse) -> Self {
+        Self {
+            time: raw.time,
+            message_id: raw.message_id,
+            real_id: raw.real_id.or(raw.message_seq),
+            font: raw.font,
+            message: raw.message,
+            ty: raw.ty,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GetForwardResponse {
     pub message: message::Message,
@@ -73,6 +125,21 @@ pub struct StrangerInfo {
     pub nickname: String,
     pub sex: SenderSex,
     pub age: i32,
+    /// QQ level, sent by go-cqhttp.
+    pub level: Option<i32>,
+    /// QQ level, sent by NapCat.
+    #[serde(rename = "qqLevel")]
+    pub qq_level: Option<i32>,
+    /// Registration time of the account as a unix timestamp, sent by NapCat.
+    pub reg_time: Option<i64>,
+    pub login_days: Option<i64>,
+}
+
+impl StrangerInfo {
+    /// The QQ level of the user, `None` if the implementation does not send it.
+    pub fn account_level(&self) -> Option<i32> {
+        self.level.or(self.qq_level)
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -136,7 +203,32 @@ pub struct GroupHonorInfo {
     pub emotion_list: Option<Vec<HonorInfo>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl GroupHonorInfo {
+    /// Every honor holder with their category, lists missing from the response are skipped.
+    ///
+    /// The current talkative is part of `talkative_list`, so it is not listed separately.
+    pub fn all_honors(&self) -> Vec<(GroupHonorType, HonorInfo)> {
+        [
+            (GroupHonorType::Talkative, &self.talkative_list),
+            (GroupHonorType::Performer, &self.performer_list),
+            (GroupHonorType::Legend, &self.legend_list),
+            (GroupHonorType::StrongNewbie, &self.strong_newbie_list),
+            (GroupHonorType::Emotion, &self.emotion_list),
+        ]
+        .into_iter()
+        .flat_map(|(honor, list)| list.iter().flatten().map(move |info| (honor, info.clone())))
+        .collect()
+    }
+
+    /// The honors held by `user_id`.
+    pub fn honors_for(&self, user_id: i64) -> Vec<(GroupHonorType, HonorInfo)> {
+        let mut honors = self.all_honors();
+        honors.retain(|(_, info)| info.user_id == user_id);
+        honors
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum GroupHonorType {
     Talkative,
@@ -147,6 +239,35 @@ pub enum GroupHonorType {
     All,
 }
 
+impl GroupHonorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupHonorType::Talkative => "talkative",
+            GroupHonorType::Performer => "performer",
+            GroupHonorType::Legend => "legend",
+            GroupHonorType::StrongNewbie => "strong_newbie",
+            GroupHonorType::Emotion => "emotion",
+            GroupHonorType::All => "all",
+        }
+    }
+}
+
+impl std::str::FromStr for GroupHonorType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "talkative" => Ok(GroupHonorType::Talkative),
+            "performer" => Ok(GroupHonorType::Performer),
+            "legend" => Ok(GroupHonorType::Legend),
+            "strong_newbie" => Ok(GroupHonorType::StrongNewbie),
+            "emotion" => Ok(GroupHonorType::Emotion),
+            "all" => Ok(GroupHonorType::All),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GetCookiesResponse {
     pub cookies: String,
@@ -163,12 +284,26 @@ pub struct GetCredentialsResponse {
     pub csrf_token: i32,
 }
 
+/// A file stored in the cache of the onebot implementation.
+///
+/// Returned by `get_image`, `get_record` and `download_file`. The `file` path is local to the implementation,
+/// so it can be passed back as a [`MediaSource::Raw`], e.g. to `upload_private_file`.
+///
+/// [`MediaSource::Raw`]: crate::message::media::MediaSource::Raw
 #[derive(Deserialize, Debug, Clone)]
 pub struct GetFileResponse {
     pub file: String,
+    /// A download url, set by some implementations.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupFileUrl {
+    pub url: String,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum RecordFormat {
     Mp3,
@@ -194,3 +329,333 @@ pub struct VersionInfo {
     #[serde(flatten)]
     pub data: HashMap<String, String>,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Device {
+    pub app_id: i64,
+    pub device_name: String,
+    pub device_kind: String,
+}
+
+/// Response of `get_online_clients`.
+///
+/// Most implementations return `{"clients": [...]}`, while some return the array directly as `data`.
+/// Both shapes are accepted.
+#[derive(Debug, Clone)]
+pub struct OnlineClientsResponse {
+    pub clients: Vec<Device>,
+}
+
+impl<'de> Deserialize<'de> for OnlineClientsResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Wrapped {
+                #[serde(default)]
+                clients: Option<Vec<Device>>,
+            },
+            Bare(Vec<Device>),
+        }
+
+        let clients = match Repr::deserialize(deserializer)? {
+            Repr::Wrapped { clients } => clients.unwrap_or_default(),
+            Repr::Bare(clients) => clients,
+        };
+        Ok(Self { clients })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ModelShowVariant {
+    pub model_show: String,
+    pub need_pay: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ModelShowResponse {
+    pub variants: Vec<ModelShowVariant>,
+}
+
+/// The `auto_escape` used when send calls pass `None`, registered with [`with_auto_escape`].
+///
+/// [`with_auto_escape`]: crate::FlowBotBuilder::with_auto_escape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoEscape(pub bool);
+
+/// The `duration` of mutes and special titles, always sent in seconds.
+///
+/// Converts from a [`Duration`](std::time::Duration), rounded up to whole seconds, from seconds as `i64`,
+/// and from `Option<i64>` seconds where `None` leaves the duration to the implementation.
+///
+/// ```no_run
+/// # use flow_bot::{api::{BanDuration, api_ext::ApiExt}, base::context::BotContext};
+/// # async fn ban(ctx: BotContext) -> Result<(), flow_bot::error::FlowError> {
+/// // Mute user 456 in group 123 for 10 minutes, then lift it.
+/// ctx.set_group_ban(123, 456, BanDuration::minutes(10)).await?;
+/// ctx.set_group_ban(123, 456, BanDuration::lift()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct BanDuration(Option<u64>);
+
+impl BanDuration {
+    pub const fn seconds(seconds: u64) -> Self {
+        Self(Some(seconds))
+    }
+
+    pub const fn minutes(minutes: u64) -> Self {
+        Self::seconds(minutes * 60)
+    }
+
+    pub const fn hours(hours: u64) -> Self {
+        Self::seconds(hours * 60 * 60)
+    }
+
+    pub const fn days(days: u64) -> Self {
+        Self::seconds(days * 24 * 60 * 60)
+    }
+
+    /// A duration of zero, which lifts a mute.
+    pub const fn lift() -> Self {
+        Self::seconds(0)
+    }
+
+    /// The duration in seconds, `None` if left to the implementation.
+    pub fn as_secs(&self) -> Option<u64> {
+        self.0
+    }
+}
+
+/// Rounded up to whole seconds, and at least one second: only [`lift`](BanDuration::lift) lifts a mute.
+impl From<std::time::Duration> for BanDuration {
+    fn from(duration: std::time::Duration) -> Self {
+        let seconds = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+        Self::seconds(seconds.max(1))
+    }
+}
+
+/// Seconds, negative ones are taken as zero.
+impl From<i64> for BanDuration {
+    fn from(seconds: i64) -> Self {
+        Self::seconds(seconds.max(0) as u64)
+    }
+}
+
+impl From<Option<i64>> for BanDuration {
+    fn from(seconds: Option<i64>) -> Self {
+        seconds.map_or(Self(None), Self::from)
+    }
+}
+
+/// Extra request headers for `download_file`.
+///
+/// Serialized as a list of `Key=Value` strings, the format go-cqhttp expects.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadHeaders(pub Vec<String>);
+
+impl Serialize for DownloadHeaders {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl From<Vec<String>> for DownloadHeaders {
+    fn from(headers: Vec<String>) -> Self {
+        Self(headers)
+    }
+}
+
+impl From<HashMap<String, String>> for DownloadHeaders {
+    fn from(headers: HashMap<String, String>) -> Self {
+        let mut headers = headers
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>();
+        headers.sort();
+        Self(headers)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupAtAllRemain {
+    pub can_at_all: bool,
+    pub remain_at_all_count_for_group: i32,
+    pub remain_at_all_count_for_uin: i32,
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+pub(crate) fn string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        String(String),
+        Number(i64),
+    }
+
+    Ok(match Repr::deserialize(deserializer)? {
+        Repr::String(s) => s,
+        Repr::Number(n) => n.to_string(),
+    })
+}
+
+pub(crate) fn number_or_string<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    string_or_number(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+/// An invitation of the bot into a group.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "RawInvitedRequest")]
+pub struct InvitedRequest {
+    /// Usable as the `flag` of `set_group_add_request` with [`GroupRequestSubType::Invite`].
+    ///
+    /// [`GroupRequestSubType::Invite`]: crate::event::request::GroupRequestSubType::Invite
+    pub request_id: String,
+    pub invitor_uin: Option<i64>,
+    pub invitor_nick: Option<String>,
+    pub group_id: i64,
+    pub group_name: Option<String>,
+    /// Whether the request was handled, `false` if the implementation does not send it.
+    pub checked: bool,
+    /// The user who handled the request.
+    pub actor: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct RawInvitedRequest {
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    request_id: Option<String>,
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    flag: Option<String>,
+    #[serde(default)]
+    invitor_uin: Option<i64>,
+    #[serde(default)]
+    invitor_nick: Option<String>,
+    group_id: i64,
+    #[serde(default)]
+    group_name: Option<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    checked: bool,
+    #[serde(default)]
+    actor: Option<i64>,
+}
+
+impl TryFrom<RawInvitedRequest> for InvitedRequest {
+    type Error = &'static str;
+
+    fn try_from(raw: RawInvitedRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            request_id: request_flag(raw.request_id, raw.flag)?,
+            invitor_uin: raw.invitor_uin,
+            invitor_nick: raw.invitor_nick,
+            group_id: raw.group_id,
+            group_name: raw.group_name,
+            checked: raw.checked,
+            actor: raw.actor,
+        })
+    }
+}
+
+/// A request of a user to join a group managed by the bot.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "RawJoinRequest")]
+pub struct JoinRequest {
+    /// Usable as the `flag` of `set_group_add_request` with [`GroupRequestSubType::Add`].
+    ///
+    /// [`GroupRequestSubType::Add`]: crate::event::request::GroupRequestSubType::Add
+    pub request_id: String,
+    pub requester_uin: i64,
+    pub requester_nick: Option<String>,
+    pub message: Option<String>,
+    pub group_id: i64,
+    pub group_name: Option<String>,
+    /// Whether the request was handled, `false` if the implementation does not send it.
+    pub checked: bool,
+    /// The user who handled the request.
+    pub actor: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct RawJoinRequest {
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    request_id: Option<String>,
+    #[serde(default, deserialize_with = "optional_string_or_number")]
+    flag: Option<String>,
+    requester_uin: i64,
+    #[serde(default)]
+    requester_nick: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    group_id: i64,
+    #[serde(default)]
+    group_name: Option<String>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    checked: bool,
+    #[serde(default)]
+    actor: Option<i64>,
+}
+
+impl TryFrom<RawJoinRequest> for JoinRequest {
+    type Error = &'static str;
+
+    fn try_from(raw: RawJoinRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            request_id: request_flag(raw.request_id, raw.flag)?,
+            requester_uin: raw.requester_uin,
+            requester_nick: raw.requester_nick,
+            message: raw.message,
+            group_id: raw.group_id,
+            group_name: raw.group_name,
+            checked: raw.checked,
+            actor: raw.actor,
+        })
+    }
+}
+
+/// The id of a group system message, sent as `request_id`, `flag` or both. `request_id` wins when both are sent.
+fn request_flag(request_id: Option<String>, flag: Option<String>) -> Result<String, &'static str> {
+    request_id
+        .or(flag)
+        .ok_or("missing field `request_id` or `flag`")
+}
+
+fn optional_string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "string_or_number")] String);
+
+    Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct GroupSystemMessages {
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub invited_requests: Vec<InvitedRequest>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub join_requests: Vec<JoinRequest>,
+}
@@ -0,0 +1,301 @@
+//! Typed params of every API call, sent with [`Context::call`].
+//!
+//! Each struct serializes to the params of its [`ApiParams::ACTION`], optional params are left out while unset:
+//!
+//! ```
+//! use flow_bot::api::{BanDuration, params::SetGroupBan};
+//!
+//! let params = SetGroupBan {
+//!     group_id: 123,
+//!     user_id: 456,
+//!     duration: BanDuration::minutes(10),
+//! };
+//! assert_eq!(
+//!     serde_json::to_string(&params).unwrap(),
+//!     r#"{"group_id":123,"user_id":456,"duration":600}"#
+//! );
+//! ```
+//!
+//! [`Context::call`]: crate::base::context::Context::call
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    event::{message::GroupAnonymousInfo, request::GroupRequestSubType},
+    message::{Message, media::MediaSource, segments::ReplyRef},
+};
+
+use super::{
+    BanDuration, BotStatus, CanSendResponse, DownloadHeaders, FriendInfo, GetCookiesResponse,
+    GetCredentialsResponse, GetCsrfTokenResponse, GetFileResponse, GetForwardResponse,
+    GetMessageResponse, GroupAtAllRemain, GroupFileUrl, GroupHonorInfo, GroupHonorType,
+    GroupInfoResponse, GroupMemberInfo, GroupSystemMessages, LoginInfo, ModelShowResponse,
+    OnlineClientsResponse, RecordFormat, SendMessageResponse, StrangerInfo, VersionInfo,
+};
+
+/// The params of an action, with the type of the `data` of its response.
+pub trait ApiParams: Serialize + Send {
+    const ACTION: &'static str;
+    type Response: DeserializeOwned + Send;
+}
+
+/// A message param, sent as segments or as a string of CQ codes.
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum MessageParam {
+    Segments(Message),
+    Raw(String),
+}
+
+impl From<Message> for MessageParam {
+    fn from(message: Message) -> Self {
+        Self::Segments(message)
+    }
+}
+
+impl From<String> for MessageParam {
+    fn from(message: String) -> Self {
+        Self::Raw(message)
+    }
+}
+
+/// Params that are left out while unset, as some implementations reject a null with retcode 1400.
+trait Omit {
+    fn omit(&self) -> bool;
+}
+
+impl<T> Omit for Option<T> {
+    fn omit(&self) -> bool {
+        self.is_none()
+    }
+}
+
+impl Omit for BanDuration {
+    fn omit(&self) -> bool {
+        self.as_secs().is_none()
+    }
+}
+
+/// An action with the names of its params, from [`CORE_ACTIONS`] and [`EXTENDED_ACTIONS`].
+///
+/// ```
+/// use flow_bot::api::params::action_spec;
+///
+/// let spec = action_spec("set_group_ban").unwrap();
+/// assert_eq!(spec.params, "SetGroupBan");
+/// assert_eq!(spec.required, ["group_id", "user_id"]);
+/// assert_eq!(spec.optional, ["duration"]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionSpec {
+    pub action: &'static str,
+    /// The name of the params struct, e.g. `SetGroupBan`.
+    pub params: &'static str,
+    pub required: &'static [&'static str],
+    /// Params that are left out while unset.
+    pub optional: &'static [&'static str],
+    /// Whether the action is one of [`ExtendedApi`](super::api_ext::ExtendedApi) rather than of the OneBot 11 standard.
+    pub extended: bool,
+}
+
+/// The spec of `action`, `None` if no params struct is declared for it.
+pub fn action_spec(action: &str) -> Option<&'static ActionSpec> {
+    CORE_ACTIONS
+        .iter()
+        .chain(EXTENDED_ACTIONS)
+        .find(|spec| spec.action == action)
+}
+
+/// The name a field is sent as, renamed with `as "name"`.
+macro_rules! wire_name {
+    ($field:ident) => {
+        stringify!($field)
+    };
+    ($field:ident $wire:literal) => {
+        $wire
+    };
+}
+
+/// Declares the params struct of actions: `Name => "action" -> Response { required; optional }`,
+/// and the list `$specs` of their [`ActionSpec`]s. Optional params must implement [`Omit`].
+macro_rules! api_params {
+    ($(#[$specs_meta:meta])* $specs:ident, extended: $extended:literal; $(
+        $name:ident => $action:literal -> $response:ty {
+            $($(#[$meta:meta])* $field:ident $(as $wire:literal)? : $ty:ty),* $(,)?
+            $(; $($(#[$opt_meta:meta])* $opt:ident : $opt_ty:ty),* $(,)?)?
+        }
+    )*) => {
+        $(
+            #[doc = concat!("Params of `", $action, "`.")]
+            #[derive(Serialize, Debug, Clone)]
+            pub struct $name {
+                $($(#[$meta])* $(#[serde(rename = $wire)])? pub $field: $ty,)*
+                $($(
+                    $(#[$opt_meta])*
+                    #[serde(skip_serializing_if = "Omit::omit")]
+                    pub $opt: $opt_ty,
+                )*)?
+            }
+
+            impl ApiParams for $name {
+                const ACTION: &'static str = $action;
+                type Response = $response;
+            }
+        )*
+
+        $(#[$specs_meta])*
+        pub const $specs: &[ActionSpec] = &[$(
+            ActionSpec {
+                action: $action,
+                params: stringify!($name),
+                required: &[$(wire_name!($field $($wire)?)),*],
+                optional: &[$($(stringify!($opt)),*)?],
+                extended: $extended,
+            },
+        )*];
+    };
+}
+
+api_params! {
+    /// Actions of the OneBot 11 standard.
+    CORE_ACTIONS, extended: false;
+
+    SendPrivateMsg => "send_private_msg" -> SendMessageResponse {
+        user_id: i64,
+        message: MessageParam;
+        /// The group of a temp session.
+        group_id: Option<i64>,
+        auto_escape: Option<bool>,
+    }
+    SendGroupMsg => "send_group_msg" -> SendMessageResponse {
+        group_id: i64,
+        message: MessageParam;
+        auto_escape: Option<bool>,
+    }
+    SendMsg => "send_msg" -> SendMessageResponse {
+        message: MessageParam;
+        /// `private` or `group`, guessed from the ids if unset.
+        message_type: Option<String>,
+        user_id: Option<i64>,
+        group_id: Option<i64>,
+        auto_escape: Option<bool>,
+    }
+    DeleteMessage => "delete_msg" -> () { message_id: i64 }
+    GetMsg => "get_msg" -> GetMessageResponse { message_id: ReplyRef }
+    GetForwardMsg => "get_forward_msg" -> GetForwardResponse { message_id: ReplyRef }
+    SendLike => "send_like" -> () { user_id: i64; times: Option<i32> }
+    SetGroupKick => "set_group_kick" -> () {
+        group_id: i64,
+        user_id: i64;
+        reject_add_request: Option<bool>,
+    }
+    SetGroupBan => "set_group_ban" -> () {
+        group_id: i64,
+        user_id: i64;
+        duration: BanDuration,
+    }
+    SetGroupAnonymousBan => "set_group_anonymous_ban" -> () {
+        group_id: i64;
+        anonymous: Option<GroupAnonymousInfo>,
+        flag: Option<String>,
+        duration: BanDuration,
+    }
+    SetWholeGroupBan => "set_group_whole_ban" -> () { group_id: i64; enable: Option<bool> }
+    SetGroupAdmin => "set_group_admin" -> () {
+        group_id: i64,
+        user_id: i64;
+        enable: Option<bool>,
+    }
+    SetGroupAnonymous => "set_group_anonymous" -> () { group_id: i64; enable: Option<bool> }
+    SetGroupCard => "set_group_card" -> () {
+        group_id: i64,
+        user_id: i64;
+        card: Option<String>,
+    }
+    SetGroupName => "set_group_name" -> () { group_id: i64, group_name: String }
+    SetGroupLeave => "set_group_leave" -> () { group_id: i64; is_dismiss: Option<bool> }
+    SetGroupSpecialTitle => "set_group_special_title" -> () {
+        group_id: i64,
+        user_id: i64;
+        special_title: Option<String>,
+        duration: BanDuration,
+    }
+    SetFriendAddRequest => "set_friend_add_request" -> () {
+        flag: String;
+        approve: Option<bool>,
+        remark: Option<String>,
+    }
+    SetGroupAddRequest => "set_group_add_request" -> () {
+        flag: String,
+        sub_type: GroupRequestSubType;
+        approve: Option<bool>,
+        reason: Option<String>,
+    }
+    GetLoginInfo => "get_login_info" -> LoginInfo {}
+    GetStrangerInfo => "get_stranger_info" -> StrangerInfo { user_id: i64; no_cache: Option<bool> }
+    GetFriendList => "get_friend_list" -> Vec<FriendInfo> {}
+    GetGroupInfo => "get_group_info" -> GroupInfoResponse { group_id: i64; no_cache: Option<bool> }
+    GetGroupList => "get_group_list" -> Vec<GroupInfoResponse> {}
+    GetGroupMemberInfo => "get_group_member_info" -> GroupMemberInfo {
+        group_id: i64,
+        user_id: i64;
+        no_cache: Option<bool>,
+    }
+    GetGroupMemberList => "get_group_member_list" -> Vec<FriendInfo> { group_id: i64 }
+    GetGroupHonorInfo => "get_group_honor_info" -> GroupHonorInfo {
+        group_id: i64,
+        ty as "type": GroupHonorType,
+    }
+    GetCookies => "get_cookies" -> GetCookiesResponse { ; domain: Option<String> }
+    GetCsrfToken => "get_csrf_token" -> GetCsrfTokenResponse {}
+    GetCredentials => "get_credentials" -> GetCredentialsResponse { ; domain: Option<String> }
+    GetRecord => "get_record" -> GetFileResponse { file: String, out_format: RecordFormat }
+    GetImage => "get_image" -> GetFileResponse { file: String }
+    CanSendImage => "can_send_image" -> CanSendResponse {}
+    CanSendRecord => "can_send_record" -> CanSendResponse {}
+    GetStatus => "get_status" -> BotStatus {}
+    GetVersionInfo => "get_version_info" -> VersionInfo {}
+    SetRestart => "set_restart" -> () { ; delay: Option<i32> }
+    CleanCache => "clean_cache" -> () {}
+}
+
+api_params! {
+    /// Actions of [`ExtendedApi`](super::api_ext::ExtendedApi), which go-cqhttp compatible implementations support.
+    EXTENDED_ACTIONS, extended: true;
+
+    GetOnlineClients => "get_online_clients" -> OnlineClientsResponse { ; no_cache: Option<bool> }
+    GetModelShow => "_get_model_show" -> ModelShowResponse { model: String }
+    SetModelShow => "_set_model_show" -> () { model: String, model_show: String }
+    DownloadFile => "download_file" -> GetFileResponse {
+        url: String;
+        thread_count: Option<i32>,
+        headers: Option<DownloadHeaders>,
+    }
+    UploadPrivateFile => "upload_private_file" -> () {
+        user_id: i64,
+        file: MediaSource,
+        name: String,
+    }
+    GetGroupFileUrl => "get_group_file_url" -> GroupFileUrl {
+        group_id: i64,
+        file_id: String,
+        busid: i64,
+    }
+    UploadGroupFile => "upload_group_file" -> () {
+        group_id: i64,
+        file: MediaSource,
+        name: String;
+        folder: Option<String>,
+    }
+    GetGroupAtAllRemain => "get_group_at_all_remain" -> GroupAtAllRemain { group_id: i64 }
+    SendGroupSign => "send_group_sign" -> () { group_id: i64 }
+    MarkMsgAsRead => "mark_msg_as_read" -> () { message_id: i64 }
+    SetMsgEmojiLike => "set_msg_emoji_like" -> () {
+        message_id: i64,
+        emoji_id: String;
+        set: Option<bool>,
+    }
+    GetGroupSystemMsg => "get_group_system_msg" -> GroupSystemMessages {}
+    GroupPoke => "group_poke" -> () { group_id: i64, user_id: i64 }
+    FriendPoke => "friend_poke" -> () { user_id: i64 }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use serde_json::Value;
+
+use super::VersionInfo;
+
+/// How a [`QuirkProfile`] sends id params, that is params whose name ends in `_id`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdFormat {
+    /// Send ids as the API methods serialize them.
+    #[default]
+    AsIs,
+    /// Send ids given as numeric strings as numbers.
+    Number,
+    /// Send numeric ids as strings.
+    String,
+}
+
+/// Request rewrites for one onebot implementation, recognized by the `app_name` of its version info.
+///
+/// The framework queries the version after connecting and uses the first profile matching it,
+/// custom profiles from [`with_quirk_profile`] before the built-in ones. Calls made before that
+/// are sent as they are. The built-in profiles of go-cqhttp, NapCat, Lagrange and LLOneBot do not
+/// rewrite anything, as the actions of [`ApiExt`] are named the same by all of them.
+///
+/// ```
+/// use flow_bot::api::quirks::{IdFormat, QuirkProfile};
+///
+/// let profile = QuirkProfile::new("my-fork")
+///     .matching("myfork")
+///     .rename_action("send_group_sign", "set_group_sign")
+///     .ids(IdFormat::String);
+/// assert_eq!(profile.action("send_group_sign"), "set_group_sign");
+/// ```
+///
+/// [`with_quirk_profile`]: crate::FlowBotBuilder::with_quirk_profile
+/// [`ApiExt`]: super::api_ext::ApiExt
+#[derive(Debug, Clone)]
+pub struct QuirkProfile {
+    name: String,
+    app_names: Vec<String>,
+    actions: HashMap<String, String>,
+    ids: IdFormat,
+}
+
+impl QuirkProfile {
+    /// A profile that matches no implementation and rewrites nothing.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            app_names: Vec::new(),
+            actions: HashMap::new(),
+            ids: IdFormat::AsIs,
+        }
+    }
+
+    /// Use the profile for implementations whose `app_name` contains `app_name`, ignoring case.
+    pub fn matching(mut self, app_name: impl Into<String>) -> Self {
+        self.app_names.push(app_name.into().to_lowercase());
+        self
+    }
+
+    /// Send calls of `action` as `alias`.
+    pub fn rename_action(mut self, action: impl Into<String>, alias: impl Into<String>) -> Self {
+        self.actions.insert(action.into(), alias.into());
+        self
+    }
+
+    pub fn ids(mut self, ids: IdFormat) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn matches(&self, version: &VersionInfo) -> bool {
+        let app_name = version.app_name.to_lowercase();
+        self.app_names.iter().any(|name| app_name.contains(name))
+    }
+
+    /// The name `action` is sent as.
+    pub fn action<'a>(&'a self, action: &'a str) -> &'a str {
+        self.actions.get(action).map_or(action, String::as_str)
+    }
+
+    pub(crate) fn rewrite_params(&self, params: &mut Value) {
+        let Value::Object(params) = params else {
+            return;
+        };
+        for (_, value) in params.iter_mut().filter(|(key, _)| key.ends_with("_id")) {
+            match (self.ids, &value) {
+                (IdFormat::Number, Value::String(id)) => {
+                    if let Ok(id) = id.parse::<i64>() {
+                        *value = Value::from(id);
+                    }
+                }
+                (IdFormat::String, Value::Number(id)) => *value = Value::String(id.to_string()),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn builtin_profiles() -> Vec<QuirkProfile> {
+    vec![
+        QuirkProfile::new("go-cqhttp").matching("go-cqhttp"),
+        QuirkProfile::new("NapCat").matching("napcat"),
+        QuirkProfile::new("Lagrange").matching("lagrange"),
+        QuirkProfile::new("LLOneBot")
+            .matching("llonebot")
+            .matching("llbot"),
+    ]
+}
+
+/// The registered profiles and the one in use.
+pub(crate) struct Quirks {
+    profiles: Vec<Arc<QuirkProfile>>,
+    active: RwLock<Option<Arc<QuirkProfile>>>,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Quirks {
+    pub(crate) fn new(custom: Vec<QuirkProfile>) -> Self {
+        Self {
+            profiles: custom
+                .into_iter()
+                .chain(builtin_profiles())
+                .map(Arc::new)
+                .collect(),
+            active: RwLock::new(None),
+        }
+    }
+
+    pub(crate) fn active(&self) -> Option<Arc<QuirkProfile>> {
+        self.active.read().unwrap().clone()
+    }
+
+    /// Use the first profile matching `version`, or none.
+    pub(crate) fn select(&self, version: &VersionInfo) {
+        let profile = self
+            .profiles
+            .iter()
+            .find(|profile| profile.matches(version))
+            .cloned();
+        match &profile {
+            Some(profile) => tracing::info!(
+                "Using the {} quirk profile for {} {}",
+                profile.name,
+                version.app_name,
+                version.app_version
+            ),
+            None => tracing::debug!("No quirk profile for {}", version.app_name),
+        }
+        *self.active.write().unwrap() = profile;
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+/// The `retcode` of an API response, as documented by OneBot 11.
+///
+/// Codes outside the standard are mapped per implementation, see [`IMPLEMENTATION_RETCODES`],
+/// and are otherwise kept as [`RetCode::Other`].
+///
+/// ```
+/// use flow_bot::api::retcode::RetCode;
+///
+/// assert_eq!(RetCode::from(1404), RetCode::Unsupported);
+/// assert!(RetCode::from(1).is_success());
+/// assert_eq!(RetCode::for_implementation(200, "NapCat.Onebot"), RetCode::Failed(200));
+/// assert_eq!(RetCode::from(200), RetCode::Other(200));
+/// assert_eq!(i32::from(RetCode::BadRequest), 1400);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetCode {
+    /// 0, the call succeeded.
+    Ok,
+    /// 1, the call was accepted and runs in the background.
+    Async,
+    /// 1400, the params are invalid.
+    BadRequest,
+    /// 1401, the access token is missing.
+    Unauthorized,
+    /// 1403, the access token is wrong.
+    Forbidden,
+    /// 1404, the action is not supported by the implementation.
+    Unsupported,
+    /// The implementation failed to carry out the call, with its own code, e.g. go-cqhttp's 100 or NapCat's 200.
+    Failed(i32),
+    Other(i32),
+}
+
+/// Retcodes outside the standard, by implementation.
+///
+/// Implementations are matched against the lowercased `app_name` of their version info.
+pub const IMPLEMENTATION_RETCODES: &[(&str, i32, RetCode)] = &[
+    ("go-cqhttp", 100, RetCode::Failed(100)),
+    ("napcat", 200, RetCode::Failed(200)),
+    ("llonebot", 200, RetCode::Failed(200)),
+    ("llbot", 200, RetCode::Failed(200)),
+];
+
+impl RetCode {
+    /// Map `code` as sent by the implementation named `app_name`.
+    pub fn for_implementation(code: i32, app_name: &str) -> Self {
+        let app_name = app_name.to_lowercase();
+        IMPLEMENTATION_RETCODES
+            .iter()
+            .find(|(name, known, _)| *known == code && app_name.contains(name))
+            .map_or_else(|| Self::from(code), |(_, _, retcode)| *retcode)
+    }
+
+    pub fn code(self) -> i32 {
+        match self {
+            RetCode::Ok => 0,
+            RetCode::Async => 1,
+            RetCode::BadRequest => 1400,
+            RetCode::Unauthorized => 1401,
+            RetCode::Forbidden => 1403,
+            RetCode::Unsupported => 1404,
+            RetCode::Failed(code) | RetCode::Other(code) => code,
+        }
+    }
+
+    /// Whether the call was carried out or accepted.
+    pub fn is_success(self) -> bool {
+        matches!(self, RetCode::Ok | RetCode::Async)
+    }
+
+    /// Whether the implementation does not support the action.
+    pub fn is_unsupported(self) -> bool {
+        self == RetCode::Unsupported
+    }
+
+    /// Whether the call may succeed when sent again unchanged.
+    ///
+    /// Failures caused by the call itself or the credentials are not, unknown codes are.
+    pub fn is_retryable(self) -> bool {
+        !self.is_success()
+            && !self.is_auth_failure()
+            && !matches!(self, RetCode::BadRequest | RetCode::Unsupported)
+    }
+
+    /// Whether the implementation refused the access token of the bot.
+    pub fn is_auth_failure(self) -> bool {
+        matches!(self, RetCode::Unauthorized | RetCode::Forbidden)
+    }
+}
+
+impl From<i32> for RetCode {
+    fn from(code: i32) -> Self {
+        match code {
+            0 => RetCode::Ok,
+            1 => RetCode::Async,
+            1400 => RetCode::BadRequest,
+            1401 => RetCode::Unauthorized,
+            1403 => RetCode::Forbidden,
+            1404 => RetCode::Unsupported,
+            code => RetCode::Other(code),
+        }
+    }
+}
+
+impl From<RetCode> for i32 {
+    fn from(retcode: RetCode) -> Self {
+        retcode.code()
+    }
+}
+
+impl fmt::Display for RetCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RetCode::Ok => "ok",
+            RetCode::Async => "async",
+            RetCode::BadRequest => "bad request",
+            RetCode::Unauthorized => "unauthorized",
+            RetCode::Forbidden => "forbidden",
+            RetCode::Unsupported => "unsupported",
+            RetCode::Failed(_) => "failed",
+            RetCode::Other(code) => return write!(f, "{}", code),
+        };
+        write!(f, "{} ({})", self.code(), name)
+    }
+}
@@ -0,0 +1,68 @@
+use futures::{StreamExt, future::BoxFuture, stream};
+
+use crate::error::FlowError;
+
+use super::context::Context;
+
+/// API calls queued to run together, created with [`Context::batch`].
+///
+/// Calls only start once the batch is executed, each as a request of its own,
+/// and their results are returned in the order the calls were added.
+///
+/// ```ignore
+/// let results = ctx
+///     .batch()
+///     .call(|ctx| ctx.set_group_ban(group_id, 1001, BanDuration::minutes(10)))
+///     .call(|ctx| ctx.set_group_ban(group_id, 1002, BanDuration::minutes(10)))
+///     .execute_concurrent(4)
+///     .await;
+/// ```
+pub struct Batch<'c, T> {
+    context: &'c Context,
+    calls: Vec<BoxFuture<'c, Result<T, FlowError>>>,
+}
+
+impl<'c, T> Batch<'c, T>
+where
+    T: Send + 'c,
+{
+    pub(crate) fn new(context: &'c Context) -> Self {
+        Self {
+            context,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Queue a call made with the context. Calls of different result types can be batched
+    /// by mapping their results, e.g. with [`TryFutureExt::map_ok`](futures::TryFutureExt::map_ok).
+    pub fn call<F, Fut>(mut self, call: F) -> Self
+    where
+        F: FnOnce(&'c Context) -> Fut,
+        Fut: Future<Output = Result<T, FlowError>> + Send + 'c,
+    {
+        self.calls.push(Box::pin(call(self.context)));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Run the calls with at most `limit` of them waiting for a response at a time.
+    pub async fn execute_concurrent(self, limit: usize) -> Vec<Result<T, FlowError>> {
+        stream::iter(self.calls)
+            .buffered(limit.max(1))
+            .collect()
+            .await
+    }
+
+    /// Run all calls at once.
+    pub async fn execute(self) -> Vec<Result<T, FlowError>> {
+        let limit = self.calls.len();
+        self.execute_concurrent(limit).await
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use crate::event::BotEvent;
+
+use super::{
+    context::BotContext,
+    explain::is_dry_run,
+    extract::{FromEvent, GroupId},
+};
+
+/// A source of random numbers for [`RngState`]. Implemented for closures returning `u64`.
+pub trait RandomSource: Send {
+    fn next_u64(&mut self) -> u64;
+}
+
+impl<F> RandomSource for F
+where
+    F: FnMut() -> u64 + Send,
+{
+    fn next_u64(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// SplitMix64, good enough for deciding whether to react to a message.
+struct SplitMix64(u64);
+
+impl RandomSource for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// The random numbers used by [`Chance`].
+///
+/// Bots get one with a random seed, register one with [`with_state`] to replace it,
+/// e.g. `RngState::seeded(42)` to get the same sequence of decisions on every run.
+///
+/// [`with_state`]: crate::FlowBotBuilder::with_state
+pub struct RngState(Mutex<Box<dyn RandomSource>>);
+
+impl RngState {
+    pub fn new(source: impl RandomSource + 'static) -> Self {
+        Self(Mutex::new(Box::new(source)))
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self::new(SplitMix64(seed))
+    }
+
+    pub(crate) fn from_entropy() -> Self {
+        Self::seeded(uuid::Uuid::new_v4().as_u64_pair().0)
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        self.0.lock().unwrap().next_u64()
+    }
+
+    /// Whether an event with a chance of `permille` in a thousand happens.
+    pub fn chance(&self, permille: u32) -> bool {
+        self.next_u64() % 1000 < u64::from(permille)
+    }
+}
+
+/// Guard extractor succeeding with a chance of `PERMILLE` in a thousand per event,
+/// e.g. `Chance<10>` for a 1% chance. The random numbers come from the [`RngState`].
+///
+/// It is [ordered](super::extract::Seq), only drawing a number once the other arguments of the handler were extracted.
+/// In a [dry run](super::explain::is_dry_run) it always succeeds.
+///
+/// # Example
+/// ```ignore
+/// async fn react(ctx: BotContext, _: Chance<10>, msg: MessageBody) -> HandlerControl { ... }
+/// ```
+pub struct Chance<const PERMILLE: u32>;
+
+#[async_trait]
+impl<const PERMILLE: u32> FromEvent for Chance<PERMILLE> {
+    const ORDERED: bool = true;
+
+    async fn from_event(context: BotContext, _: BotEvent) -> Option<Self> {
+        let rng = context.state.get::<RngState>()?;
+        // A dry run does not draw, so that the sequence of a seeded generator stays the same.
+        if is_dry_run() {
+            return Some(Self);
+        }
+        rng.chance(PERMILLE).then_some(Self)
+    }
+}
+
+/// Counts of the events seen by [`Sampled`], by sampling rate and group.
+#[derive(Default)]
+pub(crate) struct SampleCounters(DashMap<(u32, Option<i64>), u64>);
+
+/// Guard extractor succeeding for every `N`th event, counted per group. Events outside of group messages share one count.
+///
+/// Counts are kept per `N`, so handlers using the same `N` count together. It is [ordered](super::extract::Seq),
+/// so only events matched by the other extractors of a handler are counted. A [dry run](super::explain::is_dry_run)
+/// tells whether the next event would be sampled, without counting it.
+pub struct Sampled<const N: u32>;
+
+#[async_trait]
+impl<const N: u32> FromEvent for Sampled<N> {
+    const ORDERED: bool = true;
+
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let counters = context.state.get::<SampleCounters>()?;
+        let group_id = GroupId::from_event(context, event)
+            .await
+            .map(|group| group.0);
+        let count = match is_dry_run() {
+            true => counters.0.get(&(N, group_id)).map_or(0, |count| *count) + 1,
+            false => {
+                let mut count = counters.0.entry((N, group_id)).or_default();
+                *count += 1;
+                *count
+            }
+        };
+        (N > 0 && count % u64::from(N) == 0).then_some(Self)
+    }
+}
@@ -1,4 +1,8 @@
 /// Reconnection strategy configuration
+///
+/// Connections refused for their credentials are not retried, see [`FlowError::is_auth_failure`].
+///
+/// [`FlowError::is_auth_failure`]: crate::error::FlowError::is_auth_failure
 #[derive(Clone, Debug)]
 pub enum ReconnectionStrategy {
     /// Reconnect endlessly with exponential backoff
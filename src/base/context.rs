@@ -1,89 +1,386 @@
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
-    sync::Arc,
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
 };
 
 use async_trait::async_trait;
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use futures::{SinkExt, stream::SplitSink};
 use serde_json::json;
 use tokio::{
     net::TcpStream,
-    sync::{Mutex, oneshot},
+    sync::{Mutex, oneshot, watch},
+};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream,
+    tungstenite::{Message, Utf8Bytes},
 };
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
 
 use crate::{
-    api::{ApiResponse, api_ext::ApiExt},
+    api::{
+        ApiResponse, GetForwardResponse, GetMessageResponse, SendMessageResponse,
+        api_ext::ApiExt,
+        capabilities::Capabilities,
+        digest::ParamRedaction,
+        quirks::{QuirkProfile, Quirks},
+        retcode::RetCode,
+    },
+    base::chance::{RngState, SampleCounters},
     error::FlowError,
-    event::BotEvent,
+    event::{
+        BotEvent,
+        message::{self, ReplyStyle, TypedMessageInfo},
+    },
+    message::{
+        IntoMessage,
+        segments::{ReplyRef, Segment},
+    },
+};
+
+use super::{
+    batch::Batch,
+    dead_letter::{DeadLetter, FailedCall},
+    debug::{DebugSnapshot, DebugState, PendingRequestInfo, StateInfo},
+    extract::FromEvent,
+    health::{Health, HealthTracker},
+    identity::BotIdentity,
+    json,
+    mute::MuteAwareness,
+    outbox::{Outbox, OutboxConfig},
+    outgoing::OutgoingHooks,
+    policy::ActionPolicy,
 };
 
-use super::extract::FromEvent;
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
+/// Number of resolved echoes remembered to detect duplicate responses.
+const RESOLVED_ECHOES: usize = 64;
+
+/// Echoes are `<prefix>:<counter>`, the prefix is random and changes with every connection.
+fn new_echo_prefix() -> Arc<str> {
+    let mut prefix = uuid::Uuid::new_v4().simple().to_string();
+    prefix.truncate(12);
+    prefix.into()
+}
 
 pub struct Context {
-    pub(crate) sink: Mutex<Option<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>>,
-    pending_requests: Arc<DashMap<String, oneshot::Sender<String>>>,
+    pub(crate) sink: Mutex<Option<WsSink>>,
+    /// Requests waiting for their response, with the time they were sent.
+    pending_requests: Arc<DashMap<String, (Instant, oneshot::Sender<Utf8Bytes>)>>,
+    echo_prefix: std::sync::RwLock<Arc<str>>,
+    next_echo: AtomicU64,
+    resolved_echoes: std::sync::Mutex<VecDeque<String>>,
     pub(crate) state: StateMap,
+    pub(crate) health: HealthTracker,
+    outbox: Option<Outbox>,
+    capabilities: Capabilities,
+    pub(crate) quirks: Quirks,
+    /// Locked while collecting, so that concurrent callers wait for the same collection.
+    identity: Mutex<Option<Arc<BotIdentity>>>,
+    generation: AtomicU64,
+    /// The generation of the current connection, `None` while disconnected.
+    connection: watch::Sender<Option<u64>>,
+    ignored_groups: DashSet<i64>,
+    stopping: watch::Sender<bool>,
+    #[cfg(feature = "handler-stats")]
+    pub(crate) handler_stats: Vec<super::handler_stats::HandlerStatsCell>,
+    #[cfg(feature = "metrics")]
+    metrics: crate::extensions::metrics::Metrics,
 }
 
 impl Context {
-    pub(crate) fn new(mut states: StateMap) -> Self {
+    pub(crate) fn new(mut states: StateMap, outbox: Option<OutboxConfig>) -> Self {
+        if !states.contains::<RngState>() {
+            states.insert(RngState::from_entropy());
+        }
+        states.insert(SampleCounters::default());
+
         #[cfg(feature = "turso")]
         {
             use crate::extensions::turso::TursoDispatcher;
             states.insert(TursoDispatcher::new());
         }
 
+        #[cfg(feature = "redis")]
+        if let Some(config) = states.get::<crate::extensions::redis::RedisConfig>() {
+            use crate::extensions::redis::RedisDispatcher;
+            states.insert(RedisDispatcher::new((*config).clone()));
+        }
+
+        #[cfg(feature = "sqlx-sqlite")]
+        if let Some(config) = states.get::<crate::extensions::sqlite::SqliteConfig>() {
+            use crate::extensions::sqlite::SqliteDispatcher;
+            states.insert(SqliteDispatcher::new((*config).clone()));
+        }
+
         Self {
             sink: Mutex::new(None),
             pending_requests: Arc::new(DashMap::new()),
+            echo_prefix: std::sync::RwLock::new(new_echo_prefix()),
+            next_echo: AtomicU64::new(0),
+            resolved_echoes: std::sync::Mutex::new(VecDeque::new()),
             state: states,
+            health: HealthTracker::new(),
+            outbox: outbox.map(Outbox::new),
+            capabilities: Capabilities::default(),
+            quirks: Quirks::default(),
+            identity: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            connection: watch::Sender::new(None),
+            ignored_groups: DashSet::new(),
+            stopping: watch::Sender::new(false),
+            #[cfg(feature = "handler-stats")]
+            handler_stats: Vec::new(),
+            #[cfg(feature = "metrics")]
+            metrics: Default::default(),
         }
     }
 }
 
+/// The retcode and message of a response whose data could not be deserialized.
+#[derive(serde::Deserialize)]
+struct FailedResponse {
+    retcode: i32,
+    #[serde(default, alias = "msg")]
+    message: Option<String>,
+    #[serde(default)]
+    wording: Option<String>,
+}
+
+fn echo_prefix(echo: &str) -> Option<&str> {
+    echo.split_once(':').map(|(prefix, _)| prefix)
+}
+
 impl Context {
+    /// Send a request, failures are wrapped in [`FlowError::ApiCall`] with a digest of the params.
+    /// With `check_retcode`, responses with a failed retcode are failures too.
     pub(crate) async fn send_obj<T, R>(
         &self,
         action: String,
         obj: T,
+        check_retcode: bool,
     ) -> Result<ApiResponse<R>, FlowError>
     where
         T: serde::Serialize,
         R: for<'de> serde::Deserialize<'de>,
     {
-        // Generate random echo string
-        let echo = uuid::Uuid::new_v4().to_string();
+        let mut params = serde_json::to_value(obj)?;
+        let sent = async {
+            self.check_action_policy(&action)?;
+            if let Some(hooks) = self.state.get::<OutgoingHooks>() {
+                hooks.apply(&action, &mut params).await?;
+            }
+            if let Some(mute) = self.state.get::<MuteAwareness>() {
+                mute.check(&action, &params)?;
+            }
+            let response = self.send_obj_attempt::<R>(&action, &params, 1).await?;
+            let retcode = self.capabilities.retcode(response.retcode);
+            match check_retcode && !retcode.is_success() {
+                true => Err(FlowError::Api {
+                    retcode,
+                    message: response.message.or(response.wording),
+                }),
+                false => Ok(response),
+            }
+        }
+        .await;
+        sent.map_err(|e| self.api_call_error(&action, &params, e))
+    }
 
-        // Create oneshot channel for this specific request
-        let (tx, rx) = oneshot::channel();
+    /// Wrap the failure of a call of `action` in [`FlowError::ApiCall`].
+    pub(crate) fn api_call_error(
+        &self,
+        action: &str,
+        params: &serde_json::Value,
+        source: FlowError,
+    ) -> FlowError {
+        let params_digest = match self.state.get::<ParamRedaction>() {
+            Some(redaction) => redaction.digest(params),
+            None => ParamRedaction::default().digest(params),
+        };
+        FlowError::ApiCall {
+            action: action.to_string(),
+            source: Box::new(source),
+            params_digest,
+        }
+    }
 
-        // Register the request BEFORE sending (lock-free)
-        self.pending_requests.insert(echo.clone(), tx);
+    /// Fail with [`FlowError::ActionDenied`] if the [`ActionPolicy`] does not permit `action`.
+    pub(crate) fn check_action_policy(&self, action: &str) -> Result<(), FlowError> {
+        let Some(policy) = self.state.get::<ActionPolicy>() else {
+            return Ok(());
+        };
+        let checked = policy.check(action);
+        if checked.is_err() {
+            // Handlers run in a span carrying their name, which the warning is logged with.
+            tracing::warn!("Denied call of {} by the action policy", action);
+            #[cfg(feature = "metrics")]
+            self.metrics.record_denied_action(action);
+        }
+        checked
+    }
 
-        // Build and send the message
-        let msg = json!({
-            "action": action,
-            "params": obj,
-            "echo": echo,
-        });
-        let text = serde_json::to_string(&msg)?;
-        let msg = Message::Text(text.into());
+    /// Call `action` with `params` as they are, for actions without a typed method in [`ApiExt`].
+    ///
+    /// The response is returned whatever its `retcode`.
+    pub async fn call_action(
+        &self,
+        action: impl Into<String>,
+        params: serde_json::Value,
+    ) -> Result<ApiResponse<serde_json::Value>, FlowError> {
+        self.send_obj(action.into(), params, false).await
+    }
 
-        // Send message and release lock immediately
+    /// Send a request, passing it to the [`DeadLetter`] sink registered as a state if it fails.
+    /// `attempt` counts this try of the call, starting at 1.
+    pub(crate) async fn send_obj_attempt<R>(
+        &self,
+        action: &str,
+        params: &serde_json::Value,
+        attempt: u32,
+    ) -> Result<ApiResponse<R>, FlowError>
+    where
+        R: for<'de> serde::Deserialize<'de>,
+    {
+        #[cfg(feature = "metrics")]
+        let started = std::time::Instant::now();
+
+        let frame = self.send_request(action, params).await;
+
+        let failed = |retcode: i32| !self.capabilities.retcode(retcode).is_success();
+        let (result, failed_retcode) = match frame {
+            // Deserialized straight from the received frame, without an intermediate copy.
+            Ok(frame) => match json::from_frame::<ApiResponse<R>>(frame.as_bytes()) {
+                Ok(resp) => {
+                    let retcode = Some(resp.retcode).filter(|retcode| failed(*retcode));
+                    (Ok(resp), retcode)
+                }
+                // Failed calls usually have a null `data`, which fails to deserialize as `R`.
+                Err(e) => match json::from_frame::<FailedResponse>(frame.as_bytes()) {
+                    Ok(resp) if failed(resp.retcode) => {
+                        let error = FlowError::Api {
+                            retcode: self.capabilities.retcode(resp.retcode),
+                            message: resp.message.or(resp.wording),
+                        };
+                        (Err(error), Some(resp.retcode))
+                    }
+                    _ => (Err(e.into()), None),
+                },
+            },
+            Err(e) => (Err(e), None),
+        };
+
+        match (failed_retcode, &result) {
+            (Some(retcode), _) => self
+                .capabilities
+                .learn(action, self.capabilities.retcode(retcode)),
+            (None, Ok(_)) => self.capabilities.learn(action, RetCode::Ok),
+            (None, Err(_)) => {}
+        }
+
+        #[cfg(feature = "metrics")]
         {
+            self.metrics.record_api_call(action, started.elapsed());
+            if failed_retcode.is_some() || result.is_err() {
+                self.metrics.record_api_failure(failed_retcode);
+            }
+        }
+
+        if let Some(dead_letter) = self.state.get::<DeadLetter>() {
+            let error = match (failed_retcode, &result) {
+                (Some(retcode), _) => Some(format!("retcode {}", retcode)),
+                (None, Err(e)) => Some(e.to_string()),
+                (None, Ok(_)) => None,
+            };
+            if let Some(error) = error {
+                dead_letter
+                    .send(FailedCall::new(
+                        action.to_string(),
+                        params.clone(),
+                        error,
+                        failed_retcode,
+                        attempt,
+                    ))
+                    .await;
+            }
+        }
+
+        result
+    }
+
+    /// Send a request and wait for its response frame.
+    async fn send_request(
+        &self,
+        action: &str,
+        params: &serde_json::Value,
+    ) -> Result<Utf8Bytes, FlowError> {
+        // Only the request is rewritten, the call keeps its own action name in metrics, dead letters and capabilities.
+        let profile = self.quirks.active();
+        let (action, params) = match &profile {
+            Some(profile) => {
+                let mut params = params.clone();
+                profile.rewrite_params(&mut params);
+                (profile.action(action), Cow::Owned(params))
+            }
+            None => (action, Cow::Borrowed(params)),
+        };
+
+        // Send message and release lock immediately
+        let (echo, rx, buffered) = {
             let mut sink = self.sink.lock().await;
-            let sink = sink.as_mut().ok_or(FlowError::NoConnection)?;
-            sink.send(msg).await?;
+
+            // The echo is generated under the sink lock, so that it belongs to the connection the frame is sent on
+            let echo = self.next_echo();
+            let msg = json!({
+                "action": action,
+                "params": params,
+                "echo": echo,
+            });
+            let text: Utf8Bytes = serde_json::to_string(&msg)?.into();
+
+            // Register the request BEFORE sending (lock-free)
+            let (tx, rx) = oneshot::channel();
+            self.pending_requests
+                .insert(echo.clone(), (Instant::now(), tx));
+
+            let buffered = match (sink.as_mut(), &self.outbox) {
+                (Some(sink), _) => {
+                    if let Err(e) = sink.send(Message::Text(text)).await {
+                        self.pending_requests.remove(&echo);
+                        return Err(e.into());
+                    }
+                    None
+                }
+                (None, Some(outbox)) if outbox.accepts(action) => {
+                    Some((outbox.push(action, text), outbox.max_age()))
+                }
+                (None, _) => {
+                    self.pending_requests.remove(&echo);
+                    return Err(FlowError::NoConnection);
+                }
+            };
+            (echo, rx, buffered)
+        };
+
+        // Buffered while disconnected, the response timeout starts once it is actually sent
+        if let Some((sent, max_age)) = buffered
+            && !matches!(tokio::time::timeout(max_age, sent).await, Ok(Ok(())))
+        {
+            self.pending_requests.remove(&echo);
+            return Err(FlowError::NoConnection);
         }
 
         // Wait for response with timeout
         let response = tokio::time::timeout(std::time::Duration::from_secs(30), rx).await;
 
         match response {
-            Ok(Ok(data)) => Ok(serde_json::from_str(&data)?),
+            Ok(Ok(frame)) => Ok(frame),
             Ok(Err(_)) => Err(FlowError::NoResponse), // Sender dropped
             Err(_) => {
                 // Timeout occurred, clean up the pending request (lock-free)
@@ -93,15 +390,367 @@ impl Context {
         }
     }
 
-    pub(crate) fn on_recv_echo(&self, echo: String, data: String) {
-        let pending_requests = self.pending_requests.clone();
-        tokio::spawn(async move {
-            // DashMap::remove returns Option<(K, V)>, extract the sender
-            if let Some((_, tx)) = pending_requests.remove(&echo) {
-                let _ = tx.send(data); // Ignore error if receiver dropped
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &crate::extensions::metrics::Metrics {
+        &self.metrics
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn pending_request_count(&self) -> usize {
+        self.pending_requests.len()
+    }
+
+    /// Statistics of every registered handler and service, in registration order.
+    #[cfg(feature = "handler-stats")]
+    pub fn handler_stats(&self) -> Vec<super::handler_stats::HandlerStats> {
+        self.handler_stats
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| cell.snapshot(index))
+            .collect()
+    }
+
+    /// Reply to a message in the chat it was sent in, in the [`ReplyStyle`] registered as a state.
+    /// Temp sessions are answered through the group they were started from, when it is known.
+    pub async fn reply<M>(
+        &self,
+        to: &message::Message,
+        message: M,
+    ) -> Result<SendMessageResponse, FlowError>
+    where
+        M: IntoMessage + Send,
+    {
+        let style = self.state.get::<ReplyStyle>().as_deref().copied();
+        self.reply_with_style(to, message, style.unwrap_or_default())
+            .await
+    }
+
+    /// Like [`reply`](Self::reply), overriding the registered [`ReplyStyle`].
+    pub async fn reply_with_style<M>(
+        &self,
+        to: &message::Message,
+        message: M,
+        style: ReplyStyle,
+    ) -> Result<SendMessageResponse, FlowError>
+    where
+        M: IntoMessage + Send,
+    {
+        let message = to.reply_styled(message, style);
+        match &to.info {
+            TypedMessageInfo::Group(info) => {
+                self.send_group_message(info.group_id, message, None).await
             }
-            // If echo not found, response arrived after timeout - silently ignore
-        });
+            TypedMessageInfo::Private(info) => match info.temp_group_id() {
+                Some(group_id) => self.send_temp_message(group_id, to.user_id, message).await,
+                None => self.send_private_message(to.user_id, message, None).await,
+            },
+        }
+    }
+
+    /// Fetch the message `message_id` and the messages it replies to in turn, nearest first, up to `max_depth` messages.
+    ///
+    /// The walk stops early at a message that cannot be fetched or was already seen, returning the messages fetched so far.
+    pub async fn resolve_reply_chain<R>(
+        &self,
+        message_id: R,
+        max_depth: usize,
+    ) -> Vec<GetMessageResponse>
+    where
+        R: Into<ReplyRef>,
+    {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = Some(message_id.into());
+        while let Some(id) = next.take()
+            && chain.len() < max_depth
+            && seen.insert(id.clone())
+        {
+            let message = match self.get_message(id.clone()).await {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::debug!("Reply chain ends at {}, which failed to fetch: {}", id, e);
+                    break;
+                }
+            };
+            // Replies may refer to the message by another form of its id.
+            seen.insert(ReplyRef::Id(message.message_id));
+            next = message.message.iter().find_map(|segment| match segment {
+                Segment::Reply(reply) => Some(reply.reply_ref()),
+                _ => None,
+            });
+            chain.push(message);
+        }
+        chain
+    }
+
+    /// Fetch the forwarded messages `forward_id` and the forwards nested in them, outermost first,
+    /// following forwards at most `max_depth` levels deep.
+    ///
+    /// Forward ids are numeric for most implementations but opaque strings for some, as in [`ForwardSegment`],
+    /// both are fetched as they are. Forwards that cannot be fetched or were already seen are left out,
+    /// the others are still returned.
+    ///
+    /// [`ForwardSegment`]: crate::message::segments::ForwardSegment
+    pub async fn resolve_forward(
+        &self,
+        forward_id: &str,
+        max_depth: usize,
+    ) -> Vec<GetForwardResponse> {
+        let mut forwards = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([(ReplyRef::from(forward_id), 1)]);
+        while let Some((id, depth)) = queue.pop_front() {
+            if depth > max_depth || !seen.insert(id.clone()) {
+                continue;
+            }
+            let forward = match self.get_forward_message(id.clone()).await {
+                Ok(forward) => forward,
+                Err(e) => {
+                    tracing::debug!("Failed to fetch forward {}: {}", id, e);
+                    continue;
+                }
+            };
+            for segment in &forward.message {
+                if let Segment::Forward(nested) = segment {
+                    queue.push_back((ReplyRef::from(nested.id.as_str()), depth + 1));
+                }
+            }
+            forwards.push(forward);
+        }
+        forwards
+    }
+
+    /// What the connected implementation supports. Its version is queried on the first call, and again later if that failed.
+    pub async fn capabilities(&self) -> &Capabilities {
+        let version = self
+            .capabilities
+            .version_cell()
+            .get_or_try_init(|| self.get_version_info())
+            .await;
+        if let Err(e) = version {
+            tracing::warn!("Failed to query the version of the implementation: {}", e);
+        }
+        &self.capabilities
+    }
+
+    /// The quirk profile requests are rewritten with, `None` until the implementation is recognized.
+    pub fn quirk_profile(&self) -> Option<Arc<QuirkProfile>> {
+        self.quirks.active()
+    }
+
+    /// Recognize the implementation and use the matching quirk profile, if it was not recognized before.
+    pub(crate) async fn detect_quirks(&self) {
+        if self.quirks.active().is_some() {
+            return;
+        }
+        if let Some(version) = self.capabilities().await.version() {
+            self.quirks.select(version);
+        }
+    }
+
+    /// Who the bot is logged in as and which implementation it is connected to, see [`BotIdentity`].
+    /// Collected on the first call after every connection and cached until the next one.
+    pub async fn identity(&self) -> Arc<BotIdentity> {
+        let mut identity = self.identity.lock().await;
+        if let Some(cached) = identity.as_ref()
+            && cached.generation == self.connection_generation()
+        {
+            return cached.clone();
+        }
+        let collected = Arc::new(BotIdentity::collect(self).await);
+        *identity = Some(collected.clone());
+        collected
+    }
+
+    /// Start a [`Batch`] of API calls made with this context, whose results are of type `T`.
+    pub fn batch<'c, T: Send + 'c>(&'c self) -> Batch<'c, T> {
+        Batch::new(self)
+    }
+
+    /// A snapshot of the connection state and activity of the bot.
+    pub fn health(&self) -> Health {
+        self.health.snapshot(self.pending_requests.len())
+    }
+
+    /// A report of the registered states, the requests waiting for a response and the health of the bot,
+    /// for debugging, see [`DebugSnapshot`].
+    pub fn debug_snapshot(&self) -> DebugSnapshot {
+        let now = Instant::now();
+        let mut pending_requests = self
+            .pending_requests
+            .iter()
+            .map(|entry| PendingRequestInfo {
+                echo: entry.key().clone(),
+                age: now.saturating_duration_since(entry.value().0),
+            })
+            .collect::<Vec<_>>();
+        pending_requests.sort_by_key(|request| std::cmp::Reverse(request.age));
+        let mut ignored_groups = self.ignored_groups();
+        ignored_groups.sort_unstable();
+
+        DebugSnapshot {
+            health: self.health(),
+            connection_generation: self.connection_generation(),
+            shutting_down: self.is_shutting_down(),
+            ignored_groups,
+            states: self.state.snapshot(),
+            pending_requests,
+        }
+    }
+
+    /// The number of the current or last connection, starting at 1 and increased on every reconnection.
+    /// 0 before the bot first connected.
+    ///
+    /// Background tasks can compare it to the generation they started on to notice that the bot reconnected since.
+    pub fn connection_generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.borrow().is_some()
+    }
+
+    /// Wait until the bot is connected, right away if it already is, returning the generation of the connection.
+    pub async fn wait_for_connected(&self) -> u64 {
+        let mut connection = self.connection.subscribe();
+        // The sender lives as long as the context, so waiting cannot fail.
+        let generation = connection.wait_for(Option::is_some).await.ok();
+        generation
+            .and_then(|generation| *generation)
+            .unwrap_or_default()
+    }
+
+    /// Drop group messages of `group_id` before they reach any handler, returning whether it was not ignored yet.
+    pub fn ignore_group(&self, group_id: i64) -> bool {
+        self.ignored_groups.insert(group_id)
+    }
+
+    /// Dispatch group messages of `group_id` again, returning whether it was ignored.
+    pub fn unignore_group(&self, group_id: i64) -> bool {
+        self.ignored_groups.remove(&group_id).is_some()
+    }
+
+    pub fn is_group_ignored(&self, group_id: i64) -> bool {
+        self.ignored_groups.contains(&group_id)
+    }
+
+    pub fn ignored_groups(&self) -> Vec<i64> {
+        self.ignored_groups.iter().map(|id| *id).collect()
+    }
+
+    /// Stop the bot gracefully.
+    ///
+    /// No more events are dispatched, handlers still running get up to [`SHUTDOWN_GRACE`] to finish,
+    /// then the connection is closed and [`FlowBot::run`] returns `Ok(())` without reconnecting.
+    ///
+    /// [`SHUTDOWN_GRACE`]: crate::SHUTDOWN_GRACE
+    /// [`FlowBot::run`]: crate::FlowBot::run
+    pub fn shutdown(&self) {
+        if !self.stopping.send_replace(true) {
+            tracing::info!("Shutting down");
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.stopping.borrow()
+    }
+
+    /// Wait until [`shutdown`](Self::shutdown) is called, right away if it was.
+    pub(crate) async fn stopped(&self) {
+        let mut stopping = self.stopping.subscribe();
+        // The sender lives as long as the context, so waiting cannot fail.
+        let _ = stopping.wait_for(|stopping| *stopping).await;
+    }
+
+    /// Close the current connection with a close frame, if there is one.
+    pub(crate) async fn close_connection(&self) {
+        if let Some(sink) = self.sink.lock().await.as_mut()
+            && let Err(e) = sink.send(Message::Close(None)).await
+        {
+            tracing::debug!("Failed to close the connection: {}", e);
+        }
+    }
+
+    /// Install the sink of a new connection, first sending the frames buffered while disconnected.
+    ///
+    /// Fails if a buffered frame cannot be sent. The connection is then not used: the unsent frames stay buffered,
+    /// with their requests still pending, for the next connection to send.
+    pub(crate) async fn set_sink(&self, mut new_sink: WsSink) -> Result<(), FlowError> {
+        let mut sink = self.sink.lock().await;
+        if let Some(outbox) = &self.outbox {
+            let mut entries = outbox.drain().into_iter();
+            while let Some(entry) = entries.next() {
+                if let Err(e) = new_sink.send(Message::Text(entry.text.clone())).await {
+                    tracing::warn!("Failed to flush outbox: {}", e);
+                    outbox.requeue(std::iter::once(entry).chain(entries));
+                    return Err(e.into());
+                }
+                entry.mark_sent();
+            }
+        }
+        *sink = Some(new_sink);
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        self.connection.send_replace(Some(generation));
+        Ok(())
+    }
+
+    /// Drop the sink of a closed connection.
+    ///
+    /// Requests still waiting for a response from it fail right away, and the echo prefix is changed
+    /// so that late responses from the old connection cannot resolve newer requests.
+    pub(crate) async fn clear_sink(&self) {
+        let mut sink = self.sink.lock().await;
+        *sink = None;
+        self.connection.send_replace(None);
+
+        let stale = std::mem::replace(&mut *self.echo_prefix.write().unwrap(), new_echo_prefix());
+        self.pending_requests
+            .retain(|echo, _| echo_prefix(echo) != Some(&*stale));
+    }
+
+    fn next_echo(&self) -> String {
+        let prefix = self.echo_prefix.read().unwrap().clone();
+        format!(
+            "{}:{}",
+            prefix,
+            self.next_echo.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    pub(crate) fn on_recv_echo(&self, echo: String, data: Utf8Bytes) {
+        if echo_prefix(&echo) != Some(&**self.echo_prefix.read().unwrap()) {
+            tracing::debug!(
+                "Ignoring response {}, sent by another client or on a previous connection",
+                echo
+            );
+            return;
+        }
+
+        // DashMap::remove returns Option<(K, V)>, extract the sender
+        if let Some((_, (_, tx))) = self.pending_requests.remove(&echo) {
+            let _ = tx.send(data); // Ignore error if receiver dropped
+
+            let mut resolved = self.resolved_echoes.lock().unwrap();
+            resolved.push_back(echo);
+            if resolved.len() > RESOLVED_ECHOES {
+                resolved.pop_front();
+            }
+        } else if self.resolved_echoes.lock().unwrap().contains(&echo) {
+            tracing::warn!("Received a duplicate response for {}", echo);
+        }
+        // Otherwise the response arrived after the request timed out
+    }
+
+    /// Prepare extensions that need async setup, before the bot connects.
+    pub(crate) async fn init_extensions(&self) -> Result<(), FlowError> {
+        #[cfg(feature = "sqlx-sqlite")]
+        if let Some(dispatcher) = self
+            .state
+            .get::<crate::extensions::sqlite::SqliteDispatcher>()
+        {
+            dispatcher.get_pool().await?;
+        }
+        Ok(())
     }
 
     pub async fn get_self_id(&self) -> Result<i64, FlowError> {
@@ -119,8 +768,17 @@ impl FromEvent for BotContext {
     }
 }
 
+type DebugFn = fn(&(dyn Any + Send + Sync)) -> serde_json::Value;
+
+struct StateEntry {
+    state: Arc<dyn Any + Send + Sync>,
+    type_name: &'static str,
+    /// Renders the state, for states registered as a [`DebugState`].
+    debug: Option<DebugFn>,
+}
+
 pub(crate) struct StateMap {
-    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    map: HashMap<TypeId, StateEntry>,
 }
 
 impl StateMap {
@@ -130,13 +788,57 @@ impl StateMap {
         }
     }
 
-    pub(crate) fn insert<T: Any + Send + Sync>(&mut self, state: T) {
-        self.map.insert(TypeId::of::<T>(), Arc::new(state));
+    /// Insert a state, returning whether a state of the same type was replaced.
+    pub(crate) fn insert<T: Any + Send + Sync>(&mut self, state: T) -> bool {
+        self.insert_entry(state, None)
+    }
+
+    /// Insert a state that shows itself in [`Context::debug_snapshot`].
+    pub(crate) fn insert_debug<T: DebugState>(&mut self, state: T) -> bool {
+        self.insert_entry(
+            state,
+            Some(|state| {
+                state
+                    .downcast_ref::<T>()
+                    .map_or(serde_json::Value::Null, T::debug_json)
+            }),
+        )
+    }
+
+    fn insert_entry<T: Any + Send + Sync>(&mut self, state: T, debug: Option<DebugFn>) -> bool {
+        let entry = StateEntry {
+            state: Arc::new(state),
+            type_name: std::any::type_name::<T>(),
+            debug,
+        };
+        self.map.insert(TypeId::of::<T>(), entry).is_some()
+    }
+
+    pub(crate) fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.contains_id(TypeId::of::<T>())
+    }
+
+    pub(crate) fn contains_id(&self, type_id: TypeId) -> bool {
+        self.map.contains_key(&type_id)
     }
 
     pub(crate) fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
         self.map
             .get(&TypeId::of::<T>())
-            .and_then(|state| Arc::clone(state).downcast::<T>().ok())
+            .and_then(|entry| Arc::clone(&entry.state).downcast::<T>().ok())
+    }
+
+    /// The states in the map, sorted by type name.
+    pub(crate) fn snapshot(&self) -> Vec<StateInfo> {
+        let mut states = self
+            .map
+            .values()
+            .map(|entry| StateInfo {
+                type_name: entry.type_name,
+                debug: entry.debug.map(|debug| debug(&*entry.state)),
+            })
+            .collect::<Vec<_>>();
+        states.sort_by_key(|state| state.type_name);
+        states
     }
 }
@@ -0,0 +1,215 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+
+use crate::{
+    api::{ApiResponse, retcode::RetCode},
+    error::FlowError,
+};
+
+use super::context::Context;
+
+/// An API call that failed, either without a response or with a non-zero retcode.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FailedCall {
+    pub action: String,
+    pub params: serde_json::Value,
+    pub error: String,
+    /// `None` when no response was received.
+    pub retcode: Option<i32>,
+    /// Number of times the call was sent, counting redrives.
+    pub attempts: u32,
+    /// Unix timestamp in seconds of the failure.
+    pub time: i64,
+}
+
+impl FailedCall {
+    pub(crate) fn new(
+        action: String,
+        params: serde_json::Value,
+        error: String,
+        retcode: Option<i32>,
+        attempts: u32,
+    ) -> Self {
+        Self {
+            action,
+            params,
+            error,
+            retcode,
+            attempts,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64,
+        }
+    }
+
+    /// Whether sending the call again may succeed, see [`RetCode::is_retryable`].
+    /// Calls that got no response are.
+    pub fn is_retryable(&self) -> bool {
+        self.retcode
+            .is_none_or(|retcode| RetCode::from(retcode).is_retryable())
+    }
+
+    /// Send the call again. If it fails again it is passed to the dead letter sink with one more attempt.
+    pub async fn redrive(
+        &self,
+        context: &Context,
+    ) -> Result<ApiResponse<serde_json::Value>, FlowError> {
+        let sent = match context.check_action_policy(&self.action) {
+            // Skips the outgoing hooks, the params already went through them.
+            Ok(()) => {
+                context
+                    .send_obj_attempt(&self.action, &self.params, self.attempts + 1)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        sent.map_err(|e| context.api_call_error(&self.action, &self.params, e))
+    }
+}
+
+type DeadLetterFn = dyn Fn(FailedCall) -> BoxFuture<'static, ()> + Send + Sync;
+
+/// Receives every failed API call, registered with [`with_dead_letter`].
+///
+/// The call still returns its error, the sink runs before it is returned.
+///
+/// [`with_dead_letter`]: crate::FlowBotBuilder::with_dead_letter
+#[derive(Clone)]
+pub struct DeadLetter(Arc<DeadLetterFn>);
+
+impl DeadLetter {
+    pub fn new<F, Fut>(sink: F) -> Self
+    where
+        F: Fn(FailedCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self(Arc::new(move |call| Box::pin(sink(call))))
+    }
+
+    pub(crate) async fn send(&self, call: FailedCall) {
+        (self.0)(call).await
+    }
+}
+
+/// A dead letter sink appending failed calls to a JSON lines file, registered with [`with_dead_letter_file`].
+///
+/// Extract it with `State<DeadLetterFile>` to [`redrive`](Self::redrive) the calls later.
+///
+/// [`with_dead_letter_file`]: crate::FlowBotBuilder::with_dead_letter_file
+#[derive(Clone)]
+pub struct DeadLetterFile {
+    path: Arc<PathBuf>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl DeadLetterFile {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: Arc::new(path.into()),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn append(&self, call: &FailedCall) -> Result<(), FlowError> {
+        let mut line = serde_json::to_vec(call)?;
+        line.push(b'\n');
+        let _guard = self.lock.lock().await;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&*self.path)
+            .await?
+            .write_all(&line)
+            .await?;
+        Ok(())
+    }
+
+    /// The calls in the file, skipping corrupted lines.
+    pub async fn read(&self) -> Result<Vec<FailedCall>, FlowError> {
+        let _guard = self.lock.lock().await;
+        self.read_unlocked().await
+    }
+
+    async fn read_unlocked(&self) -> Result<Vec<FailedCall>, FlowError> {
+        let content = match tokio::fs::read_to_string(&*self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(call) => Some(call),
+                Err(e) => {
+                    tracing::warn!("Skipping corrupted dead letter entry: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Send every call in the file again, in order, returning how many succeeded.
+    ///
+    /// The retryable calls are taken out of the file first, calls failing again are appended back by the sink
+    /// if this file is still registered. Calls that are not [retryable](FailedCall::is_retryable) are kept in
+    /// the file without being sent. Calls appended while redriving are kept for the next redrive.
+    pub async fn redrive(&self, context: &Context) -> Result<usize, FlowError> {
+        let calls = {
+            // Held from reading to rewriting the file, so that no call appended meanwhile is lost.
+            let _guard = self.lock.lock().await;
+            let (calls, kept): (Vec<_>, Vec<_>) = self
+                .read_unlocked()
+                .await?
+                .into_iter()
+                .partition(FailedCall::is_retryable);
+            let mut content = Vec::new();
+            for call in &kept {
+                serde_json::to_writer(&mut content, call)?;
+                content.push(b'\n');
+            }
+            tokio::fs::write(&*self.path, content).await?;
+            calls
+        };
+
+        let capabilities = context.capabilities().await;
+        let mut succeeded = 0;
+        for call in calls {
+            match call.redrive(context).await {
+                Ok(resp) if capabilities.retcode(resp.retcode).is_success() => succeeded += 1,
+                _ => {}
+            }
+        }
+        Ok(succeeded)
+    }
+}
+
+impl From<DeadLetterFile> for DeadLetter {
+    fn from(file: DeadLetterFile) -> Self {
+        DeadLetter::new(move |call| {
+            let file = file.clone();
+            async move {
+                if let Err(e) = file.append(&call).await {
+                    tracing::error!(
+                        "Failed to write dead letter {} to {}: {}",
+                        call.action,
+                        file.path().display(),
+                        e
+                    );
+                }
+            }
+        })
+    }
+}
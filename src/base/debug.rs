@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+use super::health::Health;
+
+/// A state that shows its content in [`Context::debug_snapshot`], registered with [`with_debug_state`].
+///
+/// ```
+/// use flow_bot::base::debug::DebugState;
+/// use serde_json::json;
+///
+/// struct Counter(std::sync::atomic::AtomicU64);
+///
+/// impl DebugState for Counter {
+///     fn debug_json(&self) -> serde_json::Value {
+///         json!({ "count": self.0.load(std::sync::atomic::Ordering::Relaxed) })
+///     }
+/// }
+/// ```
+///
+/// [`Context::debug_snapshot`]: crate::base::context::Context::debug_snapshot
+/// [`with_debug_state`]: crate::FlowBotBuilder::with_debug_state
+pub trait DebugState: Send + Sync + 'static {
+    fn debug_json(&self) -> serde_json::Value;
+}
+
+/// A report of the runtime state of the bot, from [`Context::debug_snapshot`].
+///
+/// [`Context::debug_snapshot`]: crate::base::context::Context::debug_snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugSnapshot {
+    pub health: Health,
+    pub connection_generation: u64,
+    pub shutting_down: bool,
+    pub ignored_groups: Vec<i64>,
+    /// The registered states, sorted by type name.
+    pub states: Vec<StateInfo>,
+    /// Requests waiting for their response, the oldest first.
+    pub pending_requests: Vec<PendingRequestInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StateInfo {
+    pub type_name: &'static str,
+    /// The [`DebugState::debug_json`] of the state, `None` for other states.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingRequestInfo {
+    pub echo: String,
+    /// Time since the request was sent, or buffered while disconnected.
+    #[serde(rename = "age_ms", serialize_with = "as_millis")]
+    pub age: Duration,
+}
+
+fn as_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    (duration.as_millis() as u64).serialize(serializer)
+}
@@ -0,0 +1,77 @@
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use dashmap::{DashMap, mapref::entry::Entry};
+
+use crate::event::{Event, TypedEvent};
+
+/// Recently seen events, set up with [`with_event_dedup`].
+///
+/// Keys are kept in a map for lookups and in a queue in the order they were seen, which is also
+/// the order they expire in, so that the oldest ones are dropped first once `capacity` is reached.
+///
+/// [`with_event_dedup`]: crate::FlowBotBuilder::with_event_dedup
+pub(crate) struct EventDedup {
+    window: Duration,
+    capacity: usize,
+    seen: DashMap<u64, Instant>,
+    order: Mutex<VecDeque<(u64, Instant)>>,
+}
+
+impl EventDedup {
+    pub(crate) fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            seen: DashMap::new(),
+            order: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Messages are keyed by their id, notices by their whole payload since they have none.
+    fn key(event: &Event) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        match &event.event {
+            TypedEvent::Message(message) => {
+                (0u8, event.self_id, message.message_id).hash(&mut hasher)
+            }
+            TypedEvent::Notice(_) => (1u8, event.raw.as_bytes()).hash(&mut hasher),
+            _ => return None,
+        }
+        Some(hasher.finish())
+    }
+
+    /// Record the event, returning whether it was already seen within the window.
+    pub(crate) fn is_duplicate(&self, event: &Event) -> bool {
+        let Some(key) = Self::key(event) else {
+            return false;
+        };
+        let now = Instant::now();
+        // Checked and recorded under the same lock, so that of two copies arriving at once only one passes.
+        match self.seen.entry(key) {
+            Entry::Occupied(seen) if now.duration_since(*seen.get()) <= self.window => return true,
+            Entry::Occupied(mut seen) => {
+                seen.insert(now);
+            }
+            Entry::Vacant(seen) => {
+                seen.insert(now);
+            }
+        }
+
+        let mut order = self.order.lock().unwrap();
+        order.push_back((key, now));
+        while let Some(&(oldest, at)) = order.front() {
+            if order.len() <= self.capacity && now.duration_since(at) <= self.window {
+                break;
+            }
+            order.pop_front();
+            // The key may have been seen again since, then it is kept for the newer entry.
+            self.seen.remove_if(&oldest, |_, seen| *seen == at);
+        }
+        false
+    }
+}
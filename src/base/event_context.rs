@@ -0,0 +1,105 @@
+use std::{ops::Deref, time::Duration};
+
+use async_trait::async_trait;
+
+use crate::{
+    api::{SendMessageResponse, api_ext::ApiExt},
+    error::FlowError,
+    event::{
+        BotEvent, TypedEvent,
+        message::{Message, ReplyStyle, TypedMessageInfo},
+    },
+    message::IntoMessage,
+};
+
+use super::{
+    context::{BotContext, Context},
+    extract::FromEvent,
+};
+
+/// Extractor bundling the context with a message event, for acting on the message and its sender
+/// without passing ids around. Other events are skipped.
+///
+/// Derefs to [`Context`], so every API call is available as well.
+pub struct EventContext {
+    pub context: BotContext,
+    pub event: BotEvent,
+}
+
+impl EventContext {
+    pub fn message(&self) -> &Message {
+        match &self.event.event {
+            TypedEvent::Message(msg) => msg,
+            _ => unreachable!("event context of a non-message event"),
+        }
+    }
+
+    fn group_id(&self) -> Result<i64, FlowError> {
+        match &self.message().info {
+            TypedMessageInfo::Group(info) => Ok(info.group_id),
+            TypedMessageInfo::Private(_) => Err(FlowError::NotInGroup),
+        }
+    }
+
+    /// Reply in the chat of the message, see [`Context::reply`].
+    pub async fn reply<M>(&self, message: M) -> Result<SendMessageResponse, FlowError>
+    where
+        M: IntoMessage + Send,
+    {
+        self.context.reply(self.message(), message).await
+    }
+
+    /// Reply quoting the message and mentioning its sender, mentions are left out in private chats.
+    pub async fn reply_at<M>(&self, message: M) -> Result<SendMessageResponse, FlowError>
+    where
+        M: IntoMessage + Send,
+    {
+        self.context
+            .reply_with_style(self.message(), message, ReplyStyle::ReplyAndAt)
+            .await
+    }
+
+    /// Recall the message.
+    pub async fn recall(&self) -> Result<(), FlowError> {
+        self.context
+            .delete_message(self.message().message_id as i64)
+            .await
+    }
+
+    /// Mute the sender in the group, fails with [`FlowError::NotInGroup`] for private messages.
+    pub async fn ban_sender(&self, duration: Duration) -> Result<(), FlowError> {
+        let group_id = self.group_id()?;
+        self.context
+            .set_group_ban(group_id, self.message().user_id, duration)
+            .await
+    }
+
+    /// Kick the sender from the group, fails with [`FlowError::NotInGroup`] for private messages.
+    pub async fn kick_sender(&self) -> Result<(), FlowError> {
+        let group_id = self.group_id()?;
+        self.context
+            .set_group_kick(group_id, self.message().user_id, None)
+            .await
+    }
+}
+
+impl Deref for EventContext {
+    type Target = Context;
+
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
+}
+
+#[async_trait]
+impl FromEvent for EventContext {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        match event.event {
+            TypedEvent::Message(_) => Some(Self {
+                context,
+                event: event.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::future::Future;
+
+use serde::Serialize;
+
+use super::handler::ExtractorMiss;
+
+tokio::task_local! {
+    /// Set while [`FlowBot::explain`](crate::FlowBot::explain) runs extractors.
+    static DRY_RUN: ();
+}
+
+/// Whether extractors are run by [`FlowBot::explain`](crate::FlowBot::explain) rather than to call a handler,
+/// so that extractors keeping state, like [`Sampled`](super::chance::Sampled), leave it as it is.
+pub fn is_dry_run() -> bool {
+    DRY_RUN.try_with(|_| ()).is_ok()
+}
+
+pub(crate) async fn dry_run<F: Future>(f: F) -> F::Output {
+    DRY_RUN.scope((), f).await
+}
+
+/// What [`FlowBot::explain`](crate::FlowBot::explain) found for one registered handler or service.
+#[derive(Debug, Clone, Serialize)]
+pub struct HandlerExplanation {
+    /// Position in the registration order, counting handlers and services.
+    pub index: usize,
+    /// The type name of the handler or the name of the service.
+    pub name: &'static str,
+    /// The group given to [`with_handler_group`](crate::FlowBotBuilder::with_handler_group), if registered inside one.
+    pub group: Option<&'static str>,
+    #[serde(flatten)]
+    pub verdict: Verdict,
+}
+
+/// Whether a handler would be called with an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "verdict")]
+pub enum Verdict {
+    /// Every extractor of the handler succeeded, it would be called.
+    Matched,
+    /// An extractor returned `None`, the handler would be skipped.
+    Skipped(ExtractorMiss),
+    /// The handler is not registered for this type of event.
+    Filtered,
+    /// Services have no extractors, they would be called with the event.
+    Service,
+    /// The event is dropped before any handler runs.
+    Dropped { reason: &'static str },
+}
@@ -1,28 +1,57 @@
-use std::{ops::Deref, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashSet},
+    marker::PhantomData,
+    ops::Deref,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::api_ext::ApiExt,
+    api::{GetMessageResponse, api_ext::ApiExt},
     event::{
         BotEvent, TypedEvent,
         message::{
-            GroupSenderInfo, GroupSenderRole, PrivateSenderInfo, SenderSex, TypedMessageInfo,
+            GroupSenderInfo, GroupSenderRole, GroupSubType, PrivateSenderInfo, PrivateSubType,
+            SenderSex, TypedMessageInfo,
         },
     },
-    message::{self, message_ext::MessageExt, segments::Segment},
+    message::{
+        self,
+        message_ext::MessageExt,
+        segments::{AtSegment, Mention, RecordSegment, Segment},
+    },
 };
 
-use super::context::BotContext;
+use super::{context::BotContext, group_config::GroupConfigStore};
 
 #[async_trait]
 /// Extractor trait for extracting information from BotEvent and BotContext.
 pub trait FromEvent {
+    /// Whether the extractor has side effects that must only happen once the other arguments of a handler
+    /// were extracted. Ordered extractors run after the others, in argument order, see [`Seq`].
+    ///
+    /// Unordered extractors are started together, polled in argument order. One making API calls, like
+    /// [`RepliedMessage`], thus sends its requests even when another argument fails, unless that argument comes
+    /// before it and fails without waiting. Its result is then dropped. Wrap it in [`Seq`] to only call the API
+    /// once the other arguments were extracted.
+    const ORDERED: bool = false;
+
     async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
     where
         Self: Sized;
 }
 
+/// Always succeeds, useful as a placeholder in composed extractors.
+#[async_trait]
+impl FromEvent for () {
+    async fn from_event(_: BotContext, _: BotEvent) -> Option<Self> {
+        Some(())
+    }
+}
+
 /// State extractor, extract the state from BotContext.
 /// If the required state is not found, the handler will be skipped.
 pub struct State<S>(pub Arc<S>);
@@ -46,14 +75,52 @@ impl<S> Deref for State<S> {
     }
 }
 
+/// A shared view of a message, which is not cloned when extracted.
+/// Use [`MessageView::to_owned`] to get an owned copy.
+#[derive(Clone)]
+pub struct MessageView(MessageSource);
+
+#[derive(Clone)]
+enum MessageSource {
+    // Only constructed for message events.
+    Event(BotEvent),
+    Fetched(Arc<GetMessageResponse>),
+}
+
+impl MessageView {
+    pub fn to_owned(&self) -> message::Message {
+        self.deref().clone()
+    }
+}
+
+impl Deref for MessageView {
+    type Target = message::Message;
+
+    fn deref(&self) -> &Self::Target {
+        match &self.0 {
+            MessageSource::Event(event) => match &event.event {
+                TypedEvent::Message(msg) => &msg.message,
+                _ => unreachable!("message view of a non-message event"),
+            },
+            MessageSource::Fetched(response) => &response.message,
+        }
+    }
+}
+
+impl std::fmt::Debug for MessageView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
 /// Extractor for message event.
-pub struct MessageBody(pub message::Message);
+pub struct MessageBody(pub MessageView);
 
 #[async_trait]
 impl FromEvent for MessageBody {
     async fn from_event(_: BotContext, event: BotEvent) -> Option<MessageBody> {
         match event.event {
-            TypedEvent::Message(ref msg) => Some(Self(msg.message.clone())),
+            TypedEvent::Message(_) => Some(Self(MessageView(MessageSource::Event(event.clone())))),
             _ => None,
         }
     }
@@ -64,7 +131,7 @@ impl FromEvent for GroupSenderRole {
     async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
         match event.event {
             TypedEvent::Message(ref msg) => match &msg.info {
-                TypedMessageInfo::Group(info) => info.sender.role.clone(),
+                TypedMessageInfo::Group(info) => info.sender.role,
                 _ => None,
             },
             _ => None,
@@ -98,6 +165,7 @@ impl FromEvent for PrivateSenderInfo {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct BasicSenderInfo {
     pub user_id: Option<i64>,
     pub nickname: Option<String>,
@@ -127,40 +195,147 @@ impl From<GroupSenderInfo> for BasicSenderInfo {
     }
 }
 
-pub struct Sender(pub BasicSenderInfo);
+/// A shared view of the sender of a message, which is not cloned when extracted.
+/// Use [`SenderView::to_owned`] to get an owned [`BasicSenderInfo`].
+#[derive(Clone)]
+pub struct SenderView(BotEvent);
+
+impl SenderView {
+    fn info(
+        &self,
+    ) -> (
+        &Option<i64>,
+        &Option<String>,
+        &Option<SenderSex>,
+        &Option<i32>,
+    ) {
+        match &self.0.event {
+            TypedEvent::Message(msg) => match &msg.info {
+                TypedMessageInfo::Private(info) => {
+                    let s = &info.sender;
+                    (&s.user_id, &s.nickname, &s.sex, &s.age)
+                }
+                TypedMessageInfo::Group(info) => {
+                    let s = &info.sender;
+                    (&s.user_id, &s.nickname, &s.sex, &s.age)
+                }
+            },
+            _ => unreachable!("sender view of a non-message event"),
+        }
+    }
+
+    pub fn user_id(&self) -> Option<i64> {
+        *self.info().0
+    }
+
+    pub fn nickname(&self) -> Option<&str> {
+        self.info().1.as_deref()
+    }
+
+    pub fn sex(&self) -> Option<&SenderSex> {
+        self.info().2.as_ref()
+    }
+
+    pub fn age(&self) -> Option<i32> {
+        *self.info().3
+    }
+
+    pub fn to_owned(&self) -> BasicSenderInfo {
+        BasicSenderInfo {
+            user_id: self.user_id(),
+            nickname: self.nickname().map(str::to_string),
+            sex: self.sex().cloned(),
+            age: self.age(),
+        }
+    }
+}
+
+pub struct Sender(pub SenderView);
 
 #[async_trait]
 impl FromEvent for Sender {
     async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
         match event.event {
-            TypedEvent::Message(ref msg) => {
-                let info = match &msg.info {
-                    TypedMessageInfo::Private(info) => info.sender.clone().into(),
-                    TypedMessageInfo::Group(info) => info.sender.clone().into(),
-                };
-                Some(Self(info))
-            }
+            TypedEvent::Message(_) => Some(Self(SenderView(event.clone()))),
             _ => None,
         }
     }
 }
 
+fn mentions(event: &BotEvent) -> impl Iterator<Item = (&AtSegment, Mention)> {
+    let segments = match &event.event {
+        TypedEvent::Message(msg) => msg.message.as_slice(),
+        _ => &[],
+    };
+    segments.iter().filter_map(|seg| match seg {
+        Segment::At(at) => match at.mention() {
+            Some(mention) => Some((at, mention)),
+            None => {
+                tracing::debug!("Ignoring at segment with invalid target {}", at.qq);
+                None
+            }
+        },
+        _ => None,
+    })
+}
+
+/// Extractor for the first mention of the message, either a user id or `all`.
+/// At segments with invalid targets are ignored.
 pub struct At(pub String);
 
+impl At {
+    /// The mentioned user id, `None` for `all`.
+    pub fn user_id_i64(&self) -> Option<i64> {
+        self.0.parse().ok()
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.0 == "all"
+    }
+}
+
 #[async_trait]
 impl FromEvent for At {
     async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
-        if let TypedEvent::Message(ref msg) = event.event {
-            msg.message.iter().find_map(|seg| match seg {
-                Segment::At(at) => Some(Self(at.qq.clone())),
-                _ => None,
-            })
-        } else {
+        mentions(&event).next().map(|(at, _)| Self(at.qq.clone()))
+    }
+}
+
+/// Extractor for all mentions of the message, in order.
+/// At segments with invalid targets are ignored, and messages without mentions are skipped.
+pub struct Mentions(pub Vec<Mention>);
+
+#[async_trait]
+impl FromEvent for Mentions {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        let mentions = mentions(&event)
+            .map(|(_, mention)| mention)
+            .collect::<Vec<_>>();
+        if mentions.is_empty() {
             None
+        } else {
+            Some(Self(mentions))
         }
     }
 }
 
+/// Extractor for the first record segment of the message, skipping messages without one.
+/// Use [`RecordSegment::fetch`] to get the audio in a playable format.
+pub struct Voice(pub RecordSegment);
+
+#[async_trait]
+impl FromEvent for Voice {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        let TypedEvent::Message(msg) = &event.event else {
+            return None;
+        };
+        msg.message.iter().find_map(|segment| match segment {
+            Segment::Record(record) => Some(Self(record.clone())),
+            _ => None,
+        })
+    }
+}
+
 pub struct GroupId(pub i64);
 
 #[async_trait]
@@ -189,7 +364,41 @@ impl FromEvent for SenderId {
         Self: Sized,
     {
         let sender_info = Sender::from_event(context, event).await?;
-        Some(Self(sender_info.0.user_id?))
+        Some(Self(sender_info.0.user_id()?))
+    }
+}
+
+/// Extractor for the time of the event, which always succeeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventTime(pub i64);
+
+impl EventTime {
+    /// Seconds since the unix epoch.
+    pub fn timestamp(&self) -> i64 {
+        self.0
+    }
+
+    pub fn system_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.0.max(0) as u64)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn datetime(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(self.0, 0).unwrap_or_default()
+    }
+
+    /// How long ago the event happened, zero if its time is in the future.
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.system_time())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl FromEvent for EventTime {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        Some(Self(event.time))
     }
 }
 
@@ -206,31 +415,232 @@ impl<const ID: i64> FromEvent for MatchGroupId<ID> {
     }
 }
 
-pub struct Reply(pub message::Message);
+/// Guard extractor matching group messages from any of the given groups.
+///
+/// # Example
+/// ```ignore
+/// async fn handler(_: MatchAnyGroupId<{ &[123, 456] }>) -> HandlerControl { ... }
+/// ```
+pub struct MatchAnyGroupId<const IDS: &'static [i64]>;
 
 #[async_trait]
-impl FromEvent for Reply {
+impl<const IDS: &'static [i64]> FromEvent for MatchAnyGroupId<IDS> {
     async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
     where
         Self: Sized,
     {
-        if let TypedEvent::Message(ref msg) = event.event {
-            for segment in msg.message.iter() {
-                if let Segment::Reply(reply) = segment {
-                    let id = reply.id.parse::<i64>().ok()?;
-                    let message = context
-                        .get_message(id)
-                        .await
-                        .map(|msg| msg.message.clone())
-                        .ok()?;
-                    return Some(Self(message));
-                }
-            }
+        let group_id = GroupId::from_event(context, event).await?.0;
+        if IDS.contains(&group_id) {
+            Some(Self)
+        } else {
+            None
+        }
+    }
+}
+
+/// Guard extractor matching private messages.
+pub struct MatchPrivate {
+    pub user_id: i64,
+    pub sub_type: PrivateSubType,
+}
+
+#[async_trait]
+impl FromEvent for MatchPrivate {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        let TypedEvent::Message(ref msg) = event.event else {
+            return None;
+        };
+        match &msg.info {
+            TypedMessageInfo::Private(info) => Some(Self {
+                user_id: msg.user_id,
+                sub_type: info.sub_type,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Guard extractor matching group messages.
+pub struct MatchGroup {
+    pub group_id: i64,
+    pub sub_type: GroupSubType,
+}
+
+#[async_trait]
+impl FromEvent for MatchGroup {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        let TypedEvent::Message(ref msg) = event.event else {
+            return None;
+        };
+        match &msg.info {
+            TypedMessageInfo::Group(info) => Some(Self {
+                group_id: info.group_id,
+                sub_type: info.sub_type,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Guard extractor matching private messages of the given sub type,
+/// e.g. `MatchPrivateSubType<{ PrivateSubType::Friend }>` to leave out temporary sessions.
+pub struct MatchPrivateSubType<const SUB_TYPE: PrivateSubType>;
+
+#[async_trait]
+impl<const SUB_TYPE: PrivateSubType> FromEvent for MatchPrivateSubType<SUB_TYPE> {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let private = MatchPrivate::from_event(context, event).await?;
+        (private.sub_type == SUB_TYPE).then_some(Self)
+    }
+}
+
+/// Guard extractor matching group messages of the given sub type, e.g. `MatchGroupSubType<{ GroupSubType::Normal }>`.
+pub struct MatchGroupSubType<const SUB_TYPE: GroupSubType>;
+
+#[async_trait]
+impl<const SUB_TYPE: GroupSubType> FromEvent for MatchGroupSubType<SUB_TYPE> {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let group = MatchGroup::from_event(context, event).await?;
+        (group.sub_type == SUB_TYPE).then_some(Self)
+    }
+}
+
+/// State listing the groups a bot is allowed to act in, used by [`InAllowedGroups`].
+pub struct AllowedGroups(pub HashSet<i64>);
+
+/// State listing the groups a bot must ignore, used by [`NotInDeniedGroups`].
+pub struct DeniedGroups(pub HashSet<i64>);
+
+/// Extracts the group id of group messages from groups in the [`AllowedGroups`] state.
+/// Private messages and bots without the state are skipped.
+pub struct InAllowedGroups(pub i64);
+
+#[async_trait]
+impl FromEvent for InAllowedGroups {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let allowed = context.state.get::<AllowedGroups>()?;
+        let group_id = GroupId::from_event(context, event).await?.0;
+        if allowed.0.contains(&group_id) {
+            Some(Self(group_id))
+        } else {
+            None
         }
-        None
     }
 }
 
+/// Extracts the group id of group messages from groups not in the [`DeniedGroups`] state.
+/// Private messages are skipped, and without the state no group is denied.
+pub struct NotInDeniedGroups(pub i64);
+
+#[async_trait]
+impl FromEvent for NotInDeniedGroups {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let denied = context.state.get::<DeniedGroups>();
+        let group_id = GroupId::from_event(context, event).await?.0;
+        match denied {
+            Some(denied) if denied.0.contains(&group_id) => None,
+            _ => Some(Self(group_id)),
+        }
+    }
+}
+
+/// Extractor for the full message replied to by the event message, fetched with `get_msg`.
+/// Reply ids that are not numeric are passed to `get_msg` unchanged, see [`ReplyRef`].
+///
+/// [`ReplyRef`]: crate::message::segments::ReplyRef
+///
+/// The fetched message is cached on the event, so [`Reply`] and `RepliedMessage` in the same or later handlers only fetch it once.
+pub struct RepliedMessage(pub Arc<GetMessageResponse>);
+
+struct CachedReply(Option<Arc<GetMessageResponse>>);
+
+#[async_trait]
+impl FromEvent for RepliedMessage {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let TypedEvent::Message(ref msg) = event.event else {
+            return None;
+        };
+        let reply_ref = msg.message.iter().find_map(|segment| match segment {
+            Segment::Reply(reply) => Some(reply.reply_ref()),
+            _ => None,
+        })?;
+
+        let cached = event
+            .extensions
+            .get_or_insert_async(|| async move {
+                let response = context.get_message(reply_ref).await;
+                CachedReply(response.ok().map(Arc::new))
+            })
+            .await;
+        cached.0.clone().map(Self)
+    }
+}
+
+impl Deref for RepliedMessage {
+    type Target = GetMessageResponse;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Extractor for the segments of the message replied to by the event message.
+/// See [`RepliedMessage`] for the full message with its sender.
+pub struct Reply(pub MessageView);
+
+#[async_trait]
+impl FromEvent for Reply {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let RepliedMessage(replied) = RepliedMessage::from_event(context, event).await?;
+        Some(Self(MessageView(MessageSource::Fetched(replied))))
+    }
+}
+
+/// The key of the [`CommandConfig`] store registered by [`with_command_config`](crate::FlowBotBuilder::with_command_config).
+pub const COMMAND_CONFIG_KEY: &str = "commands";
+
+/// Per-group overrides of the [`Command`] extractor, registered with [`with_command_config`]
+/// and editable at runtime, e.g. with the commands of the [`AdminService`].
+///
+/// [`with_command_config`]: crate::FlowBotBuilder::with_command_config
+/// [`AdminService`]: crate::extensions::admin::AdminService
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandConfig {
+    /// Replaces the prefix of every command in the group, e.g. `#` to use `#echo` for `Command<"/echo", _>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Names of the commands that are not extracted in the group, without prefix, e.g. `echo`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub disabled: BTreeSet<String>,
+}
+
+/// Split a command like `/echo` into its prefix, the leading ASCII punctuation, and its name.
+pub(crate) fn split_command(command: &str) -> (&str, &str) {
+    let name_start = command
+        .find(|c: char| !c.is_ascii_punctuation())
+        .unwrap_or(command.len());
+    command.split_at(name_start)
+}
+
+/// Extractor for a command parsed with clap, for messages starting with `PREFIX`, e.g. `Command<"/echo", Echo>`.
+///
+/// `PREFIX` is made of the command prefix, the leading punctuation like `/`, and the command name.
+/// With [`with_command_config`], the [`CommandConfig`] of the group can replace the prefix and disable the command.
+/// Private messages always use `PREFIX` as is. The configuration is read on every message, so changes apply right away.
+///
+/// [`with_command_config`]: crate::FlowBotBuilder::with_command_config
 pub struct Command<const PREFIX: &'static str, Cmd> {
     pub command: Cmd,
 }
@@ -244,11 +654,27 @@ where
     where
         Self: Sized,
     {
-        let message_body = MessageBody::from_event(context, event).await?;
+        let group_id = GroupId::from_event(context.clone(), event.clone()).await;
+        let message_body = MessageBody::from_event(context.clone(), event).await?;
         let plain_text = message_body.0.extract_if_plain_text()?;
         let trimmed = plain_text.trim();
+        let word = trimmed.split_whitespace().next()?;
 
-        if trimmed.split_whitespace().next() != Some(PREFIX) {
+        let (mut prefix, name) = split_command(PREFIX);
+        let store = context.state.get::<GroupConfigStore<CommandConfig>>();
+        let config = match (store, group_id) {
+            (Some(store), Some(GroupId(group_id))) => Some(store.get(group_id).await),
+            _ => None,
+        };
+        if let Some(config) = &config {
+            if config.disabled.contains(name) {
+                return None;
+            }
+            if let Some(group_prefix) = &config.prefix {
+                prefix = group_prefix;
+            }
+        }
+        if word.strip_prefix(prefix) != Some(name) {
             return None;
         }
 
@@ -262,6 +688,8 @@ impl<T> FromEvent for Option<T>
 where
     T: FromEvent,
 {
+    const ORDERED: bool = T::ORDERED;
+
     async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
     where
         Self: Sized,
@@ -270,24 +698,199 @@ where
     }
 }
 
+/// Extractor that succeeds with the first of two extractors that matches.
+///
+/// Useful to accept different kinds of events with one handler argument, e.g. `Either<GroupId, SenderId>`.
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+/// Like [`Either`] with three alternatives.
+pub enum Either3<A, B, C> {
+    First(A),
+    Second(B),
+    Third(C),
+}
+
+/// Like [`Either`] with four alternatives.
+pub enum Either4<A, B, C, D> {
+    First(A),
+    Second(B),
+    Third(C),
+    Fourth(D),
+}
+
+macro_rules! impl_either {
+    ($name:ident, $($variant:ident : $ty:ident),*) => {
+        #[async_trait]
+        impl<$($ty),*> FromEvent for $name<$($ty),*>
+        where
+            $($ty: FromEvent + Send),*
+        {
+            const ORDERED: bool = false $(|| $ty::ORDERED)*;
+
+            async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+            where
+                Self: Sized,
+            {
+                $(
+                    if let Some(matched) = $ty::from_event(context.clone(), event.clone()).await {
+                        return Some(Self::$variant(matched));
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
+impl_either!(Either, Left: A, Right: B);
+impl_either!(Either3, First: A, Second: B, Third: C);
+impl_either!(Either4, First: A, Second: B, Third: C, Fourth: D);
+
+/// Guard extractor that succeeds exactly when `T` fails to extract.
+///
+/// E.g. `Not<At>` only matches messages that do not mention anyone.
+/// Note that for [`State`] the absence of the state counts as a failure, so `Not<State<S>>` matches when `S` was never added to the bot.
+pub struct Not<T>(PhantomData<fn() -> T>);
+
+#[async_trait]
+impl<T> FromEvent for Not<T>
+where
+    T: FromEvent,
+{
+    const ORDERED: bool = T::ORDERED;
+
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        match T::from_event(context, event).await {
+            Some(_) => None,
+            None => Some(Self(PhantomData)),
+        }
+    }
+}
+
+/// Extractor for `T`, extracted only once the other arguments of the handler were, for extractors with side effects.
+///
+/// The arguments of a handler are extracted concurrently, each one polled in argument order, and the handler is skipped
+/// as soon as one of them fails. `Seq` arguments instead run after all others succeeded, one by one in argument order,
+/// e.g. to only count or charge events the handler actually handles.
+///
+/// # Example
+/// ```ignore
+/// async fn quota(msg: Command<"/draw", Draw>, Seq(charge): Seq<ChargeCredits>) -> HandlerControl { ... }
+/// ```
+pub struct Seq<T>(pub T);
+
+#[async_trait]
+impl<T> FromEvent for Seq<T>
+where
+    T: FromEvent,
+{
+    const ORDERED: bool = true;
+
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        T::from_event(context, event).await.map(Self)
+    }
+}
+
+impl<T> Deref for Seq<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Extractor that succeeds only when every extractor in the tuple succeeds, e.g. `All<(MatchGroupId<123>, GroupSenderRole)>`.
+/// The extracted values are exposed in the same order.
+pub struct All<T>(pub T);
+
+macro_rules! impl_all {
+    ([$($ty:ident),*]) => {
+        #[allow(non_snake_case)]
+        #[async_trait]
+        impl<$($ty),*> FromEvent for All<($($ty,)*)>
+        where
+            $($ty: FromEvent + Send),*
+        {
+            const ORDERED: bool = false $(|| $ty::ORDERED)*;
+
+            async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+            where
+                Self: Sized,
+            {
+                $(
+                    let $ty = $ty::from_event(context.clone(), event.clone()).await?;
+                )*
+                Some(Self(($($ty,)*)))
+            }
+        }
+    };
+}
+
+impl_all!([T1, T2]);
+impl_all!([T1, T2, T3]);
+impl_all!([T1, T2, T3, T4]);
+impl_all!([T1, T2, T3, T4, T5]);
+impl_all!([T1, T2, T3, T4, T5, T6]);
+
 /// A helper macro for matching one of the variants.
 /// The macro will generate an enum with the given name and variants.
 /// The enum will implement the FromEvent trait, and will try to match the event with the given matchers.
 ///
+/// Attributes such as `#[derive(Debug)]` can be put before the name and are applied to the enum.
+/// An optional trailing `_ => Variant` arm adds a unit variant used when no matcher matches, so the extraction never fails.
+///
+/// For inline use without defining an enum, see [`Either`].
+///
 /// # Example
 /// ```no_run
-/// match_one!(MatchOne, A: AMatcher, B: BMatcher);
+/// use flow_bot::{base::extract::{MatchGroup, MatchPrivate}, match_one};
+///
+/// match_one!(MatchOne, A: MatchPrivate, B: MatchGroup, _ => Neither);
 /// ```
 /// The above code will generate an enum like this:
 /// ```no_run
+/// # use flow_bot::base::extract::{MatchGroup, MatchPrivate};
 /// pub enum MatchOne {
-///    A(AMatcher),
-///    B(BMatcher),
+///    A(MatchPrivate),
+///    B(MatchGroup),
+///    Neither,
 /// }
 /// ```
 #[macro_export]
 macro_rules! match_one {
-    ($name:ident,$($variant:ident : $matcher:ty),*) => {
+    ($(#[$meta:meta])* $name:ident, $($variant:ident : $matcher:ty),*, _ => $fallback:ident) => {
+        $(#[$meta])*
+        pub enum $name {
+            $(
+                $variant($matcher),
+            )*
+            $fallback,
+        }
+
+        #[async_trait::async_trait]
+        impl $crate::base::extract::FromEvent for $name {
+            async fn from_event(context: $crate::base::context::BotContext, event: $crate::event::BotEvent) -> Option<Self> {
+                $(
+                    if let Some(matcher) = <$matcher>::from_event(context.clone(), event.clone()).await {
+                        return Some(Self::$variant(matcher));
+                    }
+                )*
+                Some(Self::$fallback)
+            }
+        }
+    };
+
+    ($(#[$meta:meta])* $name:ident, $($variant:ident : $matcher:ty),*) => {
+        $(#[$meta])*
         pub enum $name {
             $(
                 $variant($matcher),
@@ -307,3 +910,95 @@ macro_rules! match_one {
         }
     };
 }
+
+// Extractors are moved across tasks by the handler machinery, so they must stay `Send + Sync` without manual impls.
+// Should an extractor ever need an `unsafe impl`, it must come with a `// SAFETY:` justification.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<State<()>>();
+    assert_send_sync::<MessageBody>();
+    assert_send_sync::<GroupSenderRole>();
+    assert_send_sync::<GroupSenderInfo>();
+    assert_send_sync::<PrivateSenderInfo>();
+    assert_send_sync::<BasicSenderInfo>();
+    assert_send_sync::<Sender>();
+    assert_send_sync::<At>();
+    assert_send_sync::<Mentions>();
+    assert_send_sync::<GroupId>();
+    assert_send_sync::<SenderId>();
+    assert_send_sync::<EventTime>();
+    assert_send_sync::<MatchGroupId<0>>();
+    assert_send_sync::<MatchAnyGroupId<{ &[] }>>();
+    assert_send_sync::<MatchPrivate>();
+    assert_send_sync::<MatchGroup>();
+    assert_send_sync::<MatchPrivateSubType<{ PrivateSubType::Friend }>>();
+    assert_send_sync::<MatchGroupSubType<{ GroupSubType::Normal }>>();
+    assert_send_sync::<InAllowedGroups>();
+    assert_send_sync::<NotInDeniedGroups>();
+    assert_send_sync::<RepliedMessage>();
+    assert_send_sync::<Reply>();
+    assert_send_sync::<Either4<At, GroupId, SenderId, Reply>>();
+    assert_send_sync::<Not<At>>();
+    assert_send_sync::<All<(At, GroupId)>>();
+    assert_send_sync::<()>();
+    assert_send_sync::<Voice>();
+    assert_send_sync::<Command<"/echo", ()>>();
+    assert_send_sync::<Option<At>>();
+    assert_send_sync::<Seq<(At, GroupId)>>();
+    assert_send_sync::<super::chance::Chance<500>>();
+    assert_send_sync::<super::chance::Sampled<10>>();
+    assert_send_sync::<BotContext>();
+    assert_send_sync::<super::event_context::EventContext>();
+    assert_send_sync::<super::group_config::GroupConfig<()>>();
+    assert_send_sync::<super::identity::BotIdentity>();
+    assert_send_sync::<super::kv_args::KvArgs<"">>();
+    assert_send_sync::<Result<super::kv_args::KvArgs<"">, super::kv_args::KvArgsError>>();
+    assert_send_sync::<super::storage::Storage<"">>();
+    assert_send_sync::<crate::event::BotEvent>();
+    assert_send_sync::<crate::event::RawEvent>();
+    assert_send_sync::<crate::event::message::Message>();
+    assert_send_sync::<crate::event::message::PrivateMessageInfo>();
+    assert_send_sync::<crate::event::message::GroupMessageInfo>();
+    assert_send_sync::<crate::event::message::PrivateMessageEvent>();
+    assert_send_sync::<crate::event::message::GroupMessageEvent>();
+    assert_send_sync::<crate::event::message::Anonymous>();
+    assert_send_sync::<crate::event::notice::Notice>();
+    assert_send_sync::<crate::event::notice::GroupUpload>();
+    assert_send_sync::<crate::event::notice::GroupAdmin>();
+    assert_send_sync::<crate::event::notice::GroupDecrease>();
+    assert_send_sync::<crate::event::notice::GroupIncrease>();
+    assert_send_sync::<crate::event::notice::GroupBan>();
+    assert_send_sync::<crate::event::notice::FriendAdd>();
+    assert_send_sync::<crate::event::notice::GroupRecall>();
+    assert_send_sync::<crate::event::notice::FriendRecall>();
+    assert_send_sync::<crate::event::notice::GroupMsgEmojiLike>();
+    assert_send_sync::<crate::event::notice::Notify>();
+    assert_send_sync::<crate::event::notice::Poke>();
+    assert_send_sync::<crate::event::notice::LuckyKing>();
+    assert_send_sync::<crate::event::notice::Honor>();
+    assert_send_sync::<crate::event::notice::HonorChanged>();
+    assert_send_sync::<crate::event::notice::SelfBanned>();
+    assert_send_sync::<crate::event::notice::PokedMe>();
+    assert_send_sync::<crate::event::request::Request>();
+    assert_send_sync::<crate::event::request::FriendRequest>();
+    assert_send_sync::<crate::event::request::GroupRequest>();
+    assert_send_sync::<crate::event::meta_event::MetaEvent>();
+    assert_send_sync::<crate::event::meta_event::Lifecycle>();
+    assert_send_sync::<crate::event::meta_event::Heartbeat>();
+    assert_send_sync::<crate::event::internal::InternalEvent>();
+    assert_send_sync::<crate::event::internal::Connected>();
+    assert_send_sync::<crate::event::internal::Disconnected>();
+    assert_send_sync::<crate::event::internal::Reconnecting>();
+    assert_send_sync::<crate::message::template::Lang>();
+    assert_send_sync::<crate::extensions::flood::NotFlooding>();
+    assert_send_sync::<crate::extensions::recall_cache::RecalledMessage>();
+    #[cfg(feature = "redis")]
+    assert_send_sync::<crate::extensions::redis::Redis>();
+    #[cfg(feature = "redis")]
+    assert_send_sync::<crate::extensions::redis::RedisKeyed<"">>();
+    #[cfg(feature = "sqlx-sqlite")]
+    assert_send_sync::<crate::extensions::sqlite::Db>();
+    #[cfg(feature = "turso")]
+    assert_send_sync::<crate::extensions::turso::TursoDatabase<"">>();
+};
@@ -0,0 +1,125 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+use crate::event::{Event, TypedEvent, meta_event::MetaEvent};
+
+/// A set of event types, checked before a handler is called so that it is not invoked for events it never handles.
+///
+/// Combine types with `|`, e.g. `EventFilter::MESSAGE | EventFilter::NOTICE`, and exclude them with `&` and `!`,
+/// e.g. `EventFilter::ALL & !EventFilter::HEARTBEAT`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventFilter(u32);
+
+impl EventFilter {
+    pub const MESSAGE: Self = Self(1 << 0);
+    pub const NOTICE: Self = Self(1 << 1);
+    pub const REQUEST: Self = Self(1 << 2);
+    pub const HEARTBEAT: Self = Self(1 << 3);
+    pub const LIFECYCLE: Self = Self(1 << 4);
+    /// Meta events, heartbeats and lifecycle events.
+    pub const META_EVENT: Self = Self(Self::HEARTBEAT.0 | Self::LIFECYCLE.0);
+    /// Events generated by flow-bot, see [`InternalEvent`](crate::event::internal::InternalEvent).
+    pub const INTERNAL: Self = Self(1 << 5);
+    /// Events of an unknown post type.
+    pub const UNKNOWN: Self = Self(1 << 6);
+    pub const ALL: Self = Self((1 << 7) - 1);
+    pub const NONE: Self = Self(0);
+
+    /// The type of `event`, a single flag.
+    pub fn of(event: &Event) -> Self {
+        match &event.event {
+            TypedEvent::Message(_) => Self::MESSAGE,
+            TypedEvent::Notice(_) => Self::NOTICE,
+            TypedEvent::Request(_) => Self::REQUEST,
+            TypedEvent::MetaEvent(MetaEvent::Heartbeat(_)) => Self::HEARTBEAT,
+            TypedEvent::MetaEvent(_) => Self::LIFECYCLE,
+            TypedEvent::FlowInternal(_) => Self::INTERNAL,
+            TypedEvent::Unknown(_) => Self::UNKNOWN,
+        }
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        self.contains(Self::of(event))
+    }
+
+    /// Whether every type in `other` is in the set.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl BitOr for EventFilter {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for EventFilter {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for EventFilter {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for EventFilter {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl Not for EventFilter {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0 & Self::ALL.0)
+    }
+}
+
+impl std::fmt::Debug for EventFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const NAMES: [(EventFilter, &str); 7] = [
+            (EventFilter::MESSAGE, "MESSAGE"),
+            (EventFilter::NOTICE, "NOTICE"),
+            (EventFilter::REQUEST, "REQUEST"),
+            (EventFilter::HEARTBEAT, "HEARTBEAT"),
+            (EventFilter::LIFECYCLE, "LIFECYCLE"),
+            (EventFilter::INTERNAL, "INTERNAL"),
+            (EventFilter::UNKNOWN, "UNKNOWN"),
+        ];
+
+        let mut names = NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .peekable();
+        if names.peek().is_none() {
+            return f.write_str("EventFilter(NONE)");
+        }
+        f.write_str("EventFilter(")?;
+        for (i, name) in names.enumerate() {
+            if i > 0 {
+                f.write_str(" | ")?;
+            }
+            f.write_str(name)?;
+        }
+        f.write_str(")")
+    }
+}
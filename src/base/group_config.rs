@@ -0,0 +1,88 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::event::BotEvent;
+
+use super::{
+    context::BotContext,
+    extract::{FromEvent, GroupId},
+    persistent::PersistentState,
+};
+
+/// Per-group configuration, persisted as `<key>.json` in the persistent state directory.
+///
+/// Registered with [`with_group_config`] and accessed with the `State<GroupConfigStore<T>>` extractor,
+/// or with [`GroupConfig`] for the group of the event. Groups without a stored configuration use `T::default()`.
+///
+/// [`with_group_config`]: crate::FlowBotBuilder::with_group_config
+pub struct GroupConfigStore<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    configs: PersistentState<HashMap<i64, T>>,
+}
+
+impl<T> GroupConfigStore<T>
+where
+    T: Serialize + DeserializeOwned + Default + Clone + Send + Sync + 'static,
+{
+    pub(crate) fn load(dir: &Path, key: &str) -> Self {
+        Self {
+            configs: PersistentState::load(dir, key, HashMap::new()),
+        }
+    }
+
+    pub async fn get(&self, group_id: i64) -> Arc<T> {
+        let configs = self.configs.read().await;
+        Arc::new(configs.get(&group_id).cloned().unwrap_or_default())
+    }
+
+    /// Modify the configuration of a group, returning the updated configuration.
+    pub async fn update<F>(&self, group_id: i64, f: F) -> Arc<T>
+    where
+        F: FnOnce(&mut T),
+    {
+        let mut configs = self.configs.write().await;
+        let config = configs.entry(group_id).or_default();
+        f(config);
+        Arc::new(config.clone())
+    }
+
+    /// Reset a group to the default configuration.
+    pub async fn remove(&self, group_id: i64) {
+        self.configs.write().await.remove(&group_id);
+    }
+
+    /// Write the configurations to disk now.
+    pub async fn flush(&self) -> std::io::Result<()> {
+        self.configs.flush().await
+    }
+}
+
+/// Extractor for the configuration of the group the event happened in, registered with [`with_group_config`].
+/// Events outside of groups are skipped.
+///
+/// [`with_group_config`]: crate::FlowBotBuilder::with_group_config
+pub struct GroupConfig<T>(pub Arc<T>);
+
+#[async_trait]
+impl<T> FromEvent for GroupConfig<T>
+where
+    T: Serialize + DeserializeOwned + Default + Clone + Send + Sync + 'static,
+{
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let store = context.state.get::<GroupConfigStore<T>>()?;
+        let GroupId(group_id) = GroupId::from_event(context, event).await?;
+        Some(Self(store.get(group_id).await))
+    }
+}
+
+impl<T> std::ops::Deref for GroupConfig<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -1,19 +1,72 @@
 use crate::{base::extract::FromEvent, event::BotEvent};
 use async_trait::async_trait;
-use std::{convert::Infallible, future::Future, ops::FromResidual};
+use serde::Serialize;
+use std::{borrow::Cow, cell::RefCell, convert::Infallible, future::Future, ops::FromResidual};
 
 use super::context::BotContext;
 
+/// What the dispatch loop does after a handler, see the [`because`](Self::because) constructors to also say why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HandlerControl {
     Skip,
     Continue,
     Block,
 }
 
+tokio::task_local! {
+    /// The reason given by the running handler, see [`HandlerControl::because`].
+    static REASON: RefCell<Option<Cow<'static, str>>>;
+}
+
+impl HandlerControl {
+    /// Return this control with `reason`, which is logged, counted in the metrics and passed to the control policy
+    /// as [`HandlerMeta::reason`]. The last reason given by a handler wins.
+    ///
+    /// Reasons are only recorded while a handler is dispatched, and are lost if it returns from a spawned task.
+    /// The metrics only count `&'static str` reasons by name, reasons built at runtime are counted as `dynamic`.
+    ///
+    /// ```ignore
+    /// if is_spam(&message) {
+    ///     return HandlerControl::block_because("spam filter");
+    /// }
+    /// ```
+    pub fn because(self, reason: impl Into<Cow<'static, str>>) -> Self {
+        let reason = reason.into();
+        // Outside of dispatch there is nobody to tell.
+        let _ = REASON.try_with(|slot| *slot.borrow_mut() = Some(reason));
+        self
+    }
+
+    pub fn skip_because(reason: impl Into<Cow<'static, str>>) -> Self {
+        HandlerControl::Skip.because(reason)
+    }
+
+    pub fn continue_because(reason: impl Into<Cow<'static, str>>) -> Self {
+        HandlerControl::Continue.because(reason)
+    }
+
+    pub fn block_because(reason: impl Into<Cow<'static, str>>) -> Self {
+        HandlerControl::Block.because(reason)
+    }
+}
+
+/// Run `handler`, returning its control with the reason it gave, if any.
+pub(crate) async fn with_reason<F>(handler: F) -> (HandlerControl, Option<Cow<'static, str>>)
+where
+    F: Future<Output = HandlerControl>,
+{
+    REASON
+        .scope(RefCell::new(None), async {
+            let control = handler.await;
+            (control, REASON.with(|slot| slot.borrow_mut().take()))
+        })
+        .await
+}
+
 impl<E> FromResidual<Result<Infallible, E>> for HandlerControl {
     fn from_residual(residual: Result<Infallible, E>) -> Self {
         match residual {
-            Err(_) => HandlerControl::Skip,
+            Err(_) => HandlerControl::skip_because("returned an error"),
         }
     }
 }
@@ -21,31 +74,220 @@ impl<E> FromResidual<Result<Infallible, E>> for HandlerControl {
 impl FromResidual<Option<Infallible>> for HandlerControl {
     fn from_residual(residual: Option<Infallible>) -> Self {
         match residual {
-            None => HandlerControl::Skip,
+            None => HandlerControl::skip_because("returned None"),
         }
     }
 }
 
+/// Describes the handler or service whose result is passed to a control policy, see [`with_control_policy`].
+///
+/// [`with_control_policy`]: crate::FlowBotBuilder::with_control_policy
+#[derive(Debug, Clone, Copy)]
+pub struct HandlerMeta<'a> {
+    /// Position in the registration order, counting handlers and services.
+    pub index: usize,
+    /// The type name of the handler or the name of the service.
+    pub name: &'static str,
+    /// The group given to [`with_handler_group`], if registered inside one.
+    ///
+    /// [`with_handler_group`]: crate::FlowBotBuilder::with_handler_group
+    pub group: Option<&'static str>,
+    /// Why the handler returned its control, if it said so, see [`HandlerControl::because`].
+    pub reason: Option<&'a str>,
+}
+
+/// The argument of a handler whose extractor returned `None`, so that the handler is skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ExtractorMiss {
+    /// Position of the argument, counting from zero.
+    pub argument: usize,
+    /// The type name of the extractor.
+    pub extractor: &'static str,
+}
+
+impl ExtractorMiss {
+    fn skip(self, handler: &'static str) -> HandlerControl {
+        tracing::debug!(
+            handler,
+            argument = self.argument,
+            extractor = self.extractor,
+            "Handler skipped, extractor returned None"
+        );
+        HandlerControl::skip_because("extractor returned None")
+    }
+}
+
 #[async_trait]
 pub trait Handler<T> {
     async fn handle(&self, context: BotContext, event: BotEvent) -> HandlerControl;
+
+    /// Run only the extractors of the handler, without calling it, see [`FlowBot::explain`].
+    ///
+    /// Handlers not generated from functions have no extractors to run, and are taken to match every event.
+    ///
+    /// [`FlowBot::explain`]: crate::FlowBot::explain
+    async fn check(&self, _context: BotContext, _event: BotEvent) -> Result<(), ExtractorMiss> {
+        Ok(())
+    }
+}
+
+/// Extract the arguments `$ty` of a handler, evaluating to a tuple of them,
+/// or to the [`ExtractorMiss`] of the first one that cannot be extracted.
+///
+/// Unordered extractors run concurrently, polled in argument order, so that one returning `None` right away
+/// stops the others before they do any work. Ordered extractors run afterwards one by one, see [`Seq`].
+///
+/// [`Seq`]: crate::base::extract::Seq
+macro_rules! extract_args {
+    ($context:ident, $event:ident, []) => {
+        Ok::<(), ExtractorMiss>(())
+    };
+    ($context:ident, $event:ident, [$($ty:ident),+]) => {
+        async {
+            let mut argument = 0usize;
+            $(
+                let $ty = {
+                    let (context, event, argument) = ($context.clone(), $event.clone(), argument);
+                    async move {
+                        if <$ty as FromEvent>::ORDERED {
+                            return Ok(None);
+                        }
+                        match $ty::from_event(context, event).await {
+                            Some(value) => Ok(Some(value)),
+                            None => Err(ExtractorMiss {
+                                argument,
+                                extractor: std::any::type_name::<$ty>(),
+                            }),
+                        }
+                    }
+                };
+                argument += 1;
+            )+
+            let ($($ty,)+) = futures::try_join!($($ty),+)?;
+
+            argument = 0;
+            $(
+                let $ty = match $ty {
+                    Some(value) => value,
+                    None => match $ty::from_event($context.clone(), $event.clone()).await {
+                        Some(value) => value,
+                        None => {
+                            return Err(ExtractorMiss {
+                                argument,
+                                extractor: std::any::type_name::<$ty>(),
+                            });
+                        }
+                    },
+                };
+                argument += 1;
+            )+
+            Ok(($($ty,)+))
+        }
+        .await
+    };
 }
 
 macro_rules! impl_handler {
     ([$($ty:ident),*]) => {
-        #[allow(unused_variables, unused_mut,unused_parens,non_snake_case)]
+        #[allow(unused_variables, unused_mut, unused_parens, unused_assignments, non_snake_case)]
         #[async_trait]
-        impl<F,Fut, $($ty),*> Handler<($($ty),*)> for F
+        impl<F,Fut, $($ty),*> Handler<($($ty,)*)> for F
         where
             F: Fn($($ty),*) -> Fut + Send + Sync + 'static,
             Fut: Future<Output = HandlerControl> + Send + 'static,
             $($ty: FromEvent+Send),*
         {
             async fn handle(&self, context: BotContext, event: BotEvent) -> HandlerControl {
-                match ($($ty::from_event(context.clone(), event.clone()).await,)*) {
-                    ($(Some($ty),)*) => self($($ty),*).await,
-                    _ => HandlerControl::Skip,
-                }
+                let ($($ty,)*) = match extract_args!(context, event, [$($ty),*]) {
+                    Ok(extracted) => extracted,
+                    Err(miss) => return miss.skip(std::any::type_name::<F>()),
+                };
+                self($($ty),*).await
+            }
+
+            async fn check(&self, context: BotContext, event: BotEvent) -> Result<(), ExtractorMiss> {
+                extract_args!(context, event, [$($ty),*]).map(|_| ())
+            }
+        }
+    };
+}
+
+/// A handler created with [`handler_fn`].
+pub struct HandlerFn<C, F> {
+    capture: C,
+    f: F,
+}
+
+/// Create a handler from a closure that is passed a clone of `capture` before the extracted arguments.
+///
+/// A closure returning `async move { ... }` cannot use what it captured without cloning it first,
+/// since the returned future must not borrow the closure. Passing the captured values in as the
+/// first argument avoids that.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use flow_bot::{
+///     base::{extract::MessageBody, handler::{HandlerControl, handler_fn}},
+///     message::message_ext::MessageExt,
+/// };
+/// # use flow_bot::{FlowBotBuilder, base::connect::{ReconnectionStrategy, ReverseConnectionConfig}};
+///
+/// struct Config {
+///     keyword: String,
+/// }
+///
+/// let greeting = "Hello".to_string();
+/// let config = Arc::new(Config {
+///     keyword: "ping".to_string(),
+/// });
+///
+/// # let builder = FlowBotBuilder::new(ReverseConnectionConfig {
+/// #     target: "ws://localhost:19999".to_string(),
+/// #     auth: None,
+/// #     reconnection: ReconnectionStrategy::None,
+/// # });
+/// builder
+///     .with_handler(handler_fn(greeting, |greeting: String| async move {
+///         println!("{}", greeting);
+///         HandlerControl::Continue
+///     }))
+///     .with_handler(handler_fn(
+///         config,
+///         |config: Arc<Config>, MessageBody(message): MessageBody| async move {
+///             match message.extract_plain_text().contains(&config.keyword) {
+///                 true => HandlerControl::Block,
+///                 false => HandlerControl::Skip,
+///             }
+///         },
+///     ));
+/// ```
+pub fn handler_fn<C, F>(capture: C, f: F) -> HandlerFn<C, F> {
+    HandlerFn { capture, f }
+}
+
+macro_rules! impl_handler_fn {
+    ([$($ty:ident),*]) => {
+        #[allow(unused_variables, unused_mut, unused_parens, unused_assignments, non_snake_case)]
+        #[async_trait]
+        impl<C, F, Fut, $($ty),*> Handler<(C, $($ty,)*)> for HandlerFn<C, F>
+        where
+            C: Clone + Send + Sync + 'static,
+            F: Fn(C, $($ty),*) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = HandlerControl> + Send + 'static,
+            $($ty: FromEvent+Send),*
+        {
+            async fn handle(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+                let ($($ty,)*) = match extract_args!(context, event, [$($ty),*]) {
+                    Ok(extracted) => extracted,
+                    Err(miss) => return miss.skip(std::any::type_name::<F>()),
+                };
+                (self.f)(self.capture.clone(), $($ty),*).await
+            }
+
+            async fn check(&self, context: BotContext, event: BotEvent) -> Result<(), ExtractorMiss> {
+                extract_args!(context, event, [$($ty),*]).map(|_| ())
             }
         }
     };
@@ -54,10 +296,15 @@ macro_rules! impl_handler {
 #[async_trait]
 pub(crate) trait ErasedHandler: Send + Sync {
     async fn call(&self, context: BotContext, event: BotEvent) -> HandlerControl;
+
+    async fn check(&self, context: BotContext, event: BotEvent) -> Result<(), ExtractorMiss>;
+
+    fn name(&self) -> &'static str;
 }
 
 pub(crate) struct HWrapped<T, H> {
     pub handler: H,
+    pub name: &'static str,
     pub _phantom: std::marker::PhantomData<T>,
 }
 
@@ -70,10 +317,48 @@ where
     async fn call(&self, context: BotContext, event: BotEvent) -> HandlerControl {
         self.handler.handle(context, event).await
     }
+
+    async fn check(&self, context: BotContext, event: BotEvent) -> Result<(), ExtractorMiss> {
+        self.handler.check(context, event).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// A type-erased handler, used to register handlers of different types in bulk with [`with_handlers`].
+///
+/// [`with_handlers`]: crate::FlowBotBuilder::with_handlers
+pub struct BoxedHandler(pub(crate) Box<dyn ErasedHandler>);
+
+impl BoxedHandler {
+    pub fn new<T, H>(handler: H) -> Self
+    where
+        T: Send + Sync + 'static,
+        H: Handler<T> + Send + Sync + 'static,
+    {
+        Self::named(std::any::type_name::<H>(), handler)
+    }
+
+    /// Like [`new`](Self::new), labelling the handler with `name` in logs, statistics and control policies
+    /// instead of its type name.
+    pub fn named<T, H>(name: &'static str, handler: H) -> Self
+    where
+        T: Send + Sync + 'static,
+        H: Handler<T> + Send + Sync + 'static,
+    {
+        Self(Box::new(HWrapped {
+            handler,
+            name,
+            _phantom: std::marker::PhantomData,
+        }))
+    }
 }
 
 macro_rules! all_tuples {
     ($macro:ident) => {
+        $macro!([]);
         $macro!([T1]);
         $macro!([T1, T2]);
         $macro!([T1, T2, T3]);
@@ -95,3 +380,4 @@ macro_rules! all_tuples {
 }
 
 all_tuples!(impl_handler);
+all_tuples!(impl_handler_fn);
@@ -0,0 +1,104 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::handler::HandlerControl;
+
+// Bucket `i` counts calls taking less than 2^i microseconds, the last one everything slower.
+const BUCKETS: usize = 32;
+
+/// Runtime statistics of a registered handler or service, see [`Context::handler_stats`].
+///
+/// Latencies are approximate, rounded up to a power of two microseconds.
+///
+/// [`Context::handler_stats`]: crate::base::context::Context::handler_stats
+#[derive(Debug, Clone)]
+pub struct HandlerStats {
+    /// Position in the handler chain.
+    pub index: usize,
+    pub name: &'static str,
+    pub skips: u64,
+    pub continues: u64,
+    pub blocks: u64,
+    pub panics: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+pub(crate) struct HandlerStatsCell {
+    name: &'static str,
+    skips: AtomicU64,
+    continues: AtomicU64,
+    blocks: AtomicU64,
+    panics: AtomicU64,
+    max_micros: AtomicU64,
+    latency: [AtomicU64; BUCKETS],
+}
+
+impl HandlerStatsCell {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            skips: AtomicU64::new(0),
+            continues: AtomicU64::new(0),
+            blocks: AtomicU64::new(0),
+            panics: AtomicU64::new(0),
+            max_micros: AtomicU64::new(0),
+            latency: Default::default(),
+        }
+    }
+
+    /// Record a call, `control` is `None` if the handler panicked.
+    pub(crate) fn record(&self, control: Option<&HandlerControl>, elapsed: Duration) {
+        let counter = match control {
+            Some(HandlerControl::Skip) => &self.skips,
+            Some(HandlerControl::Continue) => &self.continues,
+            Some(HandlerControl::Block) => &self.blocks,
+            None => &self.panics,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let micros = elapsed.as_micros() as u64;
+        let bucket = ((u64::BITS - micros.leading_zeros()) as usize).min(BUCKETS - 1);
+        self.latency[bucket].fetch_add(1, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, index: usize) -> HandlerStats {
+        let buckets = self
+            .latency
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        let max = Duration::from_micros(self.max_micros.load(Ordering::Relaxed));
+        HandlerStats {
+            index,
+            name: self.name,
+            skips: self.skips.load(Ordering::Relaxed),
+            continues: self.continues.load(Ordering::Relaxed),
+            blocks: self.blocks.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+            p50: percentile(&buckets, 0.5).min(max),
+            p95: percentile(&buckets, 0.95).min(max),
+            max,
+        }
+    }
+}
+
+fn percentile(buckets: &[u64], quantile: f64) -> Duration {
+    let total = buckets.iter().sum::<u64>();
+    if total == 0 {
+        return Duration::ZERO;
+    }
+    let target = (total as f64 * quantile).ceil() as u64;
+    let mut seen = 0;
+    for (bucket, count) in buckets.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return Duration::from_micros(1 << bucket);
+        }
+    }
+    Duration::MAX
+}
@@ -0,0 +1,125 @@
+use std::{
+    sync::atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use serde::{Serialize, Serializer};
+
+use super::context::BotContext;
+
+/// State of the connection to the onebot implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connected,
+    Reconnecting,
+    Down,
+}
+
+/// A snapshot of the bot health, see [`Context::health`].
+///
+/// [`Context::health`]: crate::base::context::Context::health
+#[derive(Debug, Clone, Serialize)]
+pub struct Health {
+    pub state: ConnectionState,
+    /// Time since the last frame was received, `None` if none was received yet.
+    #[serde(rename = "since_last_frame_ms", serialize_with = "as_millis")]
+    pub since_last_frame: Option<Duration>,
+    /// Time since the last heartbeat meta event, `None` if none was received yet.
+    #[serde(rename = "since_last_heartbeat_ms", serialize_with = "as_millis")]
+    pub since_last_heartbeat: Option<Duration>,
+    pub pending_requests: usize,
+    pub in_flight_handlers: usize,
+    /// Events dropped as duplicates, see [`with_event_dedup`].
+    ///
+    /// [`with_event_dedup`]: crate::FlowBotBuilder::with_event_dedup
+    pub duplicate_events: u64,
+}
+
+fn as_millis<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    duration
+        .map(|duration| duration.as_millis() as u64)
+        .serialize(serializer)
+}
+
+pub(crate) struct HealthTracker {
+    started: Instant,
+    state: AtomicU8,
+    // Milliseconds since `started` plus one, zero if never recorded.
+    last_frame: AtomicU64,
+    last_heartbeat: AtomicU64,
+    in_flight: AtomicUsize,
+    duplicates: AtomicU64,
+}
+
+impl HealthTracker {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            state: AtomicU8::new(ConnectionState::Down as u8),
+            last_frame: AtomicU64::new(0),
+            last_heartbeat: AtomicU64::new(0),
+            in_flight: AtomicUsize::new(0),
+            duplicates: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn set_state(&self, state: ConnectionState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_frame(&self) {
+        self.last_frame.store(self.now(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_heartbeat(&self) {
+        self.last_heartbeat.store(self.now(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_duplicate(&self) {
+        self.duplicates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, pending_requests: usize) -> Health {
+        let state = match self.state.load(Ordering::Relaxed) {
+            s if s == ConnectionState::Connected as u8 => ConnectionState::Connected,
+            s if s == ConnectionState::Reconnecting as u8 => ConnectionState::Reconnecting,
+            _ => ConnectionState::Down,
+        };
+        Health {
+            state,
+            since_last_frame: self.since(&self.last_frame),
+            since_last_heartbeat: self.since(&self.last_heartbeat),
+            pending_requests,
+            in_flight_handlers: self.in_flight.load(Ordering::Relaxed),
+            duplicate_events: self.duplicates.load(Ordering::Relaxed),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64 + 1
+    }
+
+    fn since(&self, recorded: &AtomicU64) -> Option<Duration> {
+        match recorded.load(Ordering::Relaxed) {
+            0 => None,
+            at => Some(Duration::from_millis(self.now().saturating_sub(at))),
+        }
+    }
+}
+
+/// Counts an event being handled for as long as it is alive, even if a handler panics.
+pub(crate) struct InFlight(BotContext);
+
+impl InFlight {
+    pub(crate) fn new(context: BotContext) -> Self {
+        context.health.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(context)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.health.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fmt, sync::Arc};
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::{api::api_ext::ApiExt, event::BotEvent};
+
+use super::{
+    context::{BotContext, Context},
+    extract::FromEvent,
+};
+
+/// Registered by [`with_identity_counts`] to also count the groups and friends of the bot.
+///
+/// [`with_identity_counts`]: crate::FlowBotBuilder::with_identity_counts
+pub(crate) struct IdentityCounts;
+
+/// Who the bot is logged in as and which implementation it is connected to, from [`Context::identity`].
+///
+/// Every field is queried with its own call and is `None` if that call failed, so that one failure does not hide the rest.
+/// Also an extractor, e.g. for an "about" command.
+///
+/// [`Context::identity`]: crate::base::context::Context::identity
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BotIdentity {
+    pub user_id: Option<i64>,
+    pub nickname: Option<String>,
+    pub app_name: Option<String>,
+    pub app_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// `None` unless enabled with [`with_identity_counts`](crate::FlowBotBuilder::with_identity_counts).
+    pub group_count: Option<usize>,
+    /// `None` unless enabled with [`with_identity_counts`](crate::FlowBotBuilder::with_identity_counts).
+    pub friend_count: Option<usize>,
+    /// The [`connection_generation`](Context::connection_generation) the identity was collected on.
+    pub generation: u64,
+}
+
+impl BotIdentity {
+    pub(crate) async fn collect(context: &Context) -> Self {
+        let counts = context.state.contains::<IdentityCounts>();
+        let (login, version, groups, friends) = tokio::join!(
+            context.get_login_info(),
+            context.get_version_info(),
+            async {
+                match counts {
+                    true => Some(context.get_group_list().await),
+                    false => None,
+                }
+            },
+            async {
+                match counts {
+                    true => Some(context.get_friend_list().await),
+                    false => None,
+                }
+            },
+        );
+
+        let mut identity = BotIdentity {
+            generation: context.connection_generation(),
+            ..Default::default()
+        };
+        match login {
+            Ok(login) => {
+                identity.user_id = Some(login.user_id);
+                identity.nickname = Some(login.nickname);
+            }
+            Err(e) => tracing::warn!("Failed to get the login info: {}", e),
+        }
+        match version {
+            Ok(version) => {
+                identity.app_name = Some(version.app_name);
+                identity.app_version = Some(version.app_version);
+                identity.protocol_version = Some(version.protocol_version);
+            }
+            Err(e) => tracing::warn!("Failed to get the version info: {}", e),
+        }
+        match groups {
+            Some(Ok(groups)) => identity.group_count = Some(groups.len()),
+            Some(Err(e)) => tracing::warn!("Failed to get the group list: {}", e),
+            None => {}
+        }
+        match friends {
+            Some(Ok(friends)) => identity.friend_count = Some(friends.len()),
+            Some(Err(e)) => tracing::warn!("Failed to get the friend list: {}", e),
+            None => {}
+        }
+        identity
+    }
+}
+
+/// The startup banner, e.g. `Bot (10001) on NapCat.Onebot 4.0, protocol v11, 12 groups, 30 friends`.
+impl fmt::Display for BotIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.user_id, &self.nickname) {
+            (Some(user_id), Some(nickname)) => write!(f, "{} ({})", nickname, user_id)?,
+            (Some(user_id), None) => write!(f, "{}", user_id)?,
+            (None, _) => write!(f, "unknown account")?,
+        }
+        match (&self.app_name, &self.app_version) {
+            (Some(name), Some(version)) => write!(f, " on {} {}", name, version)?,
+            (Some(name), None) => write!(f, " on {}", name)?,
+            (None, _) => write!(f, " on an unknown implementation")?,
+        }
+        if let Some(protocol) = &self.protocol_version {
+            write!(f, ", protocol {}", protocol)?;
+        }
+        if let Some(groups) = self.group_count {
+            write!(f, ", {} groups", groups)?;
+        }
+        if let Some(friends) = self.friend_count {
+            write!(f, ", {} friends", friends)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FromEvent for BotIdentity {
+    async fn from_event(context: BotContext, _: BotEvent) -> Option<Self> {
+        Some(Arc::unwrap_or_clone(context.identity().await))
+    }
+}
@@ -0,0 +1,30 @@
+use serde::de::DeserializeOwned;
+
+/// Deserialize a received frame, with simd-json when the `fast-json` feature is enabled.
+///
+/// Errors are always [`serde_json::Error`], so they convert into [`FlowError::JsonError`] with either backend.
+///
+/// [`FlowError::JsonError`]: crate::error::FlowError::JsonError
+pub(crate) fn from_frame<T: DeserializeOwned>(frame: &[u8]) -> Result<T, serde_json::Error> {
+    backend::from_slice(frame)
+}
+
+#[cfg(not(feature = "fast-json"))]
+mod backend {
+    use serde::de::DeserializeOwned;
+
+    pub(super) fn from_slice<T: DeserializeOwned>(frame: &[u8]) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(frame)
+    }
+}
+
+#[cfg(feature = "fast-json")]
+mod backend {
+    use serde::de::{DeserializeOwned, Error};
+
+    pub(super) fn from_slice<T: DeserializeOwned>(frame: &[u8]) -> Result<T, serde_json::Error> {
+        // simd-json parses in place, so it needs its own copy of the frame.
+        let mut frame = frame.to_vec();
+        simd_json::serde::from_slice(&mut frame).map_err(serde_json::Error::custom)
+    }
+}
@@ -0,0 +1,255 @@
+use std::{fmt::Display, str::FromStr};
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::{event::BotEvent, message::message_ext::MessageExt};
+
+use super::{
+    context::BotContext,
+    extract::{FromEvent, MessageBody},
+};
+
+/// What [`KvArgs`] does with a key given more than once, registered as a state. [`RepeatedKeys::LastWins`] by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepeatedKeys {
+    /// Keep the last value, in the place of the first one.
+    #[default]
+    LastWins,
+    /// Keep every value, in order.
+    Collect,
+    /// Fail with [`KvArgsError::RepeatedKey`].
+    Reject,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum KvArgsError {
+    #[error("Missing closing quote after {0}")]
+    UnterminatedQuote(String),
+
+    #[error("Missing key before = in {0}")]
+    EmptyKey(String),
+
+    #[error("Argument {0} must come before the key=value pairs")]
+    PositionalAfterPair(String),
+
+    #[error("Key {0} is given more than once")]
+    RepeatedKey(String),
+
+    #[error("Missing value for {0}")]
+    Missing(String),
+
+    #[error("Invalid value {value} for {key}: {reason}")]
+    Invalid {
+        key: String,
+        value: String,
+        reason: String,
+    },
+}
+
+/// Extractor for `key=value` arguments, like `/config welcome=on lang=zh`, from a plain text message.
+///
+/// Arguments without `=` are positional and must come before the pairs. Values can be quoted with `"` or `'`
+/// to hold spaces or `=`, and `\` escapes the next character in quotes.
+///
+/// With a non-empty `PREFIX` the message must start with it, and it is not part of the arguments,
+/// otherwise the command, if any, is the first positional argument. Messages with malformed arguments
+/// are skipped, extract `Result<KvArgs, KvArgsError>` to tell the user what is wrong instead.
+///
+/// ```
+/// use flow_bot::base::kv_args::{KvArgs, KvArgsError, RepeatedKeys};
+///
+/// let args: KvArgs = KvArgs::parse(r#"/config welcome=on 称呼="小 明" note='say \'hi\'' lang=zh lang=en"#).unwrap();
+/// assert_eq!(args.positional, ["/config"]);
+/// assert_eq!(args.get("称呼"), Some("小 明"));
+/// assert_eq!(args.get("note"), Some("say 'hi'"));
+/// assert_eq!(args.get("lang"), Some("en"));
+/// assert_eq!(args.get_parsed::<u32>("welcome").unwrap_err().to_string(), "Invalid value on for welcome: invalid digit found in string");
+/// assert_eq!(args.get_parsed::<u32>("level"), Ok(None));
+///
+/// let args: KvArgs = KvArgs::parse_with("tag=a tag=b", RepeatedKeys::Collect).unwrap();
+/// assert_eq!(args.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+///
+/// assert_eq!(KvArgs::<"">::parse("a=1 b"), Err(KvArgsError::PositionalAfterPair("b".to_string())));
+/// assert_eq!(KvArgs::<"">::parse("=1"), Err(KvArgsError::EmptyKey("=1".to_string())));
+/// assert_eq!(KvArgs::<"">::parse(r#"a="1 b"#), Err(KvArgsError::UnterminatedQuote(r#"a="1 b"#.to_string())));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KvArgs<const PREFIX: &'static str = ""> {
+    pub positional: Vec<String>,
+    /// The pairs in the order they were given.
+    pub pairs: Vec<(String, String)>,
+}
+
+impl<const PREFIX: &'static str> KvArgs<PREFIX> {
+    /// Parse `text`, keeping the last value of repeated keys.
+    pub fn parse(text: &str) -> Result<Self, KvArgsError> {
+        Self::parse_with(text, RepeatedKeys::default())
+    }
+
+    pub fn parse_with(text: &str, repeated: RepeatedKeys) -> Result<Self, KvArgsError> {
+        let mut args = Self::default();
+        for token in tokenize(text)? {
+            let Some((key, value)) = token.pair else {
+                if !args.pairs.is_empty() {
+                    return Err(KvArgsError::PositionalAfterPair(token.raw));
+                }
+                args.positional.push(token.text);
+                continue;
+            };
+            if key.is_empty() {
+                return Err(KvArgsError::EmptyKey(token.raw));
+            }
+            let existing = args.pairs.iter_mut().find(|(k, _)| *k == key);
+            match (existing, repeated) {
+                (Some(_), RepeatedKeys::Reject) => return Err(KvArgsError::RepeatedKey(key)),
+                (Some((_, existing)), RepeatedKeys::LastWins) => *existing = value,
+                _ => args.pairs.push((key, value)),
+            }
+        }
+        Ok(args)
+    }
+
+    /// The last value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value of `key`, only one unless parsed with [`RepeatedKeys::Collect`].
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value of `key` parsed as `T`, `None` if it is not given.
+    pub fn get_parsed<T>(&self, key: &str) -> Result<Option<T>, KvArgsError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let Some(value) = self.get(key) else {
+            return Ok(None);
+        };
+        value
+            .parse()
+            .map(Some)
+            .map_err(|e: T::Err| KvArgsError::Invalid {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// The value of `key` parsed as `T`, failing with [`KvArgsError::Missing`] if it is not given.
+    pub fn require<T>(&self, key: &str) -> Result<T, KvArgsError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get_parsed(key)?
+            .ok_or_else(|| KvArgsError::Missing(key.to_string()))
+    }
+
+    async fn extract(context: BotContext, event: BotEvent) -> Option<Result<Self, KvArgsError>> {
+        let repeated = context
+            .state
+            .get::<RepeatedKeys>()
+            .map_or_else(RepeatedKeys::default, |repeated| *repeated);
+        let message_body = MessageBody::from_event(context, event).await?;
+        let plain_text = message_body.0.extract_if_plain_text()?;
+        let mut text = plain_text.trim();
+        if !PREFIX.is_empty() {
+            text = text.strip_prefix(PREFIX)?;
+            // The prefix must be a whole word.
+            if text.chars().next().is_some_and(|c| !c.is_whitespace()) {
+                return None;
+            }
+        }
+        Some(Self::parse_with(text, repeated))
+    }
+}
+
+#[async_trait]
+impl<const PREFIX: &'static str> FromEvent for KvArgs<PREFIX> {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        Self::extract(context, event).await?.ok()
+    }
+}
+
+#[async_trait]
+impl<const PREFIX: &'static str> FromEvent for Result<KvArgs<PREFIX>, KvArgsError> {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        KvArgs::extract(context, event).await
+    }
+}
+
+struct Token {
+    /// The token as written, for errors.
+    raw: String,
+    /// The token without quotes and escapes.
+    text: String,
+    /// The key and value, split at the first `=` outside quotes.
+    pair: Option<(String, String)>,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, KvArgsError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(tokens);
+        }
+
+        let mut token = Token {
+            raw: String::new(),
+            text: String::new(),
+            pair: None,
+        };
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            match c {
+                '"' | '\'' => {
+                    token.raw.push(c);
+                    let mut escaped = false;
+                    loop {
+                        let Some(inner) = chars.next() else {
+                            return Err(KvArgsError::UnterminatedQuote(token.raw));
+                        };
+                        token.raw.push(inner);
+                        match inner {
+                            _ if escaped => {
+                                token.text.push(inner);
+                                escaped = false;
+                            }
+                            '\\' => escaped = true,
+                            end if end == c => break,
+                            inner => token.text.push(inner),
+                        }
+                    }
+                    continue;
+                }
+                '=' if token.pair.is_none() => {
+                    token.pair = Some((std::mem::take(&mut token.text), String::new()));
+                }
+                c => token.text.push(c),
+            }
+            token.raw.push(c);
+        }
+        if let Some((_, value)) = &mut token.pair {
+            *value = std::mem::take(&mut token.text);
+        }
+        tokens.push(token);
+    }
+}
@@ -1,5 +1,28 @@
+pub mod batch;
+pub mod chance;
 pub mod connect;
 pub mod context;
+pub mod dead_letter;
+pub mod debug;
+pub(crate) mod dedup;
+pub mod event_context;
+pub mod explain;
 pub mod extract;
+pub mod filter;
+pub mod group_config;
 pub mod handler;
+#[cfg(feature = "handler-stats")]
+pub mod handler_stats;
+pub mod health;
+pub mod identity;
+pub(crate) mod json;
+pub mod kv_args;
+pub mod mute;
+pub mod outbox;
+pub mod outgoing;
+pub mod persistent;
+pub mod plugin;
+pub mod policy;
+pub(crate) mod pool;
 pub mod service;
+pub mod storage;
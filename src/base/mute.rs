@@ -0,0 +1,110 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use dashmap::DashMap;
+use serde_json::Value;
+
+use super::debug::DebugState;
+use crate::{
+    error::FlowError,
+    event::{
+        Event, TypedEvent,
+        notice::{GroupBanSubType, Notice},
+    },
+};
+
+/// The groups the bot itself is muted in, kept up to date from ban notices when enabled with [`with_mute_awareness`].
+///
+/// While the bot is muted in a group, messages sent there with `send_group_msg` or `send_msg` fail with
+/// [`FlowError::SelfMuted`] without being sent. Handlers can look it up with the `State` extractor.
+/// Only bans of the bot account itself are seen, not whole group bans.
+///
+/// [`with_mute_awareness`]: crate::FlowBotBuilder::with_mute_awareness
+#[derive(Debug, Default)]
+pub struct MuteAwareness {
+    muted: DashMap<i64, SystemTime>,
+}
+
+impl MuteAwareness {
+    /// When the ban of the bot in `group_id` ends, if it is muted there.
+    pub fn muted_until(&self, group_id: i64) -> Option<SystemTime> {
+        let until = *self.muted.get(&group_id)?;
+        if until > SystemTime::now() {
+            return Some(until);
+        }
+        self.muted.remove_if(&group_id, |_, at| *at == until);
+        None
+    }
+
+    pub fn is_muted(&self, group_id: i64) -> bool {
+        self.muted_until(group_id).is_some()
+    }
+
+    /// The groups the bot is muted in, with the unix timestamp in seconds of the end of the ban.
+    fn muted_groups(&self) -> Vec<(i64, u64)> {
+        let mut muted = self
+            .muted
+            .iter()
+            .filter(|entry| *entry.value() > SystemTime::now())
+            .map(|entry| {
+                let until = entry.value().duration_since(UNIX_EPOCH).unwrap_or_default();
+                (*entry.key(), until.as_secs())
+            })
+            .collect::<Vec<_>>();
+        muted.sort_unstable();
+        muted
+    }
+
+    /// Record bans and lifts of the bot account.
+    pub(crate) fn observe(&self, event: &Event) {
+        let TypedEvent::Notice(Notice::GroupBan(ban)) = &event.event else {
+            return;
+        };
+        if ban.user_id != event.self_id {
+            return;
+        }
+        match ban.sub_type {
+            GroupBanSubType::Ban if ban.duration > 0 => {
+                tracing::warn!(
+                    "Muted in group {} for {}s by {}",
+                    ban.group_id,
+                    ban.duration,
+                    ban.operator_id
+                );
+                let until = SystemTime::now() + Duration::from_secs(ban.duration as u64);
+                self.muted.insert(ban.group_id, until);
+            }
+            _ => {
+                if self.muted.remove(&ban.group_id).is_some() {
+                    tracing::info!("Unmuted in group {}", ban.group_id);
+                }
+            }
+        }
+    }
+
+    /// Fail sends of `action` to a group the bot is muted in.
+    pub(crate) fn check(&self, action: &str, params: &Value) -> Result<(), FlowError> {
+        let to_group = match action {
+            "send_group_msg" => true,
+            // `send_msg` may leave the type to be guessed from the ids.
+            "send_msg" => match params.get("message_type").and_then(Value::as_str) {
+                Some(message_type) => message_type == "group",
+                None => true,
+            },
+            _ => false,
+        };
+        let group_id = params.get("group_id").and_then(Value::as_i64);
+        match (to_group, group_id) {
+            (true, Some(group_id)) => match self.muted_until(group_id) {
+                Some(until) => Err(FlowError::SelfMuted { group_id, until }),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DebugState for MuteAwareness {
+    fn debug_json(&self) -> Value {
+        serde_json::json!({ "muted_until": self.muted_groups() })
+    }
+}
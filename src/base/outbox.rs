@@ -0,0 +1,150 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::Utf8Bytes;
+
+/// Which actions are buffered by the outbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxActions {
+    /// Only actions sending something, those whose name starts with `send_`.
+    Send,
+    All,
+}
+
+type ExpiredCallback = Arc<dyn Fn(&str, Duration) + Send + Sync>;
+
+/// Configuration of the outbox buffering API calls while the bot is disconnected,
+/// see [`with_offline_buffering`].
+///
+/// Buffered calls are sent in order once the bot reconnects, and their response timeout starts when they are actually sent.
+/// Calls waiting longer than `max_age`, or pushed out of a full outbox, fail with [`FlowError::NoConnection`].
+///
+/// [`with_offline_buffering`]: crate::FlowBotBuilder::with_offline_buffering
+/// [`FlowError::NoConnection`]: crate::error::FlowError::NoConnection
+#[derive(Clone)]
+pub struct OutboxConfig {
+    capacity: usize,
+    max_age: Duration,
+    actions: OutboxActions,
+    on_dropped: Option<ExpiredCallback>,
+}
+
+impl OutboxConfig {
+    pub fn new(capacity: usize, max_age: Duration) -> Self {
+        Self {
+            capacity,
+            max_age,
+            actions: OutboxActions::Send,
+            on_dropped: None,
+        }
+    }
+
+    /// Set which actions are buffered, [`OutboxActions::Send`] by default.
+    pub fn with_actions(mut self, actions: OutboxActions) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    /// Set a callback called with the action name and age of every buffered call that is dropped.
+    pub fn on_dropped<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str, Duration) + Send + Sync + 'static,
+    {
+        self.on_dropped = Some(Arc::new(callback));
+        self
+    }
+}
+
+pub(crate) struct Entry {
+    action: String,
+    pub(crate) text: Utf8Bytes,
+    enqueued: Instant,
+    sent: oneshot::Sender<()>,
+}
+
+impl Entry {
+    /// Tell the waiting caller the frame was sent, its response timeout starts now.
+    pub(crate) fn mark_sent(self) {
+        let _ = self.sent.send(());
+    }
+}
+
+pub(crate) struct Outbox {
+    config: OutboxConfig,
+    queue: Mutex<VecDeque<Entry>>,
+}
+
+impl Outbox {
+    pub(crate) fn new(config: OutboxConfig) -> Self {
+        Self {
+            config,
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn accepts(&self, action: &str) -> bool {
+        match self.config.actions {
+            OutboxActions::Send => action.starts_with("send_"),
+            OutboxActions::All => true,
+        }
+    }
+
+    pub(crate) fn max_age(&self) -> Duration {
+        self.config.max_age
+    }
+
+    /// Buffer a frame, the receiver resolves once it is sent and errors if it is dropped.
+    pub(crate) fn push(&self, action: &str, text: Utf8Bytes) -> oneshot::Receiver<()> {
+        let (sent, rx) = oneshot::channel();
+        let overflow = {
+            let mut queue = self.queue.lock().unwrap();
+            queue.push_back(Entry {
+                action: action.to_string(),
+                text,
+                enqueued: Instant::now(),
+                sent,
+            });
+            if queue.len() > self.config.capacity {
+                queue.pop_front()
+            } else {
+                None
+            }
+        };
+        if let Some(entry) = overflow {
+            tracing::warn!("Outbox is full, dropping buffered {}", entry.action);
+            self.dropped(entry);
+        }
+        rx
+    }
+
+    /// Take every buffered frame that has not expired, in order.
+    pub(crate) fn drain(&self) -> Vec<Entry> {
+        let entries = std::mem::take(&mut *self.queue.lock().unwrap());
+        let (fresh, expired): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|entry| entry.enqueued.elapsed() <= self.config.max_age);
+        for entry in expired {
+            tracing::warn!("Buffered {} expired before reconnecting", entry.action);
+            self.dropped(entry);
+        }
+        fresh
+    }
+
+    /// Put back frames that could not be sent, ahead of anything buffered since.
+    pub(crate) fn requeue(&self, entries: impl IntoIterator<Item = Entry>) {
+        let mut queue = self.queue.lock().unwrap();
+        let mut entries = entries.into_iter().collect::<VecDeque<_>>();
+        entries.append(&mut queue);
+        *queue = entries;
+    }
+
+    fn dropped(&self, entry: Entry) {
+        if let Some(callback) = &self.config.on_dropped {
+            callback(&entry.action, entry.enqueued.elapsed());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+use crate::{
+    error::FlowError,
+    message::{
+        Message,
+        segments::{Segment, TextSegment},
+    },
+};
+
+/// Actions whose messages pass through the outgoing hooks.
+const SEND_ACTIONS: &[&str] = &["send_private_msg", "send_group_msg", "send_msg"];
+
+/// Where an [`OutgoingMessage`] is sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutgoingTarget {
+    Private(i64),
+    Group(i64),
+    /// The params did not say, the implementation will likely reject the call.
+    Unknown,
+}
+
+/// A message about to be sent, passed to the hooks registered with [`with_outgoing_hook`].
+///
+/// Messages sent as CQ code strings are seen as a single text segment, and sent as a string again
+/// if the hooks only left text segments in it.
+///
+/// [`with_outgoing_hook`]: crate::FlowBotBuilder::with_outgoing_hook
+#[derive(Debug, Clone)]
+pub struct OutgoingMessage {
+    /// The send action, changing it or the target has no effect.
+    pub action: String,
+    pub target: OutgoingTarget,
+    pub message: Message,
+}
+
+/// What an outgoing hook decided for a message.
+pub enum HookResult {
+    /// Send the message, as changed by the hook. Later hooks see the changes.
+    Proceed(OutgoingMessage),
+    /// Do not send the message, the call fails with [`FlowError::MessageDropped`].
+    Drop,
+    /// Do not send the message, the call fails with this error.
+    Fail(FlowError),
+}
+
+type HookFn = dyn Fn(OutgoingMessage) -> BoxFuture<'static, HookResult> + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct OutgoingHook(Arc<HookFn>);
+
+impl OutgoingHook {
+    pub(crate) fn new<F, Fut>(hook: F) -> Self
+    where
+        F: Fn(OutgoingMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HookResult> + Send + 'static,
+    {
+        Self(Arc::new(move |message| Box::pin(hook(message))))
+    }
+}
+
+/// The hooks registered on the builder, stored as a state in registration order.
+pub(crate) struct OutgoingHooks(pub(crate) Vec<OutgoingHook>);
+
+impl OutgoingHooks {
+    /// Run the hooks on the params of `action`, changing them to the params to send.
+    /// Params of other actions, or without a message, are left as they are.
+    pub(crate) async fn apply(&self, action: &str, params: &mut Value) -> Result<(), FlowError> {
+        if !SEND_ACTIONS.contains(&action) {
+            return Ok(());
+        }
+        let (message, raw) = match params.get("message") {
+            Some(Value::String(raw)) => {
+                (vec![Segment::Text(TextSegment { text: raw.clone() })], true)
+            }
+            Some(message) => (serde_json::from_value(message.clone())?, false),
+            None => return Ok(()),
+        };
+
+        let group_id = params.get("group_id").and_then(Value::as_i64);
+        let user_id = params.get("user_id").and_then(Value::as_i64);
+        let target = match (action, params.get("message_type").and_then(Value::as_str)) {
+            ("send_group_msg", _) | (_, Some("group")) => group_id.map(OutgoingTarget::Group),
+            ("send_private_msg", _) | (_, Some("private")) => user_id.map(OutgoingTarget::Private),
+            // `send_msg` may leave the type to be guessed from the ids.
+            _ => group_id
+                .map(OutgoingTarget::Group)
+                .or(user_id.map(OutgoingTarget::Private)),
+        };
+        let target = target.unwrap_or(OutgoingTarget::Unknown);
+
+        let mut outgoing = OutgoingMessage {
+            action: action.to_string(),
+            target,
+            message,
+        };
+        for hook in &self.0 {
+            outgoing = match (hook.0)(outgoing).await {
+                HookResult::Proceed(outgoing) => outgoing,
+                HookResult::Drop => return Err(FlowError::MessageDropped),
+                HookResult::Fail(e) => return Err(e),
+            };
+        }
+
+        let texts: Option<String> = outgoing
+            .message
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        params["message"] = match (raw, texts) {
+            (true, Some(text)) => Value::String(text),
+            _ => serde_json::to_value(&outgoing.message)?,
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const FLUSH_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// State that is persisted as a json file and restored on the next start.
+///
+/// Registered with [`with_persistent_state`] and accessed with the `State<PersistentState<T>>` extractor.
+/// Changes made through [`write`] are flushed to disk shortly after the guard is dropped, and when the state itself is dropped.
+///
+/// [`with_persistent_state`]: crate::FlowBotBuilder::with_persistent_state
+/// [`write`]: PersistentState::write
+pub struct PersistentState<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    path: PathBuf,
+    value: RwLock<T>,
+    dirty: AtomicBool,
+    flush_scheduled: AtomicBool,
+    flush_lock: Mutex<()>,
+}
+
+impl<T> PersistentState<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Load the state stored in `dir` under `key`.
+    /// Falls back to `default` if the file is missing or corrupted.
+    pub(crate) fn load(dir: &Path, key: &str, default: T) -> Self {
+        let path = dir.join(format!("{}.json", key));
+        let value = match std::fs::read(&path) {
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(value) => value,
+                Err(e) => {
+                    tracing::warn!(
+                        "Corrupted persistent state {}, falling back to default: {}",
+                        path.display(),
+                        e
+                    );
+                    default
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => default,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to read persistent state {}, falling back to default: {}",
+                    path.display(),
+                    e
+                );
+                default
+            }
+        };
+
+        Self {
+            inner: Arc::new(Inner {
+                path,
+                value: RwLock::new(value),
+                dirty: AtomicBool::new(false),
+                flush_scheduled: AtomicBool::new(false),
+                flush_lock: Mutex::new(()),
+            }),
+        }
+    }
+}
+
+impl<T> PersistentState<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.inner.value.read().await
+    }
+
+    /// Lock the state for writing. The state is flushed to disk after the guard is dropped.
+    pub async fn write(&self) -> PersistentWriteGuard<'_, T> {
+        PersistentWriteGuard {
+            guard: self.inner.value.write().await,
+            state: self,
+        }
+    }
+
+    /// Write the state to disk now.
+    pub async fn flush(&self) -> std::io::Result<()> {
+        self.inner.flush().await
+    }
+
+    fn schedule_flush(&self) {
+        self.inner.dirty.store(true, Ordering::SeqCst);
+        if self.inner.flush_scheduled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(FLUSH_DEBOUNCE).await;
+            inner.flush_scheduled.store(false, Ordering::SeqCst);
+            if let Err(e) = inner.flush().await {
+                tracing::error!(
+                    "Failed to flush persistent state {}: {}",
+                    inner.path.display(),
+                    e
+                );
+            }
+        });
+    }
+}
+
+impl<T> Inner<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    async fn flush(&self) -> std::io::Result<()> {
+        let _flushing = self.flush_lock.lock().await;
+        self.dirty.store(false, Ordering::SeqCst);
+        let data = serde_json::to_vec_pretty(&*self.value.read().await)?;
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || write_atomic(&path, &data))
+            .await
+            .map_err(std::io::Error::other)?
+    }
+}
+
+impl<T> Drop for Inner<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        if !*self.dirty.get_mut() {
+            return;
+        }
+        let result = serde_json::to_vec_pretty(self.value.get_mut())
+            .map_err(std::io::Error::from)
+            .and_then(|data| write_atomic(&self.path, &data));
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to flush persistent state {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Write to a temporary file first and rename it, so readers never see a partially written file.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
+
+pub struct PersistentWriteGuard<'a, T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    guard: RwLockWriteGuard<'a, T>,
+    state: &'a PersistentState<T>,
+}
+
+impl<T> Deref for PersistentWriteGuard<'_, T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for PersistentWriteGuard<'_, T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for PersistentWriteGuard<'_, T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        self.state.schedule_flush();
+    }
+}
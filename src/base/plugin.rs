@@ -0,0 +1,21 @@
+use crate::FlowBotBuilder;
+
+/// A set of handlers, services and states installed together, e.g. by a third-party crate.
+///
+/// States added by a plugin never replace states of the same type that are already registered, a warning is logged instead.
+///
+/// # Example
+/// ```ignore
+/// struct Greeter;
+///
+/// impl Plugin for Greeter {
+///     fn install(self, builder: FlowBotBuilder) -> FlowBotBuilder {
+///         builder.with_state(GreeterConfig::default()).with_handler(greet)
+///     }
+/// }
+///
+/// let bot = FlowBotBuilder::new(config).with_plugin(Greeter).build();
+/// ```
+pub trait Plugin {
+    fn install(self, builder: FlowBotBuilder) -> FlowBotBuilder;
+}
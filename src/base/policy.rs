@@ -0,0 +1,88 @@
+use super::debug::DebugState;
+use crate::error::FlowError;
+
+/// Which API actions the bot may call, registered with [`with_action_policy`].
+///
+/// Patterns are action names in which `*` matches any run of characters, e.g. `set_group_*`.
+/// Calls of other actions fail with [`FlowError::ActionDenied`] without being sent, whichever handler makes them.
+///
+/// ```
+/// use flow_bot::base::policy::ActionPolicy;
+///
+/// let policy = ActionPolicy::deny(["set_group_kick", "set_group_*ban", "set_group_leave"]);
+/// assert!(policy.permits("send_group_msg"));
+/// assert!(!policy.permits("set_group_whole_ban"));
+/// ```
+///
+/// [`with_action_policy`]: crate::FlowBotBuilder::with_action_policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionPolicy {
+    /// Only actions matching one of the patterns may be called.
+    Allow(Vec<String>),
+    /// Actions matching one of the patterns may not be called.
+    Deny(Vec<String>),
+}
+
+impl ActionPolicy {
+    pub fn allow<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::Allow(patterns.into_iter().map(Into::into).collect())
+    }
+
+    pub fn deny<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::Deny(patterns.into_iter().map(Into::into).collect())
+    }
+
+    /// Whether `action` may be called.
+    pub fn permits(&self, action: &str) -> bool {
+        match self {
+            ActionPolicy::Allow(patterns) => patterns.iter().any(|p| glob_match(p, action)),
+            ActionPolicy::Deny(patterns) => !patterns.iter().any(|p| glob_match(p, action)),
+        }
+    }
+
+    pub(crate) fn check(&self, action: &str) -> Result<(), FlowError> {
+        match self.permits(action) {
+            true => Ok(()),
+            false => Err(FlowError::ActionDenied(action.to_string())),
+        }
+    }
+}
+
+impl DebugState for ActionPolicy {
+    fn debug_json(&self) -> serde_json::Value {
+        match self {
+            ActionPolicy::Allow(patterns) => serde_json::json!({ "allow": patterns }),
+            ActionPolicy::Deny(patterns) => serde_json::json!({ "deny": patterns }),
+        }
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(prefix) else {
+        return false;
+    };
+    let mut parts = rest.split('*').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            // The last part is anchored to the end of the text.
+            return text.ends_with(part);
+        }
+        match text.find(part) {
+            Some(at) => text = &text[at + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,55 @@
+use tokio::{
+    runtime::{Builder, Handle},
+    sync::oneshot,
+    task::JoinHandle,
+};
+
+/// Where handlers registered with [`with_blocking_handler`] run, away from the runtime reading the connection.
+///
+/// [`with_blocking_handler`]: crate::FlowBotBuilder::with_blocking_handler
+pub(crate) enum HandlerPool {
+    /// On the blocking threads of the main runtime.
+    Blocking,
+    /// On a dedicated runtime, which lives on its own thread until the pool is dropped.
+    Runtime {
+        handle: Handle,
+        _stop: oneshot::Sender<()>,
+    },
+}
+
+impl HandlerPool {
+    pub(crate) fn new(threads: Option<usize>) -> std::io::Result<Self> {
+        let Some(threads) = threads else {
+            return Ok(Self::Blocking);
+        };
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(threads.max(1))
+            .thread_name("flow-bot-handler")
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+        let (stop, stopped) = oneshot::channel::<()>();
+        // Runtimes cannot be dropped from async code, so it is owned by a thread of its own.
+        std::thread::Builder::new()
+            .name("flow-bot-handler-runtime".to_string())
+            .spawn(move || runtime.block_on(async move { stopped.await.ok() }))?;
+        Ok(Self::Runtime {
+            handle,
+            _stop: stop,
+        })
+    }
+
+    pub(crate) fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self {
+            HandlerPool::Blocking => {
+                let handle = Handle::current();
+                tokio::task::spawn_blocking(move || handle.block_on(future))
+            }
+            HandlerPool::Runtime { handle, .. } => handle.spawn(future),
+        }
+    }
+}
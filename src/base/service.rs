@@ -1,9 +1,31 @@
+use std::{
+    any::{Any, TypeId},
+    sync::Arc,
+};
+
 use async_trait::async_trait;
 
 use crate::event::BotEvent;
 
 use super::{context::BotContext, handler::HandlerControl};
 
+/// A state a service needs, see [`Service::required_states`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequiredState {
+    pub type_id: TypeId,
+    pub name: &'static str,
+}
+
+impl RequiredState {
+    /// The state `S`, as registered with [`with_state`](crate::FlowBotBuilder::with_state).
+    pub fn of<S: Any>() -> Self {
+        Self {
+            type_id: TypeId::of::<S>(),
+            name: std::any::type_name::<S>(),
+        }
+    }
+}
+
 #[async_trait]
 pub trait Service: Send + Sync {
     /// Extractors are not possible to be used in services but you can call [`FromEvent::from_event`] manually.
@@ -11,6 +33,83 @@ pub trait Service: Send + Sync {
     /// [`FromEvent::from_event`]: crate::base::extract::FromEvent::from_event
     async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl;
 
+    /// Called once, when the bot first connected. See [`on_reconnect`](Self::on_reconnect) for later connections.
+    ///
+    /// Responses to API calls are only received once every service is initialized, so calls should be made from a spawned task.
     #[allow(unused_variables)]
     async fn init(&self, bot: BotContext) {}
+
+    /// Called when the bot connected again after losing the connection,
+    /// `attempt` is the number of failed attempts before it. The same applies to API calls as for [`init`](Self::init).
+    #[allow(unused_variables)]
+    async fn on_reconnect(&self, bot: BotContext, attempt: u32) {}
+
+    /// Called when the connection is lost, before any reconnection is attempted. API calls fail until the bot reconnects.
+    #[allow(unused_variables)]
+    async fn on_disconnect(&self, bot: BotContext) {}
+
+    /// Called with frames that could not be parsed as an event, which are not passed to any handler.
+    #[allow(unused_variables)]
+    async fn on_parse_error(&self, bot: BotContext, raw: Arc<str>, error: String) {}
+
+    /// Called once the bot stopped after [`Context::shutdown`], before [`FlowBot::run`] returns.
+    /// Background work should be finished here, e.g. by flushing what is still buffered.
+    ///
+    /// [`Context::shutdown`]: crate::base::context::Context::shutdown
+    /// [`FlowBot::run`]: crate::FlowBot::run
+    #[allow(unused_variables)]
+    async fn on_shutdown(&self, bot: BotContext) {}
+
+    /// Name used in logs and handler statistics, the type name by default.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// States the service cannot work without, which [`validate`] reports when they are not registered.
+    ///
+    /// Persistent states are required as `PersistentState<S>`, as handlers extract them.
+    ///
+    /// [`validate`]: crate::FlowBotBuilder::validate
+    fn required_states(&self) -> Vec<RequiredState> {
+        Vec::new()
+    }
+}
+
+/// Allows registering a service that is also kept elsewhere, e.g. as a state for handlers to call its methods.
+#[async_trait]
+impl<S> Service for Arc<S>
+where
+    S: Service + ?Sized,
+{
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        (**self).serve(context, event).await
+    }
+
+    async fn init(&self, bot: BotContext) {
+        (**self).init(bot).await
+    }
+
+    async fn on_reconnect(&self, bot: BotContext, attempt: u32) {
+        (**self).on_reconnect(bot, attempt).await
+    }
+
+    async fn on_disconnect(&self, bot: BotContext) {
+        (**self).on_disconnect(bot).await
+    }
+
+    async fn on_parse_error(&self, bot: BotContext, raw: Arc<str>, error: String) {
+        (**self).on_parse_error(bot, raw, error).await
+    }
+
+    async fn on_shutdown(&self, bot: BotContext) {
+        (**self).on_shutdown(bot).await
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn required_states(&self) -> Vec<RequiredState> {
+        (**self).required_states()
+    }
 }
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{error::FlowError, event::BotEvent};
+
+use super::{context::BotContext, extract::FromEvent, persistent::PersistentState};
+
+/// The file handler storage is persisted in, in the persistent state directory.
+const STORAGE_KEY: &str = "handler_storage";
+
+/// Small persistent values of handlers, one json value per key, accessed with the [`Storage`] extractor.
+///
+/// Stored as `handler_storage.json` in the persistent state directory. It is always available,
+/// without registering a state type.
+pub struct HandlerStorage {
+    values: PersistentState<HashMap<String, serde_json::Value>>,
+}
+
+impl HandlerStorage {
+    pub(crate) fn load(dir: &Path) -> Self {
+        Self {
+            values: PersistentState::load(dir, STORAGE_KEY, HashMap::new()),
+        }
+    }
+
+    /// The value stored under `key`, `None` if nothing is stored.
+    /// Fails if the stored value is not a `T`.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, FlowError> {
+        let values = self.values.read().await;
+        match values.get(key) {
+            Some(value) => Ok(Some(T::deserialize(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store `value` under `key`, replacing what was stored. Fails if `value` cannot be serialized to json.
+    ///
+    /// Use [`update`](Self::update) to change the stored value, a `get` followed by a `set` loses concurrent changes.
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<(), FlowError> {
+        let value = serde_json::to_value(value)?;
+        self.values.write().await.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Modify the value stored under `key`, starting from `T::default()` if nothing is stored,
+    /// returning the new value. The storage is locked meanwhile, so concurrent updates are not lost.
+    pub async fn update<T, F>(&self, key: &str, f: F) -> Result<T, FlowError>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T),
+    {
+        let mut values = self.values.write().await;
+        let mut value = match values.get(key) {
+            Some(value) => T::deserialize(value)?,
+            None => T::default(),
+        };
+        f(&mut value);
+        values.insert(key.to_string(), serde_json::to_value(&value)?);
+        Ok(value)
+    }
+
+    /// Remove the value stored under `key`, a later [`get`](Self::get) returns `None`. Does nothing if nothing is stored.
+    pub async fn remove(&self, key: &str) {
+        self.values.write().await.remove(key);
+    }
+
+    /// Write the storage to disk now.
+    pub async fn flush(&self) -> std::io::Result<()> {
+        self.values.flush().await
+    }
+}
+
+/// Extractor for the value stored under `KEY` in the [`HandlerStorage`], e.g. a counter of a handler.
+///
+/// Handlers using different keys do not see each other's values.
+///
+/// # Example
+/// ```ignore
+/// async fn count(ctx: BotContext, msg: Message, _: Command<"/count", Count>, storage: Storage<"count">) -> HandlerControl {
+///     let Ok(count) = storage.update(|count: &mut u64| *count += 1).await else {
+///         return HandlerControl::Skip;
+///     };
+///     ...
+/// }
+/// ```
+pub struct Storage<const KEY: &'static str> {
+    storage: Arc<HandlerStorage>,
+}
+
+impl<const KEY: &'static str> Storage<KEY> {
+    /// The stored value, `None` if nothing is stored. Fails if the stored value is not a `T`.
+    pub async fn get<T: DeserializeOwned>(&self) -> Result<Option<T>, FlowError> {
+        self.storage.get(KEY).await
+    }
+
+    /// Replace the stored value, see [`HandlerStorage::set`].
+    pub async fn set<T: Serialize>(&self, value: &T) -> Result<(), FlowError> {
+        self.storage.set(KEY, value).await
+    }
+
+    /// Modify the stored value, see [`HandlerStorage::update`].
+    pub async fn update<T, F>(&self, f: F) -> Result<T, FlowError>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T),
+    {
+        self.storage.update(KEY, f).await
+    }
+
+    /// Remove the stored value, see [`HandlerStorage::remove`].
+    pub async fn remove(&self) {
+        self.storage.remove(KEY).await
+    }
+}
+
+#[async_trait]
+impl<const KEY: &'static str> FromEvent for Storage<KEY> {
+    async fn from_event(context: BotContext, _: BotEvent) -> Option<Self> {
+        let storage = context.state.get::<HandlerStorage>()?;
+        Some(Self { storage })
+    }
+}
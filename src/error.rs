@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::api::{SendMessageResponse, retcode::RetCode};
+
 #[derive(Error, Debug)]
 pub enum FlowError {
     #[error("Cannot apply extractor {extractor} to event {event}")]
@@ -14,6 +16,9 @@ pub enum FlowError {
     #[error("Json error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("Io error: {0}")]
+    IoError(#[from] std::io::Error),
+
     #[error("No connection")]
     NoConnection,
 
@@ -26,7 +31,173 @@ pub enum FlowError {
     #[error("Reconnection failed after {0} attempts")]
     ReconnectionFailed(u32),
 
+    /// The onebot implementation closed the connection, `code` is 1005 if it gave none.
+    #[error("Connection closed with code {code}: {reason}")]
+    ConnectionClosed { code: u16, reason: String },
+
+    /// A failed API call, `params_digest` is a short rendering of its params with credentials redacted,
+    /// see [`ParamRedaction`](crate::api::digest::ParamRedaction).
+    ///
+    /// Every call made through [`Context`](crate::base::context::Context) fails with this variant,
+    /// use [`root_cause`](Self::root_cause) to match on the error behind it.
+    #[error("{action}({params_digest}) {}", describe_call_failure(source))]
+    ApiCall {
+        action: String,
+        source: Box<FlowError>,
+        params_digest: String,
+    },
+
+    /// The implementation answered a call with a failed `retcode`, `message` says why if it told.
+    #[error("Api call failed with retcode {retcode}{}", describe_message(message))]
+    Api {
+        retcode: RetCode,
+        message: Option<String>,
+    },
+
+    /// A chunked send failed after `sent` were sent, out of `total` chunks, see
+    /// [`send_group_message_chunked`](crate::api::api_ext::ApiExt::send_group_message_chunked).
+    #[error("Sent {} of {total} chunks before failing: {source}", sent.len())]
+    PartiallySent {
+        sent: Vec<SendMessageResponse>,
+        total: usize,
+        source: Box<FlowError>,
+    },
+
+    #[error("Action {0} is denied by the action policy")]
+    ActionDenied(String),
+
+    #[error("The message was dropped by an outgoing hook")]
+    MessageDropped,
+
+    #[error("The bot is muted in group {group_id}")]
+    SelfMuted {
+        group_id: i64,
+        until: std::time::SystemTime,
+    },
+
+    #[error("The event did not happen in a group")]
+    NotInGroup,
+
+    #[error("The anonymous sender has no flag to ban it with")]
+    NoAnonymousFlag,
+
+    #[error("Download exceeds the limit of {0} bytes")]
+    TooLarge(u64),
+
+    #[error("Download failed: {0}")]
+    FetchFailed(String),
+
+    #[cfg(feature = "redis")]
+    #[error("Redis error: {0}")]
+    RedisError(#[from] redis::RedisError),
+
+    #[cfg(feature = "sqlx-sqlite")]
+    #[error("Sqlite error: {0}")]
+    SqliteError(#[from] sqlx::Error),
+
     #[cfg(feature = "turso")]
     #[error("Turso error: {0}")]
     TursoError(#[from] turso::Error),
 }
+
+impl FlowError {
+    /// The error behind [`FlowError::ApiCall`] and [`FlowError::PartiallySent`], or the error itself.
+    pub fn root_cause(&self) -> &FlowError {
+        match self {
+            FlowError::ApiCall { source, .. } | FlowError::PartiallySent { source, .. } => {
+                source.root_cause()
+            }
+            e => e,
+        }
+    }
+
+    /// Whether the onebot implementation refused the credentials of the bot, which reconnecting cannot fix.
+    ///
+    /// That is a 401 or 403 response to the handshake, or a close with code 1008 (policy violation)
+    /// or 4001 or 4003, used by some implementations for a wrong or missing token.
+    /// Calls answered with retcode 1401 or 1403 are auth failures too.
+    pub fn is_auth_failure(&self) -> bool {
+        match self {
+            FlowError::ConnectionClosed { code, .. } => matches!(code, 1008 | 4001 | 4003),
+            FlowError::WebSocketError(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                matches!(response.status().as_u16(), 401 | 403)
+            }
+            FlowError::Api { retcode, .. } => retcode.is_auth_failure(),
+            FlowError::ApiCall { source, .. } | FlowError::PartiallySent { source, .. } => {
+                source.is_auth_failure()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// What happened to a call, following its action and params in [`FlowError::ApiCall`].
+fn describe_call_failure(error: &FlowError) -> String {
+    match error {
+        FlowError::Timeout(millis) if millis % 1000 == 0 => {
+            format!("timed out after {}s", millis / 1000)
+        }
+        FlowError::Timeout(millis) => format!("timed out after {}ms", millis),
+        FlowError::NoConnection => "failed without a connection".to_string(),
+        FlowError::NoResponse => "got no response".to_string(),
+        FlowError::ActionDenied(_) => "is denied by the action policy".to_string(),
+        FlowError::MessageDropped => "was dropped by an outgoing hook".to_string(),
+        FlowError::Api { retcode, message } => {
+            format!(
+                "failed with retcode {}{}",
+                retcode,
+                describe_message(message)
+            )
+        }
+        e => format!("failed: {}", e),
+    }
+}
+
+fn describe_message(message: &Option<String>) -> String {
+    match message {
+        Some(message) if !message.is_empty() => format!(": {}", message),
+        _ => String::new(),
+    }
+}
+
+/// A problem found by [`FlowBotBuilder::validate`].
+///
+/// [`FlowBotBuilder::validate`]: crate::FlowBotBuilder::validate
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    #[error("Connection target is empty")]
+    EmptyTarget,
+
+    #[error("Connection target {0} is not a ws:// or wss:// uri")]
+    InvalidTarget(String),
+
+    #[error("Auth is not a valid header value")]
+    InvalidAuth,
+
+    #[error("Invalid reconnection strategy: {0}")]
+    InvalidReconnection(&'static str),
+
+    #[error("No handler or service is registered")]
+    NoHandlers,
+
+    #[error("State {0} is registered more than once, only the last one is kept")]
+    DuplicateState(&'static str),
+
+    #[error("Service {service} requires state {state}, which is not registered")]
+    MissingState {
+        service: &'static str,
+        state: &'static str,
+    },
+
+    #[error("Failed to start the runtime for blocking handlers: {0}")]
+    HandlerRuntime(String),
+}
+
+impl BuildError {
+    /// Warnings do not prevent [`try_build`] from building the bot.
+    ///
+    /// [`try_build`]: crate::FlowBotBuilder::try_build
+    pub fn is_warning(&self) -> bool {
+        matches!(self, BuildError::NoHandlers | BuildError::DuplicateState(_))
+    }
+}
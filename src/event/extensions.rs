@@ -0,0 +1,63 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    future::Future,
+    sync::Arc,
+};
+
+use dashmap::DashMap;
+use tokio::sync::OnceCell;
+
+type Slot = Arc<OnceCell<Arc<dyn Any + Send + Sync>>>;
+
+/// Typed storage scoped to a single event.
+///
+/// Every dispatched event carries its own `Extensions`, shared by all handlers and extractors that see the event.
+/// Extractors use it to cache expensive results, e.g. API responses, so they are only computed once per event.
+#[derive(Default, Clone)]
+pub struct Extensions {
+    map: DashMap<TypeId, Slot>,
+}
+
+impl Extensions {
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let slot = self.map.get(&TypeId::of::<T>())?.clone();
+        slot.get()
+            .and_then(|value| Arc::clone(value).downcast::<T>().ok())
+    }
+
+    /// Insert a value, replacing the one of the same type if present.
+    pub fn insert<T: Any + Send + Sync>(&self, value: T) -> Arc<T> {
+        let value = Arc::new(value);
+        let slot = OnceCell::new_with(Some(value.clone() as Arc<dyn Any + Send + Sync>));
+        self.map.insert(TypeId::of::<T>(), Arc::new(slot));
+        value
+    }
+
+    /// Get the value of type `T`, computing it with `init` if absent.
+    ///
+    /// Concurrent callers for the same type wait for a single `init` instead of computing the value again.
+    pub async fn get_or_insert_async<T, F, Fut>(&self, init: F) -> Arc<T>
+    where
+        T: Any + Send + Sync,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        // Clone the slot out so that the map is not locked while `init` runs.
+        let slot = self.map.entry(TypeId::of::<T>()).or_default().clone();
+        let value = slot
+            .get_or_init(|| async { Arc::new(init().await) as Arc<dyn Any + Send + Sync> })
+            .await;
+        Arc::clone(value)
+            .downcast::<T>()
+            .expect("extension slots are keyed by their type")
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
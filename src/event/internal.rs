@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::base::{context::BotContext, extract::FromEvent};
+
+use super::{BotEvent, TypedEvent};
+
+/// Events generated by flow-bot itself for connection state changes.
+///
+/// They are dispatched through the handler chain like received events, but are never parsed from the connection.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "internal_type", rename_all = "snake_case")]
+pub enum InternalEvent {
+    Connected(Connected),
+    Disconnected(Disconnected),
+    Reconnecting(Reconnecting),
+}
+
+/// The connection is established, `attempt` is the number of failed attempts before it.
+///
+/// `generation` is the [`connection_generation`] of the new connection.
+///
+/// [`connection_generation`]: crate::base::context::Context::connection_generation
+#[derive(Serialize, Debug, Clone)]
+pub struct Connected {
+    pub attempt: u32,
+    pub generation: u64,
+}
+
+/// The connection of `generation` is closed, `error` is `None` if it was closed normally.
+#[derive(Serialize, Debug, Clone)]
+pub struct Disconnected {
+    pub error: Option<String>,
+    pub generation: u64,
+}
+
+/// A reconnection is scheduled after `delay`.
+#[derive(Serialize, Debug, Clone)]
+pub struct Reconnecting {
+    pub attempt: u32,
+    pub delay: Duration,
+}
+
+macro_rules! impl_internal_from_event {
+    ($variant:ident) => {
+        #[async_trait]
+        impl FromEvent for $variant {
+            async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+                match &event.event {
+                    TypedEvent::FlowInternal(InternalEvent::$variant(inner)) => Some(inner.clone()),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_internal_from_event!(Connected);
+impl_internal_from_event!(Disconnected);
+impl_internal_from_event!(Reconnecting);
+
+#[async_trait]
+impl FromEvent for InternalEvent {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        match &event.event {
+            TypedEvent::FlowInternal(inner) => Some(inner.clone()),
+            _ => None,
+        }
+    }
+}
@@ -1,15 +1,19 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use crate::base::context::BotContext;
+use crate::api::api_ext::ApiExt;
+use crate::base::context::{BotContext, Context};
 use crate::base::extract::FromEvent;
+use crate::error::FlowError;
 use crate::event::{BotEvent, TypedEvent};
 use crate::message::{
     self, IntoMessage,
     segments::{ReplySegment, Segment},
 };
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, std::marker::ConstParamTy)]
 #[serde(rename_all = "snake_case")]
 pub enum PrivateSubType {
     Friend,
@@ -17,7 +21,7 @@ pub enum PrivateSubType {
     Other,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, std::marker::ConstParamTy)]
 #[serde(rename_all = "snake_case")]
 pub enum GroupSubType {
     Normal,
@@ -25,7 +29,7 @@ pub enum GroupSubType {
     Notice,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum SenderSex {
     Male,
@@ -33,21 +37,38 @@ pub enum SenderSex {
     Unknown,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PrivateSenderInfo {
     pub user_id: Option<i64>,
     pub nickname: Option<String>,
     pub sex: Option<SenderSex>,
     pub age: Option<i32>,
+    /// The group of a temp session, set by go-cqhttp and NapCat.
+    pub group_id: Option<i64>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PrivateMessageInfo {
     pub sub_type: PrivateSubType,
     pub sender: PrivateSenderInfo,
+    /// The group a temp session was started from, when the implementation sends it with the message.
+    /// Use [`temp_group_id`](Self::temp_group_id), which also looks at the sender.
+    pub group_id: Option<i64>,
+    /// Where a temp session was started from, 0 for a group in go-cqhttp.
+    pub temp_source: Option<i32>,
+}
+
+impl PrivateMessageInfo {
+    /// The group of a temp session, `None` for other private messages or if the implementation does not send it.
+    pub fn temp_group_id(&self) -> Option<i64> {
+        match self.sub_type {
+            PrivateSubType::Group => self.group_id.or(self.sender.group_id),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum GroupSenderRole {
     Owner,
@@ -55,7 +76,7 @@ pub enum GroupSenderRole {
     Member,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupSenderInfo {
     pub user_id: Option<i64>,
     pub nickname: Option<String>,
@@ -68,22 +89,25 @@ pub struct GroupSenderInfo {
     pub title: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Some implementations leave out fields, they default to `0` or an empty string.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct GroupAnonymousInfo {
     pub id: i64,
     pub name: String,
     pub flag: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupMessageInfo {
     pub sub_type: GroupSubType,
     pub group_id: i64,
     pub sender: GroupSenderInfo,
+    #[serde(default)]
     pub anonymous: Option<GroupAnonymousInfo>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "message_type")]
 #[serde(rename_all = "snake_case")]
 pub enum TypedMessageInfo {
@@ -91,7 +115,7 @@ pub enum TypedMessageInfo {
     Private(PrivateMessageInfo),
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Message {
     pub message_id: i32,
     pub user_id: i64,
@@ -114,6 +138,60 @@ impl Message {
         ret.extend(message.into_message());
         ret
     }
+
+    /// Like [`reply`](Self::reply), but also mentions the sender after the reply segment.
+    pub fn reply_at<T>(&self, message: T) -> message::Message
+    where
+        T: IntoMessage,
+    {
+        self.reply_styled(message, ReplyStyle::ReplyAndAt)
+    }
+
+    /// Prepend the segments of `style` to `message`.
+    /// Mentions are left out for private messages, where they are meaningless.
+    pub fn reply_styled<T>(&self, message: T, style: ReplyStyle) -> message::Message
+    where
+        T: IntoMessage,
+    {
+        let is_group = matches!(self.info, TypedMessageInfo::Group(_));
+        let (reply, at) = match style {
+            ReplyStyle::None => (false, false),
+            ReplyStyle::Reply => (true, false),
+            ReplyStyle::ReplyAndAt => (true, is_group),
+            ReplyStyle::AtOnly => (false, is_group),
+        };
+
+        let mut ret = Vec::new();
+        if reply {
+            ret.push(Segment::Reply(ReplySegment {
+                id: self.message_id.to_string(),
+            }));
+        }
+        if at {
+            ret.push(Segment::at_user(self.user_id));
+        }
+        ret.extend(message.into_message());
+        ret
+    }
+}
+
+/// How replies sent with [`Context::reply`] refer to the message they answer.
+///
+/// Set one with [`with_reply_style`] to apply it to every reply, [`ReplyStyle::Reply`] is used otherwise.
+///
+/// [`Context::reply`]: crate::base::context::Context::reply
+/// [`with_reply_style`]: crate::FlowBotBuilder::with_reply_style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplyStyle {
+    /// Send the message as is.
+    None,
+    /// Quote the message.
+    #[default]
+    Reply,
+    /// Quote the message and mention its sender.
+    ReplyAndAt,
+    /// Only mention the sender.
+    AtOnly,
 }
 
 #[async_trait]
@@ -154,3 +232,130 @@ impl FromEvent for GroupMessageInfo {
         }
     }
 }
+
+/// Extractor for group messages with everything sent with them, private messages are skipped.
+#[derive(Debug, Clone)]
+pub struct GroupMessageEvent {
+    pub time: i64,
+    pub message_id: i32,
+    pub user_id: i64,
+    pub group_id: i64,
+    pub sub_type: GroupSubType,
+    pub sender: GroupSenderInfo,
+    pub anonymous: Option<GroupAnonymousInfo>,
+    pub message: message::Message,
+    pub raw_message: String,
+    pub font: i32,
+}
+
+#[async_trait]
+impl FromEvent for GroupMessageEvent {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        let TypedEvent::Message(ref msg) = event.event else {
+            return None;
+        };
+        let TypedMessageInfo::Group(info) = &msg.info else {
+            return None;
+        };
+        Some(Self {
+            time: event.time,
+            message_id: msg.message_id,
+            user_id: msg.user_id,
+            group_id: info.group_id,
+            sub_type: info.sub_type,
+            sender: info.sender.clone(),
+            anonymous: info.anonymous.clone(),
+            message: msg.message.clone(),
+            raw_message: msg.raw_message.clone(),
+            font: msg.font,
+        })
+    }
+}
+
+/// Extractor for private messages with everything sent with them, group messages are skipped.
+#[derive(Debug, Clone)]
+pub struct PrivateMessageEvent {
+    pub time: i64,
+    pub message_id: i32,
+    pub user_id: i64,
+    pub sub_type: PrivateSubType,
+    pub sender: PrivateSenderInfo,
+    /// The group of a temp session, see [`PrivateMessageInfo::temp_group_id`].
+    pub temp_group_id: Option<i64>,
+    /// Where a temp session was started from, 0 for a group in go-cqhttp.
+    pub temp_source: Option<i32>,
+    pub message: message::Message,
+    pub raw_message: String,
+    pub font: i32,
+}
+
+#[async_trait]
+impl FromEvent for PrivateMessageEvent {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        let TypedEvent::Message(ref msg) = event.event else {
+            return None;
+        };
+        let TypedMessageInfo::Private(info) = &msg.info else {
+            return None;
+        };
+        Some(Self {
+            time: event.time,
+            message_id: msg.message_id,
+            user_id: msg.user_id,
+            sub_type: info.sub_type,
+            sender: info.sender.clone(),
+            temp_group_id: info.temp_group_id(),
+            temp_source: info.temp_source,
+            message: msg.message.clone(),
+            raw_message: msg.raw_message.clone(),
+            font: msg.font,
+        })
+    }
+}
+
+/// Extractor for anonymous group messages, other messages are skipped.
+#[derive(Debug, Clone)]
+pub struct Anonymous {
+    pub group_id: i64,
+    /// `None` if the implementation did not send it, which some do even for anonymous messages.
+    pub info: Option<GroupAnonymousInfo>,
+}
+
+impl Anonymous {
+    /// Mute the anonymous sender in the group of the message, by the flag of its anonymous info.
+    ///
+    /// Fails with [`FlowError::NoAnonymousFlag`] if the implementation did not send the flag.
+    pub async fn ban(&self, context: &Context, duration: Duration) -> Result<(), FlowError> {
+        let flag = self
+            .info
+            .as_ref()
+            .map(|info| info.flag.clone())
+            .filter(|flag| !flag.is_empty())
+            .ok_or(FlowError::NoAnonymousFlag)?;
+        context
+            .set_group_anonymous_ban(self.group_id, None, Some(flag), duration)
+            .await
+    }
+}
+
+#[async_trait]
+impl FromEvent for Anonymous {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        if let TypedEvent::Message(ref msg) = event.event {
+            match &msg.info {
+                TypedMessageInfo::Group(GroupMessageInfo {
+                    sub_type: GroupSubType::Anonymous,
+                    group_id,
+                    anonymous,
+                    ..
+                }) => Some(Self {
+                    group_id: *group_id,
+                    info: anonymous.clone(),
+                }),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+}
@@ -1,8 +1,8 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{api::BotStatus, impl_from_event};
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum LifecycleSubType {
     Enable,
@@ -10,18 +10,18 @@ pub enum LifecycleSubType {
     Connect,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Lifecycle {
     pub sub_type: LifecycleSubType,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Heartbeat {
     pub interval: i64,
     pub status: BotStatus,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "meta_event_type", rename_all = "snake_case")]
 pub enum MetaEvent {
     Lifecycle(Lifecycle),
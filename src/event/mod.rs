@@ -1,16 +1,20 @@
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+
+use tokio_tungstenite::tungstenite::Utf8Bytes;
 
 use async_trait::async_trait;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::base::{context::BotContext, extract::FromEvent};
 
+pub mod extensions;
+pub mod internal;
 pub mod message;
 pub mod meta_event;
 pub mod notice;
 pub mod request;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "post_type")]
 #[serde(rename_all = "snake_case")]
 pub enum TypedEvent {
@@ -19,6 +23,9 @@ pub enum TypedEvent {
     Notice(notice::Notice),
     Request(request::Request),
     MetaEvent(meta_event::MetaEvent),
+    /// Generated by flow-bot, a payload claiming this post type is parsed as [`TypedEvent::Unknown`].
+    #[serde(skip_deserializing)]
+    FlowInternal(internal::InternalEvent),
     #[serde(untagged)]
     Unknown(serde_json::Value),
 }
@@ -30,17 +37,40 @@ impl TypedEvent {
             TypedEvent::Notice(..) => "notice",
             TypedEvent::Request(..) => "request",
             TypedEvent::MetaEvent(..) => "meta_event",
+            TypedEvent::FlowInternal(..) => "flow_internal",
             TypedEvent::Unknown(..) => "unknown",
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// An event received from the onebot implementation.
+///
+/// Serializing an event produces the onebot wire format, with a few differences from the received payload:
+/// fields unknown to the typed event are dropped and absent optional fields are written as `null`.
+/// Use [`Event::raw_json`] when the exact payload is needed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Event {
     pub time: i64,
     pub self_id: i64,
     #[serde(flatten)]
     pub event: TypedEvent,
+    #[serde(skip)]
+    pub extensions: extensions::Extensions,
+    #[serde(skip)]
+    pub(crate) raw: Utf8Bytes,
+}
+
+impl Event {
+    /// The exact json payload the event was parsed from, including fields unknown to the typed event.
+    /// For internal events, it is the serialized event.
+    pub fn raw_json(&self) -> &str {
+        &self.raw
+    }
+
+    /// Whether the event is an [`InternalEvent`](internal::InternalEvent) generated by flow-bot.
+    pub fn is_internal(&self) -> bool {
+        matches!(self.event, TypedEvent::FlowInternal(_))
+    }
 }
 
 pub type BotEvent = Arc<Event>;
@@ -52,6 +82,34 @@ impl FromEvent for BotEvent {
     }
 }
 
+/// Extractor for the raw json payload of the event.
+pub struct RawEvent {
+    raw: Utf8Bytes,
+    value: OnceLock<serde_json::Value>,
+}
+
+impl RawEvent {
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The payload parsed as a json value, parsed on first access.
+    pub fn value(&self) -> &serde_json::Value {
+        self.value
+            .get_or_init(|| serde_json::from_str(&self.raw).unwrap_or(serde_json::Value::Null))
+    }
+}
+
+#[async_trait]
+impl FromEvent for RawEvent {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        Some(Self {
+            raw: event.raw.clone(),
+            value: OnceLock::new(),
+        })
+    }
+}
+
 #[macro_export]
 macro_rules! impl_from_event {
     ($event_type:ident) => {
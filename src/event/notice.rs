@@ -1,11 +1,22 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use serde::Deserialize;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::impl_from_event;
+use crate::{
+    api::{GroupHonorType, api_ext::ApiExt},
+    base::{
+        context::{BotContext, Context},
+        extract::FromEvent,
+    },
+    error::FlowError,
+    event::{BotEvent, TypedEvent},
+    impl_from_event,
+    message::media::MediaSource,
+};
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupFile {
     pub id: String,
     pub name: String,
@@ -13,28 +24,70 @@ pub struct GroupFile {
     pub busid: i64,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupUpload {
     pub group_id: i64,
     pub user_id: i64,
     pub file: GroupFile,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl GroupUpload {
+    /// A download url of the uploaded file, from `get_group_file_url`.
+    pub async fn download_url(&self, context: &Context) -> Result<String, FlowError> {
+        let file = context
+            .get_group_file_url(self.group_id, self.file.id.clone(), self.file.busid)
+            .await?;
+        Ok(file.url)
+    }
+
+    /// Copy the uploaded file to the root folder of another group, keeping its name.
+    ///
+    /// The file is downloaded by the onebot implementation with `download_file` and uploaded from its cache,
+    /// so it never passes through the bot.
+    pub async fn mirror_to(&self, context: &Context, group_id: i64) -> Result<(), FlowError> {
+        let url = self.download_url(context).await?;
+        tracing::debug!(
+            "Downloading {} ({} bytes) from group {}",
+            self.file.name,
+            self.file.size,
+            self.group_id
+        );
+        let downloaded = context.download_file(url, None, None).await?;
+        tracing::debug!("Uploading {} to group {}", self.file.name, group_id);
+        context
+            .upload_group_file(
+                group_id,
+                MediaSource::Raw(downloaded.file),
+                self.file.name.clone(),
+                None,
+            )
+            .await
+            .inspect_err(|e| {
+                tracing::warn!(
+                    "Downloaded {} but failed to upload it to group {}: {}",
+                    self.file.name,
+                    group_id,
+                    e
+                )
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum GroupAdminSubType {
     Set,
     Unset,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupAdmin {
     pub group_id: i64,
     pub user_id: i64,
     pub sub_type: GroupAdminSubType,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum GroupDecreaseSubType {
     Leave,
@@ -42,7 +95,7 @@ pub enum GroupDecreaseSubType {
     KickMe,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupDecrease {
     pub group_id: i64,
     pub user_id: i64,
@@ -50,14 +103,14 @@ pub struct GroupDecrease {
     pub sub_type: GroupDecreaseSubType,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum GroupIncreaseSubType {
     Approve,
     Invite,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupIncrease {
     pub group_id: i64,
     pub user_id: i64,
@@ -65,14 +118,14 @@ pub struct GroupIncrease {
     pub sub_type: GroupIncreaseSubType,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum GroupBanSubType {
     Ban,
     LiftBan,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupBan {
     pub group_id: i64,
     pub user_id: i64,
@@ -81,12 +134,12 @@ pub struct GroupBan {
     pub duration: i64,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FriendAdd {
     pub user_id: i64,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupRecall {
     pub group_id: i64,
     pub user_id: i64,
@@ -94,13 +147,75 @@ pub struct GroupRecall {
     pub message_id: i64,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FriendRecall {
     pub user_id: i64,
     pub message_id: i64,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmojiLike {
+    pub emoji_id: String,
+    pub count: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupMsgEmojiLike {
+    pub group_id: i64,
+    pub user_id: i64,
+    pub message_id: i64,
+    pub likes: Vec<EmojiLike>,
+    /// Whether the reaction was added or removed, not reported by every implementation.
+    pub is_add: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Poke {
+    /// Absent for pokes in private chats.
+    pub group_id: Option<i64>,
+    pub user_id: i64,
+    pub target_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LuckyKing {
+    pub group_id: i64,
+    pub user_id: i64,
+    pub target_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Honor {
+    pub group_id: i64,
+    pub user_id: i64,
+    pub honor_type: String,
+}
+
+/// The `notify` notices, by `sub_type`.
+///
+/// # Migration
+///
+/// This replaces the `Notice::Notify { data }` variant. Pokes, lucky kings and honors parse into their own variants,
+/// the other sub types into [`Notify::Other`] with every field, `sub_type` included, as `data` held them:
+///
+/// ```ignore
+/// // Before
+/// Notice::Notify { data } => handle(data),
+/// // After
+/// Notice::Notify(Notify::Other(data)) => handle(data),
+/// Notice::Notify(Notify::Poke(poke)) => handle_poke(poke),
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "sub_type", rename_all = "snake_case")]
+pub enum Notify {
+    Poke(Poke),
+    LuckyKing(LuckyKing),
+    Honor(Honor),
+    #[serde(untagged)]
+    Other(HashMap<String, Value>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "notice_type")]
 #[serde(rename_all = "snake_case")]
 pub enum Notice {
@@ -112,10 +227,26 @@ pub enum Notice {
     FriendAdd(FriendAdd),
     GroupRecall(GroupRecall),
     FriendRecall(FriendRecall),
-    Notify {
-        #[serde(flatten)]
-        data: HashMap<String, Value>,
-    },
+    GroupMsgEmojiLike(GroupMsgEmojiLike),
+    Notify(Notify),
+}
+
+impl Notice {
+    /// The `notice_type` of the notice.
+    pub fn get_type(&self) -> &str {
+        match self {
+            Notice::GroupUpload(..) => "group_upload",
+            Notice::GroupAdmin(..) => "group_admin",
+            Notice::GroupDecrease(..) => "group_decrease",
+            Notice::GroupIncrease(..) => "group_increase",
+            Notice::GroupBan(..) => "group_ban",
+            Notice::FriendAdd(..) => "friend_add",
+            Notice::GroupRecall(..) => "group_recall",
+            Notice::FriendRecall(..) => "friend_recall",
+            Notice::GroupMsgEmojiLike(..) => "group_msg_emoji_like",
+            Notice::Notify(..) => "notify",
+        }
+    }
 }
 
 impl_from_event!(Notice);
@@ -135,3 +266,94 @@ impl_from_event!(Notice, FriendAdd);
 impl_from_event!(Notice, GroupRecall);
 
 impl_from_event!(Notice, FriendRecall);
+
+impl_from_event!(Notice, GroupMsgEmojiLike);
+
+impl_from_event!(Notice, Notify);
+
+macro_rules! impl_notify_from_event {
+    ($variant:ident) => {
+        #[async_trait]
+        impl FromEvent for $variant {
+            async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+                match &event.event {
+                    TypedEvent::Notice(Notice::Notify(Notify::$variant(inner))) => {
+                        Some(inner.clone())
+                    }
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_notify_from_event!(Poke);
+
+impl_notify_from_event!(LuckyKing);
+
+impl_notify_from_event!(Honor);
+
+/// Extractor for `honor` notify notices, with their honor type parsed. Notices of unknown honor types are skipped.
+#[derive(Debug, Clone)]
+pub struct HonorChanged {
+    pub group_id: i64,
+    pub user_id: i64,
+    pub honor: GroupHonorType,
+}
+
+#[async_trait]
+impl FromEvent for HonorChanged {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let honor = Honor::from_event(context, event).await?;
+        Some(Self {
+            group_id: honor.group_id,
+            user_id: honor.user_id,
+            honor: honor.honor_type.parse().ok()?,
+        })
+    }
+}
+
+/// Extractor matching bans of the bot itself, not their lifts.
+pub struct SelfBanned {
+    pub group_id: i64,
+    pub operator_id: i64,
+    pub duration: Duration,
+}
+
+#[async_trait]
+impl FromEvent for SelfBanned {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let self_id = event.self_id;
+        let ban = GroupBan::from_event(context, event).await?;
+        match ban.sub_type {
+            GroupBanSubType::Ban if ban.user_id == self_id && ban.duration > 0 => Some(Self {
+                group_id: ban.group_id,
+                operator_id: ban.operator_id,
+                duration: Duration::from_secs(ban.duration as u64),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Extractor matching pokes aimed at the bot itself.
+pub struct PokedMe {
+    pub user_id: i64,
+    pub group_id: Option<i64>,
+}
+
+#[async_trait]
+impl FromEvent for PokedMe {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let self_id = event.self_id;
+        let poke = Poke::from_event(context, event).await?;
+        if poke.target_id == self_id {
+            Some(Self {
+                user_id: poke.user_id,
+                group_id: poke.group_id,
+            })
+        } else {
+            None
+        }
+    }
+}
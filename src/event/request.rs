@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::impl_from_event;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FriendRequest {
     pub user_id: i64,
     pub comment: String,
@@ -16,7 +16,7 @@ pub enum GroupRequestSubType {
     Invite,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GroupRequest {
     pub user_id: i64,
     pub sub_type: GroupRequestSubType,
@@ -25,7 +25,7 @@ pub struct GroupRequest {
     pub flag: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "request_type")]
 #[serde(rename_all = "snake_case")]
 pub enum Request {
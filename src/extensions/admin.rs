@@ -0,0 +1,313 @@
+use std::{collections::HashSet, time::Duration};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+use crate::{
+    base::{
+        context::BotContext, extract::CommandConfig, group_config::GroupConfigStore,
+        handler::HandlerControl, service::Service,
+    },
+    event::{
+        BotEvent, TypedEvent,
+        message::{Message, TypedMessageInfo},
+    },
+    message::message_ext::MessageExt,
+};
+
+type ReloadFn = dyn Fn(BotContext) -> BoxFuture<'static, Result<String, String>> + Send + Sync;
+
+/// Service giving superusers control over the running bot, in private chats and groups.
+///
+/// - `status` replies with the [`Health`](crate::base::health::Health) of the bot
+/// - `stats` replies with the handler statistics, with the `handler-stats` feature
+/// - `reload-config` runs the [`on_reload`](Self::on_reload) callback
+/// - `mute-group [id]` and `unmute-group [id]` stop and resume dispatching messages of a group,
+///   the current one if no id is given, see [`Context::ignore_group`]
+/// - `muted-groups` lists the groups muted this way
+/// - `command-prefix <prefix> [id]`, `disable-command <name> [id]` and `enable-command <name> [id]` edit the
+///   [`CommandConfig`] of a group, the current one if no id is given, and `reset-commands [id]` removes it.
+///   They need [`with_command_config`]
+/// - `shutdown` stops the bot gracefully, see [`Context::shutdown`]
+///
+/// Commands of other users are ignored and passed on to later handlers.
+///
+/// [`Context::ignore_group`]: crate::base::context::Context::ignore_group
+/// [`Context::shutdown`]: crate::base::context::Context::shutdown
+/// [`with_command_config`]: crate::FlowBotBuilder::with_command_config
+pub struct AdminService {
+    superusers: HashSet<i64>,
+    command_prefix: String,
+    on_reload: Option<Box<ReloadFn>>,
+}
+
+const COMMANDS: &[&str] = &[
+    "status",
+    "stats",
+    "reload-config",
+    "mute-group",
+    "unmute-group",
+    "muted-groups",
+    "command-prefix",
+    "disable-command",
+    "enable-command",
+    "reset-commands",
+    "shutdown",
+];
+
+impl AdminService {
+    /// Commands prefixed with `/`, for the given superusers.
+    pub fn new(superusers: impl IntoIterator<Item = i64>) -> Self {
+        Self {
+            superusers: superusers.into_iter().collect(),
+            command_prefix: "/".to_string(),
+            on_reload: None,
+        }
+    }
+
+    pub fn command_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.command_prefix = prefix.into();
+        self
+    }
+
+    /// Called by `reload-config`, replying with the message it returns or the error it failed with.
+    pub fn on_reload<F, Fut>(mut self, on_reload: F) -> Self
+    where
+        F: Fn(BotContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        self.on_reload = Some(Box::new(move |context| Box::pin(on_reload(context))));
+        self
+    }
+
+    pub fn is_superuser(&self, user_id: i64) -> bool {
+        self.superusers.contains(&user_id)
+    }
+
+    /// Execute a command, returning the reply.
+    async fn run_command(
+        &self,
+        context: &BotContext,
+        message: &Message,
+        command: &str,
+        args: &[&str],
+    ) -> String {
+        // The group id, if any, is the argument at `index`.
+        let group_arg = |index: usize| match (args.get(index), &message.info) {
+            (Some(id), _) => id.parse().ok(),
+            (None, TypedMessageInfo::Group(info)) => Some(info.group_id),
+            (None, TypedMessageInfo::Private(_)) => None,
+        };
+        match command {
+            "status" => format_status(context),
+            "stats" => format_stats(context),
+            "reload-config" => match &self.on_reload {
+                Some(on_reload) => match on_reload(context.clone()).await {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        tracing::error!("Failed to reload the config: {}", e);
+                        format!("Failed to reload the config: {}", e)
+                    }
+                },
+                None => "No config to reload".to_string(),
+            },
+            "mute-group" | "unmute-group" => {
+                let Some(group_id) = group_arg(0) else {
+                    return format!("Usage: {}{} <group id>", self.command_prefix, command);
+                };
+                match (command, context.is_group_ignored(group_id)) {
+                    ("mute-group", false) => {
+                        context.ignore_group(group_id);
+                        tracing::info!("Group {} muted by {}", group_id, message.user_id);
+                        format!("Ignoring messages of group {}", group_id)
+                    }
+                    ("mute-group", true) => format!("Group {} is already muted", group_id),
+                    (_, true) => {
+                        context.unignore_group(group_id);
+                        tracing::info!("Group {} unmuted by {}", group_id, message.user_id);
+                        format!("Handling messages of group {} again", group_id)
+                    }
+                    (_, false) => format!("Group {} is not muted", group_id),
+                }
+            }
+            "muted-groups" => {
+                let mut groups = context.ignored_groups();
+                groups.sort_unstable();
+                if groups.is_empty() {
+                    "No muted groups".to_string()
+                } else {
+                    groups
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+            }
+            "command-prefix" | "disable-command" | "enable-command" => {
+                let (Some(value), Some(group_id)) = (args.first(), group_arg(1)) else {
+                    return format!(
+                        "Usage: {}{} <{}> [group id]",
+                        self.command_prefix,
+                        command,
+                        if command == "command-prefix" {
+                            "prefix"
+                        } else {
+                            "command"
+                        }
+                    );
+                };
+                edit_command_config(context, group_id, |config| match command {
+                    "command-prefix" => config.prefix = Some(value.to_string()),
+                    "disable-command" => {
+                        config.disabled.insert(value.to_string());
+                    }
+                    _ => {
+                        config.disabled.remove(*value);
+                    }
+                })
+                .await
+            }
+            "reset-commands" => {
+                let Some(group_id) = group_arg(0) else {
+                    return format!("Usage: {}{} [group id]", self.command_prefix, command);
+                };
+                let Some(store) = context.state.get::<GroupConfigStore<CommandConfig>>() else {
+                    return COMMAND_CONFIG_DISABLED.to_string();
+                };
+                store.remove(group_id).await;
+                tracing::info!(
+                    "Commands of group {} reset by {}",
+                    group_id,
+                    message.user_id
+                );
+                format!("Group {} uses the default commands again", group_id)
+            }
+            "shutdown" => {
+                tracing::warn!("Shutdown requested by {}", message.user_id);
+                // Stops once this reply is sent, as the service counts as a running handler.
+                context.shutdown();
+                "Shutting down".to_string()
+            }
+            _ => unreachable!("unknown commands are filtered before"),
+        }
+    }
+}
+
+const COMMAND_CONFIG_DISABLED: &str = "Per-group commands are not enabled, see with_command_config";
+
+/// Edit the [`CommandConfig`] of `group_id`, replying with the result.
+async fn edit_command_config<F>(context: &BotContext, group_id: i64, edit: F) -> String
+where
+    F: FnOnce(&mut CommandConfig),
+{
+    let Some(store) = context.state.get::<GroupConfigStore<CommandConfig>>() else {
+        return COMMAND_CONFIG_DISABLED.to_string();
+    };
+    let config = store.update(group_id, edit).await;
+    tracing::info!("Commands of group {} changed to {:?}", group_id, config);
+    let disabled = match config.disabled.is_empty() {
+        true => "none".to_string(),
+        false => config
+            .disabled
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    format!(
+        "Group {}: prefix {}, disabled commands: {}",
+        group_id,
+        config.prefix.as_deref().unwrap_or("default"),
+        disabled
+    )
+}
+
+fn format_ago(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{:.1}s ago", duration.as_secs_f64()),
+        None => "never".to_string(),
+    }
+}
+
+fn format_status(context: &BotContext) -> String {
+    let snapshot = context.debug_snapshot();
+    let health = &snapshot.health;
+    let pending_requests = match snapshot.pending_requests.first() {
+        Some(oldest) => format!(
+            "{} (oldest sent {})",
+            snapshot.pending_requests.len(),
+            format_ago(Some(oldest.age))
+        ),
+        None => "0".to_string(),
+    };
+    format!(
+        "State: {:?}\nConnection: #{}\nLast frame: {}\nLast heartbeat: {}\nPending requests: {}\nRunning handlers: {}\nDuplicate events: {}\nStates: {}",
+        health.state,
+        snapshot.connection_generation,
+        format_ago(health.since_last_frame),
+        format_ago(health.since_last_heartbeat),
+        pending_requests,
+        health.in_flight_handlers,
+        health.duplicate_events,
+        snapshot.states.len(),
+    )
+}
+
+#[cfg(feature = "handler-stats")]
+fn format_stats(context: &BotContext) -> String {
+    context
+        .handler_stats()
+        .iter()
+        .map(|stats| {
+            format!(
+                "{} {}: {} skip, {} continue, {} block, {} panic, p95 {:?}",
+                stats.index,
+                stats.name,
+                stats.skips,
+                stats.continues,
+                stats.blocks,
+                stats.panics,
+                stats.p95
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(not(feature = "handler-stats"))]
+fn format_stats(_context: &BotContext) -> String {
+    "Handler statistics need the handler-stats feature".to_string()
+}
+
+#[async_trait]
+impl Service for AdminService {
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        let TypedEvent::Message(ref msg) = event.event else {
+            return HandlerControl::Continue;
+        };
+
+        let text = msg.message.extract_plain_text();
+        let mut words = text.split_whitespace();
+        let Some(command) = words
+            .next()
+            .and_then(|word| word.strip_prefix(self.command_prefix.as_str()))
+            .filter(|command| COMMANDS.contains(command))
+        else {
+            return HandlerControl::Continue;
+        };
+        if !self.is_superuser(msg.user_id) {
+            tracing::debug!(
+                "Ignoring admin command of {}, who is not a superuser",
+                msg.user_id
+            );
+            return HandlerControl::Continue;
+        }
+
+        let args = words.collect::<Vec<_>>();
+        let reply = self.run_command(&context, msg, command, &args).await;
+        if let Err(e) = context.reply(msg, reply).await {
+            tracing::error!("Failed to reply to admin command {}: {}", command, e);
+        }
+        HandlerControl::Block
+    }
+}
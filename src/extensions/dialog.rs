@@ -0,0 +1,274 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use tokio::sync::Mutex;
+
+use crate::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, handler::HandlerControl, service::Service},
+    event::{
+        BotEvent, TypedEvent,
+        message::{Message, TypedMessageInfo},
+    },
+    message::segments::{Segment, TextSegment},
+};
+
+/// What a dialog step decided, see [`Dialog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transition<St, Out> {
+    /// Keep the current state, e.g. to ask again after an invalid answer.
+    Stay,
+    Move(St),
+    /// End the dialog, passing `Out` to the [`on_finish`](Dialog::on_finish) callback.
+    Finish(Out),
+}
+
+/// The user and chat a dialog is held with, `group_id` is `None` in private chats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DialogKey {
+    pub user_id: i64,
+    pub group_id: Option<i64>,
+}
+
+impl DialogKey {
+    pub fn of(message: &Message) -> Self {
+        let group_id = match &message.info {
+            TypedMessageInfo::Group(info) => Some(info.group_id),
+            TypedMessageInfo::Private(_) => None,
+        };
+        Self {
+            user_id: message.user_id,
+            group_id,
+        }
+    }
+}
+
+type StepFn<St, Out> =
+    dyn Fn(St, BotContext, Message) -> BoxFuture<'static, Transition<St, Out>> + Send + Sync;
+type TriggerFn<St> = dyn Fn(&Message) -> Option<St> + Send + Sync;
+type FinishFn<Out> = dyn Fn(Out, BotContext, Message) -> BoxFuture<'static, ()> + Send + Sync;
+
+struct Session<St> {
+    state: St,
+    /// Changes with every step, so that expiry timers of earlier steps do nothing.
+    step: u64,
+}
+
+type Slot<St> = Arc<Mutex<Option<Session<St>>>>;
+
+/// A multi-step conversation with a single user, driven by a state machine.
+///
+/// Messages of a user in a dialog, in the chat it was started in, are passed to the step function with the current state
+/// and are not seen by later handlers. Other messages are skipped, except those the [`trigger`](Self::trigger) starts a dialog with,
+/// which are passed to the step function with the state the trigger returned. Register the service before the regular handlers,
+/// and as an `Arc` that is also a state to start dialogs from handlers with [`start`](Self::start).
+///
+/// Dialogs that got no message for the [`ttl`](Self::ttl) end, telling the user if an [`expired_notice`](Self::expired_notice) is set.
+/// Messages of a user are handled one at a time. States are cloned for every step, so that [`Transition::Stay`] can keep them.
+///
+/// # Example
+/// ```ignore
+/// let dialog = Dialog::new(|state, ctx, message| async move {
+///     match state {
+///         Ask::Name => {
+///             ctx.reply(&message, "How old are you?").await.ok();
+///             Transition::Move(Ask::Age(message.message.extract_plain_text()))
+///         }
+///         Ask::Age(name) => Transition::Finish((name, message.message.extract_plain_text())),
+///     }
+/// })
+/// .trigger(|message| (message.message.extract_plain_text() == "/register").then_some(Ask::Start))
+/// .on_finish(|(name, age), ctx, message| async move { /* ... */ });
+/// ```
+pub struct Dialog<St, Out> {
+    step: Arc<StepFn<St, Out>>,
+    trigger: Option<Box<TriggerFn<St>>>,
+    on_finish: Option<Arc<FinishFn<Out>>>,
+    ttl: Duration,
+    expired_notice: Option<String>,
+    sessions: Arc<DashMap<DialogKey, Slot<St>>>,
+    next_step: Arc<AtomicU64>,
+}
+
+impl<St, Out> Dialog<St, Out>
+where
+    St: Clone + Send + 'static,
+    Out: Send + 'static,
+{
+    /// A dialog moving between states with `step`, expiring after 5 minutes without an answer.
+    pub fn new<F, Fut>(step: F) -> Self
+    where
+        F: Fn(St, BotContext, Message) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Transition<St, Out>> + Send + 'static,
+    {
+        Self {
+            step: Arc::new(move |state, context, message| Box::pin(step(state, context, message))),
+            trigger: None,
+            on_finish: None,
+            ttl: Duration::from_secs(5 * 60),
+            expired_notice: None,
+            sessions: Arc::new(DashMap::new()),
+            next_step: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Start a dialog in the state returned by `trigger`, for messages of users not in a dialog yet.
+    pub fn trigger<F>(mut self, trigger: F) -> Self
+    where
+        F: Fn(&Message) -> Option<St> + Send + Sync + 'static,
+    {
+        self.trigger = Some(Box::new(trigger));
+        self
+    }
+
+    /// Called with the output of finished dialogs and the message that finished them.
+    pub fn on_finish<F, Fut>(mut self, on_finish: F) -> Self
+    where
+        F: Fn(Out, BotContext, Message) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_finish = Some(Arc::new(move |output, context, message| {
+            Box::pin(on_finish(output, context, message))
+        }));
+        self
+    }
+
+    /// End dialogs that got no message for `ttl`.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Send `notice` to users whose dialog expired, mentioning them in groups.
+    pub fn expired_notice(mut self, notice: impl Into<String>) -> Self {
+        self.expired_notice = Some(notice.into());
+        self
+    }
+
+    /// Start a dialog with `key` in `state`, replacing the dialog it was in. The next message of the user is its first step.
+    pub fn start(&self, context: &BotContext, key: DialogKey, state: St) {
+        self.begin(context, key, state);
+    }
+
+    fn begin(&self, context: &BotContext, key: DialogKey, state: St) -> Slot<St> {
+        let step = self.next_step.fetch_add(1, Ordering::Relaxed);
+        let slot = Arc::new(Mutex::new(Some(Session { state, step })));
+        self.arm(context, key, &slot, step);
+        self.sessions.insert(key, slot.clone());
+        slot
+    }
+
+    /// End the dialog with `key`, returning whether there was one.
+    pub fn cancel(&self, key: DialogKey) -> bool {
+        self.sessions.remove(&key).is_some()
+    }
+
+    pub fn is_active(&self, key: DialogKey) -> bool {
+        self.sessions.contains_key(&key)
+    }
+
+    /// Start the expiry timer of `step` of `slot`.
+    fn arm(&self, context: &BotContext, key: DialogKey, slot: &Slot<St>, step: u64) {
+        let sessions = self.sessions.clone();
+        let slot = slot.clone();
+        let ttl = self.ttl;
+        let notice = self.expired_notice.clone();
+        let context = context.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(ttl).await;
+            {
+                let mut session = slot.lock().await;
+                if session.as_ref().is_none_or(|session| session.step != step) {
+                    return;
+                }
+                *session = None;
+            }
+            // Replaced or cancelled dialogs end silently.
+            if sessions
+                .remove_if(&key, |_, current| Arc::ptr_eq(current, &slot))
+                .is_none()
+            {
+                return;
+            }
+            tracing::debug!("Dialog with {} expired", key.user_id);
+
+            let Some(notice) = notice else {
+                return;
+            };
+            let result = match key.group_id {
+                Some(group_id) => {
+                    let message = vec![
+                        Segment::at_user(key.user_id),
+                        Segment::Text(TextSegment { text: notice }),
+                    ];
+                    context.send_group_message(group_id, message, None).await
+                }
+                None => {
+                    context
+                        .send_private_message(key.user_id, notice, None)
+                        .await
+                }
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to tell {} the dialog expired: {}", key.user_id, e);
+            }
+        });
+    }
+
+    async fn handle(&self, context: BotContext, message: &Message) -> HandlerControl {
+        let key = DialogKey::of(message);
+        let slot = match self.sessions.get(&key) {
+            Some(slot) => slot.clone(),
+            None => match self.trigger.as_ref().and_then(|trigger| trigger(message)) {
+                Some(state) => self.begin(&context, key, state),
+                None => return HandlerControl::Skip,
+            },
+        };
+
+        let mut session = slot.lock().await;
+        // Expired or cancelled while waiting for the previous step.
+        let Some(Session { state, .. }) = session.take() else {
+            return HandlerControl::Skip;
+        };
+        let transition = (self.step)(state.clone(), context.clone(), message.clone()).await;
+        let state = match transition {
+            Transition::Stay => state,
+            Transition::Move(state) => state,
+            Transition::Finish(output) => {
+                drop(session);
+                self.sessions
+                    .remove_if(&key, |_, current| Arc::ptr_eq(current, &slot));
+                if let Some(on_finish) = &self.on_finish {
+                    on_finish(output, context, message.clone()).await;
+                }
+                return HandlerControl::Block;
+            }
+        };
+        let step = self.next_step.fetch_add(1, Ordering::Relaxed);
+        *session = Some(Session { state, step });
+        self.arm(&context, key, &slot, step);
+        HandlerControl::Block
+    }
+}
+
+#[async_trait]
+impl<St, Out> Service for Dialog<St, Out>
+where
+    St: Clone + Send + 'static,
+    Out: Send + 'static,
+{
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        match &event.event {
+            TypedEvent::Message(message) => self.handle(context, message).await,
+            _ => HandlerControl::Skip,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+
+use crate::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        extract::{FromEvent, GroupId, SenderId},
+        handler::HandlerControl,
+        service::Service,
+    },
+    event::BotEvent,
+};
+
+/// At most `max_messages` messages per user within `window`.
+#[derive(Clone, Copy, Debug)]
+pub struct FloodLimit {
+    pub max_messages: usize,
+    pub window: Duration,
+}
+
+/// State overriding the [`FloodLimit`] of a [`FloodDetector`] for single groups.
+#[derive(Default)]
+pub struct FloodLimits(pub DashMap<i64, FloodLimit>);
+
+/// What a [`FloodDetector`] found when a user exceeded the limit.
+#[derive(Clone, Debug)]
+pub struct FloodEvidence {
+    pub group_id: i64,
+    pub user_id: i64,
+    pub limit: FloodLimit,
+    /// Times of the messages within the window, oldest first.
+    pub timestamps: Vec<Instant>,
+}
+
+type FloodCallback = Arc<dyn Fn(BotContext, FloodEvidence) -> BoxFuture<'static, ()> + Send + Sync>;
+
+pub enum FloodAction {
+    /// Mute the user for the given duration, rounded up to whole seconds.
+    Ban(Duration),
+    Callback(FloodCallback),
+}
+
+impl FloodAction {
+    pub fn callback<F, Fut>(callback: F) -> Self
+    where
+        F: Fn(BotContext, FloodEvidence) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self::Callback(Arc::new(move |context, evidence| {
+            Box::pin(callback(context, evidence))
+        }))
+    }
+}
+
+/// Set on the event by a [`FloodDetector`] when its sender is flooding.
+struct Flooding;
+
+/// Service detecting users that send too many group messages in a short time.
+///
+/// Every group message is counted per group and user, and once a user exceeds the limit the [`FloodAction`] is taken.
+/// Handlers registered after the detector can use the [`NotFlooding`] extractor to skip such messages.
+pub struct FloodDetector {
+    limit: FloodLimit,
+    action: FloodAction,
+    history: DashMap<(i64, i64), History>,
+    seen: AtomicU64,
+}
+
+#[derive(Default)]
+struct History {
+    timestamps: VecDeque<Instant>,
+    flagged_until: Option<Instant>,
+}
+
+enum Verdict {
+    Ok,
+    /// Carries the evidence when the action should be taken.
+    Flooding(Option<FloodEvidence>),
+}
+
+const EVICT_EVERY: u64 = 256;
+
+impl FloodDetector {
+    pub fn new(limit: FloodLimit, action: FloodAction) -> Self {
+        Self {
+            limit,
+            action,
+            history: DashMap::new(),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a message and check the sender against the limit.
+    fn record(&self, group_id: i64, user_id: i64, limit: FloodLimit) -> Verdict {
+        let now = Instant::now();
+        let mut history = self.history.entry((group_id, user_id)).or_default();
+
+        while history
+            .timestamps
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > limit.window)
+        {
+            history.timestamps.pop_front();
+        }
+        history.timestamps.push_back(now);
+        // Only the last `max_messages + 1` messages matter, which bounds the memory per user.
+        while history.timestamps.len() > limit.max_messages + 1 {
+            history.timestamps.pop_front();
+        }
+
+        if history.timestamps.len() <= limit.max_messages {
+            return Verdict::Ok;
+        }
+
+        // Take the action once per burst, later messages of the burst are only flagged.
+        if history.flagged_until.is_some_and(|until| now < until) {
+            return Verdict::Flooding(None);
+        }
+        history.flagged_until = Some(now + limit.window);
+        Verdict::Flooding(Some(FloodEvidence {
+            group_id,
+            user_id,
+            limit,
+            timestamps: history.timestamps.iter().copied().collect(),
+        }))
+    }
+
+    /// Drop users whose messages all fell out of the window, bounding the memory used.
+    fn evict_stale(&self, limits: Option<&FloodLimits>) {
+        let now = Instant::now();
+        self.history.retain(|(group_id, _), history| {
+            let window = limits
+                .and_then(|limits| limits.0.get(group_id).map(|limit| limit.window))
+                .unwrap_or(self.limit.window);
+            history
+                .timestamps
+                .back()
+                .is_some_and(|t| now.duration_since(*t) <= window)
+        });
+    }
+}
+
+#[async_trait]
+impl Service for FloodDetector {
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        let Some(GroupId(group_id)) = GroupId::from_event(context.clone(), event.clone()).await
+        else {
+            return HandlerControl::Continue;
+        };
+        let Some(SenderId(user_id)) = SenderId::from_event(context.clone(), event.clone()).await
+        else {
+            return HandlerControl::Continue;
+        };
+
+        let limits = context.state.get::<FloodLimits>();
+        if self.seen.fetch_add(1, Ordering::Relaxed) % EVICT_EVERY == EVICT_EVERY - 1 {
+            self.evict_stale(limits.as_deref());
+        }
+
+        let limit = limits
+            .and_then(|limits| limits.0.get(&group_id).map(|limit| *limit))
+            .unwrap_or(self.limit);
+
+        let Verdict::Flooding(evidence) = self.record(group_id, user_id, limit) else {
+            return HandlerControl::Continue;
+        };
+        event.extensions.insert(Flooding);
+        let Some(evidence) = evidence else {
+            return HandlerControl::Continue;
+        };
+
+        match &self.action {
+            FloodAction::Ban(duration) => {
+                if let Err(e) = context.set_group_ban(group_id, user_id, *duration).await {
+                    tracing::error!("Failed to mute flooding user {}: {}", user_id, e);
+                }
+            }
+            FloodAction::Callback(callback) => callback(context, evidence).await,
+        }
+        HandlerControl::Continue
+    }
+}
+
+/// Guard extractor skipping messages whose sender was found flooding by a [`FloodDetector`] registered before the handler.
+pub struct NotFlooding;
+
+#[async_trait]
+impl FromEvent for NotFlooding {
+    async fn from_event(_: BotContext, event: BotEvent) -> Option<Self> {
+        match event.extensions.get::<Flooding>() {
+            Some(_) => None,
+            None => Some(Self),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext, group_config::GroupConfigStore, handler::HandlerControl,
+        service::Service,
+    },
+    event::{
+        BotEvent, TypedEvent,
+        notice::{GroupDecreaseSubType, Notice},
+    },
+    message::{
+        segments::Segment,
+        template::{TemplateArg, render_template},
+    },
+};
+
+/// Per-group settings of a [`GreeterService`], stored with [`with_group_config`].
+///
+/// Templates left unset use the ones of the service.
+///
+/// [`with_group_config`]: crate::FlowBotBuilder::with_group_config
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GreeterSettings {
+    pub enabled: bool,
+    pub welcome: Option<String>,
+    pub farewell: Option<String>,
+}
+
+impl Default for GreeterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            welcome: None,
+            farewell: None,
+        }
+    }
+}
+
+/// Service welcoming new group members and saying farewell to leaving ones.
+///
+/// Templates may use `{at_new_member}` (a mention of the member), `{nickname}`, `{user_id}`, `{group_name}` and `{group_id}`.
+/// Nicknames and group names are only looked up when the template uses them.
+///
+/// Groups can be configured individually by registering `GroupConfigStore<GreeterSettings>` with [`with_group_config`].
+///
+/// [`with_group_config`]: crate::FlowBotBuilder::with_group_config
+pub struct GreeterService {
+    welcome: Option<String>,
+    farewell: Option<String>,
+    rate_limit: Option<(usize, Duration)>,
+    recent: DashMap<i64, VecDeque<Instant>>,
+}
+
+impl Default for GreeterService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GreeterService {
+    /// A greeter without default templates, only greeting in groups that set their own.
+    pub fn new() -> Self {
+        Self {
+            welcome: None,
+            farewell: None,
+            rate_limit: None,
+            recent: DashMap::new(),
+        }
+    }
+
+    pub fn welcome(mut self, template: impl Into<String>) -> Self {
+        self.welcome = Some(template.into());
+        self
+    }
+
+    pub fn farewell(mut self, template: impl Into<String>) -> Self {
+        self.farewell = Some(template.into());
+        self
+    }
+
+    /// Welcome at most `count` members of a group within `window`, later joins are not welcomed.
+    /// Keeps the bot quiet during mass joins.
+    pub fn max_welcomes(mut self, count: usize, window: Duration) -> Self {
+        self.rate_limit = Some((count, window));
+        self
+    }
+
+    /// Record a welcome, returning whether it is within the rate limit.
+    fn allow_welcome(&self, group_id: i64) -> bool {
+        let Some((count, window)) = self.rate_limit else {
+            return true;
+        };
+
+        let now = Instant::now();
+        let mut recent = self.recent.entry(group_id).or_default();
+        while recent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > window)
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= count {
+            return false;
+        }
+        recent.push_back(now);
+        true
+    }
+
+    async fn greet(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        template: &str,
+        is_member: bool,
+    ) {
+        let mut args = vec![
+            (
+                "at_new_member",
+                TemplateArg::Segment(Segment::at_user(user_id)),
+            ),
+            ("user_id", user_id.into()),
+            ("group_id", group_id.into()),
+        ];
+
+        if template.contains("{nickname}") {
+            // Members that left can only be looked up as strangers.
+            let member = match is_member {
+                true => context
+                    .get_group_member_info(group_id, user_id, None)
+                    .await
+                    .map(|member| match member.card.is_empty() {
+                        true => member.nickname,
+                        false => member.card,
+                    })
+                    .ok(),
+                false => None,
+            };
+            let nickname = match member {
+                Some(nickname) => nickname,
+                None => match context.get_stranger_info(user_id, None).await {
+                    Ok(stranger) => stranger.nickname,
+                    Err(e) => {
+                        tracing::warn!("Failed to get the nickname of {}: {}", user_id, e);
+                        user_id.to_string()
+                    }
+                },
+            };
+            args.push(("nickname", nickname.into()));
+        }
+        if template.contains("{group_name}") {
+            let group_name = match context.get_group_info(group_id, None).await {
+                Ok(group) => group.group_name,
+                Err(e) => {
+                    tracing::warn!("Failed to get the name of group {}: {}", group_id, e);
+                    group_id.to_string()
+                }
+            };
+            args.push(("group_name", group_name.into()));
+        }
+
+        let message = render_template(template, &args);
+        if let Err(e) = context.send_group_message(group_id, message, None).await {
+            tracing::error!("Failed to greet {} in group {}: {}", user_id, group_id, e);
+        }
+    }
+}
+
+#[async_trait]
+impl Service for GreeterService {
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        let TypedEvent::Notice(ref notice) = event.event else {
+            return HandlerControl::Continue;
+        };
+        let (group_id, user_id, joined) = match notice {
+            Notice::GroupIncrease(increase) => (increase.group_id, increase.user_id, true),
+            Notice::GroupDecrease(decrease)
+                if !matches!(decrease.sub_type, GroupDecreaseSubType::KickMe) =>
+            {
+                (decrease.group_id, decrease.user_id, false)
+            }
+            _ => return HandlerControl::Continue,
+        };
+        if user_id == event.self_id {
+            return HandlerControl::Continue;
+        }
+
+        let settings = match context.state.get::<GroupConfigStore<GreeterSettings>>() {
+            Some(store) => store.get(group_id).await.as_ref().clone(),
+            None => GreeterSettings::default(),
+        };
+        if !settings.enabled {
+            return HandlerControl::Continue;
+        }
+
+        let template = match joined {
+            true => settings.welcome.as_ref().or(self.welcome.as_ref()),
+            false => settings.farewell.as_ref().or(self.farewell.as_ref()),
+        };
+        let Some(template) = template else {
+            return HandlerControl::Continue;
+        };
+        if joined && !self.allow_welcome(group_id) {
+            tracing::debug!(
+                "Not welcoming {} in group {}, too many recent joins",
+                user_id,
+                group_id
+            );
+            return HandlerControl::Continue;
+        }
+
+        self.greet(&context, group_id, user_id, template, joined)
+            .await;
+        HandlerControl::Continue
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    base::{
+        context::BotContext, handler::HandlerControl, health::ConnectionState, service::Service,
+    },
+    event::BotEvent,
+};
+
+use super::http;
+
+/// Service serving the bot [`Health`] as json at `http://<addr>/health`, for container liveness probes.
+///
+/// Responds with `200 OK` while connected and `503 Service Unavailable` otherwise.
+/// The http server is started on the first connection and kept running across reconnections.
+///
+/// [`Health`]: crate::base::health::Health
+pub struct HealthService {
+    addr: SocketAddr,
+    started: AtomicBool,
+}
+
+impl HealthService {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            started: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl Service for HealthService {
+    async fn serve(&self, _: BotContext, _: BotEvent) -> HandlerControl {
+        HandlerControl::Continue
+    }
+
+    async fn init(&self, bot: BotContext) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let started = http::serve(self.addr, "health", move |request| {
+            if !request.starts_with(b"GET /health") {
+                return http::Response::not_found();
+            }
+            let health = bot.health();
+            http::Response {
+                status: if health.state == ConnectionState::Connected {
+                    "200 OK"
+                } else {
+                    "503 Service Unavailable"
+                },
+                content_type: "application/json",
+                body: serde_json::to_string(&health).unwrap_or_default(),
+            }
+        })
+        .await;
+        if !started {
+            self.started.store(false, Ordering::SeqCst);
+        }
+    }
+}
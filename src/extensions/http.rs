@@ -0,0 +1,75 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// How long to wait before accepting again after a failed accept.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A minimal http response, enough for scrapers and probes.
+pub(crate) struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub(crate) fn not_found() -> Self {
+        Self {
+            status: "404 Not Found",
+            content_type: "text/plain",
+            body: String::new(),
+        }
+    }
+}
+
+/// Bind `addr` and answer every request with `respond`, given the start of the request.
+/// Returns `false` if the address could not be bound.
+pub(crate) async fn serve<F>(addr: SocketAddr, name: &str, respond: F) -> bool
+where
+    F: Fn(&[u8]) -> Response + Send + Sync + 'static,
+{
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("Failed to bind {} endpoint on {}: {}", name, addr, e);
+            return false;
+        }
+    };
+
+    let respond = Arc::new(respond);
+    let name = name.to_string();
+    tokio::spawn(async move {
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // Errors like running out of file descriptors last, retrying at once would spin.
+                    tracing::warn!("Failed to accept a {} connection: {}", name, e);
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let Ok(n) = stream.read(&mut buf).await else {
+                    return;
+                };
+
+                let response = respond(&buf[..n]);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.status,
+                    response.content_type,
+                    response.body.len(),
+                    response.body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    true
+}
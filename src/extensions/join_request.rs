@@ -0,0 +1,207 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+use crate::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, handler::HandlerControl, service::Service},
+    event::{
+        BotEvent, TypedEvent,
+        request::{GroupRequest, GroupRequestSubType, Request},
+    },
+};
+
+/// What a [`JoinPolicy`] decided for a group request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinDecision {
+    Approve,
+    Reject {
+        reason: Option<String>,
+    },
+    /// Leave the request for someone else to handle.
+    Ignore,
+}
+
+type PolicyFn = dyn Fn(BotContext, GroupRequest) -> BoxFuture<'static, JoinDecision> + Send + Sync;
+
+/// Decides group requests for a [`JoinRequestService`].
+#[derive(Clone)]
+pub struct JoinPolicy(Arc<PolicyFn>);
+
+impl JoinPolicy {
+    pub fn new<F, Fut>(policy: F) -> Self
+    where
+        F: Fn(BotContext, GroupRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = JoinDecision> + Send + 'static,
+    {
+        Self(Arc::new(move |context, request| {
+            Box::pin(policy(context, request))
+        }))
+    }
+
+    /// Approve requests whose answer contains one of `keywords`, ignoring case, and reject the others.
+    ///
+    /// For groups with a question, the comment is `问题：...\n答案：...` and only the answer is searched.
+    pub fn answer_contains<S: Into<String>>(keywords: impl IntoIterator<Item = S>) -> Self {
+        let keywords: Arc<[String]> = keywords
+            .into_iter()
+            .map(|keyword| keyword.into().to_lowercase())
+            .collect();
+        Self::new(move |_, request| {
+            let answer = match request.comment.split_once("答案：") {
+                Some((_, answer)) => answer.to_lowercase(),
+                None => request.comment.to_lowercase(),
+            };
+            let decision = match keywords.iter().any(|keyword| answer.contains(keyword)) {
+                true => JoinDecision::Approve,
+                false => JoinDecision::Reject { reason: None },
+            };
+            async move { decision }
+        })
+    }
+
+    /// Reject accounts registered less than `min_age` ago and approve the others.
+    ///
+    /// Uses the registration time from `get_stranger_info`, or the number of login days if that is missing.
+    /// Requests are ignored when neither is known.
+    pub fn min_account_age(min_age: Duration) -> Self {
+        Self::new(move |context, request| async move {
+            let stranger = match context.get_stranger_info(request.user_id, Some(true)).await {
+                Ok(stranger) => stranger,
+                Err(e) => {
+                    tracing::warn!("Failed to look up {}: {}", request.user_id, e);
+                    return JoinDecision::Ignore;
+                }
+            };
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs() as i64);
+            let age = match (stranger.reg_time, stranger.login_days) {
+                (Some(reg_time), _) if reg_time > 0 => (now - reg_time).max(0) as u64,
+                (_, Some(days)) => days.max(0) as u64 * 24 * 60 * 60,
+                _ => return JoinDecision::Ignore,
+            };
+            match age >= min_age.as_secs() {
+                true => JoinDecision::Approve,
+                false => JoinDecision::Reject { reason: None },
+            }
+        })
+    }
+
+    /// Reject users below QQ level `min_level` and approve the others.
+    /// Requests are ignored when the implementation does not send the level.
+    pub fn min_level(min_level: i32) -> Self {
+        Self::new(move |context, request| async move {
+            let level = match context.get_stranger_info(request.user_id, Some(true)).await {
+                Ok(stranger) => stranger.account_level(),
+                Err(e) => {
+                    tracing::warn!("Failed to look up {}: {}", request.user_id, e);
+                    None
+                }
+            };
+            match level {
+                Some(level) if level >= min_level => JoinDecision::Approve,
+                Some(_) => JoinDecision::Reject { reason: None },
+                None => JoinDecision::Ignore,
+            }
+        })
+    }
+
+    pub async fn decide(&self, context: BotContext, request: GroupRequest) -> JoinDecision {
+        (self.0)(context, request).await
+    }
+}
+
+/// Service deciding group join requests with a [`JoinPolicy`].
+///
+/// Invitations of the bot to other groups are only handled if they have a policy of their own, set with [`invite_policy`](Self::invite_policy).
+/// Decisions are logged and sent to the superuser if one is set.
+pub struct JoinRequestService {
+    policy: JoinPolicy,
+    invite_policy: Option<JoinPolicy>,
+    superuser: Option<i64>,
+}
+
+impl JoinRequestService {
+    pub fn new(policy: JoinPolicy) -> Self {
+        Self {
+            policy,
+            invite_policy: None,
+            superuser: None,
+        }
+    }
+
+    pub fn invite_policy(mut self, policy: JoinPolicy) -> Self {
+        self.invite_policy = Some(policy);
+        self
+    }
+
+    /// Send every decision, including ignored requests, to `user_id`.
+    pub fn superuser(mut self, user_id: i64) -> Self {
+        self.superuser = Some(user_id);
+        self
+    }
+
+    async fn handle(&self, context: &BotContext, request: &GroupRequest) {
+        let (policy, kind) = match request.sub_type {
+            GroupRequestSubType::Add => (&self.policy, "Join request"),
+            GroupRequestSubType::Invite => match &self.invite_policy {
+                Some(policy) => (policy, "Invitation"),
+                None => return,
+            },
+        };
+        let decision = policy.decide(context.clone(), request.clone()).await;
+
+        let summary = match &decision {
+            JoinDecision::Approve => "approved".to_string(),
+            JoinDecision::Reject {
+                reason: Some(reason),
+            } => format!("rejected: {}", reason),
+            JoinDecision::Reject { reason: None } => "rejected".to_string(),
+            JoinDecision::Ignore => "left for review".to_string(),
+        };
+        let report = format!(
+            "{} of {} to group {} {}, comment: {}",
+            kind, request.user_id, request.group_id, summary, request.comment
+        );
+        tracing::info!("{}", report);
+
+        let answer = match decision {
+            JoinDecision::Approve => Some((true, None)),
+            JoinDecision::Reject { reason } => Some((false, reason)),
+            JoinDecision::Ignore => None,
+        };
+        if let Some((approve, reason)) = answer
+            && let Err(e) = context
+                .set_group_add_request(
+                    request.flag.clone(),
+                    request.sub_type.clone(),
+                    Some(approve),
+                    reason,
+                )
+                .await
+        {
+            tracing::error!("Failed to answer the request of {}: {}", request.user_id, e);
+        }
+
+        if let Some(superuser) = self.superuser
+            && let Err(e) = context.send_private_message(superuser, report, None).await
+        {
+            tracing::error!("Failed to report the request of {}: {}", request.user_id, e);
+        }
+    }
+}
+
+#[async_trait]
+impl Service for JoinRequestService {
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        if let TypedEvent::Request(Request::Group(request)) = &event.event {
+            self.handle(&context, request).await;
+        }
+        HandlerControl::Continue
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    base::{context::BotContext, handler::HandlerControl, service::Service},
+    event::BotEvent,
+};
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use super::http;
+
+/// The reason label of handler results whose reason was built at runtime, which could take any number of values.
+const DYNAMIC_REASON: &str = "dynamic";
+
+/// A label value escaped as the Prometheus text format requires, as actions and reasons may contain anything.
+struct Label<'a>(&'a str);
+
+impl fmt::Display for Label<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct ApiCallStats {
+    count: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+/// Counters collected by the bot while running, rendered in the Prometheus text format.
+///
+/// Accessible at runtime with [`Context::metrics`], and scraped over http with [`MetricsService`].
+///
+/// [`Context::metrics`]: crate::base::context::Context::metrics
+#[derive(Default)]
+pub struct Metrics {
+    events: DashMap<String, AtomicU64>,
+    handler_skip: AtomicU64,
+    handler_continue: AtomicU64,
+    handler_block: AtomicU64,
+    /// Counts by outcome and static reason, see [`HandlerControl::because`].
+    handler_reasons: DashMap<(&'static str, &'static str), AtomicU64>,
+    api_calls: DashMap<String, ApiCallStats>,
+    api_failures: DashMap<String, AtomicU64>,
+    denied_actions: DashMap<String, AtomicU64>,
+    reconnects: AtomicU64,
+    duplicate_events: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn record_event(&self, post_type: &str) {
+        self.events
+            .entry(post_type.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_handler(
+        &self,
+        control: &HandlerControl,
+        reason: Option<&Cow<'static, str>>,
+    ) {
+        let (outcome, counter) = match control {
+            HandlerControl::Skip => ("skip", &self.handler_skip),
+            HandlerControl::Continue => ("continue", &self.handler_continue),
+            HandlerControl::Block => ("block", &self.handler_block),
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Some(reason) = reason {
+            // Only `&'static str` reasons are kept, bounding the number of distinct series.
+            let label = match reason {
+                Cow::Borrowed(reason) => reason,
+                Cow::Owned(_) => DYNAMIC_REASON,
+            };
+            self.handler_reasons
+                .entry((outcome, label))
+                .or_default()
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_api_call(&self, action: &str, latency: Duration) {
+        let stats = self.api_calls.entry(action.to_string()).or_default();
+        stats.count.fetch_add(1, Ordering::Relaxed);
+        stats
+            .latency_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Record a failed api call, `retcode` is `None` when no response was received.
+    pub(crate) fn record_api_failure(&self, retcode: Option<i32>) {
+        let label = retcode.map_or_else(|| "none".to_string(), |code| code.to_string());
+        self.api_failures
+            .entry(label)
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_denied_action(&self, action: &str) {
+        self.denied_actions
+            .entry(action.to_string())
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn events_received(&self, post_type: &str) -> u64 {
+        self.events
+            .get(post_type)
+            .map_or(0, |count| count.load(Ordering::Relaxed))
+    }
+
+    /// Number of calls of `action` denied by the [`ActionPolicy`](crate::base::policy::ActionPolicy).
+    pub fn denied_actions(&self, action: &str) -> u64 {
+        self.denied_actions
+            .get(action)
+            .map_or(0, |count| count.load(Ordering::Relaxed))
+    }
+
+    pub fn handler_outcomes(&self) -> (u64, u64, u64) {
+        (
+            self.handler_skip.load(Ordering::Relaxed),
+            self.handler_continue.load(Ordering::Relaxed),
+            self.handler_block.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Number of handler results with `outcome` (`skip`, `continue` or `block`) and `reason`.
+    ///
+    /// Reasons built at runtime, e.g. with `format!`, are all counted as `"dynamic"`.
+    pub fn handler_reasons(&self, outcome: &str, reason: &str) -> u64 {
+        self.handler_reasons
+            .iter()
+            .find(|entry| entry.key().0 == outcome && entry.key().1 == reason)
+            .map_or(0, |entry| entry.value().load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn record_duplicate(&self) {
+        self.duplicate_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn duplicate_events(&self) -> u64 {
+        self.duplicate_events.load(Ordering::Relaxed)
+    }
+
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self, pending_requests: usize) -> String {
+        let mut out = String::new();
+
+        out.push_str("# TYPE flow_bot_events_received_total counter\n");
+        for entry in self.events.iter() {
+            let _ = writeln!(
+                out,
+                "flow_bot_events_received_total{{post_type=\"{}\"}} {}",
+                Label(entry.key()),
+                entry.value().load(Ordering::Relaxed)
+            );
+        }
+
+        out.push_str("# TYPE flow_bot_handler_invocations_total counter\n");
+        for (outcome, counter) in [
+            ("skip", &self.handler_skip),
+            ("continue", &self.handler_continue),
+            ("block", &self.handler_block),
+        ] {
+            let _ = writeln!(
+                out,
+                "flow_bot_handler_invocations_total{{outcome=\"{}\"}} {}",
+                outcome,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+
+        out.push_str("# TYPE flow_bot_handler_reasons_total counter\n");
+        for entry in self.handler_reasons.iter() {
+            let (outcome, reason) = entry.key();
+            let _ = writeln!(
+                out,
+                "flow_bot_handler_reasons_total{{outcome=\"{}\",reason=\"{}\"}} {}",
+                outcome,
+                Label(reason),
+                entry.value().load(Ordering::Relaxed)
+            );
+        }
+
+        out.push_str("# TYPE flow_bot_api_calls_total counter\n");
+        out.push_str("# TYPE flow_bot_api_latency_seconds_sum counter\n");
+        for entry in self.api_calls.iter() {
+            let _ = writeln!(
+                out,
+                "flow_bot_api_calls_total{{action=\"{}\"}} {}",
+                Label(entry.key()),
+                entry.value().count.load(Ordering::Relaxed)
+            );
+            let _ = writeln!(
+                out,
+                "flow_bot_api_latency_seconds_sum{{action=\"{}\"}} {}",
+                Label(entry.key()),
+                entry.value().latency_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+            );
+        }
+
+        out.push_str("# TYPE flow_bot_api_failures_total counter\n");
+        for entry in self.api_failures.iter() {
+            let _ = writeln!(
+                out,
+                "flow_bot_api_failures_total{{retcode=\"{}\"}} {}",
+                Label(entry.key()),
+                entry.value().load(Ordering::Relaxed)
+            );
+        }
+
+        out.push_str("# TYPE flow_bot_api_denied_total counter\n");
+        for entry in self.denied_actions.iter() {
+            let _ = writeln!(
+                out,
+                "flow_bot_api_denied_total{{action=\"{}\"}} {}",
+                Label(entry.key()),
+                entry.value().load(Ordering::Relaxed)
+            );
+        }
+
+        out.push_str("# TYPE flow_bot_reconnects_total counter\n");
+        let _ = writeln!(out, "flow_bot_reconnects_total {}", self.reconnects());
+
+        out.push_str("# TYPE flow_bot_duplicate_events_total counter\n");
+        let _ = writeln!(
+            out,
+            "flow_bot_duplicate_events_total {}",
+            self.duplicate_events()
+        );
+
+        out.push_str("# TYPE flow_bot_pending_requests gauge\n");
+        let _ = writeln!(out, "flow_bot_pending_requests {}", pending_requests);
+
+        out
+    }
+}
+
+/// Service serving the bot [`Metrics`] at `http://<addr>/metrics`.
+///
+/// The http server is started on the first connection and kept running across reconnections.
+pub struct MetricsService {
+    addr: SocketAddr,
+    started: AtomicBool,
+}
+
+impl MetricsService {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            started: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl Service for MetricsService {
+    async fn serve(&self, _: BotContext, _: BotEvent) -> HandlerControl {
+        HandlerControl::Continue
+    }
+
+    async fn init(&self, bot: BotContext) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let started = http::serve(self.addr, "metrics", move |request| {
+            if request.starts_with(b"GET /metrics") {
+                http::Response {
+                    status: "200 OK",
+                    content_type: "text/plain; version=0.0.4",
+                    body: bot.metrics().render(bot.pending_request_count()),
+                }
+            } else {
+                http::Response::not_found()
+            }
+        })
+        .await;
+        if !started {
+            self.started.store(false, Ordering::SeqCst);
+        }
+    }
+}
@@ -1,2 +1,24 @@
+pub mod admin;
+pub mod dialog;
+pub mod flood;
+pub mod greeter;
+#[cfg(feature = "health")]
+pub mod health;
+#[cfg(any(feature = "metrics", feature = "health"))]
+mod http;
+pub mod join_request;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod moderation;
+pub mod recall_cache;
+pub mod recall_mirror;
+pub mod recorder;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod selfcheck;
+#[cfg(feature = "sqlx-sqlite")]
+pub mod sqlite;
 #[cfg(feature = "turso")]
 pub mod turso;
+#[cfg(feature = "webhook")]
+pub mod webhook;
@@ -0,0 +1,525 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{BanDuration, api_ext::ApiExt},
+    base::{
+        context::BotContext, handler::HandlerControl, persistent::PersistentState, service::Service,
+    },
+    error::FlowError,
+    event::{
+        BotEvent, TypedEvent,
+        message::{GroupSenderRole, TypedMessageInfo},
+    },
+    message::{
+        message_ext::MessageExt,
+        segments::{Mention, Segment},
+    },
+};
+
+/// The longest mute onebot implementations accept.
+const MAX_BAN: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Parse a duration like `30s`, `10m`, `2h`, `1d` or a combination like `1h30m`.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let mut total = 0u64;
+    let mut number = None::<u64>;
+    for c in text.trim().chars() {
+        if let Some(digit) = c.to_digit(10) {
+            number = Some(
+                number
+                    .unwrap_or(0)
+                    .checked_mul(10)?
+                    .checked_add(digit as u64)?,
+            );
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        total = total.checked_add(number.take()?.checked_mul(unit)?)?;
+    }
+    if number.is_some() || total == 0 {
+        return None;
+    }
+    Some(Duration::from_secs(total))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// The user was warned, `count` is their number of warnings afterwards.
+    Warn {
+        count: u32,
+        reason: Option<String>,
+    },
+    Ban {
+        seconds: u64,
+    },
+    Unban,
+    Kick {
+        reason: Option<String>,
+    },
+    ClearWarnings,
+}
+
+impl std::fmt::Display for ModerationAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModerationAction::Warn { count, reason } => {
+                write!(f, "warning #{}", count)?;
+                if let Some(reason) = reason {
+                    write!(f, " ({})", reason)?;
+                }
+                Ok(())
+            }
+            ModerationAction::Ban { seconds } => write!(f, "muted for {}s", seconds),
+            ModerationAction::Unban => f.write_str("unmuted"),
+            ModerationAction::Kick { reason } => {
+                f.write_str("kicked")?;
+                if let Some(reason) = reason {
+                    write!(f, " ({})", reason)?;
+                }
+                Ok(())
+            }
+            ModerationAction::ClearWarnings => f.write_str("warnings cleared"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+    /// Unix timestamp in seconds.
+    pub time: i64,
+    pub user_id: i64,
+    /// The user who issued the action, `None` when it was triggered programmatically or by escalation.
+    pub operator: Option<i64>,
+    #[serde(flatten)]
+    pub action: ModerationAction,
+}
+
+/// Warnings and audit logs of a [`ModerationService`], by group.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ModerationRecords {
+    pub warnings: HashMap<i64, HashMap<i64, u32>>,
+    pub audit: HashMap<i64, VecDeque<AuditEntry>>,
+}
+
+pub struct ModerationConfig {
+    /// Prefix of the moderation commands, e.g. `/` for `/warn`.
+    pub command_prefix: String,
+    /// Kick users once they reach this many warnings, their warnings are cleared afterwards.
+    pub kick_after: Option<u32>,
+    /// Reject later join requests of kicked users.
+    pub reject_rejoin: bool,
+    /// Number of audit entries kept per group, oldest are dropped first.
+    pub audit_capacity: usize,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            command_prefix: "/".to_string(),
+            kick_after: Some(3),
+            reject_rejoin: false,
+            audit_capacity: 200,
+        }
+    }
+}
+
+/// Result of [`ModerationService::warn`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarnOutcome {
+    /// Number of warnings of the user, counting this one.
+    pub warnings: u32,
+    /// Whether the warning made the user reach [`ModerationConfig::kick_after`] and they were kicked.
+    pub kicked: bool,
+}
+
+/// Service implementing warnings, temporary bans and kicks for group admins.
+///
+/// Group owners and admins use the commands below, targeting a user with a mention or their id:
+///
+/// - `warn <user> [reason]`, kicking the user once they reach [`ModerationConfig::kick_after`] warnings
+/// - `ban <user> <duration>`, with durations as accepted by [`parse_duration`]
+/// - `unban <user>`
+/// - `kick <user> [reason]`
+/// - `warnings <user>` and `pardon <user>` to show and clear warnings
+/// - `modlog [count]` to show the latest audit entries of the group
+///
+/// The same actions are available as methods. To call them from handlers, register the service as an `Arc`
+/// with both [`with_service`] and [`with_state`] and extract it with `State<Arc<ModerationService>>`.
+///
+/// Warnings and the audit log are kept in memory, register `PersistentState<ModerationRecords>`
+/// with [`with_persistent_state`] to keep them across restarts.
+///
+/// [`with_service`]: crate::FlowBotBuilder::with_service
+/// [`with_state`]: crate::FlowBotBuilder::with_state
+/// [`with_persistent_state`]: crate::FlowBotBuilder::with_persistent_state
+pub struct ModerationService {
+    config: ModerationConfig,
+    records: Mutex<ModerationRecords>,
+}
+
+impl ModerationService {
+    pub fn new(config: ModerationConfig) -> Self {
+        Self {
+            config,
+            records: Mutex::new(ModerationRecords::default()),
+        }
+    }
+
+    /// Run `f` on the persistent records if registered, the in-memory ones otherwise.
+    async fn with_records<R>(
+        &self,
+        context: &BotContext,
+        f: impl FnOnce(&mut ModerationRecords) -> R,
+    ) -> R {
+        match context.state.get::<PersistentState<ModerationRecords>>() {
+            Some(state) => f(&mut *state.write().await),
+            None => f(&mut *self.records.lock().await),
+        }
+    }
+
+    async fn audit(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        operator: Option<i64>,
+        action: ModerationAction,
+    ) {
+        let entry = AuditEntry {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64,
+            user_id,
+            operator,
+            action,
+        };
+        let capacity = self.config.audit_capacity;
+        self.with_records(context, |records| {
+            let log = records.audit.entry(group_id).or_default();
+            log.push_back(entry);
+            while log.len() > capacity {
+                log.pop_front();
+            }
+        })
+        .await;
+    }
+
+    /// Warn a user, kicking them if they reach [`ModerationConfig::kick_after`] warnings.
+    pub async fn warn(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        operator: Option<i64>,
+        reason: Option<String>,
+    ) -> Result<WarnOutcome, FlowError> {
+        let warnings = self
+            .with_records(context, |records| {
+                let count = records
+                    .warnings
+                    .entry(group_id)
+                    .or_default()
+                    .entry(user_id)
+                    .or_default();
+                *count += 1;
+                *count
+            })
+            .await;
+        self.audit(
+            context,
+            group_id,
+            user_id,
+            operator,
+            ModerationAction::Warn {
+                count: warnings,
+                reason,
+            },
+        )
+        .await;
+
+        let kicked = self.config.kick_after.is_some_and(|n| warnings >= n);
+        if kicked {
+            self.kick(
+                context,
+                group_id,
+                user_id,
+                None,
+                Some(format!("reached {} warnings", warnings)),
+            )
+            .await?;
+            self.with_records(context, |records| {
+                if let Some(group) = records.warnings.get_mut(&group_id) {
+                    group.remove(&user_id);
+                }
+            })
+            .await;
+        }
+        Ok(WarnOutcome { warnings, kicked })
+    }
+
+    /// Mute a user, capped at the 30 days onebot implementations accept. A zero duration lifts the mute.
+    pub async fn temp_ban(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        duration: Duration,
+        operator: Option<i64>,
+    ) -> Result<(), FlowError> {
+        let seconds = duration.min(MAX_BAN).as_secs();
+        context
+            .set_group_ban(group_id, user_id, BanDuration::seconds(seconds))
+            .await?;
+        let action = match seconds {
+            0 => ModerationAction::Unban,
+            seconds => ModerationAction::Ban { seconds },
+        };
+        self.audit(context, group_id, user_id, operator, action)
+            .await;
+        Ok(())
+    }
+
+    pub async fn unban(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        operator: Option<i64>,
+    ) -> Result<(), FlowError> {
+        self.temp_ban(context, group_id, user_id, Duration::ZERO, operator)
+            .await
+    }
+
+    pub async fn kick(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        operator: Option<i64>,
+        reason: Option<String>,
+    ) -> Result<(), FlowError> {
+        context
+            .set_group_kick(group_id, user_id, Some(self.config.reject_rejoin))
+            .await?;
+        self.audit(
+            context,
+            group_id,
+            user_id,
+            operator,
+            ModerationAction::Kick { reason },
+        )
+        .await;
+        Ok(())
+    }
+
+    pub async fn warnings(&self, context: &BotContext, group_id: i64, user_id: i64) -> u32 {
+        self.with_records(context, |records| {
+            records
+                .warnings
+                .get(&group_id)
+                .and_then(|group| group.get(&user_id))
+                .copied()
+                .unwrap_or(0)
+        })
+        .await
+    }
+
+    pub async fn clear_warnings(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        operator: Option<i64>,
+    ) {
+        self.with_records(context, |records| {
+            if let Some(group) = records.warnings.get_mut(&group_id) {
+                group.remove(&user_id);
+            }
+        })
+        .await;
+        self.audit(
+            context,
+            group_id,
+            user_id,
+            operator,
+            ModerationAction::ClearWarnings,
+        )
+        .await;
+    }
+
+    /// The audit log of a group, oldest first.
+    pub async fn audit_log(&self, context: &BotContext, group_id: i64) -> Vec<AuditEntry> {
+        self.with_records(context, |records| {
+            records
+                .audit
+                .get(&group_id)
+                .map(|log| log.iter().cloned().collect())
+                .unwrap_or_default()
+        })
+        .await
+    }
+
+    /// Execute a command, returning the reply.
+    async fn run_command(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        operator: i64,
+        command: &str,
+        target: Option<i64>,
+        args: &[&str],
+    ) -> Result<String, FlowError> {
+        let rest = || Some(args.join(" ")).filter(|reason| !reason.is_empty());
+        let reply = match (command, target) {
+            ("modlog", _) => {
+                let count = args.first().and_then(|n| n.parse().ok()).unwrap_or(10);
+                let log = self.audit_log(context, group_id).await;
+                let lines = log
+                    .iter()
+                    .rev()
+                    .take(count)
+                    .map(|entry| format!("{}: {}", entry.user_id, entry.action))
+                    .collect::<Vec<_>>();
+                if lines.is_empty() {
+                    "No moderation actions yet".to_string()
+                } else {
+                    lines.join("\n")
+                }
+            }
+            (_, None) => format!("Usage: {}{} <user>", self.config.command_prefix, command),
+            ("warn", Some(user_id)) => {
+                let outcome = self
+                    .warn(context, group_id, user_id, Some(operator), rest())
+                    .await?;
+                if outcome.kicked {
+                    format!(
+                        "Warned {}, kicked after {} warnings",
+                        user_id, outcome.warnings
+                    )
+                } else {
+                    format!("Warned {} ({} warnings)", user_id, outcome.warnings)
+                }
+            }
+            ("ban", Some(user_id)) => {
+                let Some(duration) = args.first().and_then(|text| parse_duration(text)) else {
+                    return Ok(format!(
+                        "Usage: {}ban <user> <duration, e.g. 10m or 2h>",
+                        self.config.command_prefix
+                    ));
+                };
+                self.temp_ban(context, group_id, user_id, duration, Some(operator))
+                    .await?;
+                format!("Muted {} for {}s", user_id, duration.min(MAX_BAN).as_secs())
+            }
+            ("unban", Some(user_id)) => {
+                self.unban(context, group_id, user_id, Some(operator))
+                    .await?;
+                format!("Unmuted {}", user_id)
+            }
+            ("kick", Some(user_id)) => {
+                self.kick(context, group_id, user_id, Some(operator), rest())
+                    .await?;
+                format!("Kicked {}", user_id)
+            }
+            ("warnings", Some(user_id)) => {
+                let warnings = self.warnings(context, group_id, user_id).await;
+                format!("{} has {} warnings", user_id, warnings)
+            }
+            ("pardon", Some(user_id)) => {
+                self.clear_warnings(context, group_id, user_id, Some(operator))
+                    .await;
+                format!("Cleared the warnings of {}", user_id)
+            }
+            _ => unreachable!("unknown commands are filtered before"),
+        };
+        Ok(reply)
+    }
+}
+
+const COMMANDS: &[&str] = &[
+    "warn", "ban", "unban", "kick", "warnings", "pardon", "modlog",
+];
+
+#[async_trait]
+impl Service for ModerationService {
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        let TypedEvent::Message(ref msg) = event.event else {
+            return HandlerControl::Continue;
+        };
+        let TypedMessageInfo::Group(ref info) = msg.info else {
+            return HandlerControl::Continue;
+        };
+
+        let text = msg.message.extract_plain_text();
+        let mut words = text.split_whitespace();
+        let Some(command) = words
+            .next()
+            .and_then(|word| word.strip_prefix(self.config.command_prefix.as_str()))
+            .filter(|command| COMMANDS.contains(command))
+        else {
+            return HandlerControl::Continue;
+        };
+        if !matches!(
+            info.sender.role,
+            Some(GroupSenderRole::Owner | GroupSenderRole::Admin)
+        ) {
+            tracing::debug!(
+                "Ignoring moderation command of {}, who is not an admin",
+                msg.user_id
+            );
+            return HandlerControl::Continue;
+        }
+
+        let mut args = words.collect::<Vec<_>>();
+        // Mentions are not part of the plain text, an id argument is used otherwise.
+        let mentioned = msg.message.iter().find_map(|segment| match segment {
+            Segment::At(at) => match at.mention() {
+                Some(Mention::User(user_id)) => Some(user_id),
+                _ => None,
+            },
+            _ => None,
+        });
+        let target = match mentioned {
+            Some(user_id) => Some(user_id),
+            None if command == "modlog" => None,
+            None => match args.first().and_then(|id| id.parse().ok()) {
+                Some(user_id) => {
+                    args.remove(0);
+                    Some(user_id)
+                }
+                None => None,
+            },
+        };
+
+        let reply = match self
+            .run_command(&context, info.group_id, msg.user_id, command, target, &args)
+            .await
+        {
+            Ok(reply) => reply,
+            Err(e) => {
+                tracing::error!("Moderation command {} failed: {}", command, e);
+                format!("Failed to {}: {}", command, e)
+            }
+        };
+        if let Err(e) = context.send_group_message(info.group_id, reply, None).await {
+            tracing::error!("Failed to reply to moderation command: {}", e);
+        }
+        HandlerControl::Block
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use crate::{
+    api::{GetMessageType, api_ext::ApiExt},
+    base::{
+        context::BotContext,
+        extract::{BasicSenderInfo, FromEvent},
+        handler::HandlerControl,
+        service::Service,
+    },
+    event::{BotEvent, TypedEvent, message::TypedMessageInfo, notice::Notice},
+    message::Message,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Chat {
+    Group(i64),
+    Private(i64),
+}
+
+struct CachedMessage {
+    message_id: i64,
+    time: i64,
+    sender: BasicSenderInfo,
+    message: Message,
+    received: Instant,
+}
+
+/// Set on recall notices by a [`RecentMessageCache`] that still had the message.
+struct Recalled(Arc<CachedMessage>);
+
+const EVICT_EVERY: u64 = 256;
+
+/// Service remembering the latest messages of every chat, so that the [`RecalledMessage`] extractor
+/// can show recalled messages that the onebot implementation already forgot.
+///
+/// Keeps at most `capacity` messages per group or private chat, each for at most `ttl`.
+/// Register it before the handlers using [`RecalledMessage`].
+pub struct RecentMessageCache {
+    capacity: usize,
+    ttl: Duration,
+    chats: DashMap<Chat, VecDeque<Arc<CachedMessage>>>,
+    seen: AtomicU64,
+}
+
+impl RecentMessageCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            chats: DashMap::new(),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    fn insert(&self, chat: Chat, message: CachedMessage) {
+        let mut messages = self.chats.entry(chat).or_default();
+        messages.push_back(Arc::new(message));
+        while messages.len() > self.capacity {
+            messages.pop_front();
+        }
+    }
+
+    fn find(&self, chat: Chat, message_id: i64) -> Option<Arc<CachedMessage>> {
+        let messages = self.chats.get(&chat)?;
+        messages
+            .iter()
+            .rev()
+            .find(|message| message.message_id == message_id)
+            .filter(|message| message.received.elapsed() <= self.ttl)
+            .cloned()
+    }
+
+    /// Drop expired messages and chats left empty, bounding the memory used.
+    fn evict_stale(&self) {
+        self.chats.retain(|_, messages| {
+            while messages
+                .front()
+                .is_some_and(|message| message.received.elapsed() > self.ttl)
+            {
+                messages.pop_front();
+            }
+            !messages.is_empty()
+        });
+    }
+}
+
+#[async_trait]
+impl Service for RecentMessageCache {
+    async fn serve(&self, _: BotContext, event: BotEvent) -> HandlerControl {
+        match &event.event {
+            TypedEvent::Message(msg) => {
+                if self.seen.fetch_add(1, Ordering::Relaxed) % EVICT_EVERY == EVICT_EVERY - 1 {
+                    self.evict_stale();
+                }
+
+                let (chat, sender) = match &msg.info {
+                    TypedMessageInfo::Group(info) => {
+                        (Chat::Group(info.group_id), info.sender.clone().into())
+                    }
+                    TypedMessageInfo::Private(info) => {
+                        (Chat::Private(msg.user_id), info.sender.clone().into())
+                    }
+                };
+                self.insert(
+                    chat,
+                    CachedMessage {
+                        message_id: msg.message_id as i64,
+                        time: event.time,
+                        sender,
+                        message: msg.message.clone(),
+                        received: Instant::now(),
+                    },
+                );
+            }
+            TypedEvent::Notice(notice) => {
+                let found = match notice {
+                    Notice::GroupRecall(recall) => {
+                        self.find(Chat::Group(recall.group_id), recall.message_id)
+                    }
+                    Notice::FriendRecall(recall) => {
+                        self.find(Chat::Private(recall.user_id), recall.message_id)
+                    }
+                    _ => None,
+                };
+                if let Some(message) = found {
+                    event.extensions.insert(Recalled(message));
+                }
+            }
+            _ => {}
+        }
+        HandlerControl::Continue
+    }
+}
+
+/// Extractor for the content of a message recalled by a `group_recall` or `friend_recall` notice.
+///
+/// Taken from a [`RecentMessageCache`] registered before the handler, otherwise fetched with `get_msg`,
+/// which fails if the implementation already forgot the message. The handler is skipped in that case.
+#[derive(Debug, Clone)]
+pub struct RecalledMessage {
+    pub message_id: i64,
+    /// `None` for private messages.
+    pub group_id: Option<i64>,
+    /// The user who sent the message.
+    pub user_id: i64,
+    /// The user who recalled the message, the sender unless a group admin recalled it.
+    pub operator_id: i64,
+    /// When the message was sent.
+    pub time: i64,
+    pub sender: BasicSenderInfo,
+    pub message: Message,
+    /// Whether the message was found in a [`RecentMessageCache`] rather than fetched.
+    pub cached: bool,
+}
+
+#[async_trait]
+impl FromEvent for RecalledMessage {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let (message_id, group_id, user_id, operator_id) = match &event.event {
+            TypedEvent::Notice(Notice::GroupRecall(recall)) => (
+                recall.message_id,
+                Some(recall.group_id),
+                recall.user_id,
+                recall.operator_id,
+            ),
+            TypedEvent::Notice(Notice::FriendRecall(recall)) => {
+                (recall.message_id, None, recall.user_id, recall.user_id)
+            }
+            _ => return None,
+        };
+
+        if let Some(recalled) = event.extensions.get::<Recalled>() {
+            let cached = &recalled.0;
+            return Some(Self {
+                message_id,
+                group_id,
+                user_id,
+                operator_id,
+                time: cached.time,
+                sender: cached.sender.clone(),
+                message: cached.message.clone(),
+                cached: true,
+            });
+        }
+
+        let response = match context.get_message(message_id).await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!("Failed to fetch recalled message {}: {}", message_id, e);
+                return None;
+            }
+        };
+        let sender = match response.ty {
+            GetMessageType::Private { sender } => sender.into(),
+            GetMessageType::Group { sender, .. } => sender.into(),
+        };
+        Some(Self {
+            message_id,
+            group_id,
+            user_id,
+            operator_id,
+            time: response.time,
+            sender,
+            message: response.message,
+            cached: false,
+        })
+    }
+}
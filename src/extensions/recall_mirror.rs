@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+
+use crate::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, extract::FromEvent, handler::HandlerControl, service::Service},
+    event::{BotEvent, TypedEvent, message::GroupSenderRole, notice::Notice},
+    message::{
+        Message,
+        segments::{Segment, TextSegment},
+    },
+};
+
+use super::recall_cache::RecalledMessage;
+
+/// Where a [`RecallMirrorService`] reposts the messages recalled in a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorTarget {
+    Group(i64),
+    Private(i64),
+}
+
+/// Service reposting messages recalled in groups, either to an audit chat with attribution ([`mirror`](Self::mirror)),
+/// or right back into the group ([`anti_recall`](Self::anti_recall)).
+///
+/// The content comes from a [`RecentMessageCache`](super::recall_cache::RecentMessageCache) registered before the service,
+/// otherwise it is fetched with `get_msg`, see [`RecalledMessage`]. Mirrors note when it is unavailable, anti-recall then does nothing.
+///
+/// Recalls of the messages of the bot itself are ignored, so that its own reposts are never reposted.
+/// Anti-recall also leaves alone messages recalled by admins and messages of admins and the owner.
+pub struct RecallMirrorService {
+    mirrors: HashMap<i64, Vec<MirrorTarget>>,
+    anti_recall: HashSet<i64>,
+}
+
+impl Default for RecallMirrorService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecallMirrorService {
+    pub fn new() -> Self {
+        Self {
+            mirrors: HashMap::new(),
+            anti_recall: HashSet::new(),
+        }
+    }
+
+    /// Repost messages recalled in `group_id` to `target`.
+    pub fn mirror(mut self, group_id: i64, target: MirrorTarget) -> Self {
+        self.mirrors.entry(group_id).or_default().push(target);
+        self
+    }
+
+    /// Send messages recalled in `groups` again into the same group.
+    pub fn anti_recall(mut self, groups: impl IntoIterator<Item = i64>) -> Self {
+        self.anti_recall.extend(groups);
+        self
+    }
+
+    /// Whether anti-recall leaves the message alone, being recalled by an admin or sent by one.
+    async fn exempt(
+        &self,
+        context: &BotContext,
+        group_id: i64,
+        user_id: i64,
+        operator_id: i64,
+    ) -> bool {
+        if operator_id != user_id {
+            return true;
+        }
+        match context.get_group_member_info(group_id, user_id, None).await {
+            Ok(member) => member.role != GroupSenderRole::Member,
+            Err(e) => {
+                tracing::debug!(
+                    "Failed to get the role of {} in group {}: {}",
+                    user_id,
+                    group_id,
+                    e
+                );
+                false
+            }
+        }
+    }
+}
+
+fn text(text: String) -> Segment {
+    Segment::Text(TextSegment { text })
+}
+
+/// The recalled segments that can be sent again, without replies to messages of the original chat.
+fn repost_content(message: &Message) -> impl Iterator<Item = Segment> + '_ {
+    message
+        .iter()
+        .filter(|segment| !matches!(segment, Segment::Reply(_)))
+        .cloned()
+}
+
+/// `Nick (10001)`, or only the id if the nickname is unknown.
+fn display_name(nickname: Option<&str>, user_id: i64) -> String {
+    match nickname {
+        Some(nickname) => format!("{} ({})", nickname, user_id),
+        None => user_id.to_string(),
+    }
+}
+
+fn mirror_message(
+    group_id: i64,
+    user_id: i64,
+    operator_id: i64,
+    recalled: Option<&RecalledMessage>,
+) -> Message {
+    let name = display_name(
+        recalled.and_then(|recalled| recalled.sender.nickname.as_deref()),
+        user_id,
+    );
+    let header = match operator_id == user_id {
+        true => format!("{} recalled a message in group {}", name, group_id),
+        false => format!(
+            "A message of {} in group {} was recalled by {}",
+            name, group_id, operator_id
+        ),
+    };
+    let Some(recalled) = recalled else {
+        return vec![text(format!("{}, its content is unavailable", header))];
+    };
+    std::iter::once(text(format!("{}:\n", header)))
+        .chain(repost_content(&recalled.message))
+        .collect()
+}
+
+fn anti_recall_message(recalled: &RecalledMessage) -> Message {
+    let name = display_name(recalled.sender.nickname.as_deref(), recalled.user_id);
+    std::iter::once(text(format!("{} recalled:\n", name)))
+        .chain(repost_content(&recalled.message))
+        .collect()
+}
+
+#[async_trait]
+impl Service for RecallMirrorService {
+    async fn serve(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        let TypedEvent::Notice(Notice::GroupRecall(ref recall)) = event.event else {
+            return HandlerControl::Continue;
+        };
+        let (group_id, user_id, operator_id) =
+            (recall.group_id, recall.user_id, recall.operator_id);
+        let targets = self.mirrors.get(&group_id);
+        let anti_recall = self.anti_recall.contains(&group_id);
+        if (targets.is_none() && !anti_recall) || user_id == event.self_id {
+            return HandlerControl::Continue;
+        }
+
+        let recalled = RecalledMessage::from_event(context.clone(), event.clone()).await;
+        for target in targets.into_iter().flatten() {
+            let message = mirror_message(group_id, user_id, operator_id, recalled.as_ref());
+            let result = match *target {
+                MirrorTarget::Group(target) => {
+                    context.send_group_message(target, message, None).await
+                }
+                MirrorTarget::Private(target) => {
+                    context.send_private_message(target, message, None).await
+                }
+            };
+            if let Err(e) = result {
+                tracing::error!(
+                    "Failed to mirror a recall of group {} to {:?}: {}",
+                    group_id,
+                    target,
+                    e
+                );
+            }
+        }
+
+        if anti_recall
+            && let Some(recalled) = &recalled
+            && !self.exempt(&context, group_id, user_id, operator_id).await
+        {
+            let message = anti_recall_message(recalled);
+            if let Err(e) = context.send_group_message(group_id, message, None).await {
+                tracing::error!(
+                    "Failed to repost a recalled message in group {}: {}",
+                    group_id,
+                    e
+                );
+            }
+        }
+        HandlerControl::Continue
+    }
+}
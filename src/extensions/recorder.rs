@@ -0,0 +1,216 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex as StdMutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde_json::json;
+use tokio::{
+    sync::{Mutex, mpsc},
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::Utf8Bytes;
+
+use crate::{
+    base::{context::BotContext, handler::HandlerControl, service::Service},
+    event::BotEvent,
+};
+
+/// Which frames an [`EventRecorderService`] records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFilter {
+    All,
+    /// Only frames that could not be parsed as an event.
+    ParseErrors,
+}
+
+/// When an [`EventRecorderService`] starts a new file.
+#[derive(Clone, Copy, Debug)]
+pub enum Rotation {
+    Never,
+    /// Once the current file exceeds the given number of bytes.
+    Size(u64),
+    /// Once the current file is older than the given duration.
+    Interval(Duration),
+}
+
+struct Record {
+    recorded_at: u128,
+    error: Option<String>,
+    frame: Utf8Bytes,
+}
+
+/// Service appending incoming frames to `events-<unix ms>.jsonl` files in a directory, one record per line.
+///
+/// Each record holds the raw frame, the time it was recorded and, for frames that could not be parsed, the parse error.
+/// Records are written by a background task so that a stalled disk never blocks event processing,
+/// records arriving while the queue is full are dropped.
+/// The queue is closed when the bot shuts down, and the task writes what is left before the bot stops.
+pub struct EventRecorderService {
+    dir: PathBuf,
+    filter: RecordFilter,
+    rotation: Rotation,
+    // Taken on shutdown, which closes the queue.
+    queue: StdMutex<Option<mpsc::Sender<Record>>>,
+    receiver: Mutex<Option<mpsc::Receiver<Record>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventRecorderService {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let (queue, receiver) = mpsc::channel(1024);
+        Self {
+            dir: dir.into(),
+            filter: RecordFilter::All,
+            rotation: Rotation::Never,
+            queue: StdMutex::new(Some(queue)),
+            receiver: Mutex::new(Some(receiver)),
+            writer: Mutex::new(None),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: RecordFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    fn record(&self, frame: Utf8Bytes, error: Option<String>) {
+        let record = Record {
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            error,
+            frame,
+        };
+        let queue = self.queue.lock().unwrap();
+        // Closed, the bot is shutting down.
+        let Some(queue) = queue.as_ref() else {
+            return;
+        };
+        if queue.try_send(record).is_err() {
+            tracing::warn!("Event recorder queue is full, dropping frame");
+        }
+    }
+}
+
+#[async_trait]
+impl Service for EventRecorderService {
+    async fn serve(&self, _: BotContext, event: BotEvent) -> HandlerControl {
+        if self.filter == RecordFilter::All && !event.is_internal() {
+            self.record(event.raw.clone(), None);
+        }
+        HandlerControl::Continue
+    }
+
+    async fn init(&self, _: BotContext) {
+        // Only the first connection starts the writer, it keeps running across reconnections.
+        let Some(receiver) = self.receiver.lock().await.take() else {
+            return;
+        };
+
+        let dir = self.dir.clone();
+        let rotation = self.rotation;
+        let writer = tokio::task::spawn_blocking(move || write_records(&dir, rotation, receiver));
+        *self.writer.lock().await = Some(writer);
+    }
+
+    async fn on_parse_error(&self, _: BotContext, raw: Arc<str>, error: String) {
+        self.record(Utf8Bytes::from(&*raw), Some(error));
+    }
+
+    async fn on_shutdown(&self, _: BotContext) {
+        // The writer stops once the queue is closed and drained, flushing the last file.
+        self.queue.lock().unwrap().take();
+        if let Some(writer) = self.writer.lock().await.take()
+            && let Err(e) = writer.await
+        {
+            tracing::error!("Event recorder failed: {}", e);
+        }
+    }
+}
+
+struct RecordFile {
+    writer: BufWriter<File>,
+    opened_at: Instant,
+    size: u64,
+}
+
+impl RecordFile {
+    fn open(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // Rotating twice within a millisecond would append to the previous file, the next free name is taken instead.
+        let file = loop {
+            let path = dir.join(format!("events-{}.jsonl", millis));
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => millis += 1,
+                result => break result?,
+            }
+        };
+        Ok(Self {
+            writer: BufWriter::new(file),
+            opened_at: Instant::now(),
+            size: 0,
+        })
+    }
+
+    fn should_rotate(&self, rotation: Rotation) -> bool {
+        match rotation {
+            Rotation::Never => false,
+            Rotation::Size(max) => self.size >= max,
+            Rotation::Interval(interval) => self.opened_at.elapsed() >= interval,
+        }
+    }
+}
+
+fn write_records(dir: &Path, rotation: Rotation, mut receiver: mpsc::Receiver<Record>) {
+    let mut file: Option<RecordFile> = None;
+
+    while let Some(record) = receiver.blocking_recv() {
+        let result = (|| {
+            if file.as_ref().is_none_or(|f| f.should_rotate(rotation)) {
+                if let Some(mut old) = file.take() {
+                    old.writer.flush()?;
+                }
+                file = Some(RecordFile::open(dir)?);
+            }
+            let current = file.as_mut().expect("record file was just opened");
+
+            let line = json!({
+                "recorded_at": record.recorded_at,
+                "error": record.error,
+                "frame": record.frame.as_str(),
+            })
+            .to_string();
+            writeln!(current.writer, "{}", line)?;
+            current.size += line.len() as u64 + 1;
+
+            // Flush whenever the queue is drained, so that nothing is lost when the bot stops.
+            if receiver.is_empty() {
+                current.writer.flush()?;
+            }
+            Ok::<_, std::io::Error>(())
+        })();
+
+        if let Err(e) = result {
+            tracing::error!("Failed to record event in {}: {}", dir.display(), e);
+            file = None;
+        }
+    }
+
+    if let Some(mut file) = file {
+        let _ = file.writer.flush();
+    }
+}
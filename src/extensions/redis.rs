@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use tokio::sync::OnceCell;
+
+use crate::{
+    base::{context::BotContext, extract::FromEvent},
+    error::FlowError,
+    event::BotEvent,
+};
+
+/// Redis configuration, register it with [`with_state`] to enable the [`Redis`] extractors.
+///
+/// [`with_state`]: crate::FlowBotBuilder::with_state
+#[derive(Clone, Debug)]
+pub struct RedisConfig {
+    pub url: String,
+}
+
+/// Connecting is retried briefly, so that an unreachable server skips handlers instead of stalling them for minutes.
+const CONNECTION_RETRIES: usize = 1;
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY_MS: u64 = 500;
+
+pub(crate) struct RedisDispatcher {
+    config: RedisConfig,
+    manager: OnceCell<ConnectionManager>,
+}
+
+impl RedisDispatcher {
+    pub(crate) fn new(config: RedisConfig) -> Self {
+        Self {
+            config,
+            manager: OnceCell::new(),
+        }
+    }
+
+    pub(crate) async fn get_connection(&self) -> Result<ConnectionManager, FlowError> {
+        let manager = self
+            .manager
+            .get_or_try_init(|| async {
+                let client = redis::Client::open(self.config.url.as_str())?;
+                let config = ConnectionManagerConfig::new()
+                    .set_number_of_retries(CONNECTION_RETRIES)
+                    .set_max_delay(MAX_RETRY_DELAY_MS)
+                    .set_connection_timeout(CONNECTION_TIMEOUT);
+                ConnectionManager::new_with_config(client, config).await
+            })
+            .await?;
+        Ok(manager.clone())
+    }
+}
+
+async fn connect(context: &BotContext) -> Option<ConnectionManager> {
+    let dispatcher = context.state.get::<RedisDispatcher>()?;
+    match dispatcher.get_connection().await {
+        Ok(connection) => Some(connection),
+        Err(e) => {
+            tracing::error!("Failed to connect to redis: {}", e);
+            None
+        }
+    }
+}
+
+/// Extractor for a pooled redis connection.
+/// The handler is skipped if [`RedisConfig`] is not registered or the connection fails.
+pub struct Redis(pub ConnectionManager);
+
+#[async_trait::async_trait]
+impl FromEvent for Redis {
+    async fn from_event(context: BotContext, _: BotEvent) -> Option<Self> {
+        connect(&context).await.map(Self)
+    }
+}
+
+/// Like [`Redis`], but namespaces keys with `PREFIX` so that different handlers do not collide.
+pub struct RedisKeyed<const PREFIX: &'static str>(pub ConnectionManager);
+
+impl<const PREFIX: &'static str> RedisKeyed<PREFIX> {
+    /// Prefix `key` with the namespace, e.g. `cooldown:123` for `RedisKeyed<"cooldown">`.
+    pub fn key(&self, key: impl std::fmt::Display) -> String {
+        Self::prefixed(key)
+    }
+
+    /// Like [`key`](Self::key), without a connection.
+    pub fn prefixed(key: impl std::fmt::Display) -> String {
+        format!("{}:{}", PREFIX, key)
+    }
+}
+
+#[async_trait::async_trait]
+impl<const PREFIX: &'static str> FromEvent for RedisKeyed<PREFIX> {
+    async fn from_event(context: BotContext, _: BotEvent) -> Option<Self> {
+        connect(&context).await.map(Self)
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::{
+    api::{VersionInfo, api_ext::ApiExt},
+    base::{context::BotContext, handler::HandlerControl, service::Service},
+    event::{BotEvent, message::GroupSenderRole},
+};
+
+/// A problem found by a [`SelfCheckService`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SelfCheckProblem {
+    #[error("Failed to get the login info, check the connection and the access token: {0}")]
+    LoginFailed(String),
+
+    #[error("Failed to get the group list: {0}")]
+    GroupListFailed(String),
+
+    #[error("The bot is not a member of group {0}")]
+    MissingGroup(i64),
+
+    #[error("The bot is {actual:?} in group {group_id} but needs to be {required:?}")]
+    InsufficientRole {
+        group_id: i64,
+        actual: GroupSenderRole,
+        required: GroupSenderRole,
+    },
+
+    #[error("Failed to get the role of the bot in group {group_id}: {error}")]
+    RoleUnknown { group_id: i64, error: String },
+}
+
+/// The result of a [`SelfCheckService`] run.
+#[derive(Debug, Clone, Default)]
+pub struct SelfCheckReport {
+    /// The id the bot is logged in with, `None` if the login info could not be queried.
+    pub self_id: Option<i64>,
+    /// The version of the implementation, `None` if it does not answer `get_version_info`.
+    pub version: Option<VersionInfo>,
+    pub problems: Vec<SelfCheckProblem>,
+}
+
+impl SelfCheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for SelfCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.problems.len() {
+            0 => write!(f, "Self check passed")?,
+            n => write!(f, "Self check found {} problem(s):", n)?,
+        }
+        for problem in &self.problems {
+            write!(f, "\n- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Service checking on every connection that the bot is logged in, is a member of the required groups
+/// and has the required role in them, so that misconfigurations show up at startup instead of as failing calls later.
+///
+/// Problems are logged as warnings and sent to the superuser if one is set.
+/// The check runs in the background, the latest report is available from [`report`](Self::report); register the service in an `Arc` to keep access to it.
+pub struct SelfCheckService {
+    groups: Vec<i64>,
+    role: GroupSenderRole,
+    superuser: Option<i64>,
+    report: Arc<RwLock<Option<Arc<SelfCheckReport>>>>,
+}
+
+impl Default for SelfCheckService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SelfCheckService {
+    /// A check of the login only, without required groups.
+    pub fn new() -> Self {
+        Self {
+            groups: Vec::new(),
+            role: GroupSenderRole::Member,
+            superuser: None,
+            report: Arc::default(),
+        }
+    }
+
+    pub fn require_groups(mut self, groups: impl IntoIterator<Item = i64>) -> Self {
+        self.groups.extend(groups);
+        self
+    }
+
+    /// The role the bot needs in every required group, e.g. [`GroupSenderRole::Admin`] for moderation.
+    pub fn require_role(mut self, role: GroupSenderRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    /// Send the report to `user_id` when the check finds problems.
+    pub fn superuser(mut self, user_id: i64) -> Self {
+        self.superuser = Some(user_id);
+        self
+    }
+
+    /// The report of the latest check, `None` until the first one finished.
+    pub fn report(&self) -> Option<Arc<SelfCheckReport>> {
+        self.report.read().unwrap().clone()
+    }
+
+    /// Run the check now.
+    pub async fn check(&self, context: &BotContext) -> SelfCheckReport {
+        check(context, &self.groups, self.role).await
+    }
+}
+
+fn rank(role: GroupSenderRole) -> u8 {
+    match role {
+        GroupSenderRole::Member => 0,
+        GroupSenderRole::Admin => 1,
+        GroupSenderRole::Owner => 2,
+    }
+}
+
+async fn check(context: &BotContext, groups: &[i64], role: GroupSenderRole) -> SelfCheckReport {
+    let mut report = SelfCheckReport::default();
+
+    let self_id = match context.get_login_info().await {
+        Ok(login) => login.user_id,
+        Err(e) => {
+            // Nothing else can work without a login.
+            report
+                .problems
+                .push(SelfCheckProblem::LoginFailed(e.to_string()));
+            return report;
+        }
+    };
+    report.self_id = Some(self_id);
+
+    // Not every implementation answers this, which is no reason to fail the check.
+    let capabilities = context.capabilities().await;
+    report.version = capabilities.version().cloned();
+
+    if groups.is_empty() {
+        return report;
+    }
+    // Without the list, membership is left to the role lookup.
+    let joined: Option<Vec<i64>> = match context.get_group_list().await {
+        Ok(list) => Some(list.into_iter().map(|group| group.group_id).collect()),
+        Err(e) => {
+            report
+                .problems
+                .push(SelfCheckProblem::GroupListFailed(e.to_string()));
+            None
+        }
+    };
+
+    for &group_id in groups {
+        if joined
+            .as_ref()
+            .is_some_and(|joined| !joined.contains(&group_id))
+        {
+            report
+                .problems
+                .push(SelfCheckProblem::MissingGroup(group_id));
+            continue;
+        }
+        if rank(role) == 0 || !capabilities.supports("get_group_member_info") {
+            continue;
+        }
+        match context
+            .get_group_member_info(group_id, self_id, Some(true))
+            .await
+        {
+            Ok(member) if rank(member.role) < rank(role) => {
+                report.problems.push(SelfCheckProblem::InsufficientRole {
+                    group_id,
+                    actual: member.role,
+                    required: role,
+                })
+            }
+            Ok(_) => {}
+            Err(e) => report.problems.push(SelfCheckProblem::RoleUnknown {
+                group_id,
+                error: e.to_string(),
+            }),
+        }
+    }
+    report
+}
+
+impl SelfCheckService {
+    /// Run the check in the background, since no response is received while services are started.
+    fn spawn_check(&self, bot: BotContext) {
+        let groups = self.groups.clone();
+        let role = self.role;
+        let superuser = self.superuser;
+        let slot = self.report.clone();
+        tokio::spawn(async move {
+            let report = check(&bot, &groups, role).await;
+            if report.is_ok() {
+                tracing::info!("{}", report);
+            } else {
+                tracing::warn!("{}", report);
+            }
+            if let (false, Some(superuser)) = (report.is_ok(), superuser)
+                && let Err(e) = bot
+                    .send_private_message(superuser, report.to_string(), None)
+                    .await
+            {
+                tracing::error!("Failed to send the self check report: {}", e);
+            }
+            *slot.write().unwrap() = Some(Arc::new(report));
+        });
+    }
+}
+
+#[async_trait]
+impl Service for SelfCheckService {
+    async fn serve(&self, _context: BotContext, _event: BotEvent) -> HandlerControl {
+        HandlerControl::Continue
+    }
+
+    async fn init(&self, bot: BotContext) {
+        self.spawn_check(bot);
+    }
+
+    async fn on_reconnect(&self, bot: BotContext, _attempt: u32) {
+        self.spawn_check(bot);
+    }
+}
@@ -0,0 +1,115 @@
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+use tokio::sync::OnceCell;
+
+use crate::{
+    base::{context::BotContext, extract::FromEvent},
+    error::FlowError,
+    event::BotEvent,
+};
+
+/// A schema migration, applied once in ascending `version` order.
+#[derive(Clone, Copy, Debug)]
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Sqlite configuration, register it with [`with_state`] to enable the [`Db`] extractor.
+///
+/// The pool is created and the migrations are run when the bot starts, before any event is processed.
+///
+/// [`with_state`]: crate::FlowBotBuilder::with_state
+#[derive(Clone, Debug)]
+pub struct SqliteConfig {
+    pub path: String,
+    pub max_connections: u32,
+    pub migrations: Option<&'static [Migration]>,
+}
+
+pub(crate) struct SqliteDispatcher {
+    config: SqliteConfig,
+    pool: OnceCell<SqlitePool>,
+}
+
+impl SqliteDispatcher {
+    pub(crate) fn new(config: SqliteConfig) -> Self {
+        Self {
+            config,
+            pool: OnceCell::new(),
+        }
+    }
+
+    pub(crate) async fn get_pool(&self) -> Result<SqlitePool, FlowError> {
+        let pool = self
+            .pool
+            .get_or_try_init(|| async {
+                let options = SqliteConnectOptions::new()
+                    .filename(&self.config.path)
+                    .create_if_missing(true);
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(self.config.max_connections)
+                    .connect_with(options)
+                    .await?;
+                if let Some(migrations) = self.config.migrations {
+                    run_migrations(&pool, migrations).await?;
+                }
+                Ok::<_, FlowError>(pool)
+            })
+            .await?;
+        Ok(pool.clone())
+    }
+}
+
+async fn run_migrations(pool: &SqlitePool, migrations: &[Migration]) -> Result<(), FlowError> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS _flow_bot_migrations (version INTEGER PRIMARY KEY, description TEXT NOT NULL)",
+    )
+    .execute(pool)
+    .await?;
+
+    let mut migrations = migrations.to_vec();
+    migrations.sort_by_key(|migration| migration.version);
+
+    for migration in migrations {
+        let applied: Option<i64> =
+            sqlx::query_scalar("SELECT version FROM _flow_bot_migrations WHERE version = ?")
+                .bind(migration.version)
+                .fetch_optional(pool)
+                .await?;
+        if applied.is_some() {
+            continue;
+        }
+
+        // The sqlite driver runs every statement of a multi-statement query.
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration.sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO _flow_bot_migrations (version, description) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// Extractor for the sqlite pool configured by [`SqliteConfig`].
+pub struct Db(pub SqlitePool);
+
+#[async_trait::async_trait]
+impl FromEvent for Db {
+    async fn from_event(context: BotContext, _: BotEvent) -> Option<Self> {
+        let dispatcher = context.state.get::<SqliteDispatcher>()?;
+        match dispatcher.get_pool().await {
+            Ok(pool) => Some(Self(pool)),
+            Err(e) => {
+                tracing::error!("Failed to open sqlite database: {}", e);
+                None
+            }
+        }
+    }
+}
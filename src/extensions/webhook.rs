@@ -0,0 +1,262 @@
+use std::{fmt::Write as _, path::PathBuf, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{Mutex, Semaphore, mpsc},
+};
+use tokio_tungstenite::tungstenite::{Bytes, Utf8Bytes};
+
+use crate::{
+    base::{context::BotContext, handler::HandlerControl, service::Service},
+    event::{BotEvent, Event, TypedEvent},
+};
+
+/// Selects the events forwarded by a [`WebhookService`].
+#[derive(Clone)]
+pub enum WebhookFilter {
+    /// Events whose `post_type` is in the list, e.g. `message` or `notice`.
+    PostTypes(Vec<String>),
+    /// Notice events whose `notice_type` is in the list, e.g. `group_increase`.
+    NoticeTypes(Vec<String>),
+    Custom(Arc<dyn Fn(&Event) -> bool + Send + Sync>),
+}
+
+impl WebhookFilter {
+    pub fn custom<F>(filter: F) -> Self
+    where
+        F: Fn(&Event) -> bool + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(filter))
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        match self {
+            WebhookFilter::PostTypes(types) => types.iter().any(|ty| ty == event.event.get_type()),
+            WebhookFilter::NoticeTypes(types) => {
+                let notice_type = match &event.event {
+                    TypedEvent::Notice(notice) => notice.get_type(),
+                    // Notices of types unknown to the typed events.
+                    TypedEvent::Unknown(value) if value["post_type"] == "notice" => {
+                        match value["notice_type"].as_str() {
+                            Some(notice_type) => notice_type,
+                            None => return false,
+                        }
+                    }
+                    _ => return false,
+                };
+                types.iter().any(|ty| ty == notice_type)
+            }
+            WebhookFilter::Custom(filter) => filter(event),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct WebhookConfig {
+    url: String,
+    secret: Option<String>,
+    max_retries: u32,
+    retry_delay: Duration,
+    timeout: Duration,
+    dead_letter: Option<PathBuf>,
+}
+
+/// The longest delay between two attempts of a delivery.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Service forwarding the raw json of selected events to a webhook with http POST.
+///
+/// Deliveries are queued and sent in the background, so a slow webhook never delays the handlers.
+/// Failed deliveries are retried with exponential backoff, and finally written to the dead letter log if one is configured.
+pub struct WebhookService {
+    config: WebhookConfig,
+    filter: WebhookFilter,
+    concurrency: usize,
+    queue: mpsc::Sender<Utf8Bytes>,
+    receiver: Mutex<Option<mpsc::Receiver<Utf8Bytes>>>,
+}
+
+impl WebhookService {
+    pub fn new(url: impl Into<String>, filter: WebhookFilter) -> Self {
+        let (queue, receiver) = mpsc::channel(1024);
+        Self {
+            config: WebhookConfig {
+                url: url.into(),
+                secret: None,
+                max_retries: 3,
+                retry_delay: Duration::from_millis(500),
+                timeout: Duration::from_secs(10),
+                dead_letter: None,
+            },
+            filter,
+            concurrency: 4,
+            queue,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Sign the payloads with HMAC-SHA256, sent hex encoded in the `X-Signature: sha256=<hex>` header.
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.config.secret = Some(secret.into());
+        self
+    }
+
+    /// Set how many times a failed delivery is retried, 3 by default.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.config.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry, doubled for every further retry up to 5 minutes. 500ms by default.
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.config.retry_delay = delay;
+        self
+    }
+
+    /// Set how long an attempt may take before it fails and is retried, 10s by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Set how many deliveries may be in flight at once, 4 by default.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Append permanently failed payloads to the given file, one per line.
+    pub fn with_dead_letter_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.dead_letter = Some(path.into());
+        self
+    }
+}
+
+#[async_trait]
+impl Service for WebhookService {
+    async fn serve(&self, _: BotContext, event: BotEvent) -> HandlerControl {
+        if !event.is_internal()
+            && self.filter.matches(&event)
+            && self.queue.try_send(event.raw.clone()).is_err()
+        {
+            tracing::warn!(
+                "Webhook queue for {} is full, dropping event",
+                self.config.url
+            );
+        }
+        HandlerControl::Continue
+    }
+
+    async fn init(&self, _: BotContext) {
+        // Only the first connection starts the workers, they keep running across reconnections.
+        let Some(mut receiver) = self.receiver.lock().await.take() else {
+            return;
+        };
+
+        let config = self.config.clone();
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+        // A hanging webhook would otherwise hold its permit forever.
+        let client = match reqwest::Client::builder().timeout(config.timeout).build() {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("Failed to create the webhook client: {}", e);
+                return;
+            }
+        };
+        tokio::spawn(async move {
+            while let Some(payload) = receiver.recv().await {
+                let Ok(permit) = permits.clone().acquire_owned().await else {
+                    break;
+                };
+                let client = client.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    deliver(&client, &config, payload).await;
+                    drop(permit);
+                });
+            }
+        });
+    }
+}
+
+async fn deliver(client: &reqwest::Client, config: &WebhookConfig, payload: Utf8Bytes) {
+    let signature = config.secret.as_ref().map(|secret| sign(secret, &payload));
+
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(&config.url)
+            .header("Content-Type", "application/json")
+            .body(Bytes::from(payload.clone()));
+        if let Some(signature) = &signature {
+            request = request.header("X-Signature", format!("sha256={}", signature));
+        }
+
+        let error = match request.send().await {
+            Ok(resp) if resp.status().is_success() => return,
+            Ok(resp) => format!("status {}", resp.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt >= config.max_retries {
+            tracing::error!(
+                "Webhook delivery to {} failed after {} attempts: {}",
+                config.url,
+                attempt + 1,
+                error
+            );
+            write_dead_letter(config, &payload).await;
+            return;
+        }
+
+        tokio::time::sleep(retry_delay(config.retry_delay, attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// The delay before retrying after `attempt` failed, counting from zero.
+fn retry_delay(initial: Duration, attempt: u32) -> Duration {
+    initial
+        .saturating_mul(2_u32.checked_pow(attempt).unwrap_or(u32::MAX))
+        .min(MAX_RETRY_DELAY)
+}
+
+fn sign(secret: &str, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(payload.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+async fn write_dead_letter(config: &WebhookConfig, payload: &str) {
+    let Some(path) = &config.dead_letter else {
+        return;
+    };
+    let line = format!("{}\n", payload);
+    let result = async {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::error!(
+            "Failed to write webhook dead letter log {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
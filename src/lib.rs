@@ -1,6 +1,10 @@
 #![feature(try_trait_v2)]
 #![feature(adt_const_params)]
 #![feature(unsized_const_params)]
+#![allow(
+    incomplete_features,
+    reason = "unsized_const_params, for the &'static str and slice parameters of extractors like Command"
+)]
 
 //! An onebot-11 SDK that simplifies bot creation.
 //!
@@ -16,7 +20,8 @@
 //! ```no_run
 //! use flow_bot::{
 //!     FlowBotBuilder,
-//!     base::{connect::ReverseConnectionConfig, extract::Message, handler::HandlerControl},
+//!     base::{connect::{ReconnectionStrategy, ReverseConnectionConfig}, handler::HandlerControl},
+//!     event::message::Message,
 //! };
 //!
 //! async fn on_message(msg: Message) -> HandlerControl {
@@ -24,10 +29,12 @@
 //!     HandlerControl::Continue
 //! }
 //!
+//! #[tokio::main]
 //! async fn main() {
 //!     let bot = FlowBotBuilder::new(ReverseConnectionConfig {
 //!         target: "ws://localhost:19999".to_string(),
 //!         auth: None,
+//!         reconnection: ReconnectionStrategy::None,
 //!     })
 //!     .with_state(())
 //!     .with_handler(on_message)
@@ -41,14 +48,17 @@
 //!
 //! Handlers are functions that can be registered to process events. They can be registered using the [`with_handler`] method.
 //! Commonly, a handler responds to a event by calling methods in [`ApiExt`] which is implemented by [`BotContext`] to control the bot.
+//! A handler takes up to 15 extractors as arguments, or none at all, in which case it is called for every event.
 //!
 //! [`with_handler`]: crate::FlowBotBuilder::with_handler
+//! [`with_fallback_handler`]: crate::FlowBotBuilder::with_fallback_handler
 //! [`ApiExt`]: crate::api::api_ext::ApiExt
 //! [`BotContext`]: crate::base::context::BotContext
 //!
 //! The returned value of a handler is a [`HandlerControl`] which determines the flow of the event processing.
 //! [`HandlerControl::Continue`] means the event will be passed to the next handler, [`HandlerControl::Block`] means the event will not be passed to the next handler.
 //! [`HandlerControl::Skip`] means the event will be passed to the next handler but the event will not be processed by the current handler, used in the case where the event criteria is not met within the handler.
+//! If every handler skipped the event, the fallback handler set with [`with_fallback_handler`] is called.
 //! It is a crucial difference from many other bot SDKs that we do not provide a matcher machenism to match the event, so that you need to implement the logic in the handler. However, a similar way is mimiced by the extractor mechanism. See the [Extractors] section below.
 //!
 //! [`HandlerControl`]: crate::base::handler::HandlerControl
@@ -71,7 +81,7 @@
 //!
 //! ```no_run
 //! use flow_bot::{
-//!    base::extract::MatchGroupId,handler::HandlerControl
+//!    base::extract::MatchGroupId,base::handler::HandlerControl
 //! };
 //!
 //! async fn on_group_msg(_: MatchGroupId<123>) -> HandlerControl {
@@ -81,6 +91,17 @@
 //! }
 //! ```
 //!
+//! The extractors of a handler run concurrently, and the handler is skipped if one of them returns `None`.
+//! An extractor with side effects, like calling an API as [`RepliedMessage`] does, may thus run even though a cheap
+//! guard in another argument fails. Such extractors should set [`FromEvent::ORDERED`] or be wrapped in [`Seq`]:
+//! ordered extractors only run once every other argument was extracted, one by one in argument order.
+//! Argument order matters for ordered extractors only.
+//! The skipping extractor is reported with a `debug` level tracing event, naming the handler, the argument index and the extractor type.
+//!
+//! [`RepliedMessage`]: crate::base::extract::RepliedMessage
+//! [`FromEvent::ORDERED`]: crate::base::extract::FromEvent::ORDERED
+//! [`Seq`]: crate::base::extract::Seq
+//!
 //! ## Optional Extraction
 //!
 //! Extractors can be optional by using the [`Option`] type. This is useful when the data is not always present in the event.
@@ -90,7 +111,12 @@
 //! It is also possible to create custom extractors by implementing the [`FromEvent`] trait.
 //! This is an async trait that takes the context and event as arguments and returns a result of the extracted data.
 //!
+//! Extractors that call APIs should cache their results in the [`Extensions`] of the event with [`get_or_insert_async`],
+//! so that the call is made once per event no matter how many handlers use the extractor.
+//!
 //! [`FromEvent`]: crate::base::extract::FromEvent
+//! [`Extensions`]: crate::event::extensions::Extensions
+//! [`get_or_insert_async`]: crate::event::extensions::Extensions::get_or_insert_async
 //!
 //! # States
 //!
@@ -100,6 +126,10 @@
 //!
 //! [`with_state`]: crate::FlowBotBuilder::with_state
 //!
+//! States that should survive restarts can be added with [`with_persistent_state`] instead, which stores them as json files.
+//!
+//! [`with_persistent_state`]: crate::FlowBotBuilder::with_persistent_state
+//!
 //! In a handler, a state is accessed by using the [`State`] extractor.
 //!
 //! [`State`]: crate::base::extract::State
@@ -116,31 +146,64 @@
 //! [`Service`]: crate::base::service::Service
 //! [`with_service`]: crate::FlowBotBuilder::with_service
 use std::{
-    any::Any,
+    any::{Any, TypeId},
     ops::Deref,
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
     sync::{
         Arc,
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicI64, AtomicU32, Ordering},
     },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use api::{
+    AutoEscape, RecordFormat,
+    digest::ParamRedaction,
+    quirks::{QuirkProfile, Quirks},
+};
 use base::{
     connect::ReverseConnectionConfig,
     context::{BotContext, Context, StateMap},
-    handler::{ErasedHandler, HWrapped, Handler, HandlerControl},
+    dead_letter::{DeadLetter, DeadLetterFile, FailedCall},
+    debug::DebugState,
+    dedup::EventDedup,
+    explain::{HandlerExplanation, Verdict, dry_run},
+    extract::EventTime,
+    filter::EventFilter,
+    group_config::GroupConfigStore,
+    handler::{BoxedHandler, ErasedHandler, Handler, HandlerControl, HandlerMeta, with_reason},
+    health::{ConnectionState, Health, InFlight},
+    identity::IdentityCounts,
+    mute::MuteAwareness,
+    outbox::OutboxConfig,
+    outgoing::{HookResult, OutgoingHook, OutgoingHooks, OutgoingMessage},
+    persistent::PersistentState,
+    plugin::Plugin,
+    policy::ActionPolicy,
+    pool::HandlerPool,
     service::Service,
+    storage::HandlerStorage,
+};
+use error::{BuildError, FlowError};
+use event::{
+    BotEvent, Event, TypedEvent,
+    internal::{Connected, Disconnected, InternalEvent, Reconnecting},
+    message::{ReplyStyle, TypedMessageInfo},
+    meta_event::MetaEvent,
 };
-use error::FlowError;
-use event::Event;
 use futures::{
-    StreamExt,
+    FutureExt, StreamExt,
     stream::{SplitSink, SplitStream},
 };
+use message::download::{AudioFormat, AudioTranscoder};
+use serde::{Serialize, de::DeserializeOwned};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
     MaybeTlsStream, WebSocketStream, connect_async,
     tungstenite::{Message, Utf8Bytes, client::IntoClientRequest},
 };
+use tracing::Instrument;
 
 pub mod api;
 pub mod base;
@@ -157,17 +220,82 @@ enum HandlerOrService {
     Service(Box<dyn Service>),
 }
 
+impl HandlerOrService {
+    fn name(&self) -> &'static str {
+        match self {
+            HandlerOrService::Handler(handler) => handler.name(),
+            HandlerOrService::Service(service) => service.name(),
+        }
+    }
+
+    async fn call(&self, context: BotContext, event: BotEvent) -> HandlerControl {
+        match self {
+            HandlerOrService::Handler(handler) => handler.call(context, event).await,
+            HandlerOrService::Service(service) => service.serve(context, event).await,
+        }
+    }
+
+    async fn check(&self, context: BotContext, event: BotEvent) -> Verdict {
+        match self {
+            HandlerOrService::Handler(handler) => match handler.check(context, event).await {
+                Ok(()) => Verdict::Matched,
+                Err(miss) => Verdict::Skipped(miss),
+            },
+            HandlerOrService::Service(_) => Verdict::Service,
+        }
+    }
+}
+
+struct HandlerEntry {
+    inner: HandlerOrService,
+    filter: EventFilter,
+    group: Option<&'static str>,
+    /// Run in the [`HandlerPool`].
+    blocking: bool,
+}
+
+/// How long a [`shutdown`](Context::shutdown) waits for running handlers before closing the connection.
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+type ControlPolicy =
+    dyn Fn(&Event, HandlerControl, HandlerMeta<'_>) -> HandlerControl + Send + Sync;
+
 pub struct FlowBot {
-    handlers: Arc<Vec<HandlerOrService>>,
+    handlers: Arc<Vec<HandlerEntry>>,
+    fallback: Option<Arc<dyn ErasedHandler>>,
+    events: EventFilter,
+    max_event_age: Option<Duration>,
+    dedup: Option<EventDedup>,
+    control_policy: Option<Arc<ControlPolicy>>,
+    handler_pool: Option<Arc<HandlerPool>>,
     context: BotContext,
     connection: ReverseConnectionConfig,
     reconnect_attempt: AtomicU32,
+    self_id: AtomicI64,
 }
 
+type PersistentStateLoader = Box<dyn FnOnce(&Path, &mut StateMap) + Send>;
+
 pub struct FlowBotBuilder {
-    handlers: Vec<HandlerOrService>,
+    handlers: Vec<HandlerEntry>,
+    fallback: Option<Box<dyn ErasedHandler>>,
+    events: EventFilter,
+    max_event_age: Option<Duration>,
+    dedup: Option<(Duration, usize)>,
+    control_policy: Option<Arc<ControlPolicy>>,
+    handler_group: Option<&'static str>,
+    handler_threads: Option<usize>,
+    outbox: Option<OutboxConfig>,
+    outgoing_hooks: Vec<OutgoingHook>,
+    quirk_profiles: Vec<QuirkProfile>,
     connection: ReverseConnectionConfig,
     states: StateMap,
+    persistent_state_dir: PathBuf,
+    persistent_states: Vec<PersistentStateLoader>,
+    /// Types of the states loaded by `persistent_states`.
+    persistent_state_types: Vec<TypeId>,
+    duplicate_states: Vec<&'static str>,
+    installing_plugin: Option<&'static str>,
 }
 
 impl FlowBotBuilder {
@@ -175,52 +303,600 @@ impl FlowBotBuilder {
     pub fn new(connection: ReverseConnectionConfig) -> Self {
         Self {
             handlers: Vec::new(),
+            fallback: None,
+            events: EventFilter::ALL,
+            max_event_age: None,
+            dedup: None,
+            control_policy: None,
+            handler_group: None,
+            handler_threads: None,
+            outbox: None,
+            outgoing_hooks: Vec::new(),
+            quirk_profiles: Vec::new(),
             connection,
             states: StateMap::new(),
+            persistent_state_dir: PathBuf::from("./persistent_states"),
+            persistent_states: Vec::new(),
+            persistent_state_types: Vec::new(),
+            duplicate_states: Vec::new(),
+            installing_plugin: None,
         }
     }
 
     /// Add a state to the bot.
     /// If the state of the same type is already present, it will be replaced.
-    pub fn with_state<S: 'static + Any + Send + Sync>(mut self, state: S) -> Self {
-        self.states.insert(state);
+    ///
+    /// States added by a [`Plugin`] do not replace existing states, so that plugins can not override user configuration.
+    pub fn with_state<S: 'static + Any + Send + Sync>(self, state: S) -> Self {
+        self.insert_state(state, StateMap::insert)
+    }
+
+    /// Add a state like [`with_state`](Self::with_state), which also shows its content in [`Context::debug_snapshot`].
+    pub fn with_debug_state<S: DebugState>(self, state: S) -> Self {
+        self.insert_state(state, StateMap::insert_debug)
+    }
+
+    fn insert_state<S: 'static + Any + Send + Sync>(
+        mut self,
+        state: S,
+        insert: fn(&mut StateMap, S) -> bool,
+    ) -> Self {
+        if let Some(plugin) = self.installing_plugin
+            && self.states.contains::<S>()
+        {
+            tracing::warn!(
+                "Plugin {} tried to replace state {}, keeping the existing state",
+                plugin,
+                std::any::type_name::<S>()
+            );
+            return self;
+        }
+
+        if insert(&mut self.states, state) {
+            self.duplicate_states.push(std::any::type_name::<S>());
+        }
+        self
+    }
+
+    /// Add a state that is persisted as `<key>.json` in the persistent state directory.
+    /// The stored value is loaded when the bot is built, falling back to `default` if it is missing or corrupted.
+    ///
+    /// In a handler, it is accessed with `State<PersistentState<S>>`.
+    pub fn with_persistent_state<S>(self, key: &str, default: S) -> Self
+    where
+        S: 'static + Serialize + DeserializeOwned + Send + Sync,
+    {
+        let key = key.to_string();
+        self.push_persistent_state::<PersistentState<S>>(Box::new(move |dir, states| {
+            states.insert(PersistentState::load(dir, &key, default));
+        }))
+    }
+
+    /// Add a per-group configuration persisted as `<key>.json` in the persistent state directory,
+    /// see [`GroupConfigStore`](base::group_config::GroupConfigStore).
+    pub fn with_group_config<T>(self, key: &str) -> Self
+    where
+        T: 'static + Serialize + DeserializeOwned + Default + Clone + Send + Sync,
+    {
+        let key = key.to_string();
+        self.push_persistent_state::<GroupConfigStore<T>>(Box::new(move |dir, states| {
+            states.insert(GroupConfigStore::<T>::load(dir, &key));
+        }))
+    }
+
+    fn push_persistent_state<S: 'static>(mut self, load: PersistentStateLoader) -> Self {
+        if let Some(plugin) = self.installing_plugin
+            && self.persistent_state_types.contains(&TypeId::of::<S>())
+        {
+            tracing::warn!(
+                "Plugin {} tried to replace state {}, keeping the existing state",
+                plugin,
+                std::any::type_name::<S>()
+            );
+            return self;
+        }
+
+        self.persistent_states.push(load);
+        self.persistent_state_types.push(TypeId::of::<S>());
+        self
+    }
+
+    /// Let groups override the prefix of [`Command`](base::extract::Command)s and disable some of them,
+    /// with a [`CommandConfig`](base::extract::CommandConfig) persisted as `commands.json`.
+    pub fn with_command_config(self) -> Self {
+        self.with_group_config::<base::extract::CommandConfig>(base::extract::COMMAND_CONFIG_KEY)
+    }
+
+    /// Set the directory persistent states are stored in, `./persistent_states` by default.
+    pub fn with_persistent_state_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.persistent_state_dir = dir.into();
         self
     }
 
     /// Add a handler to the bot.
     /// The order of the handlers added is the order in which they will be called.
+    ///
+    /// Closures using values they capture can be wrapped with [`handler_fn`](base::handler::handler_fn).
     pub fn with_handler<T, H>(mut self, handler: H) -> Self
     where
         T: Send + Sync + 'static,
         H: Handler<T> + Send + Sync + 'static,
     {
-        let wrapped = HWrapped {
-            handler,
-            _phantom: std::marker::PhantomData,
-        };
-        self.handlers
-            .push(HandlerOrService::Handler(Box::new(wrapped)));
+        self.push_handler(
+            HandlerOrService::Handler(BoxedHandler::new(handler).0),
+            EventFilter::ALL,
+        );
+        self
+    }
+
+    /// Add a handler labelled `name` in logs, statistics and control policies, instead of the type name of the function.
+    pub fn with_named_handler<T, H>(mut self, name: &'static str, handler: H) -> Self
+    where
+        T: Send + Sync + 'static,
+        H: Handler<T> + Send + Sync + 'static,
+    {
+        self.push_handler(
+            HandlerOrService::Handler(BoxedHandler::named(name, handler).0),
+            EventFilter::ALL,
+        );
+        self
+    }
+
+    /// Add a handler that is only called for the event types in `filter`.
+    ///
+    /// The filter is checked before any extractor runs, which saves work for handlers that only handle a few types.
+    /// Filtered out events are treated as if the handler returned [`HandlerControl::Skip`].
+    pub fn with_handler_filtered<T, H>(mut self, handler: H, filter: EventFilter) -> Self
+    where
+        T: Send + Sync + 'static,
+        H: Handler<T> + Send + Sync + 'static,
+    {
+        self.push_handler(
+            HandlerOrService::Handler(BoxedHandler::new(handler).0),
+            filter,
+        );
+        self
+    }
+
+    /// Add a handler doing CPU heavy or blocking work, which runs away from the runtime reading the connection
+    /// so that it cannot delay other handlers.
+    ///
+    /// It runs on the blocking threads of the runtime, or on a dedicated runtime set up with [`with_blocking_handlers`](Self::with_blocking_handlers).
+    pub fn with_blocking_handler<T, H>(mut self, handler: H) -> Self
+    where
+        T: Send + Sync + 'static,
+        H: Handler<T> + Send + Sync + 'static,
+    {
+        self.push_handler(
+            HandlerOrService::Handler(BoxedHandler::new(handler).0),
+            EventFilter::ALL,
+        );
+        if let Some(entry) = self.handlers.last_mut() {
+            entry.blocking = true;
+        }
+        self
+    }
+
+    /// Run the handlers added with [`with_blocking_handler`](Self::with_blocking_handler) on a dedicated runtime with `threads` worker threads.
+    pub fn with_blocking_handlers(mut self, threads: usize) -> Self {
+        self.handler_threads = Some(threads);
+        self
+    }
+
+    /// Add multiple handlers at once, in the order given.
+    pub fn with_handlers<I>(mut self, handlers: I) -> Self
+    where
+        I: IntoIterator<Item = BoxedHandler>,
+    {
+        for handler in handlers {
+            self.push_handler(HandlerOrService::Handler(handler.0), EventFilter::ALL);
+        }
+        self
+    }
+
+    /// Register the handlers and services added by `register` in the group `group`, which control policies can tell apart.
+    ///
+    /// # Example
+    /// ```ignore
+    /// builder.with_handler_group("commands", |builder| {
+    ///     builder.with_handler(help).with_handler(ping)
+    /// })
+    /// ```
+    pub fn with_handler_group<F>(mut self, group: &'static str, register: F) -> Self
+    where
+        F: FnOnce(Self) -> Self,
+    {
+        let outer = self.handler_group.replace(group);
+        let mut builder = register(self);
+        builder.handler_group = outer;
+        builder
+    }
+
+    /// Map the control returned by every handler and service before the dispatch loop acts on it.
+    ///
+    /// The policy sees the event, the returned control and the [`HandlerMeta`] of the handler. It does not apply to panicking handlers,
+    /// which always stop the chain, nor to the fallback handler. Handler statistics and metrics record the control before it is mapped.
+    /// Setting it again replaces the previous one.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Only the first command handling a message runs.
+    /// builder.with_control_policy(|_, control, meta| match (control, meta.group) {
+    ///     (HandlerControl::Continue, Some("commands")) => HandlerControl::Block,
+    ///     (control, _) => control,
+    /// })
+    /// ```
+    pub fn with_control_policy<F>(mut self, policy: F) -> Self
+    where
+        F: Fn(&Event, HandlerControl, HandlerMeta<'_>) -> HandlerControl + Send + Sync + 'static,
+    {
+        self.control_policy = Some(Arc::new(policy));
+        self
+    }
+
+    fn push_handler(&mut self, inner: HandlerOrService, filter: EventFilter) {
+        self.handlers.push(HandlerEntry {
+            inner,
+            filter,
+            group: self.handler_group,
+            blocking: false,
+        });
+    }
+
+    /// Only dispatch events of the types in `filter`, other events reach no handler, service or the fallback handler.
+    pub fn with_event_filter(mut self, filter: EventFilter) -> Self {
+        self.events = filter;
+        self
+    }
+
+    /// Do not dispatch heartbeat meta events, which most bots never handle but arrive every few seconds.
+    /// They are still used for [`health`](FlowBot::health).
+    pub fn with_ignore_heartbeats(mut self, ignore: bool) -> Self {
+        if ignore {
+            self.events &= !EventFilter::HEARTBEAT;
+        } else {
+            self.events |= EventFilter::HEARTBEAT;
+        }
+        self
+    }
+
+    /// Drop events that happened more than `max_age` ago instead of dispatching them,
+    /// e.g. those some implementations replay after a reconnect, so that the bot does not answer hour-old commands.
+    ///
+    /// Ages are compared in whole seconds, the precision of the `time` of events.
+    pub fn with_max_event_age(mut self, max_age: Duration) -> Self {
+        self.max_event_age = Some(max_age);
+        self
+    }
+
+    /// Drop message and notice events seen less than `window` ago, such as those some implementations redeliver after a reconnect.
+    ///
+    /// Messages are recognized by their id and notices by their whole payload. At most `capacity` events are remembered,
+    /// the oldest ones are forgotten first. Dropped events are counted in [`Health::duplicate_events`].
+    pub fn with_event_dedup(mut self, window: Duration, capacity: usize) -> Self {
+        self.dedup = Some((window, capacity));
+        self
+    }
+
+    /// Set a handler that is called only when no handler or service handled the event,
+    /// that is every one of them returned [`HandlerControl::Skip`]. Returning [`HandlerControl::Continue`] counts as handled.
+    ///
+    /// Useful for "unknown command" replies and for logging unhandled events. Setting it again replaces the previous one.
+    pub fn with_fallback_handler<T, H>(mut self, handler: H) -> Self
+    where
+        T: Send + Sync + 'static,
+        H: Handler<T> + Send + Sync + 'static,
+    {
+        self.fallback = Some(BoxedHandler::new(handler).0);
+        self
+    }
+
+    /// Convert records with `transcode` when the onebot implementation cannot, see [`RecordSegment::fetch`].
+    ///
+    /// [`RecordSegment::fetch`]: message::segments::RecordSegment::fetch
+    pub fn with_audio_transcoder<F, Fut>(self, transcode: F) -> Self
+    where
+        F: Fn(Vec<u8>, AudioFormat, RecordFormat) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<u8>, FlowError>> + Send + 'static,
+    {
+        self.with_state(AudioTranscoder::new(transcode))
+    }
+
+    /// Set the `auto_escape` sent when send calls pass `None`, equivalent to registering [`AutoEscape`] with [`with_state`](Self::with_state).
+    pub fn with_auto_escape(self, auto_escape: bool) -> Self {
+        self.with_state(AutoEscape(auto_escape))
+    }
+
+    /// Pass every failed API call to `sink`, e.g. to store it and send it again later with [`FailedCall::redrive`].
+    ///
+    /// A call fails when it gets no response, e.g. on a timeout or while disconnected, or a non-zero retcode.
+    /// The sink runs once per failure, before the error is returned to the caller.
+    pub fn with_dead_letter<F, Fut>(self, sink: F) -> Self
+    where
+        F: Fn(FailedCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.with_state(DeadLetter::new(sink))
+    }
+
+    /// Append every failed API call to a JSON lines file, see [`DeadLetterFile`].
+    /// The file is also registered as a state, so that handlers can redrive the calls.
+    pub fn with_dead_letter_file(self, file: DeadLetterFile) -> Self {
+        self.with_state(DeadLetter::from(file.clone()))
+            .with_state(file)
+    }
+
+    /// Run `hook` on every message before it is sent with `send_private_msg`, `send_group_msg` or `send_msg`,
+    /// to change it or stop it from being sent, see [`HookResult`]. Hooks run in the order they are registered.
+    pub fn with_outgoing_hook<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(OutgoingMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HookResult> + Send + 'static,
+    {
+        self.outgoing_hooks.push(OutgoingHook::new(hook));
+        self
+    }
+
+    /// Rewrite requests with `profile` when connected to an implementation it matches, see [`QuirkProfile`].
+    /// Profiles are tried in the order they are registered, before the built-in ones.
+    pub fn with_quirk_profile(mut self, profile: QuirkProfile) -> Self {
+        self.quirk_profiles.push(profile);
+        self
+    }
+
+    /// Keep track of the groups the bot is muted in, failing sends there with [`FlowError::SelfMuted`] until the ban ends,
+    /// see [`MuteAwareness`].
+    pub fn with_mute_awareness(self) -> Self {
+        self.with_debug_state(MuteAwareness::default())
+    }
+
+    /// Restrict the API actions the bot may call, see [`ActionPolicy`].
+    /// Denied calls fail with [`FlowError::ActionDenied`] and are logged with the name of the calling handler.
+    pub fn with_action_policy(self, policy: ActionPolicy) -> Self {
+        self.with_debug_state(policy)
+    }
+
+    /// Also redact `fields` from the params shown in [`FlowError::ApiCall`], besides the default ones, see [`ParamRedaction`].
+    pub fn with_redacted_params<I, S>(self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let redaction = fields
+            .into_iter()
+            .fold(ParamRedaction::default(), |redaction, field| {
+                redaction.with_field(field)
+            });
+        self.with_state(redaction)
+    }
+
+    /// Also count the groups and friends of the bot in its [`BotIdentity`](base::identity::BotIdentity). Off by default,
+    /// as listing them can be slow on accounts with many of them.
+    pub fn with_identity_counts(self) -> Self {
+        self.with_state(IdentityCounts)
+    }
+
+    /// Set how [`Context::reply`] refers to the message being answered, equivalent to registering the style with [`with_state`](Self::with_state).
+    pub fn with_reply_style(self, style: ReplyStyle) -> Self {
+        self.with_state(style)
+    }
+
+    /// Buffer up to `capacity` send actions while the bot is disconnected and send them once it reconnects,
+    /// dropping those older than `max_age`. See [`OutboxConfig`] for more options.
+    pub fn with_offline_buffering(self, capacity: usize, max_age: Duration) -> Self {
+        self.with_outbox(OutboxConfig::new(capacity, max_age))
+    }
+
+    /// Buffer API calls while the bot is disconnected, as configured by `config`.
+    pub fn with_outbox(mut self, config: OutboxConfig) -> Self {
+        self.outbox = Some(config);
         self
     }
 
+    /// Install a plugin, adding its handlers, services and states.
+    pub fn with_plugin<P: Plugin>(mut self, plugin: P) -> Self {
+        let outer = self.installing_plugin.replace(std::any::type_name::<P>());
+        let mut builder = plugin.install(self);
+        builder.installing_plugin = outer;
+        builder
+    }
+
     /// Add a service to the bot.
     pub fn with_service<Svc>(mut self, service: Svc) -> Self
     where
         Svc: Service + Send + Sync + 'static,
     {
-        self.handlers
-            .push(HandlerOrService::Service(Box::new(service)));
+        self.push_handler(
+            HandlerOrService::Service(Box::new(service)),
+            EventFilter::ALL,
+        );
         self
     }
 
+    /// Add a service together with a state it requires, see [`Service::required_states`].
+    pub fn with_service_state<Svc, S>(self, service: Svc, state: S) -> Self
+    where
+        Svc: Service + Send + Sync + 'static,
+        S: 'static + Any + Send + Sync,
+    {
+        self.with_state(state).with_service(service)
+    }
+
+    /// Check the configuration for problems that would otherwise only show up at runtime.
+    /// Returns every problem found, see [`BuildError::is_warning`] for which of them are fatal.
+    pub fn validate(&self) -> Result<(), Vec<BuildError>> {
+        use base::connect::ReconnectionStrategy;
+        use tokio_tungstenite::tungstenite::http::{HeaderValue, Uri};
+
+        let mut errors = Vec::new();
+
+        let target = &self.connection.target;
+        if target.is_empty() {
+            errors.push(BuildError::EmptyTarget);
+        } else {
+            let is_ws = target
+                .parse::<Uri>()
+                .ok()
+                .and_then(|uri| {
+                    uri.scheme_str()
+                        .map(|scheme| scheme == "ws" || scheme == "wss")
+                })
+                .unwrap_or(false);
+            if !is_ws {
+                errors.push(BuildError::InvalidTarget(target.clone()));
+            }
+        }
+
+        if let Some(auth) = &self.connection.auth
+            && HeaderValue::from_str(auth).is_err()
+        {
+            errors.push(BuildError::InvalidAuth);
+        }
+
+        let delays = match &self.connection.reconnection {
+            ReconnectionStrategy::None => None,
+            ReconnectionStrategy::Infinite {
+                initial_delay_ms,
+                max_delay_ms,
+            } => Some((*initial_delay_ms, *max_delay_ms)),
+            ReconnectionStrategy::Limited {
+                max_attempts,
+                initial_delay_ms,
+                max_delay_ms,
+            } => {
+                if *max_attempts == 0 {
+                    errors.push(BuildError::InvalidReconnection(
+                        "max_attempts must be nonzero",
+                    ));
+                }
+                Some((*initial_delay_ms, *max_delay_ms))
+            }
+        };
+        if let Some((initial_delay_ms, max_delay_ms)) = delays {
+            if initial_delay_ms == 0 {
+                errors.push(BuildError::InvalidReconnection(
+                    "initial_delay_ms must be nonzero",
+                ));
+            }
+            if initial_delay_ms > max_delay_ms {
+                errors.push(BuildError::InvalidReconnection(
+                    "initial_delay_ms must not exceed max_delay_ms",
+                ));
+            }
+        }
+
+        if self.handlers.is_empty() {
+            errors.push(BuildError::NoHandlers);
+        }
+
+        errors.extend(
+            self.duplicate_states
+                .iter()
+                .map(|name| BuildError::DuplicateState(name)),
+        );
+
+        for handler in &self.handlers {
+            let HandlerOrService::Service(service) = &handler.inner else {
+                continue;
+            };
+            for required in service.required_states() {
+                if !self.states.contains_id(required.type_id)
+                    && !self.persistent_state_types.contains(&required.type_id)
+                {
+                    errors.push(BuildError::MissingState {
+                        service: service.name(),
+                        state: required.name,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Validate the configuration and build the FlowBot.
+    /// Fails if [`validate`] reports any problem that is not a warning.
+    ///
+    /// [`validate`]: FlowBotBuilder::validate
+    pub fn try_build(self) -> Result<FlowBot, Vec<BuildError>> {
+        if let Err(errors) = self.validate() {
+            if errors.iter().any(|e| !e.is_warning()) {
+                return Err(errors);
+            }
+            for warning in errors {
+                tracing::warn!("{}", warning);
+            }
+        }
+        self.build_unchecked().map_err(|error| vec![error])
+    }
+
     /// Build the FlowBot.
+    /// Problems found by [`validate`] are logged but do not prevent building, use [`try_build`] to fail on them.
+    ///
+    /// # Panics
+    /// If the runtime for blocking handlers cannot be started, which [`try_build`] returns as an error instead.
+    ///
+    /// [`validate`]: FlowBotBuilder::validate
+    /// [`try_build`]: FlowBotBuilder::try_build
     pub fn build(self) -> FlowBot {
-        FlowBot {
+        if let Err(errors) = self.validate() {
+            for error in errors {
+                tracing::warn!("{}", error);
+            }
+        }
+        self.build_unchecked()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    fn build_unchecked(mut self) -> Result<FlowBot, BuildError> {
+        for load in self.persistent_states {
+            load(&self.persistent_state_dir, &mut self.states);
+        }
+        self.states
+            .insert(HandlerStorage::load(&self.persistent_state_dir));
+        if !self.outgoing_hooks.is_empty() {
+            self.states.insert(OutgoingHooks(self.outgoing_hooks));
+        }
+
+        let mut context = Context::new(self.states, self.outbox);
+        context.quirks = Quirks::new(self.quirk_profiles);
+        #[cfg(feature = "handler-stats")]
+        {
+            context.handler_stats = self
+                .handlers
+                .iter()
+                .map(|handler| base::handler_stats::HandlerStatsCell::new(handler.inner.name()))
+                .collect();
+        }
+
+        let handler_pool = self
+            .handlers
+            .iter()
+            .any(|handler| handler.blocking)
+            .then(|| HandlerPool::new(self.handler_threads))
+            .transpose()
+            .map_err(|error| BuildError::HandlerRuntime(error.to_string()))?
+            .map(Arc::new);
+
+        Ok(FlowBot {
+            handler_pool,
             handlers: Arc::new(self.handlers),
-            context: BotContext::new(Context::new(self.states)),
+            fallback: self.fallback.map(Arc::from),
+            events: self.events,
+            max_event_age: self.max_event_age,
+            dedup: self
+                .dedup
+                .map(|(window, capacity)| EventDedup::new(window, capacity)),
+            control_policy: self.control_policy,
+            context: BotContext::new(context),
             connection: self.connection,
             reconnect_attempt: AtomicU32::new(0),
-        }
+            self_id: AtomicI64::new(0),
+        })
     }
 }
 
@@ -231,7 +907,9 @@ impl FlowBot {
     pub async fn run(&self) -> Result<(), FlowError> {
         use base::connect::ReconnectionStrategy;
 
-        match &self.connection.reconnection {
+        self.context.init_extensions().await?;
+
+        let result = match &self.connection.reconnection {
             ReconnectionStrategy::None => self.run_once().await,
             ReconnectionStrategy::Infinite {
                 initial_delay_ms,
@@ -248,20 +926,170 @@ impl FlowBot {
                 self.run_with_limited_reconnect(*max_attempts, *initial_delay_ms, *max_delay_ms)
                     .await
             }
+        };
+
+        if self.context.is_shutting_down() {
+            for service in self.services() {
+                service.on_shutdown(self.context.clone()).await;
+            }
         }
+        result
+    }
+
+    /// A snapshot of the connection state and activity of the bot.
+    pub fn health(&self) -> Health {
+        self.context.health()
+    }
+
+    /// The context handlers are called with, e.g. to make API calls from outside of handlers.
+    pub fn context(&self) -> BotContext {
+        self.context.clone()
+    }
+
+    /// Stop the bot gracefully, see [`Context::shutdown`].
+    pub fn shutdown(&self) {
+        self.context.shutdown();
+    }
+
+    /// Statistics of every registered handler and service, in registration order.
+    #[cfg(feature = "handler-stats")]
+    pub fn handler_stats(&self) -> Vec<base::handler_stats::HandlerStats> {
+        self.context.handler_stats()
+    }
+
+    /// Which of the registered handlers would be called with `event`, the json of an event as sent by the implementation,
+    /// without calling any of them, e.g. to find out why the bot did not respond to a message.
+    ///
+    /// Only the extractors of the handlers run, in a [dry run](base::explain::is_dry_run) against the real context,
+    /// so extractors calling the API still call it. Services are not run, so extractors relying on what a service
+    /// did, like [`NotFlooding`](extensions::flood::NotFlooding), see the event as if it had not.
+    /// Whether a handler would block the event is not known without calling it, so every handler is reported.
+    /// The age of the event is not checked, so that logged events can be explained later.
+    ///
+    /// ```
+    /// use flow_bot::{
+    ///     FlowBotBuilder,
+    ///     base::{
+    ///         connect::{ReconnectionStrategy, ReverseConnectionConfig},
+    ///         explain::Verdict,
+    ///         extract::{MatchGroupId, MatchPrivate},
+    ///         filter::EventFilter,
+    ///         handler::HandlerControl,
+    ///     },
+    /// };
+    ///
+    /// async fn in_group(_: MatchGroupId<123>) -> HandlerControl {
+    ///     HandlerControl::Continue
+    /// }
+    ///
+    /// async fn in_private(_: MatchPrivate) -> HandlerControl {
+    ///     HandlerControl::Continue
+    /// }
+    ///
+    /// async fn on_notice() -> HandlerControl {
+    ///     HandlerControl::Continue
+    /// }
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let bot = FlowBotBuilder::new(ReverseConnectionConfig {
+    ///     target: "ws://localhost:19999".to_string(),
+    ///     auth: None,
+    ///     reconnection: ReconnectionStrategy::None,
+    /// })
+    /// .with_handler(in_group)
+    /// .with_handler(in_private)
+    /// .with_handler_filtered(on_notice, EventFilter::NOTICE)
+    /// .build();
+    ///
+    /// let event = r#"{
+    ///     "time": 1700000000, "self_id": 10000, "post_type": "message", "message_type": "group",
+    ///     "sub_type": "normal", "message_id": 1, "group_id": 123, "user_id": 10001,
+    ///     "message": [{"type": "text", "data": {"text": "hi"}}], "raw_message": "hi", "font": 0,
+    ///     "sender": {"user_id": 10001, "nickname": "Alice", "role": "member"}
+    /// }"#;
+    /// let explanations = bot.explain(event).await.unwrap();
+    ///
+    /// assert_eq!(explanations[0].verdict, Verdict::Matched);
+    /// let Verdict::Skipped(miss) = &explanations[1].verdict else {
+    ///     panic!("in_private was not skipped");
+    /// };
+    /// assert_eq!((miss.argument, miss.extractor), (0, "flow_bot::base::extract::MatchPrivate"));
+    /// assert_eq!(explanations[2].verdict, Verdict::Filtered);
+    /// # }
+    /// ```
+    pub async fn explain(&self, event: &str) -> Result<Vec<HandlerExplanation>, FlowError> {
+        let mut parsed: Event = base::json::from_frame(event.as_bytes())?;
+        parsed.raw = Utf8Bytes::from(event);
+        let event = Arc::new(parsed);
+
+        let event_type = EventFilter::of(&event);
+        let dropped = match &event.event {
+            _ if !self.events.matches(&event) => Some("event filter"),
+            TypedEvent::Message(message)
+                if let TypedMessageInfo::Group(info) = &message.info
+                    && self.context.is_group_ignored(info.group_id) =>
+            {
+                Some("ignored group")
+            }
+            _ => None,
+        };
+
+        let mut explanations = Vec::with_capacity(self.handlers.len());
+        for (index, handler) in self.handlers.iter().enumerate() {
+            let verdict = match dropped {
+                Some(reason) => Verdict::Dropped { reason },
+                None if !handler.filter.contains(event_type) => Verdict::Filtered,
+                None => dry_run(handler.inner.check(self.context.clone(), event.clone())).await,
+            };
+            explanations.push(HandlerExplanation {
+                index,
+                name: handler.inner.name(),
+                group: handler.group,
+                verdict,
+            });
+        }
+        Ok(explanations)
     }
 
     async fn run_once(&self) -> Result<(), FlowError> {
         let (write, read) = self.connect().await?;
+        self.context.set_sink(write).await?;
 
         // Connection established successfully, reset attempt counter
-        self.reconnect_attempt.store(0, Ordering::Relaxed);
+        let attempt = self.reconnect_attempt.swap(0, Ordering::Relaxed);
 
-        self.set_sink(write).await;
-        self.init_services().await;
-        self.run_msg_loop(read).await?;
+        self.context.health.set_state(ConnectionState::Connected);
+        let generation = self.context.connection_generation();
+        self.start_services(generation, attempt).await;
+        // Spawned, responses are only received once the message loop runs.
+        let context = self.context.clone();
+        tokio::spawn(async move {
+            context.detect_quirks().await;
+            tracing::info!("Connected as {}", context.identity().await);
+        });
+        // Spawned, so that the responses to API calls of the handlers are read.
+        let connected = tokio::spawn(self.dispatch(self.internal_event(InternalEvent::Connected(
+            Connected {
+                attempt,
+                generation,
+            },
+        ))));
+        let result = self.run_msg_loop(read).await;
+        self.context.clear_sink().await;
+        self.context.health.set_state(ConnectionState::Down);
+        for service in self.services() {
+            service.on_disconnect(self.context.clone()).await;
+        }
+        // Handlers see Disconnected after Connected, even when the connection was short.
+        let _ = connected.await;
+        self.dispatch_internal(InternalEvent::Disconnected(Disconnected {
+            error: result.as_ref().err().map(ToString::to_string),
+            generation,
+        }))
+        .await;
 
-        Ok(())
+        result
     }
 
     async fn run_with_infinite_reconnect(
@@ -273,7 +1101,17 @@ impl FlowBot {
             let attempt = self.reconnect_attempt.load(Ordering::Relaxed);
             let current_delay = (initial_delay_ms * 2_u64.pow(attempt)).min(max_delay_ms);
 
-            match self.run_once().await {
+            let result = self.run_once().await;
+            if self.context.is_shutting_down() {
+                return result;
+            }
+            if let Err(e) = &result
+                && e.is_auth_failure()
+            {
+                self.context.health.set_state(ConnectionState::Down);
+                return result;
+            }
+            match result {
                 Ok(_) => {
                     eprintln!("Connection closed. Reconnecting in {}ms...", current_delay);
                 }
@@ -285,8 +1123,20 @@ impl FlowBot {
                 }
             }
 
-            self.reconnect_attempt.fetch_add(1, Ordering::Relaxed);
-            tokio::time::sleep(tokio::time::Duration::from_millis(current_delay)).await;
+            let attempt = self.reconnect_attempt.fetch_add(1, Ordering::Relaxed) + 1;
+            #[cfg(feature = "metrics")]
+            self.context.metrics().record_reconnect();
+            let delay = tokio::time::Duration::from_millis(current_delay);
+            self.context.health.set_state(ConnectionState::Reconnecting);
+            self.dispatch_internal(InternalEvent::Reconnecting(Reconnecting { attempt, delay }))
+                .await;
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.context.stopped() => {
+                    self.context.health.set_state(ConnectionState::Down);
+                    return Ok(());
+                }
+            }
         }
     }
 
@@ -300,12 +1150,23 @@ impl FlowBot {
             let attempt = self.reconnect_attempt.load(Ordering::Relaxed);
 
             if attempt >= max_attempts {
+                self.context.health.set_state(ConnectionState::Down);
                 return Err(FlowError::ReconnectionFailed(max_attempts));
             }
 
             let current_delay = (initial_delay_ms * 2_u64.pow(attempt)).min(max_delay_ms);
 
-            match self.run_once().await {
+            let result = self.run_once().await;
+            if self.context.is_shutting_down() {
+                return result;
+            }
+            if let Err(e) = &result
+                && e.is_auth_failure()
+            {
+                self.context.health.set_state(ConnectionState::Down);
+                return result;
+            }
+            match result {
                 Ok(_) => {
                     // Connection was successful and has now closed
                     // Counter was already reset to 0 in run_once
@@ -322,8 +1183,20 @@ impl FlowBot {
                 }
             }
 
-            self.reconnect_attempt.fetch_add(1, Ordering::Relaxed);
-            tokio::time::sleep(tokio::time::Duration::from_millis(current_delay)).await;
+            let attempt = self.reconnect_attempt.fetch_add(1, Ordering::Relaxed) + 1;
+            #[cfg(feature = "metrics")]
+            self.context.metrics().record_reconnect();
+            let delay = tokio::time::Duration::from_millis(current_delay);
+            self.context.health.set_state(ConnectionState::Reconnecting);
+            self.dispatch_internal(InternalEvent::Reconnecting(Reconnecting { attempt, delay }))
+                .await;
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.context.stopped() => {
+                    self.context.health.set_state(ConnectionState::Down);
+                    return Ok(());
+                }
+            }
         }
     }
 
@@ -347,67 +1220,271 @@ impl FlowBot {
         Ok(ws_stream.split())
     }
 
-    async fn set_sink(&self, sink: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>) {
-        let mut ws_sink = self.context.sink.lock().await;
-        *ws_sink = Some(sink);
-    }
-
     async fn run_msg_loop(
         &self,
         mut read: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
     ) -> Result<(), FlowError> {
-        while let Some(msg) = read.next().await {
-            let msg = msg?;
-            if let Message::Text(text) = msg {
-                if let Some(echo) = Self::check_is_echo(&text) {
-                    self.context.on_recv_echo(echo, text.to_string());
-                    continue;
+        // Once stopping, responses are still read for the handlers finishing up, events are dropped.
+        let mut deadline = None;
+        let mut tick = tokio::time::interval(Duration::from_millis(50));
+        loop {
+            tokio::select! {
+                msg = read.next() => {
+                    let Some(msg) = msg else {
+                        return Ok(());
+                    };
+                    self.context.health.record_frame();
+                    match msg? {
+                        Message::Text(text) => {
+                            if let Some(echo) = Self::check_is_echo(&text) {
+                                self.context.on_recv_echo(echo, text);
+                            } else if deadline.is_none() {
+                                self.handle_event(text);
+                            }
+                        }
+                        // The close of a shutdown being answered.
+                        Message::Close(_) if deadline.is_some() => return Ok(()),
+                        Message::Close(frame) => {
+                            let (code, reason) = frame.map_or((1005, String::new()), |frame| {
+                                (frame.code.into(), frame.reason.to_string())
+                            });
+                            tracing::warn!("Connection closed with code {}: {}", code, reason);
+                            return Err(FlowError::ConnectionClosed { code, reason });
+                        }
+                        // Pings are answered by tungstenite, which sends the pong with the next read.
+                        _ => {}
+                    }
+                }
+                _ = self.context.stopped(), if deadline.is_none() => {
+                    deadline = Some(tokio::time::Instant::now() + SHUTDOWN_GRACE);
+                }
+                _ = tick.tick(), if deadline.is_some() => {
+                    let in_flight = self.context.health().in_flight_handlers;
+                    if in_flight == 0 || deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+                        if in_flight > 0 {
+                            tracing::warn!("Shutting down with {} handlers still running", in_flight);
+                        }
+                        self.context.close_connection().await;
+                        return Ok(());
+                    }
                 }
-                self.handle_event(text)?;
             }
         }
-        Ok(())
     }
 
-    async fn init_services(&self) {
-        for handler in self.handlers.deref() {
-            if let HandlerOrService::Service(service) = handler {
-                service.init(self.context.clone()).await;
+    fn services(&self) -> impl Iterator<Item = &dyn Service> {
+        self.handlers
+            .iter()
+            .filter_map(|handler| match &handler.inner {
+                HandlerOrService::Service(service) => Some(service.as_ref()),
+                HandlerOrService::Handler(_) => None,
+            })
+    }
+
+    /// Initialize the services on the first connection, and tell them about later ones.
+    async fn start_services(&self, generation: u64, attempt: u32) {
+        for service in self.services() {
+            match generation {
+                1 => service.init(self.context.clone()).await,
+                _ => service.on_reconnect(self.context.clone(), attempt).await,
             }
         }
     }
 
-    fn handle_event(&self, text: Utf8Bytes) -> Result<(), FlowError> {
-        let event: Event = serde_json::from_slice(text.as_bytes())?;
-        let event = Arc::new(event);
+    fn handle_event(&self, text: Utf8Bytes) {
+        let mut event: Event = match base::json::from_frame(text.as_bytes()) {
+            Ok(event) => event,
+            Err(e) => {
+                tracing::warn!("Failed to parse event: {}", e);
+                self.handle_parse_error(Arc::from(text.as_str()), e.to_string());
+                return;
+            }
+        };
+        event.raw = text;
+        #[cfg(feature = "metrics")]
+        self.context.metrics().record_event(event.event.get_type());
+        if let Some(dedup) = &self.dedup
+            && dedup.is_duplicate(&event)
+        {
+            tracing::debug!("Dropping duplicate {} event", event.event.get_type());
+            self.context.health.record_duplicate();
+            #[cfg(feature = "metrics")]
+            self.context.metrics().record_duplicate();
+            return;
+        }
+        self.self_id.store(event.self_id, Ordering::Relaxed);
+        if let TypedEvent::Message(message) = &event.event
+            && let TypedMessageInfo::Group(info) = &message.info
+            && self.context.is_group_ignored(info.group_id)
+        {
+            tracing::trace!("Dropping message of ignored group {}", info.group_id);
+            return;
+        }
+        // Before the handlers run, so that sends from handlers of this event already see the ban.
+        if let Some(mute) = self.context.state.get::<MuteAwareness>() {
+            mute.observe(&event);
+        }
+        if let TypedEvent::MetaEvent(MetaEvent::Heartbeat(_)) = event.event {
+            self.context.health.record_heartbeat();
+        }
+        tokio::spawn(self.dispatch(Arc::new(event)));
+    }
+
+    /// Dispatch an internal event, waiting for the handler chain so that internal events are seen in order.
+    async fn dispatch_internal(&self, internal: InternalEvent) {
+        self.dispatch(self.internal_event(internal)).await;
+    }
+
+    fn internal_event(&self, internal: InternalEvent) -> BotEvent {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs() as i64);
+        let mut event = Event {
+            time,
+            self_id: self.self_id.load(Ordering::Relaxed),
+            event: TypedEvent::FlowInternal(internal),
+            extensions: Default::default(),
+            raw: Utf8Bytes::default(),
+        };
+        event.raw = serde_json::to_string(&event).unwrap_or_default().into();
+        Arc::new(event)
+    }
+
+    fn dispatch(&self, event: BotEvent) -> impl Future<Output = ()> + Send + 'static {
         let context = self.context.clone();
         let handlers = self.handlers.clone();
-        tokio::spawn(async move {
-            for handler in handlers.deref() {
-                let control = match handler {
-                    HandlerOrService::Handler(handler) => {
-                        handler.call(context.clone(), event.clone()).await
+        let fallback = self.fallback.clone();
+        let events = self.events;
+        let max_event_age = self.max_event_age;
+        let control_policy = self.control_policy.clone();
+        let handler_pool = self.handler_pool.clone();
+        async move {
+            if !events.matches(&event) {
+                return;
+            }
+            if let Some(max_age) = max_event_age {
+                // Whole seconds, as event times are, so that an event exactly `max_age` old is kept.
+                let age = EventTime(event.time).age();
+                if age.as_secs() > max_age.as_secs() {
+                    tracing::debug!("Dropping event from {}s ago", age.as_secs());
+                    return;
+                }
+            }
+
+            let _in_flight = InFlight::new(context.clone());
+            let event_type = EventFilter::of(&event);
+            let mut handled = false;
+            for (index, handler) in handlers.iter().enumerate() {
+                if !handler.filter.contains(event_type) {
+                    continue;
+                }
+
+                #[cfg(feature = "handler-stats")]
+                let started = std::time::Instant::now();
+
+                // Logs of the handler carry its name.
+                let span = tracing::debug_span!("handler", name = handler.inner.name());
+                let control = match &handler_pool {
+                    Some(pool) if handler.blocking => {
+                        let (handlers, context, event) =
+                            (handlers.clone(), context.clone(), event.clone());
+                        let call =
+                            with_reason(
+                                async move { handlers[index].inner.call(context, event).await },
+                            );
+                        pool.spawn(call.instrument(span))
+                            .await
+                            .map_err(|e| Box::new(e) as Box<dyn Any + Send>)
                     }
-                    HandlerOrService::Service(service) => {
-                        service.serve(context.clone(), event.clone()).await
+                    _ => {
+                        let call = with_reason(handler.inner.call(context.clone(), event.clone()));
+                        AssertUnwindSafe(call.instrument(span)).catch_unwind().await
                     }
                 };
 
-                if let HandlerControl::Block = control {
-                    break;
+                #[cfg(feature = "handler-stats")]
+                if let Some(stats) = context.handler_stats.get(index) {
+                    stats.record(
+                        control.as_ref().ok().map(|(control, _)| control),
+                        started.elapsed(),
+                    );
+                }
+
+                // A panicking handler stops the chain for this event, as if it blocked it.
+                let Ok((control, reason)) = control else {
+                    tracing::error!("Handler {} panicked", handler.inner.name());
+                    return;
+                };
+                if let Some(reason) = &reason {
+                    tracing::debug!(
+                        handler = handler.inner.name(),
+                        ?control,
+                        reason = reason.as_ref(),
+                        "Handler returned"
+                    );
+                }
+
+                #[cfg(feature = "metrics")]
+                context.metrics().record_handler(&control, reason.as_ref());
+
+                let control = match &control_policy {
+                    Some(policy) => policy(
+                        &event,
+                        control,
+                        HandlerMeta {
+                            index,
+                            name: handler.inner.name(),
+                            group: handler.group,
+                            reason: reason.as_deref(),
+                        },
+                    ),
+                    None => control,
+                };
+
+                match control {
+                    HandlerControl::Skip => {}
+                    HandlerControl::Continue => handled = true,
+                    HandlerControl::Block => {
+                        handled = true;
+                        break;
+                    }
+                }
+            }
+
+            if !handled && let Some(fallback) = fallback {
+                fallback.call(context, event).await;
+            }
+        }
+    }
+
+    fn handle_parse_error(&self, raw: Arc<str>, error: String) {
+        let context = self.context.clone();
+        let handlers = self.handlers.clone();
+        tokio::spawn(async move {
+            for handler in handlers.deref() {
+                if let HandlerOrService::Service(service) = &handler.inner {
+                    service
+                        .on_parse_error(context.clone(), raw.clone(), error.clone())
+                        .await;
                 }
             }
         });
-        Ok(())
     }
 
     fn check_is_echo(msg: &str) -> Option<String> {
-        let msg = serde_json::from_str::<serde_json::Value>(msg).unwrap();
-        if let serde_json::Value::Object(obj) = msg
-            && let Some(serde_json::Value::String(echo)) = obj.get("echo")
-        {
-            return Some(echo.clone());
+        // Only the echo field is deserialized, so large responses are not parsed twice.
+        // Some implementations also set `echo` on pushed events, which are told apart by their `post_type`.
+        #[derive(serde::Deserialize)]
+        struct Echo {
+            echo: Option<String>,
+            post_type: Option<serde::de::IgnoredAny>,
+        }
+
+        // Always with serde_json, which skips the other fields in place where simd-json would copy the frame first.
+        let frame = serde_json::from_str::<Echo>(msg).ok()?;
+        match frame.post_type {
+            Some(_) => None,
+            None => frame.echo,
         }
-        None
     }
 }
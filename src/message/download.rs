@@ -0,0 +1,310 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    api::{RecordFormat, api_ext::ApiExt},
+    base::context::Context,
+    error::FlowError,
+};
+
+use super::segments::{ImageSegment, RecordSegment};
+
+/// Limits applied by [`ImageSegment::fetch`] and [`RecordSegment::fetch`],
+/// register one as a state to override the defaults of 10 MiB and 30 seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadLimits {
+    pub max_size: u64,
+    pub timeout: Duration,
+}
+
+impl Default for DownloadLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 10 * 1024 * 1024,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadedImage {
+    pub bytes: Vec<u8>,
+    /// The content type sent by the server, or guessed from the content.
+    pub content_type: Option<String>,
+    /// Width and height, `None` if the format is not recognized.
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl ImageSegment {
+    /// Download the image, with the [`DownloadLimits`] registered as a state or the default ones.
+    ///
+    /// Uses the `url` of the segment, falling back to `get_image` for images without one.
+    pub async fn fetch(&self, context: &Context) -> Result<DownloadedImage, FlowError> {
+        let limits = context
+            .state
+            .get::<DownloadLimits>()
+            .as_deref()
+            .copied()
+            .unwrap_or_default();
+        self.fetch_with(context, limits).await
+    }
+
+    pub async fn fetch_with(
+        &self,
+        context: &Context,
+        limits: DownloadLimits,
+    ) -> Result<DownloadedImage, FlowError> {
+        let download = async {
+            if let Some(url) = &self.url {
+                return download_url(url, limits.max_size).await;
+            }
+
+            let file = context.get_image(self.file.clone()).await?;
+            match file.url {
+                Some(url) if url.starts_with("http") => download_url(&url, limits.max_size).await,
+                _ => read_file(&file.file, limits.max_size).await,
+            }
+        };
+
+        let (bytes, content_type) = tokio::time::timeout(limits.timeout, download)
+            .await
+            .map_err(|_| FlowError::Timeout(limits.timeout.as_millis() as u64))??;
+        let content_type = content_type.or_else(|| sniff(&bytes).map(str::to_string));
+        let dimensions = dimensions(&bytes);
+        Ok(DownloadedImage {
+            bytes,
+            content_type,
+            dimensions,
+        })
+    }
+}
+
+/// The encoding of an audio file, as detected from its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    /// The format of QQ voice messages, which few players support.
+    Silk,
+    Amr,
+    Mp3,
+    Ogg,
+    Wav,
+    Flac,
+    M4a,
+    Unknown,
+}
+
+impl AudioFormat {
+    pub fn detect(bytes: &[u8]) -> Self {
+        // Tencent prefixes silk files with an extra 0x02 byte.
+        let silk = bytes.strip_prefix(b"\x02").unwrap_or(bytes);
+        if silk.starts_with(b"#!SILK_V3") {
+            AudioFormat::Silk
+        } else if bytes.starts_with(b"#!AMR") {
+            AudioFormat::Amr
+        } else if bytes.starts_with(b"ID3") || bytes.starts_with(b"\xFF\xFB") {
+            AudioFormat::Mp3
+        } else if bytes.starts_with(b"OggS") {
+            AudioFormat::Ogg
+        } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+            AudioFormat::Wav
+        } else if bytes.starts_with(b"fLaC") {
+            AudioFormat::Flac
+        } else if bytes.get(4..8) == Some(b"ftyp") {
+            AudioFormat::M4a
+        } else {
+            AudioFormat::Unknown
+        }
+    }
+}
+
+type TranscodeFn = dyn Fn(Vec<u8>, AudioFormat, RecordFormat) -> BoxFuture<'static, Result<Vec<u8>, FlowError>>
+    + Send
+    + Sync;
+
+/// Converts records when the onebot implementation cannot, registered with [`with_audio_transcoder`].
+///
+/// [`with_audio_transcoder`]: crate::FlowBotBuilder::with_audio_transcoder
+#[derive(Clone)]
+pub struct AudioTranscoder(Arc<TranscodeFn>);
+
+impl AudioTranscoder {
+    pub fn new<F, Fut>(transcode: F) -> Self
+    where
+        F: Fn(Vec<u8>, AudioFormat, RecordFormat) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<u8>, FlowError>> + Send + 'static,
+    {
+        Self(Arc::new(move |bytes, from, to| {
+            Box::pin(transcode(bytes, from, to))
+        }))
+    }
+
+    pub async fn transcode(
+        &self,
+        bytes: Vec<u8>,
+        from: AudioFormat,
+        to: RecordFormat,
+    ) -> Result<Vec<u8>, FlowError> {
+        (self.0)(bytes, from, to).await
+    }
+}
+
+impl RecordSegment {
+    /// Download the record converted to `out_format`, with the [`DownloadLimits`] registered as a state or the default ones.
+    ///
+    /// The conversion is done by the implementation with `get_record`. If that fails and an [`AudioTranscoder`] is registered,
+    /// the original file is downloaded from the `url` of the segment and converted by the transcoder instead.
+    pub async fn fetch(
+        &self,
+        context: &Context,
+        out_format: RecordFormat,
+    ) -> Result<Vec<u8>, FlowError> {
+        let limits = context
+            .state
+            .get::<DownloadLimits>()
+            .as_deref()
+            .copied()
+            .unwrap_or_default();
+        let timed_out = || FlowError::Timeout(limits.timeout.as_millis() as u64);
+
+        let converted = tokio::time::timeout(limits.timeout, async {
+            let file = context.get_record(self.file.clone(), out_format).await?;
+            match file.url {
+                Some(url) if url.starts_with("http") => download_url(&url, limits.max_size).await,
+                _ => read_file(&file.file, limits.max_size).await,
+            }
+        })
+        .await;
+        let error = match converted {
+            Ok(Ok((bytes, _))) => return Ok(bytes),
+            Ok(Err(e)) => e,
+            Err(_) => timed_out(),
+        };
+
+        let (Some(transcoder), Some(url)) = (context.state.get::<AudioTranscoder>(), &self.url)
+        else {
+            return Err(error);
+        };
+        tracing::debug!(
+            "get_record failed, converting {} with the transcoder: {}",
+            self.file,
+            error
+        );
+        let (bytes, _) = tokio::time::timeout(limits.timeout, download_url(url, limits.max_size))
+            .await
+            .map_err(|_| timed_out())??;
+        let from = AudioFormat::detect(&bytes);
+        transcoder.transcode(bytes, from, out_format).await
+    }
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+async fn download_url(url: &str, max_size: u64) -> Result<(Vec<u8>, Option<String>), FlowError> {
+    let fetch_failed = |e: reqwest::Error| FlowError::FetchFailed(e.to_string());
+    let mut response = client()
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(fetch_failed)?;
+
+    if response.content_length().is_some_and(|len| len > max_size) {
+        return Err(FlowError::TooLarge(max_size));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    // The content length may be missing or wrong, so the limit is also checked while reading.
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(fetch_failed)? {
+        if (bytes.len() + chunk.len()) as u64 > max_size {
+            return Err(FlowError::TooLarge(max_size));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok((bytes, content_type))
+}
+
+/// Read an image cached by the implementation, which only works if it runs on the same machine.
+async fn read_file(path: &str, max_size: u64) -> Result<(Vec<u8>, Option<String>), FlowError> {
+    let path = path.strip_prefix("file://").unwrap_or(path);
+    let read_failed = |e: std::io::Error| FlowError::FetchFailed(format!("{}: {}", path, e));
+    let size = tokio::fs::metadata(path).await.map_err(read_failed)?.len();
+    if size > max_size {
+        return Err(FlowError::TooLarge(max_size));
+    }
+    Ok((tokio::fs::read(path).await.map_err(read_failed)?, None))
+}
+
+fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else if bytes.starts_with(b"\xFF\xD8\xFF") {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+/// Read the dimensions from the header of a png, jpeg, gif, webp or bmp image.
+fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let u16_le =
+        |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let u16_be =
+        |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let u32_be = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let i32_le = |at: usize| Some(i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let u24_le = |at: usize| {
+        let b = bytes.get(at..at + 3)?;
+        Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+    };
+
+    match sniff(bytes)? {
+        "image/png" => Some((u32_be(16)?, u32_be(20)?)),
+        "image/gif" => Some((u16_le(6)?, u16_le(8)?)),
+        "image/bmp" => Some((i32_le(18)?.unsigned_abs(), i32_le(22)?.unsigned_abs())),
+        "image/webp" => match bytes.get(12..16)? {
+            b"VP8 " => Some((u16_le(26)? & 0x3FFF, u16_le(28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((u24_le(24)? + 1, u24_le(27)? + 1)),
+            _ => None,
+        },
+        "image/jpeg" => {
+            // Walk the segments up to the start of frame, which holds the dimensions.
+            let mut at = 2;
+            loop {
+                if *bytes.get(at)? != 0xFF {
+                    return None;
+                }
+                let marker = *bytes.get(at + 1)?;
+                match marker {
+                    0xC0..=0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                        return Some((u16_be(at + 7)?, u16_be(at + 5)?));
+                    }
+                    0xD0..=0xD9 | 0x01 => at += 2,
+                    _ => at += 2 + u16_be(at + 2)? as usize,
+                }
+            }
+        }
+        _ => None,
+    }
+}
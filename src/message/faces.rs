@@ -0,0 +1,208 @@
+//! Ids of common QQ faces, for use with [`FaceSegment::new`](super::segments::FaceSegment::new).
+//!
+//! Names are English approximations of the Chinese ones, which are given in the docs.
+
+macro_rules! faces {
+    ($($(#[doc = $doc:literal])* $name:ident = $id:literal,)*) => {
+        $(
+            $(#[doc = $doc])*
+            pub const $name: u32 = $id;
+        )*
+
+        /// Every face in this module as `(name, id)`, the name being the name of the constant.
+        pub const ALL: &[(&str, u32)] = &[$((stringify!($name), $id),)*];
+    };
+}
+
+faces! {
+    /// 惊讶
+    SURPRISED = 0,
+    /// 撇嘴
+    POUT = 1,
+    /// 色
+    INFATUATED = 2,
+    /// 发呆
+    BLANK_STARE = 3,
+    /// 得意
+    PROUD = 4,
+    /// 流泪
+    TEARS = 5,
+    /// 害羞
+    SHY = 6,
+    /// 闭嘴
+    SHUT_UP = 7,
+    /// 睡
+    SLEEP = 8,
+    /// 大哭
+    CRY = 9,
+    /// 尴尬
+    AWKWARD = 10,
+    /// 发怒
+    ANGRY = 11,
+    /// 调皮
+    TONGUE = 12,
+    /// 呲牙
+    GRIN = 13,
+    /// 微笑
+    SMILE = 14,
+    /// 难过
+    SAD = 15,
+    /// 酷
+    COOL = 16,
+    /// 抓狂
+    FRANTIC = 18,
+    /// 吐
+    VOMIT = 19,
+    /// 偷笑
+    CHUCKLE = 20,
+    /// 可爱
+    CUTE = 21,
+    /// 白眼
+    EYE_ROLL = 22,
+    /// 傲慢
+    ARROGANT = 23,
+    /// 困
+    SLEEPY = 25,
+    /// 惊恐
+    TERRIFIED = 26,
+    /// 流汗
+    SWEAT = 27,
+    /// 憨笑
+    SILLY_LAUGH = 28,
+    /// 奋斗
+    STRUGGLE = 30,
+    /// 疑问
+    QUESTION = 32,
+    /// 嘘
+    SHUSH = 33,
+    /// 晕
+    DIZZY = 34,
+    /// 衰
+    UNLUCKY = 36,
+    /// 骷髅
+    SKULL = 37,
+    /// 敲打
+    KNOCK = 38,
+    /// 再见
+    BYE = 39,
+    /// 发抖
+    SHIVER = 41,
+    /// 拥抱
+    HUG = 49,
+    /// 蛋糕
+    CAKE = 53,
+    /// 咖啡
+    COFFEE = 60,
+    /// 玫瑰
+    ROSE = 63,
+    /// 凋谢
+    WILTED = 64,
+    /// 爱心
+    HEART = 66,
+    /// 心碎
+    BROKEN_HEART = 67,
+    /// 太阳
+    SUN = 74,
+    /// 月亮
+    MOON = 75,
+    /// 赞
+    THUMBS_UP = 76,
+    /// 踩
+    THUMBS_DOWN = 77,
+    /// 握手
+    HANDSHAKE = 78,
+    /// 胜利
+    VICTORY = 79,
+    /// 飞吻
+    BLOW_KISS = 85,
+    /// 西瓜
+    WATERMELON = 89,
+    /// 冷汗
+    COLD_SWEAT = 96,
+    /// 擦汗
+    WIPE_SWEAT = 97,
+    /// 抠鼻
+    PICK_NOSE = 98,
+    /// 鼓掌
+    APPLAUSE = 99,
+    /// 坏笑
+    SMIRK = 101,
+    /// 哈欠
+    YAWN = 104,
+    /// 鄙视
+    DESPISE = 105,
+    /// 委屈
+    WRONGED = 106,
+    /// 快哭了
+    ABOUT_TO_CRY = 107,
+    /// 阴险
+    SINISTER = 108,
+    /// 吓
+    SCARED = 110,
+    /// 可怜
+    PITIFUL = 111,
+    /// 抱拳
+    FIST_SALUTE = 118,
+    /// 拳头
+    FIST = 120,
+    /// OK
+    OK = 124,
+    /// 眨眼睛
+    WINK = 172,
+    /// 泪奔
+    SOBBING = 173,
+    /// 无奈
+    HELPLESS = 174,
+    /// 卖萌
+    ACT_CUTE = 175,
+    /// 斜眼笑, often mistaken for [`DOGE`]
+    SIDE_EYE_SMILE = 178,
+    /// doge
+    DOGE = 179,
+    /// 惊喜
+    PLEASANT_SURPRISE = 180,
+    /// 笑哭
+    TEARS_OF_JOY = 182,
+    /// 捂脸
+    FACEPALM = 264,
+    /// 辣眼睛
+    EYE_BURN = 265,
+    /// 头秃
+    BALD = 267,
+    /// 问号脸
+    CONFUSED = 268,
+    /// 暗中观察
+    LURKING = 269,
+    /// 吃瓜
+    EATING_MELON = 271,
+    /// 我酸了
+    JEALOUS = 273,
+    /// 汪汪
+    WOOF = 277,
+    /// 敬礼
+    SALUTE = 282,
+    /// 面无表情
+    EXPRESSIONLESS = 284,
+    /// 摸鱼
+    SLACKING = 285,
+    /// 加油
+    CHEER_UP = 315,
+    /// 比心
+    FINGER_HEART = 319,
+    /// 庆祝
+    CELEBRATE = 320,
+}
+
+/// The id of the face called `name`, as listed in [`ALL`], ignoring case.
+pub fn by_name(name: &str) -> Option<u32> {
+    ALL.iter()
+        .find(|(face, _)| face.eq_ignore_ascii_case(name))
+        .map(|&(_, id)| id)
+}
+
+/// The name of the face with the given id, as listed in [`ALL`].
+pub fn name_of(id: u32) -> Option<&'static str> {
+    ALL.iter()
+        .find(|&&(_, face)| face == id)
+        .map(|&(name, _)| name)
+}
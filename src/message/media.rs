@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use serde::{Serialize, Serializer};
+
+/// Where a file to be sent or uploaded comes from.
+///
+/// Onebot implementations accept the `file` parameter in a few string forms, this type builds the correct one.
+#[derive(Debug, Clone)]
+pub enum MediaSource {
+    /// A file on the machine running the onebot implementation, sent as a `file://` uri.
+    Path(PathBuf),
+    /// A remote file that the implementation downloads itself.
+    Url(String),
+    /// Base64 encoded file content, sent as a `base64://` uri.
+    Base64(String),
+    /// A file name or path already known to the implementation, e.g. the `file` of a received segment or a [`GetFileResponse`], sent as-is.
+    ///
+    /// [`GetFileResponse`]: crate::api::GetFileResponse
+    Raw(String),
+}
+
+impl MediaSource {
+    pub fn into_file(self) -> String {
+        match self {
+            MediaSource::Path(path) => format!("file://{}", path.display()),
+            MediaSource::Url(url) => url,
+            MediaSource::Base64(data) => format!("base64://{}", data),
+            MediaSource::Raw(file) => file,
+        }
+    }
+}
+
+impl Serialize for MediaSource {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.clone().into_file())
+    }
+}
+
+impl From<PathBuf> for MediaSource {
+    fn from(path: PathBuf) -> Self {
+        MediaSource::Path(path)
+    }
+}
+
+impl From<String> for MediaSource {
+    fn from(file: String) -> Self {
+        MediaSource::Raw(file)
+    }
+}
+
+impl From<&str> for MediaSource {
+    fn from(file: &str) -> Self {
+        MediaSource::Raw(file.to_string())
+    }
+}
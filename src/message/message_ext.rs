@@ -1,4 +1,8 @@
-use super::{Message, segments::Segment};
+use super::{
+    Message,
+    pattern::{self, Captured, PatternItem},
+    segments::{AtSegment, MFaceSegment, Segment, TextSegment},
+};
 
 pub trait MessageExt {
     fn extract_plain_text(&self) -> String;
@@ -12,6 +16,37 @@ pub trait MessageExt {
             None
         }
     }
+
+    /// Split the message into chunks whose plain text is at most `max_len` characters long.
+    ///
+    /// Splits happen at segment boundaries, text segments that do not fit are split, preferring the last line break.
+    /// Other segments do not count towards the length and are never split.
+    /// A leading reply segment is only kept in the first chunk. A `max_len` of 0 keeps the message whole.
+    fn split_chunks(&self, max_len: usize) -> Vec<Message>;
+
+    /// The ids of the faces in the message, in order. Faces with non-numeric ids are skipped.
+    fn faces(&self) -> Vec<u32>;
+
+    /// The stickers in the message, in order.
+    fn mfaces(&self) -> Vec<&MFaceSegment>;
+
+    /// The first at segment and its index.
+    fn find_at(&self) -> Option<(usize, &AtSegment)>;
+
+    /// The segments after the one at `index`, empty if it is the last one or out of range.
+    fn segments_after(&self, index: usize) -> &[Segment];
+
+    /// The plain text of the segments between the indices `a` and `b`, both excluded.
+    fn text_between(&self, a: usize, b: usize) -> String;
+
+    /// Match the whole message against `pattern`, returning what its parts captured.
+    ///
+    /// ```ignore
+    /// // "/tag @user some reason"
+    /// let captured = message.parse_pattern(&[PatternItem::Text("/tag"), PatternItem::At, PatternItem::AnyText])?;
+    /// let (user, reason) = (captured[0].as_at()?, captured[1].as_text()?);
+    /// ```
+    fn parse_pattern(&self, pattern: &[PatternItem]) -> Option<Vec<Captured<'_>>>;
 }
 
 impl MessageExt for Message {
@@ -29,4 +64,106 @@ impl MessageExt for Message {
         self.iter()
             .all(|segment| matches!(segment, Segment::Text(_)))
     }
+
+    fn faces(&self) -> Vec<u32> {
+        self.iter()
+            .filter_map(|segment| match segment {
+                Segment::Face(face) => face.face_id(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn mfaces(&self) -> Vec<&MFaceSegment> {
+        self.iter()
+            .filter_map(|segment| match segment {
+                Segment::MFace(mface) => Some(mface),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn find_at(&self) -> Option<(usize, &AtSegment)> {
+        self.iter()
+            .enumerate()
+            .find_map(|(index, segment)| match segment {
+                Segment::At(at) => Some((index, at)),
+                _ => None,
+            })
+    }
+
+    fn segments_after(&self, index: usize) -> &[Segment] {
+        self.get(index.saturating_add(1)..).unwrap_or_default()
+    }
+
+    fn text_between(&self, a: usize, b: usize) -> String {
+        let end = b.min(self.len());
+        self.get(a.saturating_add(1)..end)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn parse_pattern(&self, pattern: &[PatternItem]) -> Option<Vec<Captured<'_>>> {
+        pattern::parse(self, pattern)
+    }
+
+    fn split_chunks(&self, max_len: usize) -> Vec<Message> {
+        if max_len == 0 {
+            return match self.is_empty() {
+                true => Vec::new(),
+                false => vec![self.clone()],
+            };
+        }
+
+        let mut chunks = Vec::new();
+        let mut chunk = Vec::new();
+        let mut len = 0;
+        for segment in self {
+            let Segment::Text(text) = segment else {
+                chunk.push(segment.clone());
+                continue;
+            };
+
+            let mut rest = text.text.as_str();
+            loop {
+                let rest_len = rest.chars().count();
+                if len + rest_len <= max_len {
+                    if !rest.is_empty() {
+                        chunk.push(Segment::Text(TextSegment {
+                            text: rest.to_string(),
+                        }));
+                        len += rest_len;
+                    }
+                    break;
+                }
+
+                let room = max_len - len;
+                let end = rest
+                    .char_indices()
+                    .nth(room)
+                    .map_or(rest.len(), |(index, _)| index);
+                let end = match rest[..end].rfind('\n') {
+                    Some(index) if index > 0 => index + 1,
+                    _ => end,
+                };
+                if end > 0 {
+                    chunk.push(Segment::Text(TextSegment {
+                        text: rest[..end].to_string(),
+                    }));
+                }
+                rest = &rest[end..];
+                chunks.push(std::mem::take(&mut chunk));
+                len = 0;
+            }
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
 }
@@ -1,10 +1,21 @@
 use segments::TextSegment;
 
+pub mod download;
+pub mod faces;
+pub mod media;
 pub mod message_ext;
+pub mod pattern;
 pub mod segments;
+pub mod template;
 
 pub type Message = Vec<segments::Segment>;
 
+/// Conversion into a message, which is always sent as an array of segments.
+///
+/// Text is sent as a text segment, so CQ codes in it, e.g. from user input, are never interpreted
+/// and `auto_escape` has no effect. It only matters for the `send_*_raw_string` methods of [`ApiExt`].
+///
+/// [`ApiExt`]: crate::api::api_ext::ApiExt
 pub trait IntoMessage {
     fn into_message(self) -> Message;
 }
@@ -0,0 +1,133 @@
+use super::segments::{AtSegment, FaceSegment, ImageSegment, ReplySegment, Segment};
+
+/// One part of a pattern matched by [`MessageExt::parse_pattern`].
+///
+/// Adjacent text segments are matched as one text, since implementations split text arbitrarily,
+/// and whitespace between the parts is ignored.
+///
+/// [`MessageExt::parse_pattern`]: super::message_ext::MessageExt::parse_pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternItem<'p> {
+    /// Text starting with this literal, which is not captured.
+    Text(&'p str),
+    /// A single word of text, up to the next whitespace or non-text segment.
+    Word,
+    /// All text up to the next non-text segment, which must not be empty.
+    AnyText,
+    At,
+    Image,
+    Face,
+    Reply,
+    /// Any single non-text segment.
+    Any,
+}
+
+/// What a [`PatternItem`] other than [`PatternItem::Text`] matched, in pattern order.
+#[derive(Debug, Clone)]
+pub enum Captured<'a> {
+    Text(String),
+    At(&'a AtSegment),
+    Image(&'a ImageSegment),
+    Face(&'a FaceSegment),
+    Reply(&'a ReplySegment),
+    Segment(&'a Segment),
+}
+
+impl<'a> Captured<'a> {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Captured::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_at(&self) -> Option<&'a AtSegment> {
+        match self {
+            Captured::At(at) => Some(at),
+            _ => None,
+        }
+    }
+}
+
+enum Token<'a> {
+    Text(String),
+    Segment(&'a Segment),
+}
+
+/// Match a whole message, not counting whitespace around the parts of the pattern.
+pub(crate) fn parse<'a>(
+    segments: &'a [Segment],
+    pattern: &[PatternItem],
+) -> Option<Vec<Captured<'a>>> {
+    let mut tokens: Vec<Token> = Vec::new();
+    for segment in segments {
+        match (segment, tokens.last_mut()) {
+            (Segment::Text(text), Some(Token::Text(joined))) => joined.push_str(&text.text),
+            (Segment::Text(text), _) => tokens.push(Token::Text(text.text.clone())),
+            (segment, _) => tokens.push(Token::Segment(segment)),
+        }
+    }
+
+    let mut captured = Vec::new();
+    let mut index = 0;
+    let mut offset = 0;
+    for item in pattern {
+        skip_whitespace(&tokens, &mut index, &mut offset);
+        let token = tokens.get(index)?;
+        match (item, token) {
+            (PatternItem::Text(literal), Token::Text(text)) => {
+                if !text[offset..].starts_with(literal) {
+                    return None;
+                }
+                offset += literal.len();
+            }
+            (PatternItem::Word, Token::Text(text)) => {
+                let rest = &text[offset..];
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                captured.push(Captured::Text(rest[..end].to_string()));
+                offset += end;
+            }
+            (PatternItem::AnyText, Token::Text(text)) => {
+                captured.push(Captured::Text(text[offset..].trim_end().to_string()));
+                offset = text.len();
+            }
+            (PatternItem::At, Token::Segment(Segment::At(at))) => {
+                captured.push(Captured::At(at));
+                index += 1;
+            }
+            (PatternItem::Image, Token::Segment(Segment::Image(image))) => {
+                captured.push(Captured::Image(image));
+                index += 1;
+            }
+            (PatternItem::Face, Token::Segment(Segment::Face(face))) => {
+                captured.push(Captured::Face(face));
+                index += 1;
+            }
+            (PatternItem::Reply, Token::Segment(Segment::Reply(reply))) => {
+                captured.push(Captured::Reply(reply));
+                index += 1;
+            }
+            (PatternItem::Any, Token::Segment(segment)) => {
+                captured.push(Captured::Segment(segment));
+                index += 1;
+            }
+            _ => return None,
+        }
+    }
+
+    skip_whitespace(&tokens, &mut index, &mut offset);
+    (index == tokens.len()).then_some(captured)
+}
+
+/// Move past whitespace, and past text tokens that only hold whitespace.
+fn skip_whitespace(tokens: &[Token], index: &mut usize, offset: &mut usize) {
+    while let Some(Token::Text(text)) = tokens.get(*index) {
+        let rest = &text[*offset..];
+        *offset += rest.len() - rest.trim_start().len();
+        if *offset < text.len() {
+            return;
+        }
+        *index += 1;
+        *offset = 0;
+    }
+}
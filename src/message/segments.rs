@@ -5,19 +5,45 @@ pub struct TextSegment {
     pub text: String,
 }
 
+/// A QQ face, see [`faces`](super::faces) for the ids of common ones.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FaceSegment {
+    /// Some implementations send the id as a number, it is always stored as a string.
+    #[serde(deserialize_with = "crate::api::string_or_number")]
     pub id: String,
 }
 
+impl FaceSegment {
+    pub fn new(id: u32) -> Self {
+        FaceSegment { id: id.to_string() }
+    }
+
+    /// The numeric id of the face, `None` if the implementation sent a non-numeric one.
+    pub fn face_id(&self) -> Option<u32> {
+        self.id.parse().ok()
+    }
+}
+
+impl From<FaceSegment> for Segment {
+    fn from(face: FaceSegment) -> Self {
+        Segment::Face(face)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageSegment {
     pub file: String,
+    /// Download url of a received image, see [`fetch`](Self::fetch).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RecordSegment {
     pub file: String,
+    /// Download url of a received record, usually silk encoded. See [`fetch`](Self::fetch) for a playable format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -30,11 +56,38 @@ pub struct AtSegment {
     pub qq: String,
 }
 
+/// The target of an at segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mention {
+    User(i64),
+    All,
+}
+
+impl AtSegment {
+    /// Parse the target, `None` if it is neither a user id nor `all`.
+    pub fn mention(&self) -> Option<Mention> {
+        match self.qq.as_str() {
+            "all" => Some(Mention::All),
+            qq => qq.parse().ok().map(Mention::User),
+        }
+    }
+}
+
+impl From<Mention> for Segment {
+    fn from(mention: Mention) -> Self {
+        match mention {
+            Mention::User(user_id) => Segment::at_user(user_id),
+            Mention::All => Segment::at_all(),
+        }
+    }
+}
+
+// Braced rather than unit structs, so `data` is sent as `{}` instead of `null`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct DiceSegment;
+pub struct DiceSegment {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ShakeSegment;
+pub struct ShakeSegment {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PokeSegment {
@@ -44,20 +97,54 @@ pub struct PokeSegment {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct AnonymousSegment;
+pub struct AnonymousSegment {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ShareSegment {
     pub url: String,
     pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl ShareSegment {
+    pub fn new(url: impl Into<String>, title: impl Into<String>) -> Self {
+        ShareSegment {
+            url: url.into(),
+            title: title.into(),
+            content: None,
+            image: None,
+        }
+    }
+
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    /// Set the url of the cover image.
+    pub fn image(mut self, image: impl Into<String>) -> Self {
+        self.image = Some(image.into());
+        self
+    }
+}
+
+impl From<ShareSegment> for Segment {
+    fn from(share: ShareSegment) -> Self {
+        Segment::Share(share)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum ContactType {
     QQ,
     Group,
 }
 
+/// A recommended friend or group.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ContactSegment {
     #[serde(rename = "type")]
@@ -65,22 +152,172 @@ pub struct ContactSegment {
     pub id: String,
 }
 
+impl ContactSegment {
+    pub fn user(user_id: i64) -> Self {
+        ContactSegment {
+            ty: ContactType::QQ,
+            id: user_id.to_string(),
+        }
+    }
+
+    pub fn group(group_id: i64) -> Self {
+        ContactSegment {
+            ty: ContactType::Group,
+            id: group_id.to_string(),
+        }
+    }
+}
+
+impl From<ContactSegment> for Segment {
+    fn from(contact: ContactSegment) -> Self {
+        Segment::Contact(contact)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LocationSegment {
     pub lat: String,
     pub lon: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
 }
 
+impl LocationSegment {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        LocationSegment {
+            lat: lat.to_string(),
+            lon: lon.to_string(),
+            title: None,
+            content: None,
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+}
+
+impl From<LocationSegment> for Segment {
+    fn from(location: LocationSegment) -> Self {
+        Segment::Location(location)
+    }
+}
+
+/// A music platform whose songs can be shared by id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MusicPlatform {
+    QQ,
+    Netease,
+    Xiami,
+}
+
+impl MusicPlatform {
+    /// The value of the `type` field for this platform.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MusicPlatform::QQ => "qq",
+            MusicPlatform::Netease => "163",
+            MusicPlatform::Xiami => "xm",
+        }
+    }
+}
+
+/// A music share, either a song on a [`MusicPlatform`] or a custom one with `type` set to `custom`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MusicSegment {
     #[serde(rename = "type")]
     pub ty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub audio: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl MusicSegment {
+    pub fn platform(platform: MusicPlatform, id: impl Into<String>) -> Self {
+        MusicSegment {
+            ty: platform.as_str().to_string(),
+            id: Some(id.into()),
+            url: None,
+            audio: None,
+            title: None,
+            content: None,
+            image: None,
+        }
+    }
+
+    pub fn qq(id: impl Into<String>) -> Self {
+        Self::platform(MusicPlatform::QQ, id)
+    }
+
+    pub fn netease(id: impl Into<String>) -> Self {
+        Self::platform(MusicPlatform::Netease, id)
+    }
+
+    pub fn xm(id: impl Into<String>) -> Self {
+        Self::platform(MusicPlatform::Xiami, id)
+    }
+
+    /// A custom music share, `url` is the page opened on click and `audio` the url of the audio file.
+    /// Set the optional fields with [`content`](Self::content) and [`image`](Self::image).
+    pub fn custom(
+        url: impl Into<String>,
+        audio: impl Into<String>,
+        title: impl Into<String>,
+    ) -> Self {
+        MusicSegment {
+            ty: "custom".to_string(),
+            id: None,
+            url: Some(url.into()),
+            audio: Some(audio.into()),
+            title: Some(title.into()),
+            content: None,
+            image: None,
+        }
+    }
+
+    /// The platform of the song, `None` for custom shares and unknown platforms.
+    pub fn music_platform(&self) -> Option<MusicPlatform> {
+        match self.ty.as_str() {
+            "qq" => Some(MusicPlatform::QQ),
+            "163" => Some(MusicPlatform::Netease),
+            "xm" => Some(MusicPlatform::Xiami),
+            _ => None,
+        }
+    }
+
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    /// Set the url of the cover image.
+    pub fn image(mut self, image: impl Into<String>) -> Self {
+        self.image = Some(image.into());
+        self
+    }
+}
+
+impl From<MusicSegment> for Segment {
+    fn from(music: MusicSegment) -> Self {
+        Segment::Music(music)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -88,6 +325,61 @@ pub struct ReplySegment {
     pub id: String,
 }
 
+/// A reference to a message, numeric for most implementations but some use opaque string ids.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum ReplyRef {
+    Id(i64),
+    Raw(String),
+}
+
+impl ReplySegment {
+    pub fn reply_ref(&self) -> ReplyRef {
+        ReplyRef::from(self.id.as_str())
+    }
+}
+
+impl From<i64> for ReplyRef {
+    fn from(id: i64) -> Self {
+        ReplyRef::Id(id)
+    }
+}
+
+impl From<&str> for ReplyRef {
+    fn from(id: &str) -> Self {
+        match id.parse() {
+            Ok(id) => ReplyRef::Id(id),
+            Err(_) => ReplyRef::Raw(id.to_string()),
+        }
+    }
+}
+
+impl From<String> for ReplyRef {
+    fn from(id: String) -> Self {
+        match id.parse() {
+            Ok(id) => ReplyRef::Id(id),
+            Err(_) => ReplyRef::Raw(id),
+        }
+    }
+}
+
+impl std::fmt::Display for ReplyRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplyRef::Id(id) => id.fmt(f),
+            ReplyRef::Raw(id) => f.write_str(id),
+        }
+    }
+}
+
+impl From<ReplyRef> for ReplySegment {
+    fn from(reply: ReplyRef) -> Self {
+        ReplySegment {
+            id: reply.to_string(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ForwardSegment {
     pub id: String,
@@ -108,6 +400,52 @@ pub struct JsonSegment {
     pub data: String,
 }
 
+/// A markdown card, supported by NapCat and LLOneBot.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarkdownSegment {
+    pub content: String,
+}
+
+/// A market face, i.e. a sticker from a sticker pack, supported by NapCat and LLOneBot.
+///
+/// ```
+/// use flow_bot::message::segments::Segment;
+///
+/// let segment: Segment = serde_json::from_str(
+///     r#"{"type":"mface","data":{"summary":"[开心]","url":"https://gxh.vip.qq.com/club/item/parcel/item/a1/a1b2/raw300.gif","emoji_id":"a1b2","emoji_package_id":235125,"key":"7c9a"}}"#,
+/// ).unwrap();
+/// let Segment::MFace(mface) = &segment else { panic!() };
+/// assert_eq!(mface.emoji_package_id, 235125);
+/// assert_eq!(mface.summary, "[开心]");
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MFaceSegment {
+    pub emoji_id: String,
+    /// Some implementations send the id as a string, it is always sent as a number.
+    #[serde(deserialize_with = "crate::api::number_or_string")]
+    pub emoji_package_id: i64,
+    #[serde(default)]
+    pub key: String,
+    /// The text shown in place of the sticker, e.g. `[开心]`.
+    #[serde(default)]
+    pub summary: String,
+    /// Image url of a received sticker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl From<MarkdownSegment> for Segment {
+    fn from(markdown: MarkdownSegment) -> Self {
+        Segment::Markdown(markdown)
+    }
+}
+
+impl From<MFaceSegment> for Segment {
+    fn from(mface: MFaceSegment) -> Self {
+        Segment::MFace(mface)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum Segment {
@@ -130,4 +468,46 @@ pub enum Segment {
     Node(NodeSegment),
     Xml(XmlSegment),
     Json(JsonSegment),
+    Markdown(MarkdownSegment),
+    #[serde(rename = "mface")]
+    MFace(MFaceSegment),
+}
+
+impl Segment {
+    pub fn at_user(user_id: i64) -> Self {
+        Segment::At(AtSegment {
+            qq: user_id.to_string(),
+        })
+    }
+
+    /// Mention everyone in the group.
+    ///
+    /// The remaining quota can be checked with `get_group_at_all_remain` beforehand.
+    pub fn at_all() -> Self {
+        Segment::At(AtSegment {
+            qq: "all".to_string(),
+        })
+    }
+
+    pub fn markdown(content: impl Into<String>) -> Self {
+        Segment::Markdown(MarkdownSegment {
+            content: content.into(),
+        })
+    }
+
+    /// A sticker, with the ids and key of a received [`MFaceSegment`].
+    pub fn mface(
+        emoji_package_id: i64,
+        emoji_id: impl Into<String>,
+        key: impl Into<String>,
+        summary: impl Into<String>,
+    ) -> Self {
+        Segment::MFace(MFaceSegment {
+            emoji_id: emoji_id.into(),
+            emoji_package_id,
+            key: key.into(),
+            summary: summary.into(),
+            url: None,
+        })
+    }
 }
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use crate::{
+    base::{context::BotContext, extract::FromEvent},
+    event::{BotEvent, TypedEvent, message::TypedMessageInfo},
+};
+
+use super::{
+    Message,
+    segments::{Segment, TextSegment},
+};
+
+/// A value substituted for a `{placeholder}` in a template.
+#[derive(Debug, Clone)]
+pub enum TemplateArg {
+    Text(String),
+    /// Inserted as its own segment, e.g. an at segment for `{at_sender}`.
+    Segment(Segment),
+}
+
+impl From<String> for TemplateArg {
+    fn from(text: String) -> Self {
+        TemplateArg::Text(text)
+    }
+}
+
+impl From<&str> for TemplateArg {
+    fn from(text: &str) -> Self {
+        TemplateArg::Text(text.to_string())
+    }
+}
+
+impl From<i64> for TemplateArg {
+    fn from(value: i64) -> Self {
+        TemplateArg::Text(value.to_string())
+    }
+}
+
+impl From<Segment> for TemplateArg {
+    fn from(segment: Segment) -> Self {
+        TemplateArg::Segment(segment)
+    }
+}
+
+/// Reply templates in multiple languages, registered as a state.
+///
+/// Templates are written as `key -> { lang -> template }`, e.g.
+/// `{"greet": {"en": "Hello {at_sender}!", "zh": "你好 {at_sender}！"}}`.
+/// `{name}` is replaced by the argument of the same name and `{{`/`}}` produce literal braces.
+pub struct Templates {
+    templates: HashMap<String, HashMap<String, String>>,
+    default_lang: String,
+}
+
+impl Templates {
+    pub fn new(templates: HashMap<String, HashMap<String, String>>, default_lang: &str) -> Self {
+        Self {
+            templates,
+            default_lang: default_lang.to_string(),
+        }
+    }
+
+    /// Load the templates from a json map.
+    pub fn from_json(json: &str, default_lang: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(serde_json::from_str(json)?, default_lang))
+    }
+
+    pub fn default_lang(&self) -> &str {
+        &self.default_lang
+    }
+
+    /// Render the template `key` in `lang`.
+    ///
+    /// A missing language falls back to the default language, then to the alphabetically first available one.
+    /// A missing key renders the key itself. Both cases are logged.
+    pub fn render(&self, key: &str, lang: &str, args: &[(&str, TemplateArg)]) -> Message {
+        let Some(translations) = self.templates.get(key) else {
+            tracing::warn!("Missing template {}", key);
+            return vec![Segment::Text(TextSegment {
+                text: key.to_string(),
+            })];
+        };
+
+        let template = translations
+            .get(lang)
+            .or_else(|| {
+                tracing::warn!("Missing language {} for template {}", lang, key);
+                translations.get(&self.default_lang)
+            })
+            .or_else(|| {
+                translations
+                    .iter()
+                    .min_by(|a, b| a.0.cmp(b.0))
+                    .map(|(_, template)| template)
+            });
+
+        match template {
+            Some(template) => render_template(template, args),
+            None => vec![Segment::Text(TextSegment {
+                text: key.to_string(),
+            })],
+        }
+    }
+}
+
+pub(crate) fn render_template(template: &str, args: &[(&str, TemplateArg)]) -> Message {
+    let mut message = Message::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+
+                match args.iter().find(|(arg, _)| *arg == name) {
+                    Some((_, TemplateArg::Text(value))) if closed => text.push_str(value),
+                    Some((_, TemplateArg::Segment(segment))) if closed => {
+                        if !text.is_empty() {
+                            message.push(Segment::Text(TextSegment {
+                                text: std::mem::take(&mut text),
+                            }));
+                        }
+                        message.push(segment.clone());
+                    }
+                    _ => {
+                        // Leave unknown placeholders untouched so that they are easy to spot.
+                        text.push('{');
+                        text.push_str(&name);
+                        if closed {
+                            text.push('}');
+                        }
+                    }
+                }
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        message.push(Segment::Text(TextSegment { text }));
+    }
+    message
+}
+
+/// State holding the language of each group, used by the [`Lang`] extractor.
+pub struct LangSettings {
+    pub default: String,
+    pub groups: DashMap<i64, String>,
+}
+
+impl LangSettings {
+    pub fn new(default: &str) -> Self {
+        Self {
+            default: default.to_string(),
+            groups: DashMap::new(),
+        }
+    }
+}
+
+/// Extractor for the language of the event, the group setting from [`LangSettings`] or its default.
+/// The handler is skipped if [`LangSettings`] is not registered.
+pub struct Lang(pub String);
+
+#[async_trait]
+impl FromEvent for Lang {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let settings = context.state.get::<LangSettings>()?;
+        let group_id = match &event.event {
+            TypedEvent::Message(msg) => match &msg.info {
+                TypedMessageInfo::Group(info) => Some(info.group_id),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let lang = group_id
+            .and_then(|id| settings.groups.get(&id).map(|lang| lang.clone()))
+            .unwrap_or_else(|| settings.default.clone());
+        Some(Self(lang))
+    }
+}
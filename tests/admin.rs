@@ -0,0 +1,282 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBot, FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::message::Message,
+    extensions::admin::AdminService,
+    message::message_ext::MessageExt,
+};
+use serde_json::Value;
+
+const SUPERUSER: i64 = 7;
+
+/// Tells which messages got past the admin service.
+async fn later(ctx: BotContext, message: Message) -> HandlerControl {
+    let text = message.message.extract_plain_text();
+    ctx.send_private_message(99, format!("later {}", text), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+async fn connect(
+    server: &MockServer,
+    admin: AdminService,
+    configure: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder,
+) -> Arc<FlowBot> {
+    let bot = common::spawn(
+        configure(server.builder())
+            .with_service(admin)
+            .with_handler_filtered(later, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    bot
+}
+
+/// The text of every message sent since the last call, in order.
+struct Replies<'s> {
+    server: &'s MockServer,
+    seen: usize,
+}
+
+impl<'s> Replies<'s> {
+    fn new(server: &'s MockServer) -> Self {
+        Self { server, seen: 0 }
+    }
+
+    /// Send `event` and return the texts of the messages sent for it.
+    async fn to(&mut self, event: Value) -> Vec<String> {
+        self.server.send_event(event);
+        self.server.settle(Duration::from_millis(50)).await;
+        let sent = self
+            .server
+            .calls()
+            .into_iter()
+            .filter(|call| call.action.starts_with("send_"))
+            .map(|call| {
+                call.params["message"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|segment| segment["data"]["text"].as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        let new = sent[self.seen..].to_vec();
+        self.seen = sent.len();
+        new
+    }
+}
+
+fn private(text: &str) -> Value {
+    common::private_message(SUPERUSER, text)
+}
+
+fn group(group_id: i64, text: &str) -> Value {
+    common::group_message(group_id, SUPERUSER, "member", text)
+}
+
+#[tokio::test]
+async fn only_superusers_are_obeyed() {
+    let server = MockServer::start().await;
+    let bot = connect(&server, AdminService::new([SUPERUSER]), |builder| builder).await;
+    let mut replies = Replies::new(&server);
+
+    let status = common::private_message(2, "/shutdown");
+    assert_eq!(replies.to(status).await, ["later /shutdown"]);
+    assert!(!bot.context().is_shutting_down());
+    // Unknown commands and other prefixes are passed on.
+    assert_eq!(replies.to(private("/unknown")).await, ["later /unknown"]);
+    assert_eq!(replies.to(private("!status")).await, ["later !status"]);
+}
+
+#[tokio::test]
+async fn status_and_stats_are_reported() {
+    let server = MockServer::start().await;
+    let bot = connect(
+        &server,
+        AdminService::new([SUPERUSER]).command_prefix("!"),
+        |builder| builder,
+    )
+    .await;
+    let mut replies = Replies::new(&server);
+
+    let status = replies.to(private("!status")).await;
+    let [status] = &status[..] else {
+        panic!("{:?}", status);
+    };
+    assert!(
+        status.starts_with("State: Connected\nConnection: #1\n"),
+        "{}",
+        status
+    );
+    assert!(status.contains("\nRunning handlers: 1\n"), "{}", status);
+    // From the debug snapshot.
+    let states = bot.context().debug_snapshot().states.len();
+    assert!(
+        status.ends_with(&format!("\nStates: {}", states)),
+        "{}",
+        status
+    );
+
+    #[cfg(feature = "handler-stats")]
+    {
+        replies.to(private("hello")).await;
+        // Counting the Connected event, but not the stats command still running.
+        let stats = replies.to(private("!stats")).await;
+        let lines = stats[0].lines().collect::<Vec<_>>();
+        assert!(
+            lines[0].starts_with(
+                "0 flow_bot::extensions::admin::AdminService: 0 skip, 2 continue, 1 block, 0 panic"
+            ),
+            "{:?}",
+            lines
+        );
+        assert!(
+            lines[1].starts_with("1 admin::later: 0 skip, 1 continue, 0 block, 0 panic"),
+            "{:?}",
+            lines
+        );
+    }
+}
+
+#[tokio::test]
+async fn configs_are_reloaded_with_the_callback() {
+    let server = MockServer::start().await;
+    let _bot = connect(&server, AdminService::new([SUPERUSER]), |builder| builder).await;
+    let mut replies = Replies::new(&server);
+    assert_eq!(
+        replies.to(private("/reload-config")).await,
+        ["No config to reload"]
+    );
+
+    for (result, reply) in [
+        (Ok("Reloaded 3 settings"), "Reloaded 3 settings"),
+        (
+            Err("missing file"),
+            "Failed to reload the config: missing file",
+        ),
+    ] {
+        let server = MockServer::start().await;
+        let admin = AdminService::new([SUPERUSER])
+            .on_reload(move |_| async move { result.map(str::to_string).map_err(str::to_string) });
+        let _bot = connect(&server, admin, |builder| builder).await;
+        assert_eq!(
+            Replies::new(&server).to(private("/reload-config")).await,
+            [reply]
+        );
+    }
+}
+
+#[tokio::test]
+async fn groups_are_muted_and_unmuted() {
+    let server = MockServer::start().await;
+    let bot = connect(&server, AdminService::new([SUPERUSER]), |builder| builder).await;
+    let mut replies = Replies::new(&server);
+
+    assert_eq!(
+        replies.to(group(1, "/mute-group")).await,
+        ["Ignoring messages of group 1"]
+    );
+    assert_eq!(
+        replies.to(private("/mute-group 2")).await,
+        ["Ignoring messages of group 2"]
+    );
+    assert_eq!(
+        replies.to(private("/mute-group 2")).await,
+        ["Group 2 is already muted"]
+    );
+    assert_eq!(
+        replies.to(private("/mute-group")).await,
+        ["Usage: /mute-group <group id>"]
+    );
+    // Even the commands of superusers are dropped in muted groups.
+    assert!(replies.to(group(1, "/unmute-group")).await.is_empty());
+    assert!(replies.to(group(1, "hello")).await.is_empty());
+    assert_eq!(replies.to(group(3, "hello")).await, ["later hello"]);
+    assert_eq!(replies.to(private("/muted-groups")).await, ["1\n2"]);
+    assert_eq!(bot.context().ignored_groups().len(), 2);
+
+    assert_eq!(
+        replies.to(group(3, "/unmute-group 1")).await,
+        ["Handling messages of group 1 again"]
+    );
+    assert_eq!(
+        replies.to(private("/unmute-group 1")).await,
+        ["Group 1 is not muted"]
+    );
+    assert_eq!(replies.to(group(1, "hello")).await, ["later hello"]);
+    replies.to(private("/unmute-group 2")).await;
+    assert_eq!(
+        replies.to(private("/muted-groups")).await,
+        ["No muted groups"]
+    );
+}
+
+#[tokio::test]
+async fn commands_are_configured_per_group() {
+    let server = MockServer::start().await;
+    let _bot = connect(&server, AdminService::new([SUPERUSER]), |builder| builder).await;
+    assert_eq!(
+        Replies::new(&server)
+            .to(group(1, "/command-prefix !"))
+            .await,
+        ["Per-group commands are not enabled, see with_command_config"]
+    );
+
+    let server = MockServer::start().await;
+    let _bot = connect(&server, AdminService::new([SUPERUSER]), |builder| {
+        builder.with_command_config()
+    })
+    .await;
+    let mut replies = Replies::new(&server);
+    assert_eq!(
+        replies.to(group(1, "/command-prefix !")).await,
+        ["Group 1: prefix !, disabled commands: none"]
+    );
+    assert_eq!(
+        replies.to(private("/disable-command ping 1")).await,
+        ["Group 1: prefix !, disabled commands: ping"]
+    );
+    assert_eq!(
+        replies.to(group(1, "/enable-command ping")).await,
+        ["Group 1: prefix !, disabled commands: none"]
+    );
+    assert_eq!(
+        replies.to(private("/disable-command ping")).await,
+        ["Usage: /disable-command <command> [group id]"]
+    );
+    assert_eq!(
+        replies.to(group(1, "/reset-commands")).await,
+        ["Group 1 uses the default commands again"]
+    );
+}
+
+#[tokio::test]
+async fn shutdown_stops_the_bot_after_replying() {
+    let server = MockServer::start().await;
+    let bot = server
+        .builder()
+        .with_service(AdminService::new([SUPERUSER]))
+        .build();
+    let context = bot.context();
+    let run = tokio::spawn(async move { bot.run().await });
+    context.wait_for_connected().await;
+
+    server.send_event(private("/shutdown"));
+    tokio::time::timeout(common::TIMEOUT, run)
+        .await
+        .expect("run did not return")
+        .unwrap()
+        .unwrap();
+    let sent = server.calls_of("send_private_msg");
+    assert_eq!(
+        sent[0].params["message"][1]["data"]["text"],
+        "Shutting down"
+    );
+}
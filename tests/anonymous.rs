@@ -0,0 +1,83 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    base::{context::BotContext, extract::State, filter::EventFilter, handler::HandlerControl},
+    error::FlowError,
+    event::message::Anonymous,
+};
+use serde_json::json;
+
+/// The anonymous names seen and whether banning them worked.
+#[derive(Default)]
+struct Bans(Mutex<Vec<(Option<String>, String)>>);
+
+impl Bans {
+    fn push(&self, name: Option<String>, result: Result<(), FlowError>) {
+        let result = match result {
+            Ok(()) => "banned".to_string(),
+            Err(FlowError::NoAnonymousFlag) => "no flag".to_string(),
+            Err(e) => e.to_string(),
+        };
+        self.0.lock().unwrap().push((name, result));
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+async fn ban(ctx: BotContext, anonymous: Anonymous, bans: State<Arc<Bans>>) -> HandlerControl {
+    let name = anonymous.info.as_ref().map(|info| info.name.clone());
+    let result = anonymous.ban(&ctx, Duration::from_secs(60)).await;
+    bans.push(name, result);
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn anonymous_senders_are_banned_by_flag() {
+    let server = MockServer::start().await;
+    let bans = Arc::new(Bans::default());
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(bans.clone())
+            .with_handler_filtered(ban, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for (count, fixture) in ["complete", "nameless", "null"].into_iter().enumerate() {
+        server.send_event(common::fixture(&format!("anonymous/{}.json", fixture)));
+        server.wait_until(|_| bans.len() > count).await;
+    }
+    // Not anonymous.
+    server.send_event(common::group_message(1, 3, "member", "hello"));
+    server.settle(Duration::from_millis(100)).await;
+
+    assert_eq!(
+        *bans.0.lock().unwrap(),
+        [
+            (Some("大力鲸".to_string()), "banned".to_string()),
+            (Some(String::new()), "banned".to_string()),
+            (None, "no flag".to_string()),
+        ]
+    );
+    let params = server
+        .calls_of("set_group_anonymous_ban")
+        .into_iter()
+        .map(|call| call.params)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        params,
+        [
+            json!({"group_id": 1, "flag": "1234|大力鲸", "duration": 60}),
+            json!({"group_id": 1, "flag": "5678|", "duration": 60}),
+        ]
+    );
+}
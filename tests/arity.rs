@@ -0,0 +1,122 @@
+mod common;
+
+use std::sync::Mutex;
+
+use common::MockServer;
+use flow_bot::{
+    base::{
+        context::BotContext,
+        extract::{GroupId, MessageBody, SenderId, State},
+        filter::EventFilter,
+        handler::{HandlerControl, handler_fn},
+    },
+    event::{BotEvent, message::Message},
+};
+
+/// The handlers that ran, by name.
+static RAN: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+fn ran(name: &'static str) -> HandlerControl {
+    RAN.lock().unwrap().push(name);
+    HandlerControl::Continue
+}
+
+struct Marker;
+
+async fn no_arguments() -> HandlerControl {
+    ran("no_arguments")
+}
+
+async fn one_argument(_: Message) -> HandlerControl {
+    ran("one_argument")
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn fifteen_arguments(
+    _: Message,
+    _: BotContext,
+    _: BotEvent,
+    _: MessageBody,
+    _: SenderId,
+    _: Option<GroupId>,
+    _: State<Marker>,
+    _: (),
+    _: (),
+    _: (),
+    _: (),
+    _: (),
+    _: (),
+    _: (),
+    _: Option<State<String>>,
+) -> HandlerControl {
+    ran("fifteen_arguments")
+}
+
+#[tokio::test]
+async fn handlers_of_every_arity_run() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(Marker)
+            .with_handler_filtered(no_arguments, EventFilter::MESSAGE)
+            .with_handler_filtered(
+                || async { ran("no_arguments_closure") },
+                EventFilter::MESSAGE,
+            )
+            .with_handler_filtered(one_argument, EventFilter::MESSAGE)
+            .with_handler_filtered(fifteen_arguments, EventFilter::MESSAGE)
+            .with_handler_filtered(
+                handler_fn("captured", |name: &'static str| async move { ran(name) }),
+                EventFilter::MESSAGE,
+            )
+            .with_handler_filtered(
+                handler_fn(
+                    "captured_one",
+                    |name: &'static str, _: Message| async move { ran(name) },
+                ),
+                EventFilter::MESSAGE,
+            )
+            .with_handler_filtered(
+                handler_fn(
+                    "captured_fifteen",
+                    |name: &'static str,
+                     _: Message,
+                     _: BotContext,
+                     _: BotEvent,
+                     _: MessageBody,
+                     _: SenderId,
+                     _: Option<GroupId>,
+                     _: State<Marker>,
+                     _: (),
+                     _: (),
+                     _: (),
+                     _: (),
+                     _: (),
+                     _: (),
+                     _: (),
+                     _: Option<State<String>>| async move { ran(name) },
+                ),
+                EventFilter::MESSAGE,
+            )
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    server.send_event(common::private_message(2, "hello"));
+    server.wait_until(|_| RAN.lock().unwrap().len() == 7).await;
+
+    let mut names = RAN.lock().unwrap().clone();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "captured",
+            "captured_fifteen",
+            "captured_one",
+            "fifteen_arguments",
+            "no_arguments",
+            "no_arguments_closure",
+            "one_argument",
+        ]
+    );
+}
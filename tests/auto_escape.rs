@@ -0,0 +1,79 @@
+mod common;
+
+use common::MockServer;
+use flow_bot::{FlowBotBuilder, api::api_ext::ApiExt, base::context::BotContext};
+use serde_json::{Value, json};
+
+const CQ: &str = "[CQ:face,id=1]";
+
+async fn connect(builder: FlowBotBuilder) -> BotContext {
+    let bot = common::spawn(builder.build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+fn sent(server: &MockServer) -> Vec<(Value, Value)> {
+    server
+        .calls_of("send_private_msg")
+        .into_iter()
+        .chain(server.calls_of("send_group_msg"))
+        .map(|call| {
+            (
+                call.params["auto_escape"].clone(),
+                call.params["message"].clone(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn text_is_always_sent_as_segments() {
+    let server = MockServer::start().await;
+    let context = connect(server.builder()).await;
+
+    context.send_private_message(2, CQ, None).await.unwrap();
+    context
+        .send_group_message(1, CQ.to_string(), None)
+        .await
+        .unwrap();
+
+    // The implementation decides without an `auto_escape`, a literal text segment is never parsed.
+    let literal = json!([{"type": "text", "data": {"text": CQ}}]);
+    assert_eq!(
+        sent(&server),
+        [(Value::Null, literal.clone()), (Value::Null, literal)]
+    );
+}
+
+#[tokio::test]
+async fn the_default_applies_unless_overridden() {
+    let server = MockServer::start().await;
+    let context = connect(server.builder().with_auto_escape(true)).await;
+
+    context.send_private_message(2, "hi", None).await.unwrap();
+    context
+        .send_private_message(2, "hi", Some(false))
+        .await
+        .unwrap();
+    context
+        .send_group_message_raw_string(1, CQ.to_string(), None)
+        .await
+        .unwrap();
+    context
+        .send_group_message_raw_string(1, CQ.to_string(), Some(false))
+        .await
+        .unwrap();
+
+    let text = json!([{"type": "text", "data": {"text": "hi"}}]);
+    assert_eq!(
+        sent(&server),
+        [
+            (json!(true), text.clone()),
+            (json!(false), text),
+            // Strings are sent as they are, to be parsed unless escaped.
+            (json!(true), json!(CQ)),
+            (json!(false), json!(CQ)),
+        ]
+    );
+}
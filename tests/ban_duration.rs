@@ -0,0 +1,78 @@
+mod common;
+
+use std::time::Duration;
+
+use common::MockServer;
+use flow_bot::api::{BanDuration, api_ext::ApiExt};
+use serde_json::{Value, json};
+
+fn serialized(duration: impl Into<BanDuration>) -> Value {
+    serde_json::to_value(duration.into()).unwrap()
+}
+
+#[test]
+fn constructors_serialize_to_seconds() {
+    assert_eq!(serialized(BanDuration::seconds(30)), json!(30));
+    assert_eq!(serialized(BanDuration::minutes(10)), json!(600));
+    assert_eq!(serialized(BanDuration::hours(2)), json!(7200));
+    assert_eq!(serialized(BanDuration::days(30)), json!(2592000));
+    assert_eq!(serialized(BanDuration::lift()), json!(0));
+    // Left to the implementation.
+    assert_eq!(serialized(BanDuration::default()), Value::Null);
+}
+
+#[test]
+fn raw_seconds_convert() {
+    assert_eq!(serialized(600), json!(600));
+    assert_eq!(serialized(0), json!(0));
+    assert_eq!(serialized(-5), json!(0));
+    assert_eq!(serialized(Some(60)), json!(60));
+    assert_eq!(serialized(None::<i64>), Value::Null);
+    assert_eq!(serialized(Duration::from_secs(90)), json!(90));
+    assert_eq!(BanDuration::minutes(10).as_secs(), Some(600));
+    assert_eq!(BanDuration::default().as_secs(), None);
+}
+
+#[tokio::test]
+async fn durations_are_sent_in_seconds() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    context
+        .set_group_ban(1, 2, BanDuration::minutes(10))
+        .await
+        .unwrap();
+    context.set_group_ban(1, 2, 600).await.unwrap();
+    context
+        .set_group_ban(1, 2, Duration::from_secs(600))
+        .await
+        .unwrap();
+    context
+        .set_group_ban(1, 2, BanDuration::lift())
+        .await
+        .unwrap();
+    let durations = server
+        .calls_of("set_group_ban")
+        .into_iter()
+        .map(|call| call.params["duration"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(durations, [json!(600), json!(600), json!(600), json!(0)]);
+
+    context
+        .set_group_special_title(1, 2, Some("title".to_string()), BanDuration::days(1))
+        .await
+        .unwrap();
+    context
+        .set_group_special_title(1, 2, None, None::<i64>)
+        .await
+        .unwrap();
+    let titles = server.calls_of("set_group_special_title");
+    assert_eq!(titles[0].params["duration"], 86400);
+    assert!(
+        titles[1].params.get("duration").is_none(),
+        "{}",
+        titles[1].params
+    );
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use flow_bot::{api::api_ext::ApiExt, base::context::BotContext, error::FlowError};
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+
+/// Answered with [`Reply::Silent`], the tests answer it themselves.
+const SILENT: &str = "silent_action";
+
+fn response(echo: &str, data: Value) -> String {
+    json!({"status": "ok", "retcode": 0, "data": data, "echo": echo}).to_string()
+}
+
+async fn connect(server: &MockServer) -> BotContext {
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+/// Run a batch of `count` calls of [`SILENT`], numbered in their params, with at most `limit` at a time.
+fn run_batch(
+    context: &BotContext,
+    count: i64,
+    limit: usize,
+) -> JoinHandle<Vec<Result<Value, FlowError>>> {
+    let context = context.clone();
+    tokio::spawn(async move {
+        let mut batch = context.batch();
+        for n in 0..count {
+            batch = batch.call(move |ctx| async move {
+                ctx.call_action(SILENT, json!({"n": n}))
+                    .await
+                    .map(|response| response.data)
+            });
+        }
+        assert_eq!(batch.len(), count as usize);
+        batch.execute_concurrent(limit).await
+    })
+}
+
+/// Answer the call numbered `n` with `n * 10`.
+fn answer(server: &MockServer, n: i64) {
+    let call = server
+        .calls_of(SILENT)
+        .into_iter()
+        .find(|call| call.params["n"] == n)
+        .unwrap();
+    server.send_event(response(&call.echo, json!(n * 10)));
+}
+
+fn numbers(server: &MockServer) -> Vec<i64> {
+    server
+        .calls_of(SILENT)
+        .into_iter()
+        .map(|call| call.params["n"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn calls_are_limited_and_results_keep_their_order() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        SILENT => Reply::Silent,
+        action => common::canned(action),
+    })
+    .await;
+    let context = connect(&server).await;
+
+    let batch = run_batch(&context, 5, 2);
+    server.wait_calls_of(SILENT, 2).await;
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(numbers(&server), [0, 1]);
+
+    // A later call finishing first does not free its slot before the earlier ones.
+    answer(&server, 1);
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(numbers(&server), [0, 1]);
+    answer(&server, 0);
+    server.wait_calls_of(SILENT, 4).await;
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(numbers(&server), [0, 1, 2, 3]);
+
+    answer(&server, 3);
+    answer(&server, 2);
+    server.wait_calls_of(SILENT, 5).await;
+    answer(&server, 4);
+
+    let results = tokio::time::timeout(common::TIMEOUT, batch)
+        .await
+        .unwrap()
+        .unwrap();
+    let results = results.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        [json!(0), json!(10), json!(20), json!(30), json!(40)]
+    );
+
+    // Every call was a request of its own.
+    let mut echoes = server
+        .calls_of(SILENT)
+        .into_iter()
+        .map(|call| call.echo)
+        .collect::<Vec<_>>();
+    echoes.sort();
+    echoes.dedup();
+    assert_eq!(echoes.len(), 5);
+}
+
+#[tokio::test]
+async fn failures_are_returned_per_call() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "send_like" if call.params["user_id"] == 2 => Reply::Failed(100),
+        action => common::canned(action),
+    })
+    .await;
+    let context = connect(&server).await;
+
+    let results = context
+        .batch()
+        .call(|ctx| ctx.send_like(1, None))
+        .call(|ctx| ctx.send_like(2, None))
+        .call(|ctx| ctx.send_like(3, None))
+        // A limit of zero runs one call at a time.
+        .execute_concurrent(0)
+        .await;
+    let failed = results.iter().map(Result::is_err).collect::<Vec<_>>();
+    assert_eq!(failed, [false, true, false]);
+    assert_eq!(server.calls_of("send_like").len(), 3);
+}
+
+#[tokio::test]
+async fn empty_batches_return_right_away() {
+    let server = MockServer::start().await;
+    let context = connect(&server).await;
+
+    let batch = context.batch::<()>();
+    assert!(batch.is_empty());
+    assert!(batch.execute().await.is_empty());
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{context::BotContext, handler::HandlerControl},
+    event::message::Message,
+    message::message_ext::MessageExt,
+};
+
+const BLOCKED_FOR: Duration = Duration::from_secs(1);
+
+/// Blocks its thread on `slow`, as CPU heavy work would.
+async fn slow(message: Message) -> HandlerControl {
+    if message.message.extract_plain_text() == "slow" {
+        std::thread::sleep(BLOCKED_FOR);
+    }
+    HandlerControl::Continue
+}
+
+async fn pong(ctx: BotContext, message: Message) -> HandlerControl {
+    if message.message.extract_plain_text() == "ping" {
+        ctx.send_private_message(message.user_id, "pong", None)
+            .await?;
+    }
+    HandlerControl::Continue
+}
+
+/// Send `slow` and then `ping`, returning how long the pong took.
+/// The tests run on a single threaded runtime, which a blocking handler would stall.
+async fn pong_delay(configure: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder) -> Duration {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        configure(server.builder().with_blocking_handler(slow))
+            .with_handler(pong)
+            .try_build()
+            .unwrap(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(2, "slow"));
+    // Let the slow handler start blocking.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let started = Instant::now();
+    server.send_event(common::private_message(2, "ping"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    started.elapsed()
+}
+
+#[tokio::test]
+async fn blocking_handlers_do_not_delay_others() {
+    let delay = pong_delay(|builder| builder).await;
+    assert!(delay < BLOCKED_FOR / 2, "{:?}", delay);
+}
+
+// The ping also passes through the blocking handler, so it needs a second thread.
+#[tokio::test]
+async fn blocking_handlers_on_a_dedicated_runtime_do_not_delay_others() {
+    let delay = pong_delay(|builder| builder.with_blocking_handlers(2)).await;
+    assert!(delay < BLOCKED_FOR / 2, "{:?}", delay);
+}
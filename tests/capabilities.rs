@@ -0,0 +1,98 @@
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{api_ext::ApiExt, retcode::RetCode},
+    base::context::BotContext,
+    error::FlowError,
+};
+
+async fn connect(server: &MockServer) -> BotContext {
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+#[tokio::test]
+async fn unsupported_actions_are_learned_from_1404() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "send_like" | "mark_msg_as_read" => Reply::Failed(1404),
+        action => common::canned(action),
+    })
+    .await;
+    let context = connect(&server).await;
+
+    let capabilities = context.capabilities().await;
+    assert!(capabilities.supports("send_like"));
+    assert!(capabilities.supports("mark_msg_as_read"));
+
+    let error = context.send_like(2, None).await.unwrap_err();
+    assert!(matches!(
+        error.root_cause(),
+        FlowError::Api {
+            retcode: RetCode::Unsupported,
+            ..
+        }
+    ));
+    context.mark_msg_as_read(1).await.unwrap_err();
+
+    let capabilities = context.capabilities().await;
+    assert!(!capabilities.supports("send_like"));
+    assert!(!capabilities.supports("mark_msg_as_read"));
+    // Other actions are not affected.
+    assert!(capabilities.supports("send_private_msg"));
+    assert!(capabilities.supports("set_msg_emoji_like"));
+}
+
+#[tokio::test]
+async fn an_implementation_refusing_everything_supports_nothing_it_was_asked() {
+    let server = MockServer::start_with(|_| Reply::Failed(1404)).await;
+    let context = connect(&server).await;
+
+    context.send_like(2, None).await.unwrap_err();
+    let capabilities = context.capabilities().await;
+    assert!(!capabilities.supports("send_like"));
+    assert!(!capabilities.supports("get_version_info"));
+    // Without a version, extensions are not assumed.
+    assert!(capabilities.version().is_none());
+    assert!(!capabilities.supports("set_msg_emoji_like"));
+}
+
+#[tokio::test]
+async fn other_failures_say_nothing_about_support() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "send_like" => Reply::Failed(100),
+        action => common::canned(action),
+    })
+    .await;
+    let context = connect(&server).await;
+
+    context.send_like(2, None).await.unwrap_err();
+    assert!(context.capabilities().await.supports("send_like"));
+}
+
+#[tokio::test]
+async fn actions_succeeding_later_are_supported_again() {
+    let supported = Arc::new(AtomicBool::new(false));
+    let answer = supported.clone();
+    let server = MockServer::start_with(move |call| match call.action.as_str() {
+        "send_like" if !answer.load(Ordering::Relaxed) => Reply::Failed(1404),
+        action => common::canned(action),
+    })
+    .await;
+    let context = connect(&server).await;
+
+    context.send_like(2, None).await.unwrap_err();
+    assert!(!context.capabilities().await.supports("send_like"));
+
+    // E.g. after the implementation was updated.
+    supported.store(true, Ordering::Relaxed);
+    context.send_like(2, None).await.unwrap();
+    assert!(context.capabilities().await.supports("send_like"));
+}
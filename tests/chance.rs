@@ -0,0 +1,226 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    base::{
+        chance::{Chance, RngState, Sampled},
+        extract::{MatchGroup, State},
+        filter::EventFilter,
+        handler::HandlerControl,
+    },
+    event::message::Message,
+    message::message_ext::MessageExt,
+};
+use serde_json::Value;
+
+/// Whether each event passed the guard, as `text:passed`.
+#[derive(Default)]
+struct Outcomes(Mutex<Vec<String>>);
+
+impl Outcomes {
+    fn push(&self, message: &Message, passed: bool) {
+        let text = message.message.extract_if_plain_text().unwrap();
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}:{}", text, passed as u8));
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+async fn half(
+    _: MatchGroup,
+    chance: Option<Chance<500>>,
+    message: Message,
+    outcomes: State<Arc<Outcomes>>,
+) -> HandlerControl {
+    outcomes.push(&message, chance.is_some());
+    HandlerControl::Continue
+}
+
+async fn never(
+    chance: Option<Chance<0>>,
+    message: Message,
+    outcomes: State<Arc<Outcomes>>,
+) -> HandlerControl {
+    outcomes.push(&message, chance.is_some());
+    HandlerControl::Continue
+}
+
+async fn always(
+    chance: Option<Chance<1000>>,
+    message: Message,
+    outcomes: State<Arc<Outcomes>>,
+) -> HandlerControl {
+    outcomes.push(&message, chance.is_some());
+    HandlerControl::Continue
+}
+
+async fn third(
+    sampled: Option<Sampled<3>>,
+    message: Message,
+    outcomes: State<Arc<Outcomes>>,
+) -> HandlerControl {
+    outcomes.push(&message, sampled.is_some());
+    HandlerControl::Continue
+}
+
+/// Start a bot recording into `outcomes`, with the handlers added by `configure`.
+async fn connect(
+    server: &MockServer,
+    outcomes: &Arc<Outcomes>,
+    configure: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder,
+) -> Arc<flow_bot::FlowBot> {
+    let bot = common::spawn(configure(server.builder().with_state(outcomes.clone())).build());
+    bot.context().wait_for_connected().await;
+    bot
+}
+
+/// Send the events one at a time, waiting after each until `expected` outcomes were recorded.
+async fn send_in_order(
+    server: &MockServer,
+    outcomes: &Outcomes,
+    events: impl IntoIterator<Item = (Value, usize)>,
+) {
+    for (event, expected) in events {
+        server.send_event(&event);
+        tokio::time::timeout(common::TIMEOUT, async {
+            while outcomes.len() < expected {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("{:?}", outcomes.take()));
+    }
+    server.settle(Duration::from_millis(50)).await;
+}
+
+#[tokio::test]
+async fn a_seeded_chance_passes_the_same_events_every_run() {
+    let expected = (0..12)
+        .map(|i| format!("{}:{}", i, [1, 1, 0, 0, 1, 1, 0, 0, 1, 0, 1, 0][i]))
+        .collect::<Vec<_>>();
+    for _ in 0..2 {
+        let outcomes = Arc::new(Outcomes::default());
+        let server = MockServer::start().await;
+        let _bot = connect(&server, &outcomes, |builder| {
+            builder
+                .with_state(RngState::seeded(42))
+                .with_handler_filtered(half, EventFilter::MESSAGE)
+        })
+        .await;
+
+        let mut events = Vec::new();
+        for i in 0..12 {
+            // Events the other extractors reject draw no number.
+            events.push((common::private_message(2, "ignored"), i));
+            events.push((common::group_message(1, 2, "member", i.to_string()), i + 1));
+        }
+        send_in_order(&server, &outcomes, events).await;
+
+        assert_eq!(outcomes.take(), expected);
+    }
+}
+
+#[tokio::test]
+async fn the_numbers_come_from_the_registered_source() {
+    let draws = Arc::new(Mutex::new(vec![999, 0, 500, 499, 1499]));
+    let source = draws.clone();
+    let outcomes = Arc::new(Outcomes::default());
+    let server = MockServer::start().await;
+    let _bot = connect(&server, &outcomes, |builder| {
+        builder
+            .with_state(RngState::new(move || source.lock().unwrap().remove(0)))
+            .with_handler_filtered(half, EventFilter::MESSAGE)
+    })
+    .await;
+
+    send_in_order(
+        &server,
+        &outcomes,
+        (0..5).map(|i| (common::group_message(1, 2, "member", i.to_string()), i + 1)),
+    )
+    .await;
+
+    assert_eq!(outcomes.take(), ["0:0", "1:1", "2:0", "3:1", "4:1"]);
+    assert!(draws.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn no_chance_never_passes_and_a_certain_one_always_does() {
+    let outcomes = Arc::new(Outcomes::default());
+    let server = MockServer::start().await;
+    let _bot = connect(&server, &outcomes, |builder| {
+        builder
+            .with_handler_filtered(never, EventFilter::MESSAGE)
+            .with_handler_filtered(always, EventFilter::MESSAGE)
+    })
+    .await;
+
+    send_in_order(
+        &server,
+        &outcomes,
+        (0..50).map(|i| (common::private_message(2, "x"), 2 * (i + 1))),
+    )
+    .await;
+
+    let mut outcomes = outcomes.take();
+    outcomes.sort();
+    assert_eq!(outcomes[..50], ["x:0"; 50]);
+    assert_eq!(outcomes[50..], ["x:1"; 50]);
+}
+
+#[tokio::test]
+async fn every_nth_event_is_sampled_per_group() {
+    let outcomes = Arc::new(Outcomes::default());
+    let server = MockServer::start().await;
+    let _bot = connect(&server, &outcomes, |builder| {
+        builder.with_handler_filtered(third, EventFilter::MESSAGE)
+    })
+    .await;
+
+    let events = [
+        common::group_message(1, 2, "member", "a1"),
+        common::group_message(2, 2, "member", "b1"),
+        common::group_message(1, 3, "member", "a2"),
+        common::private_message(2, "p1"),
+        common::group_message(2, 2, "member", "b2"),
+        common::group_message(1, 2, "member", "a3"),
+        common::private_message(3, "p2"),
+        common::group_message(2, 3, "member", "b3"),
+        common::group_message(1, 2, "member", "a4"),
+        common::private_message(2, "p3"),
+        common::group_message(1, 2, "member", "a5"),
+        common::group_message(1, 2, "member", "a6"),
+    ];
+    send_in_order(
+        &server,
+        &outcomes,
+        events
+            .into_iter()
+            .enumerate()
+            .map(|(i, event)| (event, i + 1)),
+    )
+    .await;
+
+    assert_eq!(
+        outcomes.take(),
+        [
+            "a1:0", "b1:0", "a2:0", "p1:0", "b2:0", "a3:1", "p2:0", "b3:1", "a4:0", "p3:1", "a5:0",
+            "a6:1",
+        ]
+    );
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::api_ext::ApiExt,
+    error::FlowError,
+    message::{
+        IntoMessage, Message,
+        message_ext::MessageExt,
+        segments::{ImageSegment, ReplySegment, Segment, TextSegment},
+    },
+};
+use serde_json::json;
+
+fn text(text: &str) -> Segment {
+    Segment::Text(TextSegment {
+        text: text.to_string(),
+    })
+}
+
+fn image() -> Segment {
+    Segment::Image(ImageSegment {
+        file: "a.png".to_string(),
+        url: None,
+    })
+}
+
+fn reply() -> Segment {
+    Segment::Reply(ReplySegment {
+        id: "1".to_string(),
+    })
+}
+
+/// The chunks with text as is and other segments as `[image]` or `[reply]`.
+fn render(chunks: &[Message]) -> Vec<String> {
+    chunks
+        .iter()
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Text(text) => text.text.clone(),
+                    Segment::Image(_) => "[image]".to_string(),
+                    Segment::Reply(_) => "[reply]".to_string(),
+                    other => panic!("unexpected segment {:?}", other),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn text_of_exactly_the_limit_is_one_chunk() {
+    let chunks = "abcde".into_message().split_chunks(5);
+    assert_eq!(render(&chunks), ["abcde"]);
+
+    let chunks = vec![text("ab"), text("cde")].split_chunks(5);
+    assert_eq!(render(&chunks), ["abcde"]);
+    assert_eq!(chunks[0].len(), 2);
+}
+
+#[test]
+fn one_more_character_starts_a_chunk() {
+    let chunks = "abcdef".into_message().split_chunks(5);
+    assert_eq!(render(&chunks), ["abcde", "f"]);
+}
+
+#[test]
+fn an_oversized_text_segment_is_split_within() {
+    let chunks = "a".repeat(12).into_message().split_chunks(5);
+    assert_eq!(render(&chunks), ["aaaaa", "aaaaa", "aa"]);
+}
+
+#[test]
+fn splits_prefer_the_last_line_break() {
+    let chunks = "ab\ncd\nefgh".into_message().split_chunks(7);
+    assert_eq!(render(&chunks), ["ab\ncd\n", "efgh"]);
+}
+
+#[test]
+fn characters_are_counted_not_bytes() {
+    let chunks = "你好世界！".into_message().split_chunks(2);
+    assert_eq!(render(&chunks), ["你好", "世界", "！"]);
+}
+
+#[test]
+fn media_is_never_split_and_does_not_count() {
+    let message = vec![text("abc"), image(), text("defgh"), image(), text("ij")];
+    let chunks = message.split_chunks(4);
+    // A full chunk still takes the media following it.
+    assert_eq!(render(&chunks), ["abc[image]d", "efgh[image]", "ij"]);
+    let images = chunks
+        .iter()
+        .flatten()
+        .filter(|segment| matches!(segment, Segment::Image(_)))
+        .count();
+    assert_eq!(images, 2);
+}
+
+#[test]
+fn a_leading_reply_is_only_in_the_first_chunk() {
+    let chunks = vec![reply(), text("abcdefgh")].split_chunks(4);
+    assert_eq!(render(&chunks), ["[reply]abcd", "efgh"]);
+}
+
+#[test]
+fn a_zero_limit_keeps_the_message_whole() {
+    let message = vec![text("abc"), image()];
+    assert_eq!(render(&message.split_chunks(0)), ["abc[image]"]);
+    assert!(Message::new().split_chunks(0).is_empty());
+    assert!(Message::new().split_chunks(5).is_empty());
+}
+
+#[tokio::test]
+async fn every_chunk_is_sent_in_order() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let responses = context
+        .send_group_message_chunked(1, "abcdefghij", 4)
+        .await
+        .unwrap();
+    assert_eq!(responses.len(), 3);
+    let sent = server
+        .calls_of("send_group_msg")
+        .into_iter()
+        .map(|call| call.params["message"][0]["data"]["text"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(sent, [json!("abcd"), json!("efgh"), json!("ij")]);
+}
+
+#[tokio::test]
+async fn a_failed_chunk_returns_the_chunks_sent_before() {
+    // Chunks with an x fail.
+    let server = MockServer::start_with(|call| {
+        let text = call.params["message"][0]["data"]["text"].as_str();
+        match (call.action.as_str(), text) {
+            ("send_private_msg", Some(text)) if text.contains('x') => Reply::Failed(1400),
+            (action, _) => common::canned(action),
+        }
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let error = context
+        .send_private_message_chunked(2, "abcdxfghij", 4)
+        .await
+        .unwrap_err();
+    let FlowError::PartiallySent { sent, total, .. } = &error else {
+        panic!("{:?}", error);
+    };
+    assert_eq!((sent.len(), *total), (1, 3));
+    assert!(matches!(error.root_cause(), FlowError::Api { .. }));
+    assert!(
+        error
+            .to_string()
+            .starts_with("Sent 1 of 3 chunks before failing: "),
+        "{}",
+        error
+    );
+    // Stopped at the failure.
+    assert_eq!(server.calls_of("send_private_msg").len(), 2);
+
+    // Nothing was sent, the error is returned as is.
+    let error = context
+        .send_private_message_chunked(2, "xbcdefgh", 4)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, FlowError::ApiCall { .. }), "{:?}", error);
+}
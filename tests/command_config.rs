@@ -0,0 +1,147 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use clap::Parser;
+use common::MockServer;
+use flow_bot::{
+    FlowBot, FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        extract::{Command, GroupId},
+        filter::EventFilter,
+        handler::HandlerControl,
+    },
+    extensions::admin::AdminService,
+};
+use serde_json::Value;
+
+const SUPERUSER: i64 = 7;
+
+/// Where the answers of [`ping`] are sent.
+const OBSERVER: i64 = 99;
+
+#[derive(Parser)]
+struct Ping;
+
+/// Answers `/ping`, telling where it was sent.
+async fn ping(
+    ctx: BotContext,
+    group: Option<GroupId>,
+    _: Command<"/ping", Ping>,
+) -> HandlerControl {
+    let answer = match group {
+        Some(GroupId(group_id)) => format!("pong in {}", group_id),
+        None => "pong in private".to_string(),
+    };
+    ctx.send_private_message(OBSERVER, answer, None).await?;
+    HandlerControl::Block
+}
+
+async fn connect(
+    server: &MockServer,
+    configure: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder,
+) -> Arc<FlowBot> {
+    let bot = common::spawn(
+        configure(server.builder())
+            .with_service(AdminService::new([SUPERUSER]))
+            .with_handler_filtered(ping, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    bot
+}
+
+/// Send `event` and return the answers of [`ping`] to it.
+async fn pongs(server: &MockServer, event: Value) -> Vec<String> {
+    let before = answers(server).len();
+    server.send_event(event);
+    server.settle(Duration::from_millis(50)).await;
+    answers(server)[before..].to_vec()
+}
+
+fn answers(server: &MockServer) -> Vec<String> {
+    server
+        .calls_of("send_private_msg")
+        .into_iter()
+        .filter(|call| call.params["user_id"] == OBSERVER)
+        .map(|call| {
+            call.params["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Send an admin command, from a private chat so that it is not subject to the configuration it changes.
+async fn admin(server: &MockServer, command: &str) {
+    let before = server.calls_of("send_private_msg").len();
+    server.send_event(common::private_message(SUPERUSER, command));
+    server.wait_calls_of("send_private_msg", before + 1).await;
+}
+
+fn group(group_id: i64, text: &str) -> Value {
+    common::group_message(group_id, 2, "member", text)
+}
+
+fn private(text: &str) -> Value {
+    common::private_message(2, text)
+}
+
+#[tokio::test]
+async fn the_group_prefix_replaces_the_command_prefix() {
+    let server = MockServer::start().await;
+    let _bot = connect(&server, FlowBotBuilder::with_command_config).await;
+
+    // Without a configuration, the prefix of the command is used.
+    assert_eq!(pongs(&server, group(1, "/ping")).await, ["pong in 1"]);
+    assert!(pongs(&server, group(1, "#ping")).await.is_empty());
+
+    admin(&server, "/command-prefix # 1").await;
+    assert_eq!(pongs(&server, group(1, "#ping")).await, ["pong in 1"]);
+    assert!(pongs(&server, group(1, "/ping")).await.is_empty());
+    // Other groups and private messages keep the prefix of the command.
+    assert_eq!(pongs(&server, group(2, "/ping")).await, ["pong in 2"]);
+    assert!(pongs(&server, group(2, "#ping")).await.is_empty());
+    assert_eq!(pongs(&server, private("/ping")).await, ["pong in private"]);
+    assert!(pongs(&server, private("#ping")).await.is_empty());
+
+    // Prefixes may be longer than one character.
+    admin(&server, "/command-prefix !! 1").await;
+    assert_eq!(pongs(&server, group(1, "!!ping")).await, ["pong in 1"]);
+    assert!(pongs(&server, group(1, "#ping")).await.is_empty());
+
+    admin(&server, "/reset-commands 1").await;
+    assert_eq!(pongs(&server, group(1, "/ping")).await, ["pong in 1"]);
+}
+
+#[tokio::test]
+async fn disabled_commands_only_skip_in_their_group() {
+    let server = MockServer::start().await;
+    let _bot = connect(&server, FlowBotBuilder::with_command_config).await;
+
+    admin(&server, "/disable-command ping 1").await;
+    assert!(pongs(&server, group(1, "/ping")).await.is_empty());
+    assert_eq!(pongs(&server, group(2, "/ping")).await, ["pong in 2"]);
+    assert_eq!(pongs(&server, private("/ping")).await, ["pong in private"]);
+
+    // Disabled whatever the prefix of the group.
+    admin(&server, "/command-prefix # 1").await;
+    assert!(pongs(&server, group(1, "#ping")).await.is_empty());
+
+    admin(&server, "/enable-command ping 1").await;
+    assert_eq!(pongs(&server, group(1, "#ping")).await, ["pong in 1"]);
+}
+
+#[tokio::test]
+async fn without_command_config_the_command_prefix_is_used_everywhere() {
+    let server = MockServer::start().await;
+    let _bot = connect(&server, |builder| builder).await;
+
+    admin(&server, "/command-prefix # 1").await;
+    assert_eq!(pongs(&server, group(1, "/ping")).await, ["pong in 1"]);
+    assert!(pongs(&server, group(1, "#ping")).await.is_empty());
+    assert_eq!(pongs(&server, private("/ping")).await, ["pong in private"]);
+}
@@ -0,0 +1,72 @@
+//! Capturing what the bot logs, for tests asserting on warnings and debug events.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    Event, Subscriber,
+    field::{Field, Visit},
+    subscriber::DefaultGuard,
+};
+use tracing_subscriber::{Layer, layer::Context, prelude::*};
+
+/// The fields of every event logged, by name. The text of an event is its `message`.
+#[derive(Clone, Default)]
+pub struct Captured(Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+impl Captured {
+    /// Capture the events logged on this thread until the guard is dropped.
+    ///
+    /// The runtime of a `#[tokio::test]` is single threaded, so the bot logs here too.
+    pub fn start() -> (Self, DefaultGuard) {
+        let captured = Self::default();
+        let guard = tracing_subscriber::registry()
+            .with(captured.clone())
+            .set_default();
+        (captured, guard)
+    }
+
+    /// The events whose message is `message`.
+    pub fn events(&self, message: &str) -> Vec<HashMap<String, String>> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|fields| fields.get("message").is_some_and(|m| m == message))
+            .cloned()
+            .collect()
+    }
+
+    /// The messages of every event logged.
+    pub fn messages(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|fields| fields.get("message").cloned())
+            .collect()
+    }
+}
+
+struct Fields<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Captured {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        event.record(&mut Fields(&mut fields));
+        self.0.lock().unwrap().push(fields);
+    }
+}
@@ -0,0 +1,407 @@
+//! A mock onebot implementation for the integration tests.
+//!
+//! The bot under test connects to a [`MockServer`], which records every API call, answers it with a
+//! [`Reply`] and pushes events to the bot. Tests either push events one by one or [replay](MockServer::replay)
+//! a fixture of recorded events, then assert the calls the bot made.
+#![allow(dead_code)]
+
+pub mod logs;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use flow_bot::{
+    FlowBot, FlowBotBuilder,
+    base::connect::{ReconnectionStrategy, ReverseConnectionConfig},
+};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_tungstenite::tungstenite::{
+    Message,
+    protocol::{CloseFrame, frame::coding::CloseCode},
+};
+
+/// How long a test waits for the bot before failing.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The id of the bot account in events and canned responses.
+pub const SELF_ID: i64 = 10000;
+
+/// An API call received by the mock server.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub action: String,
+    pub params: Value,
+    /// The connection it was received on, counting from 0.
+    pub connection: usize,
+    /// The echo the response must carry.
+    pub echo: String,
+}
+
+/// How the mock server answers a call.
+pub enum Reply {
+    Ok(Value),
+    Failed(i32),
+    /// Never answer, the call times out.
+    Silent,
+}
+
+type Responder = dyn Fn(&Call) -> Reply + Send + Sync;
+
+enum Command {
+    Frame(String),
+    /// Close with the given frame, or a plain close without one.
+    Close(Option<CloseFrame>),
+}
+
+pub struct MockServer {
+    url: String,
+    calls: Arc<Mutex<Vec<Call>>>,
+    connections: Arc<Mutex<Vec<mpsc::UnboundedSender<Command>>>>,
+    changed: Arc<tokio::sync::Notify>,
+}
+
+impl MockServer {
+    /// Start a server answering every call with [`canned`] data.
+    pub async fn start() -> Self {
+        Self::start_with(|call| canned(&call.action)).await
+    }
+
+    /// Start a server answering calls with `responder`.
+    pub async fn start_with<F>(responder: F) -> Self
+    where
+        F: Fn(&Call) -> Reply + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = Self {
+            url,
+            calls: Default::default(),
+            connections: Default::default(),
+            changed: Default::default(),
+        };
+
+        let responder: Arc<Responder> = Arc::new(responder);
+        let (calls, connections, changed) = (
+            server.calls.clone(),
+            server.connections.clone(),
+            server.changed.clone(),
+        );
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+                    continue;
+                };
+                let (commands, receiver) = mpsc::unbounded_channel();
+                let connection = {
+                    let mut connections = connections.lock().unwrap();
+                    connections.push(commands);
+                    connections.len() - 1
+                };
+                changed.notify_waiters();
+                tokio::spawn(serve_connection(
+                    ws,
+                    connection,
+                    receiver,
+                    responder.clone(),
+                    calls.clone(),
+                    changed.clone(),
+                ));
+            }
+        });
+        server
+    }
+
+    /// A connection config for the server that does not reconnect.
+    pub fn connection(&self) -> ReverseConnectionConfig {
+        self.connection_with(ReconnectionStrategy::None)
+    }
+
+    pub fn connection_with(&self, reconnection: ReconnectionStrategy) -> ReverseConnectionConfig {
+        ReverseConnectionConfig {
+            target: self.url.clone(),
+            auth: None,
+            reconnection,
+        }
+    }
+
+    /// A builder connecting to the server, with its persistent states in a fresh directory.
+    pub fn builder(&self) -> FlowBotBuilder {
+        FlowBotBuilder::new(self.connection()).with_persistent_state_dir(temp_dir())
+    }
+
+    /// The number of connections accepted so far.
+    pub fn connection_count(&self) -> usize {
+        self.connections.lock().unwrap().len()
+    }
+
+    /// Wait until `count` connections were accepted.
+    pub async fn wait_connections(&self, count: usize) {
+        self.wait_until(|server| server.connection_count() >= count)
+            .await;
+    }
+
+    /// Push an event to the latest connection.
+    pub fn send_event(&self, event: impl ToString) {
+        self.command(Command::Frame(event.to_string()));
+    }
+
+    /// Close the latest connection.
+    pub fn disconnect(&self) {
+        self.command(Command::Close(None));
+    }
+
+    /// Close the latest connection with `code` and `reason`, e.g. 1008 for a refused token.
+    pub fn disconnect_with(&self, code: u16, reason: &str) {
+        self.command(Command::Close(Some(CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.into(),
+        })));
+    }
+
+    fn command(&self, command: Command) {
+        let connections = self.connections.lock().unwrap();
+        let latest = connections.last().expect("the bot is not connected");
+        latest.send(command).ok();
+    }
+
+    /// Every call received so far.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The calls of `action` received so far.
+    pub fn calls_of(&self, action: &str) -> Vec<Call> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.action == action)
+            .collect()
+    }
+
+    /// Wait until at least `count` calls of `action` were received, returning them.
+    pub async fn wait_calls_of(&self, action: &str, count: usize) -> Vec<Call> {
+        self.wait_until(|server| server.calls_of(action).len() >= count)
+            .await;
+        self.calls_of(action)
+    }
+
+    /// Wait until no call was received for `quiet`.
+    pub async fn settle(&self, quiet: Duration) {
+        let mut count = self.calls.lock().unwrap().len();
+        loop {
+            tokio::time::sleep(quiet).await;
+            let now = self.calls.lock().unwrap().len();
+            if now == count {
+                return;
+            }
+            count = now;
+        }
+    }
+
+    /// Wait until `condition` holds, panicking after [`TIMEOUT`].
+    pub async fn wait_until(&self, condition: impl Fn(&Self) -> bool) {
+        let waited = tokio::time::timeout(TIMEOUT, async {
+            loop {
+                let changed = self.changed.notified();
+                if condition(self) {
+                    return;
+                }
+                // Polled as well, in case the change happened before waiting.
+                let _ = tokio::time::timeout(Duration::from_millis(20), changed).await;
+            }
+        })
+        .await;
+        assert!(
+            waited.is_ok(),
+            "timed out, received calls: {:#?}",
+            self.calls()
+        );
+    }
+
+    /// Push every event of the fixture `name` in `tests/fixtures`, one json event per line,
+    /// waiting for the bot to settle after each one.
+    pub async fn replay(&self, name: &str) {
+        for event in fixture_lines(name) {
+            self.send_event(event);
+            self.settle(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+async fn serve_connection(
+    ws: tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
+    connection: usize,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    responder: Arc<Responder>,
+    calls: Arc<Mutex<Vec<Call>>>,
+    changed: Arc<tokio::sync::Notify>,
+) {
+    let (mut write, mut read) = ws.split();
+    loop {
+        tokio::select! {
+            frame = read.next() => {
+                let Some(Ok(Message::Text(text))) = frame else {
+                    match frame {
+                        Some(Ok(_)) => continue,
+                        _ => return,
+                    }
+                };
+                let request: Value = serde_json::from_str(&text).unwrap();
+                let call = Call {
+                    action: request["action"].as_str().unwrap_or_default().to_string(),
+                    params: request["params"].clone(),
+                    connection,
+                    echo: request["echo"].as_str().unwrap_or_default().to_string(),
+                };
+                let reply = responder(&call);
+                calls.lock().unwrap().push(call.clone());
+                changed.notify_waiters();
+                let response = match reply {
+                    Reply::Ok(data) => {
+                        json!({"status": "ok", "retcode": 0, "data": data, "echo": call.echo})
+                    }
+                    Reply::Failed(retcode) => {
+                        json!({"status": "failed", "retcode": retcode, "data": null, "echo": call.echo})
+                    }
+                    Reply::Silent => continue,
+                };
+                if write.send(Message::Text(response.to_string().into())).await.is_err() {
+                    return;
+                }
+            }
+            command = commands.recv() => match command {
+                Some(Command::Frame(frame)) => {
+                    if write.send(Message::Text(frame.into())).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Command::Close(Some(frame))) => {
+                    let _ = write.send(Message::Close(Some(frame))).await;
+                    return;
+                }
+                Some(Command::Close(None)) | None => {
+                    let _ = write.close().await;
+                    return;
+                }
+            },
+        }
+    }
+}
+
+/// The default answer of the mock server, plausible data for the common actions and `null` otherwise.
+pub fn canned(action: &str) -> Reply {
+    Reply::Ok(match action {
+        "send_group_msg" | "send_private_msg" | "send_msg" => json!({"message_id": 1}),
+        "get_login_info" => json!({"user_id": SELF_ID, "nickname": "Bot"}),
+        "get_version_info" => {
+            json!({"app_name": "NapCat.Onebot", "app_version": "4.0", "protocol_version": "v11"})
+        }
+        "get_group_member_info" => json!({
+            "group_id": 1, "user_id": 3, "nickname": "Nick", "card": "", "sex": "unknown", "age": 0,
+            "join_time": 0, "last_sent_time": 0, "level": "1", "role": "member", "unfriendly": false,
+            "title_expire_time": 0, "card_changeable": false
+        }),
+        "get_group_list" | "get_friend_list" => json!([]),
+        _ => Value::Null,
+    })
+}
+
+/// Run `bot` in the background.
+pub fn spawn(bot: FlowBot) -> Arc<FlowBot> {
+    let bot = Arc::new(bot);
+    let running = bot.clone();
+    tokio::spawn(async move { running.run().await });
+    bot
+}
+
+/// A group message event from `user_id` in `group_id`, `message` is a plain text or an array of segments.
+pub fn group_message(group_id: i64, user_id: i64, role: &str, message: impl Into<Value>) -> Value {
+    json!({
+        "time": now(), "self_id": SELF_ID, "post_type": "message", "message_type": "group",
+        "sub_type": "normal", "message_id": 5, "group_id": group_id, "user_id": user_id,
+        "message": segments(message.into()), "raw_message": "", "font": 0,
+        "sender": {"user_id": user_id, "nickname": "Nick", "role": role}
+    })
+}
+
+pub fn private_message(user_id: i64, message: impl Into<Value>) -> Value {
+    json!({
+        "time": now(), "self_id": SELF_ID, "post_type": "message", "message_type": "private",
+        "sub_type": "friend", "message_id": 6, "user_id": user_id,
+        "message": segments(message.into()), "raw_message": "", "font": 0,
+        "sender": {"user_id": user_id, "nickname": "Nick"}
+    })
+}
+
+/// A notice event with the fields of `body`.
+pub fn notice(body: Value) -> Value {
+    let mut event = json!({"time": now(), "self_id": SELF_ID, "post_type": "notice"});
+    event
+        .as_object_mut()
+        .unwrap()
+        .extend(body.as_object().unwrap().clone());
+    event
+}
+
+fn segments(message: Value) -> Value {
+    match message {
+        Value::String(text) => json!([{"type": "text", "data": {"text": text}}]),
+        segments => segments,
+    }
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+pub fn fixture(name: &str) -> String {
+    std::fs::read_to_string(fixture_path(name)).unwrap()
+}
+
+/// The non-empty lines of the fixture `name`, with the events timestamped now so that age limits keep them.
+pub fn fixture_lines(name: &str) -> Vec<String> {
+    fixture(name)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut event: Value = serde_json::from_str(line).unwrap();
+            if event.get("time").is_some() {
+                event["time"] = now().into();
+            }
+            event.to_string()
+        })
+        .collect()
+}
+
+/// A fresh directory under the target directory, unique in the test run.
+pub fn temp_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!(
+        "{}-{}-{}",
+        std::process::id(),
+        std::thread::current()
+            .name()
+            .unwrap_or("test")
+            .replace("::", "-"),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
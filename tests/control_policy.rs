@@ -0,0 +1,118 @@
+mod common;
+
+use std::time::Duration;
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::message::Message,
+    message::message_ext::MessageExt,
+};
+
+async fn answer(ctx: &BotContext, text: &str) -> HandlerControl {
+    ctx.send_private_message(2, text, None).await?;
+    HandlerControl::Continue
+}
+
+async fn ping(ctx: BotContext, message: Message) -> HandlerControl {
+    match message.message.extract_plain_text().as_str() {
+        "/ping" => answer(&ctx, "pong").await,
+        _ => HandlerControl::Skip,
+    }
+}
+
+async fn help(ctx: BotContext, message: Message) -> HandlerControl {
+    match message.message.extract_plain_text().as_str() {
+        "/help" | "/ping" => answer(&ctx, "help").await,
+        _ => HandlerControl::Skip,
+    }
+}
+
+async fn unknown(ctx: BotContext, message: Message) -> HandlerControl {
+    match message.message.extract_plain_text().starts_with('/') {
+        true => answer(&ctx, "unknown").await,
+        false => HandlerControl::Skip,
+    }
+}
+
+/// Outside of the command group.
+async fn log(ctx: BotContext) -> HandlerControl {
+    answer(&ctx, "logged").await
+}
+
+fn commands(builder: FlowBotBuilder) -> FlowBotBuilder {
+    builder
+        .with_handler_group("commands", |builder| {
+            builder
+                .with_handler_filtered(ping, EventFilter::MESSAGE)
+                .with_handler_filtered(help, EventFilter::MESSAGE)
+                .with_handler_filtered(unknown, EventFilter::MESSAGE)
+        })
+        .with_handler_filtered(log, EventFilter::MESSAGE)
+}
+
+/// The answers to each of `texts`, sent one after the other.
+async fn answers(
+    builder: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder,
+    texts: &[&str],
+) -> Vec<Vec<String>> {
+    let server = MockServer::start().await;
+    let bot = common::spawn(builder(server.builder()).build());
+    bot.context().wait_for_connected().await;
+
+    let mut answers = Vec::new();
+    for text in texts {
+        let before = server.calls_of("send_private_msg").len();
+        server.send_event(common::private_message(3, *text));
+        server.wait_calls_of("send_private_msg", before + 1).await;
+        server.settle(Duration::from_millis(100)).await;
+        answers.push(
+            server.calls_of("send_private_msg")[before..]
+                .iter()
+                .map(|call| {
+                    call.params["message"][0]["data"]["text"]
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect(),
+        );
+    }
+    answers
+}
+
+const TEXTS: &[&str] = &["/ping", "/help", "/other", "hello"];
+
+#[tokio::test]
+async fn the_first_matching_command_blocks() {
+    let answers = answers(
+        |builder| {
+            commands(builder).with_control_policy(|_, control, meta| match (control, meta.group) {
+                (HandlerControl::Continue, Some("commands")) => HandlerControl::Block,
+                (control, _) => control,
+            })
+        },
+        TEXTS,
+    )
+    .await;
+    assert_eq!(
+        answers,
+        [vec!["pong"], vec!["help"], vec!["unknown"], vec!["logged"]]
+    );
+}
+
+#[tokio::test]
+async fn every_command_runs_without_a_policy() {
+    let answers = answers(commands, TEXTS).await;
+    assert_eq!(
+        answers,
+        [
+            vec!["pong", "help", "unknown", "logged"],
+            vec!["help", "unknown", "logged"],
+            vec!["unknown", "logged"],
+            vec!["logged"],
+        ]
+    );
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use common::{MockServer, Reply};
+use flow_bot::base::{
+    context::BotContext,
+    dead_letter::{DeadLetterFile, FailedCall},
+};
+use serde_json::json;
+
+const ACTION: &str = "do_something";
+
+/// A server failing [`ACTION`] with a retryable retcode while `failing` is set.
+async fn server(failing: Arc<AtomicBool>) -> MockServer {
+    MockServer::start_with(move |call| {
+        if call.action == ACTION && failing.load(Ordering::Relaxed) {
+            Reply::Failed(100)
+        } else {
+            common::canned(&call.action)
+        }
+    })
+    .await
+}
+
+async fn connect(server: &MockServer, file: &DeadLetterFile) -> BotContext {
+    let bot = common::spawn(server.builder().with_dead_letter_file(file.clone()).build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+fn dead_letter_file() -> DeadLetterFile {
+    DeadLetterFile::new(common::temp_dir().join("dead_letters.jsonl"))
+}
+
+fn failed_call(n: usize, retcode: i32) -> FailedCall {
+    FailedCall {
+        action: ACTION.to_string(),
+        params: json!({ "n": n }),
+        error: format!("retcode {}", retcode),
+        retcode: Some(retcode),
+        attempts: 1,
+        time: 0,
+    }
+}
+
+fn numbers<'a>(params: impl IntoIterator<Item = &'a serde_json::Value>) -> Vec<u64> {
+    let mut numbers = params
+        .into_iter()
+        .map(|params| params["n"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    numbers.sort();
+    numbers
+}
+
+#[tokio::test]
+async fn failed_calls_are_redriven_once() {
+    let failing = Arc::new(AtomicBool::new(true));
+    let server = server(failing.clone()).await;
+    let file = dead_letter_file();
+    let context = connect(&server, &file).await;
+
+    for n in 0..3 {
+        let resp = context
+            .call_action(ACTION, json!({ "n": n }))
+            .await
+            .unwrap();
+        assert_eq!(resp.retcode, 100);
+    }
+    // Kept in the file, but never sent again.
+    file.append(&failed_call(3, 1400)).await.unwrap();
+    assert_eq!(file.read().await.unwrap().len(), 4);
+
+    failing.store(false, Ordering::Relaxed);
+    assert_eq!(file.redrive(&context).await.unwrap(), 3);
+    let sent = server.calls_of(ACTION);
+    assert_eq!(
+        numbers(sent.iter().map(|call| &call.params)),
+        [0, 0, 1, 1, 2, 2]
+    );
+    let kept = file.read().await.unwrap();
+    assert_eq!(numbers(kept.iter().map(|call| &call.params)), [3]);
+
+    // Nothing left to redrive.
+    assert_eq!(file.redrive(&context).await.unwrap(), 0);
+    assert_eq!(server.calls_of(ACTION).len(), 6);
+}
+
+#[tokio::test]
+async fn calls_failing_again_are_appended_back_once() {
+    let failing = Arc::new(AtomicBool::new(true));
+    let server = server(failing).await;
+    let file = dead_letter_file();
+    let context = connect(&server, &file).await;
+
+    for n in 0..3 {
+        file.append(&failed_call(n, 100)).await.unwrap();
+    }
+    assert_eq!(file.redrive(&context).await.unwrap(), 0);
+
+    let calls = file.read().await.unwrap();
+    assert_eq!(numbers(calls.iter().map(|call| &call.params)), [0, 1, 2]);
+    assert!(calls.iter().all(|call| call.attempts == 2));
+    assert_eq!(server.calls_of(ACTION).len(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn calls_appended_while_redriving_are_kept() {
+    let server = server(Arc::new(AtomicBool::new(false))).await;
+    let file = dead_letter_file();
+    let context = connect(&server, &file).await;
+
+    for n in 0..50 {
+        file.append(&failed_call(n, 100)).await.unwrap();
+    }
+    let appending = (50..100)
+        .map(|n| {
+            let file = file.clone();
+            tokio::spawn(async move { file.append(&failed_call(n, 100)).await.unwrap() })
+        })
+        .collect::<Vec<_>>();
+    file.redrive(&context).await.unwrap();
+    for task in appending {
+        task.await.unwrap();
+    }
+
+    // Every call is either sent or still in the file, exactly once.
+    let sent = server.calls_of(ACTION);
+    let kept = file.read().await.unwrap();
+    let mut seen = HashMap::new();
+    for n in numbers(
+        sent.iter()
+            .map(|call| &call.params)
+            .chain(kept.iter().map(|call| &call.params)),
+    ) {
+        *seen.entry(n).or_insert(0) += 1;
+    }
+    assert_eq!(seen.len(), 100);
+    assert!(seen.values().all(|count| *count == 1), "{:?}", seen);
+    // The calls that were in the file before are sent.
+    assert!(
+        numbers(sent.iter().map(|call| &call.params)).starts_with(&(0..50).collect::<Vec<_>>())
+    );
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    base::{context::BotContext, debug::DebugState},
+    error::FlowError,
+};
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+
+/// Answered with [`Reply::Silent`], so its calls stay pending.
+const SILENT: &str = "silent_action";
+
+/// A state showing its content, a count shared with the test.
+struct Counter(Arc<AtomicU64>);
+
+impl DebugState for Counter {
+    fn debug_json(&self) -> Value {
+        json!({ "count": self.0.load(Ordering::Relaxed) })
+    }
+}
+
+/// A state without a debug view.
+struct Config;
+
+async fn connect(server: &MockServer) -> BotContext {
+    connect_counting(server, Default::default()).await
+}
+
+async fn connect_counting(server: &MockServer, count: Arc<AtomicU64>) -> BotContext {
+    count.store(1, Ordering::Relaxed);
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(Config)
+            .with_debug_state(Counter(count))
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    // Let the calls made on connecting be answered.
+    server.settle(Duration::from_millis(50)).await;
+    context
+}
+
+/// Start a call of [`SILENT`], returning it and its echo once the server received it.
+async fn call_silent(
+    server: &MockServer,
+    context: &BotContext,
+) -> (JoinHandle<Result<Value, FlowError>>, String) {
+    let sent = server.calls_of(SILENT).len();
+    let context = context.clone();
+    let call = tokio::spawn(async move {
+        context
+            .call_action(SILENT, json!({}))
+            .await
+            .map(|response| response.data)
+    });
+    let calls = server.wait_calls_of(SILENT, sent + 1).await;
+    (call, calls[sent].echo.clone())
+}
+
+#[tokio::test]
+async fn states_are_listed_by_type_name() {
+    let count = Arc::new(AtomicU64::new(0));
+    let server = MockServer::start().await;
+    let context = connect_counting(&server, count.clone()).await;
+
+    let snapshot = context.debug_snapshot();
+    let names = snapshot
+        .states
+        .iter()
+        .map(|state| state.type_name)
+        .collect::<Vec<_>>();
+    let mut sorted = names.clone();
+    sorted.sort_unstable();
+    assert_eq!(names, sorted);
+
+    let state = |name: &str| {
+        snapshot
+            .states
+            .iter()
+            .find(|state| state.type_name == name)
+            .unwrap_or_else(|| panic!("{} in {:?}", name, names))
+    };
+    assert_eq!(state(std::any::type_name::<Config>()).debug, None);
+    assert_eq!(
+        state(std::any::type_name::<Counter>()).debug,
+        Some(json!({"count": 1}))
+    );
+    // States registered by the bot itself are listed too.
+    assert!(
+        names.contains(&"flow_bot::base::chance::RngState"),
+        "{:?}",
+        names
+    );
+
+    // Debug views are rendered when the snapshot is taken.
+    count.store(2, Ordering::Relaxed);
+    let snapshot = context.debug_snapshot();
+    let counter = snapshot
+        .states
+        .iter()
+        .find(|state| state.type_name == std::any::type_name::<Counter>())
+        .unwrap();
+    assert_eq!(counter.debug, Some(json!({"count": 2})));
+}
+
+#[tokio::test]
+async fn pending_requests_are_listed_oldest_first() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        SILENT => Reply::Silent,
+        action => common::canned(action),
+    })
+    .await;
+    let context = connect(&server).await;
+    assert!(context.debug_snapshot().pending_requests.is_empty());
+
+    let (first, first_echo) = call_silent(&server, &context).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let (second, second_echo) = call_silent(&server, &context).await;
+
+    let snapshot = context.debug_snapshot();
+    let echoes = snapshot
+        .pending_requests
+        .iter()
+        .map(|request| request.echo.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(echoes, [first_echo.as_str(), second_echo.as_str()]);
+    let [oldest, newest] = &snapshot.pending_requests[..] else {
+        unreachable!()
+    };
+    assert!(oldest.age >= Duration::from_millis(50), "{:?}", oldest);
+    assert!(newest.age < oldest.age, "{:?}", snapshot.pending_requests);
+    assert_eq!(snapshot.health.pending_requests, 2);
+
+    first.abort();
+    second.abort();
+}
+
+#[tokio::test]
+async fn snapshots_serialize_for_reports() {
+    let server = MockServer::start().await;
+    let context = connect(&server).await;
+    context.ignore_group(3);
+    context.ignore_group(1);
+
+    let snapshot = serde_json::to_value(context.debug_snapshot()).unwrap();
+    assert_eq!(snapshot["connection_generation"], 1);
+    assert_eq!(snapshot["shutting_down"], false);
+    assert_eq!(snapshot["ignored_groups"], json!([1, 3]));
+    assert_eq!(snapshot["pending_requests"], json!([]));
+    assert!(
+        snapshot["health"]["in_flight_handlers"].is_u64(),
+        "{}",
+        snapshot
+    );
+
+    let states = snapshot["states"].as_array().unwrap();
+    let state = |name: &str| {
+        states
+            .iter()
+            .find(|state| state["type_name"] == name)
+            .unwrap_or_else(|| panic!("{} in {}", name, snapshot))
+    };
+    // States without a debug view have no `debug` field.
+    assert_eq!(
+        state(std::any::type_name::<Config>()),
+        &json!({"type_name": std::any::type_name::<Config>()})
+    );
+    assert_eq!(
+        state(std::any::type_name::<Counter>()),
+        &json!({"type_name": std::any::type_name::<Counter>(), "debug": {"count": 1}})
+    );
+}
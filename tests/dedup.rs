@@ -0,0 +1,108 @@
+mod common;
+
+use std::time::Duration;
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::BotEvent,
+};
+use serde_json::{Value, json};
+
+/// Answers every message and notice with its post type.
+async fn answer(ctx: BotContext, event: BotEvent) -> HandlerControl {
+    ctx.send_private_message(2, event.event.get_type(), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+async fn connect(server: &MockServer, window: Duration, capacity: usize) -> BotContext {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_event_dedup(window, capacity)
+            .with_handler_filtered(answer, EventFilter::MESSAGE | EventFilter::NOTICE)
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+fn message(id: i64) -> Value {
+    let mut message = common::private_message(2, "hello");
+    message["message_id"] = id.into();
+    message
+}
+
+async fn answered(server: &MockServer) -> usize {
+    server.settle(Duration::from_millis(100)).await;
+    server.calls_of("send_private_msg").len()
+}
+
+#[tokio::test]
+async fn duplicates_within_the_window_are_dropped() {
+    let server = MockServer::start().await;
+    let context = connect(&server, Duration::from_secs(60), 100).await;
+
+    for _ in 0..3 {
+        server.send_event(message(1));
+    }
+    server.send_event(message(2));
+    // Notices have no id and are compared whole.
+    let notice = common::notice(json!({"notice_type": "friend_add", "user_id": 2})).to_string();
+    server.send_event(&notice);
+    server.send_event(&notice);
+    server.send_event(common::notice(
+        json!({"notice_type": "friend_add", "user_id": 3}),
+    ));
+
+    assert_eq!(answered(&server).await, 4);
+    assert_eq!(context.health().duplicate_events, 3);
+}
+
+#[tokio::test]
+async fn events_pass_again_once_the_window_passed() {
+    let server = MockServer::start().await;
+    let context = connect(&server, Duration::from_millis(200), 100).await;
+
+    server.send_event(message(1));
+    server.wait_calls_of("send_private_msg", 1).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    server.send_event(message(1));
+
+    assert_eq!(answered(&server).await, 2);
+    assert_eq!(context.health().duplicate_events, 0);
+}
+
+#[tokio::test]
+async fn the_oldest_events_are_forgotten_first() {
+    let server = MockServer::start().await;
+    let context = connect(&server, Duration::from_secs(60), 2).await;
+
+    for id in [1, 2, 3, 1, 3] {
+        server.send_event(message(id));
+    }
+
+    // 1 was forgotten when 3 arrived, 3 is still remembered.
+    assert_eq!(answered(&server).await, 4);
+    assert_eq!(context.health().duplicate_events, 1);
+}
+
+#[tokio::test]
+async fn events_are_not_deduplicated_by_default() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(answer, EventFilter::MESSAGE | EventFilter::NOTICE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(message(1));
+    server.send_event(message(1));
+
+    assert_eq!(answered(&server).await, 2);
+}
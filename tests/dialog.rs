@@ -0,0 +1,190 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::message::Message,
+    extensions::dialog::{Dialog, DialogKey, Transition},
+    message::message_ext::MessageExt,
+};
+use serde_json::Value;
+
+type Counter = Dialog<u32, u32>;
+
+fn text(message: &Message) -> String {
+    message.message.extract_plain_text()
+}
+
+/// Counts the messages of a dialog started with "/count", "stay" is not counted and "done" finishes it.
+fn counter(ttl: Duration) -> Counter {
+    Dialog::new(|count: u32, _, message: Message| async move {
+        match text(&message).as_str() {
+            "stay" => Transition::Stay,
+            "done" => Transition::Finish(count),
+            _ => Transition::Move(count + 1),
+        }
+    })
+    .trigger(|message| (text(message) == "/count").then_some(0))
+    .on_finish(|count, ctx: BotContext, message: Message| async move {
+        ctx.reply(&message, format!("counted {}", count))
+            .await
+            .unwrap();
+    })
+    .ttl(ttl)
+}
+
+/// Tells which messages got past the dialog.
+async fn later(ctx: BotContext, message: Message) -> HandlerControl {
+    ctx.send_private_message(99, format!("later {}", text(&message)), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+async fn connect(server: &MockServer, dialog: &Arc<Counter>) -> BotContext {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(dialog.clone())
+            .with_handler_filtered(later, EventFilter::MESSAGE)
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+/// The text segments of every message sent, in order.
+fn sent(server: &MockServer) -> Vec<String> {
+    server
+        .calls()
+        .into_iter()
+        .filter(|call| call.action.starts_with("send_"))
+        .map(|call| {
+            call.params["message"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|segment| match segment["type"].as_str().unwrap() {
+                    "text" => segment["data"]["text"].as_str().unwrap().to_string(),
+                    other => format!(
+                        "[{}:{}]",
+                        other,
+                        segment["data"]
+                            .as_object()
+                            .unwrap()
+                            .values()
+                            .next()
+                            .unwrap_or(&Value::Null)
+                    ),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+async fn send(server: &MockServer, event: Value) {
+    server.send_event(event);
+    server.settle(Duration::from_millis(50)).await;
+}
+
+#[tokio::test]
+async fn transitions_are_driven_by_messages() {
+    let server = MockServer::start().await;
+    let dialog = Arc::new(counter(Duration::from_secs(60)));
+    let _context = connect(&server, &dialog).await;
+    let key = DialogKey {
+        user_id: 2,
+        group_id: None,
+    };
+
+    send(&server, common::private_message(2, "hi")).await;
+    send(&server, common::private_message(2, "/count")).await;
+    assert!(dialog.is_active(key));
+    for text in ["a", "stay", "b", "stay", "c", "done"] {
+        send(&server, common::private_message(2, text)).await;
+    }
+    assert!(!dialog.is_active(key));
+    send(&server, common::private_message(2, "after")).await;
+
+    assert_eq!(
+        sent(&server),
+        [
+            "later hi",
+            // The trigger is the first step, so it is counted too.
+            "[reply:\"6\"]counted 4",
+            "later after",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn dialogs_are_kept_per_user_and_chat() {
+    let server = MockServer::start().await;
+    let dialog = Arc::new(counter(Duration::from_secs(60)));
+    let _context = connect(&server, &dialog).await;
+
+    send(&server, common::group_message(1, 2, "member", "/count")).await;
+    // Another user, the same user in another group and in private are not in the dialog.
+    send(&server, common::group_message(1, 3, "member", "done")).await;
+    send(&server, common::group_message(5, 2, "member", "done")).await;
+    send(&server, common::private_message(2, "done")).await;
+    send(&server, common::group_message(1, 2, "member", "done")).await;
+
+    assert_eq!(
+        sent(&server),
+        [
+            "later done",
+            "later done",
+            "later done",
+            "[reply:\"5\"]counted 1",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn expired_dialogs_tell_the_user() {
+    let ttl = Duration::from_millis(400);
+    let server = MockServer::start().await;
+    let dialog = Arc::new(counter(ttl).expired_notice("Timed out"));
+    let _context = connect(&server, &dialog).await;
+
+    send(&server, common::private_message(2, "/count")).await;
+    send(&server, common::group_message(1, 3, "member", "/count")).await;
+    // Every step restarts the timer.
+    tokio::time::sleep(ttl / 2).await;
+    send(&server, common::private_message(2, "a")).await;
+
+    server.wait_calls_of("send_group_msg", 1).await;
+    assert!(server.calls_of("send_private_msg").is_empty());
+    server.wait_calls_of("send_private_msg", 1).await;
+    // Expired dialogs ignore later answers.
+    send(&server, common::private_message(2, "done")).await;
+
+    assert_eq!(
+        sent(&server),
+        ["[at:\"3\"]Timed out", "Timed out", "later done",]
+    );
+}
+
+#[tokio::test]
+async fn dialogs_are_started_and_cancelled_from_outside() {
+    let server = MockServer::start().await;
+    let dialog = Arc::new(counter(Duration::from_secs(60)));
+    let context = connect(&server, &dialog).await;
+    let key = DialogKey {
+        user_id: 2,
+        group_id: None,
+    };
+
+    dialog.start(&context, key, 10);
+    send(&server, common::private_message(2, "done")).await;
+    dialog.start(&context, key, 20);
+    assert!(dialog.cancel(key));
+    assert!(!dialog.cancel(key));
+    send(&server, common::private_message(2, "done")).await;
+
+    assert_eq!(sent(&server), ["[reply:\"6\"]counted 10", "later done"]);
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{api_ext::ApiExt, digest::ParamRedaction, retcode::RetCode},
+    error::FlowError,
+};
+use serde_json::json;
+
+#[test]
+fn default_fields_are_redacted_at_any_depth() {
+    let redaction = ParamRedaction::default();
+    for field in ["access_token", "token", "password", "cookies", "csrf_token"] {
+        assert!(redaction.is_redacted(field), "{}", field);
+    }
+    assert!(!redaction.is_redacted("user_id"));
+    // Matched by the whole name.
+    assert!(!redaction.is_redacted("token_type"));
+
+    let params = json!({
+        "token": "abc",
+        "user": {"id": 1, "password": "hunter2"},
+        "list": [{"cookies": "a=b"}, 2]
+    });
+    assert_eq!(
+        redaction.digest(&params),
+        r#"list=[{"cookies":"***"},2], token=***, user={"id":1,"password":"***"}"#
+    );
+}
+
+#[test]
+fn extra_fields_are_redacted() {
+    let redaction = ParamRedaction::default().with_field("domain");
+    assert!(redaction.is_redacted("domain"));
+    assert!(redaction.is_redacted("token"));
+    assert_eq!(
+        redaction.digest(&json!({"domain": "qq.com", "other": "qq.com"})),
+        r#"domain=***, other="qq.com""#
+    );
+}
+
+#[test]
+fn digests_are_cut() {
+    let redaction = ParamRedaction::default();
+    assert_eq!(redaction.digest(&json!(null)), "");
+    assert_eq!(redaction.digest(&json!([1, 2])), "[1,2]");
+    assert_eq!(redaction.digest(&json!("text")), r#""text""#);
+
+    // Values at 48 characters, counting characters and not bytes.
+    let long = "字".repeat(60);
+    let digest = redaction.digest(&json!({ "message": long }));
+    let value = digest.strip_prefix("message=").unwrap();
+    assert_eq!(value.chars().count(), 49);
+    assert!(value.starts_with("\"字字"));
+    assert!(value.ends_with('…'));
+
+    // The whole digest at 160.
+    let params = (0..20)
+        .map(|i| (format!("field_{:02}", i), json!(i)))
+        .collect::<serde_json::Map<_, _>>();
+    let digest = redaction.digest(&params.into());
+    assert_eq!(digest.chars().count(), 161);
+    assert!(digest.starts_with("field_00=0, field_01=1, "));
+    assert!(digest.ends_with('…'));
+}
+
+#[test]
+fn failures_read_as_calls() {
+    let call = |source: FlowError| FlowError::ApiCall {
+        action: "get_group_member_info".to_string(),
+        source: Box::new(source),
+        params_digest: "group_id=123, user_id=456".to_string(),
+    };
+    let cases = [
+        (FlowError::Timeout(30000), "timed out after 30s"),
+        (FlowError::Timeout(1500), "timed out after 1500ms"),
+        (FlowError::NoConnection, "failed without a connection"),
+        (FlowError::NoResponse, "got no response"),
+        (
+            FlowError::ActionDenied("get_group_member_info".to_string()),
+            "is denied by the action policy",
+        ),
+        (
+            FlowError::Api {
+                retcode: RetCode::from(100),
+                message: Some("no such member".to_string()),
+            },
+            "failed with retcode 100: no such member",
+        ),
+        (
+            FlowError::Api {
+                retcode: RetCode::from(100),
+                message: Some(String::new()),
+            },
+            "failed with retcode 100",
+        ),
+    ];
+    for (source, described) in cases {
+        let error = call(source);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "get_group_member_info(group_id=123, user_id=456) {}",
+                described
+            )
+        );
+    }
+}
+
+#[tokio::test]
+async fn failed_calls_name_their_redacted_params() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_cookies" | "get_group_member_info" => Reply::Failed(100),
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(server.builder().with_redacted_params(["domain"]).build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let error = context
+        .get_cookies(Some("qun.qq.com".to_string()))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "get_cookies(domain=***) failed with retcode 100"
+    );
+    let FlowError::ApiCall { action, .. } = &error else {
+        panic!("{:?}", error);
+    };
+    assert_eq!(action, "get_cookies");
+    assert!(matches!(
+        error.root_cause(),
+        FlowError::Api { retcode, .. } if i32::from(*retcode) == 100
+    ));
+
+    let error = context
+        .get_group_member_info(5, 1404, Some(true))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "get_group_member_info(group_id=5, no_cache=true, user_id=1404) failed with retcode 100"
+    );
+}
@@ -0,0 +1,221 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{RecordFormat, api_ext::ApiExt},
+    base::{context::BotContext, extract::Voice, filter::EventFilter, handler::HandlerControl},
+    error::FlowError,
+    message::{
+        download::{AudioFormat, DownloadLimits},
+        segments::{ImageSegment, RecordSegment},
+    },
+};
+use serde_json::json;
+use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+/// The start of a 3x2 png, enough to sniff its type and read its dimensions.
+fn png() -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&3u32.to_be_bytes());
+    png.extend_from_slice(&2u32.to_be_bytes());
+    png.extend_from_slice(&[8, 6, 0, 0, 0]);
+    png
+}
+
+/// A server answering `get_image` with the local file `path`, as implementations running on the same machine do.
+async fn serving(path: String) -> MockServer {
+    MockServer::start_with(move |call| match call.action.as_str() {
+        "get_image" => Reply::Ok(json!({ "file": path })),
+        action => common::canned(action),
+    })
+    .await
+}
+
+fn image() -> ImageSegment {
+    ImageSegment {
+        file: "abc.image".to_string(),
+        url: None,
+    }
+}
+
+fn limits(max_size: u64) -> DownloadLimits {
+    DownloadLimits {
+        max_size,
+        timeout: Duration::from_secs(5),
+    }
+}
+
+#[tokio::test]
+async fn local_images_are_read() {
+    let path = common::temp_dir().join("a.png");
+    std::fs::write(&path, png()).unwrap();
+    let server = serving(format!("file://{}", path.display())).await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let fetched = image().fetch_with(&context, limits(1024)).await.unwrap();
+    assert_eq!(fetched.bytes, png());
+    assert_eq!(fetched.content_type.as_deref(), Some("image/png"));
+    assert_eq!(fetched.dimensions, Some((3, 2)));
+
+    // The size limit is checked before reading.
+    let error = image()
+        .fetch_with(&context, limits(png().len() as u64 - 1))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, FlowError::TooLarge(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn missing_local_images_fail() {
+    let path = common::temp_dir().join("missing.png");
+    let server = serving(path.display().to_string()).await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let error = image()
+        .fetch_with(&context, limits(1024))
+        .await
+        .unwrap_err();
+    let FlowError::FetchFailed(message) = &error else {
+        panic!("{:?}", error);
+    };
+    assert!(
+        message.starts_with(&path.display().to_string()),
+        "{}",
+        message
+    );
+}
+
+const SILK: &[u8] = b"\x02#!SILK_V3 voice";
+
+/// A server answering `get_record` with the local file `path`, or failing without one.
+async fn converting(path: Option<String>) -> MockServer {
+    MockServer::start_with(move |call| match (call.action.as_str(), &path) {
+        ("get_record", Some(path)) => Reply::Ok(json!({ "file": path })),
+        ("get_record", None) => Reply::Failed(100),
+        (action, _) => common::canned(action),
+    })
+    .await
+}
+
+/// Serve `body` over http to every request, returning the url.
+async fn serve(body: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/voice.silk", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes()).await;
+            let _ = stream.write_all(body).await;
+        }
+    });
+    url
+}
+
+fn record(url: Option<String>) -> RecordSegment {
+    RecordSegment {
+        file: "abc.silk".to_string(),
+        url,
+    }
+}
+
+#[tokio::test]
+async fn records_are_converted_by_the_implementation() {
+    let path = common::temp_dir().join("voice.mp3");
+    std::fs::write(&path, b"ID3 voice").unwrap();
+    let server = converting(Some(path.display().to_string())).await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let bytes = record(None)
+        .fetch(&context, RecordFormat::Mp3)
+        .await
+        .unwrap();
+    assert_eq!(bytes, b"ID3 voice");
+    let calls = server.calls_of("get_record");
+    assert_eq!(
+        calls[0].params,
+        json!({"file": "abc.silk", "out_format": "mp3"})
+    );
+}
+
+#[tokio::test]
+async fn records_fall_back_to_the_transcoder() {
+    let server = converting(None).await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_audio_transcoder(|bytes, from, to| async move {
+                assert_eq!(bytes, SILK);
+                Ok(format!("{:?} as {:?}", from, to).into_bytes())
+            })
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let url = serve(SILK).await;
+    let bytes = record(Some(url))
+        .fetch(&context, RecordFormat::Ogg)
+        .await
+        .unwrap();
+    assert_eq!(bytes, b"Silk as Ogg");
+    assert_eq!(AudioFormat::detect(SILK), AudioFormat::Silk);
+
+    // Without a url there is nothing to transcode.
+    record(None)
+        .fetch(&context, RecordFormat::Ogg)
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn records_fail_without_a_transcoder() {
+    let server = converting(None).await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let url = serve(SILK).await;
+    record(Some(url))
+        .fetch(&context, RecordFormat::Mp3)
+        .await
+        .unwrap_err();
+}
+
+async fn voice(ctx: BotContext, Voice(record): Voice) -> HandlerControl {
+    ctx.send_private_message(2, record.file, None).await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn only_voice_messages_are_extracted() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(voice, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(3, "not a voice"));
+    server.send_event(common::private_message(
+        3,
+        json!([{"type": "record", "data": {"file": "abc.silk", "url": "https://a.com/abc"}}]),
+    ));
+
+    let calls = server.wait_calls_of("send_private_msg", 1).await;
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(server.calls_of("send_private_msg").len(), 1);
+    assert_eq!(calls[0].params["message"][0]["data"]["text"], "abc.silk");
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{DownloadHeaders, api_ext::ApiExt},
+    base::{context::BotContext, extract::State, handler::HandlerControl},
+    event::notice::GroupUpload,
+    message::media::MediaSource,
+};
+use serde_json::{Value, json};
+
+#[test]
+fn headers_are_key_value_lines() {
+    let map = HashMap::from([
+        ("User-Agent".to_string(), "flow-bot".to_string()),
+        ("Accept".to_string(), "*/*".to_string()),
+    ]);
+    // Sorted, so that the same map is always sent the same.
+    assert_eq!(
+        serde_json::to_value(DownloadHeaders::from(map)).unwrap(),
+        json!(["Accept=*/*", "User-Agent=flow-bot"])
+    );
+    let lines = vec!["Referer=https://example.com".to_string()];
+    assert_eq!(
+        serde_json::to_value(DownloadHeaders::from(lines)).unwrap(),
+        json!(["Referer=https://example.com"])
+    );
+    assert_eq!(
+        serde_json::to_value(DownloadHeaders::default()).unwrap(),
+        json!([])
+    );
+}
+
+#[tokio::test]
+async fn downloaded_files_are_uploaded_from_the_cache() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "download_file" => Reply::Ok(json!({"file": "/cache/a.txt"})),
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let headers = HashMap::from([("Cookie".to_string(), "a=b".to_string())]);
+    let downloaded = context
+        .download_file(
+            "https://example.com/a.txt".to_string(),
+            Some(4),
+            Some(headers.into()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(downloaded.file, "/cache/a.txt");
+    assert_eq!(downloaded.url, None);
+    context
+        .upload_private_file(2, MediaSource::Raw(downloaded.file), "a.txt".to_string())
+        .await
+        .unwrap();
+    context
+        .upload_private_file(
+            2,
+            MediaSource::Path(PathBuf::from("/data/b.txt")),
+            "b.txt".to_string(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.calls_of("download_file")[0].params,
+        json!({"url": "https://example.com/a.txt", "thread_count": 4, "headers": ["Cookie=a=b"]})
+    );
+    let uploads = server
+        .calls_of("upload_private_file")
+        .into_iter()
+        .map(|call| call.params)
+        .collect::<Vec<Value>>();
+    assert_eq!(
+        uploads,
+        [
+            json!({"user_id": 2, "file": "/cache/a.txt", "name": "a.txt"}),
+            json!({"user_id": 2, "file": "file:///data/b.txt", "name": "b.txt"}),
+        ]
+    );
+}
+
+/// The outcome of every mirrored upload.
+type Mirrored = Arc<Mutex<Vec<Result<(), String>>>>;
+
+async fn mirror(ctx: BotContext, upload: GroupUpload, mirrored: State<Mirrored>) -> HandlerControl {
+    let result = upload.mirror_to(&ctx, 2).await;
+    mirrored
+        .lock()
+        .unwrap()
+        .push(result.map_err(|e| e.to_string()));
+    HandlerControl::Continue
+}
+
+fn uploaded(file_id: &str) -> Value {
+    common::notice(json!({
+        "notice_type": "group_upload", "group_id": 1, "user_id": 3,
+        "file": {"id": file_id, "name": "a.txt", "size": 4, "busid": 102},
+    }))
+}
+
+#[tokio::test]
+async fn uploads_are_mirrored_through_the_cache() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_group_file_url" if call.params["file_id"] == "missing" => Reply::Failed(100),
+        "get_group_file_url" => Reply::Ok(json!({"url": "https://example.com/a.txt"})),
+        "download_file" => Reply::Ok(json!({"file": "/cache/a.txt"})),
+        action => common::canned(action),
+    })
+    .await;
+    let mirrored = Mirrored::default();
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(mirrored.clone())
+            .with_handler(mirror)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(uploaded("abc"));
+    server
+        .wait_until(|_| mirrored.lock().unwrap().len() == 1)
+        .await;
+    server.send_event(uploaded("missing"));
+    server
+        .wait_until(|_| mirrored.lock().unwrap().len() == 2)
+        .await;
+
+    let results = mirrored.lock().unwrap().clone();
+    assert_eq!(results[0], Ok(()));
+    assert!(results[1].is_err());
+    let urls = server
+        .calls_of("get_group_file_url")
+        .into_iter()
+        .map(|call| call.params)
+        .collect::<Vec<Value>>();
+    assert_eq!(
+        urls,
+        [
+            json!({"group_id": 1, "file_id": "abc", "busid": 102}),
+            json!({"group_id": 1, "file_id": "missing", "busid": 102}),
+        ]
+    );
+    // Nothing is downloaded without a url.
+    let downloads = server.calls_of("download_file");
+    assert_eq!(downloads.len(), 1);
+    assert_eq!(downloads[0].params["url"], "https://example.com/a.txt");
+    let uploads = server.calls_of("upload_group_file");
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(
+        uploads[0].params,
+        json!({"group_id": 2, "file": "/cache/a.txt", "name": "a.txt"})
+    );
+}
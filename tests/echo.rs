@@ -0,0 +1,128 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        connect::ReconnectionStrategy, context::BotContext, filter::EventFilter,
+        handler::HandlerControl,
+    },
+    error::FlowError,
+    event::message::Message,
+};
+use serde_json::{Value, json};
+use tokio::task::JoinHandle;
+
+/// Answered with [`Reply::Silent`], the tests answer it themselves.
+const SILENT: &str = "silent_action";
+
+async fn server() -> MockServer {
+    MockServer::start_with(|call| match call.action.as_str() {
+        SILENT => Reply::Silent,
+        action => common::canned(action),
+    })
+    .await
+}
+
+/// Answers every message, to tell that it was dispatched.
+async fn answer(ctx: BotContext, _: Message) -> HandlerControl {
+    ctx.send_private_message(2, "answer", None).await?;
+    HandlerControl::Continue
+}
+
+fn response(echo: &str, data: Value) -> String {
+    json!({"status": "ok", "retcode": 0, "data": data, "echo": echo}).to_string()
+}
+
+/// Start a call of [`SILENT`], returning it and its echo once the server received it.
+async fn call_silent(
+    server: &MockServer,
+    context: &BotContext,
+) -> (JoinHandle<Result<Value, FlowError>>, String) {
+    let sent = server.calls_of(SILENT).len();
+    let context = context.clone();
+    let call = tokio::spawn(async move {
+        context
+            .call_action(SILENT, json!({}))
+            .await
+            .map(|response| response.data)
+    });
+    let calls = server.wait_calls_of(SILENT, sent + 1).await;
+    (call, calls[sent].echo.clone())
+}
+
+#[tokio::test]
+async fn pushed_events_with_an_echo_are_events() {
+    let server = server().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(answer, EventFilter::MESSAGE)
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    let (call, echo) = call_silent(&server, &context).await;
+
+    let mut event = common::private_message(2, "hello");
+    event["echo"] = echo.clone().into();
+    server.send_event(event);
+
+    server.wait_calls_of("send_private_msg", 1).await;
+    server.settle(Duration::from_millis(100)).await;
+    // The event did not resolve the call with the same echo.
+    assert!(!call.is_finished());
+    server.send_event(response(&echo, json!("response")));
+    assert_eq!(call.await.unwrap().unwrap(), "response");
+}
+
+#[tokio::test]
+async fn responses_from_a_previous_connection_are_ignored() {
+    let server = server().await;
+    let bot = common::spawn(
+        FlowBotBuilder::new(server.connection_with(ReconnectionStrategy::Infinite {
+            initial_delay_ms: 100,
+            max_delay_ms: 100,
+        }))
+        .with_persistent_state_dir(common::temp_dir())
+        .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    let (stale_call, stale_echo) = call_silent(&server, &context).await;
+
+    server.disconnect();
+    // Pending calls fail with the connection.
+    tokio::time::timeout(common::TIMEOUT, stale_call)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap_err();
+    server.wait_connections(2).await;
+    tokio::time::timeout(common::TIMEOUT, async {
+        while context.connection_generation() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    let (call, echo) = call_silent(&server, &context).await;
+    assert_eq!(server.calls_of(SILENT)[1].connection, 1);
+    let (stale_prefix, _) = stale_echo.split_once(':').unwrap();
+    let (prefix, counter) = echo.split_once(':').unwrap();
+    assert_ne!(prefix, stale_prefix);
+
+    // The same counter with the prefix of the previous connection.
+    server.send_event(response(
+        &format!("{}:{}", stale_prefix, counter),
+        json!("stale"),
+    ));
+    server.settle(Duration::from_millis(100)).await;
+    assert!(!call.is_finished());
+    server.send_event(response(&echo, json!("fresh")));
+    assert_eq!(call.await.unwrap().unwrap(), "fresh");
+}
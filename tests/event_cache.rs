@@ -0,0 +1,113 @@
+mod common;
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        extract::{self, RepliedMessage},
+        filter::EventFilter,
+        handler::HandlerControl,
+    },
+    event::extensions::Extensions,
+};
+use serde_json::{Value, json};
+
+/// A group message replying to `id`, the [`server`] serves message 5 and fails to fetch others.
+fn replying_to(id: &str) -> Value {
+    common::group_message(
+        1,
+        3,
+        "member",
+        json!([
+            {"type": "reply", "data": {"id": id}},
+            {"type": "text", "data": {"text": "what?"}},
+        ]),
+    )
+}
+
+async fn server() -> MockServer {
+    MockServer::start_with(|call| match call.action.as_str() {
+        "get_msg" if call.params["message_id"] == 5 => {
+            Reply::Ok(serde_json::from_str(&common::fixture("get_msg/napcat.json")).unwrap())
+        }
+        "get_msg" => Reply::Failed(1200),
+        action => common::canned(action),
+    })
+    .await
+}
+
+async fn quote(ctx: BotContext, replied: RepliedMessage) -> HandlerControl {
+    ctx.send_private_message(2, format!("quote {}", replied.message_id), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+/// Takes the replied message twice, through two extractors.
+async fn segments(
+    ctx: BotContext,
+    replied: RepliedMessage,
+    extract::Reply(_): extract::Reply,
+) -> HandlerControl {
+    ctx.send_private_message(2, format!("segments {}", replied.message_id), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn replied_messages_are_fetched_once_per_event() {
+    let server = server().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(quote, EventFilter::MESSAGE)
+            .with_handler_filtered(segments, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(replying_to("5"));
+    server.wait_calls_of("send_private_msg", 2).await;
+    assert_eq!(server.calls_of("get_msg").len(), 1);
+
+    // The next event fetches again.
+    server.send_event(replying_to("5"));
+    server.wait_calls_of("send_private_msg", 4).await;
+    assert_eq!(server.calls_of("get_msg").len(), 2);
+
+    // Failures are cached too, every handler is skipped after a single call.
+    server.send_event(replying_to("9"));
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(server.calls_of("get_msg").len(), 3);
+    assert_eq!(server.calls_of("send_private_msg").len(), 4);
+}
+
+#[tokio::test]
+async fn concurrent_callers_share_one_init() {
+    let extensions = Extensions::default();
+    let inits = AtomicUsize::new(0);
+    let init = || async {
+        inits.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        42u64
+    };
+
+    let values =
+        futures::future::join_all((0..8).map(|_| extensions.get_or_insert_async(init))).await;
+    assert!(values.iter().all(|value| **value == 42));
+    assert_eq!(inits.load(Ordering::SeqCst), 1);
+    assert_eq!(extensions.get::<u64>().as_deref(), Some(&42));
+
+    // Other types have their own slot.
+    assert!(extensions.get::<u32>().is_none());
+    extensions.insert(7u32);
+    assert_eq!(extensions.get::<u32>().as_deref(), Some(&7));
+    // Inserting replaces.
+    extensions.insert(43u64);
+    assert_eq!(extensions.get::<u64>().as_deref(), Some(&43));
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    base::{
+        event_context::EventContext, extract::State, filter::EventFilter, handler::HandlerControl,
+    },
+    error::FlowError,
+    message::message_ext::MessageExt,
+};
+use serde_json::{Value, json};
+
+/// The outcome of every action.
+#[derive(Default)]
+struct Outcomes(Mutex<Vec<String>>);
+
+impl Outcomes {
+    fn push(&self, action: &str, result: Result<(), FlowError>) {
+        let outcome = match result {
+            Ok(()) => format!("{} ok", action),
+            Err(FlowError::NotInGroup) => format!("{} not in group", action),
+            Err(e) => format!("{} failed: {}", action, e),
+        };
+        self.0.lock().unwrap().push(outcome);
+    }
+}
+
+/// Does what the message says.
+async fn act(ctx: EventContext, outcomes: State<Arc<Outcomes>>) -> HandlerControl {
+    let action = ctx.message().message.extract_plain_text();
+    let result = match action.as_str() {
+        "reply" => ctx.reply("ok").await.map(|_| ()),
+        "reply_at" => ctx.reply_at("ok").await.map(|_| ()),
+        "recall" => ctx.recall().await,
+        "ban" => ctx.ban_sender(Duration::from_secs(60)).await,
+        "kick" => ctx.kick_sender().await,
+        _ => return HandlerControl::Skip,
+    };
+    outcomes.push(&action, result);
+    HandlerControl::Continue
+}
+
+async fn connect(server: &MockServer) -> Arc<Outcomes> {
+    let outcomes = Arc::new(Outcomes::default());
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(outcomes.clone())
+            .with_handler_filtered(act, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    outcomes
+}
+
+/// Send each message and wait for its action to complete.
+async fn run(server: &MockServer, outcomes: &Outcomes, messages: Vec<Value>) -> Vec<String> {
+    for (count, message) in messages.into_iter().enumerate() {
+        server.send_event(message);
+        server
+            .wait_until(|_| outcomes.0.lock().unwrap().len() > count)
+            .await;
+    }
+    outcomes.0.lock().unwrap().clone()
+}
+
+fn params(server: &MockServer, action: &str) -> Vec<Value> {
+    server
+        .calls_of(action)
+        .into_iter()
+        .map(|call| call.params)
+        .collect()
+}
+
+#[tokio::test]
+async fn group_actions_target_the_event() {
+    let server = MockServer::start().await;
+    let outcomes = connect(&server).await;
+
+    let actions = ["reply", "reply_at", "recall", "ban", "kick"];
+    let messages = actions
+        .iter()
+        .map(|action| common::group_message(1, 3, "member", *action))
+        .collect();
+    assert_eq!(
+        run(&server, &outcomes, messages).await,
+        actions.map(|action| format!("{} ok", action))
+    );
+
+    let ok = json!({"type": "text", "data": {"text": "ok"}});
+    let replies = params(&server, "send_group_msg");
+    assert_eq!(replies[0]["group_id"], 1);
+    assert_eq!(
+        replies[0]["message"],
+        json!([{"type": "reply", "data": {"id": "5"}}, ok])
+    );
+    assert_eq!(
+        replies[1]["message"],
+        json!([
+            {"type": "reply", "data": {"id": "5"}},
+            {"type": "at", "data": {"qq": "3"}},
+            ok,
+        ])
+    );
+    assert_eq!(params(&server, "delete_msg"), [json!({"message_id": 5})]);
+    assert_eq!(
+        params(&server, "set_group_ban"),
+        [json!({"group_id": 1, "user_id": 3, "duration": 60})]
+    );
+    let kicks = params(&server, "set_group_kick");
+    assert_eq!(kicks.len(), 1);
+    assert_eq!(
+        (&kicks[0]["group_id"], &kicks[0]["user_id"]),
+        (&json!(1), &json!(3))
+    );
+}
+
+#[tokio::test]
+async fn group_only_actions_fail_in_private() {
+    let server = MockServer::start().await;
+    let outcomes = connect(&server).await;
+
+    let actions = ["reply_at", "recall", "ban", "kick"];
+    let messages = actions
+        .iter()
+        .map(|action| common::private_message(3, *action))
+        .collect();
+    assert_eq!(
+        run(&server, &outcomes, messages).await,
+        [
+            "reply_at ok",
+            "recall ok",
+            "ban not in group",
+            "kick not in group"
+        ]
+    );
+
+    let replies = params(&server, "send_private_msg");
+    assert_eq!(replies[0]["user_id"], 3);
+    // No mention in private.
+    assert_eq!(
+        replies[0]["message"],
+        json!([
+            {"type": "reply", "data": {"id": "6"}},
+            {"type": "text", "data": {"text": "ok"}},
+        ])
+    );
+    assert_eq!(params(&server, "delete_msg"), [json!({"message_id": 6})]);
+    assert!(server.calls_of("set_group_ban").is_empty());
+    assert!(server.calls_of("set_group_kick").is_empty());
+}
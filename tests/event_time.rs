@@ -0,0 +1,96 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use common::MockServer;
+use flow_bot::base::{
+    extract::{EventTime, State},
+    filter::EventFilter,
+    handler::HandlerControl,
+};
+use serde_json::Value;
+
+#[derive(Default)]
+struct Times(Mutex<Vec<EventTime>>);
+
+impl Times {
+    fn push(&self, time: EventTime) {
+        self.0.lock().unwrap().push(time);
+    }
+}
+
+async fn record(time: EventTime, times: State<Arc<Times>>) -> HandlerControl {
+    times.push(time);
+    HandlerControl::Continue
+}
+
+fn now() -> i64 {
+    UNIX_EPOCH.elapsed().unwrap().as_secs() as i64
+}
+
+fn at(time: i64) -> Value {
+    let mut message = common::private_message(2, time.to_string());
+    message["time"] = time.into();
+    message
+}
+
+async fn received(max_age: Option<Duration>, times: &[i64]) -> Vec<EventTime> {
+    let server = MockServer::start().await;
+    let recorded = Arc::new(Times::default());
+    let mut builder = server.builder();
+    if let Some(max_age) = max_age {
+        builder = builder.with_max_event_age(max_age);
+    }
+    let bot = common::spawn(
+        builder
+            .with_state(recorded.clone())
+            .with_handler_filtered(record, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for &time in times {
+        server.send_event(at(time));
+    }
+    server.settle(Duration::from_millis(100)).await;
+    let mut received = std::mem::take(&mut *recorded.0.lock().unwrap());
+    received.sort_by_key(EventTime::timestamp);
+    received
+}
+
+#[tokio::test]
+async fn times_are_converted() {
+    let received = received(None, &[1_700_000_000, now() + 3600]).await;
+    let [past, future] = &received[..] else {
+        panic!("{}", received.len());
+    };
+
+    assert_eq!(past.timestamp(), 1_700_000_000);
+    assert_eq!(
+        past.system_time(),
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+    #[cfg(feature = "chrono")]
+    assert_eq!(past.datetime().to_rfc3339(), "2023-11-14T22:13:20+00:00");
+    assert!(past.age() > Duration::from_secs(365 * 24 * 60 * 60));
+    // Clocks of the implementation may be ahead.
+    assert_eq!(future.age(), Duration::ZERO);
+}
+
+#[tokio::test]
+async fn events_older_than_the_limit_are_dropped() {
+    let now = now();
+    let times = [now - 3600, now - 61, now - 59, now, now + 60];
+    let kept = received(Some(Duration::from_secs(60)), &times)
+        .await
+        .iter()
+        .map(EventTime::timestamp)
+        .collect::<Vec<_>>();
+    assert_eq!(kept, [now - 59, now, now + 60]);
+
+    // Everything is kept without a limit.
+    assert_eq!(received(None, &times).await.len(), times.len());
+}
@@ -0,0 +1,149 @@
+//! The examples, driven by the events in `tests/fixtures/examples`.
+
+mod common;
+
+#[cfg(feature = "command")]
+#[path = "../examples/echo.rs"]
+#[allow(dead_code)]
+mod echo;
+#[path = "../examples/moderation.rs"]
+#[allow(dead_code)]
+mod moderation;
+#[path = "../examples/poll.rs"]
+#[allow(dead_code)]
+mod poll;
+#[path = "../examples/survey.rs"]
+#[allow(dead_code)]
+mod survey;
+
+use std::time::Duration;
+
+use common::{Call, MockServer};
+use flow_bot::FlowBotBuilder;
+use serde_json::Value;
+
+/// A message param as text, with other segments as `[type:id]`.
+fn render_message(message: &Value) -> String {
+    match message {
+        Value::String(text) => text.clone(),
+        Value::Array(segments) => segments
+            .iter()
+            .map(|segment| {
+                let data = &segment["data"];
+                match segment["type"].as_str().unwrap_or_default() {
+                    "text" => data["text"].as_str().unwrap_or_default().to_string(),
+                    "reply" => format!("[reply:{}]", data["id"].as_str().unwrap_or_default()),
+                    "at" => format!("[at:{}]", data["qq"].as_str().unwrap_or_default()),
+                    other => format!("[{}]", other),
+                }
+            })
+            .collect(),
+        other => other.to_string(),
+    }
+}
+
+/// A call as `action key=value...`, with the message rendered last.
+fn render(call: &Call) -> String {
+    let params = call.params.as_object().cloned().unwrap_or_default();
+    let mut rendered = call.action.clone();
+    for (key, value) in &params {
+        if key != "message" && key != "messages" {
+            rendered += &format!(" {}={}", key, value);
+        }
+    }
+    if let Some(message) = params.get("message") {
+        rendered += &format!(": {}", render_message(message));
+    }
+    if let Some(Value::Array(nodes)) = params.get("messages") {
+        let contents = nodes
+            .iter()
+            .map(|node| render_message(&node["data"]["content"]))
+            .collect::<Vec<_>>();
+        rendered += &format!(": {}", contents.join(" | "));
+    }
+    rendered
+}
+
+/// Replay the fixture `name` to a bot set up by `configure`, returning the calls it made for the events.
+async fn replay(name: &str, configure: fn(FlowBotBuilder) -> FlowBotBuilder) -> Vec<String> {
+    let server = MockServer::start().await;
+    let bot = common::spawn(configure(server.builder()).build());
+    bot.context().wait_for_connected().await;
+    // Leaving out the requests the bot makes on its own once connected.
+    server.settle(Duration::from_millis(100)).await;
+    let before = server.calls().len();
+
+    server.replay(&format!("examples/{}.jsonl", name)).await;
+    server.calls()[before..].iter().map(render).collect()
+}
+
+#[cfg(feature = "command")]
+#[tokio::test]
+async fn echo() {
+    let calls = replay("echo", echo::configure).await;
+    assert_eq!(
+        calls,
+        [
+            "send_group_msg group_id=1: [reply:101]hello world\nhello world",
+            "send_private_msg user_id=4: [reply:102]pong",
+            // Not a command, and an invalid one.
+        ]
+    );
+}
+
+#[tokio::test]
+async fn moderation() {
+    let calls = replay("moderation", moderation::configure).await;
+    assert_eq!(
+        calls,
+        [
+            "delete_msg message_id=201",
+            "send_group_msg group_id=1: Links are not allowed here, warning 1/3",
+            "set_group_ban duration=600 group_id=1 user_id=3",
+            "send_group_msg group_id=1: Muted 3 for 600s",
+            "delete_msg message_id=203",
+            "send_group_msg group_id=1: Links are not allowed here, warning 2/3",
+            // The third warning, given by an admin, kicks.
+            "set_group_kick group_id=1 reject_add_request=false user_id=3",
+            "send_group_msg group_id=1: Warned 3, kicked after 3 warnings",
+            // Links of admins are left alone, the sixth message in a row is flooding.
+            "set_group_ban duration=600 group_id=1 user_id=5",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn poll() {
+    let calls = replay("poll", poll::configure).await;
+    assert_eq!(
+        calls,
+        [
+            "send_group_msg group_id=1: [reply:301]What should the poll ask?",
+            "send_group_msg group_id=1: [reply:302]Send the options one by one, then \"done\".",
+            "send_group_msg group_id=1: [reply:305]Lunch?\n1. Pizza\n2. Sushi\nVote with /vote <number>, /endpoll ends the poll.",
+            "send_group_msg group_id=1: Voted for Sushi",
+            "send_group_msg group_id=1: Voted for Sushi",
+            "send_group_msg group_id=1: Voted for Pizza",
+            "send_group_msg group_id=1: Vote with a number from 1 to 2",
+            "send_group_forward_msg group_id=1: Lunch? | Pizza: 1 votes | Sushi: 2 votes",
+            "send_group_msg group_id=1: Lunch?: Sushi (2 votes)",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn survey() {
+    let calls = replay("survey", survey::configure).await;
+    assert_eq!(
+        calls,
+        [
+            "send_private_msg user_id=4: [reply:401]What is your name?",
+            "send_private_msg user_id=4: [reply:402]How old are you?",
+            "send_private_msg user_id=4: [reply:403]Please answer with a number.",
+            // The answer in a group is not part of the private dialog.
+            "send_private_msg user_id=4: [reply:405]What is your favourite color?",
+            "send_private_msg user_id=4: [reply:406]Thanks Alice! 30 years old, likes blue.",
+            // The survey is finished.
+        ]
+    );
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        explain::{Verdict, is_dry_run},
+        extract::{FromEvent, MatchPrivate},
+        filter::EventFilter,
+        handler::HandlerControl,
+    },
+    event::{BotEvent, TypedEvent, message::TypedMessageInfo},
+};
+
+static DRY_RUNS: AtomicUsize = AtomicUsize::new(0);
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+static GREETED: AtomicUsize = AtomicUsize::new(0);
+
+/// Looks up the sender of a group message, counting whether it ran in a dry run.
+struct Member;
+
+#[async_trait]
+impl FromEvent for Member {
+    async fn from_event(context: BotContext, event: BotEvent) -> Option<Self> {
+        let TypedEvent::Message(message) = &event.event else {
+            return None;
+        };
+        let TypedMessageInfo::Group(info) = &message.info else {
+            return None;
+        };
+        match is_dry_run() {
+            true => DRY_RUNS.fetch_add(1, Ordering::SeqCst),
+            false => RUNS.fetch_add(1, Ordering::SeqCst),
+        };
+        context
+            .get_group_member_info(info.group_id, message.user_id, None)
+            .await
+            .ok()?;
+        Some(Member)
+    }
+}
+
+async fn greet(_: Member) -> HandlerControl {
+    GREETED.fetch_add(1, Ordering::SeqCst);
+    HandlerControl::Continue
+}
+
+async fn in_private(_: MatchPrivate) -> HandlerControl {
+    HandlerControl::Continue
+}
+
+async fn on_notice() -> HandlerControl {
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn explain_runs_extractors_without_calling_handlers() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(greet, EventFilter::MESSAGE)
+            .with_handler(in_private)
+            .with_handler_filtered(on_notice, EventFilter::NOTICE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    let event = common::group_message(1, 3, "member", "hi");
+
+    let explanations = bot.explain(&event.to_string()).await.unwrap();
+    let names = explanations
+        .iter()
+        .map(|explanation| (explanation.index, explanation.name.rsplit("::").next()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            (0, Some("greet")),
+            (1, Some("in_private")),
+            (2, Some("on_notice"))
+        ]
+    );
+    assert_eq!(explanations[0].verdict, Verdict::Matched);
+    let Verdict::Skipped(miss) = &explanations[1].verdict else {
+        panic!("{:?}", explanations[1]);
+    };
+    assert_eq!(
+        (miss.argument, miss.extractor),
+        (0, "flow_bot::base::extract::MatchPrivate")
+    );
+    assert_eq!(explanations[2].verdict, Verdict::Filtered);
+
+    // The extractor knew it was a dry run, and still called the API.
+    assert_eq!(DRY_RUNS.load(Ordering::SeqCst), 1);
+    assert_eq!(RUNS.load(Ordering::SeqCst), 0);
+    assert_eq!(server.calls_of("get_group_member_info").len(), 1);
+    assert_eq!(GREETED.load(Ordering::SeqCst), 0);
+
+    // Dispatched for real, it is not a dry run.
+    server.send_event(event);
+    server
+        .wait_until(|_| GREETED.load(Ordering::SeqCst) == 1)
+        .await;
+    assert_eq!(DRY_RUNS.load(Ordering::SeqCst), 1);
+    assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+    assert_eq!(server.calls_of("get_group_member_info").len(), 2);
+}
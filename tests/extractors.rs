@@ -0,0 +1,399 @@
+mod common;
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use common::MockServer;
+use flow_bot::{
+    FlowBot, FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        extract::{
+            All, At, Either, FromEvent, GroupId, MatchGroupId, Mentions, Not, SenderId, Seq, State,
+        },
+        filter::EventFilter,
+        handler::HandlerControl,
+    },
+    event::{
+        BotEvent,
+        message::GroupSenderRole,
+        notice::{GroupBan, GroupRecall},
+    },
+    match_one,
+    message::segments::{Mention, Segment},
+};
+use serde_json::json;
+use tokio::time::Instant;
+
+const DELAY: Duration = Duration::from_millis(100);
+
+/// Stands for an extractor making an API call that takes [`DELAY`], counting how often it was started.
+struct Slow<const ID: usize>;
+
+static STARTED: [AtomicUsize; 8] = [const { AtomicUsize::new(0) }; 8];
+
+#[async_trait]
+impl<const ID: usize> FromEvent for Slow<ID> {
+    async fn from_event(_: BotContext, _: BotEvent) -> Option<Self> {
+        STARTED[ID].fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(DELAY).await;
+        Some(Self)
+    }
+}
+
+/// When each handler was called.
+#[derive(Default)]
+struct Calls(Mutex<Vec<(&'static str, Instant)>>);
+
+impl Calls {
+    fn push(&self, handler: &'static str) -> HandlerControl {
+        self.0.lock().unwrap().push((handler, Instant::now()));
+        HandlerControl::Continue
+    }
+}
+
+async fn concurrent(_: Slow<0>, _: Slow<1>, calls: State<Arc<Calls>>) -> HandlerControl {
+    calls.push("concurrent")
+}
+
+async fn sequential(_: Seq<Slow<2>>, _: Seq<Slow<3>>, calls: State<Arc<Calls>>) -> HandlerControl {
+    calls.push("sequential")
+}
+
+async fn filtered(_: Slow<4>, _: GroupId) -> HandlerControl {
+    HandlerControl::Continue
+}
+
+async fn filtered_seq(_: Seq<Slow<5>>, _: GroupId) -> HandlerControl {
+    HandlerControl::Continue
+}
+
+async fn filter_first(_: GroupId, _: Slow<6>) -> HandlerControl {
+    HandlerControl::Continue
+}
+
+async fn connect(builder: FlowBotBuilder) -> Arc<FlowBot> {
+    let bot = common::spawn(builder.build());
+    bot.context().wait_for_connected().await;
+    bot
+}
+
+#[tokio::test]
+async fn extractors_run_concurrently() {
+    let server = MockServer::start().await;
+    let calls = Arc::new(Calls::default());
+    let _bot = connect(
+        server
+            .builder()
+            .with_state(calls.clone())
+            .with_handler_filtered(concurrent, EventFilter::MESSAGE)
+            .with_handler_filtered(sequential, EventFilter::MESSAGE),
+    )
+    .await;
+
+    let sent = Instant::now();
+    server.send_event(common::private_message(2, "hello"));
+    server
+        .wait_until(|_| calls.0.lock().unwrap().len() == 2)
+        .await;
+
+    let calls = calls.0.lock().unwrap().clone();
+    let (concurrent, sequential) = (calls[0], calls[1]);
+    assert_eq!((concurrent.0, sequential.0), ("concurrent", "sequential"));
+    // Both extractors of 100ms waited together, then one after the other once the first handler returned.
+    let (concurrent, sequential) = (concurrent.1 - sent, sequential.1 - concurrent.1);
+    assert!(
+        concurrent >= DELAY && concurrent < DELAY * 2,
+        "{:?}",
+        concurrent
+    );
+    assert!(sequential >= DELAY * 2, "{:?}", sequential);
+}
+
+#[tokio::test]
+async fn unordered_extractors_start_even_when_a_filter_fails() {
+    let server = MockServer::start().await;
+    // Registered for messages only, so that the Connected event does not start them.
+    let _bot = connect(
+        server
+            .builder()
+            .with_handler_filtered(filtered, EventFilter::MESSAGE)
+            .with_handler_filtered(filtered_seq, EventFilter::MESSAGE)
+            .with_handler_filtered(filter_first, EventFilter::MESSAGE),
+    )
+    .await;
+
+    server.send_event(common::private_message(2, "hello"));
+    tokio::time::sleep(DELAY * 2).await;
+    // GroupId fails for a private message, the handlers are skipped.
+    assert_eq!(STARTED[4].load(Ordering::SeqCst), 1);
+    assert_eq!(STARTED[5].load(Ordering::SeqCst), 0);
+    assert_eq!(STARTED[6].load(Ordering::SeqCst), 0);
+}
+
+/// The guards that matched the latest event, by name.
+#[derive(Default)]
+struct Matched(Mutex<Vec<&'static str>>);
+
+impl Matched {
+    fn push(&self, guard: &'static str) -> HandlerControl {
+        self.0.lock().unwrap().push(guard);
+        HandlerControl::Continue
+    }
+}
+
+/// Never registered.
+struct Missing;
+
+async fn no_mentions(_: Not<At>, matched: State<Arc<Matched>>) -> HandlerControl {
+    matched.push("no_mentions")
+}
+
+async fn outside_group_1(_: Not<MatchGroupId<1>>, matched: State<Arc<Matched>>) -> HandlerControl {
+    matched.push("outside_group_1")
+}
+
+async fn without_state(_: Not<State<Missing>>, matched: State<Arc<Matched>>) -> HandlerControl {
+    matched.push("without_state")
+}
+
+async fn admin_in_group_1(
+    All((_, role)): All<(MatchGroupId<1>, GroupSenderRole)>,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    if role == GroupSenderRole::Admin {
+        matched.push("admin_in_group_1");
+    }
+    HandlerControl::Continue
+}
+
+async fn group_1_then_slow(
+    _: All<(MatchGroupId<1>, Slow<7>)>,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push("group_1_then_slow")
+}
+
+/// Registered last, so that the other guards were all tried once it ran.
+async fn done(matched: State<Arc<Matched>>) -> HandlerControl {
+    matched.push("done")
+}
+
+/// Push `event` and wait for every handler, returning the guards that matched it.
+async fn guards(
+    server: &MockServer,
+    matched: &Matched,
+    event: serde_json::Value,
+) -> Vec<&'static str> {
+    matched.0.lock().unwrap().clear();
+    server.send_event(event);
+    server
+        .wait_until(|_| matched.0.lock().unwrap().contains(&"done"))
+        .await;
+    let mut guards = matched.0.lock().unwrap().clone();
+    guards.retain(|guard| *guard != "done");
+    guards
+}
+
+#[tokio::test]
+async fn not_and_all_guards() {
+    let server = MockServer::start().await;
+    let matched = Arc::new(Matched::default());
+    let _bot = connect(
+        server
+            .builder()
+            .with_state(matched.clone())
+            .with_handler_filtered(no_mentions, EventFilter::MESSAGE)
+            .with_handler_filtered(outside_group_1, EventFilter::MESSAGE)
+            .with_handler_filtered(without_state, EventFilter::MESSAGE)
+            .with_handler_filtered(admin_in_group_1, EventFilter::MESSAGE)
+            .with_handler_filtered(group_1_then_slow, EventFilter::MESSAGE)
+            .with_handler_filtered(done, EventFilter::MESSAGE),
+    )
+    .await;
+
+    let plain = common::group_message(1, 2, "admin", "hello");
+    assert_eq!(
+        guards(&server, &matched, plain).await,
+        [
+            "no_mentions",
+            "without_state",
+            "admin_in_group_1",
+            "group_1_then_slow"
+        ]
+    );
+    assert_eq!(STARTED[7].load(Ordering::SeqCst), 1);
+
+    let mention = json!([
+        {"type": "at", "data": {"qq": "3"}},
+        {"type": "text", "data": {"text": " hello"}},
+    ]);
+    assert_eq!(
+        guards(
+            &server,
+            &matched,
+            common::group_message(1, 2, "member", mention.clone())
+        )
+        .await,
+        ["without_state", "group_1_then_slow"]
+    );
+    assert_eq!(STARTED[7].load(Ordering::SeqCst), 2);
+
+    // MatchGroupId fails first, so the slow extractor after it is never started.
+    assert_eq!(
+        guards(
+            &server,
+            &matched,
+            common::group_message(2, 2, "admin", mention)
+        )
+        .await,
+        ["outside_group_1", "without_state"]
+    );
+    assert_eq!(
+        guards(&server, &matched, common::private_message(2, "hello")).await,
+        ["no_mentions", "outside_group_1", "without_state"]
+    );
+    assert_eq!(STARTED[7].load(Ordering::SeqCst), 2);
+}
+
+/// Answers in the group, or privately to the sender.
+async fn where_from(ctx: BotContext, from: Either<GroupId, SenderId>) -> HandlerControl {
+    let answer = match from {
+        Either::Left(GroupId(group_id)) => format!("group {}", group_id),
+        Either::Right(SenderId(user_id)) => format!("private {}", user_id),
+    };
+    ctx.send_private_message(2, answer, None).await?;
+    HandlerControl::Continue
+}
+
+match_one!(#[derive(Debug)] Moderation, Ban: GroupBan, Recall: GroupRecall, _ => Other);
+
+async fn describe(ctx: BotContext, moderation: Moderation) -> HandlerControl {
+    let answer = match &moderation {
+        Moderation::Ban(ban) => format!("ban {}", ban.user_id),
+        Moderation::Recall(recall) => format!("recall {}", recall.message_id),
+        Moderation::Other => format!("{:?}", moderation),
+    };
+    ctx.send_private_message(2, answer, None).await?;
+    HandlerControl::Continue
+}
+
+async fn answers(server: &MockServer, count: usize) -> Vec<String> {
+    server
+        .wait_calls_of("send_private_msg", count)
+        .await
+        .iter()
+        .map(|call| {
+            call.params["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn either_takes_the_first_match() {
+    let server = MockServer::start().await;
+    let _bot = connect(
+        server
+            .builder()
+            .with_handler_filtered(where_from, EventFilter::MESSAGE),
+    )
+    .await;
+
+    // Group messages have a sender too, the group is tried first.
+    server.send_event(common::group_message(1, 3, "member", "hello"));
+    answers(&server, 1).await;
+    server.send_event(common::private_message(3, "hello"));
+    assert_eq!(answers(&server, 2).await, ["group 1", "private 3"]);
+}
+
+#[tokio::test]
+async fn match_one_falls_back() {
+    let server = MockServer::start().await;
+    let _bot = connect(
+        server
+            .builder()
+            .with_handler_filtered(describe, EventFilter::NOTICE),
+    )
+    .await;
+
+    let notices = [
+        json!({"notice_type": "group_ban", "sub_type": "ban", "group_id": 1, "user_id": 3, "operator_id": 4, "duration": 60}),
+        json!({"notice_type": "group_recall", "group_id": 1, "user_id": 3, "operator_id": 3, "message_id": 7}),
+        json!({"notice_type": "friend_add", "user_id": 3}),
+    ];
+    for (count, notice) in notices.into_iter().enumerate() {
+        server.send_event(common::notice(notice));
+        answers(&server, count + 1).await;
+    }
+    assert_eq!(answers(&server, 3).await, ["ban 3", "recall 7", "Other"]);
+}
+
+async fn first_mention(ctx: BotContext, at: At) -> HandlerControl {
+    let answer = format!("first {:?} {}", at.user_id_i64(), at.is_all());
+    ctx.send_private_message(2, answer, None).await?;
+    HandlerControl::Continue
+}
+
+async fn every_mention(ctx: BotContext, Mentions(mentions): Mentions) -> HandlerControl {
+    ctx.send_private_message(2, format!("every {:?}", mentions), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+fn at(qq: &str) -> serde_json::Value {
+    json!({"type": "at", "data": {"qq": qq}})
+}
+
+#[tokio::test]
+async fn mentions_are_parsed_once() {
+    let server = MockServer::start().await;
+    let _bot = connect(
+        server
+            .builder()
+            .with_handler_filtered(first_mention, EventFilter::MESSAGE)
+            .with_handler_filtered(every_mention, EventFilter::MESSAGE),
+    )
+    .await;
+
+    server.send_event(common::group_message(
+        1,
+        3,
+        "member",
+        json!([at("nobody"), at("4"), {"type": "text", "data": {"text": " hi "}}, at("all")]),
+    ));
+    answers(&server, 2).await;
+    server.send_event(common::group_message(1, 3, "member", json!([at("all")])));
+    answers(&server, 4).await;
+    // Only invalid targets, as if there were no mentions.
+    server.send_event(common::group_message(1, 3, "member", json!([at("")])));
+    server.send_event(common::group_message(1, 3, "member", json!([at("nobody")])));
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(
+        answers(&server, 4).await,
+        [
+            "first Some(4) false",
+            "every [User(4), All]",
+            "first None true",
+            "every [All]",
+        ]
+    );
+}
+
+#[test]
+fn mentions_are_sent_as_strings() {
+    assert_eq!(
+        serde_json::to_value([Segment::from(Mention::User(4)), Segment::from(Mention::All)])
+            .unwrap(),
+        json!([at("4"), at("all")])
+    );
+}
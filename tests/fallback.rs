@@ -0,0 +1,77 @@
+mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl, service::Service},
+    event::{BotEvent, TypedEvent, message::Message},
+    message::message_ext::MessageExt,
+};
+
+fn text(message: &Message) -> String {
+    message.message.extract_if_plain_text().unwrap_or_default()
+}
+
+/// Returns the control named by the message.
+async fn obey(message: Message) -> HandlerControl {
+    match text(&message).as_str() {
+        "continue" => HandlerControl::Continue,
+        "block" => HandlerControl::Block,
+        _ => HandlerControl::Skip,
+    }
+}
+
+async fn skip() -> HandlerControl {
+    HandlerControl::Skip
+}
+
+/// Handles messages saying `service`, skips everything else.
+struct Picky;
+
+#[async_trait]
+impl Service for Picky {
+    async fn serve(&self, _: BotContext, event: BotEvent) -> HandlerControl {
+        match &event.event {
+            TypedEvent::Message(message) if text(message) == "service" => HandlerControl::Continue,
+            _ => HandlerControl::Skip,
+        }
+    }
+}
+
+async fn unknown(ctx: BotContext, message: Message) -> HandlerControl {
+    ctx.send_private_message(2, format!("unknown {}", text(&message)), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn fallback_runs_only_when_everything_skipped() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(Picky)
+            .with_handler_filtered(obey, EventFilter::MESSAGE)
+            .with_handler_filtered(skip, EventFilter::MESSAGE)
+            .with_fallback_handler(unknown)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for text in ["continue", "block", "service", "skip", "other"] {
+        server.send_event(common::private_message(3, text));
+    }
+
+    server.wait_calls_of("send_private_msg", 2).await;
+    server.settle(Duration::from_millis(100)).await;
+    let mut answers = server
+        .calls_of("send_private_msg")
+        .iter()
+        .map(|call| call.params["message"][0]["data"]["text"].clone())
+        .collect::<Vec<_>>();
+    answers.sort_by_key(|text| text.to_string());
+    assert_eq!(answers, ["unknown other", "unknown skip"]);
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    base::{extract::State, filter::EventFilter, handler::HandlerControl},
+    event::{BotEvent, TypedEvent},
+};
+use serde_json::json;
+
+/// The handlers called, with the post type of the event.
+#[derive(Default)]
+struct Seen(Mutex<Vec<(&'static str, String)>>);
+
+impl Seen {
+    fn push(&self, handler: &'static str, event: &BotEvent) {
+        let ty = event.event.get_type().to_string();
+        self.0.lock().unwrap().push((handler, ty));
+    }
+
+    fn sorted(&self) -> Vec<(&'static str, String)> {
+        let mut seen = self.0.lock().unwrap().clone();
+        seen.sort();
+        seen
+    }
+}
+
+async fn notices(event: BotEvent, seen: State<Arc<Seen>>) -> HandlerControl {
+    seen.push("notices", &event);
+    HandlerControl::Continue
+}
+
+/// Blocks messages, notices are not filtered in and pass by.
+async fn blocker(event: BotEvent, seen: State<Arc<Seen>>) -> HandlerControl {
+    seen.push("blocker", &event);
+    HandlerControl::Block
+}
+
+async fn everything(event: BotEvent, seen: State<Arc<Seen>>) -> HandlerControl {
+    if !matches!(event.event, TypedEvent::FlowInternal(_)) {
+        seen.push("everything", &event);
+    }
+    HandlerControl::Continue
+}
+
+fn heartbeat() -> serde_json::Value {
+    json!({
+        "time": 0, "self_id": common::SELF_ID, "post_type": "meta_event",
+        "meta_event_type": "heartbeat", "interval": 5000, "status": {"online": true, "good": true},
+    })
+}
+
+async fn run(
+    builder: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder,
+    seen: &Arc<Seen>,
+) -> Vec<(&'static str, String)> {
+    let server = MockServer::start().await;
+    let bot = common::spawn(builder(server.builder().with_state(seen.clone())).build());
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(2, "hello"));
+    server.send_event(common::notice(
+        json!({"notice_type": "friend_add", "user_id": 2}),
+    ));
+    server.send_event(heartbeat());
+    server.settle(Duration::from_millis(200)).await;
+    seen.sorted()
+}
+
+fn seen(expected: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+    let mut expected = expected
+        .iter()
+        .map(|&(handler, ty)| (handler, ty.to_string()))
+        .collect::<Vec<_>>();
+    expected.sort();
+    expected
+}
+
+#[tokio::test]
+async fn filtered_handlers_are_not_called_for_other_types() {
+    let state = Arc::new(Seen::default());
+    let called = run(
+        |builder| {
+            builder
+                .with_handler_filtered(notices, EventFilter::NOTICE)
+                .with_handler_filtered(blocker, EventFilter::MESSAGE)
+                .with_handler(everything)
+        },
+        &state,
+    )
+    .await;
+    assert_eq!(
+        called,
+        seen(&[
+            ("notices", "notice"),
+            ("blocker", "message"),
+            // The message was blocked before.
+            ("everything", "notice"),
+            ("everything", "meta_event"),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn heartbeats_are_ignored_globally() {
+    let state = Arc::new(Seen::default());
+    let called = run(
+        |builder| {
+            builder
+                .with_ignore_heartbeats(true)
+                .with_handler(everything)
+        },
+        &state,
+    )
+    .await;
+    assert_eq!(
+        called,
+        seen(&[("everything", "message"), ("everything", "notice")])
+    );
+}
+
+#[tokio::test]
+async fn events_are_filtered_globally() {
+    let state = Arc::new(Seen::default());
+    let called = run(
+        |builder| {
+            builder
+                .with_event_filter(EventFilter::MESSAGE | EventFilter::INTERNAL)
+                .with_handler(everything)
+        },
+        &state,
+    )
+    .await;
+    assert_eq!(called, seen(&[("everything", "message")]));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::time::Duration;
+
+use common::MockServer;
+use flow_bot::{
+    api::BanDuration,
+    extensions::flood::{FloodAction, FloodDetector, FloodLimit},
+};
+
+#[test]
+fn durations_are_rounded_up_to_whole_seconds() {
+    let cases = [
+        (Duration::ZERO, 1),
+        (Duration::from_nanos(1), 1),
+        (Duration::from_millis(500), 1),
+        (Duration::from_secs(1), 1),
+        (Duration::from_millis(1001), 2),
+        (Duration::from_secs(600), 600),
+    ];
+    for (duration, seconds) in cases {
+        assert_eq!(
+            BanDuration::from(duration).as_secs(),
+            Some(seconds),
+            "{:?}",
+            duration
+        );
+    }
+    assert_eq!(BanDuration::lift().as_secs(), Some(0));
+}
+
+#[tokio::test]
+async fn a_sub_second_flood_ban_still_mutes() {
+    let server = MockServer::start().await;
+    let limit = FloodLimit {
+        max_messages: 2,
+        window: Duration::from_secs(10),
+    };
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(FloodDetector::new(
+                limit,
+                FloodAction::Ban(Duration::from_millis(500)),
+            ))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for _ in 0..3 {
+        server.send_event(common::group_message(1, 3, "member", "spam"));
+    }
+    let bans = server.wait_calls_of("set_group_ban", 1).await;
+    assert_eq!(bans[0].params["user_id"], 3);
+    assert_eq!(bans[0].params["duration"], 1);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    base::{
+        connect::ReconnectionStrategy, context::BotContext, extract::State, handler::HandlerControl,
+    },
+    event::internal::InternalEvent,
+};
+
+const DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Default)]
+struct Log(Mutex<Vec<String>>);
+
+impl Log {
+    fn push(&self, entry: String) {
+        self.0.lock().unwrap().push(entry);
+    }
+
+    fn entries(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Logs the generation of connection events, and the generation of the context when they are handled.
+async fn record(ctx: BotContext, log: State<Arc<Log>>, event: InternalEvent) -> HandlerControl {
+    let current = ctx.connection_generation();
+    log.0.push(match event {
+        InternalEvent::Connected(connected) => {
+            format!("connected {} at {}", connected.generation, current)
+        }
+        InternalEvent::Disconnected(disconnected) => {
+            format!("disconnected {} at {}", disconnected.generation, current)
+        }
+        InternalEvent::Reconnecting(_) => return HandlerControl::Skip,
+    });
+    HandlerControl::Continue
+}
+
+fn start(server: &MockServer, log: &Arc<Log>) -> BotContext {
+    let bot = common::spawn(
+        FlowBotBuilder::new(server.connection_with(ReconnectionStrategy::Infinite {
+            initial_delay_ms: DELAY.as_millis() as u64,
+            max_delay_ms: DELAY.as_millis() as u64,
+        }))
+        .with_persistent_state_dir(common::temp_dir())
+        .with_state(log.clone())
+        .with_handler(record)
+        .build(),
+    );
+    bot.context()
+}
+
+async fn wait_entries(log: &Log, count: usize) -> Vec<String> {
+    tokio::time::timeout(common::TIMEOUT, async {
+        while log.entries().len() < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("only logged {:?}", log.entries()));
+    log.entries()
+}
+
+#[tokio::test]
+async fn connection_events_carry_their_generation() {
+    let server = MockServer::start().await;
+    let log = Arc::new(Log::default());
+    let context = start(&server, &log);
+    assert_eq!(context.connection_generation(), 0);
+
+    assert_eq!(context.wait_for_connected().await, 1);
+    server.disconnect();
+    wait_entries(&log, 3).await;
+    server.disconnect();
+
+    assert_eq!(
+        wait_entries(&log, 5).await,
+        [
+            "connected 1 at 1",
+            "disconnected 1 at 1",
+            "connected 2 at 2",
+            "disconnected 2 at 2",
+            "connected 3 at 3",
+        ]
+    );
+    assert_eq!(context.connection_generation(), 3);
+}
+
+#[tokio::test]
+async fn background_tasks_wait_while_disconnected() {
+    let server = MockServer::start().await;
+    let log = Arc::new(Log::default());
+    let context = start(&server, &log);
+    let started_on = context.wait_for_connected().await;
+
+    server.disconnect();
+    wait_entries(&log, 2).await;
+    assert!(!context.is_connected());
+    // Still the generation of the closed connection.
+    assert_eq!(context.connection_generation(), started_on);
+
+    let waiting = tokio::spawn({
+        let context = context.clone();
+        async move { context.wait_for_connected().await }
+    });
+    tokio::time::sleep(DELAY / 3).await;
+    assert!(!waiting.is_finished());
+
+    let generation = tokio::time::timeout(common::TIMEOUT, waiting)
+        .await
+        .unwrap()
+        .unwrap();
+    // The task notices it started on a previous connection.
+    assert_eq!(generation, started_on + 1);
+    assert!(context.is_connected());
+    assert_eq!(context.wait_for_connected().await, generation);
+}
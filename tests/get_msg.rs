@@ -0,0 +1,101 @@
+mod common;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{GetMessageResponse, GetMessageType, api_ext::ApiExt},
+    event::message::GroupSenderRole,
+    message::segments::Segment,
+};
+use serde_json::Value;
+
+fn parse(name: &str) -> GetMessageResponse {
+    serde_json::from_str(&common::fixture(&format!("get_msg/{}.json", name)))
+        .unwrap_or_else(|e| panic!("{}: {}", name, e))
+}
+
+#[test]
+fn go_cqhttp_responses_parse() {
+    let response = parse("go-cqhttp");
+    // Message ids are hashes and may be negative.
+    assert_eq!(response.message_id, -1786236511);
+    assert_eq!(response.real_id, Some(4027));
+    assert_eq!(response.time, 1700000000);
+    assert_eq!(response.font, None);
+    let GetMessageType::Group { group_id, sender } = &response.ty else {
+        panic!("{:?}", response.ty);
+    };
+    assert_eq!(*group_id, Some(123456789));
+    assert_eq!(sender.user_id, Some(1145141919));
+    assert!(sender.role.is_none());
+    assert!(matches!(&response.message[0], Segment::Reply(reply) if reply.id == "-2013486147"));
+    assert_eq!(response.message.len(), 4);
+}
+
+#[test]
+fn napcat_responses_parse() {
+    let response = parse("napcat");
+    // Past 2038.
+    assert_eq!(response.time, 2200000000);
+    assert_eq!(response.message_id, 1826394751);
+    // Falls back to the message_seq.
+    assert_eq!(response.real_id, Some(84213));
+    assert_eq!(response.font, Some(14));
+    let GetMessageType::Group { group_id, sender } = &response.ty else {
+        panic!("{:?}", response.ty);
+    };
+    assert_eq!(*group_id, Some(987654321));
+    assert_eq!(sender.card.as_deref(), Some("组长"));
+    assert!(matches!(sender.role, Some(GroupSenderRole::Admin)));
+    assert!(matches!(&response.message[0], Segment::Image(_)));
+    assert!(matches!(&response.message[1], Segment::Text(text) if text.text == "看这个"));
+}
+
+#[test]
+fn lagrange_responses_parse() {
+    let response = parse("lagrange");
+    // Beyond an i32.
+    assert_eq!(response.message_id, 3102938475);
+    assert_eq!(response.real_id, Some(3102938475));
+    assert_eq!(response.font, None);
+    let GetMessageType::Private { sender } = &response.ty else {
+        panic!("{:?}", response.ty);
+    };
+    assert_eq!(sender.nickname.as_deref(), Some("小明"));
+    assert!(matches!(&response.message[0], Segment::Text(text) if text.text == "在吗"));
+}
+
+#[test]
+fn real_id_is_absent_without_a_message_seq() {
+    let mut response: Value =
+        serde_json::from_str(&common::fixture("get_msg/napcat.json")).unwrap();
+    response.as_object_mut().unwrap().remove("message_seq");
+    let response: GetMessageResponse = serde_json::from_value(response).unwrap();
+    assert_eq!(response.real_id, None);
+}
+
+#[tokio::test]
+async fn get_message_reads_every_implementation() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_msg" => {
+            let name = match call.params["message_id"].as_i64() {
+                Some(1) => "go-cqhttp",
+                Some(2) => "napcat",
+                _ => "lagrange",
+            };
+            Reply::Ok(
+                serde_json::from_str(&common::fixture(&format!("get_msg/{}.json", name))).unwrap(),
+            )
+        }
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let mut ids = Vec::new();
+    for id in [1, 2, 3] {
+        ids.push(context.get_message(id).await.unwrap().message_id);
+    }
+    assert_eq!(ids, [-1786236511, 1826394751, 3102938475]);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    FlowBotBuilder,
+    base::{extract::State, group_config::GroupConfigStore, handler::HandlerControl},
+    extensions::greeter::{GreeterService, GreeterSettings},
+};
+use serde_json::{Value, json};
+
+async fn server() -> MockServer {
+    MockServer::start_with(|call| match call.action.as_str() {
+        "get_group_info" => Reply::Ok(json!({
+            "group_id": call.params["group_id"], "group_name": "Flow", "member_count": 2, "max_member_count": 200,
+        })),
+        "get_stranger_info" => Reply::Ok(json!({
+            "user_id": call.params["user_id"], "nickname": "Stranger", "sex": "unknown", "age": 0,
+        })),
+        action => common::canned(action),
+    })
+    .await
+}
+
+fn joined(group_id: i64, user_id: i64) -> Value {
+    common::notice(json!({
+        "notice_type": "group_increase", "sub_type": "approve", "group_id": group_id,
+        "user_id": user_id, "operator_id": 0,
+    }))
+}
+
+fn left(group_id: i64, user_id: i64) -> Value {
+    common::notice(json!({
+        "notice_type": "group_decrease", "sub_type": "leave", "group_id": group_id,
+        "user_id": user_id, "operator_id": user_id,
+    }))
+}
+
+async fn connect(server: &MockServer, builder: FlowBotBuilder) {
+    let bot = common::spawn(
+        builder
+            .with_service(
+                GreeterService::new()
+                    .welcome("{at_new_member} Welcome {nickname} to {group_name}!")
+                    .farewell("Bye {nickname} ({user_id})")
+                    .max_welcomes(2, Duration::from_secs(60)),
+            )
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    server.settle(Duration::from_millis(50)).await;
+}
+
+/// The messages sent to each group, in order.
+async fn greetings(server: &MockServer, count: usize) -> Vec<(i64, Value)> {
+    server.wait_calls_of("send_group_msg", count).await;
+    server.settle(Duration::from_millis(100)).await;
+    server
+        .calls_of("send_group_msg")
+        .into_iter()
+        .map(|call| {
+            (
+                call.params["group_id"].as_i64().unwrap(),
+                call.params["message"].clone(),
+            )
+        })
+        .collect()
+}
+
+fn text(text: &str) -> Value {
+    json!({"type": "text", "data": {"text": text}})
+}
+
+#[tokio::test]
+async fn members_are_welcomed_and_seen_off() {
+    let server = server().await;
+    connect(&server, server.builder()).await;
+
+    server.send_event(joined(1, 3));
+    greetings(&server, 1).await;
+    server.send_event(left(1, 3));
+    greetings(&server, 2).await;
+    // The bot itself is not greeted.
+    server.send_event(joined(1, common::SELF_ID));
+
+    assert_eq!(
+        greetings(&server, 2).await,
+        [
+            (
+                1,
+                json!([
+                    {"type": "at", "data": {"qq": "3"}},
+                    text(" Welcome Nick to Flow!"),
+                ])
+            ),
+            // Members that left are looked up as strangers.
+            (1, json!([text("Bye Stranger (3)")])),
+        ]
+    );
+    assert_eq!(server.calls_of("get_group_member_info").len(), 1);
+    assert_eq!(server.calls_of("get_stranger_info").len(), 1);
+}
+
+#[tokio::test]
+async fn mass_joins_are_not_all_welcomed() {
+    let server = server().await;
+    connect(&server, server.builder()).await;
+
+    for user_id in 3..6 {
+        server.send_event(joined(1, user_id));
+        server.settle(Duration::from_millis(50)).await;
+    }
+    // Other groups have their own limit.
+    server.send_event(joined(2, 3));
+
+    let groups = greetings(&server, 3)
+        .await
+        .into_iter()
+        .map(|(group_id, _)| group_id)
+        .collect::<Vec<_>>();
+    assert_eq!(groups, [1, 1, 2]);
+}
+
+async fn configure(store: State<GroupConfigStore<GreeterSettings>>) -> HandlerControl {
+    store.update(2, |settings| settings.enabled = false).await;
+    store
+        .update(3, |settings| {
+            settings.welcome = Some("Hi {user_id} in {group_id}".to_string())
+        })
+        .await;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn groups_configure_their_greetings() {
+    let server = server().await;
+    connect(
+        &server,
+        server
+            .builder()
+            .with_group_config::<GreeterSettings>("greeter")
+            .with_handler(configure),
+    )
+    .await;
+
+    for group_id in 1..4 {
+        server.send_event(joined(group_id, 4));
+        server.settle(Duration::from_millis(50)).await;
+    }
+
+    let greetings = greetings(&server, 2).await;
+    assert_eq!(greetings.len(), 2);
+    assert_eq!(greetings[0].0, 1);
+    assert_eq!(greetings[1], (3, json!([text("Hi 4 in 3")])));
+}
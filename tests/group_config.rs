@@ -0,0 +1,114 @@
+mod common;
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBot,
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        extract::{GroupId, MessageBody, State},
+        filter::EventFilter,
+        group_config::{GroupConfig, GroupConfigStore},
+        handler::HandlerControl,
+    },
+    message::message_ext::MessageExt,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    prefix: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            prefix: "/".to_string(),
+        }
+    }
+}
+
+/// `set <prefix>` changes the prefix of the group.
+async fn set(
+    ctx: BotContext,
+    GroupId(group_id): GroupId,
+    MessageBody(msg): MessageBody,
+    store: State<GroupConfigStore<Settings>>,
+) -> HandlerControl {
+    let text = msg.extract_plain_text();
+    let Some(prefix) = text.strip_prefix("set ") else {
+        return HandlerControl::Skip;
+    };
+    store
+        .update(group_id, |settings| settings.prefix = prefix.to_string())
+        .await;
+    store.flush().await.unwrap();
+    ctx.send_private_message(2, "set", None).await?;
+    HandlerControl::Block
+}
+
+async fn show(ctx: BotContext, settings: GroupConfig<Settings>) -> HandlerControl {
+    ctx.send_private_message(2, settings.prefix.as_str(), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+async fn connect(server: &MockServer, dir: &Path) -> Arc<FlowBot> {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_persistent_state_dir(dir)
+            .with_group_config::<Settings>("group_settings")
+            .with_handler_filtered(set, EventFilter::MESSAGE)
+            .with_handler_filtered(show, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    bot
+}
+
+async fn shown(server: &MockServer, count: usize) -> Vec<String> {
+    server
+        .wait_calls_of("send_private_msg", count)
+        .await
+        .iter()
+        .map(|call| {
+            call.params["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn updates_are_extracted_and_persisted() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir();
+    let _bot = connect(&server, &dir).await;
+
+    server.send_event(common::group_message(1, 3, "member", "show"));
+    shown(&server, 1).await;
+    server.send_event(common::group_message(1, 3, "admin", "set #"));
+    shown(&server, 2).await;
+    server.send_event(common::group_message(1, 3, "member", "show"));
+    shown(&server, 3).await;
+    server.send_event(common::group_message(2, 3, "member", "show"));
+    shown(&server, 4).await;
+    // Private messages have no group configuration.
+    server.send_event(common::private_message(3, "show"));
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(shown(&server, 4).await, ["/", "set", "#", "/"]);
+
+    // A bot started later reads the stored configurations.
+    let restarted = MockServer::start().await;
+    let _bot = connect(&restarted, &dir).await;
+    restarted.send_event(common::group_message(1, 3, "member", "show"));
+    restarted.send_event(common::group_message(2, 3, "member", "show"));
+    let mut restored = shown(&restarted, 2).await;
+    restored.sort();
+    assert_eq!(restored, ["#", "/"]);
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    base::{
+        extract::{
+            AllowedGroups, DeniedGroups, InAllowedGroups, MatchAnyGroupId, NotInDeniedGroups, State,
+        },
+        filter::EventFilter,
+        handler::HandlerControl,
+    },
+};
+use serde_json::Value;
+
+/// The extractors that matched the latest event, by name.
+#[derive(Default)]
+struct Matched(Mutex<Vec<String>>);
+
+impl Matched {
+    fn push(&self, matched: String) -> HandlerControl {
+        self.0.lock().unwrap().push(matched);
+        HandlerControl::Continue
+    }
+}
+
+async fn allowed(
+    InAllowedGroups(id): InAllowedGroups,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push(format!("allowed {}", id))
+}
+
+async fn not_denied(
+    NotInDeniedGroups(id): NotInDeniedGroups,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push(format!("not denied {}", id))
+}
+
+async fn any_of(_: MatchAnyGroupId<{ &[1, 2] }>, matched: State<Arc<Matched>>) -> HandlerControl {
+    matched.push("any of 1, 2".to_string())
+}
+
+async fn any_of_none(_: MatchAnyGroupId<{ &[] }>, matched: State<Arc<Matched>>) -> HandlerControl {
+    matched.push("any of none".to_string())
+}
+
+/// Registered last, so that the other handlers were all tried once it ran.
+async fn done(matched: State<Arc<Matched>>) -> HandlerControl {
+    matched.push("done".to_string())
+}
+
+struct Bot {
+    server: MockServer,
+    matched: Arc<Matched>,
+    _bot: Arc<flow_bot::FlowBot>,
+}
+
+impl Bot {
+    async fn start(states: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder) -> Self {
+        let server = MockServer::start().await;
+        let matched = Arc::new(Matched::default());
+        let bot = common::spawn(
+            states(server.builder())
+                .with_state(matched.clone())
+                .with_handler_filtered(allowed, EventFilter::MESSAGE)
+                .with_handler_filtered(not_denied, EventFilter::MESSAGE)
+                .with_handler_filtered(any_of, EventFilter::MESSAGE)
+                .with_handler_filtered(any_of_none, EventFilter::MESSAGE)
+                .with_handler_filtered(done, EventFilter::MESSAGE)
+                .build(),
+        );
+        bot.context().wait_for_connected().await;
+        Self {
+            server,
+            matched,
+            _bot: bot,
+        }
+    }
+
+    /// Push `event` and wait for every handler, returning what matched it.
+    async fn matched(&self, event: Value) -> Vec<String> {
+        self.matched.0.lock().unwrap().clear();
+        self.server.send_event(event);
+        self.server
+            .wait_until(|_| self.matched.0.lock().unwrap().iter().any(|m| m == "done"))
+            .await;
+        let mut matched = self.matched.0.lock().unwrap().clone();
+        matched.retain(|matched| matched != "done");
+        matched
+    }
+
+    async fn group(&self, group_id: i64) -> Vec<String> {
+        self.matched(common::group_message(group_id, 3, "member", "hello"))
+            .await
+    }
+
+    async fn private(&self) -> Vec<String> {
+        self.matched(common::private_message(3, "hello")).await
+    }
+}
+
+fn groups(ids: &[i64]) -> HashSet<i64> {
+    ids.iter().copied().collect()
+}
+
+#[tokio::test]
+async fn membership_hits_and_misses() {
+    let bot = Bot::start(|builder| {
+        builder
+            .with_state(AllowedGroups(groups(&[1, 3])))
+            .with_state(DeniedGroups(groups(&[3])))
+    })
+    .await;
+
+    assert_eq!(
+        bot.group(1).await,
+        ["allowed 1", "not denied 1", "any of 1, 2"]
+    );
+    assert_eq!(bot.group(2).await, ["not denied 2", "any of 1, 2"]);
+    assert_eq!(bot.group(3).await, ["allowed 3"]);
+    assert_eq!(bot.group(4).await, ["not denied 4"]);
+}
+
+#[tokio::test]
+async fn private_messages_are_in_no_group() {
+    let bot = Bot::start(|builder| {
+        builder
+            .with_state(AllowedGroups(groups(&[1])))
+            .with_state(DeniedGroups(groups(&[1])))
+    })
+    .await;
+
+    assert!(bot.private().await.is_empty());
+}
+
+#[tokio::test]
+async fn empty_sets_allow_and_deny_nothing() {
+    let bot = Bot::start(|builder| {
+        builder
+            .with_state(AllowedGroups(HashSet::new()))
+            .with_state(DeniedGroups(HashSet::new()))
+    })
+    .await;
+
+    assert_eq!(bot.group(1).await, ["not denied 1", "any of 1, 2"]);
+    assert_eq!(bot.group(4).await, ["not denied 4"]);
+}
+
+#[tokio::test]
+async fn without_the_states_no_group_is_allowed_or_denied() {
+    let bot = Bot::start(|builder| builder).await;
+
+    assert_eq!(bot.group(4).await, ["not denied 4"]);
+    assert!(bot.private().await.is_empty());
+}
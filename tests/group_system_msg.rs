@@ -0,0 +1,100 @@
+mod common;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{GroupSystemMessages, api_ext::ApiExt},
+    event::request::GroupRequestSubType,
+};
+use serde_json::{Value, json};
+
+fn parse(name: &str) -> GroupSystemMessages {
+    serde_json::from_str(&common::fixture(&format!("group_system_msg/{}.json", name)))
+        .unwrap_or_else(|e| panic!("{}: {}", name, e))
+}
+
+#[test]
+fn go_cqhttp_responses_parse() {
+    let messages = parse("go-cqhttp");
+    let invited = &messages.invited_requests[0];
+    // Numeric ids are read as strings, as set_group_add_request takes them.
+    assert_eq!(invited.request_id, "7412093851");
+    assert_eq!(invited.invitor_uin, Some(10001));
+    assert_eq!(invited.group_id, 123456789);
+    assert!(!invited.checked);
+    assert_eq!(invited.actor, Some(0));
+    let join = &messages.join_requests[0];
+    assert_eq!(join.request_id, "7412093852");
+    assert_eq!(join.requester_uin, 10002);
+    assert!(join.checked);
+    assert_eq!(join.actor, Some(10000));
+}
+
+#[test]
+fn request_id_wins_over_flag() {
+    let messages = parse("napcat");
+    assert_eq!(messages.invited_requests[0].request_id, "1700000000123456");
+    assert_eq!(messages.join_requests[0].request_id, "1700000000654321");
+}
+
+#[test]
+fn flag_is_read_without_request_id() {
+    let messages = parse("lagrange");
+    let invited = &messages.invited_requests[0];
+    assert_eq!(invited.request_id, "invite-1");
+    // Left out by the implementation.
+    assert_eq!(invited.invitor_uin, None);
+    assert!(!invited.checked);
+    assert_eq!(invited.actor, None);
+    assert!(messages.join_requests.is_empty());
+}
+
+#[test]
+fn null_lists_are_empty() {
+    let messages = parse("empty");
+    assert!(messages.invited_requests.is_empty());
+    assert!(messages.join_requests.is_empty());
+}
+
+#[test]
+fn requests_without_an_id_fail() {
+    let error = serde_json::from_value::<GroupSystemMessages>(json!({
+        "invited_requests": [{"group_id": 1}],
+    }))
+    .unwrap_err();
+    assert!(
+        error.to_string().contains("`request_id` or `flag`"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn flags_approve_requests() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_group_system_msg" => Reply::Ok(
+            serde_json::from_str::<Value>(&common::fixture("group_system_msg/napcat.json"))
+                .unwrap(),
+        ),
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let messages = context.get_group_system_msg().await.unwrap();
+    let invited = messages.invited_requests.into_iter().next().unwrap();
+    context
+        .set_group_add_request(
+            invited.request_id,
+            GroupRequestSubType::Invite,
+            Some(true),
+            None,
+        )
+        .await
+        .unwrap();
+
+    let calls = server.calls_of("set_group_add_request");
+    assert_eq!(calls[0].params["flag"], "1700000000123456");
+    assert_eq!(calls[0].params["sub_type"], "invite");
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    base::{
+        extract::{MatchGroup, MatchGroupSubType, MatchPrivate, MatchPrivateSubType, State},
+        filter::EventFilter,
+        handler::HandlerControl,
+    },
+    event::message::{GroupSubType, Message, PrivateSubType},
+    message::message_ext::MessageExt,
+};
+use serde_json::{Value, json};
+
+/// The guards each event passed, as `guard:text`.
+#[derive(Default)]
+struct Matched(Mutex<Vec<String>>);
+
+impl Matched {
+    fn push(&self, guard: &str, text: &str) {
+        self.0.lock().unwrap().push(format!("{}:{}", guard, text));
+    }
+}
+
+async fn private(
+    guard: MatchPrivate,
+    message: Message,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push(
+        &format!("private({:?}, {})", guard.sub_type, guard.user_id),
+        &text(&message),
+    );
+    HandlerControl::Continue
+}
+
+async fn group(
+    guard: MatchGroup,
+    message: Message,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push(
+        &format!("group({:?}, {})", guard.sub_type, guard.group_id),
+        &text(&message),
+    );
+    HandlerControl::Continue
+}
+
+async fn friend(
+    _: MatchPrivateSubType<{ PrivateSubType::Friend }>,
+    message: Message,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push("friend", &text(&message));
+    HandlerControl::Continue
+}
+
+async fn temp(
+    _: MatchPrivateSubType<{ PrivateSubType::Group }>,
+    message: Message,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push("temp", &text(&message));
+    HandlerControl::Continue
+}
+
+async fn anonymous(
+    _: MatchGroupSubType<{ GroupSubType::Anonymous }>,
+    message: Message,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push("anonymous", &text(&message));
+    HandlerControl::Continue
+}
+
+async fn notices(
+    private: Option<MatchPrivate>,
+    group: Option<MatchGroup>,
+    matched: State<Arc<Matched>>,
+) -> HandlerControl {
+    matched.push(
+        "notice",
+        &format!("{}{}", private.is_some(), group.is_some()),
+    );
+    HandlerControl::Continue
+}
+
+fn text(message: &Message) -> String {
+    message.message.extract_if_plain_text().unwrap()
+}
+
+fn private_message(sub_type: &str, text: &str) -> Value {
+    let mut message = common::private_message(2, text);
+    message["sub_type"] = sub_type.into();
+    message
+}
+
+fn group_message(sub_type: &str, text: &str) -> Value {
+    let mut message = common::group_message(1, 2, "member", text);
+    message["sub_type"] = sub_type.into();
+    message
+}
+
+#[tokio::test]
+async fn guards_match_their_kind_and_sub_type() {
+    let matched = Arc::new(Matched::default());
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(matched.clone())
+            .with_handler_filtered(private, EventFilter::MESSAGE)
+            .with_handler_filtered(group, EventFilter::MESSAGE)
+            .with_handler_filtered(friend, EventFilter::MESSAGE)
+            .with_handler_filtered(temp, EventFilter::MESSAGE)
+            .with_handler_filtered(anonymous, EventFilter::MESSAGE)
+            .with_handler_filtered(notices, EventFilter::NOTICE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    let expected: [(Value, &[&str]); 7] = [
+        (
+            private_message("friend", "a"),
+            &["private(Friend, 2):a", "friend:a"],
+        ),
+        (
+            private_message("group", "b"),
+            &["private(Group, 2):b", "temp:b"],
+        ),
+        (private_message("other", "c"), &["private(Other, 2):c"]),
+        (group_message("normal", "d"), &["group(Normal, 1):d"]),
+        (
+            group_message("anonymous", "e"),
+            &["group(Anonymous, 1):e", "anonymous:e"],
+        ),
+        (group_message("notice", "f"), &["group(Notice, 1):f"]),
+        (
+            common::notice(json!({"notice_type": "friend_add", "user_id": 2})),
+            &["notice:falsefalse"],
+        ),
+    ];
+    for (event, guards) in expected {
+        server.send_event(&event);
+        server.settle(Duration::from_millis(100)).await;
+        let mut got = std::mem::take(&mut *matched.0.lock().unwrap());
+        got.sort();
+        let mut guards = guards.to_vec();
+        guards.sort();
+        assert_eq!(got, guards, "{}", event);
+    }
+}
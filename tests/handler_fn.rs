@@ -0,0 +1,113 @@
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        extract::MessageBody,
+        filter::EventFilter,
+        handler::{HandlerControl, handler_fn},
+    },
+    message::message_ext::MessageExt,
+};
+
+struct Config {
+    keyword: String,
+    answer: String,
+    matched: AtomicUsize,
+}
+
+#[tokio::test]
+async fn closures_use_what_they_capture() {
+    let greeting = format!("Hello from {}", "flow-bot");
+    let config = Arc::new(Config {
+        keyword: "ping".to_string(),
+        answer: "pong".to_string(),
+        matched: AtomicUsize::new(0),
+    });
+
+    let server = MockServer::start().await;
+    let bot =
+        common::spawn(
+            server
+                .builder()
+                .with_handler_filtered(
+                    handler_fn(
+                        config.clone(),
+                        |config: Arc<Config>,
+                         ctx: BotContext,
+                         MessageBody(message): MessageBody| async move {
+                            if !message.extract_plain_text().contains(&config.keyword) {
+                                return HandlerControl::Skip;
+                            }
+                            config.matched.fetch_add(1, Ordering::Relaxed);
+                            ctx.send_private_message(2, config.answer.as_str(), None)
+                                .await?;
+                            HandlerControl::Block
+                        },
+                    ),
+                    EventFilter::MESSAGE,
+                )
+                .with_handler_filtered(
+                    handler_fn(greeting, |greeting: String, ctx: BotContext| async move {
+                        ctx.send_private_message(2, greeting, None).await?;
+                        HandlerControl::Continue
+                    }),
+                    EventFilter::MESSAGE,
+                )
+                .build(),
+        );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(2, "ping"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    server.send_event(common::private_message(2, "hi"));
+    server.send_event(common::private_message(2, "ping again"));
+    server.wait_calls_of("send_private_msg", 3).await;
+    server.settle(Duration::from_millis(100)).await;
+
+    let answers = server
+        .calls_of("send_private_msg")
+        .into_iter()
+        .map(|call| call.params["message"][0]["data"]["text"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(answers, ["pong", "Hello from flow-bot", "pong"]);
+    // The handler shares the config with the test, it was not copied.
+    assert_eq!(config.matched.load(Ordering::Relaxed), 2);
+}
+
+#[tokio::test]
+async fn unmatched_extractors_skip_the_closure() {
+    let called = Arc::new(AtomicUsize::new(0));
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler(handler_fn(
+                called.clone(),
+                |called: Arc<AtomicUsize>, _: MessageBody| async move {
+                    called.fetch_add(1, Ordering::Relaxed);
+                    HandlerControl::Continue
+                },
+            ))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    // Neither the Connected event nor the notice are messages.
+    server.send_event(common::notice(
+        serde_json::json!({"notice_type": "friend_add", "user_id": 2}),
+    ));
+    server.send_event(common::private_message(2, "hi"));
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(called.load(Ordering::Relaxed), 1);
+}
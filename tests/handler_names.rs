@@ -0,0 +1,114 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl, service::Service},
+    event::BotEvent,
+};
+
+/// Fails, as the mock server refuses `send_like`.
+async fn like(ctx: BotContext) -> HandlerControl {
+    ctx.send_like(2, None).await?;
+    HandlerControl::Continue
+}
+
+async fn pong() -> HandlerControl {
+    HandlerControl::Continue
+}
+
+struct Named;
+
+#[async_trait]
+impl Service for Named {
+    async fn serve(&self, _: BotContext, _: BotEvent) -> HandlerControl {
+        HandlerControl::Continue
+    }
+
+    fn name(&self) -> &'static str {
+        "named-service"
+    }
+}
+
+struct Unnamed;
+
+#[async_trait]
+impl Service for Unnamed {
+    async fn serve(&self, _: BotContext, _: BotEvent) -> HandlerControl {
+        HandlerControl::Skip
+    }
+}
+
+/// The name and reason of every handler result passed to the control policy.
+#[tokio::test]
+async fn names_reach_the_control_policy() {
+    let seen: Arc<Mutex<Vec<String>>> = Default::default();
+    let recorded = seen.clone();
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "send_like" => Reply::Failed(100),
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_event_filter(EventFilter::MESSAGE)
+            .with_named_handler("like", like)
+            .with_handler(pong)
+            .with_service(Named)
+            .with_service(Unnamed)
+            .with_control_policy(move |_, control, meta| {
+                recorded.lock().unwrap().push(format!(
+                    "{} {} {:?} {:?}",
+                    meta.index, meta.name, control, meta.reason
+                ));
+                control
+            })
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(2, "hello"));
+    server.settle(Duration::from_millis(100)).await;
+
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(
+        seen,
+        [
+            "0 like Skip Some(\"returned an error\")".to_string(),
+            format!("1 {} Continue None", std::any::type_name_of_val(&pong)),
+            "2 named-service Continue None".to_string(),
+            format!("3 {} Skip None", std::any::type_name::<Unnamed>()),
+        ]
+    );
+}
+
+#[cfg(feature = "handler-stats")]
+#[tokio::test]
+async fn names_label_the_statistics() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_named_handler("pong", pong)
+            .with_handler(pong)
+            .with_service(Named)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    let names = bot
+        .handler_stats()
+        .into_iter()
+        .map(|stats| stats.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names[0], "pong");
+    assert!(names[1].ends_with("::pong"), "{}", names[1]);
+    assert_eq!(names[2], "named-service");
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "handler-stats")]
+
+mod common;
+
+use std::time::Duration;
+
+use common::MockServer;
+use flow_bot::{
+    base::{filter::EventFilter, handler::HandlerControl, handler_stats::HandlerStats},
+    event::message::Message,
+    message::message_ext::MessageExt,
+};
+
+const EVENTS: u64 = 300;
+
+const SLOW: Duration = Duration::from_millis(30);
+
+fn number(message: &Message) -> u64 {
+    message
+        .message
+        .extract_if_plain_text()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+/// Skips, continues and blocks a third of the events each.
+async fn classify(message: Message) -> HandlerControl {
+    match number(&message) % 3 {
+        0 => HandlerControl::Skip,
+        1 => HandlerControl::Continue,
+        _ => HandlerControl::Block,
+    }
+}
+
+/// Called with the events [`classify`] did not block, slow for one in ten of them.
+async fn sleepy(message: Message) -> HandlerControl {
+    let number = number(&message);
+    if number == 1 {
+        panic!("deliberately");
+    }
+    if number.is_multiple_of(10) {
+        tokio::time::sleep(SLOW).await;
+    }
+    HandlerControl::Continue
+}
+
+fn calls(stats: &HandlerStats) -> u64 {
+    stats.skips + stats.continues + stats.blocks + stats.panics
+}
+
+#[tokio::test]
+async fn outcomes_and_latencies_are_counted() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(classify, EventFilter::MESSAGE)
+            .with_handler_filtered(sleepy, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for i in 0..EVENTS {
+        server.send_event(common::private_message(2, i.to_string()));
+    }
+    let stats = tokio::time::timeout(common::TIMEOUT, async {
+        loop {
+            let stats = bot.handler_stats();
+            if calls(&stats[0]) == EVENTS && calls(&stats[1]) == EVENTS / 3 * 2 {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("{:#?}", bot.handler_stats()));
+
+    let [classify, sleepy] = &stats[..] else {
+        panic!("{:#?}", stats);
+    };
+    assert_eq!((classify.index, sleepy.index), (0, 1));
+    assert!(classify.name.ends_with("classify"), "{}", classify.name);
+    assert_eq!(
+        (
+            classify.skips,
+            classify.continues,
+            classify.blocks,
+            classify.panics
+        ),
+        (100, 100, 100, 0)
+    );
+    assert_eq!(
+        (sleepy.skips, sleepy.continues, sleepy.blocks, sleepy.panics),
+        (0, 199, 0, 1)
+    );
+
+    // 20 of the 200 calls slept, more than the 5% above the 95th percentile.
+    assert!(sleepy.p95 >= SLOW, "{:?}", sleepy);
+    assert!(sleepy.max >= SLOW, "{:?}", sleepy);
+    assert!(sleepy.p50 < SLOW, "{:?}", sleepy);
+}
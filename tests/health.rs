@@ -0,0 +1,131 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{MockServer, Reply};
+use flow_bot::base::{
+    context::BotContext,
+    extract::State,
+    filter::EventFilter,
+    handler::HandlerControl,
+    health::{ConnectionState, Health},
+};
+use serde_json::json;
+use tokio::sync::Semaphore;
+
+/// Answered with [`Reply::Silent`], so the request stays pending.
+const SILENT: &str = "silent_action";
+
+/// Holds handlers until a permit is added.
+struct Gate(Semaphore);
+
+impl Gate {
+    async fn pass(&self) {
+        let _permit = self.0.acquire().await.unwrap();
+    }
+}
+
+async fn held(gate: State<Arc<Gate>>) -> HandlerControl {
+    gate.pass().await;
+    HandlerControl::Continue
+}
+
+async fn wait_health(context: &BotContext, until: impl Fn(&Health) -> bool) -> Health {
+    tokio::time::timeout(common::TIMEOUT, async {
+        loop {
+            let health = context.health();
+            if until(&health) {
+                return health;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("{:#?}", context.health()))
+}
+
+#[tokio::test]
+async fn health_follows_the_connection() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        SILENT => Reply::Silent,
+        action => common::canned(action),
+    })
+    .await;
+    let gate = Arc::new(Gate(Semaphore::new(0)));
+    let bot = server
+        .builder()
+        .with_state(gate.clone())
+        .with_handler_filtered(held, EventFilter::MESSAGE)
+        .build();
+    let health = bot.health();
+    assert_eq!(health.state, ConnectionState::Down);
+    assert_eq!(health.since_last_heartbeat, None);
+
+    let bot = common::spawn(bot);
+    let context = bot.context();
+    context.wait_for_connected().await;
+    assert_eq!(context.health().state, ConnectionState::Connected);
+
+    server.send_event(json!({
+        "time": 0, "self_id": common::SELF_ID, "post_type": "meta_event",
+        "meta_event_type": "heartbeat", "interval": 5000, "status": {"online": true, "good": true},
+    }));
+    let health = wait_health(&context, |health| health.since_last_heartbeat.is_some()).await;
+    assert!(health.since_last_frame.is_some());
+    assert!(health.since_last_frame <= health.since_last_heartbeat);
+
+    let pending = tokio::spawn({
+        let context = context.clone();
+        async move { context.call_action(SILENT, json!({})).await }
+    });
+    wait_health(&context, |health| health.pending_requests == 1).await;
+    pending.abort();
+
+    server.send_event(common::private_message(2, "first"));
+    server.send_event(common::private_message(2, "second"));
+    wait_health(&context, |health| health.in_flight_handlers == 2).await;
+    gate.0.add_permits(2);
+    wait_health(&context, |health| health.in_flight_handlers == 0).await;
+}
+
+#[cfg(feature = "health")]
+#[tokio::test]
+async fn health_is_served_over_http() {
+    use flow_bot::extensions::health::HealthService;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(HealthService::new(addr))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    let response = tokio::time::timeout(common::TIMEOUT, async {
+        loop {
+            if let Ok(mut stream) = TcpStream::connect(addr).await {
+                stream
+                    .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                    .await
+                    .unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                return response;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.contains(r#""state":"connected""#), "{}", response);
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{GroupHonorInfo, GroupHonorType, api_ext::ApiExt},
+    base::{extract::State, filter::EventFilter, handler::HandlerControl},
+    event::notice::HonorChanged,
+};
+use serde_json::{Value, json};
+
+fn fixture(name: &str) -> Value {
+    serde_json::from_str(&common::fixture(&format!("honor/{}.json", name))).unwrap()
+}
+
+fn honors(info: &GroupHonorInfo, user_id: Option<i64>) -> Vec<(GroupHonorType, i64)> {
+    let honors = match user_id {
+        Some(user_id) => info.honors_for(user_id),
+        None => info.all_honors(),
+    };
+    honors
+        .into_iter()
+        .map(|(honor, info)| (honor, info.user_id))
+        .collect()
+}
+
+#[test]
+fn missing_lists_are_skipped() {
+    // Querying a single type only returns its own list.
+    let talkative: GroupHonorInfo = serde_json::from_value(fixture("talkative")).unwrap();
+    assert_eq!(talkative.current_talkative.as_ref().unwrap().day_count, 4);
+    assert_eq!(
+        honors(&talkative, None),
+        [
+            (GroupHonorType::Talkative, 3),
+            (GroupHonorType::Talkative, 4)
+        ]
+    );
+    assert_eq!(
+        honors(&talkative, Some(4)),
+        [(GroupHonorType::Talkative, 4)]
+    );
+    assert!(honors(&talkative, Some(5)).is_empty());
+
+    let all: GroupHonorInfo = serde_json::from_value(fixture("all")).unwrap();
+    assert_eq!(
+        honors(&all, None),
+        [
+            (GroupHonorType::Talkative, 3),
+            (GroupHonorType::Performer, 4),
+            (GroupHonorType::Emotion, 3),
+        ]
+    );
+    assert_eq!(
+        honors(&all, Some(3)),
+        [(GroupHonorType::Talkative, 3), (GroupHonorType::Emotion, 3)]
+    );
+
+    let empty: GroupHonorInfo = serde_json::from_value(json!({"group_id": 1})).unwrap();
+    assert!(empty.all_honors().is_empty());
+}
+
+#[tokio::test]
+async fn honors_are_fetched_by_type() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_group_honor_info" => Reply::Ok(fixture("talkative")),
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let info = context
+        .get_group_honor_info(1, GroupHonorType::Talkative)
+        .await
+        .unwrap();
+    assert_eq!(info.honors_for(3)[0].1.nickname, "Chatty");
+    assert_eq!(
+        server.calls_of("get_group_honor_info")[0].params,
+        json!({"group_id": 1, "type": "talkative"})
+    );
+}
+
+#[derive(Default)]
+struct Changes(Mutex<Vec<(i64, i64, GroupHonorType)>>);
+
+impl Changes {
+    fn push(&self, changed: HonorChanged) {
+        self.0
+            .lock()
+            .unwrap()
+            .push((changed.group_id, changed.user_id, changed.honor));
+    }
+}
+
+async fn record(changes: State<Arc<Changes>>, changed: HonorChanged) -> HandlerControl {
+    changes.push(changed);
+    HandlerControl::Continue
+}
+
+fn honor(user_id: i64, honor_type: &str) -> Value {
+    common::notice(json!({
+        "notice_type": "notify", "sub_type": "honor",
+        "group_id": 1, "user_id": user_id, "honor_type": honor_type,
+    }))
+}
+
+#[tokio::test]
+async fn honor_changes_are_parsed() {
+    let changes = Arc::new(Changes::default());
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(changes.clone())
+            .with_handler_filtered(record, EventFilter::NOTICE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(honor(3, "talkative"));
+    // Unknown types are skipped, as are other notices.
+    server.send_event(honor(3, "golden_dragon"));
+    server.send_event(common::notice(json!({
+        "notice_type": "notify", "sub_type": "poke",
+        "group_id": 1, "user_id": 3, "target_id": 4,
+    })));
+    server.send_event(honor(4, "strong_newbie"));
+
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(
+        *changes.0.lock().unwrap(),
+        [
+            (1, 3, GroupHonorType::Talkative),
+            (1, 4, GroupHonorType::StrongNewbie)
+        ]
+    );
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use common::{MockServer, Reply, logs::Captured};
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        connect::ReconnectionStrategy, context::BotContext, filter::EventFilter,
+        handler::HandlerControl, identity::BotIdentity,
+    },
+    event::message::Message,
+};
+use serde_json::json;
+
+fn group(group_id: i64) -> serde_json::Value {
+    json!({"group_id": group_id, "group_name": "group", "member_count": 10, "max_member_count": 200})
+}
+
+fn friend(user_id: i64) -> serde_json::Value {
+    json!({"user_id": user_id, "nickname": "friend", "remark": ""})
+}
+
+/// Answers the identity calls, listing two groups and three friends.
+fn answer(call: &common::Call) -> Reply {
+    match call.action.as_str() {
+        "get_group_list" => Reply::Ok(json!([group(1), group(2)])),
+        "get_friend_list" => Reply::Ok(json!([friend(2), friend(3), friend(4)])),
+        action => common::canned(action),
+    }
+}
+
+async fn connect(server: &MockServer, counts: bool) -> BotContext {
+    let builder = server.builder();
+    let builder = match counts {
+        true => builder.with_identity_counts(),
+        false => builder,
+    };
+    let bot = common::spawn(builder.build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+/// Answers private messages with the identity of the bot.
+async fn about(ctx: BotContext, identity: BotIdentity, _: Message) -> HandlerControl {
+    ctx.send_private_message(2, identity.to_string(), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn the_identity_is_collected_and_logged_on_connecting() {
+    let (captured, _guard) = Captured::start();
+    let server = MockServer::start_with(answer).await;
+    let context = connect(&server, true).await;
+
+    let identity = context.identity().await;
+    assert_eq!(identity.user_id, Some(common::SELF_ID));
+    assert_eq!(identity.nickname.as_deref(), Some("Bot"));
+    assert_eq!(identity.app_name.as_deref(), Some("NapCat.Onebot"));
+    assert_eq!(identity.app_version.as_deref(), Some("4.0"));
+    assert_eq!(identity.protocol_version.as_deref(), Some("v11"));
+    assert_eq!(identity.group_count, Some(2));
+    assert_eq!(identity.friend_count, Some(3));
+    assert_eq!(identity.generation, 1);
+
+    let banner = "Connected as Bot (10000) on NapCat.Onebot 4.0, protocol v11, 2 groups, 3 friends";
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(
+        captured.events(banner).len(),
+        1,
+        "{:?}",
+        captured.messages()
+    );
+}
+
+#[tokio::test]
+async fn lists_are_not_called_without_counts() {
+    let server = MockServer::start_with(answer).await;
+    let context = connect(&server, false).await;
+
+    let identity = context.identity().await;
+    assert_eq!(identity.user_id, Some(common::SELF_ID));
+    assert_eq!((identity.group_count, identity.friend_count), (None, None));
+    assert!(server.calls_of("get_group_list").is_empty());
+    assert!(server.calls_of("get_friend_list").is_empty());
+    assert_eq!(
+        identity.to_string(),
+        "Bot (10000) on NapCat.Onebot 4.0, protocol v11"
+    );
+}
+
+#[tokio::test]
+async fn failed_calls_leave_only_their_fields_out() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_login_info" | "get_friend_list" => Reply::Failed(100),
+        _ => answer(call),
+    })
+    .await;
+    let context = connect(&server, true).await;
+
+    let identity = context.identity().await;
+    assert_eq!(
+        (identity.user_id, identity.nickname.as_deref()),
+        (None, None)
+    );
+    assert_eq!(identity.app_name.as_deref(), Some("NapCat.Onebot"));
+    assert_eq!(identity.group_count, Some(2));
+    assert_eq!(identity.friend_count, None);
+    assert_eq!(
+        identity.to_string(),
+        "unknown account on NapCat.Onebot 4.0, protocol v11, 2 groups"
+    );
+}
+
+#[tokio::test]
+async fn an_implementation_answering_nothing_gives_an_empty_identity() {
+    let server = MockServer::start_with(|_| Reply::Failed(1404)).await;
+    let context = connect(&server, true).await;
+
+    let identity = context.identity().await;
+    assert_eq!(
+        serde_json::to_value(&*identity).unwrap(),
+        json!({
+            "user_id": null, "nickname": null, "app_name": null, "app_version": null,
+            "protocol_version": null, "group_count": null, "friend_count": null, "generation": 1,
+        })
+    );
+    assert_eq!(
+        identity.to_string(),
+        "unknown account on an unknown implementation"
+    );
+}
+
+#[tokio::test]
+async fn the_identity_is_cached_until_the_bot_reconnects() {
+    let logins = Arc::new(AtomicU32::new(0));
+    let counted = logins.clone();
+    let server = MockServer::start_with(move |call| match call.action.as_str() {
+        "get_login_info" => {
+            let login = counted.fetch_add(1, Ordering::Relaxed) + 1;
+            Reply::Ok(json!({"user_id": common::SELF_ID, "nickname": format!("Bot {}", login)}))
+        }
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(
+        FlowBotBuilder::new(server.connection_with(ReconnectionStrategy::Infinite {
+            initial_delay_ms: 100,
+            max_delay_ms: 100,
+        }))
+        .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let first = context.identity().await;
+    assert_eq!(context.identity().await.nickname, first.nickname);
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(logins.load(Ordering::Relaxed), 1);
+
+    server.disconnect();
+    server.wait_connections(2).await;
+    tokio::time::timeout(common::TIMEOUT, async {
+        while context.connection_generation() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    let refreshed = context.identity().await;
+    assert_eq!(refreshed.generation, 2);
+    assert_eq!(refreshed.nickname.as_deref(), Some("Bot 2"));
+    assert_eq!(first.nickname.as_deref(), Some("Bot 1"));
+}
+
+#[tokio::test]
+async fn handlers_extract_the_identity() {
+    let server = MockServer::start_with(answer).await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_identity_counts()
+            .with_handler_filtered(about, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(2, "about"));
+    let calls = server.wait_calls_of("send_private_msg", 1).await;
+    assert_eq!(
+        calls[0].params["message"][0]["data"]["text"],
+        "Bot (10000) on NapCat.Onebot 4.0, protocol v11, 2 groups, 3 friends"
+    );
+    // Collected once, on connecting.
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(server.calls_of("get_login_info").len(), 1);
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use common::{MockServer, Reply};
+use flow_bot::extensions::join_request::{JoinDecision, JoinPolicy, JoinRequestService};
+use serde_json::{Value, json};
+
+const DAY: i64 = 24 * 60 * 60;
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+fn request(sub_type: &str, user_id: i64, comment: &str) -> Value {
+    json!({
+        "time": now(), "self_id": common::SELF_ID, "post_type": "request", "request_type": "group",
+        "sub_type": sub_type, "group_id": 1, "user_id": user_id, "comment": comment,
+        "flag": format!("flag{}", user_id),
+    })
+}
+
+/// Users 2 and 4 are old enough and of a high level, 3 is neither, 5 says nothing about either and 6 cannot be looked up.
+fn stranger(user_id: i64) -> Reply {
+    let mut stranger = json!({"user_id": user_id, "nickname": "Nick", "sex": "unknown", "age": 0});
+    let fields = match user_id {
+        2 => json!({"reg_time": now() - 400 * DAY, "level": 10}),
+        3 => json!({"reg_time": now() - DAY, "level": 2}),
+        4 => json!({"login_days": 30, "qqLevel": 20}),
+        5 => json!({}),
+        _ => return Reply::Failed(100),
+    };
+    stranger
+        .as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    Reply::Ok(stranger)
+}
+
+async fn start() -> MockServer {
+    MockServer::start_with(|call| match call.action.as_str() {
+        "get_stranger_info" => stranger(call.params["user_id"].as_i64().unwrap()),
+        action => common::canned(action),
+    })
+    .await
+}
+
+/// The answers `service` sent to `requests`, in the order of the requests.
+async fn answers(
+    server: &MockServer,
+    service: JoinRequestService,
+    requests: &[Value],
+    expected: usize,
+) -> Vec<Value> {
+    let bot = common::spawn(server.builder().with_service(service).build());
+    bot.context().wait_for_connected().await;
+
+    for request in requests {
+        server.send_event(request);
+    }
+    server
+        .wait_calls_of("set_group_add_request", expected)
+        .await;
+    server.settle(Duration::from_millis(100)).await;
+    let mut answers = server
+        .calls_of("set_group_add_request")
+        .into_iter()
+        .map(|call| call.params)
+        .collect::<Vec<_>>();
+    answers.sort_by_key(|params| params["flag"].as_str().unwrap().to_string());
+    answers
+}
+
+fn approved(user_id: i64) -> Value {
+    json!({"flag": format!("flag{}", user_id), "sub_type": "add", "approve": true})
+}
+
+fn rejected(user_id: i64) -> Value {
+    json!({"flag": format!("flag{}", user_id), "sub_type": "add", "approve": false})
+}
+
+#[tokio::test]
+async fn answers_are_matched_by_keyword() {
+    let server = start().await;
+    let service = JoinRequestService::new(JoinPolicy::answer_contains(["Rust", "ferris"]));
+    let answers = answers(
+        &server,
+        service,
+        &[
+            request("add", 2, "问题：你最喜欢的语言？\n答案：rust"),
+            // Only the answer is searched.
+            request("add", 3, "问题：Rust 还是 Go？\n答案：Go"),
+            request("add", 4, "I like FERRIS"),
+            request("add", 5, ""),
+        ],
+        4,
+    )
+    .await;
+    assert_eq!(
+        answers,
+        [approved(2), rejected(3), approved(4), rejected(5)]
+    );
+}
+
+#[tokio::test]
+async fn young_accounts_are_rejected() {
+    let server = start().await;
+    let service = JoinRequestService::new(JoinPolicy::min_account_age(Duration::from_secs(
+        7 * DAY as u64,
+    )));
+    let requests = (2..=6)
+        .map(|user_id| request("add", user_id, ""))
+        .collect::<Vec<_>>();
+    let answers = answers(&server, service, &requests, 3).await;
+    // 5 and 6 are left for review.
+    assert_eq!(answers, [approved(2), rejected(3), approved(4)]);
+    assert_eq!(
+        server.calls_of("get_stranger_info")[0].params["no_cache"],
+        true
+    );
+}
+
+#[tokio::test]
+async fn low_levels_are_rejected() {
+    let server = start().await;
+    let service = JoinRequestService::new(JoinPolicy::min_level(8));
+    let requests = (2..=6)
+        .map(|user_id| request("add", user_id, ""))
+        .collect::<Vec<_>>();
+    let answers = answers(&server, service, &requests, 3).await;
+    assert_eq!(answers, [approved(2), rejected(3), approved(4)]);
+}
+
+#[tokio::test]
+async fn invitations_need_their_own_policy() {
+    let server = start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(JoinRequestService::new(JoinPolicy::new(|_, _| async {
+                JoinDecision::Approve
+            })))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    server.send_event(request("invite", 2, ""));
+    server.settle(Duration::from_millis(100)).await;
+    assert!(server.calls_of("set_group_add_request").is_empty());
+
+    let server = start().await;
+    let service = JoinRequestService::new(JoinPolicy::new(|_, _| async { JoinDecision::Approve }))
+        .invite_policy(JoinPolicy::new(|_, request| async move {
+            JoinDecision::Reject {
+                reason: Some(format!("no invitations to {}", request.group_id)),
+            }
+        }))
+        .superuser(7);
+    let answers = answers(
+        &server,
+        service,
+        &[request("invite", 2, ""), request("add", 3, "hi")],
+        2,
+    )
+    .await;
+    assert_eq!(
+        answers,
+        [
+            json!({"flag": "flag2", "sub_type": "invite", "approve": false, "reason": "no invitations to 1"}),
+            approved(3),
+        ]
+    );
+
+    let mut reports = server
+        .calls_of("send_private_msg")
+        .into_iter()
+        .map(|call| {
+            assert_eq!(call.params["user_id"], 7);
+            call.params["message"][0]["data"]["text"].clone()
+        })
+        .collect::<Vec<_>>();
+    reports.sort_by_key(|report| report.to_string());
+    assert_eq!(
+        reports,
+        [
+            "Invitation of 2 to group 1 rejected: no invitations to 1, comment: ",
+            "Join request of 3 to group 1 approved, comment: hi",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn ignored_requests_are_only_reported() {
+    let server = start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(JoinRequestService::new(JoinPolicy::min_level(8)).superuser(7))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(request("add", 5, "hello"));
+    let reports = server.wait_calls_of("send_private_msg", 1).await;
+    assert_eq!(
+        reports[0].params["message"][0]["data"]["text"],
+        "Join request of 5 to group 1 left for review, comment: hello"
+    );
+    assert!(server.calls_of("set_group_add_request").is_empty());
+}
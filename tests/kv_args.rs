@@ -0,0 +1,236 @@
+mod common;
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        filter::EventFilter,
+        handler::HandlerControl,
+        kv_args::{KvArgs, KvArgsError, RepeatedKeys},
+    },
+    event::message::Message,
+};
+use serde_json::json;
+
+type Parsed = Result<(Vec<&'static str>, Vec<(&'static str, &'static str)>), KvArgsError>;
+
+fn check(repeated: RepeatedKeys, cases: &[(&str, Parsed)]) {
+    for (text, expected) in cases {
+        let parsed = KvArgs::<"">::parse_with(text, repeated);
+        let expected = expected.clone().map(|(positional, pairs)| KvArgs {
+            positional: positional.iter().map(|s| s.to_string()).collect(),
+            pairs: pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        });
+        assert_eq!(parsed, expected, "{}", text);
+    }
+}
+
+#[test]
+fn unicode_keys_and_values() {
+    check(
+        RepeatedKeys::LastWins,
+        &[
+            ("称呼=小明", Ok((vec![], vec![("称呼", "小明")]))),
+            ("ключ=значение", Ok((vec![], vec![("ключ", "значение")]))),
+            ("🎉=✅", Ok((vec![], vec![("🎉", "✅")]))),
+            (
+                r#"名前="山田 太郎""#,
+                Ok((vec![], vec![("名前", "山田 太郎")])),
+            ),
+            // Ideographic spaces separate arguments too.
+            (
+                "设置　a=1　b=2",
+                Ok((vec!["设置"], vec![("a", "1"), ("b", "2")])),
+            ),
+            (
+                "é=è ü",
+                Err(KvArgsError::PositionalAfterPair("ü".to_string())),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn quotes_and_escapes() {
+    check(
+        RepeatedKeys::LastWins,
+        &[
+            (
+                r#"a="say \"hi\"""#,
+                Ok((vec![], vec![("a", r#"say "hi""#)])),
+            ),
+            (r"a='it\'s'", Ok((vec![], vec![("a", "it's")]))),
+            (
+                r#"a="back\\slash""#,
+                Ok((vec![], vec![("a", r"back\slash")])),
+            ),
+            (
+                r#"a="it's" b='"q"'"#,
+                Ok((vec![], vec![("a", "it's"), ("b", r#""q""#)])),
+            ),
+            // Backslashes only escape within quotes.
+            (r"a=x\y", Ok((vec![], vec![("a", r"x\y")]))),
+            // Quoted `=` and spaces are part of the key or value.
+            (r#""my key"=v"#, Ok((vec![], vec![("my key", "v")]))),
+            (r#""a=b" c="=""#, Ok((vec!["a=b"], vec![("c", "=")]))),
+            ("a=b=c", Ok((vec![], vec![("a", "b=c")]))),
+            (
+                r#"a=pre"mid dle"post"#,
+                Ok((vec![], vec![("a", "premid dlepost")])),
+            ),
+            ("a= b=", Ok((vec![], vec![("a", ""), ("b", "")]))),
+            (r#"a="""#, Ok((vec![], vec![("a", "")]))),
+            ("", Ok((vec![], vec![]))),
+            ("  \t ", Ok((vec![], vec![]))),
+        ],
+    );
+}
+
+#[test]
+fn repeated_keys() {
+    let text = "tag=a other=x tag=b tag=c";
+    check(
+        RepeatedKeys::LastWins,
+        // In the place of the first.
+        &[(text, Ok((vec![], vec![("tag", "c"), ("other", "x")])))],
+    );
+    check(
+        RepeatedKeys::Collect,
+        &[(
+            text,
+            Ok((
+                vec![],
+                vec![("tag", "a"), ("other", "x"), ("tag", "b"), ("tag", "c")],
+            )),
+        )],
+    );
+    check(
+        RepeatedKeys::Reject,
+        &[
+            (text, Err(KvArgsError::RepeatedKey("tag".to_string()))),
+            // Keys are compared after unquoting.
+            (
+                r#"a=1 "a"=2"#,
+                Err(KvArgsError::RepeatedKey("a".to_string())),
+            ),
+            ("a=1 A=2", Ok((vec![], vec![("a", "1"), ("A", "2")]))),
+        ],
+    );
+
+    let args = KvArgs::<"">::parse_with(text, RepeatedKeys::Collect).unwrap();
+    assert_eq!(args.get("tag"), Some("c"));
+    assert_eq!(args.get_all("tag").collect::<Vec<_>>(), ["a", "b", "c"]);
+}
+
+#[test]
+fn malformed_pairs() {
+    let error = |e: fn(String) -> KvArgsError, raw: &str| Err(e(raw.to_string()));
+    check(
+        RepeatedKeys::LastWins,
+        &[
+            ("=1", error(KvArgsError::EmptyKey, "=1")),
+            ("a=1 =", error(KvArgsError::EmptyKey, "=")),
+            (r#"""=1"#, error(KvArgsError::EmptyKey, r#"""=1"#)),
+            (
+                r#"a="1 b"#,
+                error(KvArgsError::UnterminatedQuote, r#"a="1 b"#),
+            ),
+            ("a='1", error(KvArgsError::UnterminatedQuote, "a='1")),
+            // The closing quote is escaped.
+            (
+                r#"a="1\""#,
+                error(KvArgsError::UnterminatedQuote, r#"a="1\""#),
+            ),
+            (
+                r#"a="1' b"#,
+                error(KvArgsError::UnterminatedQuote, r#"a="1' b"#),
+            ),
+            ("a=1 b", error(KvArgsError::PositionalAfterPair, "b")),
+            (
+                r#"a=1 "b c""#,
+                error(KvArgsError::PositionalAfterPair, r#""b c""#),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn values_are_parsed_on_access() {
+    let args = KvArgs::<"">::parse("n=12 m=-1 flag=yes").unwrap();
+    assert_eq!(args.get_parsed::<u8>("n"), Ok(Some(12)));
+    assert_eq!(args.get_parsed::<u8>("absent"), Ok(None));
+    assert_eq!(args.require::<i32>("m"), Ok(-1));
+    assert_eq!(
+        args.require::<i32>("absent"),
+        Err(KvArgsError::Missing("absent".to_string()))
+    );
+    let error = args.get_parsed::<u8>("m").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid value -1 for m: invalid digit found in string"
+    );
+    assert!(args.get_parsed::<bool>("flag").is_err());
+}
+
+async fn configure(
+    ctx: BotContext,
+    message: Message,
+    args: Result<KvArgs<"/config">, KvArgsError>,
+) -> HandlerControl {
+    let reply = match args {
+        Ok(args) => format!("{:?} {:?}", args.positional, args.pairs),
+        Err(e) => e.to_string(),
+    };
+    ctx.send_private_message(message.user_id, reply, None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn the_extractor_strips_the_prefix() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(RepeatedKeys::Reject)
+            .with_handler_filtered(configure, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for text in [
+        "/config welcome on=是",
+        // Not the prefix as a whole word.
+        "/configure a=1",
+        "/config a=1 a=2",
+    ] {
+        server.send_event(common::private_message(2, text));
+    }
+    // Not plain text.
+    server.send_event(common::private_message(
+        2,
+        json!([
+            {"type": "text", "data": {"text": "/config a=1"}},
+            {"type": "face", "data": {"id": "1"}}
+        ]),
+    ));
+    server.settle(std::time::Duration::from_millis(100)).await;
+
+    let mut replies = server
+        .calls_of("send_private_msg")
+        .into_iter()
+        .map(|call| call.params["message"][0]["data"]["text"].clone())
+        .collect::<Vec<_>>();
+    replies.sort_by_key(|reply| reply.to_string());
+    assert_eq!(
+        replies,
+        [
+            json!("Key a is given more than once"),
+            json!(r#"["welcome"] [("on", "是")]"#),
+        ]
+    );
+}
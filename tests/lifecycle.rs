@@ -0,0 +1,175 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        connect::ReconnectionStrategy, context::BotContext, extract::State, handler::HandlerControl,
+    },
+    error::FlowError,
+    event::internal::{Connected, InternalEvent},
+};
+
+#[derive(Default)]
+struct Log(Mutex<Vec<String>>);
+
+impl Log {
+    fn push(&self, entry: String) {
+        self.0.lock().unwrap().push(entry);
+    }
+
+    fn entries(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+async fn record(log: State<Arc<Log>>, event: InternalEvent) -> HandlerControl {
+    log.0.push(match event {
+        InternalEvent::Connected(connected) => format!("connected {}", connected.attempt),
+        InternalEvent::Disconnected(_) => "disconnected".to_string(),
+        InternalEvent::Reconnecting(reconnecting) => {
+            format!("reconnecting {}", reconnecting.attempt)
+        }
+    });
+    HandlerControl::Continue
+}
+
+async fn identify(ctx: BotContext, _: Connected, log: State<Arc<Log>>) -> HandlerControl {
+    let info = ctx.get_login_info().await.unwrap();
+    log.0.push(format!("logged in as {}", info.user_id));
+    HandlerControl::Continue
+}
+
+async fn wait_entries(log: &Log, count: usize) -> Vec<String> {
+    tokio::time::timeout(common::TIMEOUT, async {
+        while log.entries().len() < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("only logged {:?}", log.entries()));
+    log.entries()
+}
+
+#[tokio::test]
+async fn lifecycle_events_are_dispatched_in_order() {
+    let server = MockServer::start().await;
+    let log = Arc::new(Log::default());
+    let _bot = common::spawn(
+        FlowBotBuilder::new(server.connection_with(ReconnectionStrategy::Infinite {
+            initial_delay_ms: 100,
+            max_delay_ms: 100,
+        }))
+        .with_persistent_state_dir(common::temp_dir())
+        .with_state(log.clone())
+        .with_handler(record)
+        .build(),
+    );
+
+    wait_entries(&log, 1).await;
+    server.disconnect();
+    let entries = wait_entries(&log, 4).await;
+    assert_eq!(
+        entries,
+        [
+            "connected 0",
+            "disconnected",
+            "reconnecting 1",
+            "connected 1"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn connected_handlers_can_call_the_api() {
+    let server = MockServer::start().await;
+    let log = Arc::new(Log::default());
+    let _bot = common::spawn(
+        server
+            .builder()
+            .with_state(log.clone())
+            .with_handler(identify)
+            .build(),
+    );
+
+    // Well before the timeout of API calls, which the call waited for when the connection was not read yet.
+    let started = tokio::time::Instant::now();
+    let entries = wait_entries(&log, 1).await;
+    assert_eq!(entries, [format!("logged in as {}", common::SELF_ID)]);
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+async fn record_disconnects(log: State<Arc<Log>>, event: InternalEvent) -> HandlerControl {
+    if let InternalEvent::Disconnected(disconnected) = event {
+        log.0.push(disconnected.error.unwrap_or_default());
+    }
+    HandlerControl::Continue
+}
+
+fn limited() -> ReconnectionStrategy {
+    ReconnectionStrategy::Limited {
+        max_attempts: 3,
+        initial_delay_ms: 50,
+        max_delay_ms: 50,
+    }
+}
+
+#[tokio::test]
+async fn refused_credentials_stop_limited_reconnection() {
+    let server = MockServer::start().await;
+    let log = Arc::new(Log::default());
+    let bot = FlowBotBuilder::new(server.connection_with(limited()))
+        .with_persistent_state_dir(common::temp_dir())
+        .with_state(log.clone())
+        .with_handler(record_disconnects)
+        .build();
+    let context = bot.context();
+    let run = tokio::spawn(async move { bot.run().await });
+    context.wait_for_connected().await;
+
+    server.disconnect_with(1008, "invalid token");
+    let result = tokio::time::timeout(common::TIMEOUT, run)
+        .await
+        .expect("run did not return")
+        .unwrap();
+    let Err(FlowError::ConnectionClosed { code, reason }) = &result else {
+        panic!("{:?}", result);
+    };
+    assert_eq!((*code, reason.as_str()), (1008, "invalid token"));
+    assert!(result.unwrap_err().is_auth_failure());
+    assert_eq!(
+        wait_entries(&log, 1).await,
+        ["Connection closed with code 1008: invalid token"]
+    );
+
+    // Not a single reconnection attempt.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(server.connection_count(), 1);
+}
+
+#[tokio::test]
+async fn other_close_codes_reconnect() {
+    let server = MockServer::start().await;
+    let log = Arc::new(Log::default());
+    let _bot = common::spawn(
+        FlowBotBuilder::new(server.connection_with(limited()))
+            .with_persistent_state_dir(common::temp_dir())
+            .with_state(log.clone())
+            .with_handler(record_disconnects)
+            .build(),
+    );
+    server.wait_connections(1).await;
+
+    server.disconnect_with(1011, "internal error");
+    server.wait_connections(2).await;
+    assert_eq!(
+        wait_entries(&log, 1).await,
+        ["Connection closed with code 1011: internal error"]
+    );
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    base::{extract::State, filter::EventFilter, handler::HandlerControl},
+    event::message::{
+        GroupMessageEvent, GroupSenderRole, GroupSubType, PrivateMessageEvent, PrivateSubType,
+        SenderSex,
+    },
+    message::message_ext::MessageExt,
+};
+
+/// The messages extracted by each handler.
+#[derive(Default)]
+struct Seen {
+    group: Mutex<Vec<GroupMessageEvent>>,
+    private: Mutex<Vec<PrivateMessageEvent>>,
+}
+
+async fn group(event: GroupMessageEvent, seen: State<Arc<Seen>>) -> HandlerControl {
+    seen.group.lock().unwrap().push(event);
+    HandlerControl::Continue
+}
+
+async fn private(event: PrivateMessageEvent, seen: State<Arc<Seen>>) -> HandlerControl {
+    seen.private.lock().unwrap().push(event);
+    HandlerControl::Continue
+}
+
+/// Send each fixture to a bot with both handlers, returning what they extracted.
+async fn extract(fixtures: &[&str]) -> (Vec<GroupMessageEvent>, Vec<PrivateMessageEvent>) {
+    let seen = Arc::new(Seen::default());
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(seen.clone())
+            .with_handler_filtered(group, EventFilter::MESSAGE)
+            .with_handler_filtered(private, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for (count, fixture) in fixtures.iter().enumerate() {
+        server.send_event(common::fixture(fixture));
+        tokio::time::timeout(common::TIMEOUT, async {
+            while seen.group.lock().unwrap().len() + seen.private.lock().unwrap().len() <= count {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("{} was not extracted", fixture));
+    }
+    server.settle(Duration::from_millis(50)).await;
+    let group = std::mem::take(&mut *seen.group.lock().unwrap());
+    let private = std::mem::take(&mut *seen.private.lock().unwrap());
+    (group, private)
+}
+
+#[tokio::test]
+async fn group_messages_are_extracted_whole() {
+    let (group, private) = extract(&["message_event/group.json"]).await;
+    assert!(private.is_empty());
+    let [event] = &group[..] else {
+        panic!("{:?}", group);
+    };
+
+    assert_eq!(event.time, 1700000000);
+    assert_eq!(event.message_id, 1826394753);
+    assert_eq!(event.user_id, 1145141919);
+    assert_eq!(event.group_id, 987654321);
+    assert_eq!(event.sub_type, GroupSubType::Normal);
+    assert!(event.anonymous.is_none());
+    assert_eq!(event.sender.user_id, Some(1145141919));
+    assert_eq!(event.sender.nickname.as_deref(), Some("小明"));
+    assert_eq!(event.sender.card.as_deref(), Some("明"));
+    assert!(matches!(event.sender.sex, Some(SenderSex::Male)));
+    assert_eq!(event.sender.age, Some(18));
+    assert_eq!(event.sender.level.as_deref(), Some("42"));
+    assert_eq!(event.sender.role, Some(GroupSenderRole::Admin));
+    assert_eq!(event.sender.title.as_deref(), Some("活跃"));
+    assert_eq!(event.message.find_at().unwrap().1.qq, "10000");
+    assert_eq!(event.message.extract_plain_text(), " 你好");
+    assert_eq!(event.raw_message, "[CQ:at,qq=10000] 你好");
+    assert_eq!(event.font, 14);
+}
+
+#[tokio::test]
+async fn anonymous_group_messages_carry_their_anonymous_info() {
+    let (group, private) = extract(&["anonymous/complete.json", "anonymous/null.json"]).await;
+    assert!(private.is_empty());
+    let [complete, null] = &group[..] else {
+        panic!("{:?}", group);
+    };
+
+    assert_eq!(complete.sub_type, GroupSubType::Anonymous);
+    let anonymous = complete.anonymous.as_ref().unwrap();
+    assert_eq!(
+        (
+            anonymous.id,
+            anonymous.name.as_str(),
+            anonymous.flag.as_str()
+        ),
+        (1234, "大力鲸", "1234|大力鲸")
+    );
+    assert_eq!(complete.sender.role, None);
+
+    assert_eq!(null.sub_type, GroupSubType::Anonymous);
+    assert!(null.anonymous.is_none());
+}
+
+#[tokio::test]
+async fn private_messages_are_extracted_whole() {
+    let (group, private) = extract(&["message_event/private.json"]).await;
+    assert!(group.is_empty());
+    let [event] = &private[..] else {
+        panic!("{:?}", private);
+    };
+
+    assert_eq!(event.time, 1700000001);
+    assert_eq!(event.message_id, 1826394754);
+    assert_eq!(event.user_id, 1145141919);
+    assert_eq!(event.sub_type, PrivateSubType::Friend);
+    assert_eq!(event.sender.nickname.as_deref(), Some("小明"));
+    assert!(event.sender.sex.is_none());
+    assert_eq!(event.temp_group_id, None);
+    assert_eq!(event.temp_source, None);
+    assert_eq!(
+        event.message.extract_if_plain_text().as_deref(),
+        Some("在吗")
+    );
+    assert_eq!(event.raw_message, "在吗");
+    assert_eq!(event.font, 14);
+}
+
+#[tokio::test]
+async fn temp_session_messages_carry_their_group() {
+    let (group, private) = extract(&[
+        "temp_session/napcat.json",
+        "temp_session/go-cqhttp.json",
+        "temp_session/no_group.json",
+    ])
+    .await;
+    assert!(group.is_empty());
+    let [napcat, go_cqhttp, no_group] = &private[..] else {
+        panic!("{:?}", private);
+    };
+
+    assert_eq!(napcat.sub_type, PrivateSubType::Group);
+    assert_eq!(napcat.temp_group_id, Some(987654321));
+    assert_eq!(napcat.temp_source, None);
+
+    assert_eq!(go_cqhttp.sub_type, PrivateSubType::Group);
+    assert_eq!(go_cqhttp.temp_group_id, Some(987654321));
+    assert_eq!(go_cqhttp.temp_source, Some(0));
+    assert!(matches!(go_cqhttp.sender.sex, Some(SenderSex::Unknown)));
+    assert_eq!(go_cqhttp.message_id, -2147480000);
+
+    assert_eq!(no_group.sub_type, PrivateSubType::Group);
+    assert_eq!(no_group.temp_group_id, None);
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "metrics")]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use common::MockServer;
+use flow_bot::extensions::metrics::MetricsService;
+use serde_json::json;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+fn free_addr() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+async fn get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn label_values_are_escaped() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    context
+        .call_action("odd \"action\"\\\nname", json!({}))
+        .await
+        .unwrap();
+    let rendered = context.metrics().render(0);
+    assert!(
+        rendered.contains(r#"flow_bot_api_calls_total{action="odd \"action\"\\\nname"} 1"#),
+        "{}",
+        rendered
+    );
+    // Every sample stays on its own line.
+    for line in rendered.lines() {
+        assert!(
+            line.starts_with('#') || line.starts_with("flow_bot_"),
+            "{}",
+            line
+        );
+    }
+}
+
+#[tokio::test]
+async fn metrics_are_served_over_http() {
+    let server = MockServer::start().await;
+    let addr = free_addr();
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(MetricsService::new(addr))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    server.send_event(common::private_message(2, "hello"));
+
+    let mut response = String::new();
+    for _ in 0..50 {
+        response = get(addr, "/metrics").await;
+        if response.contains("flow_bot_events_received_total{post_type=\"message\"} 1") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(
+        response.contains("flow_bot_events_received_total{post_type=\"message\"} 1"),
+        "{}",
+        response
+    );
+
+    assert!(
+        get(addr, "/other")
+            .await
+            .starts_with("HTTP/1.1 404 Not Found")
+    );
+}
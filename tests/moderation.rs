@@ -0,0 +1,142 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::MockServer;
+use flow_bot::{
+    base::context::BotContext,
+    extensions::moderation::{
+        ModerationAction, ModerationConfig, ModerationService, parse_duration,
+    },
+};
+use serde_json::{Value, json};
+
+#[test]
+fn durations_are_parsed() {
+    let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+    assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+    assert_eq!(parse_duration("10m"), minutes(10));
+    assert_eq!(parse_duration("2H"), minutes(120));
+    assert_eq!(parse_duration("1d"), minutes(24 * 60));
+    assert_eq!(parse_duration("1h30m"), minutes(90));
+    for invalid in ["", "10", "m", "5x", "0m", "1h30", "99999999999999999999d"] {
+        assert_eq!(parse_duration(invalid), None, "{}", invalid);
+    }
+}
+
+async fn connect(server: &MockServer) -> (Arc<ModerationService>, BotContext) {
+    let service = Arc::new(ModerationService::new(ModerationConfig::default()));
+    let bot = common::spawn(server.builder().with_service(service.clone()).build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    (service, context)
+}
+
+fn command(role: &str, text: &str, mention: Option<i64>) -> Value {
+    let mut segments = vec![json!({"type": "text", "data": {"text": text}})];
+    if let Some(user_id) = mention {
+        segments.push(json!({"type": "at", "data": {"qq": user_id.to_string()}}));
+    }
+    common::group_message(1, 2, role, Value::from(segments))
+}
+
+fn replies(server: &MockServer) -> Vec<String> {
+    server
+        .calls_of("send_group_msg")
+        .iter()
+        .map(|call| {
+            call.params["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn bans_are_sent_with_their_duration() {
+    let server = MockServer::start().await;
+    let (service, context) = connect(&server).await;
+
+    server.send_event(command("admin", "/ban 3 10m", None));
+    server.wait_calls_of("send_group_msg", 1).await;
+    server.send_event(command("owner", "/ban 40d", Some(3)));
+    server.wait_calls_of("send_group_msg", 2).await;
+    server.send_event(command("admin", "/unban 3", None));
+    server.wait_calls_of("send_group_msg", 3).await;
+    // Members are ignored.
+    server.send_event(command("member", "/ban 4 10m", None));
+    server.settle(Duration::from_millis(100)).await;
+
+    let bans = server
+        .calls_of("set_group_ban")
+        .into_iter()
+        .map(|call| call.params)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        bans,
+        [
+            json!({"group_id": 1, "user_id": 3, "duration": 600}),
+            // Capped at 30 days.
+            json!({"group_id": 1, "user_id": 3, "duration": 30 * 24 * 60 * 60}),
+            json!({"group_id": 1, "user_id": 3, "duration": 0}),
+        ]
+    );
+    assert_eq!(
+        replies(&server),
+        ["Muted 3 for 600s", "Muted 3 for 2592000s", "Unmuted 3"]
+    );
+    let actions = service
+        .audit_log(&context, 1)
+        .await
+        .into_iter()
+        .map(|entry| (entry.operator, entry.action))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [
+            (Some(2), ModerationAction::Ban { seconds: 600 }),
+            (Some(2), ModerationAction::Ban { seconds: 2592000 }),
+            (Some(2), ModerationAction::Unban),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn warnings_escalate_to_a_kick() {
+    let server = MockServer::start().await;
+    let (service, context) = connect(&server).await;
+
+    for count in 1..=2 {
+        server.send_event(command("admin", "/warn spam", Some(3)));
+        server.wait_calls_of("send_group_msg", count).await;
+    }
+    assert!(server.calls_of("set_group_kick").is_empty());
+    assert_eq!(service.warnings(&context, 1, 3).await, 2);
+
+    // Programmatic warnings count the same.
+    let outcome = service.warn(&context, 1, 3, None, None).await.unwrap();
+    assert_eq!((outcome.warnings, outcome.kicked), (3, true));
+    let kicks = server.calls_of("set_group_kick");
+    assert_eq!(kicks.len(), 1);
+    assert_eq!(
+        kicks[0].params,
+        json!({"group_id": 1, "user_id": 3, "reject_add_request": false})
+    );
+    // Counting starts over.
+    assert_eq!(service.warnings(&context, 1, 3).await, 0);
+    assert_eq!(service.warnings(&context, 2, 3).await, 0);
+
+    assert_eq!(
+        replies(&server),
+        ["Warned 3 (1 warnings)", "Warned 3 (2 warnings)"]
+    );
+    let last = service.audit_log(&context, 1).await.pop().unwrap();
+    assert_eq!(
+        last.action,
+        ModerationAction::Kick {
+            reason: Some("reached 3 warnings".to_string())
+        }
+    );
+    assert!(service.audit_log(&context, 2).await.is_empty());
+}
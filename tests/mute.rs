@@ -0,0 +1,129 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        extract::State,
+        filter::EventFilter,
+        handler::HandlerControl,
+        outgoing::{HookResult, OutgoingMessage},
+    },
+    error::FlowError,
+    event::notice::GroupBan,
+    message::message_ext::MessageExt,
+};
+use serde_json::json;
+
+/// How many ban notices the handlers saw.
+#[derive(Default)]
+struct Bans(Mutex<usize>);
+
+impl Bans {
+    fn record(&self) {
+        *self.0.lock().unwrap() += 1;
+    }
+
+    fn seen(&self) -> usize {
+        *self.0.lock().unwrap()
+    }
+}
+
+async fn count_bans(_: GroupBan, bans: State<Arc<Bans>>) -> HandlerControl {
+    bans.record();
+    HandlerControl::Continue
+}
+
+/// Drops the messages saying `drop`.
+async fn drop_hook(message: OutgoingMessage) -> HookResult {
+    match message.message.extract_if_plain_text().as_deref() {
+        Some("drop") => HookResult::Drop,
+        _ => HookResult::Proceed(message),
+    }
+}
+
+async fn connect(server: &MockServer, bans: Arc<Bans>) -> BotContext {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_mute_awareness()
+            .with_outgoing_hook(drop_hook)
+            .with_state(bans)
+            .with_handler_filtered(count_bans, EventFilter::NOTICE)
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+/// Push a ban of the bot in group 1 and wait until the handlers saw it, as the bans are recorded before.
+async fn ban(server: &MockServer, bans: &Bans, sub_type: &str, duration: i64) {
+    let seen = bans.seen();
+    server.send_event(common::notice(json!({
+        "notice_type": "group_ban", "sub_type": sub_type, "group_id": 1,
+        "user_id": common::SELF_ID, "operator_id": 2, "duration": duration,
+    })));
+    tokio::time::timeout(common::TIMEOUT, async {
+        while bans.seen() == seen {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the ban notice was not handled");
+}
+
+#[tokio::test]
+async fn sends_fail_while_muted() {
+    let server = MockServer::start().await;
+    let bans = Arc::new(Bans::default());
+    let context = connect(&server, bans.clone()).await;
+
+    ban(&server, &bans, "ban", 600).await;
+    let error = context
+        .send_group_message(1, "hello", None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error.root_cause(), FlowError::SelfMuted { group_id: 1, .. }),
+        "{:?}",
+        error
+    );
+    // Other groups are not affected.
+    context.send_group_message(2, "hello", None).await.unwrap();
+
+    ban(&server, &bans, "lift_ban", 0).await;
+    context.send_group_message(1, "hello", None).await.unwrap();
+
+    let groups = server
+        .calls_of("send_group_msg")
+        .iter()
+        .map(|call| call.params["group_id"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(groups, [2, 1]);
+}
+
+#[tokio::test]
+async fn hooks_run_before_the_mute_check() {
+    let server = MockServer::start().await;
+    let bans = Arc::new(Bans::default());
+    let context = connect(&server, bans.clone()).await;
+
+    ban(&server, &bans, "ban", 600).await;
+    let error = context
+        .send_group_message(1, "drop", None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error.root_cause(), FlowError::MessageDropped),
+        "{:?}",
+        error
+    );
+    assert!(server.calls_of("send_group_msg").is_empty());
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::MockServer;
+use flow_bot::{
+    api::{api_ext::ApiExt, emoji_id},
+    base::{context::BotContext, handler::HandlerControl},
+    event::notice::{GroupMsgEmojiLike, Notice, Notify},
+};
+use serde_json::json;
+
+fn parse(notice: serde_json::Value) -> Notice {
+    serde_json::from_value(notice).unwrap()
+}
+
+#[test]
+fn known_notify_sub_types_are_typed() {
+    let notice = parse(json!({
+        "notice_type": "notify",
+        "sub_type": "poke",
+        "group_id": 1,
+        "user_id": 2,
+        "target_id": 3,
+    }));
+    let Notice::Notify(Notify::Poke(poke)) = notice else {
+        panic!("{:?}", notice);
+    };
+    assert_eq!(
+        (poke.group_id, poke.user_id, poke.target_id),
+        (Some(1), 2, 3)
+    );
+}
+
+#[test]
+fn other_notify_sub_types_keep_every_field() {
+    let fields = json!({
+        "sub_type": "title",
+        "group_id": 1,
+        "user_id": 2,
+        "title": "群主",
+    });
+    let mut notice = fields.clone();
+    notice["notice_type"] = json!("notify");
+    let notice = parse(notice);
+    let Notice::Notify(Notify::Other(data)) = notice else {
+        panic!("{:?}", notice);
+    };
+    // As the former `Notice::Notify { data }` held them.
+    assert_eq!(serde_json::to_value(data).unwrap(), fields);
+}
+
+fn emoji_like(name: &str) -> GroupMsgEmojiLike {
+    let notice = parse(
+        serde_json::from_str(&common::fixture(&format!(
+            "group_msg_emoji_like/{}.json",
+            name
+        )))
+        .unwrap(),
+    );
+    let Notice::GroupMsgEmojiLike(like) = notice else {
+        panic!("{}: {:?}", name, notice);
+    };
+    like
+}
+
+fn likes(like: &GroupMsgEmojiLike) -> Vec<(&str, i32)> {
+    like.likes
+        .iter()
+        .map(|like| (like.emoji_id.as_str(), like.count))
+        .collect()
+}
+
+#[test]
+fn reactions_parse() {
+    let napcat = emoji_like("napcat");
+    assert_eq!(
+        (napcat.group_id, napcat.user_id, napcat.message_id),
+        (123456789, 1145141919, 1882915632)
+    );
+    assert_eq!(likes(&napcat), [(emoji_id::THUMBS_UP, 1)]);
+    assert_eq!(napcat.is_add, Some(true));
+
+    // Without telling whether the reaction was added.
+    let lagrange = emoji_like("lagrange");
+    assert_eq!(lagrange.message_id, -2013486147);
+    assert_eq!(
+        likes(&lagrange),
+        [(emoji_id::FACE_WITH_TEARS_OF_JOY, 2), (emoji_id::HEART, 1)]
+    );
+    assert_eq!(lagrange.is_add, None);
+}
+
+/// Reacts to reacted messages with the same emoji.
+async fn react(ctx: BotContext, like: GroupMsgEmojiLike) -> HandlerControl {
+    ctx.set_msg_emoji_like(like.message_id, like.likes[0].emoji_id.clone(), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn reactions_are_extracted() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(server.builder().with_handler(react).build());
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::fixture("group_msg_emoji_like/napcat.json"));
+
+    let calls = server.wait_calls_of("set_msg_emoji_like", 1).await;
+    assert_eq!(
+        calls[0].params,
+        json!({"message_id": 1882915632, "emoji_id": "76"})
+    );
+}
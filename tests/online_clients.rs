@@ -0,0 +1,84 @@
+mod common;
+
+use common::{MockServer, Reply};
+use flow_bot::api::{OnlineClientsResponse, api_ext::ApiExt};
+use serde_json::{Value, json};
+
+fn fixture(name: &str) -> Value {
+    serde_json::from_str(&common::fixture(&format!("online_clients/{}.json", name))).unwrap()
+}
+
+fn devices(response: &OnlineClientsResponse) -> Vec<(&str, &str)> {
+    response
+        .clients
+        .iter()
+        .map(|device| (device.device_name.as_str(), device.device_kind.as_str()))
+        .collect()
+}
+
+#[test]
+fn clients_parse_nested_under_data_or_bare() {
+    let wrapped: OnlineClientsResponse = serde_json::from_value(fixture("wrapped")).unwrap();
+    assert_eq!(
+        devices(&wrapped),
+        [("iPhone 15 Pro", "iPhone"), ("DESKTOP-4F2KQ", "Windows")]
+    );
+    assert_eq!(wrapped.clients[0].app_id, 537155547);
+
+    let bare: OnlineClientsResponse = serde_json::from_value(fixture("bare")).unwrap();
+    assert_eq!(devices(&bare), [("Xiaomi 14", "Android")]);
+
+    // Without other clients online.
+    let none: OnlineClientsResponse = serde_json::from_value(fixture("no_clients")).unwrap();
+    assert!(none.clients.is_empty());
+    let null: OnlineClientsResponse = serde_json::from_value(json!({"clients": null})).unwrap();
+    assert!(null.clients.is_empty());
+}
+
+#[tokio::test]
+async fn online_clients_are_fetched() {
+    for name in ["wrapped", "bare"] {
+        let data = fixture(name);
+        let server = MockServer::start_with(move |call| match call.action.as_str() {
+            "get_online_clients" => Reply::Ok(data.clone()),
+            action => common::canned(action),
+        })
+        .await;
+        let bot = common::spawn(server.builder().build());
+        let context = bot.context();
+        context.wait_for_connected().await;
+
+        let response = context.get_online_clients(Some(true)).await.unwrap();
+        assert!(!response.clients.is_empty(), "{}", name);
+        assert_eq!(
+            server.calls_of("get_online_clients")[0].params,
+            json!({"no_cache": true})
+        );
+    }
+}
+
+#[tokio::test]
+async fn model_show_is_fetched_and_set() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "_get_model_show" => Reply::Ok(json!({
+            "variants": [{"model_show": "iPhone 15 Pro", "need_pay": false}]
+        })),
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let response = context.get_model_show("iPhone".to_string()).await.unwrap();
+    assert_eq!(response.variants[0].model_show, "iPhone 15 Pro");
+    assert!(!response.variants[0].need_pay);
+    context
+        .set_model_show("iPhone".to_string(), "iPhone 15 Pro".to_string())
+        .await
+        .unwrap();
+    assert_eq!(
+        server.calls_of("_set_model_show")[0].params,
+        json!({"model": "iPhone", "model_show": "iPhone 15 Pro"})
+    );
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{connect::ReconnectionStrategy, outbox::OutboxConfig},
+    error::FlowError,
+};
+
+fn reconnecting(server: &MockServer, delay_ms: u64) -> FlowBotBuilder {
+    FlowBotBuilder::new(server.connection_with(ReconnectionStrategy::Infinite {
+        initial_delay_ms: delay_ms,
+        max_delay_ms: delay_ms,
+    }))
+    .with_persistent_state_dir(common::temp_dir())
+}
+
+/// Wait for the first connection of `bot`, then close it.
+async fn disconnect(server: &MockServer, bot: &flow_bot::FlowBot) {
+    let context = bot.context();
+    context.wait_for_connected().await;
+    server.disconnect();
+    tokio::time::timeout(common::TIMEOUT, async {
+        while context.is_connected() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("the bot did not notice the disconnection");
+}
+
+#[tokio::test]
+async fn sends_while_disconnected_are_flushed_in_order() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        reconnecting(&server, 300)
+            .with_offline_buffering(8, Duration::from_secs(5))
+            .build(),
+    );
+    disconnect(&server, &bot).await;
+
+    let context = bot.context();
+    // Joined futures are polled in order, so the calls are buffered in this order.
+    let (first, second, third) = tokio::join!(
+        context.send_group_message(1, "first", None),
+        context.send_group_message(1, "second", None),
+        context.send_private_message(2, "third", None),
+    );
+    for sent in [first, second, third] {
+        assert_eq!(sent.unwrap().message_id, 1);
+    }
+
+    let calls = server.calls();
+    let texts = calls
+        .iter()
+        .filter(|call| call.action.starts_with("send_"))
+        .map(|call| {
+            assert_eq!(call.connection, 1, "sent before reconnecting");
+            call.params["message"][0]["data"]["text"].as_str().unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(texts, ["first", "second", "third"]);
+    assert_eq!(bot.context().connection_generation(), 2);
+}
+
+#[tokio::test]
+async fn buffered_sends_expire() {
+    let server = MockServer::start().await;
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let recorded = dropped.clone();
+    let bot = common::spawn(
+        reconnecting(&server, 400)
+            .with_outbox(OutboxConfig::new(8, Duration::from_millis(100)).on_dropped(
+                move |action, age| {
+                    recorded.lock().unwrap().push((action.to_string(), age));
+                },
+            ))
+            .build(),
+    );
+    disconnect(&server, &bot).await;
+
+    let started = tokio::time::Instant::now();
+    let error = bot
+        .context()
+        .send_group_message(1, "too late", None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error.root_cause(), FlowError::NoConnection));
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    // The expired call is dropped when the bot reconnects, instead of being sent.
+    server.wait_connections(2).await;
+    bot.context().wait_for_connected().await;
+    let dropped = dropped.lock().unwrap().clone();
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].0, "send_group_msg");
+    assert!(dropped[0].1 >= Duration::from_millis(100));
+    assert!(server.calls_of("send_group_msg").is_empty());
+}
+
+#[tokio::test]
+async fn calls_other_than_sends_fail_without_a_connection() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        reconnecting(&server, 300)
+            .with_offline_buffering(8, Duration::from_secs(5))
+            .build(),
+    );
+    disconnect(&server, &bot).await;
+
+    let error = bot.context().get_login_info().await.unwrap_err();
+    assert!(matches!(error.root_cause(), FlowError::NoConnection));
+}
+
+#[tokio::test]
+async fn a_full_outbox_drops_the_oldest_call() {
+    let server = MockServer::start().await;
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let recorded = dropped.clone();
+    let bot = common::spawn(
+        reconnecting(&server, 300)
+            .with_outbox(OutboxConfig::new(2, Duration::from_secs(5)).on_dropped(
+                move |action, _| {
+                    recorded.lock().unwrap().push(action.to_string());
+                },
+            ))
+            .build(),
+    );
+    disconnect(&server, &bot).await;
+
+    let context = bot.context();
+    let (first, second, third) = tokio::join!(
+        context.send_group_message(1, "first", None),
+        context.send_group_message(1, "second", None),
+        context.send_group_message(1, "third", None),
+    );
+    assert!(matches!(
+        first.unwrap_err().root_cause(),
+        FlowError::NoConnection
+    ));
+    assert!(second.is_ok() && third.is_ok());
+    assert_eq!(*dropped.lock().unwrap(), ["send_group_msg"]);
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext,
+        dead_letter::DeadLetterFile,
+        outgoing::{HookResult, OutgoingMessage, OutgoingTarget},
+    },
+    error::FlowError,
+    message::segments::{Segment, TextSegment},
+};
+use serde_json::json;
+
+/// Hides every `secret` in the texts of the message.
+async fn censor(mut message: OutgoingMessage) -> HookResult {
+    for segment in &mut message.message {
+        if let Segment::Text(text) = segment {
+            text.text = text.text.replace("secret", "***");
+        }
+    }
+    HookResult::Proceed(message)
+}
+
+/// Rejects messages to group 2, and signs the others, after [`censor`].
+async fn sign(mut message: OutgoingMessage) -> HookResult {
+    if message.target == OutgoingTarget::Group(2) {
+        return HookResult::Fail(FlowError::ActionDenied(message.action));
+    }
+    message.message.push(Segment::Text(TextSegment {
+        text: " ~bot".to_string(),
+    }));
+    HookResult::Proceed(message)
+}
+
+async fn connect(server: &MockServer) -> BotContext {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_outgoing_hook(censor)
+            .with_outgoing_hook(sign)
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+#[tokio::test]
+async fn hooks_rewrite_messages_in_order() {
+    let server = MockServer::start().await;
+    let context = connect(&server).await;
+
+    let message = vec![
+        Segment::Text(TextSegment {
+            text: "the secret is".to_string(),
+        }),
+        Segment::Text(TextSegment {
+            text: " secret".to_string(),
+        }),
+    ];
+    context.send_group_message(1, message, None).await.unwrap();
+    // Strings stay strings.
+    context
+        .send_private_message_raw_string(3, "a secret".to_string(), None)
+        .await
+        .unwrap();
+
+    let [group] = &server.calls_of("send_group_msg")[..] else {
+        panic!("{:?}", server.calls());
+    };
+    assert_eq!(group.params["group_id"], 1);
+    assert_eq!(
+        group.params["message"],
+        json!([
+            {"type": "text", "data": {"text": "the *** is"}},
+            {"type": "text", "data": {"text": " ***"}},
+            {"type": "text", "data": {"text": " ~bot"}},
+        ])
+    );
+    let [private] = &server.calls_of("send_private_msg")[..] else {
+        panic!("{:?}", server.calls());
+    };
+    assert_eq!(private.params["message"], "a *** ~bot");
+}
+
+#[tokio::test]
+async fn rejected_messages_are_not_sent() {
+    let server = MockServer::start().await;
+    let context = connect(&server).await;
+
+    let error = context
+        .send_group_message(2, "hello", None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error.root_cause(), FlowError::ActionDenied(action) if action == "send_group_msg"),
+        "{:?}",
+        error
+    );
+    // Other actions do not go through the hooks.
+    context
+        .call_action(
+            "set_group_name",
+            json!({"group_id": 2, "group_name": "secret"}),
+        )
+        .await
+        .unwrap();
+
+    assert!(server.calls_of("send_group_msg").is_empty());
+    assert_eq!(
+        server.calls_of("set_group_name")[0].params["group_name"],
+        "secret"
+    );
+}
+
+#[tokio::test]
+async fn redriven_calls_are_not_hooked_again() {
+    let failed = Arc::new(AtomicUsize::new(0));
+    let failures = failed.clone();
+    let server = MockServer::start_with(move |call| match call.action.as_str() {
+        "send_group_msg" if failures.fetch_add(1, Ordering::Relaxed) == 0 => Reply::Failed(100),
+        action => common::canned(action),
+    })
+    .await;
+    let file = DeadLetterFile::new(common::temp_dir().join("dead_letters.jsonl"));
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_outgoing_hook(censor)
+            .with_outgoing_hook(sign)
+            .with_dead_letter_file(file.clone())
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    context
+        .send_group_message_raw_string(1, "secret".to_string(), None)
+        .await
+        .unwrap_err();
+    assert_eq!(file.redrive(&context).await.unwrap(), 1);
+
+    let messages = server
+        .calls_of("send_group_msg")
+        .iter()
+        .map(|call| call.params["message"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["*** ~bot", "*** ~bot"]);
+}
@@ -0,0 +1,181 @@
+use flow_bot::message::{
+    Message,
+    message_ext::MessageExt,
+    pattern::{Captured, PatternItem},
+};
+use serde_json::{Value, json};
+
+use PatternItem::*;
+
+fn text(text: &str) -> Value {
+    json!({"type": "text", "data": {"text": text}})
+}
+
+fn at(qq: &str) -> Value {
+    json!({"type": "at", "data": {"qq": qq}})
+}
+
+fn image(file: &str) -> Value {
+    json!({"type": "image", "data": {"file": file}})
+}
+
+fn message(segments: &[Value]) -> Message {
+    serde_json::from_value(Value::from(segments.to_vec())).unwrap()
+}
+
+fn describe(captured: &Captured) -> String {
+    match captured {
+        Captured::Text(text) => format!("text {:?}", text),
+        Captured::At(at) => format!("at {}", at.qq),
+        Captured::Image(image) => format!("image {}", image.file),
+        Captured::Face(face) => format!("face {}", face.id),
+        Captured::Reply(reply) => format!("reply {}", reply.id),
+        Captured::Segment(segment) => {
+            format!("segment {}", serde_json::to_value(segment).unwrap()["type"])
+        }
+    }
+}
+
+fn parse(segments: &[Value], pattern: &[PatternItem]) -> Option<Vec<String>> {
+    let message = message(segments);
+    let captured = message.parse_pattern(pattern)?;
+    Some(captured.iter().map(describe).collect())
+}
+
+const TAG: &[PatternItem] = &[Text("/tag"), At, AnyText];
+
+#[test]
+fn commands_with_mentions_are_destructured() {
+    let captured = parse(&[text("/tag "), at("2"), text(" some reason")], TAG);
+    assert_eq!(captured.unwrap(), ["at 2", "text \"some reason\""]);
+
+    // Without whitespace around the mention, and with trailing whitespace.
+    let captured = parse(&[text("/tag"), at("2"), text("reason  \n")], TAG);
+    assert_eq!(captured.unwrap(), ["at 2", "text \"reason\""]);
+
+    let message = message(&[text("/tag "), at("all"), text(" everyone")]);
+    let captured = message.parse_pattern(TAG).unwrap();
+    assert_eq!(captured[0].as_at().unwrap().qq, "all");
+    assert_eq!(captured[1].as_text(), Some("everyone"));
+    assert!(captured[0].as_text().is_none());
+    assert!(captured[1].as_at().is_none());
+}
+
+#[test]
+fn split_text_segments_are_joined() {
+    // Implementations split text anywhere, even inside the literal.
+    let captured = parse(
+        &[
+            text("/t"),
+            text("ag"),
+            text(" "),
+            at("2"),
+            text(" some "),
+            text("reason"),
+        ],
+        TAG,
+    );
+    assert_eq!(captured.unwrap(), ["at 2", "text \"some reason\""]);
+
+    let captured = parse(
+        &[text("/ban"), text(" 10"), text("m "), at("3")],
+        &[Text("/ban"), Word, At],
+    );
+    assert_eq!(captured.unwrap(), ["text \"10m\"", "at 3"]);
+}
+
+#[test]
+fn the_whole_message_must_match() {
+    // Missing parts.
+    assert!(parse(&[text("/tag "), at("2")], TAG).is_none());
+    assert!(parse(&[text("/tag "), at("2"), text("   ")], TAG).is_none());
+    assert!(parse(&[text("/tag some reason")], TAG).is_none());
+    // Parts out of order.
+    assert!(parse(&[at("2"), text("/tag reason")], TAG).is_none());
+    // Something left over.
+    assert!(parse(&[text("/tag "), at("2"), text(" a"), image("a.png")], TAG).is_none());
+    assert!(parse(&[text("/ping extra")], &[Text("/ping")]).is_none());
+    // Text before the literal.
+    assert!(parse(&[text("hey /ping")], &[Text("/ping")]).is_none());
+
+    assert_eq!(
+        parse(&[text("  /ping \n")], &[Text("/ping")]).unwrap(),
+        Vec::<String>::new()
+    );
+    assert_eq!(parse(&[], &[]).unwrap(), Vec::<String>::new());
+    assert_eq!(parse(&[text(" ")], &[]).unwrap(), Vec::<String>::new());
+    assert!(parse(&[], &[AnyText]).is_none());
+}
+
+#[test]
+fn words_stop_at_whitespace_and_segments() {
+    let pattern = [Text("/give"), Word, Word, AnyText];
+    let captured = parse(&[text("/give  apple 3 for being nice")], &pattern);
+    assert_eq!(
+        captured.unwrap(),
+        ["text \"apple\"", "text \"3\"", "text \"for being nice\""]
+    );
+
+    let captured = parse(&[text("/give apple"), at("2")], &[Text("/give"), Word, At]);
+    assert_eq!(captured.unwrap(), ["text \"apple\"", "at 2"]);
+    // A word is not a segment.
+    assert!(parse(&[text("/give "), at("2")], &[Text("/give"), Word]).is_none());
+}
+
+#[test]
+fn segments_are_matched_by_kind() {
+    let segments = [
+        json!({"type": "reply", "data": {"id": "5"}}),
+        at("2"),
+        text(" look "),
+        image("a.png"),
+        json!({"type": "face", "data": {"id": 13}}),
+        json!({"type": "dice", "data": {}}),
+    ];
+    let captured = parse(&segments, &[Reply, At, Text("look"), Image, Face, Any]);
+    assert_eq!(
+        captured.unwrap(),
+        [
+            "reply 5",
+            "at 2",
+            "image a.png",
+            "face 13",
+            "segment \"dice\""
+        ]
+    );
+
+    assert!(parse(&segments, &[Reply, At, Text("look"), Face, Image, Any]).is_none());
+    // Any does not match text.
+    assert!(parse(&[text("a")], &[Any]).is_none());
+    assert_eq!(
+        parse(&[image("a.png")], &[Any]).unwrap(),
+        ["segment \"image\""]
+    );
+}
+
+#[test]
+fn spans_around_mentions() {
+    let message = message(&[
+        text("/tag"),
+        image("a.png"),
+        at("2"),
+        text(" some "),
+        at("3"),
+        text("reason"),
+    ]);
+
+    let (index, first) = message.find_at().unwrap();
+    assert_eq!((index, first.qq.as_str()), (2, "2"));
+    assert_eq!(message.segments_after(index).len(), 3);
+    assert!(message.segments_after(5).is_empty());
+    assert!(message.segments_after(usize::MAX).is_empty());
+
+    assert_eq!(message.text_between(2, 4), " some ");
+    assert_eq!(message.text_between(2, 100), " some reason");
+    assert_eq!(message.text_between(0, 2), "");
+    assert_eq!(message.text_between(4, 2), "");
+    assert_eq!(message.text_between(5, 6), "");
+    assert_eq!(message.text_between(usize::MAX, usize::MAX), "");
+
+    assert!(self::message(&[text("no mention")]).find_at().is_none());
+}
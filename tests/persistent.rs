@@ -0,0 +1,97 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use common::MockServer;
+use flow_bot::base::{extract::State, handler::HandlerControl, persistent::PersistentState};
+use serde::{Deserialize, Serialize};
+
+/// Per-group settings, as a bot would persist them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    greeting: String,
+    muted: HashMap<i64, bool>,
+}
+
+type Slot = Arc<Mutex<Option<Arc<PersistentState<Settings>>>>>;
+
+/// Hand the state over to the test.
+async fn grab(settings: State<PersistentState<Settings>>, slot: State<Slot>) -> HandlerControl {
+    slot.lock().unwrap().get_or_insert(settings.0);
+    HandlerControl::Continue
+}
+
+fn defaults() -> Settings {
+    Settings {
+        greeting: "hello".to_string(),
+        muted: HashMap::new(),
+    }
+}
+
+async fn settings(server: &MockServer, dir: &Path) -> Arc<PersistentState<Settings>> {
+    let slot = Slot::default();
+    let _bot = common::spawn(
+        server
+            .builder()
+            .with_persistent_state_dir(dir)
+            .with_persistent_state("settings", defaults())
+            .with_state(slot.clone())
+            .with_handler(grab)
+            .build(),
+    );
+    server.wait_until(|_| slot.lock().unwrap().is_some()).await;
+    slot.lock().unwrap().take().unwrap()
+}
+
+fn stored(dir: &Path) -> Option<Settings> {
+    let content = std::fs::read_to_string(dir.join("settings.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[tokio::test]
+async fn written_states_are_restored() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir();
+    let settings = settings(&server, &dir).await;
+    assert_eq!(*settings.read().await, defaults());
+    assert!(stored(&dir).is_none());
+
+    let changed = {
+        let mut settings = settings.write().await;
+        settings.greeting = "hi".to_string();
+        settings.muted.insert(1, true);
+        settings.clone()
+    };
+    // Flushed shortly after the guard is dropped.
+    server
+        .wait_until(|_| stored(&dir).as_ref() == Some(&changed))
+        .await;
+    assert!(!dir.join("settings.json.tmp").exists());
+
+    let restored = self::settings(&MockServer::start().await, &dir).await;
+    assert_eq!(*restored.read().await, changed);
+}
+
+#[tokio::test]
+async fn flushing_writes_right_away() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir();
+    let settings = settings(&server, &dir).await;
+
+    settings.write().await.greeting = "hi".to_string();
+    settings.flush().await.unwrap();
+    assert_eq!(stored(&dir).unwrap().greeting, "hi");
+}
+
+#[tokio::test]
+async fn corrupted_states_fall_back_to_the_default() {
+    let dir = common::temp_dir();
+    std::fs::write(dir.join("settings.json"), "{\"greeting\": ").unwrap();
+
+    let settings = settings(&MockServer::start().await, &dir).await;
+    assert_eq!(*settings.read().await, defaults());
+}
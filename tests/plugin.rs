@@ -0,0 +1,170 @@
+mod common;
+
+use common::{MockServer, logs::Captured};
+use flow_bot::{
+    FlowBotBuilder,
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext, extract::State, filter::EventFilter, handler::HandlerControl,
+        persistent::PersistentState, plugin::Plugin,
+    },
+    event::message::Message,
+};
+use serde_json::json;
+
+/// A plugin as a third-party crate would ship it.
+mod greeter {
+    use clap::Parser;
+    use flow_bot::{
+        FlowBotBuilder,
+        api::api_ext::ApiExt,
+        base::{
+            context::BotContext,
+            extract::{Command, State},
+            handler::{BoxedHandler, HandlerControl},
+            plugin::Plugin,
+        },
+        event::message::Message,
+    };
+
+    /// What the plugin greets with.
+    pub struct Greeting(pub String);
+
+    pub struct Greeter;
+
+    impl Plugin for Greeter {
+        fn install(self, builder: FlowBotBuilder) -> FlowBotBuilder {
+            builder
+                .with_state(Greeting("你好".to_string()))
+                .with_handlers([BoxedHandler::new(hello), BoxedHandler::new(bye)])
+        }
+    }
+
+    #[derive(Parser)]
+    struct Hello;
+
+    #[derive(Parser)]
+    struct Bye;
+
+    async fn hello(
+        ctx: BotContext,
+        message: Message,
+        _: Command<"/hello", Hello>,
+        State(greeting): State<Greeting>,
+    ) -> HandlerControl {
+        ctx.send_private_message(message.user_id, &greeting.0, None)
+            .await?;
+        HandlerControl::Block
+    }
+
+    async fn bye(ctx: BotContext, message: Message, _: Command<"/bye", Bye>) -> HandlerControl {
+        ctx.send_private_message(message.user_id, "再见", None)
+            .await?;
+        HandlerControl::Block
+    }
+}
+
+const KEPT: &str = "Plugin plugin::greeter::Greeter tried to replace state plugin::greeter::Greeting, keeping the existing state";
+
+fn replies(server: &MockServer) -> Vec<serde_json::Value> {
+    server
+        .calls_of("send_private_msg")
+        .into_iter()
+        .map(|call| call.params["message"][0]["data"]["text"].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn plugins_install_handlers_and_states() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(server.builder().with_plugin(greeter::Greeter).build());
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(2, "/hello"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    server.send_event(common::private_message(2, "/bye"));
+    server.wait_calls_of("send_private_msg", 2).await;
+    assert_eq!(replies(&server), [json!("你好"), json!("再见")]);
+}
+
+#[tokio::test]
+async fn plugins_keep_user_states() {
+    let (captured, _guard) = Captured::start();
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(greeter::Greeting("Hi".to_string()))
+            .with_plugin(greeter::Greeter)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    assert_eq!(captured.events(KEPT).len(), 1);
+
+    server.send_event(common::private_message(2, "/hello"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    assert_eq!(replies(&server), [json!("Hi")]);
+}
+
+#[tokio::test]
+async fn users_replace_plugin_states_after_installing() {
+    let (captured, _guard) = Captured::start();
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_plugin(greeter::Greeter)
+            .with_state(greeter::Greeting("Hi".to_string()))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    assert!(captured.events(KEPT).is_empty());
+
+    server.send_event(common::private_message(2, "/hello"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    assert_eq!(replies(&server), [json!("Hi")]);
+}
+
+struct Counter;
+
+impl Plugin for Counter {
+    fn install(self, builder: FlowBotBuilder) -> FlowBotBuilder {
+        builder.with_persistent_state("plugin_count", 100u32)
+    }
+}
+
+async fn count(
+    ctx: BotContext,
+    message: Message,
+    count: State<PersistentState<u32>>,
+) -> HandlerControl {
+    let count = *count.read().await;
+    ctx.send_private_message(message.user_id, count.to_string(), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn plugins_keep_user_persistent_states() {
+    let (captured, _guard) = Captured::start();
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_persistent_state("user_count", 1u32)
+            .with_plugin(Counter)
+            .with_handler_filtered(count, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    assert_eq!(
+        captured
+            .events("Plugin plugin::Counter tried to replace state flow_bot::base::persistent::PersistentState<u32>, keeping the existing state")
+            .len(),
+        1
+    );
+
+    server.send_event(common::private_message(2, "count"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    assert_eq!(replies(&server), [json!("1")]);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::MockServer;
+use flow_bot::{api::api_ext::ApiExt, base::policy::ActionPolicy, error::FlowError};
+use serde_json::json;
+
+#[test]
+fn patterns_match_with_globs() {
+    let cases = [
+        ("send_group_msg", "send_group_msg", true),
+        ("send_group_msg", "send_group_msg_x", false),
+        ("send_group_msg", "send_group", false),
+        ("*", "anything", true),
+        ("*", "", true),
+        ("set_group_*", "set_group_kick", true),
+        ("set_group_*", "set_group_", true),
+        ("set_group_*", "get_group_info", false),
+        ("*_ban", "set_group_whole_ban", true),
+        ("*_ban", "set_group_ban_x", false),
+        ("set_group_*ban", "set_group_anonymous_ban", true),
+        ("set_group_*ban", "set_group_ban", true),
+        ("set_*_*ban", "set_group_whole_ban", true),
+        ("set_*_*ban", "set_ban", false),
+        ("*group*", "get_group_list", true),
+        ("*group*", "get_friend_list", false),
+        // The parts must not overlap.
+        ("ab*ba", "aba", false),
+        ("ab*ba", "abba", true),
+        ("a**b", "ab", true),
+        ("", "", true),
+        ("", "a", false),
+        // Names are compared as is.
+        ("Send_group_msg", "send_group_msg", false),
+    ];
+    for (pattern, action, matches) in cases {
+        assert_eq!(
+            ActionPolicy::allow([pattern]).permits(action),
+            matches,
+            "{} against {}",
+            pattern,
+            action
+        );
+        assert_eq!(
+            ActionPolicy::deny([pattern]).permits(action),
+            !matches,
+            "{} against {}",
+            pattern,
+            action
+        );
+    }
+
+    // Any pattern matching is enough.
+    let policy = ActionPolicy::allow(["send_*", "get_*"]);
+    assert!(policy.permits("get_login_info"));
+    assert!(!policy.permits("set_group_kick"));
+    assert!(!ActionPolicy::allow(Vec::<String>::new()).permits("send_group_msg"));
+    assert!(ActionPolicy::deny(Vec::<String>::new()).permits("set_group_kick"));
+}
+
+#[tokio::test]
+async fn denied_calls_fail_without_being_sent() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_action_policy(ActionPolicy::deny(["set_group_*", "delete_msg"]))
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    let error = context.set_group_kick(1, 2, None).await.unwrap_err();
+    assert!(
+        matches!(error.root_cause(), FlowError::ActionDenied(action) if action == "set_group_kick"),
+        "{:?}",
+        error
+    );
+    assert_eq!(
+        error.to_string(),
+        "set_group_kick(group_id=1, user_id=2) is denied by the action policy"
+    );
+    assert_eq!(
+        error.root_cause().to_string(),
+        "Action set_group_kick is denied by the action policy"
+    );
+    let error = context.call_action("delete_msg", json!({"message_id": 1}));
+    assert!(matches!(
+        error.await.unwrap_err().root_cause(),
+        FlowError::ActionDenied(_)
+    ));
+    assert!(server.calls_of("set_group_kick").is_empty());
+    assert!(server.calls_of("delete_msg").is_empty());
+
+    #[cfg(feature = "metrics")]
+    assert_eq!(context.metrics().denied_actions("set_group_kick"), 1);
+}
+
+#[tokio::test]
+async fn permitted_calls_pass_untouched() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_action_policy(ActionPolicy::allow(["send_*", "get_*"]))
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    context.send_group_message(1, "hi", None).await.unwrap();
+    context.get_login_info().await.unwrap();
+    let sent = server.calls_of("send_group_msg");
+    assert_eq!(
+        sent[0].params,
+        json!({"group_id": 1, "message": [{"type": "text", "data": {"text": "hi"}}]})
+    );
+    assert!(context.set_group_leave(1, None).await.is_err());
+    assert!(server.calls_of("set_group_leave").is_empty());
+
+    #[cfg(feature = "metrics")]
+    assert_eq!(context.metrics().denied_actions("send_group_msg"), 0);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    FlowBotBuilder,
+    api::{
+        api_ext::ApiExt,
+        quirks::{IdFormat, QuirkProfile},
+    },
+    base::context::BotContext,
+};
+use serde_json::json;
+
+/// A server reporting `app_name` as its implementation.
+async fn server(app_name: &'static str) -> MockServer {
+    MockServer::start_with(move |call| match call.action.as_str() {
+        "get_version_info" => Reply::Ok(
+            json!({"app_name": app_name, "app_version": "1.0", "protocol_version": "v11"}),
+        ),
+        action => common::canned(action),
+    })
+    .await
+}
+
+async fn connect(
+    server: &MockServer,
+    build: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder,
+) -> BotContext {
+    let bot = common::spawn(build(server.builder()).build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    // The implementation is recognized in the background.
+    server.wait_calls_of("get_version_info", 1).await;
+    server.settle(Duration::from_millis(100)).await;
+    context
+}
+
+fn fork() -> QuirkProfile {
+    QuirkProfile::new("my-fork")
+        .matching("myfork")
+        .rename_action("send_like", "_send_like")
+        .ids(IdFormat::String)
+}
+
+#[tokio::test]
+async fn custom_profiles_rename_actions_and_format_ids() {
+    let server = server("MyFork/OneBot").await;
+    let context = connect(&server, |builder| builder.with_quirk_profile(fork())).await;
+    assert_eq!(context.quirk_profile().unwrap().name(), "my-fork");
+
+    context.send_like(2, Some(5)).await.unwrap();
+    let sent = server.calls_of("_send_like");
+    assert_eq!(sent[0].params, json!({"user_id": "2", "times": 5}));
+    assert!(server.calls_of("send_like").is_empty());
+
+    // Other actions keep their name.
+    context.delete_message(7).await.unwrap();
+    assert_eq!(
+        server.calls_of("delete_msg")[0].params,
+        json!({"message_id": "7"})
+    );
+}
+
+#[tokio::test]
+async fn numeric_strings_are_sent_as_numbers() {
+    let server = server("strict-bot").await;
+    let context = connect(&server, |builder| {
+        builder.with_quirk_profile(
+            QuirkProfile::new("strict")
+                .matching("strict")
+                .ids(IdFormat::Number),
+        )
+    })
+    .await;
+
+    context
+        .call_action(
+            "custom",
+            json!({"group_id": "123", "message_id": "abc", "name": "456"}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        server.calls_of("custom")[0].params,
+        json!({"group_id": 123, "message_id": "abc", "name": "456"})
+    );
+}
+
+#[tokio::test]
+async fn known_implementations_are_sent_as_is() {
+    for (app_name, profile) in [
+        ("go-cqhttp", "go-cqhttp"),
+        ("NapCat.Onebot", "NapCat"),
+        ("Lagrange.OneBot", "Lagrange"),
+        ("LLOneBot", "LLOneBot"),
+    ] {
+        let server = server(app_name).await;
+        // The custom profile does not match, so the built-in one is used.
+        let context = connect(&server, |builder| builder.with_quirk_profile(fork())).await;
+        assert_eq!(context.quirk_profile().unwrap().name(), profile);
+
+        context.send_like(2, None).await.unwrap();
+        assert_eq!(
+            server.calls_of("send_like")[0].params,
+            json!({"user_id": 2}),
+            "{}",
+            app_name
+        );
+    }
+}
+
+#[tokio::test]
+async fn unknown_implementations_have_no_profile() {
+    let server = server("SomethingElse").await;
+    let context = connect(&server, |builder| builder).await;
+    assert!(context.quirk_profile().is_none());
+
+    context.send_like(2, None).await.unwrap();
+    assert_eq!(
+        server.calls_of("send_like")[0].params,
+        json!({"user_id": 2})
+    );
+}
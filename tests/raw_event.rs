@@ -0,0 +1,60 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::MockServer;
+use flow_bot::{
+    base::{extract::State, filter::EventFilter, handler::HandlerControl},
+    event::{BotEvent, RawEvent, message::Message},
+    message::message_ext::MessageExt,
+};
+use serde_json::{Value, json};
+
+/// What the handler saw: the raw payload, parsed, the raw json of the event and the typed text.
+type Seen = Arc<Mutex<Option<(String, Value, String, String)>>>;
+
+async fn inspect(
+    event: BotEvent,
+    raw: RawEvent,
+    message: Message,
+    seen: State<Seen>,
+) -> HandlerControl {
+    *seen.lock().unwrap() = Some((
+        raw.as_str().to_string(),
+        raw.value().clone(),
+        event.raw_json().to_string(),
+        message.message.extract_plain_text(),
+    ));
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn unknown_fields_survive_in_the_raw_form() {
+    let server = MockServer::start().await;
+    let seen = Seen::default();
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(seen.clone())
+            .with_handler_filtered(inspect, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    let mut event = common::private_message(2, "hello");
+    event["message_seq"] = json!(4242);
+    event["extension"] = json!({"nested": [1, {"deep": true}]});
+    // Sent with its own formatting, which is kept as well.
+    let payload = serde_json::to_string_pretty(&event).unwrap();
+    server.send_event(&payload);
+
+    server.wait_until(|_| seen.lock().unwrap().is_some()).await;
+    let (raw, value, raw_json, text) = seen.lock().unwrap().take().unwrap();
+    assert_eq!(raw, payload);
+    assert_eq!(raw_json, payload);
+    assert_eq!(value, event);
+    assert_eq!(value["message_seq"], 4242);
+    assert_eq!(value["extension"]["nested"][1]["deep"], true);
+    // The typed event still parses.
+    assert_eq!(text, "hello");
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::MockServer;
+use flow_bot::{
+    FlowBot,
+    base::{extract::GroupId, handler::HandlerControl},
+    event::{TypedEvent, message::Message},
+    message::message_ext::MessageExt,
+};
+
+/// The handlers that ran for an event, with their control and reason, as seen by the control policy.
+type Trace = Arc<Mutex<Vec<(String, HandlerControl, Option<String>)>>>;
+
+async fn group_only(_: GroupId) -> HandlerControl {
+    HandlerControl::Continue
+}
+
+async fn failing(message: Message) -> HandlerControl {
+    let _: i64 = message.message.extract_plain_text().trim().parse()?;
+    HandlerControl::Continue
+}
+
+async fn greeting(message: Message) -> HandlerControl {
+    HandlerControl::continue_because(format!("greeted {}", message.user_id))
+}
+
+async fn spam_filter(message: Message) -> HandlerControl {
+    match message.message.extract_plain_text().contains("spam") {
+        true => HandlerControl::block_because("spam filter"),
+        false => HandlerControl::Skip,
+    }
+}
+
+async fn after_spam_filter(_: Message) -> HandlerControl {
+    HandlerControl::Continue
+}
+
+/// Dispatch a private message with `text`, returning the trace with the bot and its server to keep them running.
+async fn dispatch(
+    text: &str,
+) -> (
+    Vec<(String, HandlerControl, Option<String>)>,
+    Arc<FlowBot>,
+    MockServer,
+) {
+    let server = MockServer::start().await;
+    let trace = Trace::default();
+    let recorded = trace.clone();
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler(group_only)
+            .with_handler(failing)
+            .with_handler(greeting)
+            .with_handler(spam_filter)
+            .with_handler(after_spam_filter)
+            .with_control_policy(move |event, control, meta| {
+                // Leaving out the Connected event.
+                if !matches!(event.event, TypedEvent::Message(_)) {
+                    return control;
+                }
+                let name = meta.name.rsplit("::").next().unwrap().to_string();
+                let reason = meta.reason.map(ToString::to_string);
+                recorded.lock().unwrap().push((name, control, reason));
+                control
+            })
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    server.send_event(common::private_message(2, text));
+    server
+        .wait_until(|_| {
+            let trace = trace.lock().unwrap();
+            trace.last().is_some_and(|(name, control, _)| {
+                name == "after_spam_filter" || *control == HandlerControl::Block
+            })
+        })
+        .await;
+    let trace = trace.lock().unwrap().clone();
+    (trace, bot, server)
+}
+
+fn reason(reason: &str) -> Option<String> {
+    Some(reason.to_string())
+}
+
+#[tokio::test]
+async fn reasons_are_recorded_in_the_dispatch_trace() {
+    let (trace, _bot, _server) = dispatch("hello").await;
+    assert_eq!(
+        trace,
+        [
+            (
+                "group_only".to_string(),
+                HandlerControl::Skip,
+                reason("extractor returned None")
+            ),
+            (
+                "failing".to_string(),
+                HandlerControl::Skip,
+                reason("returned an error")
+            ),
+            (
+                "greeting".to_string(),
+                HandlerControl::Continue,
+                reason("greeted 2")
+            ),
+            ("spam_filter".to_string(), HandlerControl::Skip, None),
+            (
+                "after_spam_filter".to_string(),
+                HandlerControl::Continue,
+                None
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn a_blocking_reason_ends_the_trace() {
+    let (trace, _bot, _server) = dispatch("spam").await;
+    assert_eq!(trace.len(), 4);
+    assert_eq!(
+        trace[3],
+        (
+            "spam_filter".to_string(),
+            HandlerControl::Block,
+            reason("spam filter")
+        )
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn only_static_reasons_are_metrics_labels() {
+    let (_, bot, _server) = dispatch("spam").await;
+    let metrics = bot.context();
+    let metrics = metrics.metrics();
+    assert_eq!(metrics.handler_reasons("block", "spam filter"), 1);
+    // Every handler for the Connected event, and group_only for the message.
+    assert_eq!(
+        metrics.handler_reasons("skip", "extractor returned None"),
+        6
+    );
+    // Built with format!, so counted without its text.
+    assert_eq!(metrics.handler_reasons("continue", "greeted 2"), 0);
+    assert_eq!(metrics.handler_reasons("continue", "dynamic"), 1);
+    assert!(
+        metrics
+            .render(0)
+            .contains(r#"flow_bot_handler_reasons_total{outcome="continue",reason="dynamic"} 1"#)
+    );
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, handler::HandlerControl},
+    extensions::recall_cache::{RecalledMessage, RecentMessageCache},
+    message::message_ext::MessageExt,
+};
+use serde_json::{Value, json};
+
+/// Serves message 1 with `get_msg`, the implementation forgot the others.
+async fn server() -> MockServer {
+    MockServer::start_with(|call| match call.action.as_str() {
+        "get_msg" if call.params["message_id"] == 1 => {
+            Reply::Ok(serde_json::from_str(&common::fixture("get_msg/napcat.json")).unwrap())
+        }
+        "get_msg" => Reply::Failed(1200),
+        action => common::canned(action),
+    })
+    .await
+}
+
+async fn show(ctx: BotContext, recalled: RecalledMessage) -> HandlerControl {
+    let answer = format!(
+        "{} {} {:?}: {}",
+        recalled.message_id,
+        if recalled.cached { "cached" } else { "fetched" },
+        recalled.sender.user_id,
+        recalled.message.extract_plain_text()
+    );
+    ctx.send_private_message(2, answer, None).await?;
+    HandlerControl::Continue
+}
+
+async fn connect(server: &MockServer, capacity: usize, ttl: Duration) {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(RecentMessageCache::new(capacity, ttl))
+            .with_handler(show)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+}
+
+fn sent(id: i64, message: Value) -> Value {
+    let mut message = message;
+    message["message_id"] = id.into();
+    message
+}
+
+fn group_recall(id: i64) -> Value {
+    common::notice(json!({
+        "notice_type": "group_recall", "group_id": 1, "user_id": 3, "operator_id": 4, "message_id": id,
+    }))
+}
+
+async fn answers(server: &MockServer) -> Vec<String> {
+    server.settle(Duration::from_millis(100)).await;
+    server
+        .calls_of("send_private_msg")
+        .iter()
+        .map(|call| {
+            call.params["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn the_latest_messages_are_kept() {
+    let server = server().await;
+    connect(&server, 2, Duration::from_secs(60)).await;
+
+    for id in 1..4 {
+        server.send_event(sent(
+            id,
+            common::group_message(1, 3, "member", format!("message {}", id)),
+        ));
+    }
+    server.send_event(sent(7, common::private_message(3, "private")));
+    server.settle(Duration::from_millis(100)).await;
+
+    server.send_event(group_recall(3));
+    server.wait_calls_of("send_private_msg", 1).await;
+    // Dropped for the newer ones, and fetched instead.
+    server.send_event(group_recall(1));
+    server.wait_calls_of("send_private_msg", 2).await;
+    server.send_event(common::notice(
+        json!({"notice_type": "friend_recall", "user_id": 3, "message_id": 7}),
+    ));
+    server.wait_calls_of("send_private_msg", 3).await;
+    // Neither kept nor known to the implementation, the handler is skipped.
+    server.send_event(group_recall(9));
+
+    assert_eq!(
+        answers(&server).await,
+        [
+            "3 cached Some(3): message 3",
+            "1 fetched Some(1145141919): 看这个",
+            "7 cached Some(3): private",
+        ]
+    );
+    assert_eq!(server.calls_of("get_msg").len(), 2);
+}
+
+#[tokio::test]
+async fn messages_expire() {
+    let server = server().await;
+    connect(&server, 10, Duration::from_millis(100)).await;
+
+    server.send_event(sent(2, common::group_message(1, 3, "member", "message 2")));
+    server.send_event(sent(3, common::group_message(1, 3, "member", "message 3")));
+    server.send_event(group_recall(2));
+    server.wait_calls_of("send_private_msg", 1).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    server.send_event(group_recall(3));
+
+    assert_eq!(answers(&server).await, ["2 cached Some(3): message 2"]);
+    assert_eq!(server.calls_of("get_msg").len(), 1);
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use flow_bot::extensions::{
+    recall_cache::RecentMessageCache,
+    recall_mirror::{MirrorTarget, RecallMirrorService},
+};
+use serde_json::{Value, json};
+
+/// An admin of every group.
+const ADMIN: i64 = 5;
+
+/// Serves message 1 with `get_msg`, the implementation forgot the others. [`ADMIN`] is an admin, everyone else a member.
+async fn server() -> MockServer {
+    MockServer::start_with(|call| match call.action.as_str() {
+        "get_msg" if call.params["message_id"] == 1 => {
+            Reply::Ok(serde_json::from_str(&common::fixture("get_msg/napcat.json")).unwrap())
+        }
+        "get_msg" => Reply::Failed(1200),
+        "get_group_member_info" => {
+            let role = match call.params["user_id"].as_i64() {
+                Some(ADMIN) => "admin",
+                _ => "member",
+            };
+            let Reply::Ok(mut member) = common::canned("get_group_member_info") else {
+                unreachable!()
+            };
+            member["role"] = role.into();
+            Reply::Ok(member)
+        }
+        action => common::canned(action),
+    })
+    .await
+}
+
+async fn connect(server: &MockServer, service: RecallMirrorService) {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(RecentMessageCache::new(100, Duration::from_secs(60)))
+            .with_service(service)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+}
+
+/// Send message `id` from `user_id` in group `group_id`, quoting another message, so that it is cached.
+async fn send(server: &MockServer, group_id: i64, user_id: i64, id: i64) {
+    let mut message = common::group_message(
+        group_id,
+        user_id,
+        "member",
+        json!([
+            {"type": "reply", "data": {"id": "40"}},
+            {"type": "text", "data": {"text": format!("message {}", id)}},
+        ]),
+    );
+    message["message_id"] = id.into();
+    server.send_event(message);
+    server.settle(Duration::from_millis(50)).await;
+}
+
+fn recall(group_id: i64, user_id: i64, operator_id: i64, id: i64) -> Value {
+    common::notice(json!({
+        "notice_type": "group_recall", "group_id": group_id, "user_id": user_id,
+        "operator_id": operator_id, "message_id": id,
+    }))
+}
+
+/// The target and the texts of the segments of every message sent, in order.
+async fn reposts(server: &MockServer) -> Vec<(String, Vec<String>)> {
+    server.settle(Duration::from_millis(100)).await;
+    server
+        .calls()
+        .into_iter()
+        .filter(|call| call.action.starts_with("send_"))
+        .map(|call| {
+            let target = match call.action.as_str() {
+                "send_group_msg" => format!("group {}", call.params["group_id"]),
+                _ => format!("private {}", call.params["user_id"]),
+            };
+            let segments = call.params["message"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|segment| match segment["type"].as_str().unwrap() {
+                    "text" => segment["data"]["text"].as_str().unwrap().to_string(),
+                    other => format!("[{}]", other),
+                })
+                .collect();
+            (target, segments)
+        })
+        .collect()
+}
+
+fn repost(target: &str, segments: &[&str]) -> (String, Vec<String>) {
+    (
+        target.to_string(),
+        segments.iter().map(|segment| segment.to_string()).collect(),
+    )
+}
+
+#[tokio::test]
+async fn recalls_are_mirrored_with_attribution() {
+    let server = server().await;
+    connect(
+        &server,
+        RecallMirrorService::new()
+            .mirror(1, MirrorTarget::Group(100))
+            .mirror(1, MirrorTarget::Private(200)),
+    )
+    .await;
+
+    send(&server, 1, 3, 2).await;
+    server.send_event(recall(1, 3, 3, 2));
+
+    // The reply to a message of the group is left out.
+    let header = "Nick (3) recalled a message in group 1:\n";
+    assert_eq!(
+        reposts(&server).await,
+        [
+            repost("group 100", &[header, "message 2"]),
+            repost("private 200", &[header, "message 2"]),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn mirrors_tell_who_recalled_a_message_of_someone_else() {
+    let server = server().await;
+    connect(
+        &server,
+        RecallMirrorService::new().mirror(1, MirrorTarget::Group(100)),
+    )
+    .await;
+
+    send(&server, 1, 3, 2).await;
+    server.send_event(recall(1, 3, ADMIN, 2));
+
+    assert_eq!(
+        reposts(&server).await,
+        [repost(
+            "group 100",
+            &[
+                "A message of Nick (3) in group 1 was recalled by 5:\n",
+                "message 2"
+            ]
+        )]
+    );
+}
+
+#[tokio::test]
+async fn mirrors_fetch_or_note_content_not_cached() {
+    let server = server().await;
+    connect(
+        &server,
+        RecallMirrorService::new().mirror(1, MirrorTarget::Group(100)),
+    )
+    .await;
+
+    // Fetched with `get_msg`.
+    server.send_event(recall(1, 1145141919, 1145141919, 1));
+    server.wait_calls_of("send_group_msg", 1).await;
+    // Neither cached nor known to the implementation.
+    server.send_event(recall(1, 3, 3, 9));
+
+    assert_eq!(
+        reposts(&server).await,
+        [
+            repost(
+                "group 100",
+                &[
+                    "小明 (1145141919) recalled a message in group 1:\n",
+                    "[image]",
+                    "看这个"
+                ]
+            ),
+            repost(
+                "group 100",
+                &["3 recalled a message in group 1, its content is unavailable"]
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn other_groups_and_recalls_of_the_bot_are_ignored() {
+    let server = server().await;
+    connect(
+        &server,
+        RecallMirrorService::new()
+            .mirror(1, MirrorTarget::Group(100))
+            .anti_recall([1, 100]),
+    )
+    .await;
+
+    send(&server, 2, 3, 2).await;
+    server.send_event(recall(2, 3, 3, 2));
+    // The bot recalling its own mirror post, or an admin recalling it.
+    server.send_event(recall(100, common::SELF_ID, common::SELF_ID, 1));
+    server.send_event(recall(100, common::SELF_ID, ADMIN, 1));
+    server.send_event(recall(1, common::SELF_ID, common::SELF_ID, 1));
+
+    assert_eq!(reposts(&server).await, []);
+    assert!(server.calls_of("get_msg").is_empty());
+}
+
+#[tokio::test]
+async fn anti_recall_reposts_into_the_same_group() {
+    let server = server().await;
+    connect(&server, RecallMirrorService::new().anti_recall([1])).await;
+
+    send(&server, 1, 3, 2).await;
+    server.send_event(recall(1, 3, 3, 2));
+    server.wait_calls_of("send_group_msg", 1).await;
+    // Not enabled in group 2.
+    send(&server, 2, 3, 3).await;
+    server.send_event(recall(2, 3, 3, 3));
+
+    assert_eq!(
+        reposts(&server).await,
+        [repost("group 1", &["Nick (3) recalled:\n", "message 2"])]
+    );
+}
+
+#[tokio::test]
+async fn anti_recall_exempts_admins() {
+    let server = server().await;
+    connect(&server, RecallMirrorService::new().anti_recall([1])).await;
+
+    // Recalled by an admin.
+    send(&server, 1, 3, 2).await;
+    server.send_event(recall(1, 3, ADMIN, 2));
+    // Sent and recalled by an admin.
+    send(&server, 1, ADMIN, 3).await;
+    server.send_event(recall(1, ADMIN, ADMIN, 3));
+    // Without content, there is nothing to repost.
+    server.send_event(recall(1, 3, 3, 9));
+
+    assert_eq!(reposts(&server).await, []);
+}
@@ -0,0 +1,223 @@
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::extensions::recorder::{EventRecorderService, RecordFilter, Rotation};
+use serde_json::{Value, json};
+
+/// The records in `dir`, by file in the order they were written.
+fn files(dir: &Path) -> Vec<Vec<Value>> {
+    paths(dir)
+        .iter()
+        .map(|path| {
+            std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+/// The record files in `dir`, in the order they were opened.
+fn paths(dir: &Path) -> Vec<PathBuf> {
+    // Created with the first record.
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    // Named by the time they were opened.
+    names.sort_by_key(|path| {
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        name.strip_prefix("events-")
+            .unwrap()
+            .parse::<u128>()
+            .unwrap()
+    });
+    names
+}
+
+/// The text of the recorded message events, in the order they were recorded.
+fn texts(files: &[Vec<Value>]) -> Vec<String> {
+    files
+        .iter()
+        .flatten()
+        .map(|record| {
+            let frame: Value = serde_json::from_str(record["frame"].as_str().unwrap()).unwrap();
+            frame["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Send `count` private messages, numbered from `from`.
+fn send(server: &MockServer, from: usize, count: usize) {
+    for i in from..from + count {
+        server.send_event(common::private_message(2, format!("message {}", i)));
+    }
+}
+
+async fn wait_recorded(dir: &Path, count: usize) {
+    tokio::time::timeout(common::TIMEOUT, async {
+        // Counting complete lines, a record may be partly written.
+        while paths(dir)
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap().matches('\n').count())
+            .sum::<usize>()
+            < count
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("records were not written");
+}
+
+#[tokio::test]
+async fn records_are_flushed_and_the_writer_stops_on_shutdown() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir().join("records");
+    let bot = Arc::new(
+        server
+            .builder()
+            .with_service(EventRecorderService::new(&dir))
+            .build(),
+    );
+    let running = bot.clone();
+    let run = tokio::spawn(async move { running.run().await });
+    bot.context().wait_for_connected().await;
+
+    send(&server, 0, 50);
+    server.settle(Duration::from_millis(50)).await;
+    bot.shutdown();
+    // Returns once the writer finished, which never happened while the queue stayed open.
+    tokio::time::timeout(common::TIMEOUT, run)
+        .await
+        .expect("run did not return")
+        .unwrap()
+        .unwrap();
+
+    let files = files(&dir);
+    assert_eq!(files.len(), 1);
+    assert_eq!(
+        texts(&files),
+        (0..50)
+            .map(|i| format!("message {}", i))
+            .collect::<Vec<_>>()
+    );
+    assert!(files[0][0]["recorded_at"].as_u64().unwrap() > 0);
+    assert!(files[0][0]["error"].is_null());
+}
+
+#[tokio::test]
+async fn files_rotate_by_size() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir().join("records");
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(EventRecorderService::new(&dir).with_rotation(Rotation::Size(1)))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    // Every record exceeds the size, so each gets a file of its own even when written within a millisecond.
+    send(&server, 0, 5);
+    wait_recorded(&dir, 5).await;
+    let files = files(&dir);
+    assert_eq!(files.iter().map(Vec::len).collect::<Vec<_>>(), [1; 5]);
+    assert_eq!(
+        texts(&files),
+        (0..5).map(|i| format!("message {}", i)).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn files_rotate_once_the_size_is_reached() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir().join("records");
+    // The length of a record line, with its newline.
+    let frame = common::private_message(2, "message 0").to_string();
+    let line = 1 + json!({"recorded_at": 1_700_000_000_000u64, "error": null, "frame": frame})
+        .to_string()
+        .len();
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(
+                EventRecorderService::new(&dir).with_rotation(Rotation::Size(2 * line as u64 + 1)),
+            )
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    // A file is only rotated once it reached the size, so the record crossing it is the last one written to it.
+    send(&server, 0, 7);
+    wait_recorded(&dir, 7).await;
+    assert_eq!(
+        files(&dir).iter().map(Vec::len).collect::<Vec<_>>(),
+        [3, 3, 1]
+    );
+}
+
+#[tokio::test]
+async fn files_rotate_by_interval() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir().join("records");
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(
+                EventRecorderService::new(&dir)
+                    .with_rotation(Rotation::Interval(Duration::from_millis(200))),
+            )
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    send(&server, 0, 2);
+    wait_recorded(&dir, 2).await;
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    send(&server, 2, 1);
+    wait_recorded(&dir, 3).await;
+
+    let files = files(&dir);
+    assert_eq!(files.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1]);
+    assert_eq!(texts(&files), ["message 0", "message 1", "message 2"]);
+}
+
+#[tokio::test]
+async fn only_parse_errors_are_recorded_when_filtered() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir().join("records");
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_service(EventRecorderService::new(&dir).with_filter(RecordFilter::ParseErrors))
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    send(&server, 0, 2);
+    server.send_event(r#"{"post_type": "message", "message_type": 1}"#);
+    wait_recorded(&dir, 1).await;
+    server.settle(Duration::from_millis(50)).await;
+
+    let files = files(&dir);
+    assert_eq!(files.concat().len(), 1);
+    let record = &files[0][0];
+    assert_eq!(
+        record["frame"],
+        r#"{"post_type": "message", "message_type": 1}"#
+    );
+    assert!(record["error"].is_string());
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "redis")]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, logs::Captured};
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::message::Message,
+    extensions::redis::{Redis, RedisConfig, RedisKeyed},
+};
+use redis::AsyncCommands;
+
+#[test]
+fn keys_are_prefixed() {
+    assert_eq!(RedisKeyed::<"cooldown">::prefixed(123), "cooldown:123");
+    assert_eq!(
+        RedisKeyed::<"session:group">::prefixed("1:2"),
+        "session:group:1:2"
+    );
+    assert_eq!(RedisKeyed::<"">::prefixed("key"), ":key");
+}
+
+/// Counts the messages of each sender in redis, answering with the count.
+async fn count(
+    ctx: BotContext,
+    mut redis: RedisKeyed<"count">,
+    message: Message,
+) -> HandlerControl {
+    let key = redis.key(message.user_id);
+    let count: i64 = redis.0.incr(&key, 1).await.unwrap();
+    ctx.send_private_message(message.user_id, count.to_string(), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+async fn connected(ctx: BotContext, _: Redis) -> HandlerControl {
+    ctx.send_private_message(2, "connected", None).await?;
+    HandlerControl::Continue
+}
+
+async fn fallback(ctx: BotContext) -> HandlerControl {
+    ctx.send_private_message(2, "fallback", None).await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn failed_connections_skip_the_handler() {
+    let (captured, _guard) = Captured::start();
+    // Nothing listens on the discard port.
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(RedisConfig {
+                url: "redis://127.0.0.1:9/".to_string(),
+            })
+            .with_handler_filtered(connected, EventFilter::MESSAGE)
+            .with_handler_filtered(fallback, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::private_message(2, "hello"));
+    let answers = server.wait_calls_of("send_private_msg", 1).await;
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(server.calls_of("send_private_msg").len(), 1);
+    assert_eq!(answers[0].params["message"][0]["data"]["text"], "fallback");
+    assert!(
+        captured
+            .messages()
+            .iter()
+            .any(|message| message.starts_with("Failed to connect to redis")),
+        "{:?}",
+        captured.messages()
+    );
+}
+
+/// Runs against the redis server at `REDIS_URL`, skipped when it is not set.
+#[tokio::test]
+async fn keyed_connections_share_the_server() {
+    let Ok(url) = std::env::var("REDIS_URL") else {
+        return;
+    };
+    let mut direct = redis::Client::open(url.as_str())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let key = RedisKeyed::<"count">::prefixed(2);
+    let _: () = direct.del(&key).await.unwrap();
+
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(RedisConfig { url })
+            .with_handler_filtered(count, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    for _ in 0..2 {
+        server.send_event(common::private_message(2, "hello"));
+        server.settle(Duration::from_millis(100)).await;
+    }
+    let counts = server
+        .wait_calls_of("send_private_msg", 2)
+        .await
+        .iter()
+        .map(|call| call.params["message"][0]["data"]["text"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(counts, ["1", "2"]);
+    let stored: i64 = direct.get(&key).await.unwrap();
+    assert_eq!(stored, 2);
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{
+        context::BotContext, extract::RepliedMessage, filter::EventFilter, handler::HandlerControl,
+    },
+    event::message::{Message, ReplyStyle},
+    message::message_ext::MessageExt,
+};
+use serde_json::{Value, json};
+
+fn replying_to(id: &str) -> Value {
+    common::group_message(
+        1,
+        3,
+        "member",
+        json!([
+            {"type": "reply", "data": {"id": id}},
+            {"type": "text", "data": {"text": "what?"}},
+        ]),
+    )
+}
+
+async fn quote(ctx: BotContext, replied: RepliedMessage) -> HandlerControl {
+    ctx.send_private_message(2, format!("quote {}", replied.message_id), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn numeric_and_string_ids_are_fetched() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_msg" => {
+            Reply::Ok(serde_json::from_str(&common::fixture("get_msg/napcat.json")).unwrap())
+        }
+        action => common::canned(action),
+    })
+    .await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(quote, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(replying_to("5"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    // Not numeric, passed on as it is instead of skipping the handler.
+    server.send_event(replying_to("abc"));
+    server.wait_calls_of("send_private_msg", 2).await;
+
+    let ids = server
+        .calls_of("get_msg")
+        .iter()
+        .map(|call| call.params["message_id"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [json!(5), json!("abc")]);
+}
+
+async fn answer(ctx: BotContext, message: Message) -> HandlerControl {
+    ctx.send_private_message(2, message.reply_at("ok"), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn reply_at_mentions_group_senders() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(answer, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::group_message(1, 3, "member", "hi"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    server.send_event(common::private_message(3, "hi"));
+
+    let calls = server.wait_calls_of("send_private_msg", 2).await;
+    let ok = json!({"type": "text", "data": {"text": "ok"}});
+    assert_eq!(
+        calls[0].params["message"],
+        json!([
+            {"type": "reply", "data": {"id": "5"}},
+            {"type": "at", "data": {"qq": "3"}},
+            ok,
+        ])
+    );
+    assert_eq!(
+        calls[1].params["message"],
+        json!([{"type": "reply", "data": {"id": "6"}}, ok])
+    );
+}
+
+/// Replies in the registered style, or with mentions only to messages saying `at`.
+async fn styled(ctx: BotContext, message: Message) -> HandlerControl {
+    if message.message.extract_plain_text() == "at" {
+        ctx.reply_with_style(&message, "ok", ReplyStyle::AtOnly)
+            .await?;
+    } else {
+        ctx.reply(&message, "ok").await?;
+    }
+    HandlerControl::Continue
+}
+
+/// The types of the segments of the group and the private reply, in `style` or the default one.
+async fn composed(style: Option<ReplyStyle>, text: &str) -> (Vec<Value>, Vec<Value>) {
+    let server = MockServer::start().await;
+    let mut builder = server.builder();
+    if let Some(style) = style {
+        builder = builder.with_reply_style(style);
+    }
+    let bot = common::spawn(
+        builder
+            .with_handler_filtered(styled, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::group_message(1, 3, "member", text));
+    server.send_event(common::private_message(3, text));
+    let types = |call: &common::Call| {
+        call.params["message"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|segment| segment["type"].clone())
+            .collect()
+    };
+    (
+        types(&server.wait_calls_of("send_group_msg", 1).await[0]),
+        types(&server.wait_calls_of("send_private_msg", 1).await[0]),
+    )
+}
+
+#[tokio::test]
+async fn replies_follow_the_style() {
+    let cases = [
+        (None, (vec!["reply", "text"], vec!["reply", "text"])),
+        (Some(ReplyStyle::None), (vec!["text"], vec!["text"])),
+        (
+            Some(ReplyStyle::Reply),
+            (vec!["reply", "text"], vec!["reply", "text"]),
+        ),
+        (
+            Some(ReplyStyle::ReplyAndAt),
+            (vec!["reply", "at", "text"], vec!["reply", "text"]),
+        ),
+        (Some(ReplyStyle::AtOnly), (vec!["at", "text"], vec!["text"])),
+    ];
+    for (style, (group, private)) in cases {
+        assert_eq!(
+            composed(style, "hi").await,
+            (
+                group.into_iter().map(Value::from).collect(),
+                private.into_iter().map(Value::from).collect()
+            ),
+            "{:?}",
+            style
+        );
+    }
+}
+
+#[tokio::test]
+async fn styles_are_overridden_per_call() {
+    let (group, private) = composed(Some(ReplyStyle::Reply), "at").await;
+    assert_eq!(group, ["at", "text"]);
+    assert_eq!(private, ["text"]);
+}
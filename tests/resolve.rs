@@ -0,0 +1,192 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{MockServer, Reply};
+use flow_bot::base::context::BotContext;
+use serde_json::{Value, json};
+
+/// A group message `id` with `text`, replying to `reply_to` if given.
+fn message(id: i64, reply_to: Option<&str>, text: &str) -> Value {
+    let mut segments = Vec::new();
+    if let Some(reply_to) = reply_to {
+        segments.push(json!({"type": "reply", "data": {"id": reply_to}}));
+    }
+    segments.push(json!({"type": "text", "data": {"text": text}}));
+    json!({
+        "time": 1700000000, "message_id": id, "message_type": "group", "group_id": 1,
+        "sender": {"user_id": 3, "nickname": "Nick", "role": "member"}, "message": segments
+    })
+}
+
+/// Forwarded messages nesting the forwards `nested`.
+fn forward(text: &str, nested: &[&str]) -> Value {
+    let mut segments = vec![json!({"type": "text", "data": {"text": text}})];
+    segments.extend(
+        nested
+            .iter()
+            .map(|id| json!({"type": "forward", "data": {"id": id}})),
+    );
+    json!({ "message": segments })
+}
+
+/// A server answering `get_msg` and `get_forward_msg` from the given messages, failing for others.
+async fn serving(
+    messages: Vec<Value>,
+    forwards: HashMap<&'static str, Value>,
+) -> (MockServer, BotContext) {
+    let messages = messages
+        .into_iter()
+        .map(|message| (message["message_id"].as_i64().unwrap(), message))
+        .collect::<HashMap<_, _>>();
+    let server = MockServer::start_with(move |call| {
+        let raw = &call.params["message_id"];
+        // Implementations accept the id as a number or a string.
+        let id = raw
+            .as_i64()
+            .or_else(|| raw.as_str().and_then(|id| id.parse().ok()));
+        let found = match call.action.as_str() {
+            "get_msg" => id.and_then(|id| messages.get(&id)),
+            // Forward ids may be opaque strings too.
+            "get_forward_msg" => match raw {
+                Value::String(id) => forwards.get(id.as_str()),
+                id => forwards.get(id.to_string().as_str()),
+            },
+            action => return common::canned(action),
+        };
+        match found {
+            Some(data) => Reply::Ok(data.clone()),
+            None => Reply::Failed(1200),
+        }
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    (server, context)
+}
+
+fn ids(chain: &[flow_bot::api::GetMessageResponse]) -> Vec<i64> {
+    chain.iter().map(|message| message.message_id).collect()
+}
+
+#[tokio::test]
+async fn reply_chains_are_followed_nearest_first() {
+    let (server, context) = serving(
+        vec![
+            message(3, Some("2"), "third"),
+            message(2, Some("1"), "second"),
+            message(1, None, "first"),
+        ],
+        HashMap::new(),
+    )
+    .await;
+
+    assert_eq!(ids(&context.resolve_reply_chain(3, 10).await), [3, 2, 1]);
+    assert_eq!(server.calls_of("get_msg").len(), 3);
+
+    // Cut at the depth, without fetching further.
+    assert_eq!(ids(&context.resolve_reply_chain(3, 2).await), [3, 2]);
+    assert_eq!(server.calls_of("get_msg").len(), 5);
+    assert!(context.resolve_reply_chain(3, 0).await.is_empty());
+    assert_eq!(server.calls_of("get_msg").len(), 5);
+}
+
+#[tokio::test]
+async fn reply_cycles_are_fetched_once() {
+    // A replies to B, which replies to A.
+    let (server, context) = serving(
+        vec![message(1, Some("2"), "a"), message(2, Some("1"), "b")],
+        HashMap::new(),
+    )
+    .await;
+
+    assert_eq!(ids(&context.resolve_reply_chain(1, 10).await), [1, 2]);
+    let fetched = server
+        .calls_of("get_msg")
+        .into_iter()
+        .map(|call| call.params["message_id"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(fetched, [json!(1), json!(2)]);
+
+    // A message replying to itself.
+    let (server, context) = serving(vec![message(5, Some("5"), "me")], HashMap::new()).await;
+    assert_eq!(ids(&context.resolve_reply_chain(5, 10).await), [5]);
+    assert_eq!(server.calls_of("get_msg").len(), 1);
+}
+
+#[tokio::test]
+async fn reply_chains_end_at_a_failed_fetch() {
+    let (server, context) = serving(
+        vec![
+            message(3, Some("2"), "third"),
+            message(2, Some("9"), "second"),
+        ],
+        HashMap::new(),
+    )
+    .await;
+
+    assert_eq!(ids(&context.resolve_reply_chain(3, 10).await), [3, 2]);
+    assert_eq!(server.calls_of("get_msg").len(), 3);
+    // Failing at the start is an empty chain.
+    assert!(context.resolve_reply_chain(9, 10).await.is_empty());
+}
+
+/// The first text of each forward.
+fn forward_texts(forwards: &[flow_bot::api::GetForwardResponse]) -> Vec<String> {
+    use flow_bot::message::segments::Segment;
+
+    forwards
+        .iter()
+        .map(|forward| match &forward.message[0] {
+            Segment::Text(text) => text.text.clone(),
+            other => panic!("{:?}", other),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn nested_forwards_are_resolved_outermost_first() {
+    // 10 nests 11 and 12, which fails. 11 nests 10 again, 13 and abc, which nests xyz, which fails.
+    let forwards = HashMap::from([
+        ("10", forward("outer", &["11", "12"])),
+        ("11", forward("inner", &["10", "13", "abc"])),
+        ("13", forward("innermost", &[])),
+        ("abc", forward("opaque", &["xyz"])),
+    ]);
+    let (server, context) = serving(Vec::new(), forwards).await;
+
+    assert_eq!(
+        forward_texts(&context.resolve_forward("10", 10).await),
+        ["outer", "inner", "innermost", "opaque"]
+    );
+    let fetched = server
+        .calls_of("get_forward_msg")
+        .into_iter()
+        .map(|call| call.params["message_id"].clone())
+        .collect::<Vec<_>>();
+    // Numeric ids are sent as numbers, the others as they are.
+    assert_eq!(
+        fetched,
+        [
+            json!(10),
+            json!(11),
+            json!(12),
+            json!(13),
+            json!("abc"),
+            json!("xyz")
+        ]
+    );
+
+    // Two levels deep leaves out the innermost.
+    assert_eq!(
+        forward_texts(&context.resolve_forward("10", 2).await),
+        ["outer", "inner"]
+    );
+    assert!(context.resolve_forward("10", 0).await.is_empty());
+    assert!(context.resolve_forward("12", 10).await.is_empty());
+    assert_eq!(
+        forward_texts(&context.resolve_forward("abc", 10).await),
+        ["opaque"]
+    );
+}
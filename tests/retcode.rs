@@ -0,0 +1,125 @@
+mod common;
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    api::{
+        api_ext::ApiExt,
+        retcode::{IMPLEMENTATION_RETCODES, RetCode},
+    },
+    error::FlowError,
+};
+use serde_json::json;
+
+#[test]
+fn standard_codes_map_both_ways() {
+    let standard = [
+        (0, RetCode::Ok),
+        (1, RetCode::Async),
+        (1400, RetCode::BadRequest),
+        (1401, RetCode::Unauthorized),
+        (1403, RetCode::Forbidden),
+        (1404, RetCode::Unsupported),
+    ];
+    for (code, retcode) in standard {
+        assert_eq!(RetCode::from(code), retcode);
+        assert_eq!(i32::from(retcode), code);
+    }
+    for code in [-1, 2, 100, 200, 1402, 1405] {
+        assert_eq!(RetCode::from(code), RetCode::Other(code));
+        assert_eq!(i32::from(RetCode::from(code)), code);
+    }
+}
+
+#[test]
+fn implementation_codes_only_map_for_their_implementation() {
+    for (name, code, retcode) in IMPLEMENTATION_RETCODES {
+        assert_eq!(RetCode::for_implementation(*code, name), *retcode);
+        // Matched by the lowercased app name, which usually has a suffix.
+        let app_name = format!("{}.Onebot", name.to_uppercase());
+        assert_eq!(RetCode::for_implementation(*code, &app_name), *retcode);
+        assert_eq!(
+            RetCode::for_implementation(*code, "unknown"),
+            RetCode::Other(*code)
+        );
+        assert_eq!(i32::from(*retcode), *code);
+    }
+    // Standard codes are the same for every implementation.
+    assert_eq!(
+        RetCode::for_implementation(1404, "NapCat.Onebot"),
+        RetCode::Unsupported
+    );
+    assert_eq!(
+        RetCode::for_implementation(100, "NapCat.Onebot"),
+        RetCode::Other(100)
+    );
+}
+
+#[test]
+fn predicates() {
+    // (retcode, success, unsupported, retryable, auth failure)
+    let table = [
+        (RetCode::Ok, true, false, false, false),
+        (RetCode::Async, true, false, false, false),
+        (RetCode::BadRequest, false, false, false, false),
+        (RetCode::Unauthorized, false, false, false, true),
+        (RetCode::Forbidden, false, false, false, true),
+        (RetCode::Unsupported, false, true, false, false),
+        (RetCode::Failed(100), false, false, true, false),
+        (RetCode::Failed(200), false, false, true, false),
+        (RetCode::Other(-1), false, false, true, false),
+        (RetCode::Other(1402), false, false, true, false),
+    ];
+    for (retcode, success, unsupported, retryable, auth_failure) in table {
+        assert_eq!(retcode.is_success(), success, "{:?}", retcode);
+        assert_eq!(retcode.is_unsupported(), unsupported, "{:?}", retcode);
+        assert_eq!(retcode.is_retryable(), retryable, "{:?}", retcode);
+        assert_eq!(retcode.is_auth_failure(), auth_failure, "{:?}", retcode);
+    }
+}
+
+#[test]
+fn display() {
+    assert_eq!(RetCode::Ok.to_string(), "0 (ok)");
+    assert_eq!(RetCode::Unsupported.to_string(), "1404 (unsupported)");
+    assert_eq!(RetCode::Failed(200).to_string(), "200 (failed)");
+    assert_eq!(RetCode::Other(7).to_string(), "7");
+}
+
+#[tokio::test]
+async fn failed_retcodes_fail_typed_calls() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "delete_msg" => Reply::Failed(200),
+        "get_msg" => Reply::Failed(1404),
+        _ => common::canned(&call.action),
+    })
+    .await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+    // Mapped with the implementation detected from the version info.
+    context.capabilities().await;
+
+    let error = context.delete_message(1).await.unwrap_err();
+    assert!(matches!(
+        error.root_cause(),
+        FlowError::Api {
+            retcode: RetCode::Failed(200),
+            ..
+        }
+    ));
+    let error = context.get_message(1).await.unwrap_err();
+    assert!(matches!(
+        error.root_cause(),
+        FlowError::Api {
+            retcode: RetCode::Unsupported,
+            ..
+        }
+    ));
+
+    // The untyped call returns the response as it is.
+    let response = context
+        .call_action("delete_msg", json!({"message_id": 1}))
+        .await
+        .unwrap();
+    assert_eq!(response.retcode, 200);
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::{Event, TypedEvent, message::Message},
+    message::{
+        message_ext::MessageExt,
+        segments::{MarkdownSegment, Segment},
+    },
+};
+use serde_json::{Value, json};
+
+/// The segments of the message in the fixture `name`, captured from NapCat or LLOneBot.
+fn segments(name: &str) -> Vec<Segment> {
+    let event: Event =
+        serde_json::from_str(&common::fixture(&format!("napcat_segments/{}", name))).unwrap();
+    let TypedEvent::Message(message) = event.event else {
+        panic!("{} is not a message", name);
+    };
+    message.message
+}
+
+fn serialized(segment: impl Into<Segment>) -> Value {
+    serde_json::to_value(segment.into()).unwrap()
+}
+
+/// Sends back the stickers of every message to its sender.
+async fn echo_stickers(ctx: BotContext, message: Message) -> HandlerControl {
+    let stickers = message
+        .message
+        .mfaces()
+        .into_iter()
+        .map(|mface| {
+            Segment::mface(
+                mface.emoji_package_id,
+                &mface.emoji_id,
+                &mface.key,
+                &mface.summary,
+            )
+        })
+        .collect::<Vec<_>>();
+    if stickers.is_empty() {
+        return HandlerControl::Skip;
+    }
+    ctx.send_private_message(message.user_id, stickers, None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[test]
+fn stickers_parse_from_napcat() {
+    let message = segments("mface.json");
+    let [Segment::MFace(mface)] = &message[..] else {
+        panic!("{:?}", message);
+    };
+    assert_eq!(mface.emoji_id, "a1b2c3d4e5f60718293a4b5c6d7e8f90");
+    assert_eq!(mface.emoji_package_id, 235125);
+    assert_eq!(mface.key, "7c9a1e2f3b4d5c6e");
+    assert_eq!(mface.summary, "[开心]");
+    assert_eq!(
+        mface.url.as_deref(),
+        Some(
+            "https://gxh.vip.qq.com/club/item/parcel/item/a1/a1b2c3d4e5f60718293a4b5c6d7e8f90/raw300.gif"
+        )
+    );
+    assert!(!message.is_plain_text());
+}
+
+#[test]
+fn stickers_parse_from_llonebot() {
+    // The package id is a string and there is no url.
+    let message = segments("mface_llonebot.json");
+    let mfaces = message.mfaces();
+    let [mface] = &mfaces[..] else {
+        panic!("{:?}", message);
+    };
+    assert_eq!(mface.emoji_package_id, 1234);
+    assert_eq!(mface.url, None);
+    assert_eq!(message.extract_plain_text(), "看");
+}
+
+#[test]
+fn markdown_parses_from_napcat() {
+    let message = segments("markdown.json");
+    let [Segment::Markdown(markdown)] = &message[..] else {
+        panic!("{:?}", message);
+    };
+    assert_eq!(
+        markdown.content,
+        "# 标题\n**加粗** [链接](https://example.com)"
+    );
+    assert!(message.mfaces().is_empty());
+}
+
+#[test]
+fn segments_serialize_as_napcat_expects() {
+    assert_eq!(
+        serialized(Segment::markdown("**hi**")),
+        json!({"type": "markdown", "data": {"content": "**hi**"}})
+    );
+    assert_eq!(
+        serialized(MarkdownSegment {
+            content: "**hi**".to_string()
+        }),
+        serialized(Segment::markdown("**hi**"))
+    );
+    // The url is only received, never sent.
+    assert_eq!(
+        serialized(Segment::mface(235125, "a1b2", "7c9a", "[开心]")),
+        json!({"type": "mface", "data": {
+            "emoji_id": "a1b2", "emoji_package_id": 235125, "key": "7c9a", "summary": "[开心]",
+        }})
+    );
+    // Received stickers are sent as they came, the package id as a number.
+    let received = segments("mface_llonebot.json").remove(1);
+    assert_eq!(
+        serialized(received),
+        json!({"type": "mface", "data": {
+            "emoji_id": "0f1e2d3c", "emoji_package_id": 1234, "key": "k", "summary": "[哭]",
+        }})
+    );
+}
+
+#[tokio::test]
+async fn handlers_inspect_and_resend_stickers() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(echo_stickers, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::fixture("napcat_segments/markdown.json"));
+    server.send_event(common::fixture("napcat_segments/mface.json"));
+
+    let calls = server.wait_calls_of("send_private_msg", 1).await;
+    assert_eq!(calls[0].params["user_id"], 1145141919);
+    assert_eq!(
+        calls[0].params["message"],
+        json!([{"type": "mface", "data": {
+            "emoji_id": "a1b2c3d4e5f60718293a4b5c6d7e8f90", "emoji_package_id": 235125,
+            "key": "7c9a1e2f3b4d5c6e", "summary": "[开心]",
+        }}])
+    );
+}
@@ -0,0 +1,244 @@
+use flow_bot::message::{
+    faces,
+    message_ext::MessageExt,
+    segments::{
+        AnonymousSegment, ContactSegment, DiceSegment, FaceSegment, ImageSegment, JsonSegment,
+        LocationSegment, MusicPlatform, MusicSegment, NodeSegment, PokeSegment, RecordSegment,
+        ReplySegment, Segment, ShakeSegment, ShareSegment, TextSegment, VideoSegment, XmlSegment,
+    },
+};
+use serde_json::{Value, json};
+
+fn serialized(segment: impl Into<Segment>) -> Value {
+    serde_json::to_value(segment.into()).unwrap()
+}
+
+#[test]
+fn music_segments_match_the_spec() {
+    assert_eq!(
+        serialized(MusicSegment::qq("1")),
+        json!({"type": "music", "data": {"type": "qq", "id": "1"}})
+    );
+    assert_eq!(
+        serialized(MusicSegment::netease("2")),
+        json!({"type": "music", "data": {"type": "163", "id": "2"}})
+    );
+    assert_eq!(
+        serialized(MusicSegment::xm("3")),
+        json!({"type": "music", "data": {"type": "xm", "id": "3"}})
+    );
+    assert_eq!(
+        serialized(MusicSegment::custom(
+            "https://a.com",
+            "https://a.com/a.mp3",
+            "A"
+        )),
+        json!({"type": "music", "data": {
+            "type": "custom", "url": "https://a.com", "audio": "https://a.com/a.mp3", "title": "A",
+        }})
+    );
+    assert_eq!(
+        serialized(
+            MusicSegment::custom("https://a.com", "https://a.com/a.mp3", "A")
+                .content("B")
+                .image("https://a.com/a.png")
+        ),
+        json!({"type": "music", "data": {
+            "type": "custom", "url": "https://a.com", "audio": "https://a.com/a.mp3", "title": "A",
+            "content": "B", "image": "https://a.com/a.png",
+        }})
+    );
+}
+
+#[test]
+fn share_segments_match_the_spec() {
+    assert_eq!(
+        serialized(ShareSegment::new("https://a.com", "A")),
+        json!({"type": "share", "data": {"url": "https://a.com", "title": "A"}})
+    );
+    assert_eq!(
+        serialized(
+            ShareSegment::new("https://a.com", "A")
+                .content("B")
+                .image("https://a.com/a.png")
+        ),
+        json!({"type": "share", "data": {
+            "url": "https://a.com", "title": "A", "content": "B", "image": "https://a.com/a.png",
+        }})
+    );
+}
+
+#[test]
+fn rich_incoming_segments_parse() {
+    let segments: Vec<Segment> = serde_json::from_value(json!([
+        {"type": "music", "data": {
+            "type": "custom", "url": "https://a.com", "audio": "https://a.com/a.mp3", "title": "A",
+            "content": "B", "image": "https://a.com/a.png",
+        }},
+        {"type": "music", "data": {"type": "163", "id": "2"}},
+        {"type": "share", "data": {
+            "url": "https://a.com", "title": "A", "content": "B", "image": "https://a.com/a.png",
+        }},
+    ]))
+    .unwrap();
+
+    let [
+        Segment::Music(custom),
+        Segment::Music(song),
+        Segment::Share(share),
+    ] = &segments[..]
+    else {
+        panic!("{:?}", segments);
+    };
+    assert_eq!(custom.music_platform(), None);
+    assert_eq!(custom.image.as_deref(), Some("https://a.com/a.png"));
+    assert_eq!(song.music_platform(), Some(MusicPlatform::Netease));
+    assert_eq!(share.content.as_deref(), Some("B"));
+}
+
+/// Every segment sent, with the optional fields left out, as in the examples of the onebot 11 spec.
+#[test]
+fn every_segment_matches_the_spec() {
+    let cases = [
+        (
+            Segment::Text(TextSegment {
+                text: "hi".to_string(),
+            }),
+            json!({"type": "text", "data": {"text": "hi"}}),
+        ),
+        (
+            FaceSegment::new(178).into(),
+            json!({"type": "face", "data": {"id": "178"}}),
+        ),
+        (
+            Segment::Image(ImageSegment {
+                file: "a.png".to_string(),
+                url: None,
+            }),
+            json!({"type": "image", "data": {"file": "a.png"}}),
+        ),
+        (
+            Segment::Record(RecordSegment {
+                file: "a.amr".to_string(),
+                url: None,
+            }),
+            json!({"type": "record", "data": {"file": "a.amr"}}),
+        ),
+        (
+            Segment::Video(VideoSegment {
+                file: "a.mp4".to_string(),
+            }),
+            json!({"type": "video", "data": {"file": "a.mp4"}}),
+        ),
+        (
+            Segment::at_user(10001000),
+            json!({"type": "at", "data": {"qq": "10001000"}}),
+        ),
+        (
+            Segment::Dice(DiceSegment {}),
+            json!({"type": "dice", "data": {}}),
+        ),
+        (
+            Segment::Shake(ShakeSegment {}),
+            json!({"type": "shake", "data": {}}),
+        ),
+        (
+            Segment::Poke(PokeSegment {
+                ty: "126".to_string(),
+                id: "2003".to_string(),
+            }),
+            json!({"type": "poke", "data": {"type": "126", "id": "2003"}}),
+        ),
+        (
+            Segment::Anonymous(AnonymousSegment {}),
+            json!({"type": "anonymous", "data": {}}),
+        ),
+        (
+            ContactSegment::user(10001000).into(),
+            json!({"type": "contact", "data": {"type": "qq", "id": "10001000"}}),
+        ),
+        (
+            ContactSegment::group(100100).into(),
+            json!({"type": "contact", "data": {"type": "group", "id": "100100"}}),
+        ),
+        (
+            LocationSegment::new(39.8969426, 116.3109099).into(),
+            json!({"type": "location", "data": {"lat": "39.8969426", "lon": "116.3109099"}}),
+        ),
+        (
+            LocationSegment::new(39.8969426, 116.3109099)
+                .title("Title")
+                .content("Content")
+                .into(),
+            json!({"type": "location", "data": {
+                "lat": "39.8969426", "lon": "116.3109099", "title": "Title", "content": "Content",
+            }}),
+        ),
+        (
+            Segment::Reply(ReplySegment {
+                id: "123456".to_string(),
+            }),
+            json!({"type": "reply", "data": {"id": "123456"}}),
+        ),
+        (
+            Segment::Node(NodeSegment {
+                id: "123456".to_string(),
+            }),
+            json!({"type": "node", "data": {"id": "123456"}}),
+        ),
+        (
+            Segment::Xml(XmlSegment {
+                data: "<?xml ...".to_string(),
+            }),
+            json!({"type": "xml", "data": {"data": "<?xml ..."}}),
+        ),
+        (
+            Segment::Json(JsonSegment {
+                data: "{}".to_string(),
+            }),
+            json!({"type": "json", "data": {"data": "{}"}}),
+        ),
+    ];
+    for (segment, expected) in cases {
+        let value = serialized(segment);
+        assert_eq!(value, expected);
+        // And back.
+        let parsed: Segment = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serialized(parsed), value);
+    }
+}
+
+#[test]
+fn segments_without_fields_accept_extra_data() {
+    // E.g. the result of a dice roll, reported by some implementations.
+    let segment: Segment =
+        serde_json::from_value(json!({"type": "dice", "data": {"result": "3"}})).unwrap();
+    assert!(matches!(segment, Segment::Dice(_)));
+}
+
+#[test]
+fn face_ids_are_numbers_or_strings() {
+    let message: Vec<Segment> = serde_json::from_value(json!([
+        {"type": "face", "data": {"id": 179}},
+        {"type": "face", "data": {"id": "13"}},
+        {"type": "face", "data": {"id": "custom"}},
+    ]))
+    .unwrap();
+    // Non-numeric ids are left out.
+    assert_eq!(message.faces(), [faces::DOGE, faces::GRIN]);
+    // Always sent as a string.
+    assert_eq!(
+        serialized(FaceSegment::new(faces::DOGE)),
+        json!({"type": "face", "data": {"id": "179"}})
+    );
+}
+
+#[test]
+fn face_names_round_trip() {
+    for &(name, id) in faces::ALL {
+        assert_eq!(faces::by_name(name), Some(id), "{}", name);
+        assert_eq!(faces::name_of(id), Some(name), "{}", id);
+    }
+    assert_eq!(faces::by_name("doge"), Some(faces::DOGE));
+    assert_eq!(faces::by_name("nope"), None);
+}
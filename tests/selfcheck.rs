@@ -0,0 +1,165 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{MockServer, Reply};
+use flow_bot::{
+    event::message::GroupSenderRole,
+    extensions::selfcheck::{SelfCheckProblem, SelfCheckReport, SelfCheckService},
+};
+use serde_json::{Value, json};
+
+fn group(group_id: i64) -> Value {
+    json!({"group_id": group_id, "group_name": "Group", "member_count": 10, "max_member_count": 200})
+}
+
+/// The bot is in groups 1 and 2, owning 1 and a plain member of 2.
+fn answer(action: &str, params: &Value) -> Reply {
+    match action {
+        "get_group_list" => Reply::Ok(json!([group(1), group(2)])),
+        "get_group_member_info" => {
+            let Reply::Ok(mut member) = common::canned(action) else {
+                unreachable!()
+            };
+            member["group_id"] = params["group_id"].clone();
+            member["user_id"] = params["user_id"].clone();
+            member["role"] = match params["group_id"].as_i64() {
+                Some(1) => "owner",
+                _ => "member",
+            }
+            .into();
+            Reply::Ok(member)
+        }
+        action => common::canned(action),
+    }
+}
+
+/// Run `service` against `server`, returning its first report.
+async fn report(server: &MockServer, service: SelfCheckService) -> Arc<SelfCheckReport> {
+    let service = Arc::new(service);
+    let bot = common::spawn(server.builder().with_service(service.clone()).build());
+    bot.context().wait_for_connected().await;
+    tokio::time::timeout(common::TIMEOUT, async {
+        loop {
+            if let Some(report) = service.report() {
+                return report;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn missing_groups_and_roles_are_reported() {
+    let server = MockServer::start_with(|call| answer(&call.action, &call.params)).await;
+    let report = report(
+        &server,
+        SelfCheckService::new()
+            .require_groups([1, 2, 3])
+            .require_role(GroupSenderRole::Admin)
+            .superuser(7),
+    )
+    .await;
+
+    assert_eq!(report.self_id, Some(common::SELF_ID));
+    assert_eq!(report.version.as_ref().unwrap().app_name, "NapCat.Onebot");
+    assert_eq!(
+        report.problems,
+        [
+            SelfCheckProblem::InsufficientRole {
+                group_id: 2,
+                actual: GroupSenderRole::Member,
+                required: GroupSenderRole::Admin,
+            },
+            SelfCheckProblem::MissingGroup(3),
+        ]
+    );
+    // The role is only looked up in the groups the bot is in, as itself.
+    let lookups = server
+        .calls_of("get_group_member_info")
+        .into_iter()
+        .map(|call| {
+            (
+                call.params["group_id"].clone(),
+                call.params["user_id"].clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lookups,
+        [
+            (json!(1), json!(common::SELF_ID)),
+            (json!(2), json!(common::SELF_ID))
+        ]
+    );
+
+    let sent = server.wait_calls_of("send_private_msg", 1).await;
+    assert_eq!(sent[0].params["user_id"], 7);
+    assert_eq!(
+        sent[0].params["message"][0]["data"]["text"],
+        "Self check found 2 problem(s):\n\
+         - The bot is Member in group 2 but needs to be Admin\n\
+         - The bot is not a member of group 3"
+    );
+}
+
+#[tokio::test]
+async fn passing_checks_are_not_sent() {
+    let server = MockServer::start_with(|call| answer(&call.action, &call.params)).await;
+    let report = report(
+        &server,
+        SelfCheckService::new().require_groups([1, 2]).superuser(7),
+    )
+    .await;
+
+    assert!(report.is_ok(), "{}", report);
+    // Any member is enough, so roles are not looked up.
+    assert!(server.calls_of("get_group_member_info").is_empty());
+    server.settle(Duration::from_millis(100)).await;
+    assert!(server.calls_of("send_private_msg").is_empty());
+}
+
+#[tokio::test]
+async fn missing_apis_degrade_gracefully() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_version_info" | "get_group_list" => Reply::Failed(1404),
+        action => answer(action, &call.params),
+    })
+    .await;
+    let report = report(
+        &server,
+        SelfCheckService::new()
+            .require_groups([1, 2])
+            .require_role(GroupSenderRole::Admin),
+    )
+    .await;
+
+    assert!(report.version.is_none());
+    // Without the group list, membership is not checked but the roles still are.
+    let [
+        SelfCheckProblem::GroupListFailed(_),
+        SelfCheckProblem::InsufficientRole { group_id: 2, .. },
+    ] = &report.problems[..]
+    else {
+        panic!("{}", report);
+    };
+}
+
+#[tokio::test]
+async fn failed_logins_stop_the_check() {
+    let server = MockServer::start_with(|call| match call.action.as_str() {
+        "get_login_info" => Reply::Failed(100),
+        action => answer(action, &call.params),
+    })
+    .await;
+    let report = report(&server, SelfCheckService::new().require_groups([1])).await;
+
+    assert!(report.self_id.is_none());
+    assert!(matches!(
+        &report.problems[..],
+        [SelfCheckProblem::LoginFailed(_)]
+    ));
+    assert!(server.calls_of("get_group_list").is_empty());
+}
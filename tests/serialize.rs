@@ -0,0 +1,108 @@
+mod common;
+
+use flow_bot::event::{Event, TypedEvent};
+use serde_json::{Value, json};
+
+/// `value` without its null fields, which serializing writes for absent optional fields.
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, without_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(without_nulls).collect()),
+        value => value,
+    }
+}
+
+/// Parse `payload`, serialize it and check that it comes out as it went in and parses again.
+fn assert_round_trip(payload: Value) -> Event {
+    let event: Event = serde_json::from_value(payload.clone()).unwrap();
+    let serialized = serde_json::to_value(&event).unwrap();
+    assert_eq!(without_nulls(serialized.clone()), without_nulls(payload));
+    let again: Event = serde_json::from_value(serialized.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&again).unwrap(), serialized);
+    event
+}
+
+#[test]
+fn messages_round_trip() {
+    let group = common::group_message(
+        1,
+        2,
+        "admin",
+        json!([
+            {"type": "at", "data": {"qq": "3"}},
+            {"type": "text", "data": {"text": " hello"}},
+        ]),
+    );
+    let event = assert_round_trip(group);
+    assert!(matches!(event.event, TypedEvent::Message(_)));
+
+    let mut full = common::group_message(1, 2, "member", "hi");
+    full["sender"] = json!({
+        "user_id": 2, "nickname": "Nick", "card": "Card", "sex": "female", "age": 20,
+        "area": "", "level": "3", "role": "owner", "title": "Title",
+    });
+    full["anonymous"] = json!({"id": 4, "name": "anonymous", "flag": "flag"});
+    assert_round_trip(full);
+
+    assert_round_trip(common::private_message(2, "hello"));
+    let mut temp = common::private_message(2, "hello");
+    temp["sub_type"] = json!("group");
+    temp["group_id"] = json!(1);
+    temp["temp_source"] = json!(0);
+    assert_round_trip(temp);
+}
+
+#[test]
+fn notices_round_trip() {
+    assert_round_trip(common::notice(json!({
+        "notice_type": "group_ban", "sub_type": "ban", "group_id": 1, "user_id": 2,
+        "operator_id": 3, "duration": 60,
+    })));
+    assert_round_trip(common::notice(json!({
+        "notice_type": "notify", "sub_type": "poke", "group_id": 1, "user_id": 2, "target_id": 3,
+    })));
+    // Other notify sub types keep every field.
+    assert_round_trip(common::notice(json!({
+        "notice_type": "notify", "sub_type": "title", "group_id": 1, "user_id": 2, "title": "Title",
+    })));
+}
+
+#[test]
+fn requests_and_meta_events_round_trip() {
+    let base = json!({"time": 1700000000, "self_id": common::SELF_ID});
+    let with = |fields: Value| {
+        let mut event = base.clone();
+        event
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        event
+    };
+    assert_round_trip(with(json!({
+        "post_type": "request", "request_type": "friend", "user_id": 2, "comment": "hi", "flag": "f",
+    })));
+    assert_round_trip(with(json!({
+        "post_type": "request", "request_type": "group", "sub_type": "invite", "group_id": 1,
+        "user_id": 2, "comment": "", "flag": "f",
+    })));
+    assert_round_trip(with(json!({
+        "post_type": "meta_event", "meta_event_type": "lifecycle", "sub_type": "connect",
+    })));
+    assert_round_trip(with(json!({
+        "post_type": "meta_event", "meta_event_type": "heartbeat", "interval": 5000,
+        "status": {"online": true, "good": true},
+    })));
+}
+
+#[test]
+fn unknown_events_round_trip() {
+    let event = assert_round_trip(json!({
+        "time": 1700000000, "self_id": common::SELF_ID, "post_type": "message_sent", "extra": [1, 2],
+    }));
+    assert!(matches!(event.event, TypedEvent::Unknown(_)));
+}
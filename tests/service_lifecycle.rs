@@ -0,0 +1,167 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use common::MockServer;
+use flow_bot::{
+    FlowBot, FlowBotBuilder,
+    base::{
+        connect::ReconnectionStrategy, context::BotContext, handler::HandlerControl,
+        service::Service,
+    },
+    event::BotEvent,
+};
+
+#[derive(Default)]
+struct Log(Mutex<Vec<String>>);
+
+impl Log {
+    fn push(&self, entry: impl Into<String>) {
+        self.0.lock().unwrap().push(entry.into());
+    }
+
+    fn entries(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    async fn wait_entries(&self, count: usize) -> Vec<String> {
+        tokio::time::timeout(common::TIMEOUT, async {
+            while self.entries().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("only logged {:?}", self.entries()));
+        self.entries()
+    }
+}
+
+struct Recording(Arc<Log>);
+
+#[async_trait]
+impl Service for Recording {
+    async fn serve(&self, _: BotContext, _: BotEvent) -> HandlerControl {
+        HandlerControl::Continue
+    }
+
+    async fn init(&self, _: BotContext) {
+        self.0.push("init");
+    }
+
+    async fn on_disconnect(&self, _: BotContext) {
+        self.0.push("disconnect");
+    }
+
+    async fn on_reconnect(&self, _: BotContext, attempt: u32) {
+        self.0.push(format!("reconnect {}", attempt));
+    }
+
+    async fn on_shutdown(&self, _: BotContext) {
+        self.0.push("shutdown");
+    }
+}
+
+fn builder(server: &MockServer) -> FlowBotBuilder {
+    FlowBotBuilder::new(server.connection_with(ReconnectionStrategy::Infinite {
+        initial_delay_ms: 100,
+        max_delay_ms: 100,
+    }))
+    .with_persistent_state_dir(common::temp_dir())
+}
+
+/// Drop the connection once, then shut `bot` down.
+async fn drop_once(server: &MockServer, bot: FlowBot, log: &Log) {
+    let context = bot.context();
+    let run = tokio::spawn(async move { bot.run().await });
+    context.wait_for_connected().await;
+    log.wait_entries(1).await;
+
+    server.disconnect();
+    server.wait_connections(2).await;
+    log.wait_entries(3).await;
+    context.shutdown();
+    tokio::time::timeout(common::TIMEOUT, run)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn hooks_follow_the_connection() {
+    let server = MockServer::start().await;
+    let log = Arc::new(Log::default());
+    let bot = builder(&server)
+        .with_service(Recording(log.clone()))
+        .build();
+
+    drop_once(&server, bot, &log).await;
+    assert_eq!(
+        log.entries(),
+        [
+            "init",
+            "disconnect",
+            "reconnect 1",
+            // Shutting down closes the connection too.
+            "disconnect",
+            "shutdown"
+        ]
+    );
+}
+
+#[cfg(feature = "macros")]
+mod macros {
+    use flow_bot::{event::message::Message, flow_service};
+
+    use super::*;
+
+    struct Macro(Arc<Log>);
+
+    #[flow_service]
+    impl Service for Macro {
+        async fn init(&self, _: BotContext) {
+            self.0.push("init");
+        }
+
+        async fn on_disconnect(&self, _: BotContext) {
+            self.0.push("disconnect");
+        }
+
+        async fn on_reconnect(&self, _: BotContext, attempt: u32) {
+            self.0.push(format!("reconnect {}", attempt));
+        }
+
+        async fn on_shutdown(&self, _: BotContext) {
+            self.0.push("shutdown");
+        }
+
+        async fn message(&self, _message: Message) -> HandlerControl {
+            self.0.push("message");
+            HandlerControl::Continue
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_pass_through_the_macro() {
+        let server = MockServer::start().await;
+        let log = Arc::new(Log::default());
+        let bot = builder(&server).with_service(Macro(log.clone())).build();
+
+        drop_once(&server, bot, &log).await;
+        // The hooks are not handlers, so the connection events did not call them.
+        assert_eq!(
+            log.entries(),
+            [
+                "init",
+                "disconnect",
+                "reconnect 1",
+                "disconnect",
+                "shutdown"
+            ]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{MockServer, logs::Captured};
+use flow_bot::{
+    base::{explain::Verdict, extract::GroupId, filter::EventFilter, handler::HandlerControl},
+    event::message::Message,
+};
+
+const SKIPPED: &str = "Handler skipped, extractor returned None";
+
+async fn in_group(_: Message, _: GroupId) -> HandlerControl {
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn a_group_handler_on_a_private_message_names_group_id() {
+    let (captured, _guard) = Captured::start();
+
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(in_group, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+    let event = common::private_message(2, "hello");
+    server.send_event(event.clone());
+    server
+        .wait_until(|_| !captured.events(SKIPPED).is_empty())
+        .await;
+
+    let skip = &captured.events(SKIPPED)[0];
+    assert_eq!(skip["extractor"], "flow_bot::base::extract::GroupId");
+    assert_eq!(skip["argument"], "1");
+    assert!(skip["handler"].ends_with("in_group"), "{:?}", skip);
+
+    // Explaining the event names it as well.
+    let explanations = bot.explain(&event.to_string()).await.unwrap();
+    let Verdict::Skipped(miss) = &explanations[0].verdict else {
+        panic!("{:?}", explanations[0]);
+    };
+    assert_eq!(
+        (miss.argument, miss.extractor),
+        (1, "flow_bot::base::extract::GroupId")
+    );
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "sqlx-sqlite")]
+
+mod common;
+
+use std::path::Path;
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::message::Message,
+    extensions::sqlite::{Db, Migration, SqliteConfig},
+    message::message_ext::MessageExt,
+};
+
+/// Not idempotent, so running one twice fails.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "note authors",
+        sql: "ALTER TABLE notes ADD COLUMN user_id INTEGER NOT NULL DEFAULT 0",
+    },
+    Migration {
+        version: 1,
+        description: "notes",
+        sql: "CREATE TABLE notes (id INTEGER PRIMARY KEY, text TEXT NOT NULL)",
+    },
+];
+
+/// Store the message as a note, answering with every note of its sender.
+async fn note(ctx: BotContext, Db(pool): Db, message: Message) -> HandlerControl {
+    let user_id = message.user_id;
+    sqlx::query("INSERT INTO notes (text, user_id) VALUES (?, ?)")
+        .bind(message.message.extract_plain_text())
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let notes: Vec<String> =
+        sqlx::query_scalar("SELECT text FROM notes WHERE user_id = ? ORDER BY id")
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    ctx.send_private_message(user_id, notes.join(", "), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+async fn connect(server: &MockServer, path: &Path) -> BotContext {
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(SqliteConfig {
+                path: path.to_string_lossy().into_owned(),
+                max_connections: 2,
+                migrations: Some(MIGRATIONS),
+            })
+            .with_handler_filtered(note, EventFilter::MESSAGE)
+            .build(),
+    );
+    let context = bot.context();
+    context.wait_for_connected().await;
+    context
+}
+
+async fn answers(server: &MockServer, count: usize) -> Vec<String> {
+    server
+        .wait_calls_of("send_private_msg", count)
+        .await
+        .iter()
+        .map(|call| {
+            call.params["message"][0]["data"]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn handlers_use_the_migrated_database() {
+    let path = common::temp_dir().join("bot.db");
+    let server = MockServer::start().await;
+    let _context = connect(&server, &path).await;
+
+    server.send_event(common::private_message(2, "first"));
+    answers(&server, 1).await;
+    server.send_event(common::private_message(2, "second"));
+    server.send_event(common::private_message(3, "other"));
+    let mut answers = answers(&server, 3).await;
+    answers.sort();
+    assert_eq!(answers, ["first", "first, second", "other"]);
+
+    // The next start keeps the rows and does not migrate again.
+    let server = MockServer::start().await;
+    let _context = connect(&server, &path).await;
+    server.send_event(common::private_message(2, "third"));
+    assert_eq!(self::answers(&server, 1).await, ["first, second, third"]);
+
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", path.display()))
+        .await
+        .unwrap();
+    let versions: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _flow_bot_migrations ORDER BY version")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(versions, [1, 2]);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use common::MockServer;
+use flow_bot::base::{extract::State, handler::HandlerControl, storage::Storage};
+
+type Slot = Arc<Mutex<Option<Storage<"count">>>>;
+
+/// Hand the storage over to the test.
+async fn grab(storage: Storage<"count">, slot: State<Slot>) -> HandlerControl {
+    slot.lock().unwrap().get_or_insert(storage);
+    HandlerControl::Continue
+}
+
+async fn storage(server: &MockServer, dir: &Path) -> Storage<"count"> {
+    let slot = Slot::default();
+    let _bot = common::spawn(
+        server
+            .builder()
+            .with_persistent_state_dir(dir)
+            .with_state(slot.clone())
+            .with_handler(grab)
+            .build(),
+    );
+    server.wait_until(|_| slot.lock().unwrap().is_some()).await;
+    slot.lock().unwrap().take().unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_updates_are_not_lost() {
+    let server = MockServer::start().await;
+    let dir = common::temp_dir();
+    let storage = Arc::new(storage(&server, &dir).await);
+
+    let tasks = (0..8)
+        .map(|_| {
+            let storage = storage.clone();
+            tokio::spawn(async move {
+                for _ in 0..100 {
+                    storage.update(|count: &mut u64| *count += 1).await.unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(storage.get::<u64>().await.unwrap(), Some(800));
+
+    // Persisted shortly after, and loaded by the next bot using the directory.
+    storage.update(|count: &mut u64| *count += 1).await.unwrap();
+    let file = dir.join("handler_storage.json");
+    server
+        .wait_until(|_| {
+            std::fs::read_to_string(&file)
+                .ok()
+                .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                .is_some_and(|values| values["count"] == 801)
+        })
+        .await;
+    let reloaded = self::storage(&MockServer::start().await, &dir).await;
+    assert_eq!(reloaded.get::<u64>().await.unwrap(), Some(801));
+}
+
+#[tokio::test]
+async fn set_get_and_remove() {
+    let server = MockServer::start().await;
+    let storage = storage(&server, &common::temp_dir()).await;
+
+    assert_eq!(storage.get::<u64>().await.unwrap(), None);
+    storage.set(&HashMap::from([("a", 1)])).await.unwrap();
+    assert_eq!(
+        storage.get::<HashMap<String, i32>>().await.unwrap(),
+        Some(HashMap::from([("a".to_string(), 1)]))
+    );
+    // Stored as a map, not a number.
+    assert!(storage.get::<u64>().await.is_err());
+
+    storage.remove().await;
+    assert_eq!(storage.get::<u64>().await.unwrap(), None);
+    // Removing nothing is fine.
+    storage.remove().await;
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, filter::EventFilter, handler::HandlerControl},
+    event::message::{Message, PrivateMessageEvent},
+};
+use serde_json::{Value, json};
+
+/// Replies with the group of the temp session.
+async fn answer(ctx: BotContext, message: Message, event: PrivateMessageEvent) -> HandlerControl {
+    ctx.reply(&message, format!("{:?}", event.temp_group_id))
+        .await?;
+    HandlerControl::Continue
+}
+
+fn fixture(name: &str) -> Value {
+    let mut event: Value =
+        serde_json::from_str(&common::fixture(&format!("temp_session/{}.json", name))).unwrap();
+    event["time"] = common::private_message(2, "")["time"].clone();
+    event
+}
+
+/// The text of a reply, after the quote of the message replied to.
+fn text(params: &Value) -> &str {
+    let segments = params["message"].as_array().unwrap();
+    assert_eq!(segments[0]["type"], "reply", "{}", params);
+    segments[1]["data"]["text"].as_str().unwrap()
+}
+
+#[tokio::test]
+async fn temp_sessions_are_replied_through_their_group() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_handler_filtered(answer, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    let cases = [
+        ("napcat", Some(987654321)),
+        ("go-cqhttp", Some(987654321)),
+        ("top_level", Some(123456789)),
+        // Without a group, the reply is sent as to a friend, which may fail.
+        ("no_group", None),
+    ];
+    for (i, (name, group_id)) in cases.into_iter().enumerate() {
+        server.send_event(fixture(name));
+        let sent = server.wait_calls_of("send_private_msg", i + 1).await;
+        let params = &sent[i].params;
+        assert_eq!(params["user_id"], 1145141919, "{}", name);
+        assert_eq!(params["group_id"], json!(group_id), "{}", name);
+        assert_eq!(text(params), format!("{:?}", group_id), "{}", name);
+    }
+
+    // Friends are replied without a group, even if the sender has one.
+    let mut friend = fixture("napcat");
+    friend["sub_type"] = "friend".into();
+    server.send_event(friend);
+    let sent = server.wait_calls_of("send_private_msg", 5).await;
+    assert_eq!(sent[4].params["group_id"], Value::Null);
+    assert_eq!(text(&sent[4].params), "None");
+}
+
+#[tokio::test]
+async fn temp_messages_carry_the_group() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(server.builder().build());
+    let context = bot.context();
+    context.wait_for_connected().await;
+
+    context
+        .send_temp_message(987654321, 1145141919, "hello")
+        .await
+        .unwrap();
+    let sent = server.calls_of("send_private_msg");
+    assert_eq!(sent[0].params["group_id"], 987654321);
+    assert_eq!(sent[0].params["user_id"], 1145141919);
+    assert_eq!(sent[0].params["message"][0]["data"]["text"], "hello");
+}
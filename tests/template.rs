@@ -0,0 +1,190 @@
+mod common;
+
+use common::MockServer;
+use flow_bot::{
+    api::api_ext::ApiExt,
+    base::{context::BotContext, extract::State, filter::EventFilter, handler::HandlerControl},
+    event::message::Message,
+    message::{
+        segments::Segment,
+        template::{Lang, LangSettings, TemplateArg, Templates},
+    },
+};
+use serde_json::{Value, json};
+
+fn templates() -> Templates {
+    Templates::from_json(
+        r#"{
+            "greet": {"en": "Hello {at_sender}, welcome to {group}!", "zh": "你好 {at_sender}，欢迎来到 {group}！"},
+            "score": {"en": "{name}: {score} {{points}}"},
+            "only_fr": {"fr": "Bonjour", "de": "Hallo"}
+        }"#,
+        "en",
+    )
+    .unwrap()
+}
+
+fn render(key: &str, lang: &str, args: &[(&str, TemplateArg)]) -> Value {
+    serde_json::to_value(templates().render(key, lang, args)).unwrap()
+}
+
+fn text(text: &str) -> Value {
+    json!({"type": "text", "data": {"text": text}})
+}
+
+#[test]
+fn text_placeholders_are_substituted() {
+    assert_eq!(
+        render(
+            "score",
+            "en",
+            &[("name", "Alice".into()), ("score", 42.into())]
+        ),
+        json!([text("Alice: 42 {points}")])
+    );
+    // Unknown and unclosed placeholders are left as they are.
+    assert_eq!(
+        render("score", "en", &[("name", "Alice".into())]),
+        json!([text("Alice: {score} {points}")])
+    );
+    let message = Templates::new(
+        [(
+            "open".to_string(),
+            [("en".to_string(), "{name} and {rest".to_string())].into(),
+        )]
+        .into(),
+        "en",
+    )
+    .render("open", "en", &[("name", "Bob".into())]);
+    assert_eq!(
+        serde_json::to_value(message).unwrap(),
+        json!([text("Bob and {rest")])
+    );
+}
+
+#[test]
+fn segment_placeholders_are_their_own_segments() {
+    let args = [
+        ("at_sender", Segment::at_user(2).into()),
+        ("group", "flow".into()),
+    ];
+    assert_eq!(
+        render("greet", "en", &args),
+        json!([
+            text("Hello "),
+            {"type": "at", "data": {"qq": "2"}},
+            text(", welcome to flow!"),
+        ])
+    );
+    assert_eq!(
+        render("greet", "zh", &args),
+        json!([
+            text("你好 "),
+            {"type": "at", "data": {"qq": "2"}},
+            text("，欢迎来到 flow！"),
+        ])
+    );
+    // At the very start, no empty text segment is left before it.
+    let templates = Templates::new(
+        [(
+            "ping".to_string(),
+            [("en".to_string(), "{at_sender}".to_string())].into(),
+        )]
+        .into(),
+        "en",
+    );
+    assert_eq!(
+        serde_json::to_value(templates.render(
+            "ping",
+            "en",
+            &[("at_sender", Segment::at_all().into())]
+        ))
+        .unwrap(),
+        json!([{"type": "at", "data": {"qq": "all"}}])
+    );
+}
+
+#[test]
+fn missing_languages_and_keys_fall_back() {
+    let args = [("name", "Alice".into()), ("score", 1.into())];
+    // The default language.
+    assert_eq!(
+        render("score", "zh", &args),
+        json!([text("Alice: 1 {points}")])
+    );
+    // Without it, the alphabetically first language.
+    assert_eq!(render("only_fr", "en", &[]), json!([text("Hallo")]));
+    // The key itself.
+    assert_eq!(render("missing", "en", &[]), json!([text("missing")]));
+}
+
+async fn greet(
+    ctx: BotContext,
+    Lang(lang): Lang,
+    message: Message,
+    templates: State<Templates>,
+) -> HandlerControl {
+    let args = [
+        ("at_sender", Segment::at_user(message.user_id).into()),
+        ("group", "flow".into()),
+    ];
+    ctx.send_private_message(2, templates.render("greet", &lang, &args), None)
+        .await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn languages_are_set_per_group() {
+    let server = MockServer::start().await;
+    let settings = LangSettings::new("en");
+    settings.groups.insert(1, "zh".to_string());
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(templates())
+            .with_state(settings)
+            .with_handler_filtered(greet, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::group_message(1, 3, "member", "hi"));
+    server.wait_calls_of("send_private_msg", 1).await;
+    server.send_event(common::group_message(2, 3, "member", "hi"));
+    server.wait_calls_of("send_private_msg", 2).await;
+    server.send_event(common::private_message(3, "hi"));
+
+    let greetings = server
+        .wait_calls_of("send_private_msg", 3)
+        .await
+        .iter()
+        .map(|call| call.params["message"][0]["data"]["text"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(greetings, ["你好 ", "Hello ", "Hello "]);
+}
+
+async fn skipped(ctx: BotContext) -> HandlerControl {
+    ctx.send_private_message(2, "skipped", None).await?;
+    HandlerControl::Continue
+}
+
+#[tokio::test]
+async fn handlers_are_skipped_without_settings() {
+    let server = MockServer::start().await;
+    let bot = common::spawn(
+        server
+            .builder()
+            .with_state(templates())
+            .with_handler_filtered(greet, EventFilter::MESSAGE)
+            .with_handler_filtered(skipped, EventFilter::MESSAGE)
+            .build(),
+    );
+    bot.context().wait_for_connected().await;
+
+    server.send_event(common::group_message(1, 3, "member", "hi"));
+
+    let calls = server.wait_calls_of("send_private_msg", 1).await;
+    server.settle(std::time::Duration::from_millis(100)).await;
+    assert_eq!(server.calls_of("send_private_msg").len(), 1);
+    assert_eq!(calls[0].params["message"][0]["data"]["text"], "skipped");
+}
@@ -0,0 +1,395 @@
+mod common;
+
+use async_trait::async_trait;
+use common::{MockServer, logs::Captured};
+use flow_bot::{
+    FlowBotBuilder,
+    base::{
+        connect::{ReconnectionStrategy, ReverseConnectionConfig},
+        context::BotContext,
+        handler::HandlerControl,
+        persistent::PersistentState,
+        service::{RequiredState, Service},
+    },
+    error::BuildError,
+    event::BotEvent,
+};
+
+async fn noop() -> HandlerControl {
+    HandlerControl::Continue
+}
+
+fn connection(
+    target: &str,
+    auth: Option<&str>,
+    reconnection: ReconnectionStrategy,
+) -> ReverseConnectionConfig {
+    ReverseConnectionConfig {
+        target: target.to_string(),
+        auth: auth.map(str::to_string),
+        reconnection,
+    }
+}
+
+/// The problems of a builder connecting with `connection` with a handler, after `configure`.
+fn validate(
+    connection: ReverseConnectionConfig,
+    configure: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder,
+) -> Vec<BuildError> {
+    let builder = FlowBotBuilder::new(connection).with_handler(noop);
+    configure(builder).validate().err().unwrap_or_default()
+}
+
+/// The problems of a builder with a valid connection, after `configure`.
+fn problems(configure: impl FnOnce(FlowBotBuilder) -> FlowBotBuilder) -> Vec<BuildError> {
+    validate(
+        connection(
+            "ws://127.0.0.1:3001",
+            Some("Bearer token"),
+            ReconnectionStrategy::None,
+        ),
+        configure,
+    )
+}
+
+/// The problems of a builder with the connection only.
+fn with_connection(
+    target: &str,
+    auth: Option<&str>,
+    reconnection: ReconnectionStrategy,
+) -> Vec<BuildError> {
+    validate(connection(target, auth, reconnection), |builder| builder)
+}
+
+#[test]
+fn a_valid_configuration_has_no_problems() {
+    assert_eq!(problems(|builder| builder), []);
+}
+
+#[test]
+fn targets_must_be_websocket_uris() {
+    assert_eq!(
+        with_connection("", None, ReconnectionStrategy::None),
+        [BuildError::EmptyTarget]
+    );
+    for target in ["http://127.0.0.1:3001", "127.0.0.1:3001", "ws://a b"] {
+        assert_eq!(
+            with_connection(target, None, ReconnectionStrategy::None),
+            [BuildError::InvalidTarget(target.to_string())],
+            "{}",
+            target
+        );
+    }
+    assert_eq!(
+        with_connection("wss://example.com/onebot", None, ReconnectionStrategy::None),
+        []
+    );
+}
+
+#[test]
+fn auth_must_be_a_header_value() {
+    assert_eq!(
+        with_connection(
+            "ws://127.0.0.1:3001",
+            Some("Bearer a\nb"),
+            ReconnectionStrategy::None
+        ),
+        [BuildError::InvalidAuth]
+    );
+}
+
+#[test]
+fn reconnection_delays_and_attempts_are_checked() {
+    let cases = [
+        (
+            ReconnectionStrategy::Limited {
+                max_attempts: 0,
+                initial_delay_ms: 1000,
+                max_delay_ms: 60000,
+            },
+            "max_attempts must be nonzero",
+        ),
+        (
+            ReconnectionStrategy::Infinite {
+                initial_delay_ms: 0,
+                max_delay_ms: 60000,
+            },
+            "initial_delay_ms must be nonzero",
+        ),
+        (
+            ReconnectionStrategy::Infinite {
+                initial_delay_ms: 2000,
+                max_delay_ms: 1000,
+            },
+            "initial_delay_ms must not exceed max_delay_ms",
+        ),
+    ];
+    for (reconnection, message) in cases {
+        assert_eq!(
+            with_connection("ws://127.0.0.1:3001", None, reconnection),
+            [BuildError::InvalidReconnection(message)]
+        );
+    }
+
+    // Every problem is reported.
+    let problems = with_connection(
+        "ws://127.0.0.1:3001",
+        None,
+        ReconnectionStrategy::Limited {
+            max_attempts: 0,
+            initial_delay_ms: 0,
+            max_delay_ms: 60000,
+        },
+    );
+    assert_eq!(
+        problems,
+        [
+            BuildError::InvalidReconnection("max_attempts must be nonzero"),
+            BuildError::InvalidReconnection("initial_delay_ms must be nonzero"),
+        ]
+    );
+}
+
+#[test]
+fn no_handlers_is_a_warning() {
+    let problems = FlowBotBuilder::new(connection(
+        "ws://127.0.0.1:3001",
+        None,
+        ReconnectionStrategy::None,
+    ))
+    .validate()
+    .unwrap_err();
+    assert_eq!(problems, [BuildError::NoHandlers]);
+    assert!(problems[0].is_warning());
+}
+
+struct Config;
+
+#[test]
+fn duplicate_states_are_a_warning() {
+    let problems = problems(|builder| {
+        builder
+            .with_state(Config)
+            .with_state(Config)
+            .with_state(Config)
+    });
+    let name = std::any::type_name::<Config>();
+    assert_eq!(
+        problems,
+        [
+            BuildError::DuplicateState(name),
+            BuildError::DuplicateState(name)
+        ]
+    );
+    assert!(problems[0].is_warning());
+}
+
+struct NeedsConfig;
+
+#[async_trait]
+impl Service for NeedsConfig {
+    async fn serve(&self, _: BotContext, _: BotEvent) -> HandlerControl {
+        HandlerControl::Continue
+    }
+
+    fn required_states(&self) -> Vec<RequiredState> {
+        vec![
+            RequiredState::of::<Config>(),
+            RequiredState::of::<PersistentState<u32>>(),
+        ]
+    }
+}
+
+#[test]
+fn services_require_their_states() {
+    let missing = |state| BuildError::MissingState {
+        service: std::any::type_name::<NeedsConfig>(),
+        state,
+    };
+    let found = problems(|builder| builder.with_service(NeedsConfig));
+    assert_eq!(
+        found,
+        [
+            missing(std::any::type_name::<Config>()),
+            missing(std::any::type_name::<PersistentState<u32>>()),
+        ]
+    );
+    assert!(!found[0].is_warning());
+
+    // Registered before or after the service, as a plain or persistent state.
+    assert_eq!(
+        problems(|builder| builder
+            .with_service(NeedsConfig)
+            .with_state(Config)
+            .with_persistent_state("count", 0u32)),
+        []
+    );
+}
+
+#[test]
+fn missing_states_name_the_service_and_the_state() {
+    let found = problems(|builder| builder.with_state(Config).with_service(NeedsConfig));
+    assert_eq!(
+        found.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [format!(
+            "Service {} requires state {}, which is not registered",
+            std::any::type_name::<NeedsConfig>(),
+            std::any::type_name::<PersistentState<u32>>(),
+        )]
+    );
+}
+
+#[test]
+fn services_registered_with_their_state_are_valid() {
+    assert_eq!(
+        problems(|builder| builder
+            .with_service_state(NeedsConfig, Config)
+            .with_persistent_state("count", 0u32)),
+        []
+    );
+    // Services shared with an `Arc` require the same states.
+    assert_eq!(
+        problems(|builder| builder
+            .with_service(std::sync::Arc::new(NeedsConfig))
+            .with_persistent_state("count", 0u32)),
+        [BuildError::MissingState {
+            service: std::any::type_name::<NeedsConfig>(),
+            state: std::any::type_name::<Config>(),
+        }]
+    );
+}
+
+#[cfg(feature = "macros")]
+mod macros {
+    use flow_bot::{base::extract::State, event::message::Message, flow_service};
+
+    use super::*;
+
+    struct Greeting;
+
+    /// Requires the states its methods extract.
+    struct Greeter;
+
+    #[flow_service]
+    impl Service for Greeter {
+        async fn greet(&self, _message: Message, _greeting: State<Greeting>) -> HandlerControl {
+            HandlerControl::Continue
+        }
+
+        async fn count(
+            &self,
+            _greeting: State<Greeting>,
+            _count: State<PersistentState<u32>>,
+        ) -> HandlerControl {
+            HandlerControl::Continue
+        }
+    }
+
+    /// Lists its required states itself, so the extracted ones are not added.
+    struct Listed;
+
+    #[flow_service]
+    impl Service for Listed {
+        fn required_states(&self) -> Vec<RequiredState> {
+            vec![RequiredState::of::<Config>()]
+        }
+
+        async fn greet(&self, _greeting: State<Greeting>) -> HandlerControl {
+            HandlerControl::Continue
+        }
+    }
+
+    #[test]
+    fn extracted_states_are_required() {
+        assert_eq!(
+            Greeter.required_states(),
+            [
+                RequiredState::of::<Greeting>(),
+                RequiredState::of::<PersistentState<u32>>(),
+            ]
+        );
+        assert_eq!(
+            problems(|builder| builder.with_service(Greeter)),
+            [
+                BuildError::MissingState {
+                    service: std::any::type_name::<Greeter>(),
+                    state: std::any::type_name::<Greeting>(),
+                },
+                BuildError::MissingState {
+                    service: std::any::type_name::<Greeter>(),
+                    state: std::any::type_name::<PersistentState<u32>>(),
+                },
+            ]
+        );
+        assert_eq!(
+            problems(|builder| builder
+                .with_service_state(Greeter, Greeting)
+                .with_persistent_state("count", 0u32)),
+            []
+        );
+    }
+
+    #[test]
+    fn declared_states_replace_the_extracted_ones() {
+        assert_eq!(Listed.required_states(), [RequiredState::of::<Config>()]);
+    }
+}
+
+#[test]
+fn try_build_fails_on_errors() {
+    let errors = FlowBotBuilder::new(connection("", None, ReconnectionStrategy::None))
+        .with_service(NeedsConfig)
+        .try_build()
+        .err()
+        .unwrap();
+    assert!(errors.contains(&BuildError::EmptyTarget));
+    assert!(
+        errors
+            .iter()
+            .any(|e| matches!(e, BuildError::MissingState { .. }))
+    );
+}
+
+#[tokio::test]
+async fn try_build_logs_warnings_and_builds() {
+    let (captured, _guard) = Captured::start();
+    let server = MockServer::start().await;
+    let bot = server
+        .builder()
+        .with_state(Config)
+        .with_state(Config)
+        .try_build()
+        .unwrap_or_else(|errors| panic!("{:?}", errors));
+    let messages = captured.messages();
+    assert!(
+        messages.contains(&BuildError::NoHandlers.to_string()),
+        "{:?}",
+        messages
+    );
+    assert!(
+        messages.contains(&BuildError::DuplicateState(std::any::type_name::<Config>()).to_string()),
+        "{:?}",
+        messages
+    );
+
+    let bot = common::spawn(bot);
+    bot.context().wait_for_connected().await;
+}
+
+#[tokio::test]
+async fn build_logs_errors_and_builds() {
+    let (captured, _guard) = Captured::start();
+    let server = MockServer::start().await;
+    let bot = server.builder().with_service(NeedsConfig).build();
+    let messages = captured.messages();
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.starts_with("Service ") && m.ends_with("which is not registered")),
+        "{:?}",
+        messages
+    );
+
+    let bot = common::spawn(bot);
+    bot.context().wait_for_connected().await;
+}
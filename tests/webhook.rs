@@ -0,0 +1,284 @@
+#![cfg(feature = "webhook")]
+
+mod common;
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::MockServer;
+use flow_bot::extensions::webhook::{WebhookFilter, WebhookService};
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+/// A request received by the webhook.
+#[derive(Debug, Clone)]
+struct Request {
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// How the webhook answers a request.
+enum Answer {
+    Status(u16),
+    /// Never answer, the request times out.
+    Hang,
+}
+
+/// A local http server standing in for the webhook.
+struct Hook {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Hook {
+    /// Start a webhook answering the request numbered `n`, counting from zero, with `answer(n)`.
+    async fn start(answer: impl Fn(usize) -> Answer + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests: Arc<Mutex<Vec<Request>>> = Default::default();
+        let answer = Arc::new(answer);
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (received, answer) = (received.clone(), answer.clone());
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut headers = Vec::new();
+                    loop {
+                        let mut line = String::new();
+                        if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let line = line.trim_end();
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some((key, value)) = line.split_once(": ") {
+                            headers.push((key.to_string(), value.to_string()));
+                        }
+                    }
+                    let length = headers
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                        .map_or(0, |(_, value)| value.parse().unwrap());
+                    let mut body = vec![0; length];
+                    stream.read_exact(&mut body).await.unwrap();
+                    let index = {
+                        let mut requests = received.lock().unwrap();
+                        requests.push(Request {
+                            headers,
+                            body: String::from_utf8(body).unwrap(),
+                        });
+                        requests.len() - 1
+                    };
+                    match answer(index) {
+                        Answer::Status(status) => {
+                            let response = format!(
+                                "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                                status
+                            );
+                            let _ = stream.get_mut().write_all(response.as_bytes()).await;
+                        }
+                        Answer::Hang => std::future::pending().await,
+                    }
+                });
+            }
+        });
+        Self { url, requests }
+    }
+
+    fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    async fn wait_requests(&self, count: usize) -> Vec<Request> {
+        tokio::time::timeout(common::TIMEOUT, async {
+            while self.requests.lock().unwrap().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("received {:?}", self.requests()));
+        self.requests()
+    }
+}
+
+/// Wait until the dead letter log at `path` holds `count` lines, returning them.
+async fn wait_dead_letters(path: &Path, count: usize) -> Vec<String> {
+    let read = || {
+        std::fs::read_to_string(path)
+            .map(|log| log.lines().map(str::to_string).collect::<Vec<_>>())
+            .unwrap_or_default()
+    };
+    tokio::time::timeout(common::TIMEOUT, async {
+        while read().len() < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("dead letters: {:?}", read()));
+    read()
+}
+
+async fn connect(server: &MockServer, webhook: WebhookService) -> Arc<flow_bot::FlowBot> {
+    let bot = common::spawn(server.builder().with_service(webhook).build());
+    bot.context().wait_for_connected().await;
+    bot
+}
+
+fn messages() -> WebhookFilter {
+    WebhookFilter::PostTypes(vec!["message".to_string()])
+}
+
+#[tokio::test]
+async fn selected_events_are_delivered_as_received() {
+    let hook = Hook::start(|_| Answer::Status(200)).await;
+    let server = MockServer::start().await;
+    let _bot = connect(
+        &server,
+        WebhookService::new(&hook.url, messages()).with_secret("secret"),
+    )
+    .await;
+
+    let event = common::private_message(2, "hello").to_string();
+    server.send_event(common::notice(
+        json!({"notice_type": "friend_add", "user_id": 2}),
+    ));
+    server.send_event(&event);
+    server.send_event(&event);
+
+    let requests = hook.wait_requests(2).await;
+    server.settle(Duration::from_millis(100)).await;
+    assert_eq!(hook.requests().len(), 2);
+    assert_eq!(requests[0].body, event);
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+    let signature = requests[0].header("x-signature").unwrap();
+    let hex = signature.strip_prefix("sha256=").unwrap();
+    assert_eq!(hex.len(), 64);
+    assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+    // The same payload is signed the same.
+    assert_eq!(requests[1].header("x-signature"), Some(signature));
+}
+
+#[tokio::test]
+async fn notices_are_selected_by_type() {
+    let hook = Hook::start(|_| Answer::Status(200)).await;
+    let server = MockServer::start().await;
+    let _bot = connect(
+        &server,
+        WebhookService::new(
+            &hook.url,
+            WebhookFilter::NoticeTypes(vec!["friend_add".to_string(), "group_card".to_string()]),
+        ),
+    )
+    .await;
+
+    let friend_add = common::notice(json!({"notice_type": "friend_add", "user_id": 2}));
+    // Not a typed notice.
+    let group_card = common::notice(json!({
+        "notice_type": "group_card", "group_id": 1, "user_id": 2, "card_new": "new", "card_old": "old",
+    }));
+    server.send_event(common::private_message(2, "friend_add"));
+    server.send_event(common::notice(
+        json!({"notice_type": "friend_recall", "user_id": 2, "message_id": 3}),
+    ));
+    server.send_event(&friend_add);
+    server.send_event(&group_card);
+
+    hook.wait_requests(2).await;
+    server.settle(Duration::from_millis(100)).await;
+    let mut bodies = hook
+        .requests()
+        .into_iter()
+        .map(|request| request.body)
+        .collect::<Vec<_>>();
+    bodies.sort();
+    let mut expected = vec![friend_add.to_string(), group_card.to_string()];
+    expected.sort();
+    assert_eq!(bodies, expected);
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried() {
+    let hook = Hook::start(|n| Answer::Status(if n < 2 { 500 } else { 200 })).await;
+    let server = MockServer::start().await;
+    let dead_letter = common::temp_dir().join("dead_letter.jsonl");
+    let _bot = connect(
+        &server,
+        WebhookService::new(&hook.url, messages())
+            .with_retry_delay(Duration::from_millis(10))
+            .with_dead_letter_log(&dead_letter),
+    )
+    .await;
+
+    let event = common::private_message(2, "hello").to_string();
+    server.send_event(&event);
+
+    let requests = hook.wait_requests(3).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(hook.requests().len(), 3);
+    assert!(requests.iter().all(|request| request.body == event));
+    assert!(!dead_letter.exists());
+}
+
+#[tokio::test]
+async fn deliveries_are_dropped_after_the_last_retry() {
+    let hook = Hook::start(|_| Answer::Status(503)).await;
+    let server = MockServer::start().await;
+    let dead_letter = common::temp_dir().join("dead_letter.jsonl");
+    let _bot = connect(
+        &server,
+        WebhookService::new(&hook.url, messages())
+            .with_max_retries(2)
+            .with_retry_delay(Duration::from_millis(10))
+            .with_dead_letter_log(&dead_letter),
+    )
+    .await;
+
+    let event = common::private_message(2, "hello").to_string();
+    server.send_event(&event);
+
+    assert_eq!(wait_dead_letters(&dead_letter, 1).await, [event]);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    // The first attempt and two retries.
+    assert_eq!(hook.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn hanging_webhooks_time_out() {
+    let hook = Hook::start(|_| Answer::Hang).await;
+    let server = MockServer::start().await;
+    let dead_letter = common::temp_dir().join("dead_letter.jsonl");
+    let _bot = connect(
+        &server,
+        WebhookService::new(&hook.url, messages())
+            .with_concurrency(1)
+            .with_max_retries(0)
+            .with_timeout(Duration::from_millis(100))
+            .with_dead_letter_log(&dead_letter),
+    )
+    .await;
+
+    // With a single permit, the second delivery only starts once the first one gave up.
+    server.send_event(common::private_message(2, "first"));
+    server.send_event(common::private_message(2, "second"));
+
+    let dead_letters = wait_dead_letters(&dead_letter, 2).await;
+    assert!(dead_letters[0].contains("first"), "{:?}", dead_letters);
+    assert!(dead_letters[1].contains("second"), "{:?}", dead_letters);
+}
@@ -0,0 +1,466 @@
+mod common;
+
+use std::{collections::BTreeSet, future::Future};
+
+use common::{Call, MockServer};
+use flow_bot::{
+    api::{
+        BanDuration, DownloadHeaders, GroupHonorType, RecordFormat,
+        api_ext::ApiExt,
+        params::{CORE_ACTIONS, EXTENDED_ACTIONS, action_spec},
+    },
+    base::context::BotContext,
+    event::{message::GroupAnonymousInfo, request::GroupRequestSubType},
+    message::media::MediaSource,
+};
+use serde_json::{Value, json};
+
+/// The requests sent by each API method, in the order they were called.
+struct Wire<'a> {
+    server: &'a MockServer,
+    seen: usize,
+    calls: Vec<(&'static str, Call)>,
+}
+
+impl<'a> Wire<'a> {
+    async fn start(server: &'a MockServer) -> (Self, BotContext) {
+        let bot = common::spawn(server.builder().build());
+        let context = bot.context();
+        context.wait_for_connected().await;
+        // Leaving out the requests the bot makes on its own once connected.
+        server.settle(std::time::Duration::from_millis(100)).await;
+        let wire = Self {
+            server,
+            seen: server.calls().len(),
+            calls: Vec::new(),
+        };
+        (wire, context)
+    }
+
+    /// Await `call` of `method`, which may fail as the server answers with placeholder data.
+    async fn record<F: Future>(&mut self, method: &'static str, call: F) {
+        let _ = call.await;
+        let calls = self.server.calls();
+        assert!(calls.len() > self.seen, "{} sent nothing", method);
+        for call in &calls[self.seen..] {
+            self.calls.push((method, call.clone()));
+        }
+        self.seen = calls.len();
+    }
+}
+
+/// The names of the params of `call`, sorted.
+fn keys(call: &Call) -> Vec<&str> {
+    let mut keys = call
+        .params
+        .as_object()
+        .map(|params| params.keys().map(String::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+    keys.sort_unstable();
+    keys
+}
+
+fn sorted<'a>(names: impl IntoIterator<Item = &'a &'static str>) -> Vec<&'static str> {
+    names
+        .into_iter()
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// The requests as `{"method", "action", "params"}` objects.
+fn snapshot(calls: &[(&'static str, Call)]) -> Vec<Value> {
+    calls
+        .iter()
+        .map(|(method, call)| json!({"method": method, "action": call.action, "params": call.params}))
+        .collect()
+}
+
+/// Compare `actual` with the fixture `name`, or overwrite the fixture when `UPDATE_SNAPSHOTS` is set.
+fn assert_snapshot(name: &str, actual: &[Value]) {
+    let path = common::fixture_path(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let json = serde_json::to_string_pretty(actual).unwrap();
+        std::fs::write(&path, json + "\n").unwrap();
+        return;
+    }
+    let expected: Vec<Value> = serde_json::from_str(&common::fixture(name)).unwrap();
+    for (actual, expected) in actual.iter().zip(&expected) {
+        assert_eq!(
+            actual, expected,
+            "rerun with UPDATE_SNAPSHOTS=1 if intended"
+        );
+    }
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "rerun with UPDATE_SNAPSHOTS=1 if intended"
+    );
+}
+
+/// Every API method with its optional arguments unset.
+async fn call_unset(wire: &mut Wire<'_>, ctx: &BotContext) {
+    wire.record(
+        "send_private_message",
+        ctx.send_private_message(2, "hi", None),
+    )
+    .await;
+    wire.record("send_group_message", ctx.send_group_message(1, "hi", None))
+        .await;
+    wire.record(
+        "send_private_message_raw_string",
+        ctx.send_private_message_raw_string(2, "hi".to_string(), None),
+    )
+    .await;
+    wire.record(
+        "send_group_message_raw_string",
+        ctx.send_group_message_raw_string(1, "hi".to_string(), None),
+    )
+    .await;
+    wire.record(
+        "send_private_message_chunked",
+        ctx.send_private_message_chunked(2, "hi", 100),
+    )
+    .await;
+    wire.record(
+        "send_group_message_chunked",
+        ctx.send_group_message_chunked(1, "hi", 100),
+    )
+    .await;
+    wire.record("delete_message", ctx.delete_message(5)).await;
+    wire.record("get_message", ctx.get_message(5)).await;
+    wire.record("get_forward_message", ctx.get_forward_message(5))
+        .await;
+    wire.record("send_like", ctx.send_like(2, None)).await;
+    wire.record("set_group_kick", ctx.set_group_kick(1, 2, None))
+        .await;
+    wire.record(
+        "set_group_ban",
+        ctx.set_group_ban(1, 2, BanDuration::default()),
+    )
+    .await;
+    wire.record(
+        "set_group_anonymous_ban",
+        ctx.set_group_anonymous_ban(1, None, None, BanDuration::default()),
+    )
+    .await;
+    wire.record("set_whole_group_ban", ctx.set_whole_group_ban(1, None))
+        .await;
+    wire.record("set_group_admin", ctx.set_group_admin(1, 2, None))
+        .await;
+    wire.record("set_group_anonymous", ctx.set_group_anonymous(1, None))
+        .await;
+    wire.record("set_group_card", ctx.set_group_card(1, 2, None))
+        .await;
+    wire.record("set_group_name", ctx.set_group_name(1, "name".to_string()))
+        .await;
+    wire.record("set_group_leave", ctx.set_group_leave(1, None))
+        .await;
+    wire.record(
+        "set_group_special_title",
+        ctx.set_group_special_title(1, 2, None, BanDuration::default()),
+    )
+    .await;
+    wire.record(
+        "set_friend_add_request",
+        ctx.set_friend_add_request("flag".to_string(), None, None),
+    )
+    .await;
+    wire.record(
+        "set_group_add_request",
+        ctx.set_group_add_request("flag".to_string(), GroupRequestSubType::Add, None, None),
+    )
+    .await;
+    wire.record("get_login_info", ctx.get_login_info()).await;
+    wire.record("get_stranger_info", ctx.get_stranger_info(2, None))
+        .await;
+    wire.record("get_friend_list", ctx.get_friend_list()).await;
+    wire.record("get_group_info", ctx.get_group_info(1, None))
+        .await;
+    wire.record("get_group_list", ctx.get_group_list()).await;
+    wire.record(
+        "get_group_member_info",
+        ctx.get_group_member_info(1, 2, None),
+    )
+    .await;
+    wire.record("get_group_member_list", ctx.get_group_member_list(1))
+        .await;
+    wire.record(
+        "get_group_honor_info",
+        ctx.get_group_honor_info(1, GroupHonorType::Talkative),
+    )
+    .await;
+    wire.record("get_cookies", ctx.get_cookies(None)).await;
+    wire.record("get_csrf_token", ctx.get_csrf_token()).await;
+    wire.record("get_credentials", ctx.get_credentials(None))
+        .await;
+    wire.record(
+        "get_record",
+        ctx.get_record("a.amr".to_string(), RecordFormat::Mp3),
+    )
+    .await;
+    wire.record("get_image", ctx.get_image("a.image".to_string()))
+        .await;
+    wire.record("can_send_image", ctx.can_send_image()).await;
+    wire.record("can_send_record", ctx.can_send_record()).await;
+    wire.record("get_status", ctx.get_status()).await;
+    wire.record("get_version_info", ctx.get_version_info())
+        .await;
+    wire.record("set_restart", ctx.set_restart(None)).await;
+    wire.record("clean_cache", ctx.clean_cache()).await;
+
+    wire.record("get_online_clients", ctx.get_online_clients(None))
+        .await;
+    wire.record("get_model_show", ctx.get_model_show("model".to_string()))
+        .await;
+    wire.record(
+        "set_model_show",
+        ctx.set_model_show("model".to_string(), "show".to_string()),
+    )
+    .await;
+    wire.record(
+        "download_file",
+        ctx.download_file("https://example.com/a.png".to_string(), None, None),
+    )
+    .await;
+    wire.record(
+        "upload_private_file",
+        ctx.upload_private_file(
+            2,
+            MediaSource::Url("https://example.com/a.txt".to_string()),
+            "a.txt".to_string(),
+        ),
+    )
+    .await;
+    wire.record(
+        "get_group_file_url",
+        ctx.get_group_file_url(1, "file".to_string(), 102),
+    )
+    .await;
+    wire.record(
+        "upload_group_file",
+        ctx.upload_group_file(
+            1,
+            MediaSource::Url("https://example.com/a.txt".to_string()),
+            "a.txt".to_string(),
+            None,
+        ),
+    )
+    .await;
+    wire.record("get_group_at_all_remain", ctx.get_group_at_all_remain(1))
+        .await;
+    wire.record("send_group_sign", ctx.send_group_sign(1)).await;
+    wire.record("mark_msg_as_read", ctx.mark_msg_as_read(5))
+        .await;
+    wire.record(
+        "set_msg_emoji_like",
+        ctx.set_msg_emoji_like(5, "76".to_string(), None),
+    )
+    .await;
+    wire.record("get_group_system_msg", ctx.get_group_system_msg())
+        .await;
+    wire.record("group_poke", ctx.group_poke(1, 2)).await;
+    wire.record("friend_poke", ctx.friend_poke(2)).await;
+}
+
+#[tokio::test]
+async fn unset_optionals_are_left_out() {
+    let server = MockServer::start().await;
+    let (mut wire, ctx) = Wire::start(&server).await;
+    call_unset(&mut wire, &ctx).await;
+
+    for (method, call) in &wire.calls {
+        let spec = action_spec(&call.action).unwrap_or_else(|| {
+            panic!("{} sent {}, which is not in the table", method, call.action)
+        });
+        assert_eq!(keys(call), sorted(spec.required), "{}", method);
+    }
+}
+
+/// Every API method with its optional arguments set.
+async fn call_set(wire: &mut Wire<'_>, ctx: &BotContext) {
+    wire.record(
+        "send_private_message",
+        ctx.send_private_message(2, "hi", Some(true)),
+    )
+    .await;
+    wire.record(
+        "send_group_message",
+        ctx.send_group_message(1, "hi", Some(true)),
+    )
+    .await;
+    wire.record(
+        "send_private_message_raw_string",
+        ctx.send_private_message_raw_string(2, "[CQ:face,id=1]".to_string(), Some(false)),
+    )
+    .await;
+    wire.record(
+        "send_group_message_raw_string",
+        ctx.send_group_message_raw_string(1, "[CQ:face,id=1]".to_string(), Some(false)),
+    )
+    .await;
+    wire.record("send_like", ctx.send_like(2, Some(10))).await;
+    wire.record("set_group_kick", ctx.set_group_kick(1, 2, Some(true)))
+        .await;
+    wire.record(
+        "set_group_ban",
+        ctx.set_group_ban(1, 2, BanDuration::minutes(10)),
+    )
+    .await;
+    wire.record(
+        "set_group_anonymous_ban",
+        ctx.set_group_anonymous_ban(
+            1,
+            Some(GroupAnonymousInfo {
+                id: 3,
+                name: "anonymous".to_string(),
+                flag: "anonymous flag".to_string(),
+            }),
+            Some("flag".to_string()),
+            BanDuration::minutes(10),
+        ),
+    )
+    .await;
+    wire.record(
+        "set_whole_group_ban",
+        ctx.set_whole_group_ban(1, Some(false)),
+    )
+    .await;
+    wire.record("set_group_admin", ctx.set_group_admin(1, 2, Some(false)))
+        .await;
+    wire.record(
+        "set_group_anonymous",
+        ctx.set_group_anonymous(1, Some(false)),
+    )
+    .await;
+    wire.record(
+        "set_group_card",
+        ctx.set_group_card(1, 2, Some("card".to_string())),
+    )
+    .await;
+    wire.record("set_group_leave", ctx.set_group_leave(1, Some(true)))
+        .await;
+    wire.record(
+        "set_group_special_title",
+        ctx.set_group_special_title(1, 2, Some("title".to_string()), BanDuration::days(1)),
+    )
+    .await;
+    wire.record(
+        "set_friend_add_request",
+        ctx.set_friend_add_request("flag".to_string(), Some(true), Some("remark".to_string())),
+    )
+    .await;
+    wire.record(
+        "set_group_add_request",
+        ctx.set_group_add_request(
+            "flag".to_string(),
+            GroupRequestSubType::Invite,
+            Some(false),
+            Some("reason".to_string()),
+        ),
+    )
+    .await;
+    wire.record("get_stranger_info", ctx.get_stranger_info(2, Some(true)))
+        .await;
+    wire.record("get_group_info", ctx.get_group_info(1, Some(true)))
+        .await;
+    wire.record(
+        "get_group_member_info",
+        ctx.get_group_member_info(1, 2, Some(true)),
+    )
+    .await;
+    wire.record(
+        "get_cookies",
+        ctx.get_cookies(Some("qun.qq.com".to_string())),
+    )
+    .await;
+    wire.record(
+        "get_credentials",
+        ctx.get_credentials(Some("qun.qq.com".to_string())),
+    )
+    .await;
+    wire.record("set_restart", ctx.set_restart(Some(2000)))
+        .await;
+
+    wire.record("send_temp_message", ctx.send_temp_message(1, 2, "hi"))
+        .await;
+    wire.record("get_online_clients", ctx.get_online_clients(Some(true)))
+        .await;
+    wire.record(
+        "download_file",
+        ctx.download_file(
+            "https://example.com/a.png".to_string(),
+            Some(2),
+            Some(DownloadHeaders::from(vec![
+                "User-Agent=flow-bot".to_string(),
+            ])),
+        ),
+    )
+    .await;
+    wire.record(
+        "upload_group_file",
+        ctx.upload_group_file(
+            1,
+            MediaSource::Url("https://example.com/a.txt".to_string()),
+            "a.txt".to_string(),
+            Some("/folder".to_string()),
+        ),
+    )
+    .await;
+    wire.record(
+        "set_msg_emoji_like",
+        ctx.set_msg_emoji_like(5, "76".to_string(), Some(false)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn params_match_the_snapshot() {
+    let server = MockServer::start().await;
+    let (mut wire, ctx) = Wire::start(&server).await;
+    call_unset(&mut wire, &ctx).await;
+    call_set(&mut wire, &ctx).await;
+
+    assert_snapshot("wire/params.json", &snapshot(&wire.calls));
+}
+
+/// Actions in the table that no API method sends, only reachable through `call_action`.
+const WITHOUT_METHOD: &[&str] = &["send_msg"];
+
+#[tokio::test]
+async fn every_action_in_the_table_is_sent() {
+    let server = MockServer::start().await;
+    let (mut wire, ctx) = Wire::start(&server).await;
+    call_unset(&mut wire, &ctx).await;
+    let unset = wire.calls.len();
+    call_set(&mut wire, &ctx).await;
+
+    let sent = wire
+        .calls
+        .iter()
+        .map(|(_, call)| call.action.as_str())
+        .chain(WITHOUT_METHOD.iter().copied())
+        .collect::<BTreeSet<_>>();
+    let table = CORE_ACTIONS
+        .iter()
+        .chain(EXTENDED_ACTIONS)
+        .map(|spec| spec.action)
+        .collect::<BTreeSet<_>>();
+    // An action added to the table needs a call here, and an action sent must be in the table.
+    assert_eq!(sent, table);
+
+    for (method, call) in &wire.calls[unset..] {
+        let spec = action_spec(&call.action).unwrap();
+        let keys = keys(call);
+        let known = sorted(spec.required.iter().chain(spec.optional));
+        assert!(
+            keys.iter().all(|key| known.contains(key)),
+            "{} sent {:?}, the table has {:?}",
+            method,
+            keys,
+            known
+        );
+    }
+}